@@ -0,0 +1,55 @@
+//! Benchmark for byte-mode regex matching in `ascii_only` grammars
+//!
+//! Tokenizes about 1MB of ASCII source text with a grammar whose regexes
+//! the character-class fast path can't take, so every token goes through
+//! the regex engine. The same grammar runs twice: as built, which matches
+//! with the Unicode engine, and flagged `ascii_only`, which matches the
+//! same regexes with the byte-oriented engine.
+//!
+//! Run with: cargo bench --bench ascii-regex
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use parsanol::portable::parser_dsl::{re, GrammarBuilder, ParsletExt};
+use parsanol::portable::{AstArena, PortableParser};
+use std::hint::black_box;
+
+const INPUT_SIZE: usize = 1024 * 1024;
+
+/// Identifiers, numbers, whitespace and punctuation
+fn tokens() -> GrammarBuilder {
+    GrammarBuilder::new().rule(
+        "tokens",
+        re("[a-zA-Z_][a-zA-Z0-9_]*")
+            .or(re("[0-9]+(\\.[0-9]+)?"))
+            .or(re("\\s+"))
+            .or(re("[^a-zA-Z0-9_\\s]"))
+            .many(),
+    )
+}
+
+fn bench_ascii_regex(c: &mut Criterion) {
+    let line = "let total_2 = compute(42, rate * 1.5) + offset;\n";
+    let input = line.repeat(INPUT_SIZE / line.len());
+
+    let mut group = c.benchmark_group("ascii_regex_1mb");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    for (name, grammar) in [
+        ("unicode_engine", tokens().build()),
+        ("byte_engine", tokens().ascii_only().build()),
+    ] {
+        grammar.warm_regex_cache();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut arena = AstArena::for_input(input.len());
+                let mut parser = PortableParser::new(&grammar, black_box(&input), &mut arena);
+                parser.parse().is_ok()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_ascii_regex);
+criterion_main!(benches);
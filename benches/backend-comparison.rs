@@ -16,7 +16,7 @@ use std::time::Duration;
 use parsanol::portable::{
     backend::{BytecodeBackend, PackratBackend, ParsingBackend},
     infix::{Assoc, InfixBuilder},
-    parser_dsl::{choice, dynamic, re, ref_, seq, str, GrammarBuilder},
+    parser_dsl::{char_range, choice, dynamic, re, ref_, seq, str, GrammarBuilder, ParsletExt},
     AstArena, Grammar, PortableParser,
 };
 
@@ -180,6 +180,60 @@ mod grammars {
             )
             .build()
     }
+
+    /// Letters `b`..=`y` via the regex engine
+    pub fn range_re() -> Grammar {
+        GrammarBuilder::new()
+            .rule("word", re("[b-y]").many1())
+            .build()
+    }
+
+    /// Letters `b`..=`y` via a character range atom
+    pub fn range_char_range() -> Grammar {
+        GrammarBuilder::new()
+            .rule("word", char_range('b', 'y').many1())
+            .build()
+    }
+
+    /// Greek lowercase letters via the regex engine
+    pub fn greek_re() -> Grammar {
+        GrammarBuilder::new()
+            .rule("word", re("[α-ω]").many1())
+            .build()
+    }
+
+    /// Sum of products where `term` and `factor` are retried by later
+    /// alternatives; `memoize_hot` caches only those two rules
+    pub fn hot_rules(memoize_hot: bool) -> Grammar {
+        let builder = GrammarBuilder::new()
+            .rule(
+                "expr",
+                ref_("term")
+                    .then(str("+"))
+                    .then(ref_("expr"))
+                    .or(ref_("term")),
+            )
+            .rule(
+                "term",
+                ref_("factor")
+                    .then(str("*"))
+                    .then(ref_("term"))
+                    .or(ref_("factor")),
+            )
+            .rule("factor", re("[0-9]").many1());
+        if memoize_hot {
+            builder.memoize_rule("term").memoize_rule("factor").build()
+        } else {
+            builder.build()
+        }
+    }
+
+    /// Greek lowercase letters via a character range atom
+    pub fn greek_char_range() -> Grammar {
+        GrammarBuilder::new()
+            .rule("word", char_range('α', 'ω').many1())
+            .build()
+    }
 }
 
 // ============================================================================
@@ -537,6 +591,58 @@ fn bench_ip_address(c: &mut Criterion) {
     group.finish();
 }
 
+/// `char_range` vs the equivalent `re` character class
+fn bench_char_range(c: &mut Criterion) {
+    let mut group = c.benchmark_group("char_range");
+
+    let ascii = "bcdefghijklmnopqrstuvwxy".repeat(40);
+    let greek = "αβγδεζηθικλμνξοπρστυφχψω".repeat(40);
+    let cases = [
+        ("ascii_re", grammars::range_re(), &ascii),
+        ("ascii_char_range", grammars::range_char_range(), &ascii),
+        ("greek_re", grammars::greek_re(), &greek),
+        ("greek_char_range", grammars::greek_char_range(), &greek),
+    ];
+
+    for (name, grammar, input) in &cases {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                let mut backend = PackratBackend::new();
+                let _ = black_box(backend.parse(grammar, input));
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Memoizing every atom vs only the rules that are retried
+fn bench_memoization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("memoization");
+
+    let input = (0..200)
+        .map(|i| format!("{}*{}", i, i + 1))
+        .collect::<Vec<_>>()
+        .join("+");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    for (name, grammar) in [
+        ("full", grammars::hot_rules(false)),
+        ("selective", grammars::hot_rules(true)),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut arena = AstArena::for_input(input.len());
+                let mut parser = PortableParser::new(&grammar, &input, &mut arena);
+                let _ = black_box(parser.parse());
+            })
+        });
+    }
+
+    group.finish();
+}
+
 /// Throughput benchmark - parsing larger inputs
 fn bench_throughput(c: &mut Criterion) {
     let mut group = c.benchmark_group("throughput");
@@ -621,6 +727,8 @@ criterion_group!(
     bench_balanced_parens,
     bench_iso_date,
     bench_ip_address,
+    bench_char_range,
+    bench_memoization,
     bench_throughput,
     bench_summary,
 );
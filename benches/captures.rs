@@ -0,0 +1,59 @@
+//! Benchmark for memoized captures
+//!
+//! Parses about 1MB of `key=value;` and `key:123;` entries. Both entry
+//! forms start with the same `key` rule, so every `key:123;` entry parses
+//! `key` once, fails on the separator, and reuses it from the packrat
+//! cache for the second alternative. The grammar runs twice: with `key`
+//! capturing its name, where the cache hit has to store the capture again,
+//! and without, as the baseline.
+//!
+//! Run with: cargo bench --bench captures
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use parsanol::portable::parser_dsl::{
+    capture, re, ref_, scope, str, GrammarBuilder, Parslet, ParsletExt,
+};
+use parsanol::portable::{AstArena, Grammar, PortableParser};
+use std::hint::black_box;
+
+const INPUT_SIZE: usize = 1024 * 1024;
+
+/// Entries of either form, with `key` as the body of the `key` rule
+fn entries(key: impl Parslet) -> Grammar {
+    GrammarBuilder::new()
+        .rule(
+            "entries",
+            scope(ref_("key").then(str("=")).then(re("[a-z]+")))
+                .or(scope(ref_("key").then(str(":")).then(re("[0-9]+"))))
+                .then(str(";\n"))
+                .many(),
+        )
+        .rule("key", key)
+        .build()
+}
+
+fn bench_captures(c: &mut Criterion) {
+    let lines = "name=total;\ncount:42;\nlimit:1000;\nmode=fast;\n";
+    let input = lines.repeat(INPUT_SIZE / lines.len());
+
+    let mut group = c.benchmark_group("captures_1mb");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    for (name, grammar) in [
+        ("without_captures", entries(re("[a-z_]+"))),
+        ("with_captures", entries(capture("key", re("[a-z_]+")))),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut arena = AstArena::for_input(input.len());
+                let mut parser = PortableParser::new(&grammar, black_box(&input), &mut arena);
+                parser.parse().is_ok()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_captures);
+criterion_main!(benches);
@@ -0,0 +1,76 @@
+//! Benchmarks comparing grammar load time: JSON vs binary
+//!
+//! Hosts that cache grammars as serialized text pay for deserialization on
+//! every load. This compares `Grammar::from_json` against
+//! `Grammar::from_bytes` for the same 200-atom grammar.
+//!
+//! Run with: cargo bench --bench grammar-load
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use parsanol::portable::{Atom, Grammar};
+use std::hint::black_box;
+
+/// Build a keyword-list grammar of exactly `target_atoms` atoms
+///
+/// Each keyword rule takes 4 atoms; leftover slots are padded with
+/// sequences so the count comes out exact.
+fn build_grammar(target_atoms: usize) -> Grammar {
+    let mut grammar = Grammar::new();
+    let ws = grammar.add_atom(Atom::Re {
+        pattern: "\\s".to_string(),
+    });
+    let ws_star = grammar.add_atom(Atom::Repetition {
+        atom: ws,
+        min: 0,
+        max: None,
+    });
+
+    let mut choices = Vec::new();
+    while grammar.atom_count() + 5 < target_atoms {
+        let n = choices.len();
+        let keyword = grammar.add_atom(Atom::Str {
+            pattern: format!("keyword_{}", n),
+        });
+        let seq = grammar.add_atom(Atom::Sequence {
+            atoms: vec![keyword, ws_star],
+        });
+        let named = grammar.add_atom(Atom::Named {
+            name: format!("kw{}", n),
+            atom: seq,
+        });
+        choices.push(grammar.add_atom(Atom::Entity { atom: named }));
+    }
+
+    let choice = grammar.add_atom(Atom::Alternative { atoms: choices });
+    while grammar.atom_count() < target_atoms - 1 {
+        grammar.add_atom(Atom::Sequence {
+            atoms: vec![choice, ws_star],
+        });
+    }
+    grammar.root = grammar.add_atom(Atom::Repetition {
+        atom: choice,
+        min: 1,
+        max: None,
+    });
+    grammar
+}
+
+fn bench_grammar_load(c: &mut Criterion) {
+    let grammar = build_grammar(200);
+    assert_eq!(grammar.atom_count(), 200);
+
+    let json = grammar.to_json().expect("grammar serializes to JSON");
+    let bytes = grammar.to_bytes();
+
+    let mut group = c.benchmark_group("grammar_load_200_atoms");
+    group.bench_function("from_json", |b| {
+        b.iter(|| Grammar::from_json(black_box(&json)).unwrap())
+    });
+    group.bench_function("from_bytes", |b| {
+        b.iter(|| Grammar::from_bytes(black_box(&bytes)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_grammar_load);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+//! Benchmarks for string pool interning with different hashers
+//!
+//! Interns 100k short strings, about a tenth of them distinct, into an
+//! arena with the built-in hasher, with a randomly seeded `ahash`, and
+//! with the standard library's SipHash.
+//!
+//! Run with: cargo bench --bench interning
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use parsanol::portable::AstArena;
+use std::hint::black_box;
+
+/// `count` short identifiers, cycling through `count / 10` distinct ones
+fn short_strings(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| format!("id{}", i % (count / 10)))
+        .collect()
+}
+
+fn bench_interning(c: &mut Criterion) {
+    let words = short_strings(100_000);
+    let arenas: [(&str, fn() -> AstArena); 3] = [
+        ("default", AstArena::new),
+        ("ahash", || AstArena::with_hasher(ahash::RandomState::new())),
+        ("siphash", || {
+            AstArena::with_hasher(std::collections::hash_map::RandomState::new())
+        }),
+    ];
+
+    let mut group = c.benchmark_group("intern_100k");
+    group.throughput(Throughput::Elements(words.len() as u64));
+    for (name, new_arena) in arenas {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut arena = new_arena();
+                for word in &words {
+                    black_box(arena.intern_string(black_box(word)));
+                }
+                arena.string_count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_interning);
+criterion_main!(benches);
@@ -0,0 +1,102 @@
+//! Benchmarks for parsing many independent records in parallel
+//!
+//! Compares `parse_records_parallel` against parsing the same 10k JSON
+//! lines one after another, each with its own arena.
+//!
+//! Run with: cargo bench --features parallel --bench parallel-records
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use parsanol::portable::parallel::{parse_records_parallel, ParallelConfig};
+use parsanol::portable::parser_dsl::{choice, dynamic, re, ref_, str, GrammarBuilder, ParsletExt};
+use parsanol::portable::{AstArena, Grammar, PortableParser};
+use std::hint::black_box;
+
+const RECORDS: usize = 10_000;
+
+/// Build a grammar for one flat JSON object per line
+fn build_json_line_grammar() -> Grammar {
+    GrammarBuilder::new()
+        .rule(
+            "object",
+            str("{")
+                .then(
+                    ref_("pair")
+                        .then(str(",").then(ref_("pair")).many())
+                        .optional(),
+                )
+                .then(str("}")),
+        )
+        .rule(
+            "pair",
+            ref_("string")
+                .label("key")
+                .then(str(":"))
+                .then(ref_("value").label("value")),
+        )
+        .rule(
+            "value",
+            choice(vec![
+                dynamic(ref_("string")),
+                dynamic(re(r#"-?[0-9]+(\.[0-9]+)?"#)),
+                dynamic(str("true")),
+                dynamic(str("false")),
+                dynamic(str("null")),
+            ]),
+        )
+        .rule("string", re(r#""[^"]*""#))
+        .build()
+}
+
+/// `RECORDS` newline-separated JSON objects
+fn json_lines() -> String {
+    (0..RECORDS)
+        .map(|i| {
+            format!(
+                r#"{{"id":{},"name":"record {}","score":{}.5,"active":{}}}"#,
+                i,
+                i,
+                i % 100,
+                i % 2 == 0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn bench_records(c: &mut Criterion) {
+    let grammar = build_json_line_grammar();
+    let input = json_lines();
+
+    let mut group = c.benchmark_group("json_lines_10k");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            let ok = input
+                .lines()
+                .filter(|line| {
+                    let mut arena = AstArena::for_input(line.len());
+                    PortableParser::new(&grammar, line, &mut arena)
+                        .parse()
+                        .is_ok()
+                })
+                .count();
+            assert_eq!(ok, RECORDS);
+            black_box(ok)
+        })
+    });
+
+    let config = ParallelConfig::new().with_min_chunk_size(64);
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            let records = parse_records_parallel(&grammar, black_box(&input), b'\n', &config);
+            assert!(records.iter().all(|record| record.result.is_ok()));
+            black_box(records.len())
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_records);
+criterion_main!(benches);
@@ -0,0 +1,124 @@
+//! Benchmarks comparing full and selective packrat memoization
+//!
+//! Parses a JSON document with every atom memoized, and again with
+//! `GrammarBuilder::memoize_composites`, which leaves literals and
+//! single-character regexes out of the cache. The memory each setting
+//! needs is printed once before timing.
+//!
+//! Run with: cargo bench --bench selective-memoization
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use parsanol::portable::parser_dsl::{choice, dynamic, re, ref_, str, GrammarBuilder, ParsletExt};
+use parsanol::portable::{AstArena, Grammar, PortableParser};
+use std::hint::black_box;
+
+/// Build a JSON grammar, optionally skipping memoization of cheap leaves
+fn build_json_grammar(selective: bool) -> Grammar {
+    let ws = || re("[ \\t\\n]").many();
+    let builder = GrammarBuilder::new()
+        .rule("document", ws().then(ref_("value")).then(ws()))
+        .rule(
+            "value",
+            choice(vec![
+                dynamic(ref_("object")),
+                dynamic(ref_("array")),
+                dynamic(ref_("string")),
+                dynamic(re("-?[0-9]+(\\.[0-9]+)?")),
+                dynamic(str("true")),
+                dynamic(str("false")),
+                dynamic(str("null")),
+            ]),
+        )
+        .rule(
+            "object",
+            str("{")
+                .then(ws())
+                .then(
+                    ref_("pair")
+                        .then(ws().then(str(",")).then(ws()).then(ref_("pair")).many())
+                        .optional(),
+                )
+                .then(ws())
+                .then(str("}")),
+        )
+        .rule(
+            "pair",
+            ref_("string")
+                .label("key")
+                .then(ws())
+                .then(str(":"))
+                .then(ws())
+                .then(ref_("value").label("value")),
+        )
+        .rule(
+            "array",
+            str("[")
+                .then(ws())
+                .then(
+                    ref_("value")
+                        .then(ws().then(str(",")).then(ws()).then(ref_("value")).many())
+                        .optional(),
+                )
+                .then(ws())
+                .then(str("]")),
+        )
+        .rule(
+            "string",
+            str("\"")
+                .then(re("[^\"\\\\]").or(str("\\").then(re("."))).many())
+                .then(str("\"")),
+        );
+    if selective {
+        builder.memoize_composites().build()
+    } else {
+        builder.build()
+    }
+}
+
+/// A JSON array of `count` small records
+fn json_document(count: usize) -> String {
+    let records: Vec<String> = (0..count)
+        .map(|i| {
+            format!(
+                "  {{\"id\": {}, \"name\": \"record {}\", \"tags\": [\"a\", \"b\"], \"active\": {}}}",
+                i,
+                i,
+                i % 2 == 0
+            )
+        })
+        .collect();
+    format!("[\n{}\n]", records.join(",\n"))
+}
+
+fn bench_memoization(c: &mut Criterion) {
+    let input = json_document(1_000);
+    let grammars = [
+        ("full", build_json_grammar(false)),
+        ("composites", build_json_grammar(true)),
+    ];
+
+    for (name, grammar) in &grammars {
+        let mut arena = AstArena::for_input(input.len());
+        let mut parser = PortableParser::new(grammar, &input, &mut arena);
+        parser.parse().expect("document parses");
+        println!("{}: {}", name, parser.memory_breakdown());
+    }
+
+    let mut group = c.benchmark_group("json_memoization");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    for (name, grammar) in &grammars {
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                let mut arena = AstArena::for_input(input.len());
+                let ok = PortableParser::new(grammar, black_box(&input), &mut arena)
+                    .parse()
+                    .is_ok();
+                black_box(ok)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_memoization);
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+//! Benchmarks for the vectorized scanning paths of the portable parser
+//!
+//! Parses 10MB of lowercase words, which runs each word through the bulk
+//! repetition path of `[a-z]+`, and scans the same input with `parse_all`
+//! for a keyword that appears once every few thousand words, which jumps
+//! between candidate first bytes.
+//!
+//! To compare against another commit, save a baseline there and rerun here:
+//!
+//! ```text
+//! git checkout <old> && cargo bench --bench simd-scan -- --save-baseline old
+//! git checkout <new> && cargo bench --bench simd-scan -- --baseline old
+//! ```
+//!
+//! Run with: cargo bench --bench simd-scan
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use parsanol::portable::parser_dsl::{re, str, GrammarBuilder, ParsletExt};
+use parsanol::portable::{AstArena, PortableParser};
+use std::hint::black_box;
+
+const INPUT_SIZE: usize = 10 * 1024 * 1024;
+
+/// About `size` bytes of 64-letter words separated by spaces, with
+/// `needle` in place of every 4096th word
+fn words(size: usize, needle: &str) -> String {
+    let word: String = (b'a'..=b'z').cycle().take(64).map(char::from).collect();
+    let mut input = String::with_capacity(size + 64);
+    let mut count = 0;
+    while input.len() < size {
+        if count % 4096 == 4095 {
+            input.push_str(needle);
+        } else {
+            input.push_str(&word);
+        }
+        input.push(' ');
+        count += 1;
+    }
+    input
+}
+
+fn bench_simd_scan(c: &mut Criterion) {
+    let input = words(INPUT_SIZE, "fnord");
+
+    let mut group = c.benchmark_group("simd_scan_10mb");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    let grammar = GrammarBuilder::new()
+        .rule("words", re("[a-z]").many1().then(str(" ")).many())
+        .build();
+    group.bench_function("char_class_repetition", |b| {
+        b.iter(|| {
+            let mut arena = AstArena::for_input(input.len());
+            let mut parser = PortableParser::new(&grammar, black_box(&input), &mut arena);
+            parser.parse().is_ok()
+        })
+    });
+
+    let grammar = GrammarBuilder::new().rule("needle", str("fnord")).build();
+    group.bench_function("literal_scan", |b| {
+        b.iter(|| {
+            let mut arena = AstArena::new();
+            let mut parser = PortableParser::new(&grammar, black_box(&input), &mut arena);
+            parser.parse_all().len()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_simd_scan);
+criterion_main!(benches);
@@ -37,6 +37,10 @@ use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, Lit};
 /// # Container Attributes
 ///
 /// - `#[ruby_class("MyModule::MyClass")]` - Specify the Ruby class name
+/// - `#[ruby_init("positional")]` - Build named fields by passing them to
+///   `new` as positional arguments, in declaration order, instead of setting
+///   instance variables on a no-argument instance. `#[ruby_init("ivars")]`
+///   is the default.
 ///
 /// # Variant Attributes
 ///
@@ -45,7 +49,8 @@ use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, Lit};
 /// # Field Attributes
 ///
 /// - `#[ruby_attr("@field_name")]` - Specify the Ruby instance variable name
-#[proc_macro_derive(RubyObject, attributes(ruby_class, ruby_variant, ruby_attr))]
+///   (ignored in positional mode)
+#[proc_macro_derive(RubyObject, attributes(ruby_class, ruby_init, ruby_variant, ruby_attr))]
 pub fn derive_ruby_object(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     impl_ruby_object(&input).into()
@@ -59,6 +64,11 @@ fn impl_ruby_object(input: &DeriveInput) -> TokenStream2 {
     let ruby_class = get_string_attr(&input.attrs, "ruby_class")
         .unwrap_or_else(|| format!("Parsanol::{}", name));
 
+    let init_mode = match get_init_mode(&input.attrs) {
+        Ok(mode) => mode,
+        Err(err) => return err.to_compile_error(),
+    };
+
     let impl_body = match &input.data {
         Data::Enum(data) => {
             let match_arms: Vec<_> = data.variants.iter().map(|variant| {
@@ -107,6 +117,20 @@ fn impl_ruby_object(input: &DeriveInput) -> TokenStream2 {
                             }
                         }
                     }
+                    Fields::Named(fields) if init_mode == InitMode::Positional => {
+                        let field_names: Vec<_> = fields.named.iter()
+                            .map(|f| f.ident.as_ref().unwrap())
+                            .collect();
+
+                        quote! {
+                            #name::#variant_name { #(#field_names),* } => {
+                                let class: magnus::RClass = ruby.class(#class_path)?;
+                                class.new_instance((#(
+                                    parsanol::ruby_ffi::RubyObject::to_ruby(#field_names, ruby)?,
+                                )*))
+                            }
+                        }
+                    }
                     Fields::Named(fields) => {
                         let field_names: Vec<_> = fields.named.iter()
                             .map(|f| f.ident.as_ref().unwrap())
@@ -162,6 +186,20 @@ fn impl_ruby_object(input: &DeriveInput) -> TokenStream2 {
                     class.new_instance((#(self.#indices,)*))
                 }
             }
+            Fields::Named(fields) if init_mode == InitMode::Positional => {
+                let field_names: Vec<_> = fields
+                    .named
+                    .iter()
+                    .map(|f| f.ident.as_ref().unwrap())
+                    .collect();
+
+                quote! {
+                    let class: magnus::RClass = ruby.class(#ruby_class)?;
+                    class.new_instance((#(
+                        parsanol::ruby_ffi::RubyObject::to_ruby(&self.#field_names, ruby)?,
+                    )*))
+                }
+            }
             Fields::Named(fields) => {
                 let field_names: Vec<_> = fields
                     .named
@@ -204,6 +242,30 @@ fn impl_ruby_object(input: &DeriveInput) -> TokenStream2 {
     }
 }
 
+/// How named fields are handed to the Ruby object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InitMode {
+    /// `new` with no arguments, then one `ivar_set` per field
+    Ivars,
+    /// `new` with the fields as positional arguments
+    Positional,
+}
+
+/// Read the container's `#[ruby_init(...)]` mode, defaulting to ivars
+fn get_init_mode(attrs: &[syn::Attribute]) -> syn::Result<InitMode> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("ruby_init")) else {
+        return Ok(InitMode::Ivars);
+    };
+    match get_string_attr(std::slice::from_ref(attr), "ruby_init").as_deref() {
+        Some("ivars") => Ok(InitMode::Ivars),
+        Some("positional") => Ok(InitMode::Positional),
+        _ => Err(syn::Error::new_spanned(
+            attr,
+            "expected #[ruby_init(\"ivars\")] or #[ruby_init(\"positional\")]",
+        )),
+    }
+}
+
 /// Get a string attribute value from attributes
 fn get_string_attr(attrs: &[syn::Attribute], attr_name: &str) -> Option<String> {
     for attr in attrs {
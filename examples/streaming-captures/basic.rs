@@ -30,6 +30,7 @@ fn example_basic_streaming() -> Result<(), Box<dyn std::error::Error>> {
     let config = ChunkConfig {
         chunk_size: 64,
         window_size: 2,
+        overlap: 0,
     };
 
     let input = "hello";
@@ -67,6 +68,7 @@ fn example_chunk_config() {
     println!("  ChunkConfig {{");
     println!("    chunk_size: 65536,   // 64 KB - size of each chunk");
     println!("    window_size: 2,      // Number of chunks to keep in memory");
+    println!("    overlap: 0,          // Bytes shared with the next chunk");
     println!("  }}");
     println!();
 
@@ -178,7 +180,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("* For very large captures: process incrementally with reset()");
 
     println!("\n--- API Summary ---");
-    println!("  let config = ChunkConfig {{ chunk_size: 65536, window_size: 2 }};");
+    println!("  let config = ChunkConfig::new(65536, 2);");
     println!("  let mut parser = StreamingParser::new(&grammar, config);");
     println!("  let result = parser.parse_from_reader(&mut reader, &mut arena)?;");
     println!("  if let Some(captures) = result.capture_state {{");
@@ -15,6 +15,8 @@ struct ParsanolAttrs {
     tag_expr: Option<Expr>,
     field: Option<String>,
     default: Option<DefaultKind>,
+    collect: bool,
+    flatten: bool,
 }
 
 #[derive(Debug)]
@@ -30,6 +32,8 @@ mod kw {
     syn::custom_keyword!(tag_expr);
     syn::custom_keyword!(field);
     syn::custom_keyword!(default);
+    syn::custom_keyword!(collect);
+    syn::custom_keyword!(flatten);
 }
 
 /// Parse a single parsanol attribute
@@ -74,6 +78,12 @@ impl Parse for ParsanolAttrs {
                 } else {
                     attrs.default = Some(DefaultKind::Simple);
                 }
+            } else if lookahead.peek(kw::collect) {
+                input.parse::<kw::collect>()?;
+                attrs.collect = true;
+            } else if lookahead.peek(kw::flatten) {
+                input.parse::<kw::flatten>()?;
+                attrs.flatten = true;
             } else {
                 return Err(lookahead.error());
             }
@@ -143,6 +153,8 @@ fn parse_attrs(attrs: &[syn::Attribute]) -> syn::Result<ParsanolAttrs> {
             if let Some(default) = parsed.default {
                 result.default = Some(default);
             }
+            result.collect |= parsed.collect;
+            result.flatten |= parsed.flatten;
         }
     }
 
@@ -150,28 +162,25 @@ fn parse_attrs(attrs: &[syn::Attribute]) -> syn::Result<ParsanolAttrs> {
 }
 
 /// Generate FromAst implementation for enums
+///
+/// Exact `tag`s and non-literal `tag_expr` match patterns are matched
+/// first; a tag none of them takes is tested against the string literal
+/// `tag_expr` regexes, which must not overlap on it.
 fn generate_enum_from_ast(name: &Ident, variants: &[&syn::Variant]) -> syn::Result<TokenStream> {
     let mut match_arms = Vec::new();
+    let mut patterns = Vec::new();
+    let mut pattern_arms = Vec::new();
 
     for variant in variants {
         let variant_name = &variant.ident;
         let attrs = parse_attrs(&variant.attrs)?;
 
-        // Get the tag attribute
-        let tag = attrs.tag;
-        let tag_expr = attrs.tag_expr;
-
-        // Determine how to match
-        let matcher = if let Some(tag) = tag {
-            quote! { Some(#tag) }
-        } else if let Some(tag_expr) = tag_expr {
-            quote! { Some(#tag_expr) }
-        } else {
+        if attrs.tag.is_none() && attrs.tag_expr.is_none() {
             return Err(syn::Error::new_spanned(
                 variant,
                 "enum variants must have #[parsanol(tag = \"...\")] or #[parsanol(tag_expr = ...)]",
             ));
-        };
+        }
 
         // Generate the conversion for this variant
         let conversion = match &variant.fields {
@@ -226,17 +235,55 @@ fn generate_enum_from_ast(name: &Ident, variants: &[&syn::Variant]) -> syn::Resu
             }
         };
 
-        match_arms.push(quote! {
-            #matcher => { #conversion }
-        });
+        if let Some(tag) = attrs.tag {
+            match_arms.push(quote! {
+                Some(#tag) => { #conversion }
+            });
+        } else if let Some(Expr::Lit(syn::ExprLit {
+            lit: Lit::Str(tag_expr),
+            ..
+        })) = attrs.tag_expr
+        {
+            let index = patterns.len();
+            let variant_str = variant_name.to_string();
+            patterns.push(quote! {
+                parsanol::derive::TagPattern::new(#variant_str, #tag_expr)
+            });
+            pattern_arms.push(quote! {
+                Some(#index) => { #conversion }
+            });
+        } else if let Some(tag_expr) = attrs.tag_expr {
+            // Any other expression is a match pattern on the tag, e.g. `"a" | "b"`
+            match_arms.push(quote! {
+                Some(#tag_expr) => { #conversion }
+            });
+        }
     }
 
+    let fallback = if patterns.is_empty() {
+        quote! {
+            _ => Err(parsanol::derive::FromAstError::UnknownTag),
+        }
+    } else {
+        let count = patterns.len();
+        quote! {
+            Some(tag) => {
+                static PATTERNS: [parsanol::derive::TagPattern; #count] = [#(#patterns),*];
+                match parsanol::derive::match_tag_pattern(tag, &PATTERNS)? {
+                    #(#pattern_arms)*
+                    _ => Err(parsanol::derive::FromAstError::UnknownTag),
+                }
+            }
+            None => Err(parsanol::derive::FromAstError::UnknownTag),
+        }
+    };
+
     Ok(quote! {
         // Get the tag from the value
-        let tag = value.get_tag();
-        match tag.map(|s| s.to_string()) {
+        let tag = value.get_tag().map(|s| s.to_string());
+        match tag.as_deref() {
             #(#match_arms)*
-            _ => Err(parsanol::derive::FromAstError::UnknownTag),
+            #fallback
         }
     })
 }
@@ -306,9 +353,27 @@ fn generate_struct_from_ast(name: &Ident, fields: &Fields) -> syn::Result<TokenS
 fn generate_field_extraction_named(field: &syn::Field) -> syn::Result<TokenStream> {
     let fname = field.ident.as_ref().unwrap();
     let attrs = parse_attrs(&field.attrs)?;
-    let field_name = attrs.field.unwrap_or_else(|| fname.to_string());
     let field_ty = &field.ty;
 
+    if attrs.flatten {
+        if attrs.field.is_some() || attrs.default.is_some() || attrs.collect {
+            return Err(syn::Error::new_spanned(
+                field,
+                "#[parsanol(flatten)] cannot be combined with other field attributes",
+            ));
+        }
+        // The nested type reads its fields from this hash
+        return Ok(quote! {
+            let #fname: #field_ty = ::std::convert::TryFrom::try_from(value.clone())?;
+        });
+    }
+
+    let field_name = attrs.field.unwrap_or_else(|| fname.to_string());
+
+    if attrs.collect {
+        return generate_collect_extraction(fname, field_ty, &field_name, attrs.default);
+    }
+
     let extract = match attrs.default {
         Some(DefaultKind::Simple) => {
             quote! {
@@ -344,3 +409,70 @@ fn generate_field_extraction_named(field: &syn::Field) -> syn::Result<TokenStrea
 
     Ok(extract)
 }
+
+/// Generate extraction code for a `#[parsanol(collect)]` field
+///
+/// Each element of the array under `field_name` is converted on its own,
+/// so an error names the element that failed.
+fn generate_collect_extraction(
+    fname: &Ident,
+    field_ty: &syn::Type,
+    field_name: &str,
+    default: Option<DefaultKind>,
+) -> syn::Result<TokenStream> {
+    let elem_ty = vec_element_type(field_ty).ok_or_else(|| {
+        syn::Error::new_spanned(field_ty, "#[parsanol(collect)] requires a `Vec<T>` field")
+    })?;
+
+    let missing = match default {
+        Some(DefaultKind::Simple) => quote! { ::std::default::Default::default() },
+        Some(DefaultKind::Expr(expr)) => quote! { #expr },
+        None => quote! {
+            return Err(parsanol::derive::FromAstError::MissingField(#field_name.to_string()))
+        },
+    };
+
+    Ok(quote! {
+        let #fname: #field_ty = match value.get_hash_field(#field_name) {
+            Some(parsanol::portable::transform::Value::Array(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    <#elem_ty as ::std::convert::TryFrom<parsanol::portable::transform::Value>>::try_from(item.clone())
+                        .map_err(|e| parsanol::derive::FromAstError::Custom(::std::format!(
+                            "field `{}` element {}: {}",
+                            #field_name,
+                            index,
+                            e
+                        )))
+                })
+                .collect::<Result<_, parsanol::derive::FromAstError>>()?,
+            Some(other) => {
+                return Err(parsanol::derive::FromAstError::Custom(::std::format!(
+                    "field `{}` collects an array, got {}",
+                    #field_name,
+                    other.type_name()
+                )))
+            }
+            None => #missing,
+        };
+    })
+}
+
+/// The `T` of a `Vec<T>` type
+fn vec_element_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Vec" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(elem) => Some(elem),
+        _ => None,
+    }
+}
@@ -50,13 +50,21 @@ mod from_ast;
 /// ## Variant attributes
 ///
 /// - `#[parsanol(tag = "name")]` - Match when the AST has this tag/name
-/// - `#[parsanol(tag_expr = "pattern")]` - Match using a pattern
+/// - `#[parsanol(tag_expr = "pattern")]` - Match tags against a regular
+///   expression, anchored at both ends, so one variant can take several
+///   related tags. Exact `tag`s are tried first; a tag matching more than
+///   one pattern is an error
+/// - `#[parsanol(tag_expr = "a" | "b")]` - Any expression other than a
+///   string literal is used as a match pattern on the tag, like `tag`
 ///
 /// ## Field attributes
 ///
 /// - `#[parsanol(field = "name")]` - Extract from hash field with this name
 /// - `#[parsanol(default)]` - Use Default if field is missing
 /// - `#[parsanol(default = "expr")]` - Use expression if field is missing
+/// - `#[parsanol(collect)]` - Convert each element of an array field into a
+///   `Vec<T>`; a non-array value is an error naming the field
+/// - `#[parsanol(flatten)]` - Read a nested struct's fields from this hash
 ///
 /// # Example
 ///
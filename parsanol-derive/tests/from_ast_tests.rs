@@ -52,3 +52,179 @@ fn test_unit_struct() {
     let value = Value::nil();
     let _unit: UnitStruct = value.try_into().unwrap();
 }
+
+// ============================================================================
+// Collected and flattened fields
+// ============================================================================
+
+#[derive(Debug, PartialEq, FromAst)]
+struct Polygon {
+    name: String,
+    #[parsanol(collect, field = "points")]
+    vertices: Vec<Point>,
+    #[parsanol(collect, default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, FromAst)]
+struct LabeledPoint {
+    label: String,
+    #[parsanol(flatten)]
+    point: Point,
+}
+
+fn point_value(x: i64, y: i64) -> Value {
+    Value::hash(vec![("x", Value::int(x)), ("y", Value::int(y))])
+}
+
+#[test]
+fn test_collect_converts_each_array_element() {
+    let value = Value::hash(vec![
+        ("name", Value::string("triangle")),
+        (
+            "points",
+            Value::array(vec![
+                point_value(0, 0),
+                point_value(4, 0),
+                point_value(0, 3),
+            ]),
+        ),
+    ]);
+
+    let polygon: Polygon = value.try_into().unwrap();
+    assert_eq!(polygon.name, "triangle");
+    assert_eq!(
+        polygon.vertices,
+        vec![
+            Point { x: 0, y: 0 },
+            Point { x: 4, y: 0 },
+            Point { x: 0, y: 3 }
+        ]
+    );
+    // Missing, but defaulted
+    assert!(polygon.tags.is_empty());
+}
+
+#[test]
+fn test_collect_rejects_non_array_field() {
+    let value = Value::hash(vec![
+        ("name", Value::string("dot")),
+        ("points", point_value(1, 1)),
+    ]);
+    let error = Polygon::try_from(value).unwrap_err().to_string();
+    assert!(error.contains("points"), "{}", error);
+    assert!(error.contains("array, got hash"), "{}", error);
+
+    // A bad element is reported by position
+    let value = Value::hash(vec![
+        ("name", Value::string("bad")),
+        (
+            "points",
+            Value::array(vec![point_value(1, 1), Value::int(7)]),
+        ),
+    ]);
+    let error = Polygon::try_from(value).unwrap_err().to_string();
+    assert!(error.contains("element 1"), "{}", error);
+}
+
+#[test]
+fn test_flatten_reads_fields_from_parent_hash() {
+    let value = Value::hash(vec![
+        ("label", Value::string("origin")),
+        ("x", Value::int(0)),
+        ("y", Value::int(-2)),
+    ]);
+    let labeled: LabeledPoint = value.try_into().unwrap();
+    assert_eq!(labeled.label, "origin");
+    assert_eq!(labeled.point, Point { x: 0, y: -2 });
+}
+
+// ============================================================================
+// Tag pattern tests
+// ============================================================================
+
+#[derive(Debug, PartialEq, FromAst)]
+enum Expr {
+    #[parsanol(tag = "number")]
+    Number { value: i64 },
+
+    #[parsanol(tag_expr = "binop_.*")]
+    BinOp { left: i64, right: i64 },
+
+    // Exact tags win over patterns that also match
+    #[parsanol(tag = "binop_nop")]
+    Nop,
+}
+
+fn tagged(tag: &str, fields: Vec<(&str, Value)>) -> Value {
+    let mut pairs = vec![("tag", Value::string(tag))];
+    pairs.extend(fields);
+    Value::hash(pairs)
+}
+
+#[test]
+fn test_tag_expr_matches_related_tags() {
+    for tag in ["binop_add", "binop_sub"] {
+        let value = tagged(tag, vec![("left", Value::int(1)), ("right", Value::int(2))]);
+        let expr: Expr = value.try_into().unwrap();
+        assert_eq!(expr, Expr::BinOp { left: 1, right: 2 });
+    }
+
+    let number: Expr = tagged("number", vec![("value", Value::int(7))])
+        .try_into()
+        .unwrap();
+    assert_eq!(number, Expr::Number { value: 7 });
+    let nop: Expr = tagged("binop_nop", vec![]).try_into().unwrap();
+    assert_eq!(nop, Expr::Nop);
+
+    // Patterns match the whole tag
+    assert!(Expr::try_from(tagged("my_binop_add", vec![])).is_err());
+}
+
+#[derive(Debug, PartialEq, FromAst)]
+enum Binding {
+    // Any other expression is a match pattern
+    #[parsanol(tag_expr = "let" | "var")]
+    Variable,
+
+    #[parsanol(tag = "const")]
+    Constant,
+}
+
+#[test]
+fn test_tag_expr_match_pattern() {
+    for tag in ["let", "var"] {
+        let binding: Binding = tagged(tag, vec![]).try_into().unwrap();
+        assert_eq!(binding, Binding::Variable);
+    }
+    let binding: Binding = tagged("const", vec![]).try_into().unwrap();
+    assert_eq!(binding, Binding::Constant);
+    assert!(Binding::try_from(tagged("let_", vec![])).is_err());
+}
+
+#[derive(Debug, FromAst)]
+enum Overlapping {
+    #[parsanol(tag_expr = "op_.*")]
+    Any,
+
+    #[parsanol(tag_expr = "op_(add|sub)")]
+    Arith,
+}
+
+#[test]
+fn test_tag_expr_reports_ambiguous_matches() {
+    assert!(matches!(
+        Overlapping::try_from(tagged("op_mul", vec![])),
+        Ok(Overlapping::Any)
+    ));
+
+    let error = Overlapping::try_from(tagged("op_add", vec![]))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("ambiguous"), "{}", error);
+    assert!(
+        error.contains("Any") && error.contains("Arith"),
+        "{}",
+        error
+    );
+}
@@ -2,7 +2,9 @@
 //!
 //! This module provides error types and utilities for the `FromAst` derive macro.
 
+use regex::Regex;
 use std::fmt;
+use std::sync::OnceLock;
 
 /// Error type for FromAst conversions
 #[derive(Debug, Clone)]
@@ -58,5 +60,68 @@ impl fmt::Display for FromAstError {
 
 impl std::error::Error for FromAstError {}
 
+/// A variant's `#[parsanol(tag_expr = "...")]` pattern
+///
+/// The derive macro emits one `static` per enum, so each pattern is
+/// compiled once, on first use. Patterns are regular expressions that
+/// must match the whole tag.
+#[derive(Debug)]
+pub struct TagPattern {
+    variant: &'static str,
+    pattern: &'static str,
+    regex: OnceLock<Result<Regex, String>>,
+}
+
+impl TagPattern {
+    /// Create a pattern for `variant`; nothing is compiled yet
+    pub const fn new(variant: &'static str, pattern: &'static str) -> Self {
+        Self {
+            variant,
+            pattern,
+            regex: OnceLock::new(),
+        }
+    }
+
+    /// Whether `tag` matches this pattern in full
+    pub fn is_match(&self, tag: &str) -> Result<bool, FromAstError> {
+        let regex = self.regex.get_or_init(|| {
+            Regex::new(&format!("^(?:{})$", self.pattern)).map_err(|e| e.to_string())
+        });
+        match regex {
+            Ok(regex) => Ok(regex.is_match(tag)),
+            Err(error) => Err(FromAstError::Custom(format!(
+                "invalid tag_expr {:?} on variant {}: {}",
+                self.pattern, self.variant, error
+            ))),
+        }
+    }
+}
+
+/// Index of the one pattern that matches `tag`, if any
+///
+/// Used by the generated code once no exact `tag` matched. A tag matching
+/// several patterns is an error naming the variants involved, rather than
+/// silently picking the first.
+pub fn match_tag_pattern(
+    tag: &str,
+    patterns: &[TagPattern],
+) -> Result<Option<usize>, FromAstError> {
+    let mut found = None;
+    for (index, pattern) in patterns.iter().enumerate() {
+        if !pattern.is_match(tag)? {
+            continue;
+        }
+        if let Some(first) = found {
+            let first: &TagPattern = &patterns[first];
+            return Err(FromAstError::Custom(format!(
+                "tag {:?} is ambiguous: it matches both {} ({:?}) and {} ({:?})",
+                tag, first.variant, first.pattern, pattern.variant, pattern.pattern
+            )));
+        }
+        found = Some(index);
+    }
+    Ok(found)
+}
+
 // Re-export derive macros (always available)
 pub use parsanol_derive::FromAst;
@@ -34,10 +34,31 @@
 //! }
 //! ```
 //!
+//! # Streaming
+//!
+//! `parsanol_parse_streaming()` delivers parse events to C function
+//! pointers in a [`ParsanolCallbacks`] table instead of building a JSON
+//! tree, mirroring the Rust `StreamingBuilder` trait:
+//!
+//! ```c
+//! static int on_int(void* user_data, int64_t value) {
+//!     *(int64_t*)user_data += value;
+//!     return PARSANOL_CONTINUE;
+//! }
+//!
+//! ParsanolCallbacks callbacks = {0};
+//! callbacks.on_int = on_int;
+//! int64_t sum = 0;
+//! int rc = parsanol_parse_streaming(grammar, input, strlen(input), &callbacks, &sum);
+//! ```
+//!
 //! # Thread Safety
 //!
 //! All functions in this module are thread-safe. Different threads can
-//! safely use different grammars simultaneously.
+//! safely use different grammars simultaneously, and a grammar is only read
+//! while parsing, so several threads may also parse with the same grammar.
+//! Streaming callbacks run synchronously on the thread that called
+//! `parsanol_parse_streaming()`, and never after it returns.
 //!
 //! # Memory Management
 //!
@@ -47,10 +68,11 @@
 //! - Use `parsanol_result_free()` to free parse results
 
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_int, c_ulong};
+use std::os::raw::{c_char, c_int, c_ulong, c_void};
 use std::ptr;
 
-use crate::portable::Grammar;
+use crate::portable::streaming_builder::{BuildControl, BuildError, BuildResult};
+use crate::portable::{AstArena, Grammar, ParseError, PortableParser, StreamingBuilder};
 
 // ============================================================================
 // Opaque Types
@@ -85,6 +107,16 @@ pub const PARSANOL_ERROR_PARSE_FAILED: c_int = -3;
 pub const PARSANOL_ERROR_OUT_OF_MEMORY: c_int = -4;
 /// Invalid grammar
 pub const PARSANOL_ERROR_INVALID_GRAMMAR: c_int = -5;
+/// A streaming callback returned an error
+pub const PARSANOL_ERROR_CALLBACK: c_int = -6;
+
+/// Streaming callback result: keep delivering events
+pub const PARSANOL_CONTINUE: c_int = 0;
+/// Streaming callback result: stop, the caller has what it needs
+///
+/// Any other positive value also stops; negative values abort the parse
+/// with `PARSANOL_ERROR_CALLBACK`.
+pub const PARSANOL_STOP: c_int = 1;
 
 // ============================================================================
 // Grammar Functions
@@ -99,13 +131,23 @@ pub const PARSANOL_ERROR_INVALID_GRAMMAR: c_int = -5;
 ///     "atoms": [
 ///         {"Str": {"pattern": "hello"}},
 ///         {"Re": {"pattern": "[0-9]+"}},
+///         {"CharRange": {"lo": "a", "hi": "z"}},
+///         {"OneOf": {"chars": ["+", "-"]}},
+///         {"NoneOf": {"chars": ["\"", "\\"]}},
 ///         {"Sequence": {"atoms": [0, 1]}},
 ///         {"Alternative": {"atoms": [0, 1]}},
+///         {"LongestAlternative": {"atoms": [0, 1]}},
 ///         {"Repetition": {"atom": 0, "min": 0, "max": null}},
 ///         {"Named": {"name": "value", "atom": 0}},
 ///         {"Entity": {"atom": 0}},
 ///         {"Lookahead": {"atom": 0, "positive": true}},
+///         {"Peek": {"atom": 0}},
+///         {"Infix": {"operand": 1, "operators": [{"pattern": "+", "precedence": 1, "associativity": "Left"}]}},
 ///         {"Cut": null},
+///         {"Eof": null},
+///         {"Indent": null},
+///         {"Samedent": null},
+///         {"Dedent": null},
 ///         {"Ignore": {"atom": 0}},
 ///         {"Custom": {"id": 100}}
 ///     ]
@@ -331,6 +373,299 @@ pub unsafe extern "C" fn parsanol_parse_end_pos(
     }
 }
 
+// ============================================================================
+// Streaming Functions
+// ============================================================================
+
+/// Callback receiving a string: `(user_data, ptr, len)`
+///
+/// The bytes are UTF-8 and are *not* NUL-terminated; they are only valid
+/// for the duration of the call.
+pub type ParsanolStrCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, ptr: *const c_char, len: usize) -> c_int>;
+
+/// Callback receiving a length or index: `(user_data, value)`
+pub type ParsanolSizeCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, value: usize) -> c_int>;
+
+/// Callback receiving an expected length, or -1 if unknown
+pub type ParsanolLenHintCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, expected_len: i64) -> c_int>;
+
+/// Parse event callbacks for `parsanol_parse_streaming()`
+///
+/// Each field mirrors the `StreamingBuilder` method of the same name and
+/// may be NULL to ignore that event. Every callback receives the
+/// `user_data` pointer passed to `parsanol_parse_streaming()` and returns
+/// `PARSANOL_CONTINUE`, `PARSANOL_STOP`, or a negative value to abort.
+///
+/// The field order is part of the ABI; zero-initialize the struct and set
+/// the callbacks you need.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParsanolCallbacks {
+    /// A named capture starts
+    pub on_named_start: ParsanolStrCallback,
+    /// A named capture ends
+    pub on_named_end: ParsanolStrCallback,
+    /// A string value: `(user_data, ptr, len, offset)`, where `offset` is
+    /// the byte offset of the match in the input
+    pub on_string: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            ptr: *const c_char,
+            len: usize,
+            offset: usize,
+        ) -> c_int,
+    >,
+    /// An integer value
+    pub on_int: Option<unsafe extern "C" fn(user_data: *mut c_void, value: i64) -> c_int>,
+    /// A float value
+    pub on_float: Option<unsafe extern "C" fn(user_data: *mut c_void, value: f64) -> c_int>,
+    /// A boolean value, as 0 or 1
+    pub on_bool: Option<unsafe extern "C" fn(user_data: *mut c_void, value: c_int) -> c_int>,
+    /// A nil value
+    pub on_nil: Option<unsafe extern "C" fn(user_data: *mut c_void) -> c_int>,
+    /// An array starts
+    pub on_array_start: ParsanolLenHintCallback,
+    /// An array element was delivered, with its index
+    pub on_array_element: ParsanolSizeCallback,
+    /// An array ends, with its length
+    pub on_array_end: ParsanolSizeCallback,
+    /// A hash starts
+    pub on_hash_start: ParsanolLenHintCallback,
+    /// A hash key, before its value's events
+    pub on_hash_key: ParsanolStrCallback,
+    /// A hash value was delivered, with its key
+    pub on_hash_value: ParsanolStrCallback,
+    /// A hash ends, with its length
+    pub on_hash_end: ParsanolSizeCallback,
+    /// The parse failed, with the error message
+    pub on_error: ParsanolStrCallback,
+}
+
+/// Bridges [`StreamingBuilder`] events to a [`ParsanolCallbacks`] table
+struct CallbackBuilder<'a> {
+    callbacks: &'a ParsanolCallbacks,
+    user_data: *mut c_void,
+    stopped: bool,
+    callback_failed: bool,
+    error_reported: bool,
+}
+
+impl CallbackBuilder<'_> {
+    /// Turn a callback's return code into flow control
+    fn check(&mut self, rc: Option<c_int>) -> BuildResult<()> {
+        match rc.unwrap_or(PARSANOL_CONTINUE) {
+            PARSANOL_CONTINUE => Ok(()),
+            rc if rc > 0 => {
+                self.stopped = true;
+                Ok(())
+            }
+            rc => {
+                self.callback_failed = true;
+                Err(BuildError::Custom {
+                    message: format!("callback returned {}", rc),
+                })
+            }
+        }
+    }
+
+    fn call_str(&mut self, callback: ParsanolStrCallback, s: &str) -> BuildResult<()> {
+        let user_data = self.user_data;
+        // SAFETY: the caller of `parsanol_parse_streaming` vouches for the
+        // callbacks; `s` outlives the call
+        let rc = callback.map(|f| unsafe { f(user_data, s.as_ptr().cast(), s.len()) });
+        self.check(rc)
+    }
+
+    fn call_size(&mut self, callback: ParsanolSizeCallback, value: usize) -> BuildResult<()> {
+        let user_data = self.user_data;
+        // SAFETY: see `call_str`
+        let rc = callback.map(|f| unsafe { f(user_data, value) });
+        self.check(rc)
+    }
+
+    fn call_len_hint(
+        &mut self,
+        callback: ParsanolLenHintCallback,
+        expected_len: Option<usize>,
+    ) -> BuildResult<()> {
+        let user_data = self.user_data;
+        let hint = expected_len.map_or(-1, |len| len as i64);
+        // SAFETY: see `call_str`
+        let rc = callback.map(|f| unsafe { f(user_data, hint) });
+        self.check(rc)
+    }
+}
+
+impl StreamingBuilder for CallbackBuilder<'_> {
+    type Output = ();
+
+    fn on_named_start(&mut self, name: &str) -> BuildResult<()> {
+        self.call_str(self.callbacks.on_named_start, name)
+    }
+
+    fn on_named_end(&mut self, name: &str) -> BuildResult<()> {
+        self.call_str(self.callbacks.on_named_end, name)
+    }
+
+    fn on_string(&mut self, value: &str, offset: usize, _length: usize) -> BuildResult<()> {
+        let user_data = self.user_data;
+        // SAFETY: see `call_str`
+        let rc = self
+            .callbacks
+            .on_string
+            .map(|f| unsafe { f(user_data, value.as_ptr().cast(), value.len(), offset) });
+        self.check(rc)
+    }
+
+    fn on_int(&mut self, value: i64) -> BuildResult<()> {
+        let user_data = self.user_data;
+        // SAFETY: see `call_str`
+        let rc = self
+            .callbacks
+            .on_int
+            .map(|f| unsafe { f(user_data, value) });
+        self.check(rc)
+    }
+
+    fn on_float(&mut self, value: f64) -> BuildResult<()> {
+        let user_data = self.user_data;
+        // SAFETY: see `call_str`
+        let rc = self
+            .callbacks
+            .on_float
+            .map(|f| unsafe { f(user_data, value) });
+        self.check(rc)
+    }
+
+    fn on_bool(&mut self, value: bool) -> BuildResult<()> {
+        let user_data = self.user_data;
+        // SAFETY: see `call_str`
+        let rc = self
+            .callbacks
+            .on_bool
+            .map(|f| unsafe { f(user_data, c_int::from(value)) });
+        self.check(rc)
+    }
+
+    fn on_nil(&mut self) -> BuildResult<()> {
+        let user_data = self.user_data;
+        // SAFETY: see `call_str`
+        let rc = self.callbacks.on_nil.map(|f| unsafe { f(user_data) });
+        self.check(rc)
+    }
+
+    fn on_array_start(&mut self, expected_len: Option<usize>) -> BuildResult<()> {
+        self.call_len_hint(self.callbacks.on_array_start, expected_len)
+    }
+
+    fn on_array_element(&mut self, index: usize) -> BuildResult<()> {
+        self.call_size(self.callbacks.on_array_element, index)
+    }
+
+    fn on_array_end(&mut self, actual_len: usize) -> BuildResult<()> {
+        self.call_size(self.callbacks.on_array_end, actual_len)
+    }
+
+    fn on_hash_start(&mut self, expected_len: Option<usize>) -> BuildResult<()> {
+        self.call_len_hint(self.callbacks.on_hash_start, expected_len)
+    }
+
+    fn on_hash_key(&mut self, key: &str) -> BuildResult<()> {
+        self.call_str(self.callbacks.on_hash_key, key)
+    }
+
+    fn on_hash_value(&mut self, key: &str) -> BuildResult<()> {
+        self.call_str(self.callbacks.on_hash_value, key)
+    }
+
+    fn on_hash_end(&mut self, actual_len: usize) -> BuildResult<()> {
+        self.call_size(self.callbacks.on_hash_end, actual_len)
+    }
+
+    fn on_error(&mut self, error: &ParseError) -> BuildResult<()> {
+        self.error_reported = true;
+        self.call_str(self.callbacks.on_error, &error.to_string())
+    }
+
+    fn control(&self) -> BuildControl {
+        if self.stopped {
+            BuildControl::Stop
+        } else {
+            BuildControl::Continue
+        }
+    }
+
+    fn finish(&mut self) -> BuildResult<()> {
+        Ok(())
+    }
+}
+
+/// Parse input, delivering events to C callbacks instead of building a tree
+///
+/// `input` is `input_len` bytes of UTF-8 and need not be NUL-terminated.
+/// When the grammar's root is a repetition, each item is parsed and
+/// delivered on its own, so memory use stays bounded by the largest item
+/// rather than the whole document.
+///
+/// Returns `PARSANOL_OK` when the parse succeeded or a callback returned
+/// `PARSANOL_STOP`, `PARSANOL_ERROR_CALLBACK` when a callback returned a
+/// negative value, and `PARSANOL_ERROR_PARSE_FAILED` when the parse failed
+/// or the input is not UTF-8; `on_error` then receives the message. Events
+/// already delivered before a failure stand.
+///
+/// # Safety
+///
+/// - `grammar` must be a valid pointer returned by `parsanol_grammar_new`
+/// - `input` must point to `input_len` readable bytes
+/// - `callbacks` must point to a valid `ParsanolCallbacks`, whose non-NULL
+///   entries must be safe to call with `user_data`
+/// - Callbacks must not unwind (e.g. throw C++ exceptions or `longjmp`)
+///   across the call
+#[no_mangle]
+pub unsafe extern "C" fn parsanol_parse_streaming(
+    grammar: *const ParsanolGrammar,
+    input: *const c_char,
+    input_len: usize,
+    callbacks: *const ParsanolCallbacks,
+    user_data: *mut c_void,
+) -> c_int {
+    if grammar.is_null() || input.is_null() || callbacks.is_null() {
+        return PARSANOL_ERROR_NULL_POINTER;
+    }
+
+    let grammar_ref = &*grammar;
+    let mut builder = CallbackBuilder {
+        callbacks: &*callbacks,
+        user_data,
+        stopped: false,
+        callback_failed: false,
+        error_reported: false,
+    };
+
+    let bytes = std::slice::from_raw_parts(input.cast::<u8>(), input_len);
+    let mut arena = AstArena::for_input(bytes.len());
+    let mut parser = match PortableParser::from_bytes(&grammar_ref.grammar, bytes, &mut arena) {
+        Ok(parser) => parser,
+        Err(e) => {
+            let _ = builder.on_error(&e);
+            return PARSANOL_ERROR_PARSE_FAILED;
+        }
+    };
+    match parser.parse_streaming(&mut builder) {
+        Ok(()) => PARSANOL_OK,
+        Err(_) if builder.callback_failed => PARSANOL_ERROR_CALLBACK,
+        Err(e) => {
+            if !builder.error_reported {
+                let _ = builder.on_error(&e);
+            }
+            PARSANOL_ERROR_PARSE_FAILED
+        }
+    }
+}
+
 // ============================================================================
 // Result Functions
 // ============================================================================
@@ -551,4 +886,123 @@ mod tests {
 
         unsafe { parsanol_grammar_free(grammar) };
     }
+
+    /// Events seen by the test callbacks, and the string to stop or fail at
+    #[derive(Default)]
+    struct Events {
+        strings: Vec<String>,
+        keys: Vec<String>,
+        errors: Vec<String>,
+        stop_at: Option<&'static str>,
+        fail_at: Option<&'static str>,
+    }
+
+    unsafe fn events<'a>(user_data: *mut c_void) -> &'a mut Events {
+        &mut *user_data.cast::<Events>()
+    }
+
+    unsafe fn text(ptr: *const c_char, len: usize) -> String {
+        let bytes = std::slice::from_raw_parts(ptr.cast::<u8>(), len);
+        std::str::from_utf8(bytes).unwrap().to_string()
+    }
+
+    unsafe extern "C" fn record_string(
+        user_data: *mut c_void,
+        ptr: *const c_char,
+        len: usize,
+        _offset: usize,
+    ) -> c_int {
+        let events = events(user_data);
+        let value = text(ptr, len);
+        let rc = if events.stop_at == Some(value.as_str()) {
+            PARSANOL_STOP
+        } else if events.fail_at == Some(value.as_str()) {
+            -1
+        } else {
+            PARSANOL_CONTINUE
+        };
+        events.strings.push(value);
+        rc
+    }
+
+    unsafe extern "C" fn record_key(
+        user_data: *mut c_void,
+        ptr: *const c_char,
+        len: usize,
+    ) -> c_int {
+        events(user_data).keys.push(text(ptr, len));
+        PARSANOL_CONTINUE
+    }
+
+    unsafe extern "C" fn record_error(
+        user_data: *mut c_void,
+        ptr: *const c_char,
+        len: usize,
+    ) -> c_int {
+        events(user_data).errors.push(text(ptr, len));
+        PARSANOL_CONTINUE
+    }
+
+    fn stream(input: &[u8], events: &mut Events) -> c_int {
+        use crate::portable::parser_dsl::*;
+
+        let item = re("[0-9]").label("n").then(str(",").optional());
+        let grammar = Box::into_raw(Box::new(ParsanolGrammar {
+            grammar: GrammarBuilder::new().rule("list", item.many()).build(),
+        }));
+        let callbacks = ParsanolCallbacks {
+            on_string: Some(record_string),
+            on_hash_key: Some(record_key),
+            on_error: Some(record_error),
+            ..Default::default()
+        };
+
+        let rc = unsafe {
+            parsanol_parse_streaming(
+                grammar,
+                input.as_ptr().cast(),
+                input.len(),
+                &callbacks,
+                (events as *mut Events).cast(),
+            )
+        };
+        unsafe { parsanol_grammar_free(grammar) };
+        rc
+    }
+
+    #[test]
+    fn test_parse_streaming_delivers_events() {
+        let mut events = Events::default();
+        assert_eq!(stream(b"1,2,3", &mut events), PARSANOL_OK);
+        assert_eq!(events.strings, ["1", "2", "3"]);
+        assert_eq!(events.keys, ["n", "n", "n"]);
+        assert!(events.errors.is_empty());
+
+        // Stopping is not an error; later items are never parsed
+        let mut events = Events {
+            stop_at: Some("2"),
+            ..Default::default()
+        };
+        assert_eq!(stream(b"1,2,3", &mut events), PARSANOL_OK);
+        assert_eq!(events.strings, ["1", "2"]);
+
+        let mut events = Events {
+            fail_at: Some("2"),
+            ..Default::default()
+        };
+        assert_eq!(stream(b"1,2,3", &mut events), PARSANOL_ERROR_CALLBACK);
+    }
+
+    #[test]
+    fn test_parse_streaming_reports_parse_errors() {
+        let mut events = Events::default();
+        assert_eq!(stream(b"1,x", &mut events), PARSANOL_ERROR_PARSE_FAILED);
+        assert_eq!(events.strings, ["1"]);
+        assert_eq!(events.errors.len(), 1);
+
+        let mut events = Events::default();
+        assert_eq!(stream(b"1,\xff", &mut events), PARSANOL_ERROR_PARSE_FAILED);
+        assert!(events.strings.is_empty());
+        assert!(events.errors[0].contains("UTF-8"));
+    }
 }
@@ -16,6 +16,7 @@ use magnus::{value::ReprValue, IntoValue, Ruby, Value};
 ///   def on_named_start(name); end
 ///   def on_named_end(name); end
 ///   def on_string(value, offset, length); end
+///   def on_ignored(text, offset, length); end   # optional
 ///   def on_int(value); end
 ///   def on_float(value); end
 ///   def on_bool(value); end
@@ -100,6 +101,24 @@ impl StreamingBuilder for RubyBuilder {
         self.call_method("on_string", &[value_val, offset_val, length_val])
     }
 
+    fn on_ignored(&mut self, text: &str, offset: usize, length: usize) -> BuildResult<()> {
+        let ruby = Ruby::get().map_err(|e| BuildError::Custom {
+            message: format!("Ruby not available: {}", e),
+        })?;
+        // Optional, so builders written before it existed keep working
+        if !self
+            .callback
+            .respond_to("on_ignored", false)
+            .unwrap_or(false)
+        {
+            return Ok(());
+        }
+        let text_val: Value = ruby.str_new(text).as_value();
+        let offset_val: Value = ruby.integer_from_i64(offset as i64).as_value();
+        let length_val: Value = ruby.integer_from_i64(length as i64).as_value();
+        self.call_method("on_ignored", &[text_val, offset_val, length_val])
+    }
+
     fn on_int(&mut self, value: i64) -> BuildResult<()> {
         let ruby = Ruby::get().map_err(|e| BuildError::Custom {
             message: format!("Ruby not available: {}", e),
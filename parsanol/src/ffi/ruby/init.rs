@@ -65,6 +65,13 @@ fn ruby_has_callback(id: u64) -> bool {
     has_dynamic_callback(id)
 }
 
+/// Clear the compiled regex cache
+///
+/// @return [nil]
+fn ruby_clear_regex_cache() {
+    crate::portable::regex_cache::clear()
+}
+
 /// Initialize the Ruby native extension module
 #[magnus::init]
 pub fn init(ruby: &Ruby) -> Result<(), Error> {
@@ -113,6 +120,10 @@ pub fn init(ruby: &Ruby) -> Result<(), Error> {
         function!(grammar_cache_capacity, 0),
     )?;
 
+    // Clear compiled regexes, e.g. after discarding transient grammars
+    native_module
+        .define_module_function("clear_regex_cache", function!(ruby_clear_regex_cache, 0))?;
+
     // =========================================================================
     // DYNAMIC CALLBACKS - For advanced use cases
     // =========================================================================
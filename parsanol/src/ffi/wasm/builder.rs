@@ -0,0 +1,192 @@
+//! JsBuilder - streaming builder wrapper for JavaScript callbacks
+
+use crate::portable::ast::ParseError;
+use crate::portable::streaming_builder::{BuildError, BuildResult, StreamingBuilder};
+use js_sys::{Array, Function, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// JavaScript callback wrapper for streaming builder
+///
+/// This wraps a JavaScript object that implements the builder callback
+/// protocol, the counterpart of `RubyBuilder` for the Ruby bindings.
+/// Unlike Ruby builders, every callback is optional: events whose method
+/// is missing are skipped, so an object only needs the methods it cares
+/// about.
+///
+/// # JavaScript Interface
+///
+/// ```javascript
+/// const builder = {
+///   on_named_start(name) {},
+///   on_named_end(name) {},
+///   on_string(value, offset, length) {},
+///   on_ignored(text, offset, length) {},
+///   on_int(value) {},
+///   on_float(value) {},
+///   on_bool(value) {},
+///   on_nil() {},
+///   on_array_start(expected_len) {},   // expected_len may be null
+///   on_array_element(index) {},
+///   on_array_end(actual_len) {},
+///   on_hash_start(expected_len) {},    // expected_len may be null
+///   on_hash_key(key) {},
+///   on_hash_value(key) {},
+///   on_hash_end(actual_len) {},
+///   on_start(input) {},
+///   on_success() {},
+///   on_error(message) {},
+///   finish() { return result; },
+/// };
+/// ```
+///
+/// A callback that throws aborts the parse; the thrown value's message is
+/// reported as a [`BuildError::Custom`].
+pub struct JsBuilder {
+    /// The JavaScript object implementing callbacks
+    callback: JsValue,
+}
+
+impl JsBuilder {
+    /// Create a new JavaScript builder wrapper
+    ///
+    /// # Arguments
+    /// * `callback` - JavaScript object with callback methods
+    pub fn new(callback: JsValue) -> Self {
+        Self { callback }
+    }
+
+    /// Call a method on the JavaScript callback object
+    ///
+    /// Returns `undefined` without calling anything if the object has no
+    /// such method.
+    fn call_method(&self, method: &str, args: &[JsValue]) -> BuildResult<JsValue> {
+        let func = Reflect::get(&self.callback, &JsValue::from_str(method))
+            .map_err(|e| js_error(method, e))?;
+        let Some(func) = func.dyn_ref::<Function>() else {
+            return Ok(JsValue::UNDEFINED);
+        };
+
+        let args: Array = args.iter().collect();
+        func.apply(&self.callback, &args)
+            .map_err(|e| js_error(method, e))
+    }
+
+    /// Call a callback, discarding its return value
+    fn notify(&self, method: &str, args: &[JsValue]) -> BuildResult<()> {
+        self.call_method(method, args).map(|_| ())
+    }
+}
+
+/// Convert a thrown JavaScript value into a build error
+fn js_error(method: &str, error: JsValue) -> BuildError {
+    let message = error
+        .dyn_ref::<js_sys::Error>()
+        .map(|e| String::from(e.message()))
+        .or_else(|| error.as_string())
+        .unwrap_or_else(|| format!("{:?}", error));
+    BuildError::Custom {
+        message: format!("JavaScript callback error in {}: {}", method, message),
+    }
+}
+
+/// Convert an optional length to a JavaScript number or `null`
+fn optional_len(len: Option<usize>) -> JsValue {
+    match len {
+        Some(n) => JsValue::from_f64(n as f64),
+        None => JsValue::NULL,
+    }
+}
+
+impl StreamingBuilder for JsBuilder {
+    type Output = JsValue;
+
+    fn on_named_start(&mut self, name: &str) -> BuildResult<()> {
+        self.notify("on_named_start", &[JsValue::from_str(name)])
+    }
+
+    fn on_named_end(&mut self, name: &str) -> BuildResult<()> {
+        self.notify("on_named_end", &[JsValue::from_str(name)])
+    }
+
+    fn on_string(&mut self, value: &str, offset: usize, length: usize) -> BuildResult<()> {
+        self.notify(
+            "on_string",
+            &[
+                JsValue::from_str(value),
+                JsValue::from_f64(offset as f64),
+                JsValue::from_f64(length as f64),
+            ],
+        )
+    }
+
+    fn on_ignored(&mut self, text: &str, offset: usize, length: usize) -> BuildResult<()> {
+        self.notify(
+            "on_ignored",
+            &[
+                JsValue::from_str(text),
+                JsValue::from_f64(offset as f64),
+                JsValue::from_f64(length as f64),
+            ],
+        )
+    }
+
+    fn on_int(&mut self, value: i64) -> BuildResult<()> {
+        self.notify("on_int", &[JsValue::from_f64(value as f64)])
+    }
+
+    fn on_float(&mut self, value: f64) -> BuildResult<()> {
+        self.notify("on_float", &[JsValue::from_f64(value)])
+    }
+
+    fn on_bool(&mut self, value: bool) -> BuildResult<()> {
+        self.notify("on_bool", &[JsValue::from_bool(value)])
+    }
+
+    fn on_nil(&mut self) -> BuildResult<()> {
+        self.notify("on_nil", &[])
+    }
+
+    fn on_array_start(&mut self, expected_len: Option<usize>) -> BuildResult<()> {
+        self.notify("on_array_start", &[optional_len(expected_len)])
+    }
+
+    fn on_array_element(&mut self, index: usize) -> BuildResult<()> {
+        self.notify("on_array_element", &[JsValue::from_f64(index as f64)])
+    }
+
+    fn on_array_end(&mut self, actual_len: usize) -> BuildResult<()> {
+        self.notify("on_array_end", &[JsValue::from_f64(actual_len as f64)])
+    }
+
+    fn on_hash_start(&mut self, expected_len: Option<usize>) -> BuildResult<()> {
+        self.notify("on_hash_start", &[optional_len(expected_len)])
+    }
+
+    fn on_hash_key(&mut self, key: &str) -> BuildResult<()> {
+        self.notify("on_hash_key", &[JsValue::from_str(key)])
+    }
+
+    fn on_hash_value(&mut self, key: &str) -> BuildResult<()> {
+        self.notify("on_hash_value", &[JsValue::from_str(key)])
+    }
+
+    fn on_hash_end(&mut self, actual_len: usize) -> BuildResult<()> {
+        self.notify("on_hash_end", &[JsValue::from_f64(actual_len as f64)])
+    }
+
+    fn on_start(&mut self, input: &str) -> BuildResult<()> {
+        self.notify("on_start", &[JsValue::from_str(input)])
+    }
+
+    fn on_success(&mut self) -> BuildResult<()> {
+        self.notify("on_success", &[])
+    }
+
+    fn on_error(&mut self, error: &ParseError) -> BuildResult<()> {
+        self.notify("on_error", &[JsValue::from_str(&error.to_string())])
+    }
+
+    fn finish(&mut self) -> BuildResult<JsValue> {
+        self.call_method("finish", &[])
+    }
+}
@@ -4,6 +4,10 @@
 //! When compiled with the `wasm` feature, this exposes a `WasmParser` class
 //! that can be used from JavaScript.
 
+mod builder;
+
+pub use builder::JsBuilder;
+
 use crate::portable::{AstArena, AstNode, Grammar, PortableParser};
 use js_sys::{Array, JsString, Object, Reflect};
 use wasm_bindgen::prelude::*;
@@ -123,6 +127,36 @@ impl WasmParser {
 
         Ok(json.as_string().unwrap_or_default())
     }
+
+    /// Parse input, streaming events to a JavaScript builder object
+    ///
+    /// The builder's `on_*` methods are called as the AST is walked (see
+    /// [`JsBuilder`] for the protocol), and the value returned by its
+    /// `finish` method is returned.
+    ///
+    /// # Arguments
+    /// * `input` - The input string to parse
+    /// * `builder` - Object with `on_string`, `on_int`, ... and `finish` methods
+    ///
+    /// # Returns
+    /// Whatever the builder's `finish` returns
+    ///
+    /// # Throws
+    /// If parsing fails or a builder callback throws
+    #[wasm_bindgen]
+    pub fn parse_with_builder(
+        &mut self,
+        input: &str,
+        builder: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        self.arena.reset();
+
+        let mut js_builder = JsBuilder::new(builder);
+        let mut parser = PortableParser::new(&self.grammar, input, &mut self.arena);
+        parser
+            .parse_with_builder(&mut js_builder)
+            .map_err(|e| JsValue::from_str(&format!("Parse error: {}", e)))
+    }
 }
 
 /// Convert AST node to JavaScript value
@@ -188,3 +222,13 @@ pub fn init() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+/// Clear the compiled regex cache
+///
+/// The cache lives as long as the module instance. Call this from a
+/// long-lived page to release patterns compiled for grammars that are no
+/// longer used.
+#[wasm_bindgen]
+pub fn clear_regex_cache() {
+    crate::portable::regex_cache::clear();
+}
@@ -76,13 +76,13 @@ pub mod portable;
 /// Re-export commonly used types for convenience
 pub use portable::{
     // Debug tools
-    debug::{GrammarVisualizer, ParseTrace, SourceFormatter, TreePrinter},
+    debug::{AtomProfile, GrammarVisualizer, ParseTrace, Profile, SourceFormatter, TreePrinter},
     // Rich errors
     error::{ErrorBuilder, RichError, Span},
     // Incremental parsing
     incremental::{DirtyRegion, DirtyRegionTracker, Edit, IncrementalParser, IncrementalResult},
     // Infix parsing
-    infix::{infix, Assoc, InfixBuilder, Operator, PrecedenceClimber},
+    infix::{infix, Assoc, Fixity, InfixBuilder, Operator, PrecedenceClimber},
     // Parser DSL
     parser_dsl::{
         any, choice, dynamic, re, ref_, seq, str, Alternative2, Alternative3, Alternative4,
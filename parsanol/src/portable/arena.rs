@@ -3,10 +3,40 @@
 //! This arena allocator provides O(1) allocation for parse trees.
 //! All nodes are allocated in a single contiguous memory region,
 //! providing excellent cache locality and O(1) deallocation (just reset).
+//!
+//! # Node spans
+//!
+//! An arena created with [`AstArena::with_spans`] also remembers which
+//! part of the input every array and hash node was parsed from, so spans
+//! survive transformations that drop the `InputRef` leaves. The spans live
+//! in vectors parallel to the array and hash pools, indexed by a node's
+//! `pool_index`, costing 8 bytes per pool entry; the arena also keeps a
+//! copy of the input to compute line and column numbers. Arenas created
+//! any other way record nothing.
+//!
+//! # String interning
+//!
+//! Hash keys, sequence tags and strings passed to
+//! [`AstArena::intern_string`] are stored once in a string pool and
+//! referenced by index. Matched input is normally not interned at all: an
+//! [`AstNode::InputRef`] is just an offset and a length, so it costs no
+//! hashing and no copying. Interning pays off when the same short strings
+//! recur many times and need to be compared or kept after the input is
+//! gone (see [`AstArena::with_interned_inputs`]); for text that is mostly
+//! unique, plain input references are cheaper.
+//!
+//! The pool looks strings up by a 64-bit hash, computed with a fixed-key
+//! `ahash` by default. [`AstArena::with_hasher`] plugs in any other
+//! [`BuildHasher`], such as a randomly seeded `ahash::RandomState`.
 
 use super::ast::AstNode;
+use super::source_location::SourceSpan;
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
 use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
 
 /// String pool entry
 #[derive(Debug, Clone, Copy)]
@@ -35,6 +65,77 @@ struct ArrayPoolEntry {
     value: AstNode,
 }
 
+/// Marks a pool slot with no recorded span
+const NO_SPAN: (u32, u32) = (u32::MAX, u32::MAX);
+
+/// Byte ranges of array and hash nodes, indexed by their `pool_index`
+#[derive(Debug, Default)]
+struct NodeSpans {
+    arrays: Vec<(u32, u32)>,
+    hashes: Vec<(u32, u32)>,
+}
+
+impl NodeSpans {
+    fn record(slots: &mut Vec<(u32, u32)>, pool_index: u32, start: usize, end: usize) {
+        let index = pool_index as usize;
+        if slots.len() <= index {
+            slots.resize(index + 1, NO_SPAN);
+        }
+        slots[index] = (start as u32, end as u32);
+    }
+
+    fn get(slots: &[(u32, u32)], pool_index: u32) -> Option<(usize, usize)> {
+        match slots.get(pool_index as usize) {
+            Some(&(start, end)) if (start, end) != NO_SPAN => Some((start as usize, end as usize)),
+            _ => None,
+        }
+    }
+
+    fn truncate(&mut self, arrays: usize, hashes: usize) {
+        self.arrays.truncate(arrays);
+        self.hashes.truncate(hashes);
+    }
+}
+
+/// Hash function for pooled strings, set by `with_hasher`
+#[derive(Clone)]
+struct StringHasher(Arc<dyn Fn(&str) -> u64 + Send + Sync>);
+
+impl fmt::Debug for StringHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StringHasher")
+    }
+}
+
+/// Hasher for keys that already are hashes
+///
+/// The string index is keyed by the string's own 64-bit hash, so hashing
+/// the key a second time would only cost time.
+#[derive(Default)]
+struct PrehashedHasher(u64);
+
+impl Hasher for PrehashedHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(b);
+        }
+    }
+
+    #[inline]
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+}
+
+/// String hash -> string pool index
+type StringIndex = HashMap<u64, usize, BuildHasherDefault<PrehashedHasher>>;
+
 /// The arena allocator
 #[derive(Debug)]
 pub struct AstArena {
@@ -43,14 +144,19 @@ pub struct AstArena {
     /// String pool (offset, length) pairs
     string_pool: Vec<StringPoolEntry>,
     /// Hash map for O(1) string lookup (hash -> pool index)
-    /// Only used when string_pool.len() >= 64
-    string_hash: HashMap<u64, usize>,
+    string_hash: StringIndex,
+    /// Custom string hash function, if set with `with_hasher`
+    string_hasher: Option<StringHasher>,
     /// Array pool - stores AST nodes
     array_pool: Vec<ArrayPoolEntry>,
     /// Hash pool - key-value pairs
     hash_pool: Vec<HashPoolEntry>,
     /// Original input string (for InputRef offset lookup)
     input: Option<String>,
+    /// Spans of array and hash nodes, if enabled with `with_spans`
+    spans: Option<NodeSpans>,
+    /// Whether parsers intern matched text, set by `with_interned_inputs`
+    intern_inputs: bool,
 }
 
 impl Default for AstArena {
@@ -72,10 +178,13 @@ impl AstArena {
         Self {
             string_data: Vec::with_capacity(4096),
             string_pool: Vec::with_capacity(capacity),
-            string_hash: HashMap::new(),
+            string_hash: StringIndex::default(),
+            string_hasher: None,
             array_pool: Vec::with_capacity(capacity * 2),
             hash_pool: Vec::with_capacity(capacity),
             input: None,
+            spans: None,
+            intern_inputs: false,
         }
     }
 
@@ -104,13 +213,137 @@ impl AstArena {
         Self {
             string_data: Vec::with_capacity(string_data_capacity),
             string_pool: Vec::with_capacity(string_capacity),
-            string_hash: HashMap::with_capacity(string_capacity),
+            string_hash: StringIndex::with_capacity_and_hasher(string_capacity, Default::default()),
+            string_hasher: None,
             array_pool: Vec::with_capacity(estimated_nodes * 2),
             hash_pool: Vec::with_capacity(estimated_nodes),
             input: None,
+            spans: None,
+            intern_inputs: false,
         }
     }
 
+    /// Create an arena whose string pool hashes strings with `hasher`
+    ///
+    /// The default is `ahash` with fixed keys, which is fast but
+    /// predictable; pass `ahash::RandomState::new()` for randomly seeded
+    /// hashing, or the standard library's `RandomState` for SipHash. Only
+    /// the speed of [`intern_string`](Self::intern_string) and hash key
+    /// lookups changes, never which strings are pooled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::AstArena;
+    ///
+    /// let mut arena = AstArena::with_hasher(ahash::RandomState::new());
+    /// let a = arena.intern_string("key");
+    /// let b = arena.intern_string("key");
+    /// assert_eq!(a, b);
+    /// assert_eq!(arena.string_count(), 1);
+    /// ```
+    pub fn with_hasher<S>(hasher: S) -> Self
+    where
+        S: BuildHasher + Send + Sync + 'static,
+    {
+        Self {
+            string_hasher: Some(StringHasher(Arc::new(move |s: &str| hasher.hash_one(s)))),
+            ..Self::new()
+        }
+    }
+
+    /// Create an arena that records the span of every array and hash node
+    ///
+    /// Parsing into this arena costs 8 extra bytes per array element and
+    /// hash pair, plus a copy of the input; see the [module docs](self).
+    /// Look spans up with [`span_of`](Self::span_of).
+    pub fn with_spans() -> Self {
+        Self {
+            spans: Some(NodeSpans::default()),
+            ..Self::new()
+        }
+    }
+
+    /// Whether this arena records node spans
+    #[inline]
+    pub fn spans_enabled(&self) -> bool {
+        self.spans.is_some()
+    }
+
+    /// Create an arena that interns matched text instead of referencing it
+    ///
+    /// [`PortableParser`](crate::portable::PortableParser) parsing into this
+    /// arena produces [`AstNode::StringRef`] nodes where the default
+    /// produces [`AstNode::InputRef`]: identical text (such as
+    /// a keyword matched many times) shares one string pool entry, so equal
+    /// matches compare equal by pool index. Each match costs a hash lookup,
+    /// and interned matches no longer carry their input position.
+    pub fn with_interned_inputs() -> Self {
+        Self {
+            intern_inputs: true,
+            ..Self::new()
+        }
+    }
+
+    /// Whether parsers intern matched text into this arena
+    #[inline]
+    pub fn interns_inputs(&self) -> bool {
+        self.intern_inputs
+    }
+
+    /// Keep a copy of the input spans are computed against, if enabled
+    #[inline]
+    pub(crate) fn attach_span_source(&mut self, input: &str) {
+        if self.spans.is_some() && self.input.as_deref() != Some(input) {
+            self.input = Some(input.to_string());
+        }
+    }
+
+    /// Record that `node` was parsed from `start..end`
+    ///
+    /// Only array and hash nodes are recorded, and only if spans are
+    /// enabled. A node recorded twice keeps the later span, which is the
+    /// wider one when an enclosing rule passes its child's value through.
+    #[inline]
+    pub(crate) fn record_span(&mut self, node: &AstNode, start: usize, end: usize) {
+        let Some(spans) = &mut self.spans else {
+            return;
+        };
+        // Empty nodes own no pool slot: theirs belongs to the next node
+        match *node {
+            AstNode::Array { pool_index, length } if length > 0 => {
+                NodeSpans::record(&mut spans.arrays, pool_index, start, end)
+            }
+            AstNode::Hash { pool_index, length } if length > 0 => {
+                NodeSpans::record(&mut spans.hashes, pool_index, start, end)
+            }
+            _ => {}
+        }
+    }
+
+    /// Source span a node was parsed from
+    ///
+    /// Input references always know their span; array and hash nodes know
+    /// theirs if this arena was created with [`with_spans`](Self::with_spans).
+    /// Returns `None` for other nodes, and when the arena holds no input to
+    /// compute lines and columns from.
+    pub fn span_of(&self, node: &AstNode) -> Option<SourceSpan> {
+        let input = self.input.as_deref()?;
+        let (start, end) = match *node {
+            AstNode::InputRef { offset, length } => {
+                (offset as usize, offset as usize + length as usize)
+            }
+            AstNode::Array { pool_index, length } if length > 0 => {
+                NodeSpans::get(&self.spans.as_ref()?.arrays, pool_index)?
+            }
+            AstNode::Hash { pool_index, length } if length > 0 => {
+                NodeSpans::get(&self.spans.as_ref()?.hashes, pool_index)?
+            }
+            _ => return None,
+        };
+        Some(SourceSpan::from_offsets(input, start, end))
+    }
+
     /// Set the original input string (for InputRef offset lookup)
     #[inline]
     pub fn set_input(&mut self, input: String) {
@@ -135,6 +368,21 @@ impl AstArena {
         self.array_pool.len()
     }
 
+    /// Get the number of stored AST nodes
+    ///
+    /// Counts array items (including sequence and repetition tags) and
+    /// hash entries; interned strings and the root node aren't included.
+    #[inline]
+    pub fn node_count(&self) -> usize {
+        self.array_pool.len() + self.hash_pool.len()
+    }
+
+    /// Get the number of interned strings
+    #[inline]
+    pub fn string_count(&self) -> usize {
+        self.string_pool.len()
+    }
+
     /// Check if the arena is empty (no parse data in array/hash pools)
     ///
     /// Note: This does NOT check string_pool, as strings are preserved
@@ -146,8 +394,10 @@ impl AstArena {
 
     /// Reset the arena for reuse
     ///
-    /// This clears all pools but retains allocated memory.
-    /// Strings are preserved for interning efficiency.
+    /// This clears the array and hash pools but retains allocated memory.
+    /// Every interned string is kept, so hash keys and other strings shared
+    /// across documents are interned once and keep their pool indices; use
+    /// [`reset_with_options`](Self::reset_with_options) to drop them too.
     /// O(1) operation.
     #[inline]
     pub fn reset(&mut self) {
@@ -184,6 +434,9 @@ impl AstArena {
         // Always clear the pools that grow per-parse
         self.array_pool.clear();
         self.hash_pool.clear();
+        if let Some(spans) = &mut self.spans {
+            spans.truncate(0, 0);
+        }
 
         // Optionally clear string pools
         if clear_strings {
@@ -193,6 +446,25 @@ impl AstArena {
         }
     }
 
+    /// Current sizes of the array and hash pools, for a later [`truncate`](Self::truncate)
+    #[inline]
+    pub(crate) fn checkpoint(&self) -> (usize, usize) {
+        (self.array_pool.len(), self.hash_pool.len())
+    }
+
+    /// Drop every array and hash allocated since `checkpoint`
+    ///
+    /// Nodes pointing into the dropped region become invalid. Interned
+    /// strings are kept.
+    #[inline]
+    pub(crate) fn truncate(&mut self, checkpoint: (usize, usize)) {
+        self.array_pool.truncate(checkpoint.0);
+        self.hash_pool.truncate(checkpoint.1);
+        if let Some(spans) = &mut self.spans {
+            spans.truncate(checkpoint.0, checkpoint.1);
+        }
+    }
+
     /// Clear only the string pools
     ///
     /// Use this when you want to free string memory while keeping
@@ -277,10 +549,13 @@ impl AstArena {
         }
     }
 
-    /// Compute a hash for a string (using ahash-like algorithm)
+    /// Compute a hash for a string, with the custom hasher if one is set
     #[inline]
     fn hash_string(&self, s: &str) -> u64 {
-        use std::hash::{Hash, Hasher};
+        use std::hash::Hash;
+        if let Some(StringHasher(hash)) = &self.string_hasher {
+            return hash(s);
+        }
         let mut hasher = ahash::AHasher::default();
         s.hash(&mut hasher);
         hasher.finish()
@@ -297,9 +572,23 @@ impl AstArena {
         }
     }
 
+    /// Intern a slice of `input` and return a reference to the pooled copy
+    ///
+    /// Like [`intern_string`](Self::intern_string) for
+    /// `&input[offset..offset + length]`: the same text always yields the
+    /// same [`AstNode::StringRef`] pool index.
+    #[inline]
+    pub fn intern_input_ref(&mut self, offset: usize, length: usize, input: &str) -> AstNode {
+        self.intern_string(&input[offset..offset + length])
+    }
+
     /// Get a string from the pool by index
     #[inline]
     pub fn get_string(&self, pool_index: usize) -> &str {
+        #[cfg(debug_assertions)]
+        if let Err(message) = self.check_string(pool_index) {
+            panic!("{}", message);
+        }
         let entry = &self.string_pool[pool_index];
         let data = &self.string_data[entry.offset as usize..(entry.offset + entry.length) as usize];
         // SAFETY: All strings added to the pool via `add_string()` are valid UTF-8
@@ -311,6 +600,10 @@ impl AstArena {
     /// Get string data from pool entry
     #[inline]
     pub fn get_string_parts(&self, pool_index: usize) -> (&str, u32, u32, u32) {
+        #[cfg(debug_assertions)]
+        if let Err(message) = self.check_string(pool_index) {
+            panic!("{}", message);
+        }
         let entry = &self.string_pool[pool_index];
         let data = &self.string_data[entry.offset as usize..(entry.offset + entry.length) as usize];
         // SAFETY: All strings added to the pool via `add_string()` are valid UTF-8
@@ -356,6 +649,10 @@ impl AstArena {
     /// Get array items from pool
     #[inline]
     pub fn get_array(&self, start: usize, len: usize) -> Vec<AstNode> {
+        #[cfg(debug_assertions)]
+        if let Err(message) = check_range("Array", start, len, self.array_pool.len()) {
+            panic!("{}", message);
+        }
         let mut result = Vec::with_capacity(len);
         for i in 0..len {
             result.push(self.array_pool[start + i].value.clone());
@@ -384,7 +681,10 @@ impl AstArena {
                     length,
                     input_offset: 0,
                 });
-                (self.string_pool.len() - 1) as u32
+                let index = self.string_pool.len() - 1;
+                let hash = self.hash_string(key);
+                self.string_hash.insert(hash, index);
+                index as u32
             };
 
             self.hash_pool.push(HashPoolEntry {
@@ -399,6 +699,10 @@ impl AstArena {
     /// Get hash items from pool
     #[inline]
     pub fn get_hash_items(&self, pool_index: usize, len: usize) -> Vec<(String, AstNode)> {
+        #[cfg(debug_assertions)]
+        if let Err(message) = check_range("Hash", pool_index, len, self.hash_pool.len()) {
+            panic!("{}", message);
+        }
         let mut result = Vec::with_capacity(len);
         for i in 0..len {
             let entry = &self.hash_pool[pool_index + i];
@@ -408,6 +712,65 @@ impl AstArena {
         result
     }
 
+    /// Check that `node` and everything it references lie inside this arena
+    ///
+    /// Walks arrays, hashes and tagged nodes recursively, checking every
+    /// pool index and length against the pool sizes, and input references
+    /// against the input when one has been set. The error names the
+    /// offending node kind and index. Meant for tests and fuzzing of custom
+    /// atoms and plugins; the accessors perform the same checks in debug
+    /// builds.
+    pub fn validate_node(&self, node: &AstNode) -> Result<(), String> {
+        match node {
+            AstNode::Nil | AstNode::Bool(_) | AstNode::Int(_) | AstNode::Float(_) => Ok(()),
+            AstNode::StringRef { pool_index } => self.check_string(*pool_index as usize),
+            AstNode::InputRef { offset, length } => match &self.input {
+                Some(input) => {
+                    check_range("InputRef", *offset as usize, *length as usize, input.len())
+                }
+                None => Ok(()),
+            },
+            AstNode::Array { pool_index, length } => {
+                let (start, len) = (*pool_index as usize, *length as usize);
+                check_range("Array", start, len, self.array_pool.len())?;
+                self.array_pool[start..start + len]
+                    .iter()
+                    .try_for_each(|entry| self.validate_node(&entry.value))
+            }
+            AstNode::Hash { pool_index, length } => {
+                let (start, len) = (*pool_index as usize, *length as usize);
+                check_range("Hash", start, len, self.hash_pool.len())?;
+                self.hash_pool[start..start + len]
+                    .iter()
+                    .try_for_each(|entry| {
+                        self.check_string(entry.key_pool_index as usize)?;
+                        self.validate_node(&entry.value)
+                    })
+            }
+            AstNode::Tagged { tag, value } => {
+                self.check_string(*tag as usize)?;
+                self.validate_node(value)
+            }
+        }
+    }
+
+    /// Check a string pool index and the data range of its entry
+    fn check_string(&self, pool_index: usize) -> Result<(), String> {
+        let entry = self.string_pool.get(pool_index).ok_or_else(|| {
+            format!(
+                "corrupt StringRef node: pool_index {} out of bounds for string pool of {}",
+                pool_index,
+                self.string_pool.len()
+            )
+        })?;
+        check_range(
+            "StringRef",
+            entry.offset as usize,
+            entry.length as usize,
+            self.string_data.len(),
+        )
+    }
+
     /// Find an interned string in the pool
     ///
     /// Uses hash-based O(1) lookup for all pool sizes since we maintain
@@ -451,6 +814,9 @@ impl AstArena {
             + self.string_pool.capacity() * mem::size_of::<StringPoolEntry>()
             + self.array_pool.capacity() * mem::size_of::<ArrayPoolEntry>()
             + self.hash_pool.capacity() * mem::size_of::<HashPoolEntry>()
+            + self.spans.as_ref().map_or(0, |spans| {
+                (spans.arrays.capacity() + spans.hashes.capacity()) * mem::size_of::<(u32, u32)>()
+            })
     }
 
     /// Allocate an array and return the complete AstNode
@@ -475,6 +841,125 @@ impl AstArena {
     }
 }
 
+/// Check that `start..start + len` lies within a pool of `pool_len` entries
+fn check_range(kind: &str, start: usize, len: usize, pool_len: usize) -> Result<(), String> {
+    match start.checked_add(len) {
+        Some(end) if end <= pool_len => Ok(()),
+        _ => Err(format!(
+            "corrupt {} node: index {} with length {} out of bounds for pool of {}",
+            kind, start, len, pool_len
+        )),
+    }
+}
+
+/// A thread-safe pool of reusable arenas
+///
+/// Worker threads check out an arena per request and it is returned to the
+/// pool automatically when the [`PooledArena`] guard is dropped. Returned
+/// arenas are [`reset`](AstArena::reset) so each checkout starts empty while
+/// keeping its allocated memory (and interned strings).
+///
+/// # Example
+///
+/// ```
+/// use parsanol::portable::arena::ArenaPool;
+///
+/// let pool = ArenaPool::new(4);
+/// {
+///     let mut arena = pool.checkout();
+///     arena.intern_string("key");
+/// } // returned to the pool here
+/// assert_eq!(pool.available(), 1);
+/// ```
+#[derive(Debug)]
+pub struct ArenaPool {
+    /// Idle arenas ready for checkout
+    arenas: Mutex<Vec<AstArena>>,
+    /// Maximum number of idle arenas retained
+    capacity: usize,
+}
+
+impl ArenaPool {
+    /// Create a pool that retains at most `capacity` idle arenas
+    ///
+    /// Checkouts beyond the number of idle arenas allocate a new one, and
+    /// arenas returned to a full pool are dropped.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            arenas: Mutex::new(Vec::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Check out an arena, reusing an idle one if available
+    pub fn checkout(&self) -> PooledArena<'_> {
+        let arena = self
+            .arenas
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop()
+            .unwrap_or_default();
+        PooledArena {
+            arena: Some(arena),
+            pool: self,
+        }
+    }
+
+    /// Number of idle arenas currently in the pool
+    pub fn available(&self) -> usize {
+        self.arenas.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Maximum number of idle arenas retained
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Reset an arena and return it to the pool
+    fn checkin(&self, mut arena: AstArena) {
+        arena.reset();
+        let mut arenas = self.arenas.lock().unwrap_or_else(|e| e.into_inner());
+        if arenas.len() < self.capacity {
+            arenas.push(arena);
+        }
+    }
+}
+
+/// An arena checked out from an [`ArenaPool`]
+///
+/// Dereferences to [`AstArena`]; the arena is returned to its pool on drop.
+#[derive(Debug)]
+pub struct PooledArena<'a> {
+    /// Always `Some` until dropped
+    arena: Option<AstArena>,
+    pool: &'a ArenaPool,
+}
+
+impl Deref for PooledArena<'_> {
+    type Target = AstArena;
+
+    #[inline]
+    fn deref(&self) -> &AstArena {
+        self.arena.as_ref().expect("pooled arena used after return")
+    }
+}
+
+impl DerefMut for PooledArena<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut AstArena {
+        self.arena.as_mut().expect("pooled arena used after return")
+    }
+}
+
+impl Drop for PooledArena<'_> {
+    fn drop(&mut self) {
+        if let Some(arena) = self.arena.take() {
+            self.pool.checkin(arena);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -510,6 +995,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_with_hasher_interns_like_default() {
+        let words = ["let", "x", "=", "let", "y", "=", "x"];
+        let mut default = AstArena::new();
+        let mut sip = AstArena::with_hasher(std::collections::hash_map::RandomState::new());
+
+        for word in words {
+            assert_eq!(default.intern_string(word), sip.intern_string(word));
+        }
+        assert_eq!(sip.string_count(), 4);
+
+        let (pool_index, length) = sip.store_hash(&[("x", AstNode::Nil)]);
+        let items = sip.get_hash_items(pool_index as usize, length as usize);
+        assert_eq!(items[0].0, "x");
+        assert_eq!(sip.string_count(), 4);
+    }
+
     #[test]
     fn test_input_ref() {
         let arena = AstArena::new();
@@ -600,6 +1102,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reset_keeps_strings_across_documents() {
+        use crate::portable::parser::PortableParser;
+        use crate::portable::parser_dsl::{re, str, GrammarBuilder, ParsletExt};
+
+        let grammar = GrammarBuilder::new()
+            .rule(
+                "pair",
+                re("[a-z]")
+                    .many1()
+                    .label("key")
+                    .then(str("="))
+                    .then(re("[0-9]").many1().label("value")),
+            )
+            .build();
+
+        let mut arena = AstArena::new();
+        PortableParser::new(&grammar, "x=1", &mut arena)
+            .parse()
+            .unwrap();
+        let interned = arena.string_count();
+        assert!(interned > 0);
+
+        arena.reset();
+        assert!(arena.is_empty());
+        assert_eq!(arena.string_count(), interned);
+
+        // Same keys again: nothing new to intern
+        PortableParser::new(&grammar, "yz=42", &mut arena)
+            .parse()
+            .unwrap();
+        assert_eq!(arena.string_count(), interned);
+    }
+
+    #[test]
+    fn test_store_hash_interns_keys_once() {
+        let mut arena = AstArena::new();
+        arena.store_hash(&[("name", AstNode::Nil)]);
+        arena.store_hash(&[("name", AstNode::Int(1))]);
+        assert_eq!(arena.string_count(), 1);
+    }
+
     #[test]
     fn test_reset_with_options_clear_strings() {
         let mut arena = AstArena::new();
@@ -687,4 +1231,107 @@ mod tests {
         let usage = arena.memory_usage();
         assert!(usage > 0);
     }
+
+    #[test]
+    fn test_arena_pool_reuse_and_reset() {
+        let pool = ArenaPool::new(2);
+        assert_eq!(pool.available(), 0);
+
+        {
+            let mut arena = pool.checkout();
+            let items = vec![arena.intern_string("a")];
+            arena.store_array(&items);
+            assert!(!arena.is_empty());
+        }
+        assert_eq!(pool.available(), 1);
+
+        // The returned arena is reused and starts empty
+        let arena = pool.checkout();
+        assert_eq!(pool.available(), 0);
+        assert!(arena.is_empty());
+    }
+
+    #[test]
+    fn test_arena_pool_capacity() {
+        let pool = ArenaPool::new(1);
+        let a = pool.checkout();
+        let b = pool.checkout();
+        drop(a);
+        drop(b);
+        // Only one idle arena is retained
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[test]
+    fn test_arena_pool_concurrent() {
+        let pool = ArenaPool::new(4);
+
+        std::thread::scope(|scope| {
+            for t in 0..4 {
+                let pool = &pool;
+                scope.spawn(move || {
+                    for i in 0..50 {
+                        let mut arena = pool.checkout();
+                        assert!(arena.is_empty(), "checked-out arena was not reset");
+                        let key = format!("t{}-{}", t, i);
+                        let node = arena.intern_string(&key);
+                        arena.store_hash(&[(key.as_str(), node)]);
+                        assert!(!arena.is_empty());
+                    }
+                });
+            }
+        });
+
+        assert!(pool.available() >= 1 && pool.available() <= pool.capacity());
+        for _ in 0..pool.available() {
+            assert!(pool.checkout().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_intern_input_ref_dedups() {
+        let input = "if x then if y";
+        let mut arena = AstArena::with_interned_inputs();
+        assert!(arena.interns_inputs());
+        assert!(!AstArena::new().interns_inputs());
+
+        let first = arena.intern_input_ref(0, 2, input);
+        let second = arena.intern_input_ref(10, 2, input);
+        let other = arena.intern_input_ref(3, 1, input);
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert_eq!(arena.string_count(), 2);
+    }
+
+    #[test]
+    fn test_validate_node_catches_corrupt_array() {
+        let mut arena = AstArena::new();
+        let word = arena.intern_string("ok");
+        let array = arena.alloc_array(vec![word.clone(), AstNode::Int(1)]);
+        let hash = arena.alloc_hash(vec![("items".to_string(), array.clone())]);
+        assert_eq!(arena.validate_node(&hash), Ok(()));
+
+        let corrupt = AstNode::Array {
+            pool_index: 1,
+            length: 5,
+        };
+        let error = arena.validate_node(&corrupt).unwrap_err();
+        assert!(error.contains("Array"), "{}", error);
+        assert!(error.contains("index 1"), "{}", error);
+
+        // Corruption nested inside a valid node is found too
+        let nested = arena.alloc_array(vec![corrupt]);
+        assert!(arena.validate_node(&nested).is_err());
+        let dangling = AstNode::StringRef { pool_index: 99 };
+        assert!(arena.validate_node(&dangling).unwrap_err().contains("99"));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "corrupt Array node: index 3 with length 2")]
+    fn test_get_array_panics_on_corrupt_range_in_debug() {
+        let mut arena = AstArena::new();
+        arena.alloc_array(vec![AstNode::Nil, AstNode::Nil, AstNode::Nil]);
+        arena.get_array(3, 2);
+    }
 }
@@ -4,6 +4,7 @@
 //! without any Ruby or external dependencies. This enables the parser
 //! to be compiled to WASM for Opal.
 
+use super::arena::AstArena;
 use std::collections::HashMap;
 use std::fmt;
 
@@ -158,6 +159,97 @@ impl AstNode {
     pub fn is_nil(&self) -> bool {
         matches!(self, AstNode::Nil)
     }
+
+    /// Collect the named captures anywhere under this node into a flat map
+    ///
+    /// Each name maps to the text its value matched: arrays are joined
+    /// without their `:sequence`/`:repetition` tags, and a nested capture
+    /// contributes its text both to its own name and to the enclosing one.
+    /// A name captured more than once keeps its last match, in input order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::{parser_dsl::*, AstArena, PortableParser};
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule("pair", re("[a-z]").many1().label("key").then(str("=")).then(re("[0-9]").many1().label("value")))
+    ///     .build();
+    /// let input = "x=42";
+    ///
+    /// let mut arena = AstArena::new();
+    /// let ast = PortableParser::new(&grammar, input, &mut arena).parse().unwrap();
+    /// let captures = ast.named_captures(&arena, input);
+    /// assert_eq!(captures["key"], "x");
+    /// assert_eq!(captures["value"], "42");
+    /// ```
+    pub fn named_captures(&self, arena: &AstArena, input: &str) -> HashMap<String, String> {
+        let mut captures = HashMap::new();
+        self.collect_captures(arena, input, &mut captures);
+        captures
+    }
+
+    fn collect_captures(
+        &self,
+        arena: &AstArena,
+        input: &str,
+        captures: &mut HashMap<String, String>,
+    ) {
+        match self {
+            AstNode::Array { pool_index, length } => {
+                for item in arena.get_array(*pool_index as usize, *length as usize) {
+                    item.collect_captures(arena, input, captures);
+                }
+            }
+            AstNode::Hash { pool_index, length } => {
+                for (name, value) in arena.get_hash_items(*pool_index as usize, *length as usize) {
+                    let mut text = String::new();
+                    value.push_text(arena, input, &mut text);
+                    captures.insert(name, text);
+                    value.collect_captures(arena, input, captures);
+                }
+            }
+            AstNode::Tagged { value, .. } => value.collect_captures(arena, input, captures),
+            _ => {}
+        }
+    }
+
+    /// Append the text this node matched to `text`
+    fn push_text(&self, arena: &AstArena, input: &str, text: &mut String) {
+        match self {
+            AstNode::Nil => {}
+            AstNode::Bool(b) => text.push_str(&b.to_string()),
+            AstNode::Int(n) => text.push_str(&n.to_string()),
+            AstNode::Float(f) => text.push_str(&f.to_string()),
+            AstNode::StringRef { pool_index } => {
+                text.push_str(arena.get_string(*pool_index as usize))
+            }
+            AstNode::InputRef { offset, length } => {
+                let start = *offset as usize;
+                text.push_str(input.get(start..start + *length as usize).unwrap_or(""));
+            }
+            AstNode::Array { pool_index, length } => {
+                let items = arena.get_array(*pool_index as usize, *length as usize);
+                let is_tag = |item: &AstNode| match item {
+                    AstNode::StringRef { pool_index } => matches!(
+                        arena.get_string(*pool_index as usize),
+                        ":sequence" | ":repetition"
+                    ),
+                    _ => false,
+                };
+                let skip = usize::from(items.first().is_some_and(is_tag));
+                for item in &items[skip..] {
+                    item.push_text(arena, input, text);
+                }
+            }
+            AstNode::Hash { pool_index, length } => {
+                for (_, value) in arena.get_hash_items(*pool_index as usize, *length as usize) {
+                    value.push_text(arena, input, text);
+                }
+            }
+            AstNode::Tagged { value, .. } => value.push_text(arena, input, text),
+        }
+    }
 }
 
 /// Result of a parse operation
@@ -221,6 +313,18 @@ pub enum ParseError {
         position: usize,
     },
 
+    /// [`Failed`](ParseError::Failed) enriched with what could have matched
+    ///
+    /// Only produced when [`ParserConfig::collect_expected`] is set.
+    ///
+    /// [`ParserConfig::collect_expected`]: crate::portable::parser::ParserConfig::collect_expected
+    FailedExpecting {
+        /// The furthest byte offset where an atom failed
+        position: usize,
+        /// Literals and patterns that were tried at `position`
+        expected: Vec<String>,
+    },
+
     /// Parse didn't consume entire input
     Incomplete {
         /// Expected number of bytes to consume
@@ -273,11 +377,54 @@ pub enum ParseError {
         max_bytes: usize,
     },
 
+    /// AST node limit exceeded during parsing
+    NodeLimitExceeded {
+        /// Nodes allocated in the arena
+        count: usize,
+        /// Node limit
+        max: usize,
+    },
+
     /// Error from streaming builder
     BuilderError {
         /// Error message from builder
         message: String,
     },
+
+    /// Start offset past the end of input or inside a UTF-8 character
+    InvalidOffset {
+        /// The rejected byte offset
+        offset: usize,
+    },
+
+    /// Input bytes are not valid UTF-8
+    ///
+    /// Produced by [`PortableParser::from_bytes`].
+    ///
+    /// [`PortableParser::from_bytes`]: crate::portable::PortableParser::from_bytes
+    InvalidUtf8 {
+        /// Byte offset of the first byte of the invalid sequence
+        offset: usize,
+    },
+
+    /// Text matched by `Atom::ParseInt`/`Atom::ParseFloat` is not a valid
+    /// number, or doesn't fit the target type
+    InvalidNumber {
+        /// Byte offset where the number starts
+        start: usize,
+        /// Byte offset just past the number
+        end: usize,
+    },
+
+    /// The parse succeeded but converting the AST to a typed value failed
+    ///
+    /// Produced by [`PortableParser::parse_into`].
+    ///
+    /// [`PortableParser::parse_into`]: crate::portable::PortableParser::parse_into
+    Transform {
+        /// The transform error's message
+        message: String,
+    },
 }
 
 impl ParseError {
@@ -297,6 +444,16 @@ impl ParseError {
                     sp.line, sp.column, position
                 )
             }
+            ParseError::FailedExpecting { position, expected } => {
+                let sp = offset_to_position(input, *position);
+                format!(
+                    "Parse failed at line {}, column {} (byte offset {}): expected {}",
+                    sp.line,
+                    sp.column,
+                    position,
+                    format_expected(expected)
+                )
+            }
             ParseError::Incomplete { expected, actual } => {
                 format!(
                     "Parse incomplete: expected {} bytes, parsed {}",
@@ -342,13 +499,48 @@ impl ParseError {
                     used_bytes, max_bytes
                 )
             }
+            ParseError::NodeLimitExceeded { count, max } => {
+                format!(
+                    "Node limit exceeded: {} nodes exceeds limit of {}",
+                    count, max
+                )
+            }
             ParseError::BuilderError { message } => {
                 format!("Builder error: {}", message)
             }
+            ParseError::InvalidOffset { offset } => {
+                format!("Invalid start offset: {}", offset)
+            }
+            ParseError::InvalidUtf8 { offset } => {
+                format!("Invalid UTF-8 at byte offset {}", offset)
+            }
+            ParseError::InvalidNumber { start, end } => {
+                let sp = offset_to_position(input, *start);
+                format!(
+                    "Invalid number {:?} at line {}, column {} (byte offset {})",
+                    input.get(*start..*end).unwrap_or(""),
+                    sp.line,
+                    sp.column,
+                    start
+                )
+            }
+            ParseError::Transform { message } => {
+                format!("Transform error: {}", message)
+            }
         }
     }
 }
 
+/// Join an expected set as `"a", "b" or "c"`
+pub(crate) fn format_expected(expected: &[String]) -> String {
+    let quoted: Vec<String> = expected.iter().map(|e| format!("{:?}", e)).collect();
+    match quoted.split_last() {
+        None => "nothing".to_string(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+    }
+}
+
 /// Convert byte offset to line/column position
 /// Uses the canonical implementation from source_location module
 #[inline]
@@ -362,6 +554,14 @@ impl fmt::Display for ParseError {
             ParseError::Failed { position } => {
                 write!(f, "Parse failed at position {}", position)
             }
+            ParseError::FailedExpecting { position, expected } => {
+                write!(
+                    f,
+                    "Parse failed at position {}: expected {}",
+                    position,
+                    format_expected(expected)
+                )
+            }
             ParseError::Incomplete { expected, actual } => {
                 write!(
                     f,
@@ -412,9 +612,28 @@ impl fmt::Display for ParseError {
                     used_bytes, max_bytes
                 )
             }
+            ParseError::NodeLimitExceeded { count, max } => {
+                write!(
+                    f,
+                    "Node limit exceeded: {} nodes exceeds limit of {}",
+                    count, max
+                )
+            }
             ParseError::BuilderError { message } => {
                 write!(f, "Builder error: {}", message)
             }
+            ParseError::InvalidOffset { offset } => {
+                write!(f, "Invalid start offset: {}", offset)
+            }
+            ParseError::InvalidUtf8 { offset } => {
+                write!(f, "Invalid UTF-8 at byte offset {}", offset)
+            }
+            ParseError::InvalidNumber { start, end } => {
+                write!(f, "Invalid number at bytes {}..{}", start, end)
+            }
+            ParseError::Transform { message } => {
+                write!(f, "Transform error: {}", message)
+            }
         }
     }
 }
@@ -614,6 +833,22 @@ mod tests {
         assert!(err.to_string().contains("Memory limit"));
     }
 
+    #[test]
+    fn test_parse_error_node_limit_exceeded() {
+        let err = ParseError::NodeLimitExceeded {
+            count: 1001,
+            max: 1000,
+        };
+        assert!(err.to_string().contains("1001"));
+        assert!(err.to_string().contains("Node limit"));
+    }
+
+    #[test]
+    fn test_parse_error_invalid_utf8() {
+        let err = ParseError::InvalidUtf8 { offset: 3 };
+        assert_eq!(err.to_string(), "Invalid UTF-8 at byte offset 3");
+    }
+
     #[test]
     fn test_parse_error_at_position() {
         let err = ParseError::at_position(42);
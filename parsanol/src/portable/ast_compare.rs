@@ -0,0 +1,319 @@
+//! Structural equality and hashing for AST nodes
+//!
+//! An [`AstNode`] only makes sense together with the arena holding its
+//! children and the input its [`AstNode::InputRef`]s point into, so the
+//! derived `PartialEq` can't compare trees from different parses.
+//! [`ast_equal`] and [`ast_hash`] resolve both and compare by content:
+//!
+//! - strings compare by text, whether they are interned (`StringRef`) or
+//!   slices of the input (`InputRef`);
+//! - hashes compare regardless of key order;
+//! - floats compare by bit pattern, so `NaN` equals itself and `-0.0`
+//!   differs from `0.0`.
+//!
+//! Equal trees always hash the same. The hash is FNV-1a based and does not
+//! depend on the process or platform, so it can key persistent caches.
+//!
+//! # Example
+//!
+//! ```
+//! use parsanol::portable::ast_compare::{ast_equal, ast_hash};
+//! use parsanol::portable::AstArena;
+//!
+//! let arena_a = AstArena::new();
+//! let a = arena_a.input_ref(0, 5);
+//!
+//! let mut arena_b = AstArena::new();
+//! let b = arena_b.intern_string("hello");
+//!
+//! assert!(ast_equal(&a, &arena_a, "hello", &b, &arena_b, ""));
+//! assert_eq!(ast_hash(&a, &arena_a, "hello"), ast_hash(&b, &arena_b, ""));
+//! ```
+
+use super::arena::AstArena;
+use super::ast::AstNode;
+
+/// Compare two trees by content, resolving arena and input references
+///
+/// `arena_a`/`input_a` belong to `a`, `arena_b`/`input_b` to `b`; they may
+/// be the same arena and input.
+pub fn ast_equal(
+    a: &AstNode,
+    arena_a: &AstArena,
+    input_a: &str,
+    b: &AstNode,
+    arena_b: &AstArena,
+    input_b: &str,
+) -> bool {
+    let side_a = Side {
+        arena: arena_a,
+        input: input_a,
+    };
+    let side_b = Side {
+        arena: arena_b,
+        input: input_b,
+    };
+    nodes_equal(a, side_a, b, side_b)
+}
+
+/// Hash a tree by content, consistently with [`ast_equal`]
+pub fn ast_hash(node: &AstNode, arena: &AstArena, input: &str) -> u64 {
+    let mut hasher = Fnv::new();
+    hash_node(node, Side { arena, input }, &mut hasher);
+    hasher.finish()
+}
+
+/// The arena and input a node is resolved against
+#[derive(Clone, Copy)]
+struct Side<'a> {
+    arena: &'a AstArena,
+    input: &'a str,
+}
+
+impl<'a> Side<'a> {
+    fn text(&self, node: &AstNode) -> Option<&'a str> {
+        match *node {
+            AstNode::StringRef { pool_index } => Some(self.arena.get_string(pool_index as usize)),
+            AstNode::InputRef { offset, length } => self
+                .input
+                .get(offset as usize..offset as usize + length as usize),
+            _ => None,
+        }
+    }
+
+    /// Hash pairs sorted by key; the sort is stable, so duplicate keys
+    /// keep their order
+    fn sorted_pairs(&self, pool_index: u32, length: u32) -> Vec<(String, AstNode)> {
+        let mut pairs = self
+            .arena
+            .get_hash_items(pool_index as usize, length as usize);
+        pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        pairs
+    }
+}
+
+fn nodes_equal(a: &AstNode, side_a: Side<'_>, b: &AstNode, side_b: Side<'_>) -> bool {
+    if let (Some(text_a), Some(text_b)) = (side_a.text(a), side_b.text(b)) {
+        return text_a == text_b;
+    }
+
+    match (a, b) {
+        (AstNode::Nil, AstNode::Nil) => true,
+        (AstNode::Bool(x), AstNode::Bool(y)) => x == y,
+        (AstNode::Int(x), AstNode::Int(y)) => x == y,
+        (AstNode::Float(x), AstNode::Float(y)) => x.to_bits() == y.to_bits(),
+        (
+            AstNode::Array {
+                pool_index: pool_a,
+                length: len_a,
+            },
+            AstNode::Array {
+                pool_index: pool_b,
+                length: len_b,
+            },
+        ) => {
+            len_a == len_b
+                && side_a
+                    .arena
+                    .get_array(*pool_a as usize, *len_a as usize)
+                    .iter()
+                    .zip(side_b.arena.get_array(*pool_b as usize, *len_b as usize))
+                    .all(|(x, y)| nodes_equal(x, side_a, &y, side_b))
+        }
+        (
+            AstNode::Hash {
+                pool_index: pool_a,
+                length: len_a,
+            },
+            AstNode::Hash {
+                pool_index: pool_b,
+                length: len_b,
+            },
+        ) => {
+            len_a == len_b
+                && side_a
+                    .sorted_pairs(*pool_a, *len_a)
+                    .iter()
+                    .zip(side_b.sorted_pairs(*pool_b, *len_b))
+                    .all(|((key_a, x), (key_b, y))| {
+                        *key_a == key_b && nodes_equal(x, side_a, &y, side_b)
+                    })
+        }
+        (
+            AstNode::Tagged {
+                tag: tag_a,
+                value: value_a,
+            },
+            AstNode::Tagged {
+                tag: tag_b,
+                value: value_b,
+            },
+        ) => tag_a == tag_b && nodes_equal(value_a, side_a, value_b, side_b),
+        _ => false,
+    }
+}
+
+fn hash_node(node: &AstNode, side: Side<'_>, hasher: &mut Fnv) {
+    if let Some(text) = side.text(node) {
+        hasher.write_u8(b's');
+        hasher.write_str(text);
+        return;
+    }
+
+    match node {
+        AstNode::Nil => hasher.write_u8(b'n'),
+        AstNode::Bool(value) => {
+            hasher.write_u8(b'b');
+            hasher.write_u8(*value as u8);
+        }
+        AstNode::Int(value) => {
+            hasher.write_u8(b'i');
+            hasher.write_u64(*value as u64);
+        }
+        AstNode::Float(value) => {
+            hasher.write_u8(b'f');
+            hasher.write_u64(value.to_bits());
+        }
+        AstNode::Array { pool_index, length } => {
+            hasher.write_u8(b'a');
+            hasher.write_u64(u64::from(*length));
+            for item in side.arena.get_array(*pool_index as usize, *length as usize) {
+                hash_node(&item, side, hasher);
+            }
+        }
+        AstNode::Hash { pool_index, length } => {
+            hasher.write_u8(b'h');
+            hasher.write_u64(u64::from(*length));
+            for (key, value) in side.sorted_pairs(*pool_index, *length) {
+                hasher.write_str(&key);
+                hash_node(&value, side, hasher);
+            }
+        }
+        AstNode::Tagged { tag, value } => {
+            hasher.write_u8(b't');
+            hasher.write_u64(u64::from(*tag));
+            hash_node(value, side, hasher);
+        }
+        // A string reference outside its input
+        AstNode::StringRef { .. } | AstNode::InputRef { .. } => hasher.write_u8(b'?'),
+    }
+}
+
+/// 64-bit FNV-1a, chosen for stability rather than speed
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write_u8(&mut self, byte: u8) {
+        self.0 ^= u64::from(byte);
+        self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.write_u8(byte);
+        }
+    }
+
+    /// Length-prefixed, so adjacent strings can't run together
+    fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        for &byte in s.as_bytes() {
+            self.write_u8(byte);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_and_interned_strings_compare_by_content() {
+        let input = "key value";
+        let arena_a = AstArena::new();
+        let from_input = arena_a.input_ref(4, 5);
+        let mut arena_b = AstArena::new();
+        let interned = arena_b.intern_string("value");
+        let other = arena_b.intern_string("other");
+
+        assert!(ast_equal(
+            &from_input,
+            &arena_a,
+            input,
+            &interned,
+            &arena_b,
+            ""
+        ));
+        assert_eq!(
+            ast_hash(&from_input, &arena_a, input),
+            ast_hash(&interned, &arena_b, "")
+        );
+        assert!(!ast_equal(
+            &from_input,
+            &arena_a,
+            input,
+            &other,
+            &arena_b,
+            ""
+        ));
+        assert!(!ast_equal(
+            &AstNode::Int(1),
+            &arena_a,
+            "",
+            &AstNode::Float(1.0),
+            &arena_a,
+            ""
+        ));
+    }
+
+    #[test]
+    fn test_hashes_ignore_key_order_across_arenas() {
+        let input = "1,2";
+        let mut arena_a = AstArena::new();
+        let items = vec![arena_a.input_ref(0, 1), arena_a.input_ref(2, 1)];
+        let list = arena_a.alloc_array(items);
+        let a = arena_a.alloc_hash(vec![
+            ("items".to_string(), list),
+            ("count".to_string(), AstNode::Int(2)),
+        ]);
+
+        let mut arena_b = AstArena::new();
+        let items = vec![arena_b.intern_string("1"), arena_b.intern_string("2")];
+        let list = arena_b.alloc_array(items);
+        let b = arena_b.alloc_hash(vec![
+            ("count".to_string(), AstNode::Int(2)),
+            ("items".to_string(), list),
+        ]);
+
+        assert!(ast_equal(&a, &arena_a, input, &b, &arena_b, ""));
+        assert_eq!(ast_hash(&a, &arena_a, input), ast_hash(&b, &arena_b, ""));
+
+        // Arrays are ordered
+        let items = vec![arena_b.intern_string("2"), arena_b.intern_string("1")];
+        let reversed = arena_b.alloc_array(items);
+        let c = arena_b.alloc_hash(vec![
+            ("count".to_string(), AstNode::Int(2)),
+            ("items".to_string(), reversed),
+        ]);
+        assert!(!ast_equal(&a, &arena_a, input, &c, &arena_b, ""));
+        assert_ne!(ast_hash(&a, &arena_a, input), ast_hash(&c, &arena_b, ""));
+    }
+
+    #[test]
+    fn test_hash_is_stable() {
+        let arena = AstArena::new();
+        assert_eq!(ast_hash(&AstNode::Nil, &arena, ""), 0xaf63_e34c_8601_f871);
+        assert_eq!(
+            ast_hash(&AstNode::Float(f64::NAN), &arena, ""),
+            ast_hash(&AstNode::Float(f64::NAN), &arena, "")
+        );
+    }
+}
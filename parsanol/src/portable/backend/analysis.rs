@@ -27,6 +27,44 @@ pub fn has_nested_repetition(grammar: &Grammar) -> bool {
     false
 }
 
+/// Check if grammar uses atoms only the packrat backend can execute
+///
+/// Back-references, optional defaults, peeks, infix expressions,
+/// indentation atoms, longest-match alternatives, lazy repetitions and
+/// number conversions have no bytecode equivalent, and neither does a grammar-level
+/// [`Grammar::skip`] atom, so grammars using them must run on Packrat.
+pub fn has_packrat_only_atoms(grammar: &Grammar) -> bool {
+    grammar.skip.is_some()
+        || grammar.atoms.iter().any(|atom| {
+            matches!(
+                atom,
+                Atom::BackRef { .. }
+                    | Atom::OptionalDefault { .. }
+                    | Atom::Peek { .. }
+                    | Atom::Infix { .. }
+                    | Atom::Indent
+                    | Atom::Samedent
+                    | Atom::Dedent
+                    | Atom::LongestAlternative { .. }
+                    | Atom::ParseInt { .. }
+                    | Atom::ParseFloat { .. }
+                    | Atom::Const { .. }
+                    | Atom::Until { .. }
+                    | Atom::ByteRange { .. }
+                    | Atom::RepetitionLazy { .. }
+                    | Atom::Text { .. }
+            )
+        })
+}
+
+/// Check if grammar has left-recursive rules
+///
+/// Only the packrat backend grows seeds for left recursion; the bytecode VM
+/// would recurse until it hits the recursion limit.
+pub fn has_left_recursion(grammar: &Grammar) -> bool {
+    grammar.left_recursion().is_some()
+}
+
 /// Grammar analysis result for backend selection
 #[derive(Debug, Clone)]
 pub struct GrammarAnalysis {
@@ -38,6 +76,14 @@ pub struct GrammarAnalysis {
     /// Nested repetitions cause exponential backtracking O(2^n) in bytecode VM,
     /// while Packrat guarantees O(n).
     pub has_nested_repetition: bool,
+
+    /// Whether the grammar uses atoms the bytecode VM cannot execute
+    /// (e.g., back-references). Such grammars always select Packrat.
+    pub has_packrat_only_atoms: bool,
+
+    /// Whether the grammar has left-recursive rules, which only Packrat
+    /// supports
+    pub has_left_recursion: bool,
 }
 
 impl GrammarAnalysis {
@@ -46,13 +92,16 @@ impl GrammarAnalysis {
         Self {
             atom_count: grammar.atoms.len(),
             has_nested_repetition: has_nested_repetition(grammar),
+            has_packrat_only_atoms: has_packrat_only_atoms(grammar),
+            has_left_recursion: has_left_recursion(grammar),
         }
     }
 
     /// Recommend a backend based on analysis
-    /// Hard rule: nested repetitions → Packrat, otherwise → Bytecode
+    /// Hard rule: nested repetitions, packrat-only atoms or left recursion → Packrat,
+    /// otherwise → Bytecode
     pub fn recommended_backend(&self) -> Backend {
-        if self.has_nested_repetition {
+        if self.has_nested_repetition || self.has_packrat_only_atoms || self.has_left_recursion {
             Backend::Packrat
         } else {
             Backend::Bytecode
@@ -112,4 +161,39 @@ mod tests {
         assert!(analysis.has_nested_repetition);
         assert_eq!(analysis.recommended_backend(), Backend::Packrat);
     }
+
+    #[test]
+    fn test_backref_requires_packrat() {
+        let mut grammar = Grammar::new();
+        grammar.add_atom(Atom::BackRef {
+            name: "tag".to_string(),
+        });
+        grammar.root = 0;
+
+        assert!(has_packrat_only_atoms(&grammar));
+        let analysis = GrammarAnalysis::analyze(&grammar);
+        assert_eq!(analysis.recommended_backend(), Backend::Packrat);
+        assert_eq!(Backend::default_for_grammar(&grammar), Backend::Packrat);
+    }
+
+    #[test]
+    fn test_left_recursion_requires_packrat() {
+        let mut grammar = Grammar::new();
+        let num = grammar.add_atom(Atom::Str {
+            pattern: "1".to_string(),
+        });
+        let expr_ref = grammar.add_atom(Atom::Entity { atom: 3 });
+        let seq = grammar.add_atom(Atom::Sequence {
+            atoms: vec![expr_ref, num],
+        });
+        grammar.add_atom(Atom::Alternative {
+            atoms: vec![seq, num],
+        });
+        grammar.root = 3;
+
+        assert!(has_left_recursion(&grammar));
+        let analysis = GrammarAnalysis::analyze(&grammar);
+        assert_eq!(analysis.recommended_backend(), Backend::Packrat);
+        assert_eq!(Backend::default_for_grammar(&grammar), Backend::Packrat);
+    }
 }
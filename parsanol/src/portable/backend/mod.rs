@@ -31,7 +31,9 @@ mod bytecode;
 mod packrat;
 mod traits;
 
-pub use analysis::{has_nested_repetition, GrammarAnalysis};
+pub use analysis::{
+    has_left_recursion, has_nested_repetition, has_packrat_only_atoms, GrammarAnalysis,
+};
 pub use bytecode::BytecodeBackend;
 pub use packrat::PackratBackend;
 pub use traits::{BackendCharacteristics, BackendResult, DynBackend, ParsingBackend};
@@ -91,9 +93,13 @@ impl Backend {
 
     /// Get default backend for a grammar
     ///
-    /// Uses the hard rule: nested repetitions → Packrat, otherwise → Bytecode
+    /// Uses the hard rule: nested repetitions, packrat-only atoms or left
+    /// recursion → Packrat, otherwise → Bytecode
     pub fn default_for_grammar(grammar: &Grammar) -> Self {
-        if has_nested_repetition(grammar) {
+        if has_nested_repetition(grammar)
+            || has_packrat_only_atoms(grammar)
+            || has_left_recursion(grammar)
+        {
             Backend::Packrat
         } else {
             Backend::Bytecode
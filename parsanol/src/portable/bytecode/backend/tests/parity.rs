@@ -4,3 +4,90 @@
 
 // Note: Most parity tests are in basic.rs, complex.rs, or captures.rs
 // This file can contain additional parity tests that don't fit elsewhere.
+
+use super::*;
+use crate::portable::bytecode::backend::Parser;
+
+/// `"a" eof | "a" "b"`
+fn eof_in_choice_grammar() -> Grammar {
+    let mut grammar = Grammar::new();
+    let a = grammar.add_atom(Atom::Str {
+        pattern: "a".to_string(),
+    });
+    let b = grammar.add_atom(Atom::Str {
+        pattern: "b".to_string(),
+    });
+    let eof = grammar.add_atom(Atom::Eof);
+    let a_eof = grammar.add_atom(Atom::Sequence {
+        atoms: vec![a, eof],
+    });
+    let a_b = grammar.add_atom(Atom::Sequence { atoms: vec![a, b] });
+    grammar.root = grammar.add_atom(Atom::Alternative {
+        atoms: vec![a_eof, a_b],
+    });
+    grammar
+}
+
+#[test]
+fn test_backend_parity_eof() {
+    let grammar = eof_in_choice_grammar();
+
+    let mut packrat_parser = Parser::packrat(grammar.clone());
+    let mut bytecode_parser = Parser::bytecode(grammar);
+
+    for (input, end_pos) in [("a", 1), ("ab", 2)] {
+        let packrat_result = packrat_parser.parse(input).unwrap();
+        let bytecode_result = bytecode_parser.parse(input).unwrap();
+        assert_eq!(packrat_result.end_pos, bytecode_result.end_pos);
+        assert_eq!(packrat_result.end_pos, end_pos);
+    }
+}
+
+#[test]
+fn test_backend_parity_str_ci_ascii_folding() {
+    let mut grammar = Grammar::new();
+    grammar.root = grammar.add_atom(Atom::StrCi {
+        pattern: "ks-1".to_string(),
+    });
+
+    let mut packrat_parser = Parser::packrat(grammar.clone());
+    let mut bytecode_parser = Parser::bytecode(grammar);
+
+    for input in ["ks-1", "KS-1", "kS-1"] {
+        let packrat_result = packrat_parser.parse(input).unwrap();
+        let bytecode_result = bytecode_parser.parse(input).unwrap();
+        assert_eq!(packrat_result.end_pos, bytecode_result.end_pos);
+        assert_eq!(bytecode_result.end_pos, input.len());
+    }
+
+    // U+212A KELVIN SIGN and U+017F LATIN SMALL LETTER LONG S only
+    // case-fold to `k`/`s` under Unicode rules
+    for input in ["\u{212A}s-1", "k\u{17F}-1", "ks_1"] {
+        assert!(packrat_parser.parse(input).is_err());
+        assert!(bytecode_parser.parse(input).is_err());
+    }
+}
+
+#[test]
+fn test_backend_parity_char_range() {
+    let mut grammar = Grammar::new();
+    let digit = grammar.add_atom(Atom::CharRange { lo: '0', hi: '7' });
+    grammar.root = grammar.add_atom(Atom::Repetition {
+        atom: digit,
+        min: 1,
+        max: None,
+    });
+
+    let mut packrat_parser = Parser::packrat(grammar.clone());
+    let mut bytecode_parser = Parser::bytecode(grammar);
+
+    for (input, end_pos) in [("0", 1), ("1234", 4), ("0789", 2)] {
+        let packrat_result = packrat_parser.parse(input).unwrap();
+        let bytecode_result = bytecode_parser.parse(input).unwrap();
+        assert_eq!(packrat_result.end_pos, bytecode_result.end_pos);
+        assert_eq!(packrat_result.end_pos, end_pos);
+    }
+
+    assert!(packrat_parser.parse("8").is_err());
+    assert!(bytecode_parser.parse("8").is_err());
+}
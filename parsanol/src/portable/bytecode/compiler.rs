@@ -49,6 +49,12 @@ impl Compiler {
 
     /// Compile the grammar into a program
     pub fn compile(mut self) -> Result<Program, CompileError> {
+        if self.grammar.skip.is_some() {
+            return Err(CompileError::UnsupportedFeature {
+                feature: "grammar-level skip atom (use the packrat backend)".to_string(),
+            });
+        }
+
         // Compile the root atom
         let entry = self.compile_atom(self.grammar.root)?;
 
@@ -96,19 +102,65 @@ impl Compiler {
 
         match atom {
             Atom::Str { pattern } => self.compile_str(&pattern),
+            Atom::StrCi { pattern } => self.compile_str_ci(&pattern),
             Atom::Re { pattern } => self.compile_re(&pattern),
+            Atom::CharRange { lo, hi } => self.compile_char_range(lo, hi),
+            Atom::OneOf { chars } => self.compile_char_set(&chars, false),
+            Atom::NoneOf { chars } => self.compile_char_set(&chars, true),
             Atom::Sequence { atoms } => self.compile_sequence(&atoms),
             Atom::Alternative { atoms } => self.compile_alternative(&atoms),
             Atom::Repetition { atom, min, max } => self.compile_repetition(atom, min, max),
             Atom::Named { name, atom } => self.compile_named(&name, atom),
             Atom::Entity { atom } => self.compile_entity(atom),
+            Atom::Instantiate { template, .. } => Err(CompileError::UnsupportedFeature {
+                feature: format!("unresolved instantiation of template {:?}", template),
+            }),
             Atom::Lookahead { atom, positive } => self.compile_lookahead(atom, positive),
             Atom::Cut => self.compile_cut(),
+            Atom::Eof => self.compile_eof(),
             Atom::Ignore { atom } => self.compile_ignore(atom),
             Atom::Capture { name, atom } => self.compile_capture(&name, atom),
             Atom::Scope { atom } => self.compile_scope(atom),
             Atom::Dynamic { callback_id } => self.compile_dynamic(callback_id),
             Atom::Custom { id } => self.compile_custom(id),
+            Atom::Peek { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "value-keeping peek (use the packrat backend)".to_string(),
+            }),
+            Atom::Infix { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "infix expressions (use the packrat backend)".to_string(),
+            }),
+            Atom::LongestAlternative { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "longest-match alternatives (use the packrat backend)".to_string(),
+            }),
+            Atom::Indent | Atom::Samedent | Atom::Dedent => Err(CompileError::UnsupportedFeature {
+                feature: "indentation tracking (use the packrat backend)".to_string(),
+            }),
+            Atom::BackRef { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "back-references (use the packrat backend)".to_string(),
+            }),
+            Atom::OptionalDefault { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "optional defaults (use the packrat backend)".to_string(),
+            }),
+            Atom::ParseInt { .. } | Atom::ParseFloat { .. } => {
+                Err(CompileError::UnsupportedFeature {
+                    feature: "number conversion (use the packrat backend)".to_string(),
+                })
+            }
+            Atom::Until { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "scanning to a terminator (use the packrat backend)".to_string(),
+            }),
+            Atom::ByteRange { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "byte ranges (use the packrat backend)".to_string(),
+            }),
+            Atom::RepetitionLazy { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "lazy repetitions (use the packrat backend)".to_string(),
+            }),
+            Atom::Text { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "matched-text atoms (use the packrat backend)".to_string(),
+            }),
+            Atom::Const { .. } => Err(CompileError::UnsupportedFeature {
+                feature: "constant-value atoms (use the packrat backend)".to_string(),
+            }),
         }
     }
 
@@ -135,12 +187,45 @@ impl Compiler {
         Ok(entry)
     }
 
+    /// Compile a case-insensitive string
+    ///
+    /// ASCII patterns fold ASCII case only, like the packrat parser: each
+    /// letter becomes a two-byte char set and every other byte a literal,
+    /// so e.g. `K` never matches U+212A KELVIN SIGN. Other patterns use a
+    /// `(?i)` regex.
+    fn compile_str_ci(&mut self, pattern: &str) -> Result<usize, CompileError> {
+        if pattern.is_empty() {
+            return self.compile_str(pattern);
+        }
+        let entry = self.program.instruction_count();
+        if pattern.is_ascii() {
+            for &b in pattern.as_bytes() {
+                if b.is_ascii_alphabetic() {
+                    let set =
+                        CharSet::from_bytes(&[b.to_ascii_lowercase(), b.to_ascii_uppercase()]);
+                    let set_idx = self.program.add_char_set(set);
+                    self.program.add_instruction(Instruction::charset(set_idx));
+                } else {
+                    self.program.add_instruction(Instruction::char(b));
+                }
+            }
+        } else {
+            let regex = format!("(?i){}", regex::escape(pattern));
+            let regex_idx = self.program.add_regex(&regex);
+            self.program.add_instruction(Instruction::regex(regex_idx));
+        }
+        Ok(entry)
+    }
+
     /// Compile a regex pattern
     fn compile_re(&mut self, pattern: &str) -> Result<usize, CompileError> {
         let entry = self.program.instruction_count();
 
-        // Try to optimize common patterns to character classes
-        if let Some(char_pattern) = CharacterPattern::from_pattern(pattern) {
+        // Try to optimize common patterns to character classes. Unicode
+        // properties need UTF-8 decoding, which the VM leaves to regex.
+        if let Some(char_pattern) =
+            CharacterPattern::from_pattern(pattern).filter(|p| !p.is_unicode())
+        {
             let set = self.char_pattern_to_set(char_pattern);
             let set_idx = self.program.add_char_set(set);
             self.program.add_instruction(Instruction::charset(set_idx));
@@ -163,6 +248,55 @@ impl Compiler {
         Ok(entry)
     }
 
+    /// Compile an inclusive character range
+    fn compile_char_range(&mut self, lo: char, hi: char) -> Result<usize, CompileError> {
+        let entry = self.program.instruction_count();
+
+        if hi.is_ascii() {
+            let set = CharSet::from_range(lo as u8, hi as u8);
+            let set_idx = self.program.add_char_set(set);
+            self.program.add_instruction(Instruction::charset(set_idx));
+        } else {
+            // Multi-byte characters need UTF-8 decoding, which the VM leaves to regex
+            let pattern = format!("[\\x{{{:X}}}-\\x{{{:X}}}]", lo as u32, hi as u32);
+            let regex_idx = self.program.add_regex(&pattern);
+            self.program.add_instruction(Instruction::regex(regex_idx));
+        }
+
+        Ok(entry)
+    }
+
+    /// Compile a one-character set match, `negated` for `NoneOf`
+    fn compile_char_set(&mut self, chars: &[char], negated: bool) -> Result<usize, CompileError> {
+        let entry = self.program.instruction_count();
+
+        if !negated && chars.iter().all(char::is_ascii) {
+            let mut set = CharSet::new();
+            for &c in chars {
+                set.add(c as u8);
+            }
+            let set_idx = self.program.add_char_set(set);
+            self.program.add_instruction(Instruction::charset(set_idx));
+        } else {
+            // A negated set matches multi-byte characters, which the VM
+            // leaves to regex
+            let pattern = if chars.is_empty() {
+                // Nothing to exclude: any one character
+                "(?s:.)".to_string()
+            } else {
+                let escaped: String = chars
+                    .iter()
+                    .map(|&c| format!("\\x{{{:X}}}", c as u32))
+                    .collect();
+                format!("[{}{}]", if negated { "^" } else { "" }, escaped)
+            };
+            let regex_idx = self.program.add_regex(&pattern);
+            self.program.add_instruction(Instruction::regex(regex_idx));
+        }
+
+        Ok(entry)
+    }
+
     /// Convert a CharacterPattern to a CharSet
     fn char_pattern_to_set(&self, pattern: CharacterPattern) -> CharSet {
         use crate::portable::char_class::CHAR_CLASSES;
@@ -408,6 +542,9 @@ impl Compiler {
         match atom {
             Atom::Re { pattern } => {
                 if let Some(char_pattern) = CharacterPattern::from_pattern(pattern) {
+                    if char_pattern.is_unicode() {
+                        return None;
+                    }
                     let set = self.char_pattern_to_set(char_pattern);
                     Some(self.program.add_char_set(set))
                 } else if pattern.starts_with('[') && pattern.ends_with(']') {
@@ -417,11 +554,22 @@ impl Compiler {
                     None
                 }
             }
+            Atom::CharRange { lo, hi } if hi.is_ascii() => Some(
+                self.program
+                    .add_char_set(CharSet::from_range(*lo as u8, *hi as u8)),
+            ),
             Atom::Str { pattern } if pattern.len() == 1 => {
                 let mut set = CharSet::new();
                 set.add(pattern.as_bytes()[0]);
                 Some(self.program.add_char_set(set))
             }
+            Atom::OneOf { chars } if chars.iter().all(char::is_ascii) => {
+                let mut set = CharSet::new();
+                for &c in chars {
+                    set.add(c as u8);
+                }
+                Some(self.program.add_char_set(set))
+            }
             _ => None,
         }
     }
@@ -550,6 +698,26 @@ impl Compiler {
         Ok(entry)
     }
 
+    /// Compile end of input as a negative lookahead for any character
+    fn compile_eof(&mut self) -> Result<usize, CompileError> {
+        let entry = self.program.instruction_count();
+
+        // Choice success, Any 1, FailTwice
+        // success: (next instruction)
+        let choice_idx = self.program.instruction_count();
+        self.program
+            .add_instruction(Instruction::choice(PLACEHOLDER_OFFSET));
+        self.program.add_instruction(Instruction::any(1));
+        self.program.add_instruction(Instruction::fail_twice());
+
+        let success_idx = self.program.instruction_count();
+        let choice_offset = (success_idx as i32) - (choice_idx as i32 + 1);
+        self.program
+            .set_instruction(choice_idx, Instruction::choice(choice_offset));
+
+        Ok(entry)
+    }
+
     /// Compile ignore (match but discard result)
     fn compile_ignore(&mut self, atom_idx: usize) -> Result<usize, CompileError> {
         let entry = self.program.instruction_count();
@@ -46,29 +46,50 @@ impl FixedLenAnalysis {
         match atom {
             // Fixed length patterns
             Atom::Str { pattern } => PatternLength::Fixed(pattern.len()),
+            Atom::StrCi { pattern } if pattern.is_ascii() => PatternLength::Fixed(pattern.len()),
             Atom::Re { pattern } if pattern.len() == 1 => {
                 // Single char regex is fixed length
                 PatternLength::Fixed(1)
             }
+            Atom::CharRange { lo, hi } if lo.len_utf8() == hi.len_utf8() => {
+                PatternLength::Fixed(lo.len_utf8())
+            }
+            Atom::OneOf { chars } if chars.iter().all(char::is_ascii) => PatternLength::Fixed(1),
 
             // Variable length patterns
             Atom::Re { .. } => PatternLength::Variable,
+            Atom::CharRange { .. } => PatternLength::Variable,
+            Atom::OneOf { .. } | Atom::NoneOf { .. } => PatternLength::Variable,
+            Atom::Until { .. } => PatternLength::Variable,
+            Atom::ByteRange { .. } => PatternLength::Fixed(1),
+            // Unicode case folding can change the byte length of the match
+            Atom::StrCi { .. } => PatternLength::Variable,
             Atom::Sequence { .. } => PatternLength::Variable,
-            Atom::Alternative { .. } => PatternLength::Variable,
-            Atom::Repetition { .. } => PatternLength::Variable,
+            Atom::Alternative { .. } | Atom::LongestAlternative { .. } => PatternLength::Variable,
+            Atom::Repetition { .. } | Atom::RepetitionLazy { .. } => PatternLength::Variable,
+            Atom::Infix { .. } => PatternLength::Variable,
 
             // Patterns that don't consume input
             Atom::Lookahead { .. } => PatternLength::CannotMatch,
+            Atom::Peek { .. } => PatternLength::CannotMatch,
             Atom::Cut => PatternLength::CannotMatch,
+            Atom::Eof => PatternLength::CannotMatch,
+            Atom::Indent | Atom::Samedent | Atom::Dedent => PatternLength::CannotMatch,
 
             // Named captures preserve the inner pattern's length (need atom index)
             Atom::Named { .. } => PatternLength::Variable,
-            Atom::Ignore { .. } => PatternLength::Variable,
+            Atom::Ignore { .. } | Atom::Text { .. } => PatternLength::Variable,
             Atom::Entity { .. } => PatternLength::Variable,
+            Atom::Instantiate { .. } => PatternLength::Variable,
             Atom::Capture { .. } => PatternLength::Variable,
             Atom::Scope { .. } => PatternLength::Variable,
             Atom::Dynamic { .. } => PatternLength::Variable,
             Atom::Custom { .. } => PatternLength::Variable,
+            Atom::BackRef { .. } => PatternLength::Variable,
+            Atom::OptionalDefault { .. } => PatternLength::Variable,
+            Atom::ParseInt { .. } | Atom::ParseFloat { .. } | Atom::Const { .. } => {
+                PatternLength::Variable
+            }
         }
     }
 }
@@ -103,23 +124,46 @@ impl NullableAnalysis {
         match atom {
             // Always nullable (can match empty)
             Atom::Lookahead { .. } => PatternNullability::Nullable,
-            Atom::Repetition { min: 0, .. } => PatternNullability::Nullable,
+            Atom::Peek { .. } => PatternNullability::Nullable,
+            Atom::Repetition { min: 0, .. } | Atom::RepetitionLazy { min: 0, .. } => {
+                PatternNullability::Nullable
+            }
             Atom::Str { pattern } if pattern.is_empty() => PatternNullability::Nullable,
 
             // Never nullable (cannot match empty)
             Atom::Str { .. } => PatternNullability::NotNullable,
+            Atom::StrCi { pattern } if pattern.is_empty() => PatternNullability::Nullable,
+            Atom::StrCi { .. } => PatternNullability::NotNullable,
             Atom::Re { .. } => PatternNullability::NotNullable,
+            Atom::CharRange { .. } => PatternNullability::NotNullable,
+            Atom::OneOf { .. } | Atom::NoneOf { .. } => PatternNullability::NotNullable,
+            // The text before the terminator may be empty
+            Atom::Until { .. } => PatternNullability::Nullable,
+            Atom::ByteRange { .. } => PatternNullability::NotNullable,
             Atom::Sequence { .. } => PatternNullability::NotNullable,
-            Atom::Alternative { .. } => PatternNullability::NotNullable,
-            Atom::Repetition { .. } => PatternNullability::NotNullable,
+            Atom::Alternative { .. } | Atom::LongestAlternative { .. } => {
+                PatternNullability::NotNullable
+            }
+            Atom::Repetition { .. } | Atom::RepetitionLazy { .. } => {
+                PatternNullability::NotNullable
+            }
+            Atom::Infix { .. } => PatternNullability::NotNullable,
             Atom::Named { .. } => PatternNullability::NotNullable,
-            Atom::Ignore { .. } => PatternNullability::NotNullable,
+            Atom::Ignore { .. } | Atom::Text { .. } => PatternNullability::NotNullable,
             Atom::Entity { .. } => PatternNullability::NotNullable,
+            Atom::Instantiate { .. } => PatternNullability::NotNullable,
             Atom::Cut => PatternNullability::NotNullable,
+            Atom::Eof => PatternNullability::Nullable,
+            Atom::Indent | Atom::Samedent | Atom::Dedent => PatternNullability::Nullable,
             Atom::Capture { .. } => PatternNullability::NotNullable,
             Atom::Scope { .. } => PatternNullability::NotNullable,
             Atom::Dynamic { .. } => PatternNullability::NotNullable,
             Atom::Custom { .. } => PatternNullability::NotNullable,
+            Atom::BackRef { .. } => PatternNullability::NotNullable,
+            Atom::OptionalDefault { .. } => PatternNullability::Nullable,
+            Atom::ParseInt { .. } | Atom::ParseFloat { .. } | Atom::Const { .. } => {
+                PatternNullability::NotNullable
+            }
         }
     }
 }
@@ -160,6 +204,26 @@ impl FirstSetAnalysis {
                 charset: vec![pattern.as_bytes()[0]],
                 nullable: false,
             },
+            Atom::StrCi { pattern } if pattern.is_empty() => FirstSetAnalysis {
+                charset: vec![],
+                nullable: true,
+            },
+            Atom::StrCi { pattern } => {
+                // Non-ASCII patterns fold Unicode case, so even an ASCII
+                // first letter may match a multi-byte char (`k` and U+212A)
+                let first = pattern.as_bytes()[0];
+                let charset = if !pattern.is_ascii() {
+                    vec![]
+                } else if first.is_ascii_alphabetic() {
+                    vec![first.to_ascii_lowercase(), first.to_ascii_uppercase()]
+                } else {
+                    vec![first]
+                };
+                FirstSetAnalysis {
+                    charset,
+                    nullable: false,
+                }
+            }
 
             // Set patterns
             Atom::Re { pattern } => {
@@ -169,18 +233,52 @@ impl FirstSetAnalysis {
                 FirstSetAnalysis { charset, nullable }
             }
 
+            Atom::CharRange { lo, hi } => FirstSetAnalysis {
+                charset: if hi.is_ascii() {
+                    (*lo as u8..=*hi as u8).collect()
+                } else {
+                    vec![]
+                },
+                nullable: false,
+            },
+
+            Atom::OneOf { chars } => FirstSetAnalysis {
+                charset: if chars.iter().all(char::is_ascii) {
+                    chars.iter().map(|&c| c as u8).collect()
+                } else {
+                    vec![]
+                },
+                nullable: false,
+            },
+            Atom::NoneOf { .. } => FirstSetAnalysis {
+                charset: vec![],
+                nullable: false,
+            },
+            Atom::Until { .. } => FirstSetAnalysis {
+                charset: vec![],
+                nullable: true,
+            },
+            Atom::ByteRange { lo, hi } => FirstSetAnalysis {
+                charset: (*lo..=*hi).collect(),
+                nullable: false,
+            },
+
             // Combinations (need atom indices)
             Atom::Sequence { .. } => FirstSetAnalysis {
                 charset: vec![],
                 nullable: false,
             },
-            Atom::Alternative { .. } => FirstSetAnalysis {
+            Atom::Alternative { .. } | Atom::LongestAlternative { .. } => FirstSetAnalysis {
+                charset: vec![],
+                nullable: false,
+            },
+            Atom::Infix { .. } => FirstSetAnalysis {
                 charset: vec![],
                 nullable: false,
             },
 
             // Repetition: nullable, first set is inner's first set
-            Atom::Repetition { .. } => FirstSetAnalysis {
+            Atom::Repetition { .. } | Atom::RepetitionLazy { .. } => FirstSetAnalysis {
                 charset: vec![],
                 nullable: false,
             },
@@ -190,15 +288,23 @@ impl FirstSetAnalysis {
                 charset: vec![],
                 nullable: true,
             },
+            Atom::Peek { .. } => FirstSetAnalysis {
+                charset: vec![],
+                nullable: true,
+            },
             Atom::Cut => FirstSetAnalysis {
                 charset: vec![],
                 nullable: false,
             },
+            Atom::Eof | Atom::Indent | Atom::Samedent | Atom::Dedent => FirstSetAnalysis {
+                charset: vec![],
+                nullable: true,
+            },
             Atom::Named { .. } => FirstSetAnalysis {
                 charset: vec![],
                 nullable: false,
             },
-            Atom::Ignore { .. } => FirstSetAnalysis {
+            Atom::Ignore { .. } | Atom::Text { .. } => FirstSetAnalysis {
                 charset: vec![],
                 nullable: false,
             },
@@ -206,6 +312,10 @@ impl FirstSetAnalysis {
                 charset: vec![],
                 nullable: false,
             },
+            Atom::Instantiate { .. } => FirstSetAnalysis {
+                charset: vec![],
+                nullable: false,
+            },
             Atom::Capture { .. } => FirstSetAnalysis {
                 charset: vec![],
                 nullable: false,
@@ -222,6 +332,20 @@ impl FirstSetAnalysis {
                 charset: vec![],
                 nullable: false,
             },
+            Atom::BackRef { .. } => FirstSetAnalysis {
+                charset: vec![],
+                nullable: false,
+            },
+            Atom::OptionalDefault { .. } => FirstSetAnalysis {
+                charset: vec![],
+                nullable: true,
+            },
+            Atom::ParseInt { .. } | Atom::ParseFloat { .. } | Atom::Const { .. } => {
+                FirstSetAnalysis {
+                    charset: vec![],
+                    nullable: false,
+                }
+            }
         }
     }
 
@@ -322,6 +446,23 @@ mod tests {
         assert!(result.nullable);
     }
 
+    #[test]
+    fn test_first_set_analysis_str_ci() {
+        let analysis = FirstSetAnalysis::new();
+        let atom = Atom::StrCi {
+            pattern: "if".to_string(),
+        };
+        assert_eq!(analysis.analyze(&atom).charset, vec![b'i', b'I']);
+
+        // Unicode case folding: `k` also matches the Kelvin sign
+        let atom = Atom::StrCi {
+            pattern: "kä".to_string(),
+        };
+        let result = analysis.analyze(&atom);
+        assert!(result.charset.is_empty());
+        assert!(!result.nullable);
+    }
+
     #[test]
     fn test_fixed_len_analysis_custom() {
         let analysis = FixedLenAnalysis::new();
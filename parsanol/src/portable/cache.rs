@@ -22,12 +22,29 @@
 //! `CacheEntry` uses bit packing to minimize memory usage:
 //! - `success` flag is stored in the high bit of `packed_ast_ref`
 //! - Fields are reordered to minimize padding
-//! - Total size: 14 bytes (down from 16 bytes with naive layout)
+//! - Total size: 15 bytes (16 with alignment), including one byte of
+//!   eviction flags that would otherwise be padding
+//!
+//! # Bounded Mode
+//!
+//! By default the cache grows with the input, which is what gives packrat
+//! parsing its linear-time guarantee. [`DenseCache::bounded`] instead caps
+//! the memory used by entries and slots, and evicts entries once it is full.
+//! Victims are chosen with the CLOCK algorithm, an approximation of LRU:
+//! every hit sets a "referenced" flag on the entry, and a hand sweeping
+//! over the entries evicts the first one whose flag is clear, clearing
+//! flags as it passes. An evicted result is simply parsed again when it is
+//! next needed, so results are unchanged but the worst case is no longer
+//! linear.
 
 /// Bit mask for the success flag stored in the high bit of packed_ast_ref
 const SUCCESS_BIT: u32 = 0x8000_0000;
 /// Bit mask for the AST reference (lower 31 bits)
 const AST_REF_MASK: u32 = 0x7FFF_FFFF;
+/// Entry flag: hit since the clock hand last passed it
+const FLAG_REFERENCED: u8 = 0x01;
+/// Entry flag: must not be evicted
+const FLAG_PINNED: u8 = 0x02;
 
 /// A cached parse result (16 bytes with alignment padding)
 ///
@@ -51,6 +68,8 @@ pub struct CacheEntry {
     packed_ast_ref: u32,
     /// Atom ID in grammar
     pub atom_id: u16,
+    /// Eviction flags (`FLAG_*`), sitting in what would be padding
+    flags: u8,
 }
 
 impl CacheEntry {
@@ -67,9 +86,17 @@ impl CacheEntry {
             end_pos,
             packed_ast_ref: packed,
             atom_id,
+            flags: 0,
         }
     }
 
+    /// This entry, protected from eviction until [`DenseCache::unpin`]
+    #[inline]
+    pub(crate) fn pinned(mut self) -> Self {
+        self.flags |= FLAG_PINNED;
+        self
+    }
+
     /// Whether the parse succeeded
     #[inline]
     pub fn success(&self) -> bool {
@@ -108,6 +135,36 @@ impl CacheEntry {
     }
 }
 
+/// Snapshot of packrat cache behavior
+///
+/// Counters are plain integer increments on the (single-threaded) cache, so
+/// collecting them costs next to nothing even in release builds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PackratStats {
+    /// Lookups that found a memoized result
+    pub hits: u64,
+    /// Lookups that found nothing
+    pub misses: u64,
+    /// Entries written to the cache
+    pub insertions: u64,
+    /// Entries evicted to stay within a bounded cache's budget
+    pub evictions: u64,
+    /// Entries currently in the cache
+    pub entries: usize,
+}
+
+impl PackratStats {
+    /// Fraction of lookups answered from the cache (0.0 when there were none)
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total > 0 {
+            self.hits as f64 / total as f64
+        } else {
+            0.0
+        }
+    }
+}
+
 /// Dense packrat cache with linear probing
 pub struct DenseCache {
     /// Hash table: maps (hash % capacity) -> entry index
@@ -123,9 +180,24 @@ pub struct DenseCache {
     /// Load factor threshold (0.0 to 1.0)
     load_factor: f64,
 
+    /// Bounded mode: the byte budget and the entry count it allows
+    bound: Option<CacheBound>,
+
+    /// Next entry the eviction clock looks at
+    clock_hand: usize,
+
     /// Statistics
     hits: u64,
     misses: u64,
+    insertions: u64,
+    evictions: u64,
+}
+
+/// Size limit of a bounded [`DenseCache`]
+#[derive(Debug, Clone, Copy)]
+struct CacheBound {
+    max_bytes: usize,
+    max_entries: usize,
 }
 
 impl Default for DenseCache {
@@ -146,8 +218,62 @@ impl DenseCache {
             entries: Vec::with_capacity(estimated_entries),
             capacity,
             load_factor: 0.75,
+            bound: None,
+            clock_hand: 0,
             hits: 0,
             misses: 0,
+            insertions: 0,
+            evictions: 0,
+        }
+    }
+
+    /// Create a cache whose entries and slots fit in `max_bytes`
+    ///
+    /// The table is allocated once, at the largest power-of-two size whose
+    /// slots plus entries (at the usual 0.75 load) fit the budget, and
+    /// never grows; once it is full each insertion evicts an entry (see the
+    /// [module docs](self)). Budgets below the minimum table of 16 slots
+    /// get that minimum.
+    pub fn bounded(max_bytes: usize) -> Self {
+        let load_factor = 0.75;
+        let table_bytes = |capacity: usize| {
+            capacity * std::mem::size_of::<i32>()
+                + (capacity as f64 * load_factor) as usize * std::mem::size_of::<CacheEntry>()
+        };
+        let mut capacity = 16;
+        while table_bytes(capacity * 2) <= max_bytes {
+            capacity *= 2;
+        }
+        let max_entries = (capacity as f64 * load_factor) as usize;
+
+        Self {
+            slots: vec![-1i32; capacity],
+            entries: Vec::with_capacity(max_entries),
+            capacity,
+            load_factor,
+            bound: Some(CacheBound {
+                max_bytes,
+                max_entries,
+            }),
+            clock_hand: 0,
+            hits: 0,
+            misses: 0,
+            insertions: 0,
+            evictions: 0,
+        }
+    }
+
+    /// The byte budget of a [bounded](Self::bounded) cache, `None` if unbounded
+    #[inline]
+    pub fn max_bytes(&self) -> Option<usize> {
+        self.bound.map(|bound| bound.max_bytes)
+    }
+
+    /// An empty cache with the same bound, or a small unbounded one
+    pub(crate) fn emptied(&self) -> Self {
+        match self.max_bytes() {
+            Some(max_bytes) => Self::bounded(max_bytes),
+            None => Self::new(256),
         }
     }
 
@@ -201,6 +327,8 @@ impl DenseCache {
             if entry.pos == pos && entry.atom_id == atom_id {
                 // Found!
                 self.hits += 1;
+                let entry = &mut self.entries[idx as usize];
+                entry.flags |= FLAG_REFERENCED;
                 return Some(entry);
             }
 
@@ -210,24 +338,129 @@ impl DenseCache {
     }
 
     /// Insert an entry into the cache
+    ///
+    /// Returns the entry evicted to make room, if the cache is bounded and
+    /// full.
     #[inline]
-    pub fn insert(&mut self, entry: CacheEntry) {
+    pub fn insert(&mut self, entry: CacheEntry) -> Option<CacheEntry> {
+        self.place(entry, None).1
+    }
+
+    /// Store `entry`, returning its index and the entry it evicted
+    ///
+    /// `empty_slot` is a free slot on the entry's probe sequence, if the
+    /// caller already found one.
+    #[inline]
+    fn place(
+        &mut self,
+        entry: CacheEntry,
+        empty_slot: Option<usize>,
+    ) -> (usize, Option<CacheEntry>) {
+        self.insertions += 1;
+
+        if let Some(bound) = self.bound {
+            if self.entries.len() >= bound.max_entries {
+                if let Some(victim) = self.clock_victim() {
+                    // Removing the victim's slot may shift others, so probe anew
+                    self.remove_slot(victim);
+                    let evicted = std::mem::replace(&mut self.entries[victim], entry);
+                    let slot = self.empty_slot(entry.pos, entry.atom_id);
+                    self.slots[slot] = victim as i32;
+                    self.evictions += 1;
+                    return (victim, Some(evicted));
+                }
+                // Everything is pinned; going over budget beats failing.
+                // The table still has room, since pins are few and the
+                // load factor leaves a quarter of the slots free.
+            }
+        }
+
+        let mut slot = empty_slot;
         // Check if we need to resize
         if self.entries.len() as f64 / self.capacity as f64 > self.load_factor {
             self.resize();
+            slot = None;
         }
+        let slot = slot.unwrap_or_else(|| self.empty_slot(entry.pos, entry.atom_id));
 
-        let mut slot = self.hash(entry.pos, entry.atom_id);
+        let idx = self.entries.len();
+        self.entries.push(entry);
+        self.slots[slot] = idx as i32;
+        (idx, None)
+    }
 
-        // Find empty slot (linear probing)
+    /// First free slot on the probe sequence of `(pos, atom_id)`
+    #[inline]
+    fn empty_slot(&self, pos: u32, atom_id: u16) -> usize {
+        let mut slot = self.hash(pos, atom_id);
         while self.slots[slot] >= 0 {
             slot = (slot + 1) & (self.capacity - 1);
         }
+        slot
+    }
 
-        // Insert
-        let idx = self.entries.len() as i32;
-        self.entries.push(entry);
-        self.slots[slot] = idx;
+    /// Advance the clock hand to the next evictable entry
+    ///
+    /// Two sweeps clear every "referenced" flag, so `None` means every
+    /// entry is pinned.
+    fn clock_victim(&mut self) -> Option<usize> {
+        let len = self.entries.len();
+        for _ in 0..2 * len {
+            let idx = self.clock_hand;
+            self.clock_hand = (self.clock_hand + 1) % len;
+
+            let entry = &mut self.entries[idx];
+            if entry.flags & FLAG_PINNED != 0 {
+                continue;
+            }
+            if entry.flags & FLAG_REFERENCED != 0 {
+                entry.flags &= !FLAG_REFERENCED;
+                continue;
+            }
+            return Some(idx);
+        }
+        None
+    }
+
+    /// Free the slot pointing at entry `idx`
+    ///
+    /// Uses backward-shift deletion: later entries of the same probe run
+    /// move up into the hole, so lookups never stop early at it.
+    fn remove_slot(&mut self, idx: usize) {
+        let mask = self.capacity - 1;
+        let entry = self.entries[idx];
+        let mut hole = self.hash(entry.pos, entry.atom_id);
+        while self.slots[hole] != idx as i32 {
+            hole = (hole + 1) & mask;
+        }
+
+        let mut next = (hole + 1) & mask;
+        while self.slots[next] >= 0 {
+            let moved = self.entries[self.slots[next] as usize];
+            let home = self.hash(moved.pos, moved.atom_id);
+            // The entry may fill the hole unless its home lies in (hole, next]
+            let home_after_hole = (home.wrapping_sub(hole) & mask) != 0
+                && (home.wrapping_sub(hole) & mask) <= (next.wrapping_sub(hole) & mask);
+            if !home_after_hole {
+                self.slots[hole] = self.slots[next];
+                hole = next;
+            }
+            next = (next + 1) & mask;
+        }
+        self.slots[hole] = -1;
+    }
+
+    /// Let a [pinned](CacheEntry::pinned) entry be evicted again
+    pub(crate) fn unpin(&mut self, pos: u32, atom_id: u16) {
+        let mut slot = self.hash(pos, atom_id);
+        while self.slots[slot] >= 0 {
+            let entry = &mut self.entries[self.slots[slot] as usize];
+            if entry.pos == pos && entry.atom_id == atom_id {
+                entry.flags &= !FLAG_PINNED;
+                return;
+            }
+            slot = (slot + 1) & (self.capacity - 1);
+        }
     }
 
     /// Get or insert an entry
@@ -253,7 +486,9 @@ impl DenseCache {
             if entry.pos == pos && entry.atom_id == atom_id {
                 // Found!
                 self.hits += 1;
-                return (&mut self.entries[idx as usize], true);
+                let entry = &mut self.entries[idx as usize];
+                entry.flags |= FLAG_REFERENCED;
+                return (entry, true);
             }
 
             // Linear probing
@@ -262,21 +497,7 @@ impl DenseCache {
 
         // Need to insert
         self.misses += 1;
-
-        if self.entries.len() as f64 / self.capacity as f64 > self.load_factor {
-            self.resize();
-            // Recompute slot after resize
-            slot = self.hash(pos, atom_id);
-            while self.slots[slot] >= 0 {
-                slot = (slot + 1) & (self.capacity - 1);
-            }
-        }
-
-        let entry = f();
-        let idx = self.entries.len();
-        self.entries.push(entry);
-        self.slots[slot] = idx as i32;
-
+        let (idx, _) = self.place(f(), Some(slot));
         (&mut self.entries[idx], false)
     }
 
@@ -285,8 +506,68 @@ impl DenseCache {
     pub fn clear(&mut self) {
         self.slots.fill(-1);
         self.entries.clear();
+        self.clock_hand = 0;
         self.hits = 0;
         self.misses = 0;
+        self.insertions = 0;
+        self.evictions = 0;
+    }
+
+    /// Invalidate entries that may depend on the input in `[start, end)`
+    ///
+    /// That is every entry starting in the region, every success that
+    /// reaches into it or ends right at `start` (where it looked at the next
+    /// character to stop), and every failure before `end`: a failure doesn't
+    /// record how far ahead it looked.
+    ///
+    /// Returns the `ast_ref`s of the invalidated successes, sorted and
+    /// deduplicated: those slots of the cached node table are no longer
+    /// referenced and can be reused. Any remaining entry that shared one
+    /// of those slots is invalidated too, so no entry is left pointing at a
+    /// slot its owner may overwrite.
+    pub fn clear_region(&mut self, start: u32, end: u32) -> Vec<u32> {
+        self.clear_region_and_shift(start, end, 0)
+    }
+
+    /// Invalidate entries that depend on `[start, end)`, then move later
+    /// entries by `delta` bytes
+    ///
+    /// Entries are invalidated as by [`clear_region`](Self::clear_region).
+    /// For an edit that changed the input length, the entries left at or
+    /// after `end` have `pos` and `end_pos` shifted by `delta` so they line
+    /// up with the new input. Only the entries are shifted; positions stored
+    /// in the cached nodes themselves are the caller's to update. Returns
+    /// the freed `ast_ref`s, as [`clear_region`](Self::clear_region) does.
+    pub fn clear_region_and_shift(&mut self, start: u32, end: u32, delta: i64) -> Vec<u32> {
+        let affected =
+            |entry: &CacheEntry| entry.pos < end && (!entry.success() || entry.end_pos >= start);
+
+        let mut freed: Vec<u32> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.success() && affected(entry))
+            .map(|entry| entry.ast_ref())
+            .collect();
+        freed.sort_unstable();
+        freed.dedup();
+
+        let shift = |pos: u32| {
+            let shifted = pos as i64 + delta;
+            debug_assert!(shifted >= 0, "shift moves an entry before the input");
+            shifted as u32
+        };
+        self.retain_mut(|entry| {
+            if affected(entry) || (entry.success() && freed.binary_search(&entry.ast_ref()).is_ok())
+            {
+                return false;
+            }
+            if delta != 0 && entry.pos >= end {
+                entry.pos = shift(entry.pos);
+                entry.end_pos = shift(entry.end_pos);
+            }
+            true
+        });
+        freed
     }
 
     /// Get cache statistics
@@ -301,6 +582,18 @@ impl DenseCache {
         (self.hits, self.misses, hit_rate)
     }
 
+    /// Get a snapshot of the hit/miss/insertion counters
+    #[inline]
+    pub fn packrat_stats(&self) -> PackratStats {
+        PackratStats {
+            hits: self.hits,
+            misses: self.misses,
+            insertions: self.insertions,
+            evictions: self.evictions,
+            entries: self.entries.len(),
+        }
+    }
+
     /// Get the number of entries
     #[inline]
     pub fn len(&self) -> usize {
@@ -342,9 +635,25 @@ impl DenseCache {
     pub fn retain<F>(&mut self, mut predicate: F)
     where
         F: FnMut(&CacheEntry) -> bool,
+    {
+        self.retain_mut(|e| predicate(e));
+    }
+
+    /// Retain only entries that match a predicate, which may also modify
+    /// them, rebuilding the hash table
+    ///
+    /// Entries are re-hashed afterwards, so the predicate may change their
+    /// `pos` or `atom_id`. If two kept entries end up with the same key,
+    /// lookups return the first.
+    pub fn retain_mut<F>(&mut self, predicate: F)
+    where
+        F: FnMut(&mut CacheEntry) -> bool,
     {
         // First, filter entries
-        self.entries.retain(|e| predicate(e));
+        self.entries.retain_mut(predicate);
+        if self.clock_hand >= self.entries.len() {
+            self.clock_hand = 0;
+        }
 
         // Rebuild hash table
         self.slots.fill(-1);
@@ -436,6 +745,79 @@ mod tests {
         assert_eq!(entry.ast_ref(), 0);
     }
 
+    #[test]
+    fn test_packrat_stats_counters() {
+        let mut cache = DenseCache::new(16);
+        cache.insert(CacheEntry::new(0, 1, true, 5, 0));
+        assert!(cache.get(0, 1).is_some());
+        assert!(cache.get(3, 1).is_none());
+        let (_, hit) = cache.get_or_insert_with(3, 1, || CacheEntry::new(3, 1, false, 3, 0));
+        assert!(!hit);
+
+        let stats = cache.packrat_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.insertions, 2);
+        assert_eq!(stats.entries, 2);
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+
+        cache.clear();
+        assert_eq!(cache.packrat_stats(), PackratStats::default());
+    }
+
+    #[test]
+    fn test_bounded_cache_evicts_within_budget() {
+        let mut cache = DenseCache::bounded(1024);
+        assert_eq!(cache.max_bytes(), Some(1024));
+        assert!(cache.memory_usage() <= 1024);
+        let max_entries = cache.entries.capacity();
+
+        let mut evicted = 0;
+        for pos in 0..1000 {
+            if cache
+                .insert(CacheEntry::new(pos, 3, true, pos + 1, pos))
+                .is_some()
+            {
+                evicted += 1;
+            }
+            // Keep one entry hot so the clock always skips it
+            assert!(cache.get(0, 3).is_some());
+        }
+
+        let stats = cache.packrat_stats();
+        assert_eq!(stats.entries, max_entries);
+        assert_eq!(stats.evictions, evicted);
+        assert_eq!(evicted, 1000 - max_entries as u64);
+        assert!(cache.memory_usage() <= 1024);
+
+        // Every surviving entry is still found, evicted ones are not
+        let found: Vec<u32> = (0..1000)
+            .filter(|&pos| cache.get(pos, 3).is_some())
+            .collect();
+        assert_eq!(found.len(), max_entries);
+        assert!(found.contains(&0));
+        assert!(found.contains(&999));
+        for &pos in &found {
+            assert_eq!(cache.get(pos, 3).unwrap().ast_ref(), pos);
+        }
+    }
+
+    #[test]
+    fn test_bounded_cache_keeps_pinned_entries() {
+        let mut cache = DenseCache::bounded(0);
+        cache.insert(CacheEntry::new(7, 1, false, 7, 0).pinned());
+        for pos in 100..200 {
+            cache.insert(CacheEntry::new(pos, 1, false, pos, 0));
+        }
+        assert!(cache.get(7, 1).is_some());
+
+        cache.unpin(7, 1);
+        for pos in 200..300 {
+            cache.insert(CacheEntry::new(pos, 1, false, pos, 0));
+        }
+        assert!(cache.get(7, 1).is_none());
+    }
+
     #[test]
     fn test_collision_handling() {
         let mut cache = DenseCache::new(4); // Small capacity to force collisions
@@ -516,6 +898,63 @@ mod tests {
         assert!(cache.get(0, 1).is_none());
     }
 
+    #[test]
+    fn test_clear_region_keeps_entries_outside() {
+        let mut cache = DenseCache::new(16);
+        cache.insert(CacheEntry::new(0, 1, true, 3, 0));
+        cache.insert(CacheEntry::new(4, 2, false, 4, 0));
+        cache.insert(CacheEntry::new(10, 1, true, 12, 1));
+        // Shares node slot 0 with the entry at position 0
+        cache.insert(CacheEntry::new(12, 3, true, 14, 0));
+
+        assert_eq!(cache.clear_region(0, 5), [0]);
+        assert!(cache.get(0, 1).is_none());
+        assert!(cache.get(4, 2).is_none());
+        assert!(cache.get(12, 3).is_none());
+        let entry = cache.get(10, 1).unwrap();
+        assert_eq!((entry.end_pos, entry.ast_ref()), (12, 1));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_clear_region_and_shift() {
+        let mut cache = DenseCache::new(16);
+        cache.insert(CacheEntry::new(1, 1, true, 2, 0));
+        cache.insert(CacheEntry::new(5, 1, true, 6, 1));
+        cache.insert(CacheEntry::new(10, 1, true, 12, 2));
+
+        // Bytes 4..8 were replaced by a single byte
+        assert_eq!(cache.clear_region_and_shift(4, 8, -3), [1]);
+        assert_eq!(cache.get(1, 1).unwrap().end_pos, 2);
+        assert!(cache.get(10, 1).is_none());
+        let entry = cache.get(7, 1).unwrap();
+        assert_eq!((entry.end_pos, entry.ast_ref()), (9, 2));
+    }
+
+    #[test]
+    fn test_clear_region_drops_entries_reaching_into_it() {
+        let mut cache = DenseCache::new(16);
+        // Starts before the region and ends inside it
+        cache.insert(CacheEntry::new(2, 1, true, 6, 0));
+        // Ends right where the region starts
+        cache.insert(CacheEntry::new(1, 2, true, 4, 1));
+        // Ends before the region
+        cache.insert(CacheEntry::new(0, 3, true, 3, 2));
+        // A failure may have looked into the region
+        cache.insert(CacheEntry::new(0, 4, false, 0, 0));
+        // Starts right after the region
+        cache.insert(CacheEntry::new(8, 1, true, 9, 3));
+
+        assert_eq!(cache.clear_region_and_shift(4, 8, 2), [0, 1]);
+        assert!(cache.get(2, 1).is_none());
+        assert!(cache.get(1, 2).is_none());
+        assert!(cache.get(0, 4).is_none());
+        assert_eq!(cache.get(0, 3).unwrap().end_pos, 3);
+        let entry = cache.get(10, 1).unwrap();
+        assert_eq!((entry.end_pos, entry.ast_ref()), (11, 3));
+        assert_eq!(cache.len(), 2);
+    }
+
     #[test]
     fn test_hit_rate() {
         let mut cache = DenseCache::new(16);
@@ -539,8 +978,8 @@ mod tests {
     fn test_cache_entry_size() {
         // CacheEntry uses bit packing for cleaner API:
         // - success flag is packed into the high bit of packed_ast_ref
-        // - Field layout: pos(4) + end_pos(4) + packed_ast_ref(4) + atom_id(2) + padding(2) = 16 bytes
-        // - The 2 bytes of trailing padding are unavoidable due to u32 alignment
+        // - Field layout: pos(4) + end_pos(4) + packed_ast_ref(4) + atom_id(2) + flags(1) + padding(1) = 16 bytes
+        // - The eviction flags live in what would otherwise be trailing padding
         // - Still optimal for cache line usage (4 entries per 64-byte cache line)
         assert_eq!(std::mem::size_of::<CacheEntry>(), 16);
         assert_eq!(std::mem::align_of::<CacheEntry>(), 4);
@@ -310,6 +310,33 @@ impl CaptureState {
         self.depth = self.scope_stack.len();
     }
 
+    /// Captures stored since `snapshot` that are still in effect, as
+    /// `(name, value)` pairs in the order they were first stored
+    ///
+    /// Storing them again on the state the snapshot was taken of gives the
+    /// same captures as this state. Scopes pushed since the snapshot must
+    /// have been popped.
+    pub fn stored_since(&self, snapshot: &CaptureSnapshot) -> Vec<(String, CaptureValue)> {
+        let mut stored: Vec<(String, CaptureValue)> = Vec::new();
+        let entries = self
+            .capture_order
+            .get(snapshot.capture_count..)
+            .unwrap_or_default();
+        for entry in entries {
+            let name = match entry {
+                CaptureEntry::New(name) => name,
+                CaptureEntry::Shadow(name, _) => name,
+            };
+            if stored.iter().any(|(stored_name, _)| stored_name == name) {
+                continue;
+            }
+            if let Some(&value) = self.captures.get(name) {
+                stored.push((name.clone(), value));
+            }
+        }
+        stored
+    }
+
     /// Clear all captures and scopes
     #[inline]
     pub fn clear(&mut self) {
@@ -590,6 +617,39 @@ mod tests {
         assert!(!state.contains("deep"));
     }
 
+    #[test]
+    fn test_stored_since() {
+        let mut state = CaptureState::new();
+        state.store("a", CaptureValue::new(0, 1));
+        let snapshot = state.snapshot();
+
+        state.store("b", CaptureValue::new(1, 1));
+        state.store("a", CaptureValue::new(2, 1));
+        state.store("b", CaptureValue::new(3, 1));
+        state.push_scope();
+        state.store("c", CaptureValue::new(4, 1));
+        state.pop_scope();
+
+        let stored = state.stored_since(&snapshot);
+        assert_eq!(
+            stored,
+            vec![
+                ("b".to_string(), CaptureValue::new(3, 1)),
+                ("a".to_string(), CaptureValue::new(2, 1)),
+            ]
+        );
+
+        // Replaying them after restoring gives the same captures
+        let mut replayed = state.clone();
+        replayed.restore(&snapshot);
+        for (name, value) in &stored {
+            replayed.store(name, *value);
+        }
+        assert_eq!(replayed.get("a"), state.get("a"));
+        assert_eq!(replayed.get("b"), state.get("b"));
+        assert!(!replayed.contains("c"));
+    }
+
     #[test]
     fn test_clear() {
         let mut state = CaptureState::new();
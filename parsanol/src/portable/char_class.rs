@@ -3,7 +3,9 @@
 //! Pre-computed lookup tables for common character classes.
 //! Each table is a 256-byte bitmap for O(1) lookup of ASCII characters.
 //!
-//! For UTF-8 multi-byte characters, we fall back to the regex crate.
+//! For UTF-8 multi-byte characters, we fall back to the regex crate, except
+//! for the Unicode property classes (`\\p{L}`, `\\p{N}`, ...) which classify
+//! decoded `char`s directly.
 //!
 //! # CharacterPattern
 //!
@@ -18,6 +20,9 @@ use std::sync::OnceLock;
 static PATTERN_MAP: OnceLock<std::collections::HashMap<&'static str, CharacterPattern>> =
     OnceLock::new();
 
+/// Compiled `\p{L}` class for non-ASCII letters (std has no General_Category lookup)
+static LETTER_REGEX: OnceLock<regex::Regex> = OnceLock::new();
+
 /// Character pattern with unified matching logic
 ///
 /// This enum consolidates character class pattern handling, providing:
@@ -77,6 +82,14 @@ pub enum CharacterPattern {
     Punct,
     /// Any character: .
     Any,
+    /// Unicode letter: \\p{L}
+    UnicodeLetter,
+    /// Unicode alphabetic: \\p{Alphabetic}
+    UnicodeAlphabetic,
+    /// Unicode number: \\p{N}
+    UnicodeNumber,
+    /// Unicode whitespace: \\p{White_Space}
+    UnicodeSpace,
 }
 
 impl CharacterPattern {
@@ -94,7 +107,7 @@ impl CharacterPattern {
     /// | `\\D` | NonDigit |
     /// | `[a-z]` | Lower |
     /// | `[A-Z]` | Upper |
-    /// | `[a-zA-Z]`, `[A-Za-z]`, `\\p{L}` | Alpha |
+    /// | `[a-zA-Z]`, `[A-Za-z]` | Alpha |
     /// | `[a-zA-Z0-9]`, `[0-9a-zA-Z]` | Alnum |
     /// | `\\w`, `[a-zA-Z0-9_]` | Word |
     /// | `\\W` | NonWord |
@@ -102,6 +115,10 @@ impl CharacterPattern {
     /// | `\\s`, `[ \\t\\n\\r]` | Space |
     /// | `\\S` | NonSpace |
     /// | `[ \\t]`, `\\h` | Blank |
+    /// | `\\p{L}`, `\\pL`, `\\p{Letter}` | UnicodeLetter |
+    /// | `\\p{Alphabetic}`, `\\p{Alpha}` | UnicodeAlphabetic |
+    /// | `\\p{N}`, `\\pN`, `\\p{Number}` | UnicodeNumber |
+    /// | `\\p{White_Space}`, `\\p{Whitespace}` | UnicodeSpace |
     #[inline]
     pub fn from_pattern(pattern: &str) -> Option<Self> {
         // Use lazy-initialized static HashMap for O(1) lookup
@@ -120,7 +137,6 @@ impl CharacterPattern {
                     // Alpha
                     ("[a-zA-Z]", Self::Alpha),
                     ("[A-Za-z]", Self::Alpha),
-                    ("\\p{L}", Self::Alpha),
                     // Alnum
                     ("[a-zA-Z0-9]", Self::Alnum),
                     ("[0-9a-zA-Z]", Self::Alnum),
@@ -140,6 +156,17 @@ impl CharacterPattern {
                     // Blank
                     ("[ \t]", Self::Blank),
                     ("\\h", Self::Blank),
+                    // Unicode properties
+                    ("\\p{L}", Self::UnicodeLetter),
+                    ("\\pL", Self::UnicodeLetter),
+                    ("\\p{Letter}", Self::UnicodeLetter),
+                    ("\\p{Alphabetic}", Self::UnicodeAlphabetic),
+                    ("\\p{Alpha}", Self::UnicodeAlphabetic),
+                    ("\\p{N}", Self::UnicodeNumber),
+                    ("\\pN", Self::UnicodeNumber),
+                    ("\\p{Number}", Self::UnicodeNumber),
+                    ("\\p{White_Space}", Self::UnicodeSpace),
+                    ("\\p{Whitespace}", Self::UnicodeSpace),
                 ])
             })
             .get(pattern)
@@ -148,12 +175,70 @@ impl CharacterPattern {
 
     /// Check if a byte matches this character pattern
     ///
-    /// Uses O(1) lookup tables from CHAR_CLASSES.
+    /// Uses O(1) lookup tables from CHAR_CLASSES. Unicode property patterns
+    /// only classify ASCII bytes here; use [`matches_char`](Self::matches_char)
+    /// for decoded scalar values.
     #[inline(always)]
     pub fn matches(&self, b: u8) -> bool {
         CHAR_CLASSES.matches_pattern(*self, b)
     }
 
+    /// Whether this pattern matches non-ASCII characters by Unicode property
+    ///
+    /// These patterns must be matched on decoded `char`s with
+    /// [`matches_char`](Self::matches_char) rather than on single bytes.
+    #[inline]
+    pub fn is_unicode(&self) -> bool {
+        matches!(
+            self,
+            Self::UnicodeLetter
+                | Self::UnicodeAlphabetic
+                | Self::UnicodeNumber
+                | Self::UnicodeSpace
+        )
+    }
+
+    /// Check if a decoded character matches this character pattern
+    ///
+    /// ASCII characters use the byte tables. Other characters are classified
+    /// by Unicode property for the `Unicode*` patterns, match the negated
+    /// ASCII classes and `Any`, and match nothing else.
+    #[inline]
+    pub fn matches_char(&self, c: char) -> bool {
+        (self.char_predicate())(c)
+    }
+
+    /// Get a predicate function for bulk matching over decoded characters
+    ///
+    /// The char-aware counterpart of [`predicate`](Self::predicate), used when
+    /// repetitions must step over whole UTF-8 sequences.
+    #[inline]
+    pub fn char_predicate(&self) -> fn(char) -> bool {
+        match self {
+            Self::UnicodeLetter => is_unicode_letter,
+            Self::UnicodeAlphabetic => char::is_alphabetic,
+            Self::UnicodeNumber => char::is_numeric,
+            Self::UnicodeSpace => char::is_whitespace,
+            Self::Any => |_c| true,
+            Self::NonDigit => |c| !c.is_ascii() || !CHAR_CLASSES.is_digit(c as u8),
+            Self::NonWord => |c| !c.is_ascii() || !CHAR_CLASSES.is_word(c as u8),
+            Self::NonSpace => |c| !c.is_ascii() || !CHAR_CLASSES.is_space(c as u8),
+            Self::Digit => |c| c.is_ascii() && CHAR_CLASSES.is_digit(c as u8),
+            Self::Lower => |c| c.is_ascii() && CHAR_CLASSES.is_lower(c as u8),
+            Self::Upper => |c| c.is_ascii() && CHAR_CLASSES.is_upper(c as u8),
+            Self::Alpha => |c| c.is_ascii() && CHAR_CLASSES.is_alpha(c as u8),
+            Self::Alnum => |c| c.is_ascii() && CHAR_CLASSES.is_alnum(c as u8),
+            Self::Word => |c| c.is_ascii() && CHAR_CLASSES.is_word(c as u8),
+            Self::HexDigit => |c| c.is_ascii() && CHAR_CLASSES.is_hex_digit(c as u8),
+            Self::Space => |c| c.is_ascii() && CHAR_CLASSES.is_space(c as u8),
+            Self::Blank => |c| c.is_ascii() && CHAR_CLASSES.is_blank(c as u8),
+            Self::Cntrl => |c| c.is_ascii() && CHAR_CLASSES.is_cntrl(c as u8),
+            Self::Punct => |c| c.is_ascii() && CHAR_CLASSES.is_punct(c as u8),
+            Self::Graph => |c| (33..127).contains(&(c as u32)),
+            Self::Print => |c| (32..127).contains(&(c as u32)),
+        }
+    }
+
     /// Get a predicate function for bulk matching
     ///
     /// Returns a function pointer that can be used for SIMD bulk matching.
@@ -179,6 +264,34 @@ impl CharacterPattern {
             // Graph and Print are combinations - handle specially
             Self::Graph => |b| (33..127).contains(&b),
             Self::Print => |b| (32..127).contains(&b),
+            // Unicode properties: ASCII subset only, see `char_predicate`
+            Self::UnicodeLetter | Self::UnicodeAlphabetic => |b| CHAR_CLASSES.is_alpha(b),
+            Self::UnicodeNumber => |b| CHAR_CLASSES.is_digit(b),
+            Self::UnicodeSpace => |b| CHAR_CLASSES.is_space(b),
+        }
+    }
+
+    /// The bytes matched by [`predicate`](Self::predicate) as inclusive
+    /// ranges, for classes that are a few ranges below `0x7f`
+    ///
+    /// Bulk repetitions of these classes compare 16 bytes at a time against
+    /// the ranges instead of looking each byte up.
+    #[inline]
+    pub fn byte_ranges(&self) -> Option<&'static [(u8, u8)]> {
+        match self {
+            Self::Digit => Some(&[(b'0', b'9')]),
+            Self::Lower => Some(&[(b'a', b'z')]),
+            Self::Upper => Some(&[(b'A', b'Z')]),
+            Self::Alpha => Some(&[(b'A', b'Z'), (b'a', b'z')]),
+            Self::Alnum => Some(&[(b'0', b'9'), (b'A', b'Z'), (b'a', b'z')]),
+            Self::Word => Some(&[(b'0', b'9'), (b'A', b'Z'), (b'_', b'_'), (b'a', b'z')]),
+            Self::HexDigit => Some(&[(b'0', b'9'), (b'A', b'F'), (b'a', b'f')]),
+            Self::Space => Some(&[(b'\t', b'\r'), (b' ', b' ')]),
+            Self::Blank => Some(&[(b'\t', b'\t'), (b' ', b' ')]),
+            Self::Punct => Some(&[(b'!', b'/'), (b':', b'@'), (b'[', b'`'), (b'{', b'~')]),
+            Self::Graph => Some(&[(b'!', b'~')]),
+            Self::Print => Some(&[(b' ', b'~')]),
+            _ => None,
         }
     }
 
@@ -196,6 +309,21 @@ impl CharacterPattern {
     }
 }
 
+/// Classify a character as a Unicode letter (General_Category `L`)
+fn is_unicode_letter(c: char) -> bool {
+    if c.is_ascii() {
+        return CHAR_CLASSES.is_alpha(c as u8);
+    }
+    // Every letter is alphabetic, so this rejects most characters cheaply
+    if !c.is_alphabetic() {
+        return false;
+    }
+    let mut buf = [0u8; 4];
+    LETTER_REGEX
+        .get_or_init(|| regex::Regex::new(r"\A\p{L}\z").expect("valid letter class"))
+        .is_match(c.encode_utf8(&mut buf))
+}
+
 /// Pre-computed character class lookup tables
 ///
 /// These tables provide O(1) lookup for ASCII character membership.
@@ -432,6 +560,11 @@ impl CharClassTables {
             CharacterPattern::Print => self.print[b as usize],
             CharacterPattern::Punct => self.punct[b as usize],
             CharacterPattern::Any => true,
+            CharacterPattern::UnicodeLetter | CharacterPattern::UnicodeAlphabetic => {
+                self.alpha[b as usize]
+            }
+            CharacterPattern::UnicodeNumber => self.digit[b as usize],
+            CharacterPattern::UnicodeSpace => self.space[b as usize],
         }
     }
 }
@@ -634,4 +767,83 @@ mod tests {
 
         assert!(!digit.is_negation_of(&word));
     }
+
+    #[test]
+    fn test_unicode_property_patterns() {
+        assert_eq!(
+            CharacterPattern::from_pattern("\\p{L}"),
+            Some(CharacterPattern::UnicodeLetter)
+        );
+        assert_eq!(
+            CharacterPattern::from_pattern("\\p{Alphabetic}"),
+            Some(CharacterPattern::UnicodeAlphabetic)
+        );
+        assert_eq!(
+            CharacterPattern::from_pattern("\\p{N}"),
+            Some(CharacterPattern::UnicodeNumber)
+        );
+        assert!(CharacterPattern::UnicodeLetter.is_unicode());
+        assert!(!CharacterPattern::Alpha.is_unicode());
+    }
+
+    #[test]
+    fn test_unicode_property_matches_char() {
+        let letter = CharacterPattern::UnicodeLetter;
+        for c in ['a', 'Z', 'λ', 'Ω', '漢', 'ア'] {
+            assert!(letter.matches_char(c), "{:?}", c);
+        }
+        for c in ['1', '_', '٣', ' ', 'Ⅻ'] {
+            assert!(!letter.matches_char(c), "{:?}", c);
+        }
+
+        // Letter numbers are alphabetic but not letters
+        assert!(CharacterPattern::UnicodeAlphabetic.matches_char('Ⅻ'));
+
+        let number = CharacterPattern::UnicodeNumber;
+        assert!(number.matches_char('7'));
+        assert!(number.matches_char('٣'));
+        assert!(!number.matches_char('a'));
+
+        assert!(CharacterPattern::UnicodeSpace.matches_char('\u{3000}'));
+
+        // ASCII classes reject non-ASCII, their negations accept it
+        assert!(!CharacterPattern::Alpha.matches_char('λ'));
+        assert!(CharacterPattern::NonDigit.matches_char('λ'));
+    }
+
+    #[test]
+    fn test_unicode_property_byte_matches_ascii_subset() {
+        let letter = CharacterPattern::UnicodeLetter;
+        assert!(letter.matches(b'a'));
+        assert!(!letter.matches(b'1'));
+        assert!(!letter.matches(0xCE));
+        assert!(letter.predicate()(b'Q'));
+    }
+
+    #[test]
+    fn test_byte_ranges_match_predicate() {
+        let patterns = [
+            CharacterPattern::Digit,
+            CharacterPattern::Lower,
+            CharacterPattern::Upper,
+            CharacterPattern::Alpha,
+            CharacterPattern::Alnum,
+            CharacterPattern::Word,
+            CharacterPattern::HexDigit,
+            CharacterPattern::Space,
+            CharacterPattern::Blank,
+            CharacterPattern::Punct,
+            CharacterPattern::Graph,
+            CharacterPattern::Print,
+        ];
+        for pattern in patterns {
+            let ranges = pattern.byte_ranges().unwrap();
+            assert!(ranges.iter().all(|&(lo, hi)| lo <= hi && hi < 0x7f));
+            for b in 0..=255u8 {
+                let in_ranges = ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(&b));
+                assert_eq!(in_ranges, pattern.predicate()(b), "{:?} {:#x}", pattern, b);
+            }
+        }
+        assert_eq!(CharacterPattern::NonDigit.byte_ranges(), None);
+    }
 }
@@ -0,0 +1,348 @@
+//! Lossless concrete syntax trees
+//!
+//! The AST produced by [`PortableParser::parse`](super::PortableParser::parse)
+//! keeps only what the grammar captures: ignored atoms, whitespace and
+//! comments leave no trace. A [`CstNode`] tree accounts for every byte of
+//! the input, so the original source can be rebuilt exactly. This is what
+//! formatters and refactoring tools need to preserve user layout.
+//!
+//! # Structure
+//!
+//! - [`CstNode::Token`] - input matched by a captured leaf (an `InputRef`)
+//! - [`CstNode::Trivia`] - input consumed but not captured (whitespace,
+//!   comments, ignored punctuation)
+//! - [`CstNode::Node`] - an array or hash from the AST, labelled with the
+//!   hash key it was stored under, if any
+//!
+//! Children are ordered by source position, and the leaves of the tree,
+//! read left to right, cover the input exactly once.
+//!
+//! # Example
+//!
+//! ```
+//! use parsanol::portable::parser_dsl::*;
+//! use parsanol::portable::{AstArena, PortableParser};
+//!
+//! let grammar = GrammarBuilder::new()
+//!     .rule(
+//!         "pair",
+//!         re("[a-z]").many1().label("key")
+//!             .then(re("[ \t]").many().ignore())
+//!             .then(str("="))
+//!             .then(re("[ \t]").many().ignore())
+//!             .then(re("[0-9]").many1().label("value")),
+//!     )
+//!     .build();
+//!
+//! let input = "x  = 42";
+//! let mut arena = AstArena::new();
+//! let mut parser = PortableParser::new(&grammar, input, &mut arena);
+//! let cst = parser.parse_cst().unwrap();
+//! assert_eq!(cst.reconstruct(input), input);
+//! ```
+
+use super::arena::AstArena;
+use super::ast::AstNode;
+
+/// A node in a lossless concrete syntax tree
+///
+/// Offsets are byte offsets into the parsed input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CstNode {
+    /// Input matched by a captured leaf
+    Token {
+        /// Start offset (inclusive)
+        start: usize,
+        /// End offset (exclusive)
+        end: usize,
+    },
+    /// Input consumed by the grammar but not captured in the AST
+    Trivia {
+        /// Start offset (inclusive)
+        start: usize,
+        /// End offset (exclusive)
+        end: usize,
+    },
+    /// Interior node built from an AST array or hash
+    Node {
+        /// Hash key this node was stored under, if any
+        label: Option<String>,
+        /// Start offset (inclusive)
+        start: usize,
+        /// End offset (exclusive)
+        end: usize,
+        /// Children, in source order
+        children: Vec<CstNode>,
+    },
+}
+
+impl CstNode {
+    /// Build a CST covering all of `input` from a successfully parsed AST
+    ///
+    /// Bytes not covered by any `InputRef` in the AST become trivia. Values
+    /// that don't reference the input (interned strings, numbers, nil) have
+    /// no source text and are left out.
+    pub fn from_ast(node: &AstNode, arena: &AstArena, input: &str) -> Self {
+        let mut root = match lower(node, arena) {
+            Some(CstNode::Node {
+                label: None,
+                children,
+                ..
+            }) => children,
+            Some(other) => vec![other],
+            None => Vec::new(),
+        };
+        fill_trivia(&mut root, 0, input.len());
+        CstNode::Node {
+            label: None,
+            start: 0,
+            end: input.len(),
+            children: root,
+        }
+    }
+
+    /// Start offset of this node
+    #[inline]
+    pub fn start(&self) -> usize {
+        match self {
+            CstNode::Token { start, .. }
+            | CstNode::Trivia { start, .. }
+            | CstNode::Node { start, .. } => *start,
+        }
+    }
+
+    /// End offset of this node
+    #[inline]
+    pub fn end(&self) -> usize {
+        match self {
+            CstNode::Token { end, .. }
+            | CstNode::Trivia { end, .. }
+            | CstNode::Node { end, .. } => *end,
+        }
+    }
+
+    /// Source text covered by this node
+    #[inline]
+    pub fn text<'i>(&self, input: &'i str) -> &'i str {
+        &input[self.start()..self.end()]
+    }
+
+    /// Whether this node is trivia
+    #[inline]
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, CstNode::Trivia { .. })
+    }
+
+    /// Label of an interior node
+    #[inline]
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            CstNode::Node { label, .. } => label.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Children of an interior node (empty for leaves)
+    #[inline]
+    pub fn children(&self) -> &[CstNode] {
+        match self {
+            CstNode::Node { children, .. } => children,
+            _ => &[],
+        }
+    }
+
+    /// Rebuild the source text from the leaves of this tree
+    ///
+    /// For a tree built by [`CstNode::from_ast`] this returns `input`
+    /// byte-for-byte.
+    pub fn reconstruct(&self, input: &str) -> String {
+        let mut out = String::with_capacity(self.end() - self.start());
+        self.write_leaves(input, &mut out);
+        out
+    }
+
+    fn write_leaves(&self, input: &str, out: &mut String) {
+        match self {
+            CstNode::Token { start, end } | CstNode::Trivia { start, end } => {
+                out.push_str(&input[*start..*end]);
+            }
+            CstNode::Node { children, .. } => {
+                for child in children {
+                    child.write_leaves(input, out);
+                }
+            }
+        }
+    }
+}
+
+/// Convert an AST node to CST nodes without trivia
+///
+/// Returns `None` for nodes that cover no input.
+fn lower(node: &AstNode, arena: &AstArena) -> Option<CstNode> {
+    match node {
+        AstNode::InputRef { offset, length } if *length > 0 => {
+            let start = *offset as usize;
+            Some(CstNode::Token {
+                start,
+                end: start + *length as usize,
+            })
+        }
+        AstNode::Array { pool_index, length } => {
+            let children = arena
+                .get_array(*pool_index as usize, *length as usize)
+                .iter()
+                .filter_map(|item| lower(item, arena))
+                .collect();
+            make_node(None, children)
+        }
+        AstNode::Hash { pool_index, length } => {
+            let children = arena
+                .get_hash_items(*pool_index as usize, *length as usize)
+                .into_iter()
+                .filter_map(|(key, value)| match lower(&value, arena)? {
+                    CstNode::Node {
+                        label: None,
+                        children,
+                        ..
+                    } => make_node(Some(key), children),
+                    leaf => make_node(Some(key), vec![leaf]),
+                })
+                .collect();
+            make_node(None, children)
+        }
+        AstNode::Tagged { value, .. } => lower(value, arena),
+        _ => None,
+    }
+}
+
+/// Create an interior node spanning its children, in source order
+///
+/// Children overlapping an earlier sibling (e.g. the same input captured
+/// twice) are dropped so each byte appears once.
+fn make_node(label: Option<String>, mut children: Vec<CstNode>) -> Option<CstNode> {
+    children.sort_by_key(|c| (c.start(), std::cmp::Reverse(c.end())));
+    let mut cursor = 0;
+    children.retain(|c| {
+        let keep = c.start() >= cursor;
+        if keep {
+            cursor = c.end();
+        }
+        keep
+    });
+
+    let start = children.first()?.start();
+    let end = children.last()?.end();
+    Some(CstNode::Node {
+        label,
+        start,
+        end,
+        children,
+    })
+}
+
+/// Insert trivia for every gap between `children` within `start..end`
+fn fill_trivia(children: &mut Vec<CstNode>, start: usize, end: usize) {
+    let mut filled = Vec::with_capacity(children.len() * 2 + 1);
+    let mut cursor = start;
+    for mut child in children.drain(..) {
+        if child.start() > cursor {
+            filled.push(CstNode::Trivia {
+                start: cursor,
+                end: child.start(),
+            });
+        }
+        if let CstNode::Node {
+            start,
+            end,
+            children,
+            ..
+        } = &mut child
+        {
+            fill_trivia(children, *start, *end);
+        }
+        cursor = child.end();
+        filled.push(child);
+    }
+    if end > cursor {
+        filled.push(CstNode::Trivia { start: cursor, end });
+    }
+    *children = filled;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portable::parser::PortableParser;
+    use crate::portable::parser_dsl::{re, ref_, str, GrammarBuilder, ParsletExt};
+
+    fn collect_kinds(node: &CstNode, input: &str, out: &mut Vec<(bool, String)>) {
+        match node {
+            CstNode::Node { children, .. } => {
+                for child in children {
+                    collect_kinds(child, input, out);
+                }
+            }
+            leaf => out.push((leaf.is_trivia(), leaf.text(input).to_string())),
+        }
+    }
+
+    #[test]
+    fn test_cst_round_trip_with_trivia() {
+        let ws = || re("\\s").many().ignore();
+        let grammar = GrammarBuilder::new()
+            .rule(
+                "list",
+                ws().then(ref_("item"))
+                    .then(
+                        ws().then(str(",").ignore())
+                            .then(ws())
+                            .then(ref_("item"))
+                            .many(),
+                    )
+                    .then(ws()),
+            )
+            .rule("item", re("[a-z]").many1().label("item"))
+            .build();
+        let input = "  foo ,\tbar,\n baz  ";
+
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        let cst = parser.parse_cst().unwrap();
+
+        assert_eq!(cst.reconstruct(input), input);
+        assert_eq!((cst.start(), cst.end()), (0, input.len()));
+
+        let mut leaves = Vec::new();
+        collect_kinds(&cst, input, &mut leaves);
+        let tokens: Vec<&str> = leaves
+            .iter()
+            .filter(|(trivia, _)| !trivia)
+            .map(|(_, text)| text.as_str())
+            .collect();
+        assert_eq!(tokens, vec!["foo", "bar", "baz"]);
+        assert!(leaves
+            .iter()
+            .any(|(trivia, text)| *trivia && text == " ,\t"));
+    }
+
+    #[test]
+    fn test_cst_labels_and_untouched_ast_values() {
+        let input = "ab";
+        let mut arena = AstArena::new();
+        let key = AstNode::InputRef {
+            offset: 1,
+            length: 1,
+        };
+        let hash = arena.alloc_hash(vec![
+            ("k".to_string(), key),
+            ("n".to_string(), AstNode::Int(3)),
+        ]);
+
+        let cst = CstNode::from_ast(&hash, &arena, input);
+        assert_eq!(cst.reconstruct(input), input);
+        assert!(cst.children()[0].is_trivia());
+        assert_eq!(cst.children()[1].label(), Some("k"));
+        assert_eq!(cst.children()[1].text(input), "b");
+        assert_eq!(cst.children().len(), 2);
+    }
+}
@@ -6,28 +6,55 @@
 //! # Features
 //! - Parse tracing (step-by-step execution)
 //! - Parse tree visualization (pretty printing)
-//! - Grammar visualization (Mermaid/DOT diagrams)
+//! - Grammar visualization (Mermaid/DOT diagrams, EBNF export)
 //! - Error visualization
 
 use super::arena::AstArena;
 use super::ast::AstNode;
 use super::grammar::{Atom, Grammar};
+use super::infix::{Assoc, Fixity};
+use super::source_location::offset_to_line_col;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt::Write;
 
 /// Parse tree pretty printer
-pub struct TreePrinter {
+///
+/// By default this prints the bare tree structure. [`with_source`] adds
+/// the `line:col` range each node was matched from, and [`color`]
+/// highlights the output with ANSI escape codes.
+///
+/// [`with_source`]: TreePrinter::with_source
+/// [`color`]: TreePrinter::color
+pub struct TreePrinter<'s> {
     /// Indentation string
     indent: String,
     /// Maximum depth to print
     max_depth: Option<usize>,
+    /// Source to annotate nodes with their `line:col` ranges from
+    source: Option<&'s str>,
+    /// Whether to emit ANSI colors
+    color: bool,
 }
 
-impl TreePrinter {
+/// ANSI codes used by colored [`TreePrinter`] output
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_YELLOW: &str = "\x1b[33m";
+const ANSI_MAGENTA: &str = "\x1b[35m";
+const ANSI_CYAN: &str = "\x1b[36m";
+
+/// Longest snippet shown for a composite node's span, in characters
+const SNIPPET_CHARS: usize = 24;
+
+impl<'s> TreePrinter<'s> {
     /// Create a new tree printer
     pub fn new() -> Self {
         Self {
             indent: "  ".to_string(),
             max_depth: None,
+            source: None,
+            color: false,
         }
     }
 
@@ -43,10 +70,27 @@ impl TreePrinter {
         self
     }
 
+    /// Annotate nodes with the `line:col` range they were matched from
+    ///
+    /// Input references show their range; arrays, hashes and tagged nodes
+    /// show the range covered by the input references inside them, with a
+    /// snippet of the matched text.
+    pub fn with_source(mut self, source: &'s str) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Enable or disable ANSI colors (disabled by default)
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
     /// Print an AST node
     pub fn print(&self, node: &AstNode, arena: &AstArena, input: &str) -> String {
         let mut output = String::new();
-        self.print_node(node, arena, input, 0, &mut output);
+        let lines = self.source.map(LineIndex::new);
+        self.print_node(node, arena, input, lines.as_ref(), 0, &mut output);
         output
     }
 
@@ -55,6 +99,7 @@ impl TreePrinter {
         node: &AstNode,
         arena: &AstArena,
         input: &str,
+        lines: Option<&LineIndex<'_>>,
         depth: usize,
         output: &mut String,
     ) {
@@ -69,59 +114,156 @@ impl TreePrinter {
 
         match node {
             AstNode::Nil => {
-                writeln!(output, "{}nil", indent).unwrap();
+                writeln!(output, "{}{}", indent, self.paint(ANSI_MAGENTA, "nil")).unwrap();
             }
             AstNode::Bool(b) => {
-                writeln!(output, "{}{}", indent, b).unwrap();
+                writeln!(output, "{}{}", indent, self.paint(ANSI_MAGENTA, b)).unwrap();
             }
             AstNode::Int(n) => {
-                writeln!(output, "{}{}", indent, n).unwrap();
+                writeln!(output, "{}{}", indent, self.paint(ANSI_YELLOW, n)).unwrap();
             }
             AstNode::Float(f) => {
-                writeln!(output, "{}{:?}", indent, f).unwrap();
+                let f = format!("{:?}", f);
+                writeln!(output, "{}{}", indent, self.paint(ANSI_YELLOW, f)).unwrap();
             }
             AstNode::StringRef { pool_index } => {
-                let s = arena.get_string(*pool_index as usize);
-                writeln!(output, "{}{:?}", indent, s).unwrap();
+                let s = format!("{:?}", arena.get_string(*pool_index as usize));
+                writeln!(output, "{}{}", indent, self.paint(ANSI_GREEN, s)).unwrap();
             }
             AstNode::InputRef { offset, length } => {
                 let start = *offset as usize;
                 let end = start + *length as usize;
-                let s = &input[start..end.min(input.len())];
-                writeln!(output, "{}{:?} @ {}..{}", indent, s, offset, end).unwrap();
+                let s = format!("{:?}", &input[start..end.min(input.len())]);
+                write!(
+                    output,
+                    "{}{} @ {}..{}",
+                    indent,
+                    self.paint(ANSI_GREEN, s),
+                    offset,
+                    end
+                )
+                .unwrap();
+                if let Some(lines) = lines {
+                    let range = lines.range(start, end);
+                    write!(output, " {}", self.paint(ANSI_DIM, range)).unwrap();
+                }
+                output.push('\n');
             }
             AstNode::Array { pool_index, length } => {
-                writeln!(output, "{}[", indent).unwrap();
+                writeln!(output, "{}[{}", indent, self.span_note(node, arena, lines)).unwrap();
                 let items = arena.get_array(*pool_index as usize, *length as usize);
                 for item in items {
-                    self.print_node(&item, arena, input, depth + 1, output);
+                    self.print_node(&item, arena, input, lines, depth + 1, output);
                 }
                 writeln!(output, "{}]", indent).unwrap();
             }
             AstNode::Hash { pool_index, length } => {
-                writeln!(output, "{}{{", indent).unwrap();
+                writeln!(output, "{}{{{}", indent, self.span_note(node, arena, lines)).unwrap();
                 let pairs = arena.get_hash_items(*pool_index as usize, *length as usize);
                 for (key, value) in pairs {
+                    let key = self.paint(ANSI_CYAN, key);
                     writeln!(output, "{}{}{}:", indent, self.indent, key).unwrap();
-                    self.print_node(&value, arena, input, depth + 2, output);
+                    self.print_node(&value, arena, input, lines, depth + 2, output);
                 }
                 writeln!(output, "{}}}", indent).unwrap();
             }
             AstNode::Tagged { tag, value } => {
-                let tag_str = arena.get_string(*tag as usize);
-                writeln!(output, "{}{}:", indent, tag_str).unwrap();
-                self.print_node(value, arena, input, depth + 1, output);
+                let tag_str = self.paint(ANSI_CYAN, arena.get_string(*tag as usize));
+                let note = self.span_note(node, arena, lines);
+                writeln!(output, "{}{}:{}", indent, tag_str, note).unwrap();
+                self.print_node(value, arena, input, lines, depth + 1, output);
             }
         }
     }
+
+    /// ` 1:1-2:4 "snippet"` for a composite node, or nothing if it has no
+    /// input references or no source was given
+    fn span_note(&self, node: &AstNode, arena: &AstArena, lines: Option<&LineIndex<'_>>) -> String {
+        let (Some(lines), Some((start, end))) = (lines, input_span(node, arena)) else {
+            return String::new();
+        };
+        let text = lines.source.get(start..end).unwrap_or("");
+        let mut snippet: String = text.chars().take(SNIPPET_CHARS).collect();
+        if snippet.len() < text.len() {
+            snippet.push('…');
+        }
+        format!(
+            " {} {}",
+            self.paint(ANSI_DIM, lines.range(start, end)),
+            self.paint(ANSI_DIM, format!("{:?}", snippet))
+        )
+    }
+
+    fn paint(&self, code: &str, text: impl std::fmt::Display) -> String {
+        if self.color {
+            format!("{}{}{}", code, text, ANSI_RESET)
+        } else {
+            text.to_string()
+        }
+    }
 }
 
-impl Default for TreePrinter {
+impl Default for TreePrinter<'_> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Byte range covered by the input references in a tree
+fn input_span(node: &AstNode, arena: &AstArena) -> Option<(usize, usize)> {
+    let merge = |span: Option<(usize, usize)>, other: Option<(usize, usize)>| match (span, other) {
+        (Some((a, b)), Some((c, d))) => Some((a.min(c), b.max(d))),
+        (span, other) => span.or(other),
+    };
+    match node {
+        AstNode::InputRef { offset, length } => {
+            Some((*offset as usize, *offset as usize + *length as usize))
+        }
+        AstNode::Array { pool_index, length } => arena
+            .get_array(*pool_index as usize, *length as usize)
+            .iter()
+            .fold(None, |span, item| merge(span, input_span(item, arena))),
+        AstNode::Hash { pool_index, length } => arena
+            .get_hash_items(*pool_index as usize, *length as usize)
+            .iter()
+            .fold(None, |span, (_, value)| {
+                merge(span, input_span(value, arena))
+            }),
+        AstNode::Tagged { value, .. } => input_span(value, arena),
+        _ => None,
+    }
+}
+
+/// Line starts of a source, for repeated offset to `line:col` lookups
+struct LineIndex<'s> {
+    source: &'s str,
+    starts: Vec<usize>,
+}
+
+impl<'s> LineIndex<'s> {
+    fn new(source: &'s str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(memchr::memchr_iter(b'\n', source.as_bytes()).map(|nl| nl + 1))
+            .collect();
+        Self { source, starts }
+    }
+
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = super::source_location::clamp_to_char_boundary(self.source, offset);
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.starts[line];
+        let (_, column) = offset_to_line_col(&self.source[line_start..], offset - line_start);
+        (line + 1, column)
+    }
+
+    /// `line:col-line:col`, with an exclusive end
+    fn range(&self, start: usize, end: usize) -> String {
+        let (start_line, start_col) = self.line_col(start);
+        let (end_line, end_col) = self.line_col(end);
+        format!("{}:{}-{}:{}", start_line, start_col, end_line, end_col)
+    }
+}
+
 /// Grammar visualizer
 pub struct GrammarVisualizer<'a> {
     grammar: &'a Grammar,
@@ -148,7 +290,9 @@ impl<'a> GrammarVisualizer<'a> {
 
             // Add connections
             match atom {
-                Atom::Sequence { atoms } | Atom::Alternative { atoms } => {
+                Atom::Sequence { atoms }
+                | Atom::Alternative { atoms }
+                | Atom::LongestAlternative { atoms } => {
                     for &child in atoms {
                         writeln!(output, "  a{} --> a{}", i, child).unwrap();
                     }
@@ -156,13 +300,19 @@ impl<'a> GrammarVisualizer<'a> {
                 Atom::Repetition { atom, .. } => {
                     writeln!(output, "  a{} --> a{}", i, atom).unwrap();
                 }
+                Atom::RepetitionLazy { atom, follow, .. } => {
+                    writeln!(output, "  a{} --> a{}", i, atom).unwrap();
+                    writeln!(output, "  a{} --> a{}", i, follow).unwrap();
+                }
                 Atom::Named { atom, .. } => {
                     writeln!(output, "  a{} --> a{}", i, atom).unwrap();
                 }
                 Atom::Entity { atom } => {
                     writeln!(output, "  a{} --> a{}", i, atom).unwrap();
                 }
-                Atom::Lookahead { atom, .. } => {
+                Atom::Lookahead { atom, .. }
+                | Atom::Peek { atom }
+                | Atom::Infix { operand: atom, .. } => {
                     writeln!(output, "  a{} --> a{}", i, atom).unwrap();
                 }
                 _ => {}
@@ -191,7 +341,9 @@ impl<'a> GrammarVisualizer<'a> {
 
             // Add edges
             match atom {
-                Atom::Sequence { atoms } | Atom::Alternative { atoms } => {
+                Atom::Sequence { atoms }
+                | Atom::Alternative { atoms }
+                | Atom::LongestAlternative { atoms } => {
                     for &child in atoms {
                         writeln!(output, "  a{} -> a{}", i, child).unwrap();
                     }
@@ -199,13 +351,19 @@ impl<'a> GrammarVisualizer<'a> {
                 Atom::Repetition { atom, .. } => {
                     writeln!(output, "  a{} -> a{}", i, atom).unwrap();
                 }
+                Atom::RepetitionLazy { atom, follow, .. } => {
+                    writeln!(output, "  a{} -> a{}", i, atom).unwrap();
+                    writeln!(output, "  a{} -> a{}", i, follow).unwrap();
+                }
                 Atom::Named { atom, .. } => {
                     writeln!(output, "  a{} -> a{}", i, atom).unwrap();
                 }
                 Atom::Entity { atom } => {
                     writeln!(output, "  a{} -> a{}", i, atom).unwrap();
                 }
-                Atom::Lookahead { atom, .. } => {
+                Atom::Lookahead { atom, .. }
+                | Atom::Peek { atom }
+                | Atom::Infix { operand: atom, .. } => {
                     writeln!(output, "  a{} -> a{}", i, atom).unwrap();
                 }
                 _ => {}
@@ -224,20 +382,67 @@ impl<'a> GrammarVisualizer<'a> {
         output
     }
 
+    /// Render the grammar in an EBNF-like notation
+    ///
+    /// Emits one `name = body ;` line per rule, starting with the root.
+    /// Every `Entity` target becomes a rule; rules whose atom is `Named`
+    /// use that name (unless two rules share it), anonymous ones are
+    /// called `_a<index>`. Everything else is inlined:
+    ///
+    /// - `"lit"` and `"lit"i` for `Str`/`StrCi`, `/pattern/` for `Re`
+    /// - space-joined sequences and `|`-joined alternatives
+    /// - `*`, `+`, `?`, `{n}` and `{n,m}` for repetitions
+    /// - `&x`/`!x` for lookaheads and `name:x` for named results
+    ///
+    /// A cycle that doesn't go through an `Entity` is broken by promoting
+    /// the repeated atom to a rule, so the output is always finite.
+    pub fn to_ebnf(&self) -> String {
+        EbnfWriter::new(self.grammar).write()
+    }
+
     fn atom_label(&self, atom: &Atom) -> String {
         match atom {
             Atom::Str { pattern } => format!("str({:?})", pattern),
+            Atom::StrCi { pattern } => format!("stri({:?})", pattern),
             Atom::Re { pattern } => format!("re({:?})", pattern),
+            Atom::CharRange { lo, hi } => format!("char_range({:?}, {:?})", lo, hi),
+            Atom::OneOf { chars } => format!("one_of({:?})", chars),
+            Atom::NoneOf { chars } => format!("none_of({:?})", chars),
+            Atom::Until {
+                terminator,
+                inclusive,
+                or_end,
+            } => format!(
+                "{}({:?}){}",
+                if *inclusive {
+                    "take_until_inclusive"
+                } else {
+                    "take_until"
+                },
+                terminator,
+                if *or_end { ".or_end()" } else { "" }
+            ),
+            Atom::ByteRange { lo, hi } => format!("byte_range(0x{:02x}, 0x{:02x})", lo, hi),
             Atom::Sequence { atoms } => format!("seq({})", atoms.len()),
             Atom::Alternative { atoms } => format!("alt({})", atoms.len()),
+            Atom::LongestAlternative { atoms } => format!("longest({})", atoms.len()),
             Atom::Repetition { atom: _, min, max } => {
                 let max_str = max
                     .map(|m| m.to_string())
                     .unwrap_or_else(|| "∞".to_string());
                 format!("rep({}..{})", min, max_str)
             }
+            Atom::RepetitionLazy { min, max, .. } => {
+                let max_str = max
+                    .map(|m| m.to_string())
+                    .unwrap_or_else(|| "∞".to_string());
+                format!("rep_lazy({}..{})", min, max_str)
+            }
             Atom::Named { name, .. } => format!("named({:?})", name),
             Atom::Entity { .. } => "entity".to_string(),
+            Atom::Instantiate { template, args } => {
+                format!("instantiate({:?}, {})", template, args.len())
+            }
             Atom::Lookahead { positive, .. } => {
                 if *positive {
                     "lookahead(+)".to_string()
@@ -245,9 +450,21 @@ impl<'a> GrammarVisualizer<'a> {
                     "lookahead(-)".to_string()
                 }
             }
+            Atom::Peek { .. } => "peek".to_string(),
+            Atom::Infix { operators, .. } => format!("infix({} ops)", operators.len()),
             Atom::Cut => "cut".to_string(),
+            Atom::Eof => "eof".to_string(),
+            Atom::Indent => "indent".to_string(),
+            Atom::Samedent => "samedent".to_string(),
+            Atom::Dedent => "dedent".to_string(),
             Atom::Ignore { atom } => format!("ignore(a{})", atom),
+            Atom::Text { atom } => format!("text(a{})", atom),
             Atom::Capture { name, .. } => format!("capture({:?})", name),
+            Atom::BackRef { name } => format!("backref({:?})", name),
+            Atom::OptionalDefault { name, .. } => format!("optional_default({:?})", name),
+            Atom::ParseInt { atom, .. } => format!("parse_int(a{})", atom),
+            Atom::ParseFloat { atom } => format!("parse_float(a{})", atom),
+            Atom::Const { atom, value } => format!("const(a{}, {:?})", atom, value),
             Atom::Scope { .. } => "scope".to_string(),
             Atom::Dynamic { callback_id } => format!("dynamic({})", callback_id),
             Atom::Custom { id } => format!("custom({})", id),
@@ -255,6 +472,309 @@ impl<'a> GrammarVisualizer<'a> {
     }
 }
 
+/// Binding strength of an EBNF expression, loosest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EbnfPrec {
+    /// `a | b`
+    Alternative,
+    /// `a b`, and prefix forms like `&a` and `name:a`
+    Sequence,
+    /// Operands of postfix operators: literals, references, groups
+    Atom,
+}
+
+/// State for [`GrammarVisualizer::to_ebnf`]
+struct EbnfWriter<'a> {
+    grammar: &'a Grammar,
+    /// The root and every `Entity` target
+    rule_targets: BTreeSet<usize>,
+    /// How many rule targets are `Named` with each name
+    name_counts: HashMap<&'a str, usize>,
+    /// Atoms already emitted or waiting to be, as rules
+    rules: HashSet<usize>,
+    queue: VecDeque<usize>,
+    /// Atoms currently being inlined, to detect cycles
+    stack: Vec<usize>,
+}
+
+impl<'a> EbnfWriter<'a> {
+    fn new(grammar: &'a Grammar) -> Self {
+        let mut rule_targets: BTreeSet<usize> = grammar
+            .atoms
+            .iter()
+            .filter_map(|atom| match atom {
+                Atom::Entity { atom } => Some(*atom),
+                _ => None,
+            })
+            .collect();
+        rule_targets.insert(grammar.root);
+
+        let mut name_counts = HashMap::new();
+        for &idx in &rule_targets {
+            if let Some(Atom::Named { name, .. }) = grammar.atoms.get(idx) {
+                *name_counts.entry(name.as_str()).or_insert(0) += 1;
+            }
+        }
+
+        Self {
+            grammar,
+            rule_targets,
+            name_counts,
+            rules: HashSet::new(),
+            queue: VecDeque::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn write(mut self) -> String {
+        let mut output = String::new();
+        if self.grammar.atoms.is_empty() {
+            return output;
+        }
+
+        self.schedule(self.grammar.root);
+        while let Some(idx) = self.queue.pop_front() {
+            let name = self.rule_name(idx);
+            // A rule named after its `Named` atom doesn't repeat the name
+            let body = match self.grammar.atoms.get(idx) {
+                Some(Atom::Named { name: label, atom }) if *label == name => {
+                    let atom = *atom;
+                    self.stack.push(idx);
+                    let body = self.render(atom, EbnfPrec::Alternative);
+                    self.stack.pop();
+                    body
+                }
+                _ => self.render_inline(idx, EbnfPrec::Alternative),
+            };
+            writeln!(output, "{} = {} ;", name, body).unwrap();
+        }
+        output
+    }
+
+    fn rule_name(&self, idx: usize) -> String {
+        match self.grammar.atoms.get(idx) {
+            Some(Atom::Named { name, .. })
+                if self.rule_targets.contains(&idx)
+                    && self.name_counts.get(name.as_str()) == Some(&1) =>
+            {
+                name.clone()
+            }
+            _ => format!("_a{}", idx),
+        }
+    }
+
+    /// Queue `idx` to be emitted as a rule, once
+    fn schedule(&mut self, idx: usize) {
+        if self.rules.insert(idx) {
+            self.queue.push_back(idx);
+        }
+    }
+
+    /// Reference `idx` as a rule
+    fn reference(&mut self, idx: usize) -> String {
+        self.schedule(idx);
+        self.rule_name(idx)
+    }
+
+    /// Render `idx` as an operand that binds at least as tightly as `prec`
+    fn render(&mut self, idx: usize, prec: EbnfPrec) -> String {
+        if self.rules.contains(&idx) || self.stack.contains(&idx) {
+            return self.reference(idx);
+        }
+        self.render_inline(idx, prec)
+    }
+
+    fn render_inline(&mut self, idx: usize, prec: EbnfPrec) -> String {
+        let grammar = self.grammar;
+        let Some(atom) = grammar.atoms.get(idx) else {
+            return format!("<missing a{}>", idx);
+        };
+
+        self.stack.push(idx);
+        let (text, own) = match atom {
+            Atom::Str { pattern } => (format!("{:?}", pattern), EbnfPrec::Atom),
+            Atom::StrCi { pattern } => (format!("{:?}i", pattern), EbnfPrec::Atom),
+            Atom::Re { pattern } => (format!("/{}/", pattern), EbnfPrec::Atom),
+            Atom::CharRange { lo, hi } => (format!("{:?}..{:?}", lo, hi), EbnfPrec::Atom),
+            Atom::OneOf { chars } => (format!("[{}]", ebnf_chars(chars)), EbnfPrec::Atom),
+            Atom::NoneOf { chars } => (format!("[^{}]", ebnf_chars(chars)), EbnfPrec::Atom),
+            // An EBNF special sequence: there is no plain notation for it
+            Atom::Until {
+                terminator,
+                inclusive,
+                or_end,
+            } => (
+                format!(
+                    "? text up to {}{:?}{} ?",
+                    if *inclusive { "and including " } else { "" },
+                    terminator,
+                    if *or_end { " or end of input" } else { "" }
+                ),
+                EbnfPrec::Atom,
+            ),
+            Atom::ByteRange { lo, hi } if lo == hi => (format!("0x{:02x}", lo), EbnfPrec::Atom),
+            Atom::ByteRange { lo, hi } => (format!("0x{:02x}..0x{:02x}", lo, hi), EbnfPrec::Atom),
+            Atom::Sequence { atoms } | Atom::Alternative { atoms } if atoms.is_empty() => {
+                ("()".to_string(), EbnfPrec::Atom)
+            }
+            Atom::Sequence { atoms } | Atom::Alternative { atoms } if atoms.len() == 1 => {
+                (self.render(atoms[0], prec), prec)
+            }
+            Atom::Sequence { atoms } => {
+                let items: Vec<String> = atoms
+                    .iter()
+                    .map(|&child| self.render(child, EbnfPrec::Sequence))
+                    .collect();
+                (items.join(" "), EbnfPrec::Sequence)
+            }
+            Atom::Alternative { atoms } => {
+                let items: Vec<String> = atoms
+                    .iter()
+                    .map(|&child| self.render(child, EbnfPrec::Alternative))
+                    .collect();
+                (items.join(" | "), EbnfPrec::Alternative)
+            }
+            Atom::LongestAlternative { atoms } => {
+                let items: Vec<String> = atoms
+                    .iter()
+                    .map(|&child| self.render(child, EbnfPrec::Alternative))
+                    .collect();
+                (format!("longest({})", items.join(" | ")), EbnfPrec::Atom)
+            }
+            Atom::Repetition { atom, min, max } => {
+                let inner = self.render(*atom, EbnfPrec::Atom);
+                (inner + &repetition_suffix(*min, *max), EbnfPrec::Atom)
+            }
+            Atom::RepetitionLazy {
+                atom,
+                min,
+                max,
+                follow,
+            } => {
+                // Regex-style lazy quantifier, followed by the stop condition
+                let inner = self.render(*atom, EbnfPrec::Atom);
+                let follow = self.render(*follow, EbnfPrec::Atom);
+                (
+                    format!("{}{}? &{}", inner, repetition_suffix(*min, *max), follow),
+                    EbnfPrec::Sequence,
+                )
+            }
+            Atom::Named { name, atom } => {
+                let inner = self.render(*atom, EbnfPrec::Atom);
+                (format!("{}:{}", name, inner), EbnfPrec::Sequence)
+            }
+            Atom::Entity { atom } => (self.reference(*atom), EbnfPrec::Atom),
+            Atom::Instantiate { template, args } => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|&arg| self.render(arg, EbnfPrec::Alternative))
+                    .collect();
+                (format!("{}<{}>", template, args.join(", ")), EbnfPrec::Atom)
+            }
+            Atom::Lookahead { atom, positive } => {
+                let op = if *positive { "&" } else { "!" };
+                let inner = self.render(*atom, EbnfPrec::Atom);
+                (format!("{}{}", op, inner), EbnfPrec::Sequence)
+            }
+            Atom::Peek { atom } => {
+                let inner = self.render(*atom, EbnfPrec::Alternative);
+                (format!("peek({})", inner), EbnfPrec::Atom)
+            }
+            Atom::Infix { operand, operators } => {
+                let operand = self.render(*operand, EbnfPrec::Alternative);
+                let operators: Vec<String> = operators
+                    .iter()
+                    .map(|op| {
+                        let assoc = match (op.fixity, op.associativity) {
+                            (Fixity::Prefix, _) => "prefix",
+                            (Fixity::Postfix, _) => "postfix",
+                            (Fixity::Infix, Assoc::Left) => "left",
+                            (Fixity::Infix, Assoc::Right) => "right",
+                            (Fixity::Infix, Assoc::NonAssoc) => "nonassoc",
+                        };
+                        format!("{:?} {} {}", op.pattern, op.precedence, assoc)
+                    })
+                    .collect();
+                (
+                    format!("infix({}; {})", operand, operators.join(", ")),
+                    EbnfPrec::Atom,
+                )
+            }
+            Atom::Cut => ("cut".to_string(), EbnfPrec::Atom),
+            Atom::Eof => ("EOF".to_string(), EbnfPrec::Atom),
+            Atom::Indent => ("INDENT".to_string(), EbnfPrec::Atom),
+            Atom::Samedent => ("SAMEDENT".to_string(), EbnfPrec::Atom),
+            Atom::Dedent => ("DEDENT".to_string(), EbnfPrec::Atom),
+            Atom::Ignore { atom } => {
+                let inner = self.render(*atom, EbnfPrec::Alternative);
+                (format!("ignore({})", inner), EbnfPrec::Atom)
+            }
+            Atom::Text { atom } => {
+                let inner = self.render(*atom, EbnfPrec::Alternative);
+                (format!("text({})", inner), EbnfPrec::Atom)
+            }
+            Atom::Capture { name, atom } => {
+                let inner = self.render(*atom, EbnfPrec::Alternative);
+                (format!("capture({}, {})", name, inner), EbnfPrec::Atom)
+            }
+            Atom::BackRef { name } => (format!("backref({})", name), EbnfPrec::Atom),
+            Atom::OptionalDefault { name, atom, .. } => {
+                let inner = self.render(*atom, EbnfPrec::Atom);
+                (format!("({}:{})?", name, inner), EbnfPrec::Atom)
+            }
+            Atom::ParseInt { atom, .. } => {
+                let inner = self.render(*atom, EbnfPrec::Alternative);
+                (format!("int({})", inner), EbnfPrec::Atom)
+            }
+            Atom::ParseFloat { atom } => {
+                let inner = self.render(*atom, EbnfPrec::Alternative);
+                (format!("float({})", inner), EbnfPrec::Atom)
+            }
+            Atom::Const { atom, value } => {
+                let inner = self.render(*atom, EbnfPrec::Alternative);
+                (format!("const({}, {:?})", inner, value), EbnfPrec::Atom)
+            }
+            Atom::Scope { atom } => {
+                let inner = self.render(*atom, EbnfPrec::Alternative);
+                (format!("scope({})", inner), EbnfPrec::Atom)
+            }
+            Atom::Dynamic { callback_id } => (format!("dynamic({})", callback_id), EbnfPrec::Atom),
+            Atom::Custom { id } => (format!("custom({})", id), EbnfPrec::Atom),
+        };
+        self.stack.pop();
+
+        if own < prec {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+}
+
+/// EBNF quantifier for a repetition with the given bounds
+fn repetition_suffix(min: usize, max: Option<usize>) -> String {
+    match (min, max) {
+        (0, None) => "*".to_string(),
+        (1, None) => "+".to_string(),
+        (0, Some(1)) => "?".to_string(),
+        (min, None) => format!("{{{},}}", min),
+        (min, Some(max)) if min == max => format!("{{{}}}", min),
+        (min, Some(max)) => format!("{{{},{}}}", min, max),
+    }
+}
+
+/// Characters of a `OneOf`/`NoneOf` set, escaped for a bracket class
+fn ebnf_chars(chars: &[char]) -> String {
+    chars
+        .iter()
+        .map(|c| match c {
+            '\\' | ']' | '^' | '-' => format!("\\{}", c),
+            c if c.is_control() => c.escape_default().to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
 /// Debug trace for parsing
 #[derive(Debug, Clone)]
 pub struct ParseTrace {
@@ -344,6 +864,167 @@ impl Default for ParseTrace {
     }
 }
 
+/// Per-atom call counts and timings from
+/// [`PortableParser::parse_with_profile`](super::parser::PortableParser::parse_with_profile)
+///
+/// Each atom is attributed to the innermost [`Atom::Named`] rule it was
+/// first called under. Self time excludes the time spent in the atoms it
+/// called; total time includes it, so a recursive atom's total counts the
+/// nested calls more than once.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// Atoms that were called at least once, by descending self time
+    pub atoms: Vec<AtomProfile>,
+}
+
+/// Profile of a single atom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtomProfile {
+    /// Atom ID in the grammar
+    pub atom_id: usize,
+    /// Name of the enclosing named rule, if any
+    pub rule: Option<String>,
+    /// Number of times the atom was tried, including cache hits
+    pub calls: u64,
+    /// Time spent in the atom itself, in nanoseconds
+    pub self_nanos: u64,
+    /// Time spent in the atom and everything it called, in nanoseconds
+    pub total_nanos: u64,
+}
+
+impl Profile {
+    /// Self time summed per named rule, by descending time
+    ///
+    /// Atoms outside any named rule are summed under `None`.
+    pub fn rule_self_nanos(&self) -> Vec<(Option<String>, u64)> {
+        let mut totals: HashMap<Option<&str>, u64> = HashMap::new();
+        for atom in &self.atoms {
+            *totals.entry(atom.rule.as_deref()).or_insert(0) += atom.self_nanos;
+        }
+        let mut rules: Vec<_> = totals
+            .into_iter()
+            .map(|(rule, nanos)| (rule.map(str::to_string), nanos))
+            .collect();
+        rules.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        rules
+    }
+
+    /// Total self time of all atoms, in nanoseconds
+    pub fn total_nanos(&self) -> u64 {
+        self.atoms.iter().map(|atom| atom.self_nanos).sum()
+    }
+
+    /// Format as a table, one row per atom, by descending self time
+    pub fn format(&self) -> String {
+        let mut output = String::new();
+        writeln!(
+            output,
+            "{:<24} {:>6} {:>10} {:>12} {:>12}",
+            "rule", "atom", "calls", "self (us)", "total (us)"
+        )
+        .unwrap();
+        for atom in &self.atoms {
+            writeln!(
+                output,
+                "{:<24} {:>6} {:>10} {:>12.1} {:>12.1}",
+                atom.rule.as_deref().unwrap_or("-"),
+                atom.atom_id,
+                atom.calls,
+                atom.self_nanos as f64 / 1_000.0,
+                atom.total_nanos as f64 / 1_000.0
+            )
+            .unwrap();
+        }
+        output
+    }
+}
+
+/// Collects a [`Profile`] while the parser runs
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParseProfiler {
+    /// Per-atom counters, indexed by atom ID
+    atoms: Vec<AtomCounters>,
+    /// Time spent in callees of each open call, innermost last
+    callee_nanos: Vec<u64>,
+    /// Named atoms being parsed, innermost last
+    rules: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct AtomCounters {
+    calls: u64,
+    self_nanos: u64,
+    total_nanos: u64,
+    rule: Option<usize>,
+}
+
+impl ParseProfiler {
+    /// Create a profiler for a grammar of `atom_count` atoms
+    pub(crate) fn new(atom_count: usize) -> Self {
+        Self {
+            atoms: vec![AtomCounters::default(); atom_count],
+            ..Default::default()
+        }
+    }
+
+    /// Note that `atom_id` is about to be tried
+    pub(crate) fn enter(&mut self, atom_id: usize, named: bool) {
+        if named {
+            self.rules.push(atom_id);
+        }
+        if let Some(counters) = self.atoms.get_mut(atom_id) {
+            if counters.calls == 0 {
+                counters.rule = self.rules.last().copied();
+            }
+        }
+        self.callee_nanos.push(0);
+    }
+
+    /// Note that the innermost open call, to `atom_id`, took `elapsed`
+    pub(crate) fn exit(&mut self, atom_id: usize, named: bool, elapsed: std::time::Duration) {
+        let total = elapsed.as_nanos() as u64;
+        let callees = self.callee_nanos.pop().unwrap_or(0);
+        if let Some(caller) = self.callee_nanos.last_mut() {
+            *caller += total;
+        }
+        if named {
+            self.rules.pop();
+        }
+        if let Some(counters) = self.atoms.get_mut(atom_id) {
+            counters.calls += 1;
+            counters.self_nanos += total.saturating_sub(callees);
+            counters.total_nanos += total;
+        }
+    }
+
+    /// Resolve rule names and sort by self time
+    pub(crate) fn finish(self, grammar: &Grammar) -> Profile {
+        let rule_name = |atom_id: usize| match grammar.get_atom(atom_id) {
+            Some(Atom::Named { name, .. }) => Some(name.clone()),
+            _ => None,
+        };
+        let mut atoms: Vec<AtomProfile> = self
+            .atoms
+            .iter()
+            .enumerate()
+            .filter(|(_, counters)| counters.calls > 0)
+            .map(|(atom_id, counters)| AtomProfile {
+                atom_id,
+                rule: counters.rule.and_then(rule_name),
+                calls: counters.calls,
+                self_nanos: counters.self_nanos,
+                total_nanos: counters.total_nanos,
+            })
+            .collect();
+        atoms.sort_by(|a, b| {
+            b.self_nanos
+                .cmp(&a.self_nanos)
+                .then_with(|| a.atom_id.cmp(&b.atom_id))
+        });
+        Profile { atoms }
+    }
+}
+
 /// Source code formatter for showing parse context
 pub struct SourceFormatter;
 
@@ -412,6 +1093,37 @@ mod tests {
         assert!(output.contains("hello"));
     }
 
+    #[test]
+    fn test_tree_printer_source_spans_and_color() {
+        let input = "ab\ncd";
+        let mut arena = AstArena::new();
+        let items = vec![arena.input_ref(0, 2), arena.input_ref(3, 2)];
+        let root = arena.alloc_array(items);
+
+        // Plain output is unchanged unless asked for
+        let plain = TreePrinter::new().print(&root, &arena, input);
+        assert_eq!(plain, "[\n  \"ab\" @ 0..2\n  \"cd\" @ 3..5\n]\n");
+
+        let annotated = TreePrinter::new()
+            .with_source(input)
+            .print(&root, &arena, input);
+        assert!(
+            annotated.starts_with("[ 1:1-2:3 \"ab\\ncd\"\n"),
+            "{}",
+            annotated
+        );
+        assert!(annotated.contains("\"ab\" @ 0..2 1:1-1:3"), "{}", annotated);
+        assert!(annotated.contains("\"cd\" @ 3..5 2:1-2:3"), "{}", annotated);
+        assert!(!annotated.contains('\x1b'));
+
+        let colored = TreePrinter::new()
+            .with_source(input)
+            .color(true)
+            .print(&root, &arena, input);
+        assert!(colored.contains("\x1b[32m\"ab\"\x1b[0m"), "{:?}", colored);
+        assert!(colored.contains("\x1b[2m1:1-1:3\x1b[0m"), "{:?}", colored);
+    }
+
     #[test]
     fn test_grammar_visualizer() {
         let grammar = Grammar::new();
@@ -424,6 +1136,111 @@ mod tests {
         assert!(dot.contains("digraph"));
     }
 
+    /// expr = term (("+" | "-") term)* ; term and factor likewise
+    fn arithmetic_grammar() -> Grammar {
+        let mut grammar = Grammar::new();
+        let str_atom = |grammar: &mut Grammar, pattern: &str| {
+            grammar.add_atom(Atom::Str {
+                pattern: pattern.to_string(),
+            })
+        };
+
+        let expr_ref = grammar.add_atom(Atom::Entity { atom: 0 });
+        let digit = grammar.add_atom(Atom::Re {
+            pattern: "[0-9]".to_string(),
+        });
+        let number = grammar.add_atom(Atom::Repetition {
+            atom: digit,
+            min: 1,
+            max: None,
+        });
+        let open = str_atom(&mut grammar, "(");
+        let close = str_atom(&mut grammar, ")");
+        let group = grammar.add_atom(Atom::Sequence {
+            atoms: vec![open, expr_ref, close],
+        });
+        let factor_body = grammar.add_atom(Atom::Alternative {
+            atoms: vec![number, group],
+        });
+        let factor = grammar.add_atom(Atom::Named {
+            name: "factor".to_string(),
+            atom: factor_body,
+        });
+
+        let binary = |grammar: &mut Grammar, name: &str, operand: usize, ops: [&str; 2]| {
+            let operand_ref = grammar.add_atom(Atom::Entity { atom: operand });
+            let ops = ops.map(|op| str_atom(grammar, op)).to_vec();
+            let op = grammar.add_atom(Atom::Alternative { atoms: ops });
+            let step = grammar.add_atom(Atom::Sequence {
+                atoms: vec![op, operand_ref],
+            });
+            let steps = grammar.add_atom(Atom::Repetition {
+                atom: step,
+                min: 0,
+                max: None,
+            });
+            let body = grammar.add_atom(Atom::Sequence {
+                atoms: vec![operand_ref, steps],
+            });
+            grammar.add_atom(Atom::Named {
+                name: name.to_string(),
+                atom: body,
+            })
+        };
+        let term = binary(&mut grammar, "term", factor, ["*", "/"]);
+        let expr = binary(&mut grammar, "expr", term, ["+", "-"]);
+
+        grammar.atoms[expr_ref] = Atom::Entity { atom: expr };
+        grammar.root = expr;
+        grammar
+    }
+
+    #[test]
+    fn test_to_ebnf_recursive_grammar() {
+        let grammar = arithmetic_grammar();
+        assert_eq!(
+            grammar.to_ebnf(),
+            concat!(
+                "expr = term ((\"+\" | \"-\") term)* ;\n",
+                "term = factor ((\"*\" | \"/\") factor)* ;\n",
+                "factor = /[0-9]/+ | \"(\" expr \")\" ;\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_ebnf_anonymous_atoms_and_cycles() {
+        let mut grammar = Grammar::new();
+        let a = grammar.add_atom(Atom::Str {
+            pattern: "a".to_string(),
+        });
+        let some = grammar.add_atom(Atom::Repetition {
+            atom: a,
+            min: 2,
+            max: Some(3),
+        });
+        let maybe = grammar.add_atom(Atom::Repetition {
+            atom: a,
+            min: 0,
+            max: Some(1),
+        });
+        let not_a = grammar.add_atom(Atom::Lookahead {
+            atom: a,
+            positive: false,
+        });
+        // Refers to itself directly rather than through an Entity
+        let looping = grammar.add_atom(Atom::Alternative { atoms: vec![a, 4] });
+        grammar.root = grammar.add_atom(Atom::Sequence {
+            atoms: vec![some, maybe, not_a, looping],
+        });
+
+        assert_eq!(
+            grammar.to_ebnf(),
+            "_a5 = \"a\"{2,3} \"a\"? !\"a\" (\"a\" | _a4) ;\n_a4 = \"a\" | _a4 ;\n"
+        );
+        assert_eq!(Grammar::new().to_ebnf(), "");
+    }
+
     #[test]
     fn test_source_formatter() {
         let input = "line one\nline two\nline three";
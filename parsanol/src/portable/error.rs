@@ -23,6 +23,7 @@
 //! let rich_error: RichError = parse_error.into_rich("input text");
 //! ```
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 
@@ -33,7 +34,11 @@ pub use super::source_location::SourceSpan as Span;
 use super::ast::ParseError;
 
 /// A rich, tree-structured parse error
-#[derive(Debug, Clone)]
+///
+/// Serializes (see [`to_json`](Self::to_json)) with every field under its
+/// own name, `children` nested recursively and `severity` in lowercase,
+/// which makes it suitable for editors and CI annotations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RichError {
     /// The error message
     pub message: String,
@@ -41,14 +46,20 @@ pub struct RichError {
     pub span: Span,
     /// What was being parsed (e.g., "expression", "term")
     pub context: Option<String>,
+    /// Named rules enclosing the failure, outermost first
+    pub context_chain: Vec<String>,
     /// Child errors (causes)
     pub children: Vec<RichError>,
     /// Error severity
     pub severity: ErrorSeverity,
+    /// What the input probably meant, e.g. a keyword close to a misspelled
+    /// word, best match first
+    pub suggestions: Vec<String>,
 }
 
 /// Error severity level
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ErrorSeverity {
     /// Just a note
     Note,
@@ -67,8 +78,10 @@ impl RichError {
             message: message.into(),
             span,
             context: None,
+            context_chain: Vec::new(),
             children: Vec::new(),
             severity: ErrorSeverity::Error,
+            suggestions: Vec::new(),
         }
     }
 
@@ -88,6 +101,12 @@ impl RichError {
         self
     }
 
+    /// Set the chain of named rules enclosing the failure, outermost first
+    pub fn with_context_chain(mut self, chain: Vec<String>) -> Self {
+        self.context_chain = chain;
+        self
+    }
+
     /// Add a child error (cause)
     pub fn with_child(mut self, child: RichError) -> Self {
         self.children.push(child);
@@ -100,6 +119,44 @@ impl RichError {
         self
     }
 
+    /// Add "did you mean" suggestions
+    pub fn with_suggestions(mut self, suggestions: Vec<String>) -> Self {
+        self.suggestions.extend(suggestions);
+        self
+    }
+
+    /// The suggestions as a hint, e.g. "did you mean `select`?"
+    ///
+    /// `None` if there are no suggestions.
+    pub fn suggestion_hint(&self) -> Option<String> {
+        let (last, rest) = self.suggestions.split_last()?;
+        let quoted: Vec<String> = rest.iter().map(|s| format!("`{}`", s)).collect();
+        Some(if quoted.is_empty() {
+            format!("did you mean `{}`?", last)
+        } else {
+            format!("did you mean {} or `{}`?", quoted.join(", "), last)
+        })
+    }
+
+    /// Serialize to JSON
+    ///
+    /// ```
+    /// use parsanol::portable::RichError;
+    ///
+    /// let error = RichError::at_position("Expected \"]\"", 4, 1, 5);
+    /// let json = error.to_json().unwrap();
+    /// assert!(json.contains(r#""span":{"offset":4,"line":1,"column":5"#));
+    /// assert!(json.contains(r#""severity":"error""#));
+    /// ```
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Deserialize from JSON produced by [`to_json`](Self::to_json)
+    pub fn from_json(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
     /// Create an "expected" error
     pub fn expected(expected: &str, span: Span) -> Self {
         Self::at(format!("Expected {}", expected), span)
@@ -115,6 +172,17 @@ impl RichError {
         Self::at(format!("Failed to match {}", what), span)
     }
 
+    /// Start of this error as an LSP position (UTF-16 columns)
+    ///
+    /// `span` offsets are UTF-8 bytes; editors speaking LSP count columns
+    /// in UTF-16 code units instead.
+    pub fn to_lsp_position(
+        &self,
+        mapper: &super::source_location::Utf16Mapper,
+    ) -> super::source_location::LspPosition {
+        mapper.to_lsp_position(self.span.start.offset)
+    }
+
     /// Get the deepest error position
     pub fn deepest_position(&self) -> Span {
         if self.children.is_empty() {
@@ -187,6 +255,15 @@ impl RichError {
             output.push_str(&format!(" (in {})", ctx));
         }
 
+        if !self.context_chain.is_empty() {
+            let chain: Vec<String> = self
+                .context_chain
+                .iter()
+                .map(|name| format!("'{}'", name))
+                .collect();
+            output.push_str(&format!(" while parsing {}", chain.join(" → ")));
+        }
+
         output.push('\n');
 
         for (i, child) in self.children.iter().enumerate() {
@@ -196,40 +273,56 @@ impl RichError {
     }
 
     /// Format with source code context
+    ///
+    /// Offsets are clamped to the source bounds before rendering, so errors
+    /// reported at (or past) the end of input render as "at end of input"
+    /// with the caret placed just after the last character.
     pub fn format_with_source(&self, source: &str) -> String {
+        use super::source_location::clamp_to_char_boundary;
+
         let mut output = String::new();
 
         // Get the deepest position for context
         let pos = self.deepest_position();
+        let offset = clamp_to_char_boundary(source, pos.start.offset);
 
         // Format header
-        output.push_str(&format!(
-            "Error at line {}, column {}:\n",
-            pos.start.line, pos.start.column
-        ));
+        if offset == source.len() {
+            output.push_str(&format!(
+                "Error at end of input (line {}, column {}):\n",
+                pos.start.line, pos.start.column
+            ));
+        } else {
+            output.push_str(&format!(
+                "Error at line {}, column {}:\n",
+                pos.start.line, pos.start.column
+            ));
+        }
 
         // Get source line
-        let line_start = source[..pos.start.offset.min(source.len())]
-            .rfind('\n')
-            .map(|n| n + 1)
-            .unwrap_or(0);
-        let line_end = source[pos.start.offset.min(source.len())..]
+        let line_start = source[..offset].rfind('\n').map(|n| n + 1).unwrap_or(0);
+        let line_end = source[offset..]
             .find('\n')
-            .map(|n| pos.start.offset + n)
+            .map(|n| offset + n)
             .unwrap_or(source.len());
 
-        let line = &source[line_start..line_end.min(source.len())];
+        let line = &source[line_start..line_end];
 
         // Print line with error pointer
         output.push_str(line);
         output.push('\n');
 
-        // Error pointer
-        for _ in 0..(pos.start.column.saturating_sub(1)) {
+        // Error pointer (derived from the clamped offset so it never points
+        // past the end of the rendered line)
+        for _ in 0..source[line_start..offset].chars().count() {
             output.push(' ');
         }
         output.push_str("^\n");
 
+        if let Some(hint) = self.suggestion_hint() {
+            output.push_str(&format!("help: {}\n", hint));
+        }
+
         // Print tree
         output.push_str(&self.ascii_tree());
 
@@ -307,6 +400,7 @@ pub struct ErrorBuilder {
     context: Option<String>,
     children: Vec<RichError>,
     severity: ErrorSeverity,
+    suggestions: Vec<String>,
 }
 
 impl ErrorBuilder {
@@ -318,6 +412,7 @@ impl ErrorBuilder {
             context: None,
             children: Vec::new(),
             severity: ErrorSeverity::Error,
+            suggestions: Vec::new(),
         }
     }
 
@@ -351,16 +446,70 @@ impl ErrorBuilder {
         self
     }
 
+    /// Add a "did you mean" suggestion
+    pub fn suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestions.push(suggestion.into());
+        self
+    }
+
     /// Build the error
     pub fn build(self) -> RichError {
         RichError {
             message: self.message,
             span: self.span,
             context: self.context,
+            context_chain: Vec::new(),
             children: self.children,
             severity: self.severity,
+            suggestions: self.suggestions,
+        }
+    }
+}
+
+/// Largest edit distance at which a literal is suggested
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Literals that `word` is probably a misspelling of, closest first
+///
+/// A literal qualifies when its Levenshtein distance to `word` is at most
+/// 2 and smaller than its length, so short literals aren't suggested for
+/// unrelated words. Ties keep the order of `literals`; exact matches and
+/// duplicates are skipped.
+pub(crate) fn suggest_literals<'l>(
+    word: &str,
+    literals: impl IntoIterator<Item = &'l str>,
+) -> Vec<String> {
+    let mut close: Vec<(usize, &str)> = Vec::new();
+    for literal in literals {
+        if literal == word || close.iter().any(|&(_, seen)| seen == literal) {
+            continue;
+        }
+        let distance = levenshtein(word, literal);
+        if distance <= MAX_SUGGESTION_DISTANCE && distance < literal.chars().count() {
+            close.push((distance, literal));
         }
     }
+    close.sort_by_key(|&(distance, _)| distance);
+    close
+        .into_iter()
+        .map(|(_, literal)| literal.to_string())
+        .collect()
+}
+
+/// Levenshtein distance in characters
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
 }
 
 // Re-export offset_to_line_col from source_location for backward compatibility
@@ -394,6 +543,13 @@ impl ParseError {
 
         let (position, message) = match &self {
             ParseError::Failed { position } => (*position, "Parse failed".to_string()),
+            ParseError::FailedExpecting { position, expected } => (
+                *position,
+                format!(
+                    "Parse failed: expected {}",
+                    super::ast::format_expected(expected)
+                ),
+            ),
             ParseError::Incomplete { expected, actual } => {
                 return RichError::at(
                     format!(
@@ -454,9 +610,37 @@ impl ParseError {
                     Span::default(),
                 );
             }
+            ParseError::NodeLimitExceeded { count, max } => {
+                return RichError::at(
+                    format!(
+                        "Node limit exceeded: {} nodes exceeds limit of {}",
+                        count, max
+                    ),
+                    Span::default(),
+                );
+            }
             ParseError::BuilderError { message } => {
                 return RichError::at(format!("Builder error: {}", message), Span::default());
             }
+            ParseError::InvalidOffset { offset } => {
+                return RichError::at(format!("Invalid start offset: {}", offset), Span::default());
+            }
+            ParseError::InvalidUtf8 { offset } => {
+                return RichError::at(
+                    format!("Invalid UTF-8 at byte offset {}", offset),
+                    Span::default(),
+                );
+            }
+            ParseError::Transform { message } => {
+                return RichError::at(format!("Transform error: {}", message), Span::default());
+            }
+            ParseError::InvalidNumber { start, end } => (
+                *start,
+                format!(
+                    "Invalid number: {:?} is out of range or malformed",
+                    input.get(*start..*end).unwrap_or("")
+                ),
+            ),
         };
 
         // Convert byte offset to line/column
@@ -503,6 +687,62 @@ mod tests {
         assert!(tree.contains("Expected"));
     }
 
+    #[test]
+    fn test_rich_error_json_round_trip() {
+        let error = ErrorBuilder::new("Failed to parse statement")
+            .at(0, 1, 1)
+            .context("statement")
+            .child(
+                ErrorBuilder::new("Failed to parse expression")
+                    .at(4, 1, 5)
+                    .child(
+                        ErrorBuilder::new("Unexpected end of input")
+                            .at(9, 2, 1)
+                            .severity(ErrorSeverity::Fatal)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        let json = error.to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["context"], "statement");
+        assert_eq!(value["span"]["offset"], 0);
+        let child = &value["children"][0];
+        assert_eq!(child["message"], "Failed to parse expression");
+        assert_eq!(child["span"]["column"], 5);
+        let grandchild = &child["children"][0];
+        assert_eq!(grandchild["message"], "Unexpected end of input");
+        assert_eq!(grandchild["span"]["line"], 2);
+        assert_eq!(grandchild["severity"], "fatal");
+
+        let decoded = RichError::from_json(&json).unwrap();
+        assert_eq!(decoded.ascii_tree(), error.ascii_tree());
+        assert_eq!(decoded.deepest().severity, ErrorSeverity::Fatal);
+        assert_eq!(decoded.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn test_suggest_literals() {
+        let literals = ["select", "insert", "set", ";", "="];
+        // Closest first
+        assert_eq!(suggest_literals("selct", literals), ["select", "set"]);
+        assert_eq!(suggest_literals("insrt", literals), ["insert"]);
+        assert!(suggest_literals("select", literals).is_empty());
+        // Not for one-character literals, which any short word is close to
+        assert!(suggest_literals("x", literals).is_empty());
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+
+        let error = RichError::at("Expected keyword", Span::default())
+            .with_suggestions(vec!["select".into(), "set".into()]);
+        assert_eq!(
+            error.suggestion_hint().as_deref(),
+            Some("did you mean `select` or `set`?")
+        );
+    }
+
     #[test]
     fn test_deepest_position() {
         let parent = ErrorBuilder::new("Parent")
@@ -525,6 +765,33 @@ mod tests {
         assert!(formatted.contains("Unexpected token"));
     }
 
+    #[test]
+    fn test_format_with_source_at_eof() {
+        let source = "hello\nworld";
+        let (line, col) = offset_to_line_col(source, source.len());
+        let error = RichError::at_position("Unexpected end", source.len(), line, col);
+
+        let formatted = error.format_with_source(source);
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert_eq!(lines[0], "Error at end of input (line 2, column 6):");
+        assert_eq!(lines[1], "world");
+        assert_eq!(lines[2], "     ^");
+    }
+
+    #[test]
+    fn test_format_with_source_offset_out_of_bounds() {
+        let source = "héllo";
+        let error = ErrorBuilder::new("Bad offset").at(100, 1, 100).build();
+        let formatted = error.format_with_source(source);
+        assert!(formatted.contains("end of input"));
+        assert!(formatted.contains("héllo\n     ^"));
+
+        // Offset inside a multi-byte character snaps back to its start
+        let error = ErrorBuilder::new("Mid char").at(2, 1, 2).build();
+        let formatted = error.format_with_source(source);
+        assert!(formatted.contains("héllo\n ^"));
+    }
+
     #[test]
     fn test_format_with_source_and_captures() {
         let source = "hello world\nthis is a test\nmore text";
@@ -554,4 +821,28 @@ mod tests {
         // Should not contain captures section when empty
         assert!(!formatted.contains("Captures at time of error"));
     }
+
+    #[test]
+    fn test_to_lsp_position_counts_utf16_units() {
+        use crate::portable::parser_dsl::{re, str, GrammarBuilder, ParsletExt};
+        use crate::portable::source_location::{LspPosition, Utf16Mapper};
+        use crate::portable::{AstArena, PortableParser};
+
+        // "x = " then a quoted emoji, then a missing ';'
+        let grammar = GrammarBuilder::new()
+            .rule("assign", re("[a-z]").then(str(" = \"😀\"")).then(str(";")))
+            .build();
+        let input = "x = \"😀\"!";
+
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        let error = parser.parse_with_rich_error().unwrap_err();
+        assert_eq!(error.span.start.offset, 10);
+
+        let mapper = Utf16Mapper::new(input);
+        // 5 ASCII characters before the emoji, 2 units for it, 1 for '"'
+        assert_eq!(error.to_lsp_position(&mapper), LspPosition::new(0, 8));
+        // Counting characters would put the caret one column short
+        assert_eq!(error.span.start.column - 1, 7);
+    }
 }
@@ -3,9 +3,19 @@
 //! This module defines the in-memory representation of parsed grammars.
 //! Grammars are serialized to JSON from Ruby and deserialized here.
 
+use crate::portable::ast::AstNode;
+use crate::portable::char_class::CharacterPattern;
 use crate::portable::grammar_analysis::{GrammarAnalyzer, GrammarWarning};
+use crate::portable::infix::InfixOperator;
+use crate::portable::parser::captures::Captures;
+use crate::portable::parser::indentation::Indentation;
+use crate::portable::parser::left_recursion::LeftRecursion;
+use crate::portable::parser_dsl::map_atom_indices;
+use crate::portable::regex_cache;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::OnceLock;
 
 /// Atom types that make up a grammar
 ///
@@ -18,12 +28,81 @@ pub enum Atom {
         pattern: String,
     },
 
+    /// Match a literal string, ignoring case
+    ///
+    /// ASCII patterns are compared with cheap ASCII case folding; patterns
+    /// containing non-ASCII characters fall back to per-character Unicode
+    /// lowercasing. The result still references the original input text.
+    StrCi {
+        /// The string pattern to match
+        pattern: String,
+    },
+
     /// Match a regular expression pattern
     Re {
         /// The regex pattern to match
         pattern: String,
     },
 
+    /// Match a single character in an inclusive range
+    ///
+    /// Decodes one UTF-8 character and compares it against `lo..=hi`
+    /// directly, without going through the regex engine.
+    CharRange {
+        /// Lowest matching character
+        lo: char,
+        /// Highest matching character
+        hi: char,
+    },
+
+    /// Match a single character from a set
+    ///
+    /// Decodes one UTF-8 character and checks it against `chars`, without
+    /// going through the regex engine.
+    OneOf {
+        /// The characters to accept
+        chars: Vec<char>,
+    },
+
+    /// Match a single character not in a set
+    ///
+    /// The negated counterpart of `OneOf`, e.g. "any character except a
+    /// quote or backslash" for string bodies. Still consumes exactly one
+    /// character and fails at end of input.
+    NoneOf {
+        /// The characters to reject
+        chars: Vec<char>,
+    },
+
+    /// Consume input up to the next occurrence of a literal
+    ///
+    /// Matches the (possibly empty) text before `terminator` as a plain
+    /// slice, e.g. a block comment body or a heredoc. The terminator is
+    /// consumed too, but not part of the result, when `inclusive` is set.
+    /// If it never occurs, the match either fails or, with `or_end`, runs
+    /// to the end of the input.
+    Until {
+        /// The literal to stop at
+        terminator: String,
+        /// Whether the terminator is consumed as well
+        inclusive: bool,
+        /// Whether a missing terminator matches the rest of the input
+        /// instead of failing
+        or_end: bool,
+    },
+
+    /// Match a single byte in an inclusive range
+    ///
+    /// Meant for binary input parsed with `PortableParserBytes`. On `&str`
+    /// input only ASCII bytes can match, so a match never splits a
+    /// multi-byte character.
+    ByteRange {
+        /// Lowest byte accepted
+        lo: u8,
+        /// Highest byte accepted
+        hi: u8,
+    },
+
     /// Match multiple atoms in sequence
     Sequence {
         /// Indices into atoms array
@@ -36,6 +115,17 @@ pub enum Atom {
         atoms: Vec<usize>,
     },
 
+    /// Try every alternative and keep the one consuming the most input
+    ///
+    /// Unlike the ordered choice of `Alternative`, a later alternative wins
+    /// if it matches further, as in a lexer's longest-match rule; ties go to
+    /// the earliest. Every alternative is always tried, so prefer
+    /// `Alternative` when order alone disambiguates.
+    LongestAlternative {
+        /// Indices into atoms array
+        atoms: Vec<usize>,
+    },
+
     /// Repeat an atom (greedy, with min/max)
     Repetition {
         /// Index into atoms array
@@ -46,6 +136,25 @@ pub enum Atom {
         max: Option<usize>,
     },
 
+    /// Repeat an atom as few times as possible before `follow` matches
+    ///
+    /// After `min` matches of `atom`, tries `follow` before each further
+    /// repetition and stops as soon as it matches, like a non-greedy `*?`
+    /// in a regex. `follow` is only looked at, not consumed, so it
+    /// normally comes next in the enclosing sequence. Fails if `follow`
+    /// never matches within `max` repetitions, or `atom` fails first.
+    /// Produces the same array as [`Atom::Repetition`].
+    RepetitionLazy {
+        /// Index into atoms array
+        atom: usize,
+        /// Minimum number of repetitions
+        min: usize,
+        /// Maximum number of repetitions (None = unlimited)
+        max: Option<usize>,
+        /// Atom that ends the repetition, tried before each repetition
+        follow: usize,
+    },
+
     /// Name the result
     Named {
         /// The name to give the result
@@ -60,6 +169,30 @@ pub enum Atom {
         atom: usize,
     },
 
+    /// Instantiation of a rule template with concrete arguments
+    ///
+    /// Created by `parser_dsl::instantiate` and replaced during
+    /// `GrammarBuilder::build()` by a copy of the template body with each
+    /// parameter substituted by the corresponding argument atom. An
+    /// instantiation that survives `build()` (e.g. the template was never
+    /// defined) fails to parse with `ParseError::InvalidGrammar`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// // block("{", "}")
+    /// Atom::Instantiate {
+    ///     template: "block".to_string(),
+    ///     args: vec![open_brace_index, close_brace_index],
+    /// }
+    /// ```
+    Instantiate {
+        /// Name of the template rule
+        template: String,
+        /// Argument atom indices, one per template parameter
+        args: Vec<usize>,
+    },
+
     /// Lookahead (doesn't consume input)
     Lookahead {
         /// Index into atoms array
@@ -68,11 +201,67 @@ pub enum Atom {
         positive: bool,
     },
 
+    /// Positive lookahead that keeps the matched value
+    ///
+    /// Matches the inner atom and returns its value like a normal match,
+    /// but consumes no input. Unlike `Lookahead { positive: true }`, which
+    /// returns `Nil`, the value can be inspected to make a decision.
+    Peek {
+        /// Index into atoms array
+        atom: usize,
+    },
+
+    /// Operator-precedence expression
+    ///
+    /// Matches `operand (operator operand)*` and nests the operands by
+    /// precedence climbing: each binary operation becomes a
+    /// `{left, op, right}` hash, grouped according to the operators'
+    /// precedence and [`Assoc`](crate::portable::infix::Assoc). A lone operand is returned as is.
+    Infix {
+        /// Index of the operand atom
+        operand: usize,
+        /// Binary operators, in any order
+        operators: Vec<InfixOperator>,
+    },
+
     /// Atomic predicate (cut)
     ///
     /// Once this matches, backtracking past this point is prevented.
     Cut,
 
+    /// End of input
+    ///
+    /// Succeeds with `Nil`, consuming nothing, only at the end of the input.
+    /// Unlike the whole-input check in `parse`, this composes inside
+    /// sequences and alternatives.
+    Eof,
+
+    /// Open an indented block
+    ///
+    /// Consumes nothing. Succeeds only where the text before the position
+    /// on its line is blank (spaces or tabs) and the position's column is
+    /// greater than the current indentation level, which it then pushes.
+    /// The starting level is column 1. Columns count characters, so a tab
+    /// is one column.
+    ///
+    /// Atoms that can reach an indentation atom are never memoized, and a
+    /// failing one restores the indentation stack, so backtracking out of
+    /// a half-parsed block is safe.
+    Indent,
+
+    /// Match a line at the current indentation level
+    ///
+    /// Consumes nothing. Like `Indent`, requires only blanks before the
+    /// position on its line, and succeeds if its column equals the current
+    /// level.
+    Samedent,
+
+    /// Close the innermost indented block
+    ///
+    /// Consumes nothing and pops the level pushed by the matching `Indent`.
+    /// Fails if no block is open.
+    Dedent,
+
     /// Ignore the result
     ///
     /// Matches the inner atom but discards the result (returns Nil).
@@ -82,6 +271,16 @@ pub enum Atom {
         atom: usize,
     },
 
+    /// Replace the result with the matched text
+    ///
+    /// Matches the inner atom and returns the input it consumed as a single
+    /// interned [`AstNode::StringRef`], whatever structure the inner atom
+    /// produced. Useful before transforms that expect string leaves.
+    Text {
+        /// Index into atoms array
+        atom: usize,
+    },
+
     /// Capture matched text with a name
     ///
     /// Stores the matched text in the capture state with the given name.
@@ -104,6 +303,119 @@ pub enum Atom {
         atom: usize,
     },
 
+    /// Match the same text as a previous named capture (back-reference)
+    ///
+    /// Looks up the text most recently captured under `name` in the current
+    /// capture state and matches the input literally against it. Fails if no
+    /// such capture exists. Useful for matching closing tags or here-doc
+    /// terminators that must repeat an earlier match.
+    ///
+    /// Back-reference results depend on capture state, so they are never
+    /// memoized in the packrat cache.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// // Must match whatever was captured as :tag
+    /// Atom::BackRef {
+    ///     name: "tag".to_string(),
+    /// }
+    /// ```
+    BackRef {
+        /// The name of the capture to match again
+        name: String,
+    },
+
+    /// Optional named match with a default value
+    ///
+    /// Like `Named`, the result is a hash with a single `name` key. When
+    /// the inner atom does not match, no input is consumed and `default` is
+    /// stored under `name` instead, so the AST has the same shape whether
+    /// or not the optional part was present.
+    ///
+    /// The default is stored in the grammar, so it is limited to the
+    /// self-contained values of [`DefaultValue`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// // { "sign" => "-" } or { "sign" => nil }
+    /// Atom::OptionalDefault {
+    ///     name: "sign".to_string(),
+    ///     atom: minus_atom_index,
+    ///     default: DefaultValue::Nil,
+    /// }
+    /// ```
+    OptionalDefault {
+        /// The name to give the result
+        name: String,
+        /// Index into atoms array
+        atom: usize,
+        /// Value stored under `name` when the inner atom doesn't match
+        default: DefaultValue,
+    },
+
+    /// Parse the matched text as an integer
+    ///
+    /// Matches the inner atom and produces an `AstNode::Int` from the text
+    /// it consumed, instead of the inner atom's value. An optional `+` or
+    /// `-` sign is accepted. With `radix_prefixes`, `0x`, `0o` and `0b`
+    /// (after the sign) select hexadecimal, octal and binary.
+    ///
+    /// Text that is not a valid integer or doesn't fit an `i64` fails with
+    /// `ParseError::InvalidNumber` rather than a plain `Failed`, so an
+    /// overflowing literal is reported as such. The error aborts the parse:
+    /// choices and repetitions don't backtrack past it.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// // "42" => Int(42)
+    /// Atom::ParseInt {
+    ///     atom: digits_atom_index,
+    ///     radix_prefixes: false,
+    /// }
+    /// ```
+    ParseInt {
+        /// Index into atoms array
+        atom: usize,
+        /// Whether `0x`, `0o` and `0b` prefixes are recognized
+        radix_prefixes: bool,
+    },
+
+    /// Parse the matched text as a floating point number
+    ///
+    /// Like `ParseInt`, but produces an `AstNode::Float` using Rust's
+    /// `f64` syntax (`1.5`, `-2e10`, `inf`). Out-of-range values become
+    /// infinities; text that is not a number is a
+    /// `ParseError::InvalidNumber`.
+    ParseFloat {
+        /// Index into atoms array
+        atom: usize,
+    },
+
+    /// Replace the result with a constant
+    ///
+    /// Matches the inner atom and produces `value` instead of the inner
+    /// atom's value, e.g. `AstNode::Bool(true)` for the keyword `true`.
+    /// Wrapping an empty literal gives a constant that consumes nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// // "true" => Bool(true)
+    /// Atom::Const {
+    ///     atom: true_keyword_atom_index,
+    ///     value: DefaultValue::Bool(true),
+    /// }
+    /// ```
+    Const {
+        /// Index into atoms array
+        atom: usize,
+        /// The value produced when the inner atom matches
+        value: DefaultValue,
+    },
+
     /// Create an isolated capture scope
     ///
     /// Captures made within this scope are discarded when the scope ends.
@@ -178,25 +490,197 @@ pub enum Atom {
     },
 }
 
+/// Value stored by [`Atom::OptionalDefault`] when its inner atom doesn't match
+///
+/// Defaults live in the grammar rather than in a parse arena, so only values
+/// that don't reference an arena pool can be used.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum DefaultValue {
+    /// Nil/null value
+    Nil,
+    /// Boolean value
+    Bool(bool),
+    /// Integer value
+    Int(i64),
+    /// Floating point value
+    Float(f64),
+}
+
+impl DefaultValue {
+    /// Convert a self-contained AST node to a default value
+    ///
+    /// Returns `None` for nodes that reference an arena pool (strings,
+    /// input refs, arrays, hashes and tagged nodes).
+    pub fn from_ast(node: &AstNode) -> Option<Self> {
+        match node {
+            AstNode::Nil => Some(DefaultValue::Nil),
+            AstNode::Bool(b) => Some(DefaultValue::Bool(*b)),
+            AstNode::Int(n) => Some(DefaultValue::Int(*n)),
+            AstNode::Float(f) => Some(DefaultValue::Float(*f)),
+            _ => None,
+        }
+    }
+
+    /// The AST node stored for this default
+    #[inline]
+    pub fn to_ast(self) -> AstNode {
+        match self {
+            DefaultValue::Nil => AstNode::Nil,
+            DefaultValue::Bool(b) => AstNode::Bool(b),
+            DefaultValue::Int(n) => AstNode::Int(n),
+            DefaultValue::Float(f) => AstNode::Float(f),
+        }
+    }
+}
+
+// Grammars compare structurally, so floats compare by bits
+impl PartialEq for DefaultValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DefaultValue::Nil, DefaultValue::Nil) => true,
+            (DefaultValue::Bool(a), DefaultValue::Bool(b)) => a == b,
+            (DefaultValue::Int(a), DefaultValue::Int(b)) => a == b,
+            (DefaultValue::Float(a), DefaultValue::Float(b)) => a.to_bits() == b.to_bits(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for DefaultValue {}
+
 /// A complete grammar
 ///
 /// Contains all atoms and the root atom index.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Grammar {
     /// All atoms in the grammar (referenced by index)
+    ///
+    /// Atoms may be edited in place, even after parsing: parsers compare
+    /// them with the ones the grammar's cached analyses were computed for,
+    /// and analyze again if they differ. Prefer
+    /// [`get_atom_mut`](Self::get_atom_mut), which drops the cache instead.
     pub atoms: Vec<Atom>,
 
     /// Index of the root atom
     pub root: usize,
+
+    /// Atoms whose results the packrat cache stores
+    ///
+    /// `None` (the default) memoizes every atom. With `Some(ids)` only the
+    /// listed atoms are cached; everything else is re-parsed whenever it is
+    /// tried. This keeps the cache small for grammars where only a few rules
+    /// are retried at the same position, but an unmemoized rule that is
+    /// retried from many alternatives can make parsing exponential.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memoized: Option<BTreeSet<usize>>,
+
+    /// Whether every regex in the grammar only needs to match ASCII text
+    ///
+    /// When set, and the input is all ASCII, regexes are matched by the
+    /// byte-oriented engine (see [`regex_cache::get_or_compile_bytes`]),
+    /// which skips UTF-8 handling. Input with any non-ASCII character is
+    /// still matched by the Unicode engine, so results never depend on this
+    /// flag for ASCII input and stay correct for other input.
+    ///
+    /// The gain is in regexes the character-class fast path doesn't take,
+    /// such as `[a-z_][a-z0-9_]*` or `[0-9]+(\.[0-9]+)?`; single-class
+    /// repetitions never reach a regex engine either way. `cargo bench
+    /// --bench ascii-regex` tokenizes 1MB of ASCII source with both
+    /// engines to measure it on a given machine.
+    ///
+    /// [`regex_cache::get_or_compile_bytes`]: crate::portable::regex_cache::get_or_compile_bytes
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ascii_only: bool,
+
+    /// Atom skipped around the root and between sequence elements
+    ///
+    /// Languages that allow whitespace and comments between any two tokens
+    /// can set this once instead of threading a whitespace rule through
+    /// every sequence. The packrat parser tries the atom (usually a
+    /// repetition that may match nothing) before and after the root,
+    /// between consecutive elements of every [`Atom::Sequence`], between
+    /// the items of every [`Atom::Repetition`] and [`Atom::RepetitionLazy`]
+    /// (and before its `follow`), and around the operators of every
+    /// [`Atom::Infix`], and ignores what it matches. A repetition of an
+    /// [`Atom::Re`] is a single token and skips nothing.
+    /// Failures inside it are not reported as expected tokens.
+    ///
+    /// Skipping would break tokens that are themselves sequences, such as
+    /// `"a".then("b")` meant to match only `ab`; list those rules in
+    /// [`no_skip`](Self::no_skip). Grammars with significant whitespace,
+    /// like indentation-based ones, should leave whitespace that matters out
+    /// of this atom or not use it. Only the packrat backend supports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip: Option<usize>,
+
+    /// Atoms inside which [`skip`](Self::skip) is not applied
+    ///
+    /// Everything parsed while one of these atoms is being parsed,
+    /// including rules it references, matches without skipping. The skip
+    /// atom itself is always parsed this way. Results inside such atoms
+    /// depend on that context, so they bypass the packrat cache; only the
+    /// listed atoms themselves are cached.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub no_skip: BTreeSet<usize>,
+
+    /// Human-readable descriptions of rules, by atom index
+    ///
+    /// Error messages for a described atom say what was expected in these
+    /// words ("Expected a valid email address") instead of spelling out
+    /// the atom's pattern. Set by
+    /// [`GrammarBuilder::rule_described`](crate::portable::parser_dsl::GrammarBuilder::rule_described).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rule_descriptions: BTreeMap<usize, String>,
+
+    /// Analyses computed on first use and shared by every parser
+    #[serde(skip)]
+    cache: AnalysisCache,
 }
 
+/// Lazily computed grammar analyses
+///
+/// Derived data, not part of the grammar itself: skipped by serde and
+/// ignored by equality.
+#[derive(Debug, Clone, Default)]
+struct AnalysisCache {
+    /// The atoms the analyses were computed for, to detect `atoms` being
+    /// edited directly
+    atoms: OnceLock<Vec<Atom>>,
+    /// The left-recursion analysis
+    left_recursion: OnceLock<Option<LeftRecursion>>,
+    /// The indent-sensitive atoms
+    indentation: OnceLock<Option<Indentation>>,
+    /// The capture-dependent and capturing atoms
+    captures: OnceLock<Option<Captures>>,
+}
+
+impl PartialEq for AnalysisCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for AnalysisCache {}
+
 impl Grammar {
     /// Create a new empty grammar
     #[inline]
     pub fn new() -> Self {
+        Self::from_atoms(Vec::new(), 0)
+    }
+
+    /// Create a grammar from its atoms and root index
+    #[inline]
+    pub fn from_atoms(atoms: Vec<Atom>, root: usize) -> Self {
         Self {
-            atoms: Vec::new(),
-            root: 0,
+            atoms,
+            root,
+            memoized: None,
+            ascii_only: false,
+            skip: None,
+            no_skip: BTreeSet::new(),
+            rule_descriptions: BTreeMap::new(),
+            cache: AnalysisCache::default(),
         }
     }
 
@@ -205,6 +689,7 @@ impl Grammar {
     pub fn add_atom(&mut self, atom: Atom) -> usize {
         let idx = self.atoms.len();
         self.atoms.push(atom);
+        self.cache = AnalysisCache::default();
         idx
     }
 
@@ -217,9 +702,83 @@ impl Grammar {
     /// Get a mutable atom by index
     #[inline]
     pub fn get_atom_mut(&mut self, idx: usize) -> Option<&mut Atom> {
+        self.cache = AnalysisCache::default();
         self.atoms.get_mut(idx)
     }
 
+    /// Whether the packrat cache stores results for this atom
+    #[inline]
+    pub fn is_memoized(&self, atom_id: usize) -> bool {
+        self.memoized
+            .as_ref()
+            .is_none_or(|memoized| memoized.contains(&atom_id))
+    }
+
+    /// Description of the rule at `atom_id`, if it has one
+    #[inline]
+    pub fn rule_description(&self, atom_id: usize) -> Option<&str> {
+        self.rule_descriptions.get(&atom_id).map(String::as_str)
+    }
+
+    /// Memoize every atom except cheap leaves
+    ///
+    /// Literals, character classes and single-character regexes are faster
+    /// to match again than to look up in the packrat cache, so caching them
+    /// only costs memory. This sets [`memoized`](Self::memoized) to every
+    /// other atom; parse results are the same as with full memoization.
+    /// Atoms added afterwards are not memoized until this is called again.
+    pub fn memoize_composites(&mut self) {
+        self.memoized = Some(
+            (0..self.atoms.len())
+                .filter(|&id| !is_cheap_leaf(&self.atoms[id]))
+                .collect(),
+        );
+    }
+
+    /// Left-recursion analysis, computed once and cached
+    ///
+    /// Returns `None` for grammars without left recursion. Editing atoms
+    /// through [`Grammar::add_atom`] or [`Grammar::get_atom_mut`] drops the
+    /// cached result; if the `atoms` vector was edited directly, the
+    /// analysis is recomputed without caching.
+    pub(crate) fn left_recursion(&self) -> Option<Cow<'_, LeftRecursion>> {
+        self.cached_analysis(&self.cache.left_recursion, LeftRecursion::analyze)
+    }
+
+    /// Indent-sensitive atoms, computed once and cached like
+    /// [`left_recursion`](Self::left_recursion)
+    ///
+    /// Returns `None` for grammars without indentation atoms.
+    pub(crate) fn indentation(&self) -> Option<Cow<'_, Indentation>> {
+        self.cached_analysis(&self.cache.indentation, Indentation::analyze)
+    }
+
+    /// Capture-dependent and capturing atoms, computed once and cached like
+    /// [`left_recursion`](Self::left_recursion)
+    ///
+    /// Returns `None` for grammars without captures, back-references or
+    /// dynamic atoms.
+    pub(crate) fn captures(&self) -> Option<Cow<'_, Captures>> {
+        self.cached_analysis(&self.cache.captures, Captures::analyze)
+    }
+
+    /// `analyze`'s result from `cached`, or computed afresh if the atoms
+    /// no longer match the ones the cache was filled for
+    fn cached_analysis<'g, T: Clone>(
+        &'g self,
+        cached: &'g OnceLock<Option<T>>,
+        analyze: fn(&Grammar) -> Option<T>,
+    ) -> Option<Cow<'g, T>> {
+        if *self.cache.atoms.get_or_init(|| self.atoms.clone()) == self.atoms {
+            cached
+                .get_or_init(|| analyze(self))
+                .as_ref()
+                .map(Cow::Borrowed)
+        } else {
+            analyze(self).map(Cow::Owned)
+        }
+    }
+
     /// Get the root atom
     #[inline]
     pub fn root_atom(&self) -> Option<&Atom> {
@@ -232,6 +791,82 @@ impl Grammar {
         self.atoms.len()
     }
 
+    /// Indices of every atom reachable from the root, including the root
+    /// and the [`skip`](Self::skip) atom
+    ///
+    /// Atoms outside this set are the ones
+    /// [`analyze_warnings`](Self::analyze_warnings) reports as unused and
+    /// [`prune`](Self::prune) drops.
+    pub fn reachable_atoms(&self) -> HashSet<usize> {
+        GrammarAnalyzer::new(self).reachable_atoms()
+    }
+
+    /// Copy of the grammar without atoms unreachable from the root
+    ///
+    /// Composed and imported grammars tend to carry atoms nothing refers
+    /// to. Pruning drops them and renumbers the rest, keeping their order,
+    /// so every child index, the root and the [`memoized`](Self::memoized)
+    /// set point at the same atoms as before. Parse results are unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parsanol::portable::parser_dsl::{GrammarBuilder, str};
+    ///
+    /// let library = GrammarBuilder::new()
+    ///     .rule("yes", str("yes"))
+    ///     .rule("no", str("no"))
+    ///     .build();
+    ///
+    /// let mut builder = GrammarBuilder::new();
+    /// builder.import(&library, Some("lib"));
+    /// let grammar = builder.build();
+    ///
+    /// let pruned = grammar.prune();
+    /// assert!(pruned.atom_count() < grammar.atom_count());
+    /// assert!(pruned.parse("yes").is_ok());
+    /// ```
+    pub fn prune(&self) -> Grammar {
+        if self.root >= self.atoms.len() {
+            return self.clone();
+        }
+
+        let reachable = self.reachable_atoms();
+        let mut new_index = vec![None; self.atoms.len()];
+        let mut kept = 0;
+        for (old, slot) in new_index.iter_mut().enumerate() {
+            if reachable.contains(&old) {
+                *slot = Some(kept);
+                kept += 1;
+            }
+        }
+        let remapped = |idx: usize| new_index.get(idx).copied().flatten();
+
+        // Dangling child indices stay dangling
+        let atoms = self
+            .atoms
+            .iter()
+            .enumerate()
+            .filter(|(old, _)| reachable.contains(old))
+            .map(|(_, atom)| map_atom_indices(atom, |idx| remapped(idx).unwrap_or(usize::MAX)))
+            .collect();
+
+        let mut pruned = Grammar::from_atoms(atoms, remapped(self.root).unwrap_or(0));
+        pruned.memoized = self
+            .memoized
+            .as_ref()
+            .map(|memoized| memoized.iter().filter_map(|&id| remapped(id)).collect());
+        pruned.ascii_only = self.ascii_only;
+        pruned.skip = self.skip.and_then(remapped);
+        pruned.no_skip = self.no_skip.iter().filter_map(|&id| remapped(id)).collect();
+        pruned.rule_descriptions = self
+            .rule_descriptions
+            .iter()
+            .filter_map(|(&id, description)| Some((remapped(id)?, description.clone())))
+            .collect();
+        pruned
+    }
+
     /// Serialize to JSON
     #[inline]
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
@@ -244,6 +879,13 @@ impl Grammar {
         serde_json::from_str(s)
     }
 
+    /// Render the grammar in an EBNF-like notation for debugging
+    ///
+    /// See [`GrammarVisualizer::to_ebnf`](crate::portable::debug::GrammarVisualizer::to_ebnf).
+    pub fn to_ebnf(&self) -> String {
+        crate::portable::debug::GrammarVisualizer::new(self).to_ebnf()
+    }
+
     /// Analyze the grammar for optimization opportunities
     pub fn analyze(&self) -> GrammarAnalysis {
         let mut atom_types = HashMap::new();
@@ -251,16 +893,37 @@ impl Grammar {
         for atom in &self.atoms {
             let ty = match atom {
                 Atom::Str { .. } => "str",
+                Atom::StrCi { .. } => "str_ci",
                 Atom::Re { .. } => "re",
+                Atom::CharRange { .. } => "char_range",
+                Atom::OneOf { .. } => "one_of",
+                Atom::NoneOf { .. } => "none_of",
+                Atom::Until { .. } => "until",
+                Atom::ByteRange { .. } => "byte_range",
                 Atom::Sequence { .. } => "sequence",
                 Atom::Alternative { .. } => "alternative",
+                Atom::LongestAlternative { .. } => "longest_alternative",
                 Atom::Repetition { .. } => "repetition",
+                Atom::RepetitionLazy { .. } => "repetition_lazy",
                 Atom::Named { .. } => "named",
                 Atom::Entity { .. } => "entity",
+                Atom::Instantiate { .. } => "instantiate",
                 Atom::Lookahead { .. } => "lookahead",
+                Atom::Peek { .. } => "peek",
+                Atom::Infix { .. } => "infix",
                 Atom::Cut => "cut",
+                Atom::Eof => "eof",
+                Atom::Indent => "indent",
+                Atom::Samedent => "samedent",
+                Atom::Dedent => "dedent",
                 Atom::Ignore { .. } => "ignore",
+                Atom::Text { .. } => "text",
                 Atom::Capture { .. } => "capture",
+                Atom::BackRef { .. } => "backref",
+                Atom::OptionalDefault { .. } => "optional_default",
+                Atom::ParseInt { .. } => "parse_int",
+                Atom::ParseFloat { .. } => "parse_float",
+                Atom::Const { .. } => "const",
                 Atom::Scope { .. } => "scope",
                 Atom::Dynamic { .. } => "dynamic",
                 Atom::Custom { .. } => "custom",
@@ -274,7 +937,7 @@ impl Grammar {
             has_repetitions: self
                 .atoms
                 .iter()
-                .any(|a| matches!(a, Atom::Repetition { .. })),
+                .any(|a| matches!(a, Atom::Repetition { .. } | Atom::RepetitionLazy { .. })),
             has_lookaheads: self
                 .atoms
                 .iter()
@@ -314,6 +977,54 @@ impl Grammar {
         GrammarAnalyzer::new(self).analyze()
     }
 
+    /// Pre-compile every regex atom into the thread-local regex cache
+    ///
+    /// Regex atoms are normally compiled lazily on first use, so the first
+    /// parse with a new grammar pays the compilation cost. Calling this after
+    /// loading a grammar (e.g. at server startup) moves that cost out of the
+    /// critical path. Patterns handled by the character-class fast path are
+    /// skipped since they never go through the regex engine.
+    ///
+    /// For [`ascii_only`](Self::ascii_only) grammars each pattern is also
+    /// compiled for the byte-oriented engine, which parses of ASCII input
+    /// use instead.
+    ///
+    /// The cache is thread-local, so this warms the cache of the calling
+    /// thread only. Returns the number of patterns that were compiled
+    /// successfully (including ones already cached).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parsanol::portable::parser_dsl::{GrammarBuilder, re};
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule("number", re("[0-9]+"))
+    ///     .build();
+    ///
+    /// assert_eq!(grammar.warm_regex_cache(), 1);
+    /// ```
+    pub fn warm_regex_cache(&self) -> usize {
+        let mut seen = std::collections::HashSet::new();
+        self.atoms
+            .iter()
+            .filter_map(|atom| match atom {
+                Atom::Re { pattern } => Some(pattern.as_str()),
+                _ => None,
+            })
+            .filter(|pattern| CharacterPattern::from_pattern(pattern).is_none())
+            .filter(|pattern| seen.insert(*pattern))
+            .filter(|pattern| {
+                if self.ascii_only {
+                    // `None` for patterns that need Unicode mode, which
+                    // always use the Unicode engine
+                    regex_cache::get_or_compile_bytes(pattern);
+                }
+                regex_cache::get_or_compile(pattern).is_some()
+            })
+            .count()
+    }
+
     /// One-shot parse convenience method
     ///
     /// Creates an arena and parser internally, parses the input, and returns the AST.
@@ -490,6 +1201,47 @@ impl Default for Grammar {
     }
 }
 
+/// Whether re-matching an atom costs about as much as a cache lookup
+fn is_cheap_leaf(atom: &Atom) -> bool {
+    match atom {
+        Atom::Str { .. }
+        | Atom::StrCi { .. }
+        | Atom::CharRange { .. }
+        | Atom::OneOf { .. }
+        | Atom::NoneOf { .. }
+        | Atom::ByteRange { .. }
+        | Atom::Cut
+        | Atom::Eof => true,
+        Atom::Re { pattern } => is_single_char_regex(pattern),
+        _ => false,
+    }
+}
+
+/// Whether a regex is one literal character, escape, `.` or bracket class
+fn is_single_char_regex(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(c), None, _) => !"\\^$|()[]{}*+?".contains(c),
+        (Some('\\'), Some(_), None) => true,
+        (Some('['), ..) => {
+            let body = pattern[1..].strip_prefix('^').unwrap_or(&pattern[1..]);
+            let mut escaped = false;
+            for (i, c) in body.char_indices() {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    // A `]` first in the class is a literal
+                    ']' if i > 0 => return i + 1 == body.len(),
+                    '[' => return false,
+                    _ => {}
+                }
+            }
+            false
+        }
+        _ => false,
+    }
+}
+
 /// Result of grammar analysis
 pub struct GrammarAnalysis {
     /// Total number of atoms
@@ -551,9 +1303,27 @@ pub trait AtomVisitor {
     /// Visit a string atom
     fn visit_str(&mut self, _pattern: &str) {}
 
+    /// Visit a case-insensitive string atom
+    fn visit_str_ci(&mut self, _pattern: &str) {}
+
     /// Visit a regex atom
     fn visit_re(&mut self, _pattern: &str) {}
 
+    /// Visit a character range atom
+    fn visit_char_range(&mut self, _lo: char, _hi: char) {}
+
+    /// Visit a character set atom
+    fn visit_one_of(&mut self, _chars: &[char]) {}
+
+    /// Visit a negated character set atom
+    fn visit_none_of(&mut self, _chars: &[char]) {}
+
+    /// Visit a scan-to-terminator atom
+    fn visit_until(&mut self, _terminator: &str) {}
+
+    /// Visit a byte range atom
+    fn visit_byte_range(&mut self, _lo: u8, _hi: u8) {}
+
     /// Visit a sequence atom (called before visiting children)
     fn visit_sequence_pre(&mut self, _atoms: &[usize]) {}
 
@@ -566,12 +1336,38 @@ pub trait AtomVisitor {
     /// Visit an alternative atom (called after visiting children)
     fn visit_alternative_post(&mut self, _atoms: &[usize]) {}
 
+    /// Visit a longest-match alternative atom (called before visiting children)
+    fn visit_longest_alternative_pre(&mut self, _atoms: &[usize]) {}
+
+    /// Visit a longest-match alternative atom (called after visiting children)
+    fn visit_longest_alternative_post(&mut self, _atoms: &[usize]) {}
+
     /// Visit a repetition atom (called before visiting child)
     fn visit_repetition_pre(&mut self, _atom: usize, _min: usize, _max: Option<usize>) {}
 
     /// Visit a repetition atom (called after visiting child)
     fn visit_repetition_post(&mut self, _atom: usize, _min: usize, _max: Option<usize>) {}
 
+    /// Visit a lazy repetition atom (called before visiting children)
+    fn visit_repetition_lazy_pre(
+        &mut self,
+        _atom: usize,
+        _min: usize,
+        _max: Option<usize>,
+        _follow: usize,
+    ) {
+    }
+
+    /// Visit a lazy repetition atom (called after visiting children)
+    fn visit_repetition_lazy_post(
+        &mut self,
+        _atom: usize,
+        _min: usize,
+        _max: Option<usize>,
+        _follow: usize,
+    ) {
+    }
+
     /// Visit a named atom (called before visiting child)
     fn visit_named_pre(&mut self, _name: &str, _atom: usize) {}
 
@@ -581,27 +1377,87 @@ pub trait AtomVisitor {
     /// Visit an entity reference
     fn visit_entity(&mut self, _atom: usize) {}
 
+    /// Visit an unresolved template instantiation
+    fn visit_instantiate(&mut self, _template: &str, _args: &[usize]) {}
+
     /// Visit a lookahead atom (called before visiting child)
     fn visit_lookahead_pre(&mut self, _atom: usize, _positive: bool) {}
 
     /// Visit a lookahead atom (called after visiting child)
     fn visit_lookahead_post(&mut self, _atom: usize, _positive: bool) {}
 
+    /// Visit a peek atom (called before visiting child)
+    fn visit_peek_pre(&mut self, _atom: usize) {}
+
+    /// Visit a peek atom (called after visiting child)
+    fn visit_peek_post(&mut self, _atom: usize) {}
+
+    /// Visit an infix atom (called before visiting the operand)
+    fn visit_infix_pre(&mut self, _operand: usize, _operators: &[InfixOperator]) {}
+
+    /// Visit an infix atom (called after visiting the operand)
+    fn visit_infix_post(&mut self, _operand: usize, _operators: &[InfixOperator]) {}
+
     /// Visit a cut atom
     fn visit_cut(&mut self) {}
 
+    /// Visit an end-of-input atom
+    fn visit_eof(&mut self) {}
+
+    /// Visit an indent atom
+    fn visit_indent(&mut self) {}
+
+    /// Visit a same-indentation atom
+    fn visit_samedent(&mut self) {}
+
+    /// Visit a dedent atom
+    fn visit_dedent(&mut self) {}
+
     /// Visit an ignore atom (called before visiting child)
     fn visit_ignore_pre(&mut self, _atom: usize) {}
 
     /// Visit an ignore atom (called after visiting child)
     fn visit_ignore_post(&mut self, _atom: usize) {}
 
+    /// Visit a matched-text atom (called before visiting child)
+    fn visit_text_pre(&mut self, _atom: usize) {}
+
+    /// Visit a matched-text atom (called after visiting child)
+    fn visit_text_post(&mut self, _atom: usize) {}
+
     /// Visit a capture atom (called before visiting child)
     fn visit_capture_pre(&mut self, _name: &str, _atom: usize) {}
 
     /// Visit a capture atom (called after visiting child)
     fn visit_capture_post(&mut self, _name: &str, _atom: usize) {}
 
+    /// Visit a back-reference atom
+    fn visit_backref(&mut self, _name: &str) {}
+
+    /// Visit an optional-with-default atom (called before visiting child)
+    fn visit_optional_default_pre(&mut self, _name: &str, _atom: usize) {}
+
+    /// Visit an optional-with-default atom (called after visiting child)
+    fn visit_optional_default_post(&mut self, _name: &str, _atom: usize) {}
+
+    /// Visit an integer-parsing atom (called before visiting child)
+    fn visit_parse_int_pre(&mut self, _atom: usize, _radix_prefixes: bool) {}
+
+    /// Visit an integer-parsing atom (called after visiting child)
+    fn visit_parse_int_post(&mut self, _atom: usize, _radix_prefixes: bool) {}
+
+    /// Visit a float-parsing atom (called before visiting child)
+    fn visit_parse_float_pre(&mut self, _atom: usize) {}
+
+    /// Visit a float-parsing atom (called after visiting child)
+    fn visit_parse_float_post(&mut self, _atom: usize) {}
+
+    /// Visit a constant-value atom (called before visiting child)
+    fn visit_const_pre(&mut self, _atom: usize, _value: DefaultValue) {}
+
+    /// Visit a constant-value atom (called after visiting child)
+    fn visit_const_post(&mut self, _atom: usize, _value: DefaultValue) {}
+
     /// Visit a scope atom (called before visiting child)
     fn visit_scope_pre(&mut self, _atom: usize) {}
 
@@ -631,9 +1487,27 @@ impl Grammar {
                 Atom::Str { pattern } => {
                     visitor.visit_str(pattern);
                 }
+                Atom::StrCi { pattern } => {
+                    visitor.visit_str_ci(pattern);
+                }
                 Atom::Re { pattern } => {
                     visitor.visit_re(pattern);
                 }
+                Atom::CharRange { lo, hi } => {
+                    visitor.visit_char_range(*lo, *hi);
+                }
+                Atom::OneOf { chars } => {
+                    visitor.visit_one_of(chars);
+                }
+                Atom::NoneOf { chars } => {
+                    visitor.visit_none_of(chars);
+                }
+                Atom::Until { terminator, .. } => {
+                    visitor.visit_until(terminator);
+                }
+                Atom::ByteRange { lo, hi } => {
+                    visitor.visit_byte_range(*lo, *hi);
+                }
                 Atom::Sequence { atoms } => {
                     visitor.visit_sequence_pre(atoms);
                     for &child_idx in atoms {
@@ -648,11 +1522,29 @@ impl Grammar {
                     }
                     visitor.visit_alternative_post(atoms);
                 }
+                Atom::LongestAlternative { atoms } => {
+                    visitor.visit_longest_alternative_pre(atoms);
+                    for &child_idx in atoms {
+                        self.visit_atom(child_idx, visitor);
+                    }
+                    visitor.visit_longest_alternative_post(atoms);
+                }
                 Atom::Repetition { atom, min, max } => {
                     visitor.visit_repetition_pre(*atom, *min, *max);
                     self.visit_atom(*atom, visitor);
                     visitor.visit_repetition_post(*atom, *min, *max);
                 }
+                Atom::RepetitionLazy {
+                    atom,
+                    min,
+                    max,
+                    follow,
+                } => {
+                    visitor.visit_repetition_lazy_pre(*atom, *min, *max, *follow);
+                    self.visit_atom(*atom, visitor);
+                    self.visit_atom(*follow, visitor);
+                    visitor.visit_repetition_lazy_post(*atom, *min, *max, *follow);
+                }
                 Atom::Named { name, atom } => {
                     visitor.visit_named_pre(name, *atom);
                     self.visit_atom(*atom, visitor);
@@ -663,24 +1555,80 @@ impl Grammar {
                     // Note: We don't recursively visit entity targets to avoid infinite loops
                     // If you need to visit all reachable atoms, use visit_atoms_reachable instead
                 }
+                Atom::Instantiate { template, args } => {
+                    visitor.visit_instantiate(template, args);
+                }
                 Atom::Lookahead { atom, positive } => {
                     visitor.visit_lookahead_pre(*atom, *positive);
                     self.visit_atom(*atom, visitor);
                     visitor.visit_lookahead_post(*atom, *positive);
                 }
+                Atom::Peek { atom } => {
+                    visitor.visit_peek_pre(*atom);
+                    self.visit_atom(*atom, visitor);
+                    visitor.visit_peek_post(*atom);
+                }
+                Atom::Infix { operand, operators } => {
+                    visitor.visit_infix_pre(*operand, operators);
+                    self.visit_atom(*operand, visitor);
+                    visitor.visit_infix_post(*operand, operators);
+                }
                 Atom::Cut => {
                     visitor.visit_cut();
                 }
+                Atom::Eof => {
+                    visitor.visit_eof();
+                }
+                Atom::Indent => {
+                    visitor.visit_indent();
+                }
+                Atom::Samedent => {
+                    visitor.visit_samedent();
+                }
+                Atom::Dedent => {
+                    visitor.visit_dedent();
+                }
                 Atom::Ignore { atom } => {
                     visitor.visit_ignore_pre(*atom);
                     self.visit_atom(*atom, visitor);
                     visitor.visit_ignore_post(*atom);
                 }
+                Atom::Text { atom } => {
+                    visitor.visit_text_pre(*atom);
+                    self.visit_atom(*atom, visitor);
+                    visitor.visit_text_post(*atom);
+                }
                 Atom::Capture { name, atom } => {
                     visitor.visit_capture_pre(name, *atom);
                     self.visit_atom(*atom, visitor);
                     visitor.visit_capture_post(name, *atom);
                 }
+                Atom::BackRef { name } => {
+                    visitor.visit_backref(name);
+                }
+                Atom::OptionalDefault { name, atom, .. } => {
+                    visitor.visit_optional_default_pre(name, *atom);
+                    self.visit_atom(*atom, visitor);
+                    visitor.visit_optional_default_post(name, *atom);
+                }
+                Atom::ParseInt {
+                    atom,
+                    radix_prefixes,
+                } => {
+                    visitor.visit_parse_int_pre(*atom, *radix_prefixes);
+                    self.visit_atom(*atom, visitor);
+                    visitor.visit_parse_int_post(*atom, *radix_prefixes);
+                }
+                Atom::ParseFloat { atom } => {
+                    visitor.visit_parse_float_pre(*atom);
+                    self.visit_atom(*atom, visitor);
+                    visitor.visit_parse_float_post(*atom);
+                }
+                Atom::Const { atom, value } => {
+                    visitor.visit_const_pre(*atom, *value);
+                    self.visit_atom(*atom, visitor);
+                    visitor.visit_const_post(*atom, *value);
+                }
                 Atom::Scope { atom } => {
                     visitor.visit_scope_pre(*atom);
                     self.visit_atom(*atom, visitor);
@@ -702,26 +1650,68 @@ impl Grammar {
 pub struct AtomTypeCounter {
     /// Count of string atoms
     pub str_count: usize,
+    /// Count of case-insensitive string atoms
+    pub str_ci_count: usize,
     /// Count of regex atoms
     pub re_count: usize,
+    /// Count of character range atoms
+    pub char_range_count: usize,
+    /// Count of character set atoms
+    pub one_of_count: usize,
+    /// Count of negated character set atoms
+    pub none_of_count: usize,
+    /// Count of scan-to-terminator atoms
+    pub until_count: usize,
+    /// Count of byte range atoms
+    pub byte_range_count: usize,
     /// Count of sequence atoms
     pub sequence_count: usize,
     /// Count of alternative atoms
     pub alternative_count: usize,
+    /// Count of longest-match alternative atoms
+    pub longest_alternative_count: usize,
     /// Count of repetition atoms
     pub repetition_count: usize,
+    /// Count of lazy repetition atoms
+    pub repetition_lazy_count: usize,
     /// Count of named atoms
     pub named_count: usize,
     /// Count of entity atoms
     pub entity_count: usize,
+    /// Count of unresolved template instantiations
+    pub instantiate_count: usize,
     /// Count of lookahead atoms
     pub lookahead_count: usize,
+    /// Count of peek atoms
+    pub peek_count: usize,
+    /// Count of infix atoms
+    pub infix_count: usize,
     /// Count of cut atoms
     pub cut_count: usize,
+    /// Count of end-of-input atoms
+    pub eof_count: usize,
+    /// Count of indent atoms
+    pub indent_count: usize,
+    /// Count of same-indentation atoms
+    pub samedent_count: usize,
+    /// Count of dedent atoms
+    pub dedent_count: usize,
     /// Count of ignore atoms
     pub ignore_count: usize,
+    /// Count of matched-text atoms
+    pub text_count: usize,
     /// Count of capture atoms
     pub capture_count: usize,
+    /// Count of back-reference atoms
+    pub backref_count: usize,
+    /// Count of optional-with-default atoms
+    pub optional_default_count: usize,
+    /// Count of integer-parsing atoms
+    pub parse_int_count: usize,
+    /// Count of float-parsing atoms
+    pub parse_float_count: usize,
+    /// Count of constant-value atoms
+    pub const_count: usize,
     /// Count of scope atoms
     pub scope_count: usize,
     /// Count of dynamic atoms
@@ -735,10 +1725,34 @@ impl AtomVisitor for AtomTypeCounter {
         self.str_count += 1;
     }
 
+    fn visit_str_ci(&mut self, _pattern: &str) {
+        self.str_ci_count += 1;
+    }
+
     fn visit_re(&mut self, _pattern: &str) {
         self.re_count += 1;
     }
 
+    fn visit_char_range(&mut self, _lo: char, _hi: char) {
+        self.char_range_count += 1;
+    }
+
+    fn visit_one_of(&mut self, _chars: &[char]) {
+        self.one_of_count += 1;
+    }
+
+    fn visit_none_of(&mut self, _chars: &[char]) {
+        self.none_of_count += 1;
+    }
+
+    fn visit_until(&mut self, _terminator: &str) {
+        self.until_count += 1;
+    }
+
+    fn visit_byte_range(&mut self, _lo: u8, _hi: u8) {
+        self.byte_range_count += 1;
+    }
+
     fn visit_sequence_pre(&mut self, _atoms: &[usize]) {
         self.sequence_count += 1;
     }
@@ -747,10 +1761,24 @@ impl AtomVisitor for AtomTypeCounter {
         self.alternative_count += 1;
     }
 
+    fn visit_longest_alternative_pre(&mut self, _atoms: &[usize]) {
+        self.longest_alternative_count += 1;
+    }
+
     fn visit_repetition_pre(&mut self, _atom: usize, _min: usize, _max: Option<usize>) {
         self.repetition_count += 1;
     }
 
+    fn visit_repetition_lazy_pre(
+        &mut self,
+        _atom: usize,
+        _min: usize,
+        _max: Option<usize>,
+        _follow: usize,
+    ) {
+        self.repetition_lazy_count += 1;
+    }
+
     fn visit_named_pre(&mut self, _name: &str, _atom: usize) {
         self.named_count += 1;
     }
@@ -759,22 +1787,74 @@ impl AtomVisitor for AtomTypeCounter {
         self.entity_count += 1;
     }
 
+    fn visit_instantiate(&mut self, _template: &str, _args: &[usize]) {
+        self.instantiate_count += 1;
+    }
+
     fn visit_lookahead_pre(&mut self, _atom: usize, _positive: bool) {
         self.lookahead_count += 1;
     }
 
+    fn visit_peek_pre(&mut self, _atom: usize) {
+        self.peek_count += 1;
+    }
+
+    fn visit_infix_pre(&mut self, _operand: usize, _operators: &[InfixOperator]) {
+        self.infix_count += 1;
+    }
+
     fn visit_cut(&mut self) {
         self.cut_count += 1;
     }
 
+    fn visit_eof(&mut self) {
+        self.eof_count += 1;
+    }
+
+    fn visit_indent(&mut self) {
+        self.indent_count += 1;
+    }
+
+    fn visit_samedent(&mut self) {
+        self.samedent_count += 1;
+    }
+
+    fn visit_dedent(&mut self) {
+        self.dedent_count += 1;
+    }
+
     fn visit_ignore_pre(&mut self, _atom: usize) {
         self.ignore_count += 1;
     }
 
+    fn visit_text_pre(&mut self, _atom: usize) {
+        self.text_count += 1;
+    }
+
     fn visit_capture_pre(&mut self, _name: &str, _atom: usize) {
         self.capture_count += 1;
     }
 
+    fn visit_backref(&mut self, _name: &str) {
+        self.backref_count += 1;
+    }
+
+    fn visit_optional_default_pre(&mut self, _name: &str, _atom: usize) {
+        self.optional_default_count += 1;
+    }
+
+    fn visit_parse_int_pre(&mut self, _atom: usize, _radix_prefixes: bool) {
+        self.parse_int_count += 1;
+    }
+
+    fn visit_parse_float_pre(&mut self, _atom: usize) {
+        self.parse_float_count += 1;
+    }
+
+    fn visit_const_pre(&mut self, _atom: usize, _value: DefaultValue) {
+        self.const_count += 1;
+    }
+
     fn visit_scope_pre(&mut self, _atom: usize) {
         self.scope_count += 1;
     }
@@ -816,6 +1896,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_warm_regex_cache() {
+        let mut grammar = Grammar::new();
+        grammar.add_atom(Atom::Re {
+            pattern: "[0-9]+".to_string(),
+        });
+        grammar.add_atom(Atom::Re {
+            pattern: "[a-z_]+".to_string(),
+        });
+        // Char-class fast path, never compiled
+        grammar.add_atom(Atom::Re {
+            pattern: "[0-9]".to_string(),
+        });
+        // Duplicate pattern only counted once
+        grammar.add_atom(Atom::Re {
+            pattern: "[0-9]+".to_string(),
+        });
+
+        regex_cache::clear_cache();
+        assert_eq!(grammar.warm_regex_cache(), 2);
+
+        let stats = regex_cache::stats();
+        assert_eq!(stats.size, 2);
+        assert_eq!(stats.misses, 2);
+
+        // Parsing afterwards hits the warmed cache
+        assert!(regex_cache::get_or_compile("[0-9]+").is_some());
+        assert!(regex_cache::get_or_compile("[a-z_]+").is_some());
+        assert_eq!(regex_cache::stats().hits, 2);
+
+        // ASCII-only grammars warm the byte-mode cache too
+        grammar.ascii_only = true;
+        regex_cache::clear_cache();
+        assert_eq!(grammar.warm_regex_cache(), 2);
+        assert_eq!(regex_cache::stats().size, 4);
+        assert!(regex_cache::get_or_compile_bytes("[0-9]+").is_some());
+        assert!(regex_cache::get_or_compile_bytes("[a-z_]+").is_some());
+        assert_eq!(regex_cache::stats().hits, 2);
+    }
+
     #[test]
     fn test_grammar_json_roundtrip() {
         let mut grammar = Grammar::new();
@@ -850,4 +1970,22 @@ mod tests {
         assert!(analysis.has_repetitions);
         assert!(!analysis.has_lookaheads);
     }
+
+    #[test]
+    fn test_single_char_regex() {
+        for pattern in ["a", ".", "\\d", "\\.", "[a-z]", "[^\"]", "[]a]", "[\\]x]"] {
+            assert!(is_single_char_regex(pattern), "{}", pattern);
+        }
+        for pattern in [
+            "ab",
+            "*",
+            "[a-z]+",
+            "[0-9][0-9]",
+            "\\d+",
+            "[[:alpha:]]",
+            "[abc",
+        ] {
+            assert!(!is_single_char_regex(pattern), "{}", pattern);
+        }
+    }
 }
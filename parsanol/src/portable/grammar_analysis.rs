@@ -6,6 +6,10 @@
 //! - Unused atoms
 //! - Excessive backtracking potential
 //!
+//! It can also produce a [`ComplexityReport`] rating the grammar's
+//! worst-case parse cost, for reviewing grammars before exposing them to
+//! untrusted input.
+//!
 //! # Example
 //!
 //! ```
@@ -21,7 +25,9 @@
 //! }
 //! ```
 
+use crate::portable::char_class::CharacterPattern;
 use crate::portable::grammar::{Atom, Grammar};
+use crate::portable::infix::Fixity;
 use std::collections::{HashMap, HashSet};
 
 /// Kind of grammar warning
@@ -38,6 +44,14 @@ pub enum WarningKind {
     /// Example: `str("a") | str("a")` - second "a" is unreachable
     UnreachableAlternative,
 
+    /// An earlier alternative can start with every character a later one can
+    ///
+    /// The earlier alternative is tried first and will often match the
+    /// later one's input, hiding it. This is a FIRST-set approximation, so
+    /// it may also fire when the earlier alternative fails further in.
+    /// Example: `re("[a-z]+") | str("for")` - the regex matches "for"
+    ShadowedAlternative,
+
     /// An atom is defined but never referenced
     ///
     /// This may indicate dead code or a typo in the grammar.
@@ -68,6 +82,7 @@ impl std::fmt::Display for WarningKind {
         match self {
             Self::LeftRecursion => write!(f, "left recursion"),
             Self::UnreachableAlternative => write!(f, "unreachable alternative"),
+            Self::ShadowedAlternative => write!(f, "shadowed alternative"),
             Self::UnusedAtom => write!(f, "unused atom"),
             Self::ExcessiveBacktracking => write!(f, "excessive backtracking"),
             Self::EmptyComposite => write!(f, "empty composite"),
@@ -118,11 +133,195 @@ impl std::fmt::Display for GrammarWarning {
     }
 }
 
+/// Qualitative worst-case parse cost, ordered from cheapest to most expensive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ComplexityRating {
+    /// No construct known to cause super-linear backtracking
+    Linear,
+    /// Some input may be re-scanned once per position (typically quadratic)
+    Polynomial,
+    /// The number of ways to match can grow exponentially with input length
+    Exponential,
+}
+
+impl std::fmt::Display for ComplexityRating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Linear => write!(f, "linear"),
+            Self::Polynomial => write!(f, "polynomial"),
+            Self::Exponential => write!(f, "exponential"),
+        }
+    }
+}
+
+/// A construct contributing to a [`ComplexityReport`]
+#[derive(Debug, Clone)]
+pub struct ComplexityHotspot {
+    /// Worst-case cost this construct can cause on its own
+    pub rating: ComplexityRating,
+    /// Where and why, as an [`WarningKind::ExcessiveBacktracking`] warning
+    pub warning: GrammarWarning,
+}
+
+/// Static estimate of a grammar's worst-case parse complexity
+///
+/// Produced by [`GrammarAnalyzer::complexity_report`]. The analysis is
+/// conservative: it flags constructs that *can* backtrack badly, which does
+/// not mean every input will trigger them.
+#[derive(Debug, Clone)]
+pub struct ComplexityReport {
+    /// Overall rating: the worst rating among the hotspots
+    pub rating: ComplexityRating,
+    /// The constructs responsible for the rating
+    pub hotspots: Vec<ComplexityHotspot>,
+}
+
+/// Bytes an atom may consume first
+///
+/// `any` means the first byte cannot be predicted (regexes, custom atoms),
+/// so the set overlaps every non-empty set.
+#[derive(Debug, Clone, Default)]
+struct FirstSet {
+    bytes: [u64; 4],
+    any: bool,
+}
+
+impl FirstSet {
+    fn any() -> Self {
+        Self {
+            any: true,
+            ..Self::default()
+        }
+    }
+
+    fn insert(&mut self, b: u8) {
+        self.bytes[(b / 64) as usize] |= 1 << (b % 64);
+    }
+
+    fn union(&mut self, other: &FirstSet) {
+        for (a, b) in self.bytes.iter_mut().zip(other.bytes) {
+            *a |= b;
+        }
+        self.any |= other.any;
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.any && self.bytes.iter().all(|&w| w == 0)
+    }
+
+    /// First bytes of a regex that starts with a required ASCII element
+    ///
+    /// Understands a leading literal, `\d`/`\w`/`\s`, or a bracket class
+    /// of ASCII characters and ranges, not followed by `*`, `?` or `{0`.
+    /// Returns `None` for anything else, including alternations, so the
+    /// caller can fall back to [`FirstSet::any`].
+    fn of_regex(pattern: &str) -> Option<Self> {
+        let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+        if pattern.contains('|') {
+            return None;
+        }
+
+        let mut first = Self::default();
+        let bytes = pattern.as_bytes();
+        let rest = match *bytes.first()? {
+            b'[' => {
+                let mut i = 1;
+                if bytes.get(i) == Some(&b'^') {
+                    return None;
+                }
+                loop {
+                    let lo = match *bytes.get(i)? {
+                        b']' if i > 1 => break,
+                        b'\\' => {
+                            i += 1;
+                            if first.insert_escape(*bytes.get(i)?) {
+                                i += 1;
+                                continue;
+                            }
+                            bytes[i]
+                        }
+                        b => b,
+                    };
+                    if !lo.is_ascii() {
+                        return None;
+                    }
+                    match (bytes.get(i + 1), bytes.get(i + 2)) {
+                        (Some(b'-'), Some(&hi)) if hi != b']' && hi != b'\\' && hi.is_ascii() => {
+                            for b in lo..=hi {
+                                first.insert(b);
+                            }
+                            i += 3;
+                        }
+                        _ => {
+                            first.insert(lo);
+                            i += 1;
+                        }
+                    }
+                }
+                &bytes[i + 1..]
+            }
+            b'\\' => {
+                let escaped = *bytes.get(1)?;
+                if !first.insert_escape(escaped) {
+                    if escaped.is_ascii_alphanumeric() || !escaped.is_ascii() {
+                        return None;
+                    }
+                    first.insert(escaped);
+                }
+                &bytes[2..]
+            }
+            b if b.is_ascii() && !b"().*+?{}$".contains(&b) => {
+                first.insert(b);
+                &bytes[1..]
+            }
+            _ => return None,
+        };
+
+        // The element may be skipped, so later ones could come first
+        match rest {
+            [b'*' | b'?', ..] | [b'{', b'0', ..] => None,
+            _ => Some(first),
+        }
+    }
+
+    /// Insert the bytes of a `\d`, `\w` or `\s` class, if `escape` is one
+    fn insert_escape(&mut self, escape: u8) -> bool {
+        let class = match escape {
+            b'd' => CharacterPattern::Digit,
+            b'w' => CharacterPattern::Word,
+            b's' => CharacterPattern::Space,
+            _ => return false,
+        };
+        for b in 0..=u8::MAX {
+            if class.matches(b) {
+                self.insert(b);
+            }
+        }
+        true
+    }
+
+    /// Whether every byte of `other` is known to be in `self`
+    ///
+    /// Always false when either set is unpredictable.
+    fn is_superset_of(&self, other: &FirstSet) -> bool {
+        !self.any && !other.any && self.bytes.iter().zip(other.bytes).all(|(a, b)| a & b == b)
+    }
+
+    fn intersects(&self, other: &FirstSet) -> bool {
+        if self.is_empty() || other.is_empty() {
+            return false;
+        }
+        self.any || other.any || self.bytes.iter().zip(other.bytes).any(|(a, b)| a & b != 0)
+    }
+}
+
 /// Grammar analyzer
 pub struct GrammarAnalyzer<'a> {
     grammar: &'a Grammar,
     /// Cache of nullable atoms (can match empty string)
     nullable: HashMap<usize, bool>,
+    /// Cache of FIRST sets
+    first_sets: HashMap<usize, FirstSet>,
 }
 
 impl<'a> GrammarAnalyzer<'a> {
@@ -131,6 +330,7 @@ impl<'a> GrammarAnalyzer<'a> {
         Self {
             grammar,
             nullable: HashMap::new(),
+            first_sets: HashMap::new(),
         }
     }
 
@@ -145,6 +345,7 @@ impl<'a> GrammarAnalyzer<'a> {
         self.detect_useless_repetitions(&mut warnings);
         self.detect_infinite_loops(&mut warnings);
         self.detect_unreachable_alternatives(&mut warnings);
+        self.detect_shadowed_alternatives(&mut warnings);
         self.detect_excessive_backtracking(&mut warnings);
 
         warnings
@@ -221,7 +422,7 @@ impl<'a> GrammarAnalyzer<'a> {
                 }
                 None
             }
-            Atom::Alternative { atoms } => {
+            Atom::Alternative { atoms } | Atom::LongestAlternative { atoms } => {
                 for &child in atoms {
                     if child == target_atom {
                         return Some(vec![start_atom, child]);
@@ -235,7 +436,16 @@ impl<'a> GrammarAnalyzer<'a> {
                 }
                 None
             }
-            Atom::Named { atom, .. } | Atom::Ignore { atom } | Atom::Lookahead { atom, .. } => {
+            Atom::Named { atom, .. }
+            | Atom::OptionalDefault { atom, .. }
+            | Atom::Ignore { atom }
+            | Atom::Text { atom }
+            | Atom::ParseInt { atom, .. }
+            | Atom::ParseFloat { atom }
+            | Atom::Const { atom, .. }
+            | Atom::Lookahead { atom, .. }
+            | Atom::Peek { atom }
+            | Atom::Infix { operand: atom, .. } => {
                 if *atom == target_atom {
                     Some(vec![start_atom, *atom])
                 } else if !visited.contains(atom) {
@@ -248,7 +458,7 @@ impl<'a> GrammarAnalyzer<'a> {
                     None
                 }
             }
-            Atom::Repetition { atom, min, .. } => {
+            Atom::Repetition { atom, min, .. } | Atom::RepetitionLazy { atom, min, .. } => {
                 if *min > 0 {
                     if *atom == target_atom {
                         return Some(vec![start_atom, *atom]);
@@ -267,15 +477,164 @@ impl<'a> GrammarAnalyzer<'a> {
                 }
             }
             Atom::Str { .. }
+            | Atom::StrCi { .. }
             | Atom::Re { .. }
+            | Atom::CharRange { .. }
+            | Atom::OneOf { .. }
+            | Atom::NoneOf { .. }
+            | Atom::Until { .. }
+            | Atom::ByteRange { .. }
             | Atom::Cut
+            | Atom::Eof
+            | Atom::Indent
+            | Atom::Samedent
+            | Atom::Dedent
             | Atom::Custom { .. }
             | Atom::Capture { .. }
+            | Atom::BackRef { .. }
             | Atom::Scope { .. }
+            | Atom::Instantiate { .. }
             | Atom::Dynamic { .. } => None,
         }
     }
 
+    /// Find all atoms that take part in a left-recursive cycle
+    ///
+    /// An atom is left-recursive when it can be re-entered at the same input
+    /// position without consuming anything. The result is indexed by atom ID.
+    /// Unlike [`analyze`](Self::analyze), this runs in time linear in the
+    /// grammar size (a strongly-connected-components pass over the
+    /// "leftmost call" graph), so it is cheap enough to run before parsing.
+    pub fn left_recursive_atoms(&mut self) -> Vec<bool> {
+        let n = self.grammar.atoms.len();
+        let edges: Vec<Vec<usize>> = (0..n)
+            .map(|atom_id| {
+                self.leftmost_children(atom_id)
+                    .into_iter()
+                    .filter(|&child| child < n)
+                    .collect()
+            })
+            .collect();
+
+        // Tarjan's SCC algorithm, iterative to avoid deep native recursion
+        const UNVISITED: usize = usize::MAX;
+        let mut index = vec![UNVISITED; n];
+        let mut lowlink = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut next_index = 0;
+        let mut recursive = vec![false; n];
+
+        for start in 0..n {
+            if index[start] != UNVISITED {
+                continue;
+            }
+            // (node, next edge to explore)
+            let mut work = vec![(start, 0)];
+            index[start] = next_index;
+            lowlink[start] = next_index;
+            next_index += 1;
+            stack.push(start);
+            on_stack[start] = true;
+
+            while let Some(&(node, edge)) = work.last() {
+                if let Some(&child) = edges[node].get(edge) {
+                    if let Some(top) = work.last_mut() {
+                        top.1 += 1;
+                    }
+                    if index[child] == UNVISITED {
+                        index[child] = next_index;
+                        lowlink[child] = next_index;
+                        next_index += 1;
+                        stack.push(child);
+                        on_stack[child] = true;
+                        work.push((child, 0));
+                    } else if on_stack[child] {
+                        lowlink[node] = lowlink[node].min(index[child]);
+                    }
+                    continue;
+                }
+
+                work.pop();
+                if let Some(&(parent, _)) = work.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[node]);
+                }
+                if lowlink[node] == index[node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    let cyclic = component.len() > 1 || edges[node].contains(&node);
+                    if cyclic {
+                        for member in component {
+                            recursive[member] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        recursive
+    }
+
+    /// Children an atom may invoke at its own start position
+    fn leftmost_children(&mut self, atom_id: usize) -> Vec<usize> {
+        let Some(atom) = self.grammar.get_atom(atom_id) else {
+            return Vec::new();
+        };
+
+        match atom {
+            Atom::Sequence { atoms } => {
+                let atoms = atoms.clone();
+                let mut children = Vec::new();
+                for child in atoms {
+                    children.push(child);
+                    if !self.is_nullable(child) {
+                        break;
+                    }
+                }
+                children
+            }
+            Atom::Alternative { atoms } | Atom::LongestAlternative { atoms } => atoms.clone(),
+            Atom::RepetitionLazy { atom, follow, .. } => vec![*atom, *follow],
+            Atom::Repetition { atom, .. }
+            | Atom::Named { atom, .. }
+            | Atom::Entity { atom }
+            | Atom::Ignore { atom }
+            | Atom::Text { atom }
+            | Atom::ParseInt { atom, .. }
+            | Atom::ParseFloat { atom }
+            | Atom::Const { atom, .. }
+            | Atom::Lookahead { atom, .. }
+            | Atom::Peek { atom }
+            | Atom::Infix { operand: atom, .. }
+            | Atom::Capture { atom, .. }
+            | Atom::OptionalDefault { atom, .. }
+            | Atom::Scope { atom } => vec![*atom],
+            Atom::Str { .. }
+            | Atom::StrCi { .. }
+            | Atom::Re { .. }
+            | Atom::CharRange { .. }
+            | Atom::OneOf { .. }
+            | Atom::NoneOf { .. }
+            | Atom::Until { .. }
+            | Atom::ByteRange { .. }
+            | Atom::Cut
+            | Atom::Eof
+            | Atom::Indent
+            | Atom::Samedent
+            | Atom::Dedent
+            | Atom::Custom { .. }
+            | Atom::BackRef { .. }
+            | Atom::Instantiate { .. }
+            | Atom::Dynamic { .. } => Vec::new(),
+        }
+    }
+
     /// Check if all atoms before the target in a sequence are nullable
     fn all_nullable_before(&mut self, atoms: &[usize], target: usize) -> bool {
         for &atom in atoms {
@@ -295,6 +654,8 @@ impl<'a> GrammarAnalyzer<'a> {
             return result;
         }
 
+        // Assume non-nullable while computing, so recursive rules terminate
+        self.nullable.insert(atom_id, false);
         let result = self.compute_nullable(atom_id);
         self.nullable.insert(atom_id, result);
         result
@@ -307,26 +668,45 @@ impl<'a> GrammarAnalyzer<'a> {
         };
 
         match atom {
-            Atom::Str { pattern } => pattern.is_empty(),
+            Atom::Str { pattern } | Atom::StrCi { pattern } => pattern.is_empty(),
             Atom::Re { .. } => false, // Assume regex requires at least one char
+            Atom::CharRange { .. } | Atom::OneOf { .. } | Atom::NoneOf { .. } => false,
+            Atom::Until {
+                terminator,
+                inclusive,
+                ..
+            } => !inclusive || terminator.is_empty(),
+            Atom::ByteRange { .. } => false,
             Atom::Sequence { atoms } => atoms.iter().all(|&a| self.is_nullable(a)),
-            Atom::Alternative { atoms } => atoms.iter().any(|&a| self.is_nullable(a)),
-            Atom::Repetition { min, .. } => *min == 0,
+            Atom::Alternative { atoms } | Atom::LongestAlternative { atoms } => {
+                atoms.iter().any(|&a| self.is_nullable(a))
+            }
+            Atom::Repetition { min, .. } | Atom::RepetitionLazy { min, .. } => *min == 0,
             Atom::Named { atom, .. }
             | Atom::Entity { atom }
             | Atom::Ignore { atom }
-            | Atom::Lookahead { atom, .. } => self.is_nullable(*atom),
+            | Atom::Text { atom }
+            | Atom::ParseInt { atom, .. }
+            | Atom::ParseFloat { atom }
+            | Atom::Const { atom, .. }
+            | Atom::Lookahead { atom, .. }
+            | Atom::Infix { operand: atom, .. } => self.is_nullable(*atom),
+            Atom::Peek { .. } => true, // Consumes nothing
             Atom::Cut => false,
+            Atom::Eof => true,
+            Atom::Indent | Atom::Samedent | Atom::Dedent => true,
             Atom::Custom { .. } => false, // Custom atoms are not nullable by default
             Atom::Capture { atom, .. } | Atom::Scope { atom } => self.is_nullable(*atom),
             Atom::Dynamic { .. } => false, // Dynamic atoms are not nullable by default
+            Atom::BackRef { .. } => false, // Captures are assumed non-empty
+            Atom::OptionalDefault { .. } => true,
+            Atom::Instantiate { .. } => false, // Unresolved, fails to parse
         }
     }
 
     /// Detect atoms that are never referenced from the root
     fn detect_unused_atoms(&self, warnings: &mut Vec<GrammarWarning>) {
-        let mut reachable = HashSet::new();
-        self.collect_reachable(self.grammar.root, &mut reachable);
+        let reachable = self.reachable_atoms();
 
         for atom_id in 0..self.grammar.atoms.len() {
             if !reachable.contains(&atom_id) && atom_id != self.grammar.root {
@@ -339,6 +719,77 @@ impl<'a> GrammarAnalyzer<'a> {
         }
     }
 
+    /// `Atom::Str` patterns reachable from the root, in atom order
+    ///
+    /// These are the keywords and punctuation a parse can expect anywhere,
+    /// which makes them the candidates for "did you mean" suggestions.
+    pub fn reachable_literals(&self) -> Vec<&'a str> {
+        // Literals only reachable from the skip atom aren't worth suggesting
+        let mut reachable = HashSet::new();
+        self.collect_reachable(self.grammar.root, &mut reachable);
+
+        let grammar: &'a Grammar = self.grammar;
+        grammar
+            .atoms
+            .iter()
+            .enumerate()
+            .filter(|(atom_id, _)| reachable.contains(atom_id))
+            .filter_map(|(_, atom)| match atom {
+                Atom::Str { pattern } => Some(pattern.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Indices of every atom reachable from the root, including the root
+    /// and the skip atom
+    pub fn reachable_atoms(&self) -> HashSet<usize> {
+        let mut reachable = HashSet::new();
+        self.collect_reachable(self.grammar.root, &mut reachable);
+        if let Some(skip) = self.grammar.skip {
+            self.collect_reachable(skip, &mut reachable);
+        }
+        reachable
+    }
+
+    /// Indices of every atom reachable from `atom_id`, including itself
+    pub fn reachable_from(&self, atom_id: usize) -> HashSet<usize> {
+        let mut reachable = HashSet::new();
+        self.collect_reachable(atom_id, &mut reachable);
+        reachable
+    }
+
+    /// Mark the atoms matching `seed` and every atom that can reach one of
+    /// them, indexed by atom ID
+    ///
+    /// This is the set of atoms whose result can depend on, or change,
+    /// state that only the `seed` atoms touch, such as captures or the
+    /// indent stack. Like [`left_recursive_atoms`](Self::left_recursive_atoms),
+    /// it is cheap enough to run before parsing.
+    pub fn atoms_reaching(&self, seed: impl Fn(&Atom) -> bool) -> Vec<bool> {
+        let atoms = &self.grammar.atoms;
+        let mut marked: Vec<bool> = atoms.iter().map(seed).collect();
+        if !marked.iter().any(|&m| m) {
+            return marked;
+        }
+
+        // Propagate to parents until nothing changes
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (idx, atom) in atoms.iter().enumerate() {
+                if !marked[idx]
+                    && child_atoms(atom).any(|child| marked.get(child).copied().unwrap_or(false))
+                {
+                    marked[idx] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        marked
+    }
+
     /// Collect all atoms reachable from the given atom
     fn collect_reachable(&self, atom_id: usize, reachable: &mut HashSet<usize>) {
         if reachable.contains(&atom_id) {
@@ -352,21 +803,46 @@ impl<'a> GrammarAnalyzer<'a> {
 
         match atom {
             Atom::Str { .. }
+            | Atom::StrCi { .. }
             | Atom::Re { .. }
+            | Atom::CharRange { .. }
+            | Atom::OneOf { .. }
+            | Atom::NoneOf { .. }
+            | Atom::Until { .. }
+            | Atom::ByteRange { .. }
             | Atom::Cut
+            | Atom::Eof
+            | Atom::Indent
+            | Atom::Samedent
+            | Atom::Dedent
             | Atom::Custom { .. }
+            | Atom::BackRef { .. }
             | Atom::Dynamic { .. } => {}
-            Atom::Sequence { atoms } | Atom::Alternative { atoms } => {
+            Atom::Sequence { atoms }
+            | Atom::Alternative { atoms }
+            | Atom::LongestAlternative { atoms }
+            | Atom::Instantiate { args: atoms, .. } => {
                 for &child in atoms {
                     self.collect_reachable(child, reachable);
                 }
             }
+            Atom::RepetitionLazy { atom, follow, .. } => {
+                self.collect_reachable(*atom, reachable);
+                self.collect_reachable(*follow, reachable);
+            }
             Atom::Repetition { atom, .. }
             | Atom::Named { atom, .. }
             | Atom::Entity { atom }
             | Atom::Ignore { atom }
+            | Atom::Text { atom }
+            | Atom::ParseInt { atom, .. }
+            | Atom::ParseFloat { atom }
+            | Atom::Const { atom, .. }
             | Atom::Lookahead { atom, .. }
+            | Atom::Peek { atom }
+            | Atom::Infix { operand: atom, .. }
             | Atom::Capture { atom, .. }
+            | Atom::OptionalDefault { atom, .. }
             | Atom::Scope { atom } => {
                 self.collect_reachable(*atom, reachable);
             }
@@ -384,7 +860,9 @@ impl<'a> GrammarAnalyzer<'a> {
                         "Empty sequence always matches (matches empty string)",
                     ));
                 }
-                Atom::Alternative { atoms } if atoms.is_empty() => {
+                Atom::Alternative { atoms } | Atom::LongestAlternative { atoms }
+                    if atoms.is_empty() =>
+                {
                     warnings.push(GrammarWarning::new(
                         WarningKind::EmptyComposite,
                         atom_id,
@@ -485,6 +963,46 @@ impl<'a> GrammarAnalyzer<'a> {
         }
     }
 
+    /// Detect alternatives hidden by an earlier one with a wider FIRST set
+    ///
+    /// Pairs that both start with a literal are left to
+    /// [`detect_unreachable_alternatives`](Self::detect_unreachable_alternatives),
+    /// which compares the literals themselves; otherwise two keywords
+    /// sharing a first letter would be reported.
+    fn detect_shadowed_alternatives(&mut self, warnings: &mut Vec<GrammarWarning>) {
+        for atom_id in 0..self.grammar.atoms.len() {
+            let Some(Atom::Alternative { atoms }) = self.grammar.get_atom(atom_id) else {
+                continue;
+            };
+            let atoms = atoms.clone();
+            let firsts: Vec<FirstSet> = atoms.iter().map(|&child| self.first_set(child)).collect();
+
+            for (j, &later) in atoms.iter().enumerate() {
+                if firsts[j].is_empty() {
+                    continue;
+                }
+                let later_literal = self.get_first_literal(later);
+                let shadowing = (0..j).find(|&i| {
+                    firsts[i].is_superset_of(&firsts[j])
+                        && (later_literal.is_none() || self.get_first_literal(atoms[i]).is_none())
+                });
+                if let Some(i) = shadowing {
+                    warnings.push(
+                        GrammarWarning::new(
+                            WarningKind::ShadowedAlternative,
+                            atom_id,
+                            format!(
+                                "Alternative {} (atom {}) can start with anything alternative {} (atom {}) can, and may hide it",
+                                i, atoms[i], j, later
+                            ),
+                        )
+                        .with_related(vec![atoms[i], later]),
+                    );
+                }
+            }
+        }
+    }
+
     /// Get the first string literal from an atom (if any)
     fn get_first_literal(&self, atom_id: usize) -> Option<String> {
         let atom = self.grammar.get_atom(atom_id)?;
@@ -501,7 +1019,19 @@ impl<'a> GrammarAnalyzer<'a> {
             Atom::Named { atom, .. }
             | Atom::Entity { atom }
             | Atom::Ignore { atom }
-            | Atom::Lookahead { atom, .. } => self.get_first_literal(*atom),
+            | Atom::Text { atom }
+            | Atom::ParseInt { atom, .. }
+            | Atom::ParseFloat { atom }
+            | Atom::Const { atom, .. }
+            | Atom::Lookahead { atom, .. }
+            | Atom::Peek { atom } => self.get_first_literal(*atom),
+            Atom::Infix { operand, operators } => {
+                if operators.iter().any(|op| op.fixity == Fixity::Prefix) {
+                    None
+                } else {
+                    self.get_first_literal(*operand)
+                }
+            }
             _ => None,
         }
     }
@@ -586,6 +1116,309 @@ impl<'a> GrammarAnalyzer<'a> {
             _ => false,
         }
     }
+
+    /// Estimate the grammar's worst-case parse complexity
+    ///
+    /// Flags three kinds of hotspot:
+    /// - repetitions nested directly inside repetitions (`(a*)*`)
+    /// - alternatives whose branches can start with the same byte; inside an
+    ///   unbounded repetition (`(a|ab)*`) this is rated exponential
+    /// - sequences where an unbounded repetition is followed by something that
+    ///   can start the same way as the repeated item (`a* a`)
+    pub fn complexity_report(&mut self) -> ComplexityReport {
+        let mut hotspots = Vec::new();
+
+        // Atoms that are the body of an unbounded repetition
+        let mut repeated = HashSet::new();
+        for atom in &self.grammar.atoms {
+            if let Atom::Repetition {
+                atom: body,
+                max: None,
+                ..
+            } = atom
+            {
+                repeated.insert(self.unwrap_transparent(*body));
+            }
+        }
+
+        for (atom_id, atom) in self.grammar.atoms.iter().enumerate() {
+            match atom {
+                Atom::Repetition {
+                    atom: body, max, ..
+                } => {
+                    let inner = self.unwrap_transparent(*body);
+                    if let Some(Atom::Repetition { max: inner_max, .. }) =
+                        self.grammar.get_atom(inner)
+                    {
+                        let rating = if max.is_none() && inner_max.is_none() {
+                            ComplexityRating::Exponential
+                        } else {
+                            ComplexityRating::Polynomial
+                        };
+                        hotspots.push(ComplexityHotspot {
+                            rating,
+                            warning: GrammarWarning::new(
+                                WarningKind::ExcessiveBacktracking,
+                                atom_id,
+                                "Repetition directly contains another repetition",
+                            )
+                            .with_related(vec![inner]),
+                        });
+                    }
+                }
+                Atom::Alternative { atoms } => {
+                    let firsts: Vec<FirstSet> =
+                        atoms.iter().map(|&child| self.first_set(child)).collect();
+                    let mut overlapping = Vec::new();
+                    for (i, first) in firsts.iter().enumerate() {
+                        let overlaps = firsts
+                            .iter()
+                            .enumerate()
+                            .any(|(j, other)| i != j && first.intersects(other));
+                        if overlaps {
+                            overlapping.push(atoms[i]);
+                        }
+                    }
+                    if !overlapping.is_empty() {
+                        let rating = if repeated.contains(&atom_id) {
+                            ComplexityRating::Exponential
+                        } else {
+                            ComplexityRating::Polynomial
+                        };
+                        hotspots.push(ComplexityHotspot {
+                            rating,
+                            warning: GrammarWarning::new(
+                                WarningKind::ExcessiveBacktracking,
+                                atom_id,
+                                format!(
+                                    "{} alternatives can start with the same input",
+                                    overlapping.len()
+                                ),
+                            )
+                            .with_related(overlapping),
+                        });
+                    }
+                }
+                Atom::Sequence { atoms } => {
+                    for pair in atoms.windows(2) {
+                        let rep = self.unwrap_transparent(pair[0]);
+                        if let Some(Atom::Repetition {
+                            atom: body,
+                            max: None,
+                            ..
+                        }) = self.grammar.get_atom(rep)
+                        {
+                            if self.first_set(*body).intersects(&self.first_set(pair[1])) {
+                                hotspots.push(ComplexityHotspot {
+                                    rating: ComplexityRating::Polynomial,
+                                    warning: GrammarWarning::new(
+                                        WarningKind::ExcessiveBacktracking,
+                                        atom_id,
+                                        "Unbounded repetition is followed by an atom it overlaps",
+                                    )
+                                    .with_related(vec![pair[0], pair[1]]),
+                                });
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let rating = hotspots
+            .iter()
+            .map(|hotspot| hotspot.rating)
+            .max()
+            .unwrap_or(ComplexityRating::Linear);
+        ComplexityReport { rating, hotspots }
+    }
+
+    /// Follow wrappers that neither consume input nor change what matches
+    fn unwrap_transparent(&self, mut atom_id: usize) -> usize {
+        let mut seen = HashSet::new();
+        while seen.insert(atom_id) {
+            match self.grammar.get_atom(atom_id) {
+                Some(
+                    Atom::Named { atom, .. }
+                    | Atom::Entity { atom }
+                    | Atom::Ignore { atom }
+                    | Atom::Text { atom }
+                    | Atom::Const { atom, .. }
+                    | Atom::Capture { atom, .. }
+                    | Atom::Scope { atom },
+                ) => atom_id = *atom,
+                _ => break,
+            }
+        }
+        atom_id
+    }
+
+    /// Bytes the atom may consume first
+    fn first_set(&mut self, atom_id: usize) -> FirstSet {
+        if let Some(first) = self.first_sets.get(&atom_id) {
+            return first.clone();
+        }
+
+        // Assume empty while computing, so recursive rules terminate
+        self.first_sets.insert(atom_id, FirstSet::default());
+        let result = self.compute_first_set(atom_id);
+        self.first_sets.insert(atom_id, result.clone());
+        result
+    }
+
+    /// Compute the FIRST set of an atom
+    fn compute_first_set(&mut self, atom_id: usize) -> FirstSet {
+        let Some(atom) = self.grammar.get_atom(atom_id) else {
+            return FirstSet::default();
+        };
+
+        let mut first = FirstSet::default();
+        match atom {
+            Atom::Str { pattern } => {
+                if let Some(&b) = pattern.as_bytes().first() {
+                    first.insert(b);
+                }
+            }
+            Atom::StrCi { pattern } => match pattern.as_bytes().first() {
+                // Non-ASCII patterns fold Unicode case, so even an ASCII
+                // first letter may match a multi-byte char (`k` and U+212A)
+                Some(_) if !pattern.is_ascii() => first = FirstSet::any(),
+                Some(b) => {
+                    first.insert(b.to_ascii_lowercase());
+                    first.insert(b.to_ascii_uppercase());
+                }
+                None => {}
+            },
+            Atom::Re { pattern } => match CharacterPattern::from_pattern(pattern) {
+                Some(class) => {
+                    for b in 0..=u8::MAX {
+                        // Unicode classes may match any multi-byte character
+                        if class.matches(b) || (class.is_unicode() && b >= 0x80) {
+                            first.insert(b);
+                        }
+                    }
+                }
+                None => first = FirstSet::of_regex(pattern).unwrap_or_else(FirstSet::any),
+            },
+            Atom::CharRange { lo, hi } => {
+                if hi.is_ascii() {
+                    for b in *lo as u8..=*hi as u8 {
+                        first.insert(b);
+                    }
+                } else {
+                    first = FirstSet::any();
+                }
+            }
+            Atom::OneOf { chars } if chars.iter().all(char::is_ascii) => {
+                for &c in chars {
+                    first.insert(c as u8);
+                }
+            }
+            Atom::OneOf { .. } | Atom::NoneOf { .. } | Atom::Until { .. } => {
+                first = FirstSet::any()
+            }
+            Atom::ByteRange { lo, hi } => {
+                for b in *lo..=*hi {
+                    first.insert(b);
+                }
+            }
+            Atom::Sequence { atoms } => {
+                let atoms = atoms.clone();
+                for child in atoms {
+                    first.union(&self.first_set(child));
+                    if !self.is_nullable(child) {
+                        break;
+                    }
+                }
+            }
+            Atom::Alternative { atoms } | Atom::LongestAlternative { atoms } => {
+                let atoms = atoms.clone();
+                for child in atoms {
+                    first.union(&self.first_set(child));
+                }
+            }
+            Atom::Repetition { atom, .. }
+            | Atom::RepetitionLazy { atom, .. }
+            | Atom::Named { atom, .. }
+            | Atom::Entity { atom }
+            | Atom::Ignore { atom }
+            | Atom::Text { atom }
+            | Atom::ParseInt { atom, .. }
+            | Atom::ParseFloat { atom }
+            | Atom::Const { atom, .. }
+            | Atom::Capture { atom, .. }
+            | Atom::OptionalDefault { atom, .. }
+            | Atom::Scope { atom } => first = self.first_set(*atom),
+            Atom::Infix { operand, operators } => {
+                // A prefix operator may come before the operand
+                let prefix_bytes: Vec<u8> = operators
+                    .iter()
+                    .filter(|op| op.fixity == Fixity::Prefix)
+                    .filter_map(|op| op.pattern.as_bytes().first().copied())
+                    .collect();
+                first = self.first_set(*operand);
+                for b in prefix_bytes {
+                    first.insert(b);
+                }
+            }
+            Atom::Custom { .. } | Atom::Dynamic { .. } | Atom::BackRef { .. } => {
+                first = FirstSet::any();
+            }
+            // Consume nothing, or never match
+            Atom::Lookahead { .. }
+            | Atom::Peek { .. }
+            | Atom::Cut
+            | Atom::Eof
+            | Atom::Indent
+            | Atom::Samedent
+            | Atom::Dedent
+            | Atom::Instantiate { .. } => {}
+        }
+        first
+    }
+}
+
+/// Atoms `atom` may try while it is being parsed
+fn child_atoms(atom: &Atom) -> impl Iterator<Item = usize> + '_ {
+    let (many, single): (&[usize], [Option<usize>; 2]) = match atom {
+        Atom::Sequence { atoms }
+        | Atom::Alternative { atoms }
+        | Atom::LongestAlternative { atoms } => (atoms, [None; 2]),
+        Atom::Instantiate { args, .. } => (args, [None; 2]),
+        Atom::RepetitionLazy { atom, follow, .. } => (&[], [Some(*atom), Some(*follow)]),
+        Atom::Repetition { atom, .. }
+        | Atom::Named { atom, .. }
+        | Atom::Entity { atom }
+        | Atom::Lookahead { atom, .. }
+        | Atom::Peek { atom }
+        | Atom::Infix { operand: atom, .. }
+        | Atom::Ignore { atom }
+        | Atom::Text { atom }
+        | Atom::ParseInt { atom, .. }
+        | Atom::ParseFloat { atom }
+        | Atom::Const { atom, .. }
+        | Atom::Capture { atom, .. }
+        | Atom::OptionalDefault { atom, .. }
+        | Atom::Scope { atom } => (&[], [Some(*atom), None]),
+        Atom::Str { .. }
+        | Atom::StrCi { .. }
+        | Atom::Re { .. }
+        | Atom::CharRange { .. }
+        | Atom::OneOf { .. }
+        | Atom::NoneOf { .. }
+        | Atom::Until { .. }
+        | Atom::ByteRange { .. }
+        | Atom::Cut
+        | Atom::Eof
+        | Atom::Indent
+        | Atom::Samedent
+        | Atom::Dedent
+        | Atom::BackRef { .. }
+        | Atom::Dynamic { .. }
+        | Atom::Custom { .. } => (&[], [None; 2]),
+    };
+    many.iter().copied().chain(single.into_iter().flatten())
 }
 
 #[cfg(test)]
@@ -608,6 +1441,52 @@ mod tests {
         assert!(warnings.is_empty());
     }
 
+    #[test]
+    fn test_left_recursive_atoms() {
+        let mut grammar = Grammar::new();
+        // expr = expr "+" num | num
+        let num = grammar.add_atom(Atom::Re {
+            pattern: "[0-9]".to_string(),
+        });
+        let plus = grammar.add_atom(Atom::Str {
+            pattern: "+".to_string(),
+        });
+        let expr_ref = grammar.add_atom(Atom::Entity { atom: 4 });
+        let seq = grammar.add_atom(Atom::Sequence {
+            atoms: vec![expr_ref, plus, num],
+        });
+        let expr = grammar.add_atom(Atom::Alternative {
+            atoms: vec![seq, num],
+        });
+        grammar.root = expr;
+
+        let recursive = GrammarAnalyzer::new(&grammar).left_recursive_atoms();
+        assert_eq!(recursive, vec![false, false, true, true, true]);
+    }
+
+    #[test]
+    fn test_right_recursion_is_not_left_recursive() {
+        let mut grammar = Grammar::new();
+        // list = num "," list | num
+        let num = grammar.add_atom(Atom::Re {
+            pattern: "[0-9]".to_string(),
+        });
+        let comma = grammar.add_atom(Atom::Str {
+            pattern: ",".to_string(),
+        });
+        let list_ref = grammar.add_atom(Atom::Entity { atom: 4 });
+        let seq = grammar.add_atom(Atom::Sequence {
+            atoms: vec![num, comma, list_ref],
+        });
+        let list = grammar.add_atom(Atom::Alternative {
+            atoms: vec![seq, num],
+        });
+        grammar.root = list;
+
+        let recursive = GrammarAnalyzer::new(&grammar).left_recursive_atoms();
+        assert!(recursive.iter().all(|&r| !r));
+    }
+
     #[test]
     fn test_detect_left_recursion() {
         let mut grammar = Grammar::new();
@@ -731,6 +1610,92 @@ mod tests {
             .any(|w| w.kind == WarningKind::ExcessiveBacktracking));
     }
 
+    fn shadowed_warnings(first: Atom, second: Atom) -> Vec<GrammarWarning> {
+        let mut grammar = Grammar::new();
+        let a = grammar.add_atom(first);
+        let b = grammar.add_atom(second);
+        grammar.root = grammar.add_atom(Atom::Alternative { atoms: vec![a, b] });
+        GrammarAnalyzer::new(&grammar)
+            .analyze()
+            .into_iter()
+            .filter(|w| w.kind == WarningKind::ShadowedAlternative)
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_shadowed_alternative() {
+        let ident = || Atom::Re {
+            pattern: "[a-z_][a-z0-9_]*".to_string(),
+        };
+        let keyword = || Atom::Str {
+            pattern: "for".to_string(),
+        };
+
+        // The identifier regex swallows the keyword
+        let warnings = shadowed_warnings(ident(), keyword());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].related_atoms, vec![0, 1]);
+
+        // Keywords first is the usual fix
+        assert!(shadowed_warnings(keyword(), ident()).is_empty());
+
+        // Literals sharing a first byte are compared as literals instead
+        let foo = Atom::Str {
+            pattern: "foo".to_string(),
+        };
+        assert!(shadowed_warnings(keyword(), foo).is_empty());
+
+        // Disjoint classes don't shadow each other
+        let digits = Atom::Re {
+            pattern: "[0-9]+".to_string(),
+        };
+        assert!(shadowed_warnings(ident(), digits).is_empty());
+    }
+
+    #[test]
+    fn test_regex_first_set() {
+        let first = FirstSet::of_regex("[a-cx]+").unwrap();
+        assert!(first.is_superset_of(&FirstSet::of_regex("b").unwrap()));
+        assert!(first.is_superset_of(&FirstSet::of_regex("x").unwrap()));
+        assert!(!first.is_superset_of(&FirstSet::of_regex("d").unwrap()));
+
+        let digits = FirstSet::of_regex(r"\d+\.\d+").unwrap();
+        assert!(digits.is_superset_of(&FirstSet::of_regex("[0-9]").unwrap()));
+        assert!(FirstSet::of_regex(r"\-").is_some());
+
+        // The first element may be absent, or the pattern is too complex
+        assert!(FirstSet::of_regex("[a-z]*x").is_none());
+        assert!(FirstSet::of_regex("a?b").is_none());
+        assert!(FirstSet::of_regex("[^a]").is_none());
+        assert!(FirstSet::of_regex("a|b").is_none());
+        assert!(FirstSet::of_regex(".").is_none());
+    }
+
+    #[test]
+    fn test_case_insensitive_first_set() {
+        let mut grammar = Grammar::new();
+        let ascii = grammar.add_atom(Atom::StrCi {
+            pattern: "if".to_string(),
+        });
+        let unicode = grammar.add_atom(Atom::StrCi {
+            pattern: "kä".to_string(),
+        });
+        let kelvin = grammar.add_atom(Atom::Str {
+            pattern: "\u{212A}".to_string(),
+        });
+        let upper = grammar.add_atom(Atom::Str {
+            pattern: "I".to_string(),
+        });
+
+        let mut analyzer = GrammarAnalyzer::new(&grammar);
+        let ascii = analyzer.first_set(ascii);
+        assert!(ascii.is_superset_of(&analyzer.first_set(upper)));
+        assert!(!ascii.intersects(&analyzer.first_set(kelvin)));
+        // `k` folds to the Kelvin sign, whose first byte isn't ASCII
+        let unicode = analyzer.first_set(unicode);
+        assert!(unicode.intersects(&analyzer.first_set(kelvin)));
+    }
+
     #[test]
     fn test_nullable_detection() {
         let mut grammar = Grammar::new();
@@ -788,4 +1753,115 @@ mod tests {
         assert!(reachable.contains(&3));
         assert!(!reachable.contains(&2));
     }
+
+    #[test]
+    fn test_atoms_reaching() {
+        let mut grammar = Grammar::new();
+        let a = grammar.add_atom(Atom::Str {
+            pattern: "a".to_string(),
+        });
+        let eof = grammar.add_atom(Atom::Eof);
+        let seq = grammar.add_atom(Atom::Sequence {
+            atoms: vec![a, eof],
+        });
+        let rep = grammar.add_atom(Atom::Repetition {
+            atom: seq,
+            min: 0,
+            max: None,
+        });
+        grammar.root = grammar.add_atom(Atom::Alternative {
+            atoms: vec![a, rep],
+        });
+
+        let analyzer = GrammarAnalyzer::new(&grammar);
+        let marked = analyzer.atoms_reaching(|atom| matches!(atom, Atom::Eof));
+        assert_eq!(marked, vec![false, true, true, true, true]);
+
+        let none = analyzer.atoms_reaching(|atom| matches!(atom, Atom::Cut));
+        assert!(!none.iter().any(|&m| m));
+    }
+
+    #[test]
+    fn test_complexity_report_flags_repeated_overlapping_alternative() {
+        let mut grammar = Grammar::new();
+        // (a | ab)*
+        let a = grammar.add_atom(Atom::Str {
+            pattern: "a".to_string(),
+        });
+        let b = grammar.add_atom(Atom::Str {
+            pattern: "b".to_string(),
+        });
+        let ab = grammar.add_atom(Atom::Sequence { atoms: vec![a, b] });
+        let alt = grammar.add_atom(Atom::Alternative { atoms: vec![a, ab] });
+        grammar.root = grammar.add_atom(Atom::Repetition {
+            atom: alt,
+            min: 0,
+            max: None,
+        });
+
+        let report = GrammarAnalyzer::new(&grammar).complexity_report();
+        assert_eq!(report.rating, ComplexityRating::Exponential);
+        assert_eq!(report.hotspots.len(), 1);
+        let hotspot = &report.hotspots[0];
+        assert_eq!(hotspot.warning.atom_id, alt);
+        assert_eq!(hotspot.warning.related_atoms, vec![a, ab]);
+    }
+
+    #[test]
+    fn test_complexity_report_ratings() {
+        // Disjoint alternatives: linear
+        let mut grammar = Grammar::new();
+        let a = grammar.add_atom(Atom::Str {
+            pattern: "a".to_string(),
+        });
+        let digit = grammar.add_atom(Atom::Re {
+            pattern: "[0-9]".to_string(),
+        });
+        let alt = grammar.add_atom(Atom::Alternative {
+            atoms: vec![a, digit],
+        });
+        grammar.root = grammar.add_atom(Atom::Repetition {
+            atom: alt,
+            min: 0,
+            max: None,
+        });
+        let report = GrammarAnalyzer::new(&grammar).complexity_report();
+        assert_eq!(report.rating, ComplexityRating::Linear);
+        assert!(report.hotspots.is_empty());
+
+        // [0-9]* [0-9]: the repetition re-scans what the next atom needs
+        let mut grammar = Grammar::new();
+        let digit = grammar.add_atom(Atom::Re {
+            pattern: "[0-9]".to_string(),
+        });
+        let digits = grammar.add_atom(Atom::Repetition {
+            atom: digit,
+            min: 0,
+            max: None,
+        });
+        grammar.root = grammar.add_atom(Atom::Sequence {
+            atoms: vec![digits, digit],
+        });
+        let report = GrammarAnalyzer::new(&grammar).complexity_report();
+        assert_eq!(report.rating, ComplexityRating::Polynomial);
+
+        // (a*)*
+        let mut grammar = Grammar::new();
+        let a = grammar.add_atom(Atom::Str {
+            pattern: "a".to_string(),
+        });
+        let inner = grammar.add_atom(Atom::Repetition {
+            atom: a,
+            min: 0,
+            max: None,
+        });
+        grammar.root = grammar.add_atom(Atom::Repetition {
+            atom: inner,
+            min: 0,
+            max: None,
+        });
+        let report = GrammarAnalyzer::new(&grammar).complexity_report();
+        assert_eq!(report.rating, ComplexityRating::Exponential);
+        assert_eq!(report.hotspots[0].warning.related_atoms, vec![inner]);
+    }
 }
@@ -0,0 +1,967 @@
+//! Compact binary encoding for grammars
+//!
+//! Hosts that compile a grammar once and load it on every call pay for
+//! `serde_json` parsing each time. [`Grammar::to_bytes`] produces a
+//! length-prefixed binary form that [`Grammar::from_bytes`] decodes without
+//! any text parsing.
+//!
+//! # Format
+//!
+//! ```text
+//! "PGRB"            magic
+//! u16 (LE)          format version
+//! varint            root atom index
+//! u8 + varints      memoized set: 0 = none, 1 = count then indices
+//! u8                flags: bit 0 = ascii_only
+//! varint            skip atom index as an `Option<usize>`
+//! varint + varints  no-skip set: count then indices
+//! varint + pairs    rule descriptions: count then index and string pairs
+//! varint + atoms    atom count, then each atom as a tag byte and fields
+//! ```
+//!
+//! Integers are unsigned LEB128 varints, strings are a varint byte length
+//! followed by UTF-8, and `Option<usize>` is stored as `0` for `None` or
+//! `n + 1`. The format is only guaranteed to round-trip within the same
+//! [`FORMAT_VERSION`]; decoding any other version fails.
+//!
+//! # Example
+//!
+//! ```
+//! use parsanol::portable::{Atom, Grammar};
+//!
+//! let mut grammar = Grammar::new();
+//! grammar.root = grammar.add_atom(Atom::Str {
+//!     pattern: "hello".to_string(),
+//! });
+//!
+//! let bytes = grammar.to_bytes();
+//! assert_eq!(Grammar::from_bytes(&bytes).unwrap(), grammar);
+//! ```
+
+use super::grammar::{Atom, DefaultValue, Grammar};
+use super::infix::{Assoc, Fixity, InfixOperator};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Leading bytes of every encoded grammar
+pub const MAGIC: &[u8; 4] = b"PGRB";
+
+/// Version written by [`Grammar::to_bytes`] and accepted by [`Grammar::from_bytes`]
+pub const FORMAT_VERSION: u16 = 5;
+
+/// Error decoding a binary grammar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrammarBytesError {
+    /// The input doesn't start with [`MAGIC`]
+    BadMagic,
+    /// The input was written by a different format version
+    UnsupportedVersion {
+        /// Version found in the header
+        found: u16,
+    },
+    /// The input ended in the middle of a value
+    UnexpectedEnd {
+        /// Byte offset where more input was needed
+        offset: usize,
+    },
+    /// A tag byte doesn't name a known variant
+    InvalidTag {
+        /// What was being decoded
+        kind: &'static str,
+        /// The unknown tag
+        tag: u8,
+        /// Byte offset of the tag
+        offset: usize,
+    },
+    /// A string, character or number is malformed
+    InvalidValue {
+        /// What was being decoded
+        kind: &'static str,
+        /// Byte offset of the value
+        offset: usize,
+    },
+    /// Bytes remain after the grammar
+    TrailingBytes {
+        /// Byte offset of the first unread byte
+        offset: usize,
+    },
+}
+
+impl std::fmt::Display for GrammarBytesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a binary grammar (bad magic header)"),
+            Self::UnsupportedVersion { found } => write!(
+                f,
+                "unsupported binary grammar version {} (expected {})",
+                found, FORMAT_VERSION
+            ),
+            Self::UnexpectedEnd { offset } => {
+                write!(f, "binary grammar truncated at byte {}", offset)
+            }
+            Self::InvalidTag { kind, tag, offset } => {
+                write!(f, "invalid {} tag {} at byte {}", kind, tag, offset)
+            }
+            Self::InvalidValue { kind, offset } => write!(f, "invalid {} at byte {}", kind, offset),
+            Self::TrailingBytes { offset } => {
+                write!(f, "unexpected data after grammar at byte {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GrammarBytesError {}
+
+// Atom tags, in `Atom` declaration order. Never renumber: add new tags at
+// the end, or bump FORMAT_VERSION.
+const TAG_STR: u8 = 0;
+const TAG_STR_CI: u8 = 1;
+const TAG_RE: u8 = 2;
+const TAG_CHAR_RANGE: u8 = 3;
+const TAG_SEQUENCE: u8 = 4;
+const TAG_ALTERNATIVE: u8 = 5;
+const TAG_REPETITION: u8 = 6;
+const TAG_NAMED: u8 = 7;
+const TAG_ENTITY: u8 = 8;
+const TAG_INSTANTIATE: u8 = 9;
+const TAG_LOOKAHEAD: u8 = 10;
+const TAG_PEEK: u8 = 11;
+const TAG_INFIX: u8 = 12;
+const TAG_CUT: u8 = 13;
+const TAG_EOF: u8 = 14;
+const TAG_IGNORE: u8 = 15;
+const TAG_CAPTURE: u8 = 16;
+const TAG_BACK_REF: u8 = 17;
+const TAG_OPTIONAL_DEFAULT: u8 = 18;
+const TAG_SCOPE: u8 = 19;
+const TAG_DYNAMIC: u8 = 20;
+const TAG_CUSTOM: u8 = 21;
+const TAG_ONE_OF: u8 = 22;
+const TAG_NONE_OF: u8 = 23;
+const TAG_INDENT: u8 = 24;
+const TAG_SAMEDENT: u8 = 25;
+const TAG_DEDENT: u8 = 26;
+const TAG_LONGEST_ALTERNATIVE: u8 = 27;
+const TAG_PARSE_INT: u8 = 28;
+const TAG_PARSE_FLOAT: u8 = 29;
+const TAG_UNTIL: u8 = 30;
+const TAG_BYTE_RANGE: u8 = 31;
+const TAG_REPETITION_LAZY: u8 = 32;
+const TAG_TEXT: u8 = 33;
+const TAG_CONST: u8 = 34;
+
+impl Grammar {
+    /// Encode the grammar in the compact binary format
+    ///
+    /// See the [`grammar_bytes`](crate::portable::grammar_bytes) module for
+    /// the layout.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer {
+            out: Vec::with_capacity(16 + self.atoms.len() * 8),
+        };
+        writer.out.extend_from_slice(MAGIC);
+        writer.out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        writer.usize(self.root);
+
+        match &self.memoized {
+            None => writer.u8(0),
+            Some(ids) => {
+                writer.u8(1);
+                writer.usize(ids.len());
+                for &id in ids {
+                    writer.usize(id);
+                }
+            }
+        }
+
+        writer.u8(u8::from(self.ascii_only));
+        writer.opt_usize(self.skip);
+        writer.usize(self.no_skip.len());
+        for &id in &self.no_skip {
+            writer.usize(id);
+        }
+        writer.usize(self.rule_descriptions.len());
+        for (&id, description) in &self.rule_descriptions {
+            writer.usize(id);
+            writer.str(description);
+        }
+
+        writer.usize(self.atoms.len());
+        for atom in &self.atoms {
+            writer.atom(atom);
+        }
+        writer.out
+    }
+
+    /// Decode a grammar written by [`to_bytes`](Self::to_bytes)
+    ///
+    /// Fails if the header is missing, the version differs from
+    /// [`FORMAT_VERSION`], or the data is truncated or malformed. Like
+    /// [`from_json`](Self::from_json), atom indices are not validated.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GrammarBytesError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(GrammarBytesError::BadMagic);
+        }
+        let mut reader = Reader {
+            bytes,
+            pos: MAGIC.len(),
+        };
+        let version = u16::from_le_bytes([reader.u8()?, reader.u8()?]);
+        if version != FORMAT_VERSION {
+            return Err(GrammarBytesError::UnsupportedVersion { found: version });
+        }
+
+        let root = reader.usize()?;
+        let memoized = match reader.u8()? {
+            0 => None,
+            1 => {
+                let count = reader.len()?;
+                let mut ids = BTreeSet::new();
+                for _ in 0..count {
+                    ids.insert(reader.usize()?);
+                }
+                Some(ids)
+            }
+            tag => return Err(reader.invalid_tag("memoized", tag)),
+        };
+        let flags = reader.u8()?;
+        if flags & !1 != 0 {
+            return Err(reader.invalid_tag("flags", flags));
+        }
+        let skip = reader.opt_usize()?;
+        let no_skip_count = reader.len()?;
+        let mut no_skip = BTreeSet::new();
+        for _ in 0..no_skip_count {
+            no_skip.insert(reader.usize()?);
+        }
+        let description_count = reader.len()?;
+        let mut rule_descriptions = BTreeMap::new();
+        for _ in 0..description_count {
+            let id = reader.usize()?;
+            rule_descriptions.insert(id, reader.str()?);
+        }
+
+        let count = reader.len()?;
+        let mut atoms = Vec::with_capacity(count);
+        for _ in 0..count {
+            atoms.push(reader.atom()?);
+        }
+
+        if reader.pos != bytes.len() {
+            return Err(GrammarBytesError::TrailingBytes { offset: reader.pos });
+        }
+
+        let mut grammar = Grammar::from_atoms(atoms, root);
+        grammar.memoized = memoized;
+        grammar.ascii_only = flags & 1 != 0;
+        grammar.skip = skip;
+        grammar.no_skip = no_skip;
+        grammar.rule_descriptions = rule_descriptions;
+        Ok(grammar)
+    }
+}
+
+struct Writer {
+    out: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.out.push(value);
+    }
+
+    fn u64(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    fn opt_usize(&mut self, value: Option<usize>) {
+        self.u64(value.map_or(0, |v| (v as u64).saturating_add(1)));
+    }
+
+    fn str(&mut self, value: &str) {
+        self.usize(value.len());
+        self.out.extend_from_slice(value.as_bytes());
+    }
+
+    fn indices(&mut self, indices: &[usize]) {
+        self.usize(indices.len());
+        for &idx in indices {
+            self.usize(idx);
+        }
+    }
+
+    fn default_value(&mut self, value: DefaultValue) {
+        match value {
+            DefaultValue::Nil => self.u8(0),
+            DefaultValue::Bool(value) => {
+                self.u8(1);
+                self.u8(value as u8);
+            }
+            DefaultValue::Int(value) => {
+                self.u8(2);
+                self.out.extend_from_slice(&value.to_le_bytes());
+            }
+            DefaultValue::Float(value) => {
+                self.u8(3);
+                self.out.extend_from_slice(&value.to_bits().to_le_bytes());
+            }
+        }
+    }
+
+    fn chars(&mut self, chars: &[char]) {
+        self.usize(chars.len());
+        for &c in chars {
+            self.u64(u64::from(c));
+        }
+    }
+
+    fn atom(&mut self, atom: &Atom) {
+        match atom {
+            Atom::Str { pattern } => {
+                self.u8(TAG_STR);
+                self.str(pattern);
+            }
+            Atom::StrCi { pattern } => {
+                self.u8(TAG_STR_CI);
+                self.str(pattern);
+            }
+            Atom::Re { pattern } => {
+                self.u8(TAG_RE);
+                self.str(pattern);
+            }
+            Atom::CharRange { lo, hi } => {
+                self.u8(TAG_CHAR_RANGE);
+                self.u64(u64::from(*lo));
+                self.u64(u64::from(*hi));
+            }
+            Atom::Sequence { atoms } => {
+                self.u8(TAG_SEQUENCE);
+                self.indices(atoms);
+            }
+            Atom::Alternative { atoms } => {
+                self.u8(TAG_ALTERNATIVE);
+                self.indices(atoms);
+            }
+            Atom::LongestAlternative { atoms } => {
+                self.u8(TAG_LONGEST_ALTERNATIVE);
+                self.indices(atoms);
+            }
+            Atom::Repetition { atom, min, max } => {
+                self.u8(TAG_REPETITION);
+                self.usize(*atom);
+                self.usize(*min);
+                self.opt_usize(*max);
+            }
+            Atom::Named { name, atom } => {
+                self.u8(TAG_NAMED);
+                self.str(name);
+                self.usize(*atom);
+            }
+            Atom::Entity { atom } => {
+                self.u8(TAG_ENTITY);
+                self.usize(*atom);
+            }
+            Atom::Instantiate { template, args } => {
+                self.u8(TAG_INSTANTIATE);
+                self.str(template);
+                self.indices(args);
+            }
+            Atom::Lookahead { atom, positive } => {
+                self.u8(TAG_LOOKAHEAD);
+                self.usize(*atom);
+                self.u8(*positive as u8);
+            }
+            Atom::Peek { atom } => {
+                self.u8(TAG_PEEK);
+                self.usize(*atom);
+            }
+            Atom::Infix { operand, operators } => {
+                self.u8(TAG_INFIX);
+                self.usize(*operand);
+                self.usize(operators.len());
+                for op in operators {
+                    self.str(&op.pattern);
+                    self.u8(op.precedence);
+                    self.u8(match op.associativity {
+                        Assoc::Left => 0,
+                        Assoc::Right => 1,
+                        Assoc::NonAssoc => 2,
+                    });
+                    self.u8(match op.fixity {
+                        Fixity::Infix => 0,
+                        Fixity::Prefix => 1,
+                        Fixity::Postfix => 2,
+                    });
+                }
+            }
+            Atom::Cut => self.u8(TAG_CUT),
+            Atom::Eof => self.u8(TAG_EOF),
+            Atom::Indent => self.u8(TAG_INDENT),
+            Atom::Samedent => self.u8(TAG_SAMEDENT),
+            Atom::Dedent => self.u8(TAG_DEDENT),
+            Atom::Ignore { atom } => {
+                self.u8(TAG_IGNORE);
+                self.usize(*atom);
+            }
+            Atom::Text { atom } => {
+                self.u8(TAG_TEXT);
+                self.usize(*atom);
+            }
+            Atom::Capture { name, atom } => {
+                self.u8(TAG_CAPTURE);
+                self.str(name);
+                self.usize(*atom);
+            }
+            Atom::BackRef { name } => {
+                self.u8(TAG_BACK_REF);
+                self.str(name);
+            }
+            Atom::ParseInt {
+                atom,
+                radix_prefixes,
+            } => {
+                self.u8(TAG_PARSE_INT);
+                self.usize(*atom);
+                self.u8(*radix_prefixes as u8);
+            }
+            Atom::ParseFloat { atom } => {
+                self.u8(TAG_PARSE_FLOAT);
+                self.usize(*atom);
+            }
+            Atom::OptionalDefault {
+                name,
+                atom,
+                default,
+            } => {
+                self.u8(TAG_OPTIONAL_DEFAULT);
+                self.str(name);
+                self.usize(*atom);
+                self.default_value(*default);
+            }
+            Atom::Const { atom, value } => {
+                self.u8(TAG_CONST);
+                self.usize(*atom);
+                self.default_value(*value);
+            }
+            Atom::Scope { atom } => {
+                self.u8(TAG_SCOPE);
+                self.usize(*atom);
+            }
+            Atom::Dynamic { callback_id } => {
+                self.u8(TAG_DYNAMIC);
+                self.u64(*callback_id);
+            }
+            Atom::Custom { id } => {
+                self.u8(TAG_CUSTOM);
+                self.u64(*id);
+            }
+            Atom::OneOf { chars } => {
+                self.u8(TAG_ONE_OF);
+                self.chars(chars);
+            }
+            Atom::NoneOf { chars } => {
+                self.u8(TAG_NONE_OF);
+                self.chars(chars);
+            }
+            Atom::Until {
+                terminator,
+                inclusive,
+                or_end,
+            } => {
+                self.u8(TAG_UNTIL);
+                self.str(terminator);
+                self.u8(*inclusive as u8);
+                self.u8(*or_end as u8);
+            }
+            Atom::ByteRange { lo, hi } => {
+                self.u8(TAG_BYTE_RANGE);
+                self.u8(*lo);
+                self.u8(*hi);
+            }
+            Atom::RepetitionLazy {
+                atom,
+                min,
+                max,
+                follow,
+            } => {
+                self.u8(TAG_REPETITION_LAZY);
+                self.usize(*atom);
+                self.usize(*min);
+                self.opt_usize(*max);
+                self.usize(*follow);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Result<u8, GrammarBytesError> {
+        let byte = *self
+            .bytes
+            .get(self.pos)
+            .ok_or(GrammarBytesError::UnexpectedEnd { offset: self.pos })?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    /// Error for the tag byte just read
+    fn invalid_tag(&self, kind: &'static str, tag: u8) -> GrammarBytesError {
+        GrammarBytesError::InvalidTag {
+            kind,
+            tag,
+            offset: self.pos - 1,
+        }
+    }
+
+    fn bool(&mut self) -> Result<bool, GrammarBytesError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(self.invalid_tag("bool", tag)),
+        }
+    }
+
+    fn u64(&mut self) -> Result<u64, GrammarBytesError> {
+        let start = self.pos;
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(GrammarBytesError::InvalidValue {
+            kind: "varint",
+            offset: start,
+        })
+    }
+
+    fn usize(&mut self) -> Result<usize, GrammarBytesError> {
+        let start = self.pos;
+        usize::try_from(self.u64()?).map_err(|_| GrammarBytesError::InvalidValue {
+            kind: "index",
+            offset: start,
+        })
+    }
+
+    /// A count of items that follow; bounded by the remaining input so a
+    /// corrupt count can't trigger a huge allocation
+    fn len(&mut self) -> Result<usize, GrammarBytesError> {
+        let start = self.pos;
+        let len = self.usize()?;
+        if len > self.bytes.len() - self.pos {
+            return Err(GrammarBytesError::UnexpectedEnd { offset: start });
+        }
+        Ok(len)
+    }
+
+    fn opt_usize(&mut self) -> Result<Option<usize>, GrammarBytesError> {
+        Ok(match self.usize()? {
+            0 => None,
+            n => Some(n - 1),
+        })
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&[u8], GrammarBytesError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(GrammarBytesError::UnexpectedEnd { offset: self.pos })?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn str(&mut self) -> Result<String, GrammarBytesError> {
+        let len = self.len()?;
+        let start = self.pos;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| GrammarBytesError::InvalidValue {
+            kind: "string",
+            offset: start,
+        })
+    }
+
+    fn char(&mut self) -> Result<char, GrammarBytesError> {
+        let start = self.pos;
+        let value = self.u64()?;
+        u32::try_from(value)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(GrammarBytesError::InvalidValue {
+                kind: "char",
+                offset: start,
+            })
+    }
+
+    fn fixed8(&mut self) -> Result<[u8; 8], GrammarBytesError> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.bytes(8)?);
+        Ok(buf)
+    }
+
+    fn indices(&mut self) -> Result<Vec<usize>, GrammarBytesError> {
+        let count = self.len()?;
+        (0..count).map(|_| self.usize()).collect()
+    }
+
+    fn default_value(&mut self) -> Result<DefaultValue, GrammarBytesError> {
+        Ok(match self.u8()? {
+            0 => DefaultValue::Nil,
+            1 => DefaultValue::Bool(self.bool()?),
+            2 => DefaultValue::Int(i64::from_le_bytes(self.fixed8()?)),
+            3 => DefaultValue::Float(f64::from_bits(u64::from_le_bytes(self.fixed8()?))),
+            tag => return Err(self.invalid_tag("default value", tag)),
+        })
+    }
+
+    fn chars(&mut self) -> Result<Vec<char>, GrammarBytesError> {
+        let count = self.len()?;
+        (0..count).map(|_| self.char()).collect()
+    }
+
+    fn atom(&mut self) -> Result<Atom, GrammarBytesError> {
+        Ok(match self.u8()? {
+            TAG_STR => Atom::Str {
+                pattern: self.str()?,
+            },
+            TAG_STR_CI => Atom::StrCi {
+                pattern: self.str()?,
+            },
+            TAG_RE => Atom::Re {
+                pattern: self.str()?,
+            },
+            TAG_CHAR_RANGE => Atom::CharRange {
+                lo: self.char()?,
+                hi: self.char()?,
+            },
+            TAG_SEQUENCE => Atom::Sequence {
+                atoms: self.indices()?,
+            },
+            TAG_ALTERNATIVE => Atom::Alternative {
+                atoms: self.indices()?,
+            },
+            TAG_LONGEST_ALTERNATIVE => Atom::LongestAlternative {
+                atoms: self.indices()?,
+            },
+            TAG_REPETITION => Atom::Repetition {
+                atom: self.usize()?,
+                min: self.usize()?,
+                max: self.opt_usize()?,
+            },
+            TAG_NAMED => Atom::Named {
+                name: self.str()?,
+                atom: self.usize()?,
+            },
+            TAG_ENTITY => Atom::Entity {
+                atom: self.usize()?,
+            },
+            TAG_INSTANTIATE => Atom::Instantiate {
+                template: self.str()?,
+                args: self.indices()?,
+            },
+            TAG_LOOKAHEAD => Atom::Lookahead {
+                atom: self.usize()?,
+                positive: self.bool()?,
+            },
+            TAG_PEEK => Atom::Peek {
+                atom: self.usize()?,
+            },
+            TAG_INFIX => {
+                let operand = self.usize()?;
+                let count = self.len()?;
+                let mut operators = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let pattern = self.str()?;
+                    let precedence = self.u8()?;
+                    let associativity = match self.u8()? {
+                        0 => Assoc::Left,
+                        1 => Assoc::Right,
+                        2 => Assoc::NonAssoc,
+                        tag => return Err(self.invalid_tag("associativity", tag)),
+                    };
+                    let fixity = match self.u8()? {
+                        0 => Fixity::Infix,
+                        1 => Fixity::Prefix,
+                        2 => Fixity::Postfix,
+                        tag => return Err(self.invalid_tag("fixity", tag)),
+                    };
+                    operators.push(InfixOperator {
+                        fixity,
+                        ..InfixOperator::new(pattern, precedence, associativity)
+                    });
+                }
+                Atom::Infix { operand, operators }
+            }
+            TAG_CUT => Atom::Cut,
+            TAG_EOF => Atom::Eof,
+            TAG_INDENT => Atom::Indent,
+            TAG_SAMEDENT => Atom::Samedent,
+            TAG_DEDENT => Atom::Dedent,
+            TAG_IGNORE => Atom::Ignore {
+                atom: self.usize()?,
+            },
+            TAG_TEXT => Atom::Text {
+                atom: self.usize()?,
+            },
+            TAG_CAPTURE => Atom::Capture {
+                name: self.str()?,
+                atom: self.usize()?,
+            },
+            TAG_BACK_REF => Atom::BackRef { name: self.str()? },
+            TAG_PARSE_INT => Atom::ParseInt {
+                atom: self.usize()?,
+                radix_prefixes: self.bool()?,
+            },
+            TAG_PARSE_FLOAT => Atom::ParseFloat {
+                atom: self.usize()?,
+            },
+            TAG_OPTIONAL_DEFAULT => {
+                let name = self.str()?;
+                let atom = self.usize()?;
+                let default = self.default_value()?;
+                Atom::OptionalDefault {
+                    name,
+                    atom,
+                    default,
+                }
+            }
+            TAG_CONST => Atom::Const {
+                atom: self.usize()?,
+                value: self.default_value()?,
+            },
+            TAG_SCOPE => Atom::Scope {
+                atom: self.usize()?,
+            },
+            TAG_DYNAMIC => Atom::Dynamic {
+                callback_id: self.u64()?,
+            },
+            TAG_CUSTOM => Atom::Custom { id: self.u64()? },
+            TAG_ONE_OF => Atom::OneOf {
+                chars: self.chars()?,
+            },
+            TAG_NONE_OF => Atom::NoneOf {
+                chars: self.chars()?,
+            },
+            TAG_UNTIL => Atom::Until {
+                terminator: self.str()?,
+                inclusive: self.bool()?,
+                or_end: self.bool()?,
+            },
+            TAG_BYTE_RANGE => Atom::ByteRange {
+                lo: self.u8()?,
+                hi: self.u8()?,
+            },
+            TAG_REPETITION_LAZY => Atom::RepetitionLazy {
+                atom: self.usize()?,
+                min: self.usize()?,
+                max: self.opt_usize()?,
+                follow: self.usize()?,
+            },
+            tag => return Err(self.invalid_tag("atom", tag)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One atom of every kind
+    fn every_atom_grammar() -> Grammar {
+        let atoms = vec![
+            Atom::Str {
+                pattern: "héllo".to_string(),
+            },
+            Atom::StrCi {
+                pattern: "select".to_string(),
+            },
+            Atom::Re {
+                pattern: "[0-9]".to_string(),
+            },
+            Atom::CharRange {
+                lo: 'a',
+                hi: '\u{1F600}',
+            },
+            Atom::Sequence {
+                atoms: vec![0, 1, 300],
+            },
+            Atom::Alternative { atoms: vec![] },
+            Atom::Repetition {
+                atom: 2,
+                min: 1,
+                max: None,
+            },
+            Atom::Repetition {
+                atom: 2,
+                min: 0,
+                max: Some(0),
+            },
+            Atom::Named {
+                name: "value".to_string(),
+                atom: 6,
+            },
+            Atom::Entity { atom: 8 },
+            Atom::Instantiate {
+                template: "list".to_string(),
+                args: vec![0, 2],
+            },
+            Atom::Lookahead {
+                atom: 0,
+                positive: false,
+            },
+            Atom::Peek { atom: 1 },
+            Atom::Infix {
+                operand: 6,
+                operators: vec![
+                    InfixOperator::new("+", 1, Assoc::Left),
+                    InfixOperator::new("**", 3, Assoc::Right),
+                    InfixOperator::new("<", 0, Assoc::NonAssoc),
+                    InfixOperator::prefix("-", 4),
+                    InfixOperator::postfix("!", 5),
+                ],
+            },
+            Atom::Cut,
+            Atom::Eof,
+            Atom::Ignore { atom: 2 },
+            Atom::Text { atom: 2 },
+            Atom::Capture {
+                name: "tag".to_string(),
+                atom: 0,
+            },
+            Atom::BackRef {
+                name: "tag".to_string(),
+            },
+            Atom::OptionalDefault {
+                name: "n".to_string(),
+                atom: 6,
+                default: DefaultValue::Int(-42),
+            },
+            Atom::OptionalDefault {
+                name: "x".to_string(),
+                atom: 6,
+                default: DefaultValue::Float(1.5),
+            },
+            Atom::Scope { atom: 4 },
+            Atom::ParseInt {
+                atom: 6,
+                radix_prefixes: true,
+            },
+            Atom::ParseFloat { atom: 6 },
+            Atom::Const {
+                atom: 6,
+                value: DefaultValue::Bool(true),
+            },
+            Atom::Dynamic {
+                callback_id: u64::MAX,
+            },
+            Atom::Custom { id: 1000 },
+            Atom::Indent,
+            Atom::Samedent,
+            Atom::Dedent,
+            Atom::OneOf {
+                chars: vec!['+', '\u{e9}'],
+            },
+            Atom::NoneOf {
+                chars: vec!['"', '\\'],
+            },
+            Atom::LongestAlternative {
+                atoms: vec![0, 1, 17],
+            },
+            Atom::Until {
+                terminator: "*/".to_string(),
+                inclusive: true,
+                or_end: false,
+            },
+            Atom::ByteRange { lo: 0x80, hi: 0xff },
+            Atom::RepetitionLazy {
+                atom: 2,
+                min: 0,
+                max: Some(8),
+                follow: 0,
+            },
+        ];
+        let mut grammar = Grammar::from_atoms(atoms, 4);
+        grammar.memoized = Some([4, 8].into_iter().collect());
+        grammar.ascii_only = true;
+        grammar.skip = Some(5);
+        grammar.no_skip = [0, 6].into_iter().collect();
+        grammar
+            .rule_descriptions
+            .insert(2, "a lowercase word".to_string());
+        grammar
+    }
+
+    #[test]
+    fn test_round_trip_every_atom() {
+        let grammar = every_atom_grammar();
+        let bytes = grammar.to_bytes();
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(Grammar::from_bytes(&bytes).unwrap(), grammar);
+
+        let plain = Grammar::from_atoms(grammar.atoms.clone(), 0);
+        assert_eq!(Grammar::from_bytes(&plain.to_bytes()).unwrap(), plain);
+    }
+
+    #[test]
+    fn test_rejects_bad_headers_and_truncation() {
+        let bytes = every_atom_grammar().to_bytes();
+
+        assert_eq!(
+            Grammar::from_bytes(b"{\"atoms\": []}"),
+            Err(GrammarBytesError::BadMagic)
+        );
+
+        let mut future = bytes.clone();
+        future[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            Grammar::from_bytes(&future),
+            Err(GrammarBytesError::UnsupportedVersion {
+                found: FORMAT_VERSION + 1
+            })
+        );
+
+        // Every strict prefix is an error, never a panic
+        for len in 0..bytes.len() {
+            assert!(
+                Grammar::from_bytes(&bytes[..len]).is_err(),
+                "prefix {}",
+                len
+            );
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            Grammar::from_bytes(&trailing),
+            Err(GrammarBytesError::TrailingBytes {
+                offset: bytes.len()
+            })
+        );
+
+        let mut bad_tag = Grammar::from_atoms(vec![Atom::Cut], 0).to_bytes();
+        *bad_tag.last_mut().unwrap() = 0xff;
+        assert!(matches!(
+            Grammar::from_bytes(&bad_tag),
+            Err(GrammarBytesError::InvalidTag {
+                kind: "atom",
+                tag: 0xff,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+//! Shared cache of deserialized grammars
+//!
+//! Applications that parse many inputs against a handful of JSON grammars
+//! would otherwise pay for `serde_json` parsing and atom allocation on every
+//! call. [`GrammarCache`] deserializes each distinct grammar once and hands
+//! out [`Arc`]s to it, the same way the Ruby extension caches grammars
+//! across calls.
+//!
+//! # Example
+//!
+//! ```
+//! use parsanol::portable::{AstArena, GrammarCache, PortableParser};
+//! use std::sync::Arc;
+//!
+//! let cache = GrammarCache::new();
+//! let json = r#"{"atoms": [{"Str": {"pattern": "hi"}}], "root": 0}"#;
+//!
+//! let grammar = cache.get_or_compile(json).unwrap();
+//! assert!(Arc::ptr_eq(&grammar, &cache.get_or_compile(json).unwrap()));
+//!
+//! let mut arena = AstArena::new();
+//! assert!(PortableParser::new(&grammar, "hi", &mut arena).parse().is_ok());
+//! ```
+
+use super::ast::ParseError;
+use super::grammar::Grammar;
+use ahash::AHashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+/// Thread-safe cache of grammars keyed by their JSON
+///
+/// Entries are keyed by an `ahash` of the JSON text; the text itself is
+/// kept alongside so a hash collision never returns the wrong grammar. The
+/// cache is unbounded: call [`clear`](Self::clear) to release grammars
+/// that are no longer needed.
+#[derive(Debug, Default)]
+pub struct GrammarCache {
+    grammars: RwLock<AHashMap<u64, (String, Arc<Grammar>)>>,
+}
+
+impl GrammarCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the grammar for `json`, deserializing it on first use
+    ///
+    /// Returns [`ParseError::InvalidGrammar`] if `json` isn't a valid
+    /// grammar; failures are not cached.
+    pub fn get_or_compile(&self, json: &str) -> Result<Arc<Grammar>, ParseError> {
+        self.get_or_insert_with(json, |json| {
+            Grammar::from_json(json).map_err(|e| ParseError::InvalidGrammar {
+                reason: e.to_string(),
+            })
+        })
+    }
+
+    /// Number of cached grammars
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Drop every cached grammar
+    ///
+    /// `Arc`s handed out earlier stay valid.
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    fn get_or_insert_with(
+        &self,
+        json: &str,
+        compile: impl FnOnce(&str) -> Result<Grammar, ParseError>,
+    ) -> Result<Arc<Grammar>, ParseError> {
+        let key = hash_json(json);
+        if let Some((cached_json, grammar)) = self.read().get(&key) {
+            if cached_json == json {
+                return Ok(Arc::clone(grammar));
+            }
+        }
+
+        // Compile outside the lock; a concurrent caller may race us, in
+        // which case the first grammar inserted wins
+        let grammar = Arc::new(compile(json)?);
+        let mut grammars = self.write();
+        match grammars.get(&key) {
+            Some((cached_json, cached)) if cached_json == json => Ok(Arc::clone(cached)),
+            // Hash collision with a different grammar: serve it uncached
+            Some(_) => Ok(grammar),
+            None => {
+                grammars.insert(key, (json.to_string(), Arc::clone(&grammar)));
+                Ok(grammar)
+            }
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, AHashMap<u64, (String, Arc<Grammar>)>> {
+        self.grammars.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, AHashMap<u64, (String, Arc<Grammar>)>> {
+        self.grammars.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn hash_json(json: &str) -> u64 {
+    let mut hasher = ahash::AHasher::default();
+    json.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portable::grammar::Atom;
+
+    const HELLO_JSON: &str = r#"{"atoms": [{"Str": {"pattern": "hello"}}], "root": 0}"#;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_same_json_shares_allocation() {
+        assert_send_sync::<GrammarCache>();
+
+        let cache = GrammarCache::new();
+        let first = cache.get_or_compile(HELLO_JSON).unwrap();
+        let second = cache.get_or_compile(HELLO_JSON).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.len(), 1);
+
+        let other = cache
+            .get_or_compile(r#"{"atoms": [{"Str": {"pattern": "bye"}}], "root": 0}"#)
+            .unwrap();
+        assert!(!Arc::ptr_eq(&first, &other));
+        assert_eq!(cache.len(), 2);
+
+        assert!(cache.get_or_compile("not a grammar").is_err());
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_clear_keeps_handed_out_grammars() {
+        let cache = GrammarCache::new();
+        let compile = |_: &str| {
+            let mut grammar = Grammar::new();
+            grammar.root = grammar.add_atom(Atom::Str {
+                pattern: "hello".to_string(),
+            });
+            Ok(grammar)
+        };
+
+        let first = cache.get_or_insert_with("hello", compile).unwrap();
+        assert!(Arc::ptr_eq(
+            &first,
+            &cache.get_or_insert_with("hello", compile).unwrap()
+        ));
+        assert!(!cache.is_empty());
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(first.atom_count(), 1);
+
+        let second = cache.get_or_insert_with("hello", compile).unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+    }
+}
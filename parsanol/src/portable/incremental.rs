@@ -66,6 +66,7 @@ use super::{
     cache::DenseCache,
     grammar::Grammar,
 };
+use std::ops::Range;
 
 /// Represents a change to the input
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -289,6 +290,62 @@ impl DirtyRegionTracker {
     pub fn is_empty(&self) -> bool {
         self.regions.is_empty()
     }
+
+    /// Render the input with dirty regions underlined by `^` markers
+    ///
+    /// Each source line is followed by a marker line when it contains dirty
+    /// bytes. Empty regions (pure insertions) are shown as a single `^` at
+    /// their position. Intended as a debugging aid for checking how edits
+    /// map to dirty regions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parsanol::portable::{DirtyRegion, DirtyRegionTracker};
+    ///
+    /// let mut tracker = DirtyRegionTracker::new();
+    /// tracker.mark_dirty(DirtyRegion::new(4, 7));
+    /// assert_eq!(tracker.visualize("let foo = 1;"), "let foo = 1;\n    ^^^\n");
+    /// ```
+    pub fn visualize(&self, input: &str) -> String {
+        let is_marked = |pos: usize| {
+            self.is_dirty(pos)
+                || self
+                    .regions
+                    .iter()
+                    .any(|r| r.start == r.end && r.start == pos)
+        };
+
+        let mut out = String::new();
+        let mut line_start = 0;
+        for line in input.split_inclusive('\n') {
+            let content = line.trim_end_matches(['\n', '\r']);
+            let line_end = line_start + content.len();
+
+            let mut markers = String::new();
+            for (i, _) in content.char_indices() {
+                markers.push(if is_marked(line_start + i) { '^' } else { ' ' });
+            }
+            // Insertions at the end of a line have no character to underline
+            if self
+                .regions
+                .iter()
+                .any(|r| r.start == line_end && r.end == line_end)
+            {
+                markers.push('^');
+            }
+
+            out.push_str(content);
+            out.push('\n');
+            if markers.contains('^') {
+                out.push_str(markers.trim_end());
+                out.push('\n');
+            }
+
+            line_start += line.len();
+        }
+        out
+    }
 }
 
 impl Default for DirtyRegionTracker {
@@ -374,6 +431,65 @@ impl<'a> IncrementalParser<'a> {
         })
     }
 
+    /// Re-parse after an edit, reusing cached results on both sides of it
+    ///
+    /// Unlike [`parse_with_edit`](Self::parse_with_edit), which drops every
+    /// result at or after the edit, this shifts results that start after
+    /// the edited text by the edit's length change and keeps them. Results
+    /// overlapping the edit's dirty region, or ending right at it, are
+    /// evicted; so are failures before the edit, since a failure doesn't
+    /// record how far ahead it looked. Reuse assumes a successful match
+    /// depends only on the text it consumed plus the next character, so
+    /// grammars with longer lookahead past the end of a match (or with
+    /// captures and back-references) should use [`parse`](Self::parse).
+    ///
+    /// Shifted results that are arrays or hashes are copied into `arena`
+    /// with their input positions updated, so `arena` must be the arena
+    /// used by the previous parse, not reset in between.
+    pub fn reparse(
+        &mut self,
+        input: &str,
+        arena: &mut AstArena,
+        edit: Edit,
+    ) -> Result<IncrementalResult, ParseError> {
+        // Only this edit is dirty: earlier regions are in stale coordinates
+        // and their entries were already evicted
+        self.dirty_tracker.clear();
+        self.dirty_tracker.mark_edit(&edit);
+        let region = DirtyRegion::new(edit.offset, edit.offset + edit.old_length);
+        let delta = edit.delta();
+
+        let before_count = self.cache.len();
+        self.cache
+            .clear_region_and_shift(region.start as u32, region.end as u32, delta as i64);
+        let reused = self.cache.len();
+
+        // Entries kept in place end before the edit; the shifted ones now
+        // start at or after its new text. Each cached node belongs to a
+        // single entry, but don't rely on it.
+        let mut shifted: Vec<u32> = self
+            .cache
+            .entries()
+            .filter(|entry| entry.success() && entry.pos as usize >= region.start)
+            .map(|entry| entry.ast_ref())
+            .collect();
+        shifted.sort_unstable();
+        shifted.dedup();
+        for ast_ref in shifted {
+            let node = &self.cached_nodes[ast_ref as usize];
+            self.cached_nodes[ast_ref as usize] = shift_node(node, delta, arena);
+        }
+
+        self.prev_input_len = input.len();
+        let ast = self.parse_incremental(input, arena)?;
+
+        Ok(IncrementalResult {
+            ast,
+            reused_cache_entries: reused,
+            invalidated_cache_entries: before_count - reused,
+        })
+    }
+
     /// Re-parse after multiple edits
     pub fn parse_with_edits(
         &mut self,
@@ -513,6 +629,40 @@ impl<'a> IncrementalParser<'a> {
     }
 }
 
+/// Move the input positions in `node` by `delta`, copying arrays and
+/// hashes into `arena`
+///
+/// Interned strings are copies of the input and are left alone.
+fn shift_node(node: &AstNode, delta: isize, arena: &mut AstArena) -> AstNode {
+    match node {
+        AstNode::InputRef { offset, length } => AstNode::InputRef {
+            offset: (*offset as isize + delta) as u32,
+            length: *length,
+        },
+        AstNode::Array { pool_index, length } => {
+            let items: Vec<AstNode> = arena
+                .get_array(*pool_index as usize, *length as usize)
+                .iter()
+                .map(|item| shift_node(item, delta, arena))
+                .collect();
+            arena.alloc_array(items)
+        }
+        AstNode::Hash { pool_index, length } => {
+            let pairs: Vec<(String, AstNode)> = arena
+                .get_hash_items(*pool_index as usize, *length as usize)
+                .into_iter()
+                .map(|(key, value)| (key, shift_node(&value, delta, arena)))
+                .collect();
+            arena.alloc_hash(pairs)
+        }
+        AstNode::Tagged { tag, value } => AstNode::Tagged {
+            tag: *tag,
+            value: Box::new(shift_node(value, delta, arena)),
+        },
+        _ => node.clone(),
+    }
+}
+
 /// Result of an incremental parse
 #[derive(Debug)]
 pub struct IncrementalResult {
@@ -539,6 +689,222 @@ impl IncrementalResult {
     }
 }
 
+/// Text that records its edits for the incremental parser
+///
+/// Editors describe changes as "replace this range with that text" rather
+/// than as [`Edit`] lengths. `EditableText` applies such changes to the
+/// text it owns, checks that they land on character boundaries, and keeps
+/// the regions changed since the last parse in current coordinates.
+/// [`take_edit`](Self::take_edit) turns those changes into one [`Edit`] for
+/// [`IncrementalParser::reparse`].
+///
+/// # Example
+///
+/// ```
+/// use parsanol::portable::incremental::{DirtyRegion, EditableText};
+///
+/// let mut text = EditableText::new("let x = 1;");
+/// text.replace(4..5, "total").unwrap();
+/// text.insert(13, "0").unwrap();
+///
+/// assert_eq!(text.text(), "let total = 10;");
+/// assert_eq!(text.dirty_regions(), &[DirtyRegion::new(4, 9), DirtyRegion::new(13, 14)]);
+///
+/// // One edit covering both changes, in the coordinates of the last parse
+/// let edit = text.take_edit().unwrap();
+/// assert_eq!((edit.offset, edit.old_length, edit.new_length), (4, 5, 10));
+/// assert!(text.dirty_regions().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct EditableText {
+    /// The current text
+    text: String,
+    /// Regions changed since the last `take_edit`, in current coordinates
+    dirty: DirtyRegionTracker,
+    /// Length change since the last `take_edit`
+    delta: isize,
+}
+
+/// Why a change couldn't be applied to an [`EditableText`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// The range ends past the end of the text, or starts after it ends
+    OutOfBounds {
+        /// Start of the rejected range
+        start: usize,
+        /// End of the rejected range
+        end: usize,
+        /// Length of the text
+        len: usize,
+    },
+    /// A range boundary falls inside a multi-byte character
+    NotCharBoundary {
+        /// The offending byte offset
+        offset: usize,
+    },
+    /// Two changes in one batch touch the same text
+    Overlapping {
+        /// Start of the first range
+        first: usize,
+        /// Start of the range that overlaps it
+        second: usize,
+    },
+}
+
+impl std::fmt::Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfBounds { start, end, len } => write!(
+                f,
+                "edit range {}..{} is out of bounds for text of length {}",
+                start, end, len
+            ),
+            Self::NotCharBoundary { offset } => {
+                write!(f, "edit offset {} is not on a character boundary", offset)
+            }
+            Self::Overlapping { first, second } => {
+                write!(f, "edits at offsets {} and {} overlap", first, second)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+impl EditableText {
+    /// Wrap `text`, with no changes pending
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            dirty: DirtyRegionTracker::new(),
+            delta: 0,
+        }
+    }
+
+    /// The current text
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Insert `new_text` at `offset`
+    pub fn insert(&mut self, offset: usize, new_text: &str) -> Result<Edit, EditError> {
+        self.replace(offset..offset, new_text)
+    }
+
+    /// Delete the bytes in `range`
+    pub fn delete(&mut self, range: Range<usize>) -> Result<Edit, EditError> {
+        self.replace(range, "")
+    }
+
+    /// Replace the bytes in `range` with `new_text`
+    ///
+    /// Returns the [`Edit`] describing the change. Nothing changes if the
+    /// range is out of bounds or splits a character.
+    pub fn replace(&mut self, range: Range<usize>, new_text: &str) -> Result<Edit, EditError> {
+        self.check_range(&range)?;
+        Ok(self.apply(range, new_text))
+    }
+
+    /// Apply several changes whose ranges all refer to the current text
+    ///
+    /// The changes may be given in any order, but must not overlap; two
+    /// insertions at the same offset count as overlapping, since their
+    /// order would be ambiguous. Either every change is applied or, on
+    /// error, none is. Returns one [`Edit`] per change, in the order they
+    /// were applied (last in the text first), so each is relative to the
+    /// text left by the one before.
+    pub fn apply_batch(
+        &mut self,
+        changes: &[(Range<usize>, &str)],
+    ) -> Result<Vec<Edit>, EditError> {
+        let mut sorted: Vec<&(Range<usize>, &str)> = changes.iter().collect();
+        sorted.sort_by_key(|(range, _)| (range.start, range.end));
+        for (range, _) in &sorted {
+            self.check_range(range)?;
+        }
+        for pair in sorted.windows(2) {
+            let (first, second) = (&pair[0].0, &pair[1].0);
+            if second.start < first.end || second.start == first.start {
+                return Err(EditError::Overlapping {
+                    first: first.start,
+                    second: second.start,
+                });
+            }
+        }
+
+        // Back to front, so earlier ranges stay valid
+        Ok(sorted
+            .into_iter()
+            .rev()
+            .map(|(range, new_text)| self.apply(range.clone(), new_text))
+            .collect())
+    }
+
+    /// Regions of the current text changed since the last
+    /// [`take_edit`](Self::take_edit)
+    ///
+    /// Sorted and merged; a deletion leaves an empty region where the text
+    /// was removed.
+    #[inline]
+    pub fn dirty_regions(&self) -> &[DirtyRegion] {
+        self.dirty.regions()
+    }
+
+    /// One [`Edit`] covering every change since the last call
+    ///
+    /// The edit spans from the first to the last dirty region; text between
+    /// them counts as replaced. Its offset and old length are in the
+    /// coordinates of the text at the last call, which is what
+    /// [`IncrementalParser::reparse`] expects. Returns `None` when nothing
+    /// changed. Clears the dirty regions.
+    pub fn take_edit(&mut self) -> Option<Edit> {
+        let regions = self.dirty.regions();
+        let start = regions.first()?.start;
+        let end = regions.last()?.end;
+        let old_end = (end as isize - self.delta) as usize;
+        let edit = Edit::replace(start, old_end - start, end - start);
+        self.dirty.clear();
+        self.delta = 0;
+        Some(edit)
+    }
+
+    /// Check that `range` lies within the text on character boundaries
+    fn check_range(&self, range: &Range<usize>) -> Result<(), EditError> {
+        if range.start > range.end || range.end > self.text.len() {
+            return Err(EditError::OutOfBounds {
+                start: range.start,
+                end: range.end,
+                len: self.text.len(),
+            });
+        }
+        for offset in [range.start, range.end] {
+            if !self.text.is_char_boundary(offset) {
+                return Err(EditError::NotCharBoundary { offset });
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace a checked range and move the dirty regions to match
+    fn apply(&mut self, range: Range<usize>, new_text: &str) -> Edit {
+        let edit = Edit::replace(range.start, range.len(), new_text.len());
+        self.text.replace_range(range, new_text);
+
+        let mut dirty = DirtyRegionTracker::new();
+        for region in self.dirty.regions() {
+            dirty.mark_dirty(DirtyRegion::new(
+                edit.translate_position(region.start),
+                edit.translate_position(region.end),
+            ));
+        }
+        dirty.mark_dirty(DirtyRegion::new(edit.offset, edit.offset + edit.new_length));
+        self.dirty = dirty;
+        self.delta += edit.delta();
+        edit
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -610,6 +976,35 @@ mod tests {
         assert_eq!(tracker.regions().len(), 2);
     }
 
+    #[test]
+    fn test_dirty_region_visualize() {
+        let old = "let x = 1;\nlet y = 2;\n";
+        let edit = Edit::replace(15, 1, 2); // "y" -> "yy"
+
+        let mut tracker = DirtyRegionTracker::new();
+        tracker.mark_edit(&edit);
+        assert_eq!(tracker.regions(), &[DirtyRegion::new(15, 16)]);
+
+        assert_eq!(tracker.visualize(old), "let x = 1;\nlet y = 2;\n    ^\n");
+
+        // Regions spanning lines underline each affected line
+        tracker.mark_dirty(DirtyRegion::new(8, 13));
+        assert_eq!(
+            tracker.visualize(old),
+            "let x = 1;\n        ^^\nlet y = 2;\n^^  ^\n"
+        );
+    }
+
+    #[test]
+    fn test_dirty_region_visualize_insertion() {
+        let mut tracker = DirtyRegionTracker::new();
+        tracker.mark_edit(&Edit::insert(3, 2));
+        assert_eq!(tracker.visualize("abc"), "abc\n   ^\n");
+
+        tracker.clear();
+        assert_eq!(tracker.visualize("abc"), "abc\n");
+    }
+
     #[test]
     fn test_dirty_region_merge() {
         let mut tracker = DirtyRegionTracker::new();
@@ -640,6 +1035,160 @@ mod tests {
         assert!(t.is_range_dirty(0, 30));
     }
 
+    #[test]
+    fn test_reparse_reuses_cache_around_edit() {
+        use crate::portable::parser_dsl::*;
+        use crate::portable::transform::ast_to_value;
+
+        let grammar = GrammarBuilder::new()
+            .rule("doc", ref_("line").many1())
+            .rule("line", re("[a-z]").many1().label("word").then(str(";")))
+            .build();
+        let old_input = "abc;".repeat(50);
+        // One-character insertion in the 41st line
+        let mut new_input = old_input.clone();
+        new_input.insert(161, 'x');
+
+        let mut parser = IncrementalParser::new(&grammar);
+        let mut arena = AstArena::new();
+        parser.parse(&old_input, &mut arena).unwrap();
+        let result = parser
+            .reparse(&new_input, &mut arena, Edit::insert(161, 1))
+            .unwrap();
+        assert!(result.efficiency() > 0.9, "{:?}", result);
+        assert_eq!(parser.dirty_region_count(), 1);
+
+        let mut fresh_arena = AstArena::new();
+        let mut fresh_parser =
+            crate::portable::parser::PortableParser::new(&grammar, &new_input, &mut fresh_arena);
+        let fresh_ast = fresh_parser.parse().unwrap();
+        assert_eq!(
+            ast_to_value(&result.ast, &arena, &new_input),
+            ast_to_value(&fresh_ast, &fresh_arena, &new_input)
+        );
+
+        // A second edit, before the first, shifts the entries again
+        let mut newer_input = new_input.clone();
+        newer_input.replace_range(8..12, "");
+        let result = parser
+            .reparse(&newer_input, &mut arena, Edit::delete(8, 4))
+            .unwrap();
+        let mut fresh_arena = AstArena::new();
+        let mut fresh_parser =
+            crate::portable::parser::PortableParser::new(&grammar, &newer_input, &mut fresh_arena);
+        let fresh_ast = fresh_parser.parse().unwrap();
+        assert_eq!(
+            ast_to_value(&result.ast, &arena, &newer_input),
+            ast_to_value(&fresh_ast, &fresh_arena, &newer_input)
+        );
+    }
+
+    #[test]
+    fn test_editable_text_tracks_edits() {
+        let mut text = EditableText::new("fn main() {}");
+
+        assert_eq!(text.insert(11, " run(); ").unwrap(), Edit::insert(11, 8));
+        assert_eq!(text.text(), "fn main() { run(); }");
+        assert_eq!(text.replace(3..7, "go").unwrap(), Edit::replace(3, 4, 2));
+        assert_eq!(text.text(), "fn go() { run(); }");
+        // The earlier region moved left with the text after the rename
+        assert_eq!(
+            text.dirty_regions(),
+            &[DirtyRegion::new(3, 5), DirtyRegion::new(9, 17)]
+        );
+
+        // Deleting text that was just inserted leaves an empty region
+        assert_eq!(text.delete(9..12).unwrap(), Edit::delete(9, 3));
+        assert_eq!(text.text(), "fn go() {n(); }");
+        assert_eq!(
+            text.dirty_regions(),
+            &[DirtyRegion::new(3, 5), DirtyRegion::new(9, 14)]
+        );
+
+        // "main() {" (old 3..11) became "go() {n(); " (new 3..14)
+        assert_eq!(text.take_edit(), Some(Edit::replace(3, 8, 11)));
+        assert_eq!(text.take_edit(), None);
+
+        // A deletion on its own is an empty dirty region
+        text.delete(7..8).unwrap();
+        assert_eq!(text.dirty_regions(), &[DirtyRegion::new(7, 7)]);
+        assert_eq!(text.take_edit(), Some(Edit::delete(7, 1)));
+    }
+
+    #[test]
+    fn test_editable_text_rejects_bad_edits() {
+        let mut text = EditableText::new("día 1");
+
+        assert_eq!(
+            text.insert(2, "x"),
+            Err(EditError::NotCharBoundary { offset: 2 })
+        );
+        assert_eq!(
+            text.delete(4..9),
+            Err(EditError::OutOfBounds {
+                start: 4,
+                end: 9,
+                len: 6
+            })
+        );
+        assert_eq!(
+            text.apply_batch(&[(0..1, "D"), (5..6, "2"), (4..6, "")]),
+            Err(EditError::Overlapping {
+                first: 4,
+                second: 5
+            })
+        );
+        assert_eq!(
+            text.apply_batch(&[(0..0, "a"), (0..0, "b")]),
+            Err(EditError::Overlapping {
+                first: 0,
+                second: 0
+            })
+        );
+        // Rejected batches change nothing
+        assert_eq!(text.text(), "día 1");
+        assert!(text.dirty_regions().is_empty());
+
+        let edits = text.apply_batch(&[(5..6, "2"), (0..1, "D")]).unwrap();
+        assert_eq!(edits, [Edit::replace(5, 1, 1), Edit::replace(0, 1, 1)]);
+        assert_eq!(text.text(), "Día 2");
+        assert_eq!(
+            text.dirty_regions(),
+            &[DirtyRegion::new(0, 1), DirtyRegion::new(5, 6)]
+        );
+    }
+
+    #[test]
+    fn test_editable_text_drives_reparse() {
+        use crate::portable::parser_dsl::*;
+        use crate::portable::transform::ast_to_value;
+
+        let grammar = GrammarBuilder::new()
+            .rule("doc", ref_("line").many1())
+            .rule("line", re("[a-z]").many1().label("word").then(str(";")))
+            .build();
+        let mut text = EditableText::new("abc;".repeat(20));
+
+        let mut parser = IncrementalParser::new(&grammar);
+        let mut arena = AstArena::new();
+        parser.parse(text.text(), &mut arena).unwrap();
+
+        text.insert(41, "xy").unwrap();
+        text.delete(60..64).unwrap();
+        let edit = text.take_edit().unwrap();
+        let result = parser.reparse(text.text(), &mut arena, edit).unwrap();
+
+        let mut fresh_arena = AstArena::new();
+        let fresh_ast =
+            crate::portable::parser::PortableParser::new(&grammar, text.text(), &mut fresh_arena)
+                .parse()
+                .unwrap();
+        assert_eq!(
+            ast_to_value(&result.ast, &arena, text.text()),
+            ast_to_value(&fresh_ast, &fresh_arena, text.text())
+        );
+    }
+
     #[test]
     fn test_incremental_result_efficiency() {
         let result = IncrementalResult {
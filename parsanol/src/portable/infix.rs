@@ -18,9 +18,10 @@
 
 use super::grammar::Atom;
 use super::parser_dsl::{GrammarBuilder, Parslet, Ref, Str};
+use serde::{Deserialize, Serialize};
 
 /// Operator associativity
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Assoc {
     /// Left-associative: a + b + c = (a + b) + c
     Left,
@@ -30,6 +31,18 @@ pub enum Assoc {
     NonAssoc,
 }
 
+/// Where an operator stands relative to its operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Fixity {
+    /// Binary, between two operands: `a + b`
+    #[default]
+    Infix,
+    /// Unary, before its operand: `-a`
+    Prefix,
+    /// Unary, after its operand: `a!`
+    Postfix,
+}
+
 /// An operator definition for infix parsing
 #[derive(Clone)]
 pub struct Operator<'a> {
@@ -37,17 +50,101 @@ pub struct Operator<'a> {
     pub op: Str<'a>,
     /// Precedence (higher = binds tighter)
     pub precedence: u8,
-    /// Associativity
+    /// Associativity (only meaningful for [`Fixity::Infix`])
     pub associativity: Assoc,
+    /// Whether the operator is binary, prefix or postfix
+    pub fixity: Fixity,
 }
 
 impl<'a> Operator<'a> {
-    /// Create a new operator
+    /// Create a new binary operator
     pub fn new(op: &'a str, precedence: u8, associativity: Assoc) -> Self {
         Self {
             op: Str(op),
             precedence,
             associativity,
+            fixity: Fixity::Infix,
+        }
+    }
+
+    /// Create a unary prefix operator such as `-x`
+    pub fn prefix(op: &'a str, precedence: u8) -> Self {
+        Self {
+            fixity: Fixity::Prefix,
+            ..Self::new(op, precedence, Assoc::Right)
+        }
+    }
+
+    /// Create a unary postfix operator such as `x!`
+    pub fn postfix(op: &'a str, precedence: u8) -> Self {
+        Self {
+            fixity: Fixity::Postfix,
+            ..Self::new(op, precedence, Assoc::Left)
+        }
+    }
+}
+
+/// A literal operator of an [`Atom::Infix`] expression
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct InfixOperator {
+    /// The operator text
+    pub pattern: String,
+    /// Precedence (higher = binds tighter)
+    pub precedence: u8,
+    /// Associativity (only meaningful for [`Fixity::Infix`])
+    pub associativity: Assoc,
+    /// Whether the operator is binary, prefix or postfix
+    #[serde(default, skip_serializing_if = "is_infix")]
+    pub fixity: Fixity,
+}
+
+/// Serde helper: binary operators leave `fixity` out of the JSON
+fn is_infix(fixity: &Fixity) -> bool {
+    *fixity == Fixity::Infix
+}
+
+impl InfixOperator {
+    /// Create a new binary operator
+    pub fn new(pattern: impl Into<String>, precedence: u8, associativity: Assoc) -> Self {
+        Self {
+            pattern: pattern.into(),
+            precedence,
+            associativity,
+            fixity: Fixity::Infix,
+        }
+    }
+
+    /// Create a unary prefix operator such as `-x`
+    ///
+    /// The operand binds every operator of at least this precedence, so
+    /// with `-` at 3 and `+` at 1, `-3 + 4` is `(-3) + 4`.
+    pub fn prefix(pattern: impl Into<String>, precedence: u8) -> Self {
+        Self {
+            fixity: Fixity::Prefix,
+            ..Self::new(pattern, precedence, Assoc::Right)
+        }
+    }
+
+    /// Create a unary postfix operator such as `x!`
+    pub fn postfix(pattern: impl Into<String>, precedence: u8) -> Self {
+        Self {
+            fixity: Fixity::Postfix,
+            ..Self::new(pattern, precedence, Assoc::Left)
+        }
+    }
+}
+
+impl From<(&str, u8, Assoc)> for InfixOperator {
+    fn from((pattern, precedence, associativity): (&str, u8, Assoc)) -> Self {
+        Self::new(pattern, precedence, associativity)
+    }
+}
+
+impl From<Operator<'_>> for InfixOperator {
+    fn from(op: Operator<'_>) -> Self {
+        Self {
+            fixity: op.fixity,
+            ..Self::new(op.op.0, op.precedence, op.associativity)
         }
     }
 }
@@ -57,7 +154,7 @@ pub struct InfixBuilder<'a> {
     /// Primary expression parser (atoms like numbers, identifiers, parenthesized expressions)
     primary: Option<Ref<'a>>,
     /// Operators grouped by precedence level
-    operators: Vec<(Vec<Str<'a>>, u8, Assoc)>,
+    operators: Vec<(Vec<Str<'a>>, u8, Assoc, Fixity)>,
     /// Custom rule name for the expression
     name: Option<&'a str>,
 }
@@ -79,17 +176,29 @@ impl<'a> InfixBuilder<'a> {
     }
 
     /// Add an operator
-    pub fn op(mut self, op: &'a str, precedence: u8, associativity: Assoc) -> Self {
+    pub fn op(self, op: &'a str, precedence: u8, associativity: Assoc) -> Self {
+        self.add(op, precedence, associativity, Fixity::Infix)
+    }
+
+    /// Add a unary prefix operator such as `-x`
+    pub fn prefix(self, op: &'a str, precedence: u8) -> Self {
+        self.add(op, precedence, Assoc::Right, Fixity::Prefix)
+    }
+
+    /// Add a unary postfix operator such as `x!`
+    pub fn postfix(self, op: &'a str, precedence: u8) -> Self {
+        self.add(op, precedence, Assoc::Left, Fixity::Postfix)
+    }
+
+    fn add(mut self, op: &'a str, precedence: u8, associativity: Assoc, fixity: Fixity) -> Self {
         // Find or create precedence level
-        if let Some((ops, _, _)) = self
-            .operators
-            .iter_mut()
-            .find(|(_, prec, assoc)| *prec == precedence && *assoc == associativity)
-        {
+        if let Some((ops, _, _, _)) = self.operators.iter_mut().find(|(_, prec, assoc, fix)| {
+            *prec == precedence && *assoc == associativity && *fix == fixity
+        }) {
             ops.push(Str(op));
         } else {
             self.operators
-                .push((vec![Str(op)], precedence, associativity));
+                .push((vec![Str(op)], precedence, associativity, fixity));
         }
         self
     }
@@ -124,8 +233,13 @@ impl<'a> InfixBuilder<'a> {
         // Build grammar from highest to lowest precedence
         let mut current_expr = primary_idx;
 
-        for (ops, _prec, assoc) in operators {
-            current_expr = Self::build_precedence_level(builder, current_expr, &ops, assoc);
+        for (ops, _prec, assoc, fixity) in operators {
+            current_expr = match fixity {
+                Fixity::Infix => Self::build_precedence_level(builder, current_expr, &ops, assoc),
+                Fixity::Prefix | Fixity::Postfix => {
+                    Self::build_unary_level(builder, current_expr, &ops, fixity)
+                }
+            };
         }
 
         current_expr
@@ -206,6 +320,39 @@ impl<'a> InfixBuilder<'a> {
             }
         }
     }
+
+    fn build_unary_level(
+        builder: &mut GrammarBuilder,
+        operand: usize,
+        ops: &[Str<'_>],
+        fixity: Fixity,
+    ) -> usize {
+        let op_indices: Vec<usize> = ops.iter().map(|op| op.build(builder)).collect();
+        let op_atom = builder.add_atom(Atom::Alternative { atoms: op_indices });
+
+        if fixity == Fixity::Postfix {
+            // Grammar: expr = operand op*
+            let repeat_idx = builder.add_atom(Atom::Repetition {
+                atom: op_atom,
+                min: 0,
+                max: None,
+            });
+            return builder.add_atom(Atom::Sequence {
+                atoms: vec![operand, repeat_idx],
+            });
+        }
+
+        // Grammar: expr = op expr | operand
+        let placeholder_idx = builder.add_atom(Atom::Entity { atom: 0 });
+        let seq_idx = builder.add_atom(Atom::Sequence {
+            atoms: vec![op_atom, placeholder_idx],
+        });
+        let expr_idx = builder.add_atom(Atom::Alternative {
+            atoms: vec![seq_idx, operand],
+        });
+        builder.update_atom(placeholder_idx, Atom::Entity { atom: expr_idx });
+        expr_idx
+    }
 }
 
 impl<'a> Default for InfixBuilder<'a> {
@@ -243,6 +390,63 @@ where
     builder
 }
 
+/// Operator-precedence expression parslet, created by [`infix_expr`]
+pub struct InfixExpr<P> {
+    operand: P,
+    operators: Vec<InfixOperator>,
+}
+
+impl<P: Parslet> Parslet for InfixExpr<P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let operand = self.operand.build(builder);
+        builder.add_atom(Atom::Infix {
+            operand,
+            operators: self.operators,
+        })
+    }
+}
+
+/// Parse an operator-precedence expression during the main parse
+///
+/// Unlike [`infix`], which expands the operator table into ordinary
+/// sequences and repetitions, this builds a single [`Atom::Infix`] that
+/// climbs precedence while parsing. Each operation becomes a
+/// `{left, op, right}` hash nested according to precedence and
+/// associativity; [`InfixOperator::prefix`] and [`InfixOperator::postfix`]
+/// operators produce `{prefix, operand}` and `{operand, postfix}` hashes.
+/// Operators are literal strings; put any surrounding whitespace in
+/// `operand`.
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::infix::{infix_expr, Assoc};
+/// use parsanol::portable::parser_dsl::*;
+///
+/// let grammar = GrammarBuilder::new()
+///     .rule(
+///         "expr",
+///         infix_expr(
+///             re("[0-9]").many1(),
+///             [("+", 1, Assoc::Left), ("*", 2, Assoc::Left), ("^", 3, Assoc::Right)],
+///         ),
+///     )
+///     .build();
+/// assert!(grammar.parse("1+2*3^4^5").is_ok());
+/// assert!(grammar.parse("1+").is_err());
+/// ```
+pub fn infix_expr<P, I, O>(operand: P, operators: I) -> InfixExpr<P>
+where
+    P: Parslet,
+    I: IntoIterator<Item = O>,
+    O: Into<InfixOperator>,
+{
+    InfixExpr {
+        operand,
+        operators: operators.into_iter().map(Into::into).collect(),
+    }
+}
+
 /// Precedence climbing parser for runtime parsing
 ///
 /// This is an alternative approach that doesn't require generating a grammar.
@@ -499,6 +703,116 @@ mod tests {
         assert!(infix_builder.operators.len() == 2);
     }
 
+    /// Parse with an `Atom::Infix` rule and render the result with
+    /// explicit parentheses
+    fn parse_infix(input: &str) -> Result<String, crate::portable::ParseError> {
+        parse_infix_with(
+            input,
+            [
+                ("+", 1, Assoc::Left),
+                ("-", 1, Assoc::Left),
+                ("*", 2, Assoc::Left),
+                ("**", 4, Assoc::Right),
+                ("^", 4, Assoc::Right),
+                ("<", 0, Assoc::NonAssoc),
+            ],
+        )
+    }
+
+    fn parse_infix_with<O: Into<InfixOperator>>(
+        input: &str,
+        operators: impl IntoIterator<Item = O>,
+    ) -> Result<String, crate::portable::ParseError> {
+        use crate::portable::parser_dsl::{re, ParsletExt, SemanticActions};
+        use crate::portable::{AstArena, PortableParser, Value};
+
+        fn render(value: &Value) -> String {
+            let text = |v: &Value| v.as_str().unwrap_or("?").to_string();
+            if let (Some(op), Some(operand)) = (value.get("prefix"), value.get("operand")) {
+                return format!("({}{})", text(op), render(operand));
+            }
+            if let (Some(operand), Some(op)) = (value.get("operand"), value.get("postfix")) {
+                return format!("({}{})", render(operand), text(op));
+            }
+            match (value.get("left"), value.get("op"), value.get("right")) {
+                (Some(left), Some(op), Some(right)) => {
+                    format!("({} {} {})", render(left), text(op), render(right))
+                }
+                _ => text(value),
+            }
+        }
+
+        let grammar = GrammarBuilder::new()
+            .rule("expr", infix_expr(re("[0-9]").many1(), operators))
+            .build();
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        parser
+            .parse_and_transform(&SemanticActions::default())
+            .map(|value| render(&value))
+    }
+
+    #[test]
+    fn test_infix_expr_precedence_and_associativity() {
+        assert_eq!(parse_infix("1+2*3").unwrap(), "(1 + (2 * 3))");
+        assert_eq!(parse_infix("1*2+3").unwrap(), "((1 * 2) + 3)");
+        assert_eq!(parse_infix("1-2-3").unwrap(), "((1 - 2) - 3)");
+        assert_eq!(parse_infix("2^3^4").unwrap(), "(2 ^ (3 ^ 4))");
+        assert_eq!(parse_infix("2*3**4").unwrap(), "(2 * (3 ** 4))");
+        assert_eq!(parse_infix("42").unwrap(), "42");
+    }
+
+    #[test]
+    fn test_infix_expr_rejects_incomplete_and_chained_non_assoc() {
+        assert_eq!(parse_infix("1<2+3").unwrap(), "(1 < (2 + 3))");
+        assert!(parse_infix("1<2<3").is_err());
+        assert!(parse_infix("1+").is_err());
+        assert!(parse_infix("+1").is_err());
+    }
+
+    #[test]
+    fn test_infix_expr_prefix_and_postfix() {
+        let operators = || {
+            [
+                InfixOperator::new("+", 1, Assoc::Left),
+                InfixOperator::new("-", 1, Assoc::Left),
+                InfixOperator::new("*", 2, Assoc::Left),
+                InfixOperator::prefix("-", 3),
+                InfixOperator::new("**", 4, Assoc::Right),
+                InfixOperator::postfix("!", 5),
+            ]
+        };
+        let parse = |input| parse_infix_with(input, operators());
+
+        assert_eq!(parse("-3+4").unwrap(), "((-3) + 4)");
+        assert_eq!(parse("5!").unwrap(), "(5!)");
+        assert_eq!(parse("5!!").unwrap(), "((5!)!)");
+        assert_eq!(parse("--3").unwrap(), "(-(-3))");
+        assert_eq!(parse("1--3").unwrap(), "(1 - (-3))");
+        assert_eq!(parse("-2**3").unwrap(), "(-(2 ** 3))");
+        assert_eq!(parse("-3!").unwrap(), "(-(3!))");
+        assert_eq!(parse("2*3!").unwrap(), "(2 * (3!))");
+        assert!(parse("!3").is_err());
+    }
+
+    #[test]
+    fn test_infix_builder_unary_levels() {
+        use super::super::parser_dsl::ref_;
+
+        let mut builder = GrammarBuilder::new();
+        let infix = InfixBuilder::new()
+            .primary(ref_("number"))
+            .prefix("-", 3)
+            .postfix("!", 4)
+            .op("+", 1, Assoc::Left);
+        assert_eq!(infix.operators.len(), 3);
+        assert_eq!(infix.operators[0].3, Fixity::Prefix);
+        assert_eq!(infix.operators[1].3, Fixity::Postfix);
+
+        let _expr_idx = infix.build(&mut builder);
+        assert!(builder.atom_count() > 4);
+    }
+
     #[test]
     fn test_precedence_climber() {
         let climber = PrecedenceClimber::new()
@@ -13,6 +13,9 @@
 //! - [`Grammar`] - PEG grammar definition
 //! - [`PortableParser`] - Main parser type
 //! - [`ParseContext`] - Mutable parsing context
+//! - [`CstNode`] - Lossless concrete syntax tree
+//! - [`ParsedTree`] - Borrowed view over a parse result
+//! - [`ast_compare`] - Structural equality and hashing across arenas
 //!
 //! ## Parser DSL
 //! - [`parser_dsl`] - Parser combinator DSL
@@ -20,6 +23,8 @@
 //! ## Caching
 //! - [`DenseCache`] - Dense packrat cache
 //! - [`CacheEntry`] - Cache entry type
+//! - [`GrammarCache`] - Shared cache of deserialized JSON grammars
+//! - [`grammar_bytes`] - Compact binary grammar encoding
 //!
 //! ## Error Handling
 //! - [`error`] - Rich error reporting
@@ -48,19 +53,24 @@
 
 pub mod arena;
 pub mod ast;
+pub mod ast_compare;
 pub mod backend;
 pub mod bytecode;
 pub mod cache;
 pub mod capture_state;
 pub mod char_class;
+pub mod cst;
 pub mod custom;
 pub mod debug;
 pub mod dynamic;
 pub mod error;
 pub mod grammar;
 pub mod grammar_analysis;
+pub mod grammar_bytes;
+pub mod grammar_cache;
 pub mod incremental;
 pub mod infix;
+pub mod parsed_tree;
 pub mod parser;
 pub mod parser_dsl;
 pub mod parslet_transform;
@@ -80,10 +90,16 @@ pub mod parallel;
 // Core Types
 // ============================================================================
 
-pub use arena::AstArena;
+pub use arena::{ArenaPool, AstArena, PooledArena};
 pub use ast::{AstNode, ParseError, ParseResult};
-pub use grammar::{Atom, AtomTypeCounter, AtomVisitor, Grammar};
-pub use parser::{ParseContext, ParserConfig, PortableParser};
+pub use ast_compare::{ast_equal, ast_hash};
+pub use cst::CstNode;
+pub use grammar::{Atom, AtomTypeCounter, AtomVisitor, DefaultValue, Grammar};
+pub use parsed_tree::ParsedTree;
+pub use parser::{
+    FailureTracker, MemoryBreakdown, ParseContext, ParserConfig, PortableParser,
+    PortableParserBytes, RecoveryConfig,
+};
 
 // ============================================================================
 // Error Handling
@@ -95,7 +111,9 @@ pub use error::{ErrorSeverity, RichError};
 // Caching
 // ============================================================================
 
-pub use cache::{CacheEntry, DenseCache};
+pub use cache::{CacheEntry, DenseCache, PackratStats};
+pub use grammar_bytes::GrammarBytesError;
+pub use grammar_cache::GrammarCache;
 
 // ============================================================================
 // Backend Abstraction
@@ -151,7 +169,10 @@ pub use crate::ffi::{
 // Grammar Analysis
 // ============================================================================
 
-pub use grammar_analysis::{GrammarAnalyzer, GrammarWarning, WarningKind};
+pub use grammar_analysis::{
+    ComplexityHotspot, ComplexityRating, ComplexityReport, GrammarAnalyzer, GrammarWarning,
+    WarningKind,
+};
 
 // ============================================================================
 // Transforms
@@ -164,18 +185,21 @@ pub use transform::{DirectTransform, TransformError, Value};
 // ============================================================================
 
 pub use incremental::{
-    DirtyRegion, DirtyRegionTracker, Edit, IncrementalParser, IncrementalResult,
+    DirtyRegion, DirtyRegionTracker, Edit, EditError, EditableText, IncrementalParser,
+    IncrementalResult,
 };
 
 // ============================================================================
 // Streaming Parsing
 // ============================================================================
 
-pub use streaming::{ChunkConfig, ChunkSource, StreamingError, StreamingParser, StreamingResult};
+pub use streaming::{
+    ChunkConfig, ChunkSource, LineChunkSource, StreamingError, StreamingParser, StreamingResult,
+};
 
 pub use streaming_builder::{
-    walk_ast, BuildError, BuildResult, BuilderNodeCounter, BuilderStringCollector, DebugBuilder,
-    DepthTracker, StreamingBuilder,
+    walk_ast, walk_ast_with_ignored, BuildControl, BuildError, BuildResult, BuilderNodeCounter,
+    BuilderStringCollector, DebugBuilder, DepthTracker, StreamingBuilder,
 };
 
 // ============================================================================
@@ -183,14 +207,18 @@ pub use streaming_builder::{
 // ============================================================================
 
 pub use source_location::{
-    get_line_at_offset, offset_to_line_col, SourceContext, SourcePosition, SourceSpan,
+    get_line_at_offset, offset_to_line_col, LspPosition, LspRange, SourceContext, SourcePosition,
+    SourceSpan, Utf16Mapper,
 };
 
 // ============================================================================
 // Visitor Pattern
 // ============================================================================
 
-pub use visitor::{walk, DefaultVisitor, DepthAnalyzer, NodeCounter, StringCollector, Visitor};
+pub use visitor::{
+    walk, walk_mut, ConstantFolder, DefaultVisitor, DepthAnalyzer, NodeCounter, StringCollector,
+    Visitor, VisitorMut,
+};
 
 // ============================================================================
 // Source Map
@@ -208,7 +236,10 @@ pub use parslet_transform::to_parslet_compatible;
 // Parallel Parsing
 // ============================================================================
 
-pub use parallel::{parse_batch_parallel, parse_batch_parallel_owned, ParallelConfig};
+pub use parallel::{
+    parse_batch_parallel, parse_batch_parallel_owned, parse_records_parallel, ParallelConfig,
+    ParsedRecord,
+};
 
 // ============================================================================
 // Plugin Architecture
@@ -216,7 +247,8 @@ pub use parallel::{parse_batch_parallel, parse_batch_parallel_owned, ParallelCon
 
 pub use plugin::{
     clear_plugins, get_plugin_info, has_plugin, list_plugins, plugin_count, register_plugin,
-    unregister_plugin, AtomRegistry, ParsanolPlugin, PluginInfo, PluginRegistry, TransformRegistry,
+    unregister_plugin, AtomRegistry, ParsanolPlugin, PluginInfo, PluginRegistry, PluginTransform,
+    TransformRegistry,
 };
 
 // ============================================================================
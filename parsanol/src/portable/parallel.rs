@@ -7,6 +7,10 @@
 //! For parsing multiple independent files (e.g., EXPRESS schemas),
 //! parallel parsing can provide linear speedup per CPU core.
 //!
+//! A single input made of independent records, such as NDJSON or CSV, can
+//! be split on its record separator and parsed the same way with
+//! [`parse_records_parallel`].
+//!
 //! # Feature Flag
 //!
 //! This module is only available when the `parallel` feature is enabled:
@@ -141,6 +145,116 @@ pub fn parse_batch_parallel_owned(
         .collect()
 }
 
+/// One record parsed by [`parse_records_parallel`]
+#[derive(Debug)]
+pub struct ParsedRecord {
+    /// Byte offset of the record in the whole input
+    pub offset: usize,
+    /// The record's AST, or why it failed to parse
+    ///
+    /// Input references and error positions are relative to the record;
+    /// add `offset` to map them into the whole input.
+    pub result: Result<AstNode, ParseError>,
+    /// The arena holding the record's AST
+    pub arena: AstArena,
+}
+
+/// Parse the records of one input in parallel
+///
+/// `input` is split on `record_separator` and each non-empty record is
+/// parsed on its own, with its own arena, so a record that fails doesn't
+/// affect the others. Empty records (blank lines, a trailing separator)
+/// are skipped. Results come back in input order.
+///
+/// `config.num_threads` limits the threads used, via a dedicated rayon
+/// pool; `config.min_chunk_size` is the fewest records a thread takes on
+/// at once. Without the `parallel` feature the records are parsed
+/// sequentially.
+///
+/// # Panics
+///
+/// If `record_separator` is not ASCII, since splitting on it could cut a
+/// UTF-8 character in half.
+///
+/// # Example
+///
+/// ```
+/// use parsanol::portable::parallel::{parse_records_parallel, ParallelConfig};
+/// use parsanol::portable::parser_dsl::{re, GrammarBuilder, ParsletExt};
+///
+/// let grammar = GrammarBuilder::new().rule("n", re("[0-9]").many1()).build();
+/// let records = parse_records_parallel(&grammar, "12\nx\n345\n", b'\n', &ParallelConfig::new());
+///
+/// assert_eq!(records.len(), 3);
+/// assert!(records[0].result.is_ok());
+/// assert_eq!(records[1].offset, 3);
+/// assert!(records[1].result.is_err());
+/// ```
+pub fn parse_records_parallel(
+    grammar: &Grammar,
+    input: &str,
+    record_separator: u8,
+    config: &ParallelConfig,
+) -> Vec<ParsedRecord> {
+    let records = split_records(input, record_separator);
+
+    #[cfg(feature = "rayon")]
+    {
+        let parse_all = || {
+            records
+                .par_iter()
+                .with_min_len(config.min_chunk_size.max(1))
+                .map(|&(offset, record)| parse_record(grammar, offset, record))
+                .collect()
+        };
+        let pool = config
+            .num_threads
+            .and_then(|n| rayon::ThreadPoolBuilder::new().num_threads(n).build().ok());
+        match pool {
+            Some(pool) => pool.install(parse_all),
+            None => parse_all(),
+        }
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        let _ = config;
+        records
+            .into_iter()
+            .map(|(offset, record)| parse_record(grammar, offset, record))
+            .collect()
+    }
+}
+
+/// Non-empty records of `input` with their byte offsets
+fn split_records(input: &str, separator: u8) -> Vec<(usize, &str)> {
+    assert!(
+        separator.is_ascii(),
+        "record separator must be an ASCII byte"
+    );
+
+    let mut records = Vec::new();
+    let mut start = 0;
+    let ends = memchr::memchr_iter(separator, input.as_bytes()).chain(Some(input.len()));
+    for end in ends {
+        if end > start {
+            records.push((start, &input[start..end]));
+        }
+        start = end + 1;
+    }
+    records
+}
+
+fn parse_record(grammar: &Grammar, offset: usize, record: &str) -> ParsedRecord {
+    let mut arena = AstArena::for_input(record.len());
+    let result = PortableParser::new(grammar, record, &mut arena).parse();
+    ParsedRecord {
+        offset,
+        result,
+        arena,
+    }
+}
+
 /// Configuration for parallel parsing
 #[derive(Debug, Clone)]
 pub struct ParallelConfig {
@@ -222,6 +336,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_records_keeps_order_and_offsets() {
+        let grammar = GrammarBuilder::new().rule("test", str("hello")).build();
+
+        let input = "hello\nworld\n\nhello\n";
+        let config = ParallelConfig::new().with_num_threads(2);
+        let records = parse_records_parallel(&grammar, input, b'\n', &config);
+
+        let offsets: Vec<usize> = records.iter().map(|r| r.offset).collect();
+        assert_eq!(offsets, [0, 6, 13]);
+        assert!(records[0].result.is_ok());
+        assert!(records[1].result.is_err());
+        assert!(records[2].result.is_ok());
+
+        assert!(split_records("", b'\n').is_empty());
+        assert_eq!(split_records("a,b", b','), [(0, "a"), (2, "b")]);
+    }
+
     #[test]
     fn test_parallel_config_default() {
         let config = ParallelConfig::default();
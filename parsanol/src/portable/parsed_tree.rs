@@ -0,0 +1,248 @@
+//! Borrowed view over a parse result
+//!
+//! An [`AstNode`] only means something together with the [`AstArena`] it
+//! was allocated in and the input it references. [`ParsedTree`] bundles the
+//! three so a parse result can be navigated without threading them through
+//! every call, while still borrowing rather than copying.
+//!
+//! # Example
+//!
+//! ```
+//! use parsanol::portable::parser_dsl::*;
+//! use parsanol::portable::{AstArena, PortableParser};
+//!
+//! let grammar = GrammarBuilder::new()
+//!     .rule("pair", re("[a-z]").many1().label("key").then(str("=")).then(re("[0-9]").many1().label("value")))
+//!     .build();
+//!
+//! let mut arena = AstArena::new();
+//! let mut parser = PortableParser::new(&grammar, "x=42", &mut arena);
+//! let tree = parser.parse_tree().unwrap();
+//!
+//! let values: Vec<_> = tree
+//!     .children()
+//!     .filter_map(|child| child.field("value"))
+//!     .filter_map(|value| value.as_str())
+//!     .collect();
+//! assert_eq!(values, ["42"]);
+//! ```
+
+use super::arena::AstArena;
+use super::ast::AstNode;
+use super::transform::{ast_to_value, Value};
+
+/// A parse result bundled with the arena and input it refers to
+#[derive(Debug, Clone)]
+pub struct ParsedTree<'a> {
+    node: AstNode,
+    arena: &'a AstArena,
+    input: &'a str,
+}
+
+impl<'a> ParsedTree<'a> {
+    /// Wrap a node with the arena and input it was parsed from
+    #[inline]
+    pub fn new(node: AstNode, arena: &'a AstArena, input: &'a str) -> Self {
+        Self { node, arena, input }
+    }
+
+    /// The underlying AST node
+    #[inline]
+    pub fn node(&self) -> &AstNode {
+        &self.node
+    }
+
+    /// The arena the node was allocated in
+    #[inline]
+    pub fn arena(&self) -> &'a AstArena {
+        self.arena
+    }
+
+    /// The parsed input
+    #[inline]
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    /// Text of a string node, borrowed from the input or the arena
+    ///
+    /// Returns `None` for non-string nodes.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match &self.node {
+            AstNode::InputRef { offset, length } => {
+                let start = *offset as usize;
+                self.input.get(start..start + *length as usize)
+            }
+            AstNode::StringRef { pool_index } => Some(self.arena.get_string(*pool_index as usize)),
+            _ => None,
+        }
+    }
+
+    /// Integer value, if this is an `Int` node
+    #[inline]
+    pub fn as_int(&self) -> Option<i64> {
+        match self.node {
+            AstNode::Int(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Float value, if this is a `Float` node
+    #[inline]
+    pub fn as_float(&self) -> Option<f64> {
+        match self.node {
+            AstNode::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// Boolean value, if this is a `Bool` node
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.node {
+            AstNode::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a `Nil` node
+    #[inline]
+    pub fn is_nil(&self) -> bool {
+        matches!(self.node, AstNode::Nil)
+    }
+
+    /// Tag of a sequence or repetition array (e.g. `":sequence"`)
+    pub fn tag(&self) -> Option<&'a str> {
+        match self.array_items()?.first() {
+            Some(AstNode::StringRef { pool_index }) => {
+                let s = self.arena.get_string(*pool_index as usize);
+                s.starts_with(':').then_some(s)
+            }
+            _ => None,
+        }
+    }
+
+    /// Elements of an array node, without its `:sequence`/`:repetition` tag
+    ///
+    /// Empty for non-array nodes.
+    pub fn children(&self) -> impl Iterator<Item = ParsedTree<'a>> + '_ {
+        let skip = usize::from(self.tag().is_some());
+        self.array_items()
+            .unwrap_or_default()
+            .into_iter()
+            .skip(skip)
+            .map(|node| self.wrap(node))
+    }
+
+    /// Value stored under `key` in a hash node
+    pub fn field(&self, key: &str) -> Option<ParsedTree<'a>> {
+        self.fields()
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Key/value pairs of a hash node, in insertion order
+    ///
+    /// Empty for non-hash nodes.
+    pub fn fields(&self) -> Vec<(String, ParsedTree<'a>)> {
+        match &self.node {
+            AstNode::Hash { pool_index, length } => self
+                .arena
+                .get_hash_items(*pool_index as usize, *length as usize)
+                .into_iter()
+                .map(|(key, value)| (key, self.wrap(value)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Convert to an owned [`Value`]
+    pub fn to_value(&self) -> Value {
+        ast_to_value(&self.node, self.arena, self.input)
+    }
+
+    fn array_items(&self) -> Option<Vec<AstNode>> {
+        match &self.node {
+            AstNode::Array { pool_index, length } => {
+                Some(self.arena.get_array(*pool_index as usize, *length as usize))
+            }
+            AstNode::Tagged { value, .. } => self.wrap((**value).clone()).array_items(),
+            _ => None,
+        }
+    }
+
+    fn wrap(&self, node: AstNode) -> ParsedTree<'a> {
+        ParsedTree::new(node, self.arena, self.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portable::parser::PortableParser;
+    use crate::portable::parser_dsl::{re, str, GrammarBuilder, ParsletExt};
+
+    #[test]
+    fn test_parsed_tree_navigation() {
+        let grammar = GrammarBuilder::new()
+            .rule(
+                "call",
+                re("[a-z]")
+                    .many1()
+                    .label("name")
+                    .then(str("("))
+                    .then(re("[0-9]").many1().label("arg"))
+                    .then(str(")")),
+            )
+            .build();
+        let input = "max(7)";
+
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        let tree = parser.parse_tree().unwrap();
+
+        // `then` chains nest to the left: [[[name, "("], arg], ")"]
+        assert_eq!(tree.tag(), Some(":sequence"));
+        let outer: Vec<_> = tree.children().collect();
+        assert_eq!(outer.len(), 2);
+        assert_eq!(outer[1].as_str(), Some(")"));
+
+        let middle: Vec<_> = outer[0].children().collect();
+        let arg = middle[1].field("arg").unwrap();
+        assert_eq!(arg.as_str(), Some("7"));
+
+        let inner: Vec<_> = middle[0].children().collect();
+        assert_eq!(inner[0].field("name").unwrap().as_str(), Some("max"));
+        assert_eq!(inner[0].fields().len(), 1);
+        assert_eq!(inner[1].as_str(), Some("("));
+        assert!(inner[1].field("name").is_none());
+        assert_eq!(inner[1].children().count(), 0);
+    }
+
+    #[test]
+    fn test_parsed_tree_scalars_and_value() {
+        let input = "abc";
+        let mut arena = AstArena::new();
+        let items = vec![
+            AstNode::InputRef {
+                offset: 1,
+                length: 2,
+            },
+            AstNode::Int(5),
+            AstNode::Nil,
+        ];
+        let array = arena.alloc_array(items);
+        let tree = ParsedTree::new(array, &arena, input);
+
+        assert!(tree.tag().is_none());
+        let children: Vec<_> = tree.children().collect();
+        assert_eq!(children[0].as_str(), Some("bc"));
+        assert_eq!(children[1].as_int(), Some(5));
+        assert!(children[2].is_nil());
+        assert_eq!(
+            tree.to_value(),
+            Value::Array(vec![Value::string("bc"), Value::Int(5), Value::Nil])
+        );
+    }
+}
@@ -0,0 +1,481 @@
+//! Parsing Binary Input
+//!
+//! [`PortableParserBytes`] runs a grammar over `&[u8]` rather than `&str`,
+//! for network protocols and file formats that aren't UTF-8 text. Results
+//! use the same [`AstArena`] and AST shapes as [`PortableParser`], with
+//! [`AstNode::InputRef`] offsets counting bytes.
+//!
+//! The matching atoms work byte by byte: literals compare bytes, regexes
+//! run in byte mode (`.` and negated classes match any single byte, `\xff`
+//! matches the byte 0xff), and [`Atom::ByteRange`] matches one byte. Atoms
+//! that only make sense on text (indentation, case folding beyond ASCII,
+//! number conversion, captures, custom and dynamic atoms, ...) fail with
+//! [`ParseError::InvalidGrammar`].
+//!
+//! [`PortableParser`]: super::PortableParser
+
+use crate::portable::arena::AstArena;
+use crate::portable::ast::{AstNode, ParseError};
+use crate::portable::grammar::{Atom, Grammar};
+use crate::portable::regex_cache;
+use hashbrown::HashMap;
+
+use super::config::DEFAULT_MAX_RECURSION_DEPTH;
+use super::simd;
+
+/// Packrat parser over binary input
+///
+/// # Example
+///
+/// ```
+/// use parsanol::portable::parser_dsl::*;
+/// use parsanol::portable::{AstArena, PortableParserBytes};
+///
+/// // A 0xCAFE magic number followed by any two bytes
+/// let grammar = GrammarBuilder::new()
+///     .rule("header", byte(0xca).then(byte(0xfe)).then(any_byte().exactly(2).label("rest")))
+///     .build();
+/// let input = [0xca, 0xfe, 0xff, 0x00];
+///
+/// let mut arena = AstArena::new();
+/// let mut parser = PortableParserBytes::new(&grammar, &input, &mut arena);
+/// assert!(parser.parse().is_ok());
+/// ```
+pub struct PortableParserBytes<'a> {
+    /// The grammar to parse with
+    grammar: &'a Grammar,
+
+    /// The input being parsed
+    input: &'a [u8],
+
+    /// AST arena for allocating nodes
+    arena: &'a mut AstArena,
+
+    /// Memoized results by `(atom, position)`; `None` records a failure
+    memo: HashMap<(usize, usize), Option<(AstNode, usize)>>,
+
+    /// Current recursion depth
+    depth: usize,
+
+    /// Maximum recursion depth (0 = unlimited)
+    max_recursion_depth: usize,
+
+    /// Furthest position an atom failed at
+    furthest_failure: usize,
+}
+
+impl<'a> PortableParserBytes<'a> {
+    /// Create a new parser over `input`
+    pub fn new(grammar: &'a Grammar, input: &'a [u8], arena: &'a mut AstArena) -> Self {
+        Self {
+            grammar,
+            input,
+            arena,
+            memo: HashMap::new(),
+            depth: 0,
+            max_recursion_depth: DEFAULT_MAX_RECURSION_DEPTH,
+            furthest_failure: 0,
+        }
+    }
+
+    /// Set maximum recursion depth (0 = unlimited)
+    pub fn set_max_recursion_depth(&mut self, max_depth: usize) {
+        self.max_recursion_depth = max_depth;
+    }
+
+    /// Parse the whole input
+    ///
+    /// A failed match reports the furthest position any atom failed at.
+    pub fn parse(&mut self) -> Result<AstNode, ParseError> {
+        let (value, end) = self.parse_prefix()?;
+        if end == self.input.len() {
+            Ok(value)
+        } else {
+            Err(ParseError::Incomplete {
+                expected: self.input.len(),
+                actual: end,
+            })
+        }
+    }
+
+    /// Parse a prefix of the input, returning the AST and where it ended
+    pub fn parse_prefix(&mut self) -> Result<(AstNode, usize), ParseError> {
+        if self.grammar.skip.is_some() {
+            return Err(unsupported("skip rules"));
+        }
+        match self.try_atom(self.grammar.root, 0) {
+            Err(ParseError::Failed { position }) => Err(ParseError::Failed {
+                position: position.max(self.furthest_failure),
+            }),
+            result => result,
+        }
+    }
+
+    /// Try to match an atom at a position, using the memo table
+    fn try_atom(&mut self, atom_id: usize, pos: usize) -> Result<(AstNode, usize), ParseError> {
+        if let Some(memoized) = self.memo.get(&(atom_id, pos)) {
+            return memoized.clone().ok_or(ParseError::Failed { position: pos });
+        }
+
+        let result = self.parse_atom(atom_id, pos);
+        match &result {
+            Ok(success) => {
+                self.memo.insert((atom_id, pos), Some(success.clone()));
+            }
+            Err(ParseError::Failed { .. }) => {
+                self.furthest_failure = self.furthest_failure.max(pos);
+                self.memo.insert((atom_id, pos), None);
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    fn parse_atom(&mut self, atom_id: usize, pos: usize) -> Result<(AstNode, usize), ParseError> {
+        let grammar = self.grammar;
+        let Some(atom) = grammar.get_atom(atom_id) else {
+            return Err(ParseError::InvalidGrammar {
+                reason: format!("atom {} does not exist", atom_id),
+            });
+        };
+        let failed = Err(ParseError::Failed { position: pos });
+
+        match atom {
+            Atom::Str { pattern } => {
+                if self.input[pos..].starts_with(pattern.as_bytes()) {
+                    Ok(self.matched(pos, pattern.len()))
+                } else {
+                    failed
+                }
+            }
+            Atom::StrCi { pattern } if pattern.is_ascii() => {
+                match self.input.get(pos..pos + pattern.len()) {
+                    Some(slice) if slice.eq_ignore_ascii_case(pattern.as_bytes()) => {
+                        Ok(self.matched(pos, pattern.len()))
+                    }
+                    _ => failed,
+                }
+            }
+            Atom::Re { pattern } => {
+                let regex = regex_cache::get_or_compile_bytes(pattern).ok_or_else(|| {
+                    ParseError::InvalidGrammar {
+                        reason: format!("regex {:?} can't match bytes", pattern),
+                    }
+                })?;
+                match regex.find(&self.input[pos..]) {
+                    Some(m) if m.start() == 0 => Ok(self.matched(pos, m.end())),
+                    _ => failed,
+                }
+            }
+            Atom::ByteRange { lo, hi } => self.parse_byte(pos, |b| (*lo..=*hi).contains(&b)),
+            Atom::CharRange { lo, hi } if hi.is_ascii() => {
+                self.parse_byte(pos, |b| (*lo as u8..=*hi as u8).contains(&b))
+            }
+            Atom::OneOf { chars } if chars.iter().all(char::is_ascii) => {
+                self.parse_byte(pos, |b| chars.contains(&(b as char)))
+            }
+            Atom::NoneOf { chars } if chars.iter().all(char::is_ascii) => {
+                self.parse_byte(pos, |b| !chars.contains(&(b as char)))
+            }
+            Atom::Until {
+                terminator,
+                inclusive,
+                or_end,
+            } => {
+                let found = if terminator.is_empty() {
+                    Some(pos)
+                } else {
+                    simd::find_pattern(self.input, pos, terminator.as_bytes())
+                };
+                match found {
+                    Some(at) => {
+                        let (value, _) = self.matched(pos, at - pos);
+                        let end = if *inclusive {
+                            at + terminator.len()
+                        } else {
+                            at
+                        };
+                        Ok((value, end))
+                    }
+                    None if *or_end => Ok(self.matched(pos, self.input.len() - pos)),
+                    None => failed,
+                }
+            }
+            Atom::Sequence { atoms } => {
+                let mut items = Vec::with_capacity(atoms.len());
+                let mut end = pos;
+                for &child in atoms {
+                    let (value, child_end) = self.try_atom(child, end)?;
+                    items.push(value);
+                    end = child_end;
+                }
+                let (pool_index, length) = self.arena.store_tagged_array(":sequence", &items);
+                Ok((AstNode::Array { pool_index, length }, end))
+            }
+            Atom::Alternative { atoms } => {
+                for &child in atoms {
+                    match self.try_atom(child, pos) {
+                        Err(ParseError::Failed { .. }) => continue,
+                        result => return result,
+                    }
+                }
+                failed
+            }
+            Atom::LongestAlternative { atoms } => {
+                let mut best: Option<(AstNode, usize)> = None;
+                for &child in atoms {
+                    match self.try_atom(child, pos) {
+                        Ok(result) if best.as_ref().is_none_or(|(_, end)| result.1 > *end) => {
+                            best = Some(result);
+                        }
+                        Ok(_) | Err(ParseError::Failed { .. }) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                best.map_or(failed, Ok)
+            }
+            Atom::Repetition { atom, min, max } => {
+                let mut items = Vec::new();
+                let mut end = pos;
+                while max.is_none_or(|max| items.len() < max) {
+                    match self.try_atom(*atom, end) {
+                        Ok((value, item_end)) => {
+                            items.push(value);
+                            // An empty match would repeat forever
+                            if item_end == end {
+                                break;
+                            }
+                            end = item_end;
+                        }
+                        Err(ParseError::Failed { .. }) => break,
+                        Err(e) => return Err(e),
+                    }
+                }
+                if items.len() < *min {
+                    return failed;
+                }
+                let (pool_index, length) = self.arena.store_tagged_array(":repetition", &items);
+                Ok((AstNode::Array { pool_index, length }, end))
+            }
+            Atom::RepetitionLazy {
+                atom,
+                min,
+                max,
+                follow,
+            } => {
+                let mut items = Vec::new();
+                let mut end = pos;
+                loop {
+                    if items.len() >= *min {
+                        match self.try_atom(*follow, end) {
+                            Ok(_) => break,
+                            Err(ParseError::Failed { .. }) => {}
+                            Err(e) => return Err(e),
+                        }
+                        if max.is_some_and(|max| items.len() >= max) {
+                            return failed;
+                        }
+                    }
+                    match self.try_atom(*atom, end) {
+                        // An empty match would never reach `follow`
+                        Ok((value, item_end)) if item_end > end || items.len() < *min => {
+                            items.push(value);
+                            end = item_end;
+                        }
+                        Ok(_) | Err(ParseError::Failed { .. }) => return failed,
+                        Err(e) => return Err(e),
+                    }
+                }
+                let (pool_index, length) = self.arena.store_tagged_array(":repetition", &items);
+                Ok((AstNode::Array { pool_index, length }, end))
+            }
+            Atom::Named { name, atom } => {
+                let (value, end) = self.try_atom(*atom, pos)?;
+                let (pool_index, length) = self.arena.store_hash(&[(name.as_str(), value)]);
+                Ok((AstNode::Hash { pool_index, length }, end))
+            }
+            Atom::Entity { atom } => {
+                self.depth += 1;
+                if self.max_recursion_depth > 0 && self.depth > self.max_recursion_depth {
+                    let depth = self.depth;
+                    self.depth -= 1;
+                    return Err(ParseError::RecursionLimitExceeded {
+                        depth,
+                        max_depth: self.max_recursion_depth,
+                    });
+                }
+                let result = self.try_atom(*atom, pos);
+                self.depth -= 1;
+                result
+            }
+            Atom::Lookahead { atom, positive } => match self.try_atom(*atom, pos) {
+                Ok(_) if *positive => Ok((AstNode::Nil, pos)),
+                Err(ParseError::Failed { .. }) if !*positive => Ok((AstNode::Nil, pos)),
+                Ok(_) | Err(ParseError::Failed { .. }) => failed,
+                Err(e) => Err(e),
+            },
+            Atom::Peek { atom } => {
+                let (value, _) = self.try_atom(*atom, pos)?;
+                Ok((value, pos))
+            }
+            Atom::Ignore { atom } => {
+                let (_, end) = self.try_atom(*atom, pos)?;
+                Ok((AstNode::Nil, end))
+            }
+            // Binary input has no string pool entry to give, so the matched
+            // span comes back as a single input reference
+            Atom::Text { atom } => {
+                let (_, end) = self.try_atom(*atom, pos)?;
+                Ok(self.matched(pos, end - pos))
+            }
+            Atom::Cut => Ok((AstNode::Nil, pos)),
+            Atom::Eof if pos == self.input.len() => Ok((AstNode::Nil, pos)),
+            Atom::Eof => failed,
+            Atom::StrCi { .. } => Err(unsupported("non-ASCII case-insensitive literals")),
+            Atom::CharRange { .. } | Atom::OneOf { .. } | Atom::NoneOf { .. } => {
+                Err(unsupported("non-ASCII character classes"))
+            }
+            Atom::Instantiate { template, .. } => Err(ParseError::InvalidGrammar {
+                reason: format!("unresolved instantiation of template {:?}", template),
+            }),
+            Atom::Infix { .. } => Err(unsupported("infix expressions")),
+            Atom::Indent | Atom::Samedent | Atom::Dedent => {
+                Err(unsupported("indentation tracking"))
+            }
+            Atom::Capture { .. } | Atom::BackRef { .. } | Atom::Scope { .. } => {
+                Err(unsupported("captures"))
+            }
+            Atom::OptionalDefault { .. } => Err(unsupported("optional defaults")),
+            Atom::ParseInt { .. } | Atom::ParseFloat { .. } => {
+                Err(unsupported("number conversion"))
+            }
+            Atom::Const { atom, value } => {
+                let (_, end) = self.try_atom(*atom, pos)?;
+                Ok((value.to_ast(), end))
+            }
+            Atom::Dynamic { .. } | Atom::Custom { .. } => {
+                Err(unsupported("dynamic and custom atoms"))
+            }
+        }
+    }
+
+    /// Match one byte satisfying `accept`
+    #[inline]
+    fn parse_byte(
+        &mut self,
+        pos: usize,
+        accept: impl Fn(u8) -> bool,
+    ) -> Result<(AstNode, usize), ParseError> {
+        match self.input.get(pos) {
+            Some(&b) if accept(b) => Ok(self.matched(pos, 1)),
+            _ => Err(ParseError::Failed { position: pos }),
+        }
+    }
+
+    /// Result for `length` bytes matched at `pos`
+    #[inline]
+    fn matched(&self, pos: usize, length: usize) -> (AstNode, usize) {
+        (self.arena.input_ref(pos, length), pos + length)
+    }
+}
+
+/// Error for an atom that can't run on binary input
+fn unsupported(feature: &str) -> ParseError {
+    ParseError::InvalidGrammar {
+        reason: format!("{} are not supported on binary input", feature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portable::parser_dsl::{
+        any_byte, byte, choice, indent, re, str, GrammarBuilder, ParsletExt,
+    };
+
+    /// Value of the first `key` found anywhere in the tree
+    fn find(arena: &AstArena, node: &AstNode, key: &str) -> Option<AstNode> {
+        match node {
+            AstNode::Array { pool_index, length } => arena
+                .get_array(*pool_index as usize, *length as usize)
+                .iter()
+                .find_map(|item| find(arena, item, key)),
+            AstNode::Hash { pool_index, length } => arena
+                .get_hash_items(*pool_index as usize, *length as usize)
+                .into_iter()
+                .find_map(|(k, v)| {
+                    if k == key {
+                        Some(v)
+                    } else {
+                        find(arena, &v, key)
+                    }
+                }),
+            _ => None,
+        }
+    }
+
+    /// Input bytes matched by a node's string leaves, in order
+    fn matched_bytes(arena: &AstArena, input: &[u8], node: &AstNode) -> Vec<u8> {
+        match node {
+            AstNode::InputRef { offset, length } => {
+                input[*offset as usize..(*offset + *length) as usize].to_vec()
+            }
+            AstNode::Array { pool_index, length } => arena
+                .get_array(*pool_index as usize, *length as usize)
+                .iter()
+                .flat_map(|item| matched_bytes(arena, input, item))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_length_prefixed_record() {
+        // "REC", a length byte, that many payload bytes, then a checksum
+        let payload = choice(
+            (0..=4u8).map(|n| byte(n).then(any_byte().exactly(n as usize).label("payload"))),
+        );
+        let grammar = GrammarBuilder::new()
+            .rule(
+                "record",
+                str("REC").then(payload).then(any_byte().label("checksum")),
+            )
+            .build();
+        let input = [b'R', b'E', b'C', 3, 0xff, 0x00, 0x80, 0x7e];
+
+        let mut arena = AstArena::new();
+        let ast = PortableParserBytes::new(&grammar, &input, &mut arena)
+            .parse()
+            .unwrap();
+
+        let payload = find(&arena, &ast, "payload").unwrap();
+        assert_eq!(matched_bytes(&arena, &input, &payload), [0xff, 0x00, 0x80]);
+        let checksum = find(&arena, &ast, "checksum").unwrap();
+        assert_eq!(matched_bytes(&arena, &input, &checksum), [0x7e]);
+
+        // A length byte that doesn't match the payload fails at the end
+        let short = [b'R', b'E', b'C', 4, 0xff, 0x00, 0x80];
+        let mut arena = AstArena::new();
+        let mut parser = PortableParserBytes::new(&grammar, &short, &mut arena);
+        assert!(matches!(
+            parser.parse(),
+            Err(ParseError::Failed { position: 7 })
+        ));
+    }
+    #[test]
+    fn test_regex_matches_raw_bytes() {
+        let grammar = GrammarBuilder::new()
+            .rule("blob", re("[\\x80-\\xff]+"))
+            .build();
+        let mut arena = AstArena::new();
+        let mut parser = PortableParserBytes::new(&grammar, &[0x80, 0xc3, 0xff], &mut arena);
+        assert!(parser.parse().is_ok());
+
+        let text_only = GrammarBuilder::new().rule("block", indent()).build();
+        let mut parser = PortableParserBytes::new(&text_only, b"  ", &mut arena);
+        assert!(matches!(
+            parser.parse(),
+            Err(ParseError::InvalidGrammar { .. })
+        ));
+    }
+}
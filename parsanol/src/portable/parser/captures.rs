@@ -0,0 +1,102 @@
+//! Capture-dependent atoms
+//!
+//! `Atom::BackRef` matches whatever text is currently captured under its
+//! name, and `Atom::Dynamic` hands the capture state to its callback, so
+//! their results depend on the capture state, not just on
+//! `(position, atom)`, and so does the result of every atom that can reach
+//! one. The parser bypasses the packrat cache for those.
+//!
+//! `Atom::Capture` only records a capture as a side effect of matching.
+//! Atoms that can reach one are still memoized: the captures a result
+//! stores are kept next to its cache entry in [`CaptureEffects`], and
+//! stored again when the entry is hit, just as parsing the atom again
+//! would. Grammars without captures, back-references or dynamic atoms get
+//! no [`Captures`] at all, so their parse path is unchanged.
+
+use crate::portable::capture_state::{CaptureSnapshot, CaptureState, CaptureValue};
+use crate::portable::grammar::{Atom, Grammar};
+use crate::portable::grammar_analysis::GrammarAnalyzer;
+use ahash::AHashMap;
+
+/// Capture-dependent and capturing atoms of a grammar, indexed by atom ID
+#[derive(Debug, Clone)]
+pub(crate) struct Captures {
+    /// Atoms whose result depends on the capture state
+    dependent: Vec<bool>,
+    /// Atoms whose match may store captures
+    capturing: Vec<bool>,
+}
+
+impl Captures {
+    /// Analyze a grammar, returning `None` if it has no captures,
+    /// back-references or dynamic atoms
+    pub(crate) fn analyze(grammar: &Grammar) -> Option<Self> {
+        let analyzer = GrammarAnalyzer::new(grammar);
+        let dependent = analyzer
+            .atoms_reaching(|atom| matches!(atom, Atom::BackRef { .. } | Atom::Dynamic { .. }));
+        let capturing = analyzer.atoms_reaching(|atom| matches!(atom, Atom::Capture { .. }));
+        if !capturing.iter().chain(&dependent).any(|&m| m) {
+            return None;
+        }
+        Some(Self {
+            dependent,
+            capturing,
+        })
+    }
+
+    /// Whether this atom's result depends on the capture state
+    #[inline]
+    pub(crate) fn is_dependent(&self, atom_id: usize) -> bool {
+        self.dependent.get(atom_id).copied().unwrap_or(false)
+    }
+
+    /// Whether matching this atom may store captures
+    #[inline]
+    pub(crate) fn is_capturing(&self, atom_id: usize) -> bool {
+        self.capturing.get(atom_id).copied().unwrap_or(false)
+    }
+}
+
+/// Captures stored by memoized results, keyed by `(position, atom)`
+///
+/// Results that store nothing have no entry. Entries outlive an evicted
+/// cache entry until the same result is memoized again, which replaces
+/// them.
+#[derive(Debug, Default)]
+pub(crate) struct CaptureEffects {
+    stored: AHashMap<(u32, u16), Box<[(String, CaptureValue)]>>,
+}
+
+impl CaptureEffects {
+    /// Remember the captures `state` stored since `since` as the effects
+    /// of `atom_id` at `pos`
+    pub(crate) fn record(
+        &mut self,
+        atom_id: usize,
+        pos: usize,
+        state: &CaptureState,
+        since: &CaptureSnapshot,
+    ) {
+        let key = (pos as u32, atom_id as u16);
+        let stored = state.stored_since(since);
+        if stored.is_empty() {
+            self.stored.remove(&key);
+        } else {
+            self.stored.insert(key, stored.into_boxed_slice());
+        }
+    }
+
+    /// Store the captures remembered for `atom_id` at `pos` into `state`
+    pub(crate) fn replay(&self, atom_id: usize, pos: usize, state: &mut CaptureState) {
+        if let Some(stored) = self.stored.get(&(pos as u32, atom_id as u16)) {
+            for (name, value) in stored.iter() {
+                state.store(name, *value);
+            }
+        }
+    }
+
+    /// Forget every remembered effect, when the cache is emptied
+    pub(crate) fn clear(&mut self) {
+        self.stored.clear();
+    }
+}
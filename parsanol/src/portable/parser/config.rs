@@ -14,6 +14,12 @@ pub const DEFAULT_TIMEOUT_MS: u64 = 0;
 /// Default maximum memory usage in bytes (0 = no limit)
 pub const DEFAULT_MAX_MEMORY: usize = 0;
 
+/// Default maximum AST node count (0 = no limit)
+pub const DEFAULT_MAX_NODES: usize = 0;
+
+/// Default packrat cache budget in bytes (0 = unbounded)
+pub const DEFAULT_MAX_CACHE_BYTES: usize = 0;
+
 /// Check interval for timeout (number of parse operations between checks)
 pub const TIMEOUT_CHECK_INTERVAL: usize = 1000;
 
@@ -33,6 +39,50 @@ pub struct ParserConfig {
 
     /// Maximum memory usage in bytes (0 = no limit)
     pub max_memory: usize,
+
+    /// Maximum AST nodes in the arena (0 = no limit)
+    ///
+    /// Counts the arrays, hashes and their items stored in the arena,
+    /// including any left there by earlier parses. Exceeding it fails the
+    /// parse with [`ParseError::NodeLimitExceeded`]. Unlike `max_memory`,
+    /// which is only sampled periodically, this is checked before every
+    /// atom, so the limit is exact.
+    ///
+    /// [`ParseError::NodeLimitExceeded`]: crate::portable::ParseError::NodeLimitExceeded
+    pub max_nodes: usize,
+
+    /// Packrat cache budget in bytes (0 = unbounded)
+    ///
+    /// A bounded cache evicts results to stay within budget, trading the
+    /// linear-time guarantee for a fixed memory cost; see
+    /// [`DenseCache::bounded`](crate::portable::DenseCache::bounded).
+    pub max_cache_bytes: usize,
+
+    /// Report what was expected when a parse fails
+    ///
+    /// When set, a [`ParseError::Failed`] from
+    /// [`PortableParser::parse_with_config`] is replaced by
+    /// [`ParseError::FailedExpecting`], listing the literals and patterns
+    /// tried at the furthest failure position. The failure position is
+    /// tracked during every parse anyway; this only adds the cost of
+    /// describing it, and only once the parse has failed.
+    ///
+    /// [`ParseError::Failed`]: crate::portable::ParseError::Failed
+    /// [`ParseError::FailedExpecting`]: crate::portable::ParseError::FailedExpecting
+    /// [`PortableParser::parse_with_config`]: super::PortableParser::parse_with_config
+    pub collect_expected: bool,
+
+    /// Report the furthest failure position (default: on)
+    ///
+    /// Ordered choice forgets where its alternatives failed, so the root
+    /// rule usually fails at the offset it started from. With this set, a
+    /// [`ParseError::Failed`] carries the furthest offset any atom failed
+    /// at instead. See
+    /// [`PortableParser::set_report_farthest_failure`].
+    ///
+    /// [`ParseError::Failed`]: crate::portable::ParseError::Failed
+    /// [`PortableParser::set_report_farthest_failure`]: super::PortableParser::set_report_farthest_failure
+    pub farthest_failure: bool,
 }
 
 impl Default for ParserConfig {
@@ -42,6 +92,10 @@ impl Default for ParserConfig {
             max_recursion_depth: DEFAULT_MAX_RECURSION_DEPTH,
             timeout_ms: DEFAULT_TIMEOUT_MS,
             max_memory: DEFAULT_MAX_MEMORY,
+            max_nodes: DEFAULT_MAX_NODES,
+            max_cache_bytes: DEFAULT_MAX_CACHE_BYTES,
+            collect_expected: false,
+            farthest_failure: true,
         }
     }
 }
@@ -75,6 +129,90 @@ impl ParserConfig {
         self.max_memory = bytes;
         self
     }
+
+    /// Set the maximum AST node count
+    pub fn with_max_nodes(mut self, count: usize) -> Self {
+        self.max_nodes = count;
+        self
+    }
+
+    /// Set the packrat cache budget
+    pub fn with_max_cache_bytes(mut self, bytes: usize) -> Self {
+        self.max_cache_bytes = bytes;
+        self
+    }
+
+    /// Set whether failed parses report the expected set
+    pub fn with_collect_expected(mut self, enabled: bool) -> Self {
+        self.collect_expected = enabled;
+        self
+    }
+
+    /// Set whether failed parses report the furthest failure position
+    pub fn with_farthest_failure(mut self, enabled: bool) -> Self {
+        self.farthest_failure = enabled;
+        self
+    }
+}
+
+/// Error recovery configuration
+///
+/// Controls where [`PortableParser::parse_recovering_with`] resumes after a
+/// syntax error: the input is skipped up to and including the nearest
+/// synchronization point.
+///
+/// [`PortableParser::parse_recovering_with`]: super::PortableParser::parse_recovering_with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryConfig {
+    /// Tokens that end a broken item (e.g. `";"` or `"}"`)
+    pub sync_tokens: Vec<String>,
+
+    /// Whether a newline is also a synchronization point
+    pub sync_on_newline: bool,
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        Self {
+            sync_tokens: Vec::new(),
+            sync_on_newline: true,
+        }
+    }
+}
+
+impl RecoveryConfig {
+    /// Create a new config that synchronizes on newlines
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a synchronization token
+    pub fn with_sync_token(mut self, token: impl Into<String>) -> Self {
+        self.sync_tokens.push(token.into());
+        self
+    }
+
+    /// Set whether newlines are synchronization points
+    pub fn with_sync_on_newline(mut self, enabled: bool) -> Self {
+        self.sync_on_newline = enabled;
+        self
+    }
+
+    /// End of the nearest synchronization point at or after `pos`
+    ///
+    /// Returns `input.len()` when there is none.
+    pub(crate) fn sync_end(&self, input: &str, pos: usize) -> usize {
+        let rest = &input[pos..];
+        let newline = self.sync_on_newline.then_some("\n");
+        self.sync_tokens
+            .iter()
+            .map(String::as_str)
+            .chain(newline)
+            .filter(|token| !token.is_empty())
+            .filter_map(|token| rest.find(token).map(|at| pos + at + token.len()))
+            .min()
+            .unwrap_or(input.len())
+    }
 }
 
 #[cfg(test)]
@@ -88,6 +226,7 @@ mod tests {
         assert_eq!(config.max_recursion_depth, DEFAULT_MAX_RECURSION_DEPTH);
         assert_eq!(config.timeout_ms, DEFAULT_TIMEOUT_MS);
         assert_eq!(config.max_memory, DEFAULT_MAX_MEMORY);
+        assert_eq!(config.max_cache_bytes, DEFAULT_MAX_CACHE_BYTES);
     }
 
     #[test]
@@ -96,11 +235,27 @@ mod tests {
             .with_max_input_size(1000)
             .with_max_recursion_depth(100)
             .with_timeout_ms(5000)
-            .with_max_memory(10000);
+            .with_max_memory(10000)
+            .with_max_cache_bytes(4096);
 
         assert_eq!(config.max_input_size, 1000);
         assert_eq!(config.max_recursion_depth, 100);
         assert_eq!(config.timeout_ms, 5000);
         assert_eq!(config.max_memory, 10000);
+        assert_eq!(config.max_cache_bytes, 4096);
+    }
+
+    #[test]
+    fn test_recovery_sync_end() {
+        let input = "a = ; b\nc";
+        let config = RecoveryConfig::new();
+        assert_eq!(config.sync_end(input, 0), 8);
+        assert_eq!(config.sync_end(input, 8), input.len());
+
+        let config = config.with_sync_token(";");
+        assert_eq!(config.sync_end(input, 0), 5);
+
+        let config = config.with_sync_on_newline(false);
+        assert_eq!(config.sync_end(input, 5), input.len());
     }
 }
@@ -7,6 +7,7 @@ use crate::portable::ast::{AstNode, ParseError};
 use crate::portable::cache::DenseCache;
 
 use super::config::TIMEOUT_CHECK_INTERVAL;
+use std::fmt;
 
 /// Mutable parsing context
 ///
@@ -30,6 +31,107 @@ pub struct ParseContext<'a> {
 
     /// Operation counter for periodic timeout checks
     pub op_count: usize,
+
+    /// Atoms that failed at the furthest position reached
+    pub failures: FailureTracker,
+}
+
+/// Furthest-failure watermark
+///
+/// Records which atoms were attempted, and failed, at the furthest input
+/// position any atom failed at. These are the alternatives an "expected one
+/// of" message or an autocompletion would offer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailureTracker {
+    /// Watermark position (meaningless while `atoms` is empty)
+    position: usize,
+
+    /// Atom ids that failed at `position`, in first-failure order
+    atoms: Vec<usize>,
+}
+
+impl FailureTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `atom_id` was attempted at `pos` and failed
+    #[inline]
+    pub fn record(&mut self, atom_id: usize, pos: usize) {
+        if self.atoms.is_empty() || pos > self.position {
+            self.position = pos;
+            self.atoms.clear();
+            self.atoms.push(atom_id);
+        } else if pos == self.position && !self.atoms.contains(&atom_id) {
+            self.atoms.push(atom_id);
+        }
+    }
+
+    /// Furthest position an atom failed at, if any failed
+    #[inline]
+    pub fn position(&self) -> Option<usize> {
+        (!self.atoms.is_empty()).then_some(self.position)
+    }
+
+    /// Atom ids that failed at the watermark, sorted
+    pub fn expected(&self) -> Vec<usize> {
+        let mut atoms = self.atoms.clone();
+        atoms.sort_unstable();
+        atoms
+    }
+
+    /// Forget all recorded failures
+    #[inline]
+    pub fn clear(&mut self) {
+        self.position = 0;
+        self.atoms.clear();
+    }
+}
+
+/// Where a parse's memory goes
+///
+/// `arena_bytes + cache_bytes` is what
+/// [`memory_usage`](ParseContext::memory_usage) reports and what the
+/// `max_memory` limit is checked against; `cached_nodes_bytes` is the
+/// side table of AST nodes the cache entries point into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryBreakdown {
+    /// AST arena pools (strings, arrays, hashes)
+    pub arena_bytes: usize,
+    /// Packrat cache slots and entries
+    pub cache_bytes: usize,
+    /// AST nodes held for cache hits
+    pub cached_nodes_bytes: usize,
+}
+
+impl MemoryBreakdown {
+    /// Measure an arena, a cache and its node table
+    pub fn measure(arena: &AstArena, cache: &DenseCache, cached_nodes: &[AstNode]) -> Self {
+        Self {
+            arena_bytes: arena.memory_usage(),
+            cache_bytes: cache.memory_usage(),
+            cached_nodes_bytes: std::mem::size_of_val(cached_nodes),
+        }
+    }
+
+    /// Sum of all fields
+    pub fn total(&self) -> usize {
+        self.arena_bytes + self.cache_bytes + self.cached_nodes_bytes
+    }
+}
+
+impl fmt::Display for MemoryBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes (arena {}, cache {}, cached nodes {})",
+            self.total(),
+            self.arena_bytes,
+            self.cache_bytes,
+            self.cached_nodes_bytes
+        )
+    }
 }
 
 impl<'a> ParseContext<'a> {
@@ -45,6 +147,7 @@ impl<'a> ParseContext<'a> {
             current_depth: 0,
             start_time: None,
             op_count: 0,
+            failures: FailureTracker::new(),
         }
     }
 
@@ -61,6 +164,7 @@ impl<'a> ParseContext<'a> {
             current_depth: 0,
             start_time: None,
             op_count: 0,
+            failures: FailureTracker::new(),
         }
     }
 
@@ -71,6 +175,7 @@ impl<'a> ParseContext<'a> {
         self.current_depth = 0;
         self.start_time = None;
         self.op_count = 0;
+        self.failures.clear();
     }
 
     /// Extract the cache and cached nodes from this context
@@ -84,6 +189,11 @@ impl<'a> ParseContext<'a> {
         self.arena.memory_usage() + self.cache.memory_usage()
     }
 
+    /// Get the memory usage estimate split by owner
+    pub fn memory_breakdown(&self) -> MemoryBreakdown {
+        MemoryBreakdown::measure(self.arena, &self.cache, &self.cached_nodes)
+    }
+
     /// Store a cached node and return its index
     #[inline(always)]
     pub fn store_cached_node(&mut self, node: AstNode) -> u32 {
@@ -201,4 +311,39 @@ mod tests {
         assert!(ctx.check_recursion_limit(5).is_ok());
         assert!(ctx.check_recursion_limit(1).is_err());
     }
+
+    #[test]
+    fn test_context_memory_breakdown() {
+        let mut arena = AstArena::new();
+        arena.alloc_array(vec![AstNode::Int(1), AstNode::Int(2)]);
+        let mut ctx = ParseContext::new(&mut arena, 100, 10);
+        ctx.store_cached_node(AstNode::Nil);
+
+        let breakdown = ctx.memory_breakdown();
+        assert_eq!(
+            breakdown.arena_bytes + breakdown.cache_bytes,
+            ctx.memory_usage()
+        );
+        assert!(breakdown.cached_nodes_bytes >= std::mem::size_of::<AstNode>());
+        assert!(breakdown
+            .to_string()
+            .starts_with(&format!("{} bytes", breakdown.total())));
+    }
+
+    #[test]
+    fn test_failure_tracker_keeps_furthest_position() {
+        let mut tracker = FailureTracker::new();
+        assert_eq!(tracker.position(), None);
+
+        tracker.record(3, 2);
+        tracker.record(1, 5);
+        tracker.record(4, 5);
+        tracker.record(1, 5);
+        tracker.record(7, 4);
+        assert_eq!(tracker.position(), Some(5));
+        assert_eq!(tracker.expected(), vec![1, 4]);
+
+        tracker.clear();
+        assert!(tracker.expected().is_empty());
+    }
 }
@@ -1,7 +1,8 @@
 //! Resource Governor for Parsing
 //!
 //! This module provides resource management for parsing operations,
-//! enforcing limits on recursion depth, timeout, memory, and input size.
+//! enforcing limits on recursion depth, timeout, memory, AST node count
+//! and input size.
 //!
 //! # Architecture
 //!
@@ -39,6 +40,7 @@ const TIMEOUT_CHECK_INTERVAL: usize = 1000;
 /// - Recursion depth tracking
 /// - Timeout enforcement
 /// - Memory limit checking
+/// - AST node count checking
 /// - Input size validation
 #[derive(Debug)]
 pub struct ResourceGovernor {
@@ -62,6 +64,9 @@ pub struct ResourceGovernor {
 
     /// Maximum memory usage in bytes (0 = unlimited)
     max_memory: usize,
+
+    /// Maximum AST nodes in the arena (0 = unlimited)
+    max_nodes: usize,
 }
 
 impl Default for ResourceGovernor {
@@ -82,6 +87,7 @@ impl ResourceGovernor {
             start_time: None,
             op_count: 0,
             max_memory: 0,
+            max_nodes: 0,
         }
     }
 
@@ -117,6 +123,13 @@ impl ResourceGovernor {
         self
     }
 
+    /// Set maximum AST node count
+    #[inline]
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
     // ========================================================================
     // Configuration Getters/Setters
     // ========================================================================
@@ -169,6 +182,18 @@ impl ResourceGovernor {
         self.max_memory = max_memory;
     }
 
+    /// Get maximum AST node count
+    #[inline]
+    pub fn max_nodes(&self) -> usize {
+        self.max_nodes
+    }
+
+    /// Set maximum AST node count
+    #[inline]
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.max_nodes = max_nodes;
+    }
+
     /// Get current recursion depth
     #[inline]
     pub fn current_depth(&self) -> usize {
@@ -255,6 +280,20 @@ impl ResourceGovernor {
         Ok(())
     }
 
+    /// Check if the AST node count exceeds limit
+    ///
+    /// Cheap enough to call on every operation, unlike memory checks.
+    #[inline]
+    pub fn check_nodes(&self, node_count: usize) -> Result<(), ParseError> {
+        if self.max_nodes > 0 && node_count > self.max_nodes {
+            return Err(ParseError::NodeLimitExceeded {
+                count: node_count,
+                max: self.max_nodes,
+            });
+        }
+        Ok(())
+    }
+
     /// Check all resources (timeout and memory)
     ///
     /// This should be called periodically during parsing.
@@ -0,0 +1,41 @@
+//! Indentation tracking for significant-whitespace grammars
+//!
+//! `Atom::Indent`, `Atom::Samedent` and `Atom::Dedent` read and update a
+//! stack of indentation columns kept by the parser. Their result depends
+//! on that stack, not just on `(position, atom)`, and so does the result
+//! of every atom that can reach one of them. Caching those atoms would
+//! replay a result computed under a different indentation, and would skip
+//! the stack changes a successful match makes.
+//!
+//! This module finds those indent-sensitive atoms. The parser bypasses the
+//! packrat cache for them, and undoes their stack changes when they fail,
+//! so alternatives and repetitions backtrack to the indentation they
+//! started with. Grammars without indentation atoms get no
+//! [`Indentation`] at all, so their parse path is unchanged.
+
+use crate::portable::grammar::{Atom, Grammar};
+use crate::portable::grammar_analysis::GrammarAnalyzer;
+
+/// Indent-sensitive atoms of a grammar, indexed by atom ID
+#[derive(Debug, Clone)]
+pub(crate) struct Indentation {
+    sensitive: Vec<bool>,
+}
+
+impl Indentation {
+    /// Analyze a grammar, returning `None` if it has no indentation atoms
+    pub(crate) fn analyze(grammar: &Grammar) -> Option<Self> {
+        let sensitive = GrammarAnalyzer::new(grammar)
+            .atoms_reaching(|atom| matches!(atom, Atom::Indent | Atom::Samedent | Atom::Dedent));
+        if !sensitive.iter().any(|&s| s) {
+            return None;
+        }
+        Some(Self { sensitive })
+    }
+
+    /// Whether this atom's result depends on, or changes, the indent stack
+    #[inline]
+    pub(crate) fn is_sensitive(&self, atom_id: usize) -> bool {
+        self.sensitive.get(atom_id).copied().unwrap_or(false)
+    }
+}
@@ -0,0 +1,375 @@
+//! Explicit-stack parsing for deeply nested input
+//!
+//! [`PortableParser::parse`] descends through the grammar on the native call
+//! stack, a few frames per atom, so input nested tens of thousands of levels
+//! deep (`[[[[...]]]]`) overflows the thread's stack before reaching
+//! [`max_recursion_depth`](super::ParserConfig::max_recursion_depth).
+//!
+//! [`PortableParser::parse_iterative`] runs the composite atoms that nest
+//! (sequences, alternatives, repetitions, rule references and named
+//! captures) as frames on a heap-allocated stack instead. Nesting is then
+//! bounded by `max_recursion_depth` and memory alone. Other atoms are
+//! parsed by the recursive engine from the frame that reaches them:
+//! terminals don't nest, and the rarer composites (lookaheads, captures,
+//! infix expressions, ...) recurse into their children as usual.
+//!
+//! Atoms that need special treatment in [`try_atom`](PortableParser::try_atom)
+//! (left recursion, indentation, no-skip rules) are handed to it whole, so
+//! both paths produce the same results.
+
+use super::PortableParser;
+use crate::portable::ast::{AstNode, ParseError, ParseResult};
+use crate::portable::capture_state::CaptureSnapshot;
+use crate::portable::grammar::Atom;
+
+/// A composite atom waiting for the result of one of its children
+struct Frame<'a> {
+    /// The atom being parsed
+    atom_id: usize,
+    /// Where the atom started
+    pos: usize,
+    /// Whether the atom's result goes into the packrat cache
+    memoize: bool,
+    /// Capture state when the atom started, if the captures it stores go
+    /// into the cache with its result
+    since: Option<CaptureSnapshot>,
+    kind: FrameKind<'a>,
+}
+
+enum FrameKind<'a> {
+    /// Waiting for `atoms[next]`, which starts at `current`
+    Sequence {
+        atoms: &'a [usize],
+        next: usize,
+        current: usize,
+        items: Vec<AstNode>,
+    },
+    /// Waiting for `atoms[next]`
+    Alternative { atoms: &'a [usize], next: usize },
+    /// Waiting for another match of `atom` at `current`
+    Repetition {
+        atom: usize,
+        min: usize,
+        max: Option<usize>,
+        current: usize,
+        items: Vec<AstNode>,
+    },
+    /// Waiting for the captured atom
+    Named { name: &'a str },
+    /// Waiting for the referenced rule
+    Entity,
+}
+
+/// What the engine does next
+enum Step {
+    /// Parse an atom at a position
+    Enter(usize, usize),
+    /// Hand a result to the frame on top of the stack
+    Return(Result<ParseResult, ParseError>),
+}
+
+impl<'a> PortableParser<'a> {
+    /// Parse the input without recursing on the native stack
+    ///
+    /// Produces the same result as [`parse`](Self::parse), but nesting depth
+    /// is limited only by the recursion limit and heap memory. The default
+    /// limit of [`DEFAULT_MAX_RECURSION_DEPTH`](super::DEFAULT_MAX_RECURSION_DEPTH)
+    /// still applies; raise it with
+    /// [`set_max_recursion_depth`](Self::set_max_recursion_depth) for deeply
+    /// nested input.
+    ///
+    /// The recursive [`parse`](Self::parse) is faster on typical input, so
+    /// prefer this only where input may nest deeper than the native stack
+    /// allows.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::{parser_dsl::*, AstArena, PortableParser};
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule("list", str("[").then(ref_("list").optional()).then(str("]")))
+    ///     .build();
+    /// let input = format!("{}{}", "[".repeat(10_000), "]".repeat(10_000));
+    ///
+    /// let mut arena = AstArena::for_input(input.len());
+    /// let mut parser = PortableParser::new(&grammar, &input, &mut arena);
+    /// parser.set_max_recursion_depth(20_000);
+    /// assert!(parser.parse_iterative().is_ok());
+    /// ```
+    pub fn parse_iterative(&mut self) -> Result<AstNode, ParseError> {
+        self.check_input_size()?;
+        self.start_timeout_timer();
+        self.indent_stack.clear();
+        let start = self.skip(0)?;
+        let result = self
+            .run_iterative(self.grammar.root, start)
+            .and_then(|result| self.skip_trailing(result));
+        self.complete_parse(result)
+    }
+
+    /// Parse `atom_id` at `pos` with an explicit stack of frames
+    fn run_iterative(&mut self, atom_id: usize, pos: usize) -> Result<ParseResult, ParseError> {
+        let mut stack: Vec<Frame<'a>> = Vec::new();
+        let mut step = Step::Enter(atom_id, pos);
+        loop {
+            step = match step {
+                Step::Enter(atom_id, pos) => self.enter_atom(atom_id, pos, &mut stack),
+                Step::Return(result) => match stack.pop() {
+                    Some(frame) => self.resume_frame(frame, result, &mut stack),
+                    None => return result,
+                },
+            };
+        }
+    }
+
+    /// Start parsing `atom_id` at `pos`
+    ///
+    /// Composite atoms push a frame and enter their first child; everything
+    /// else is parsed right away.
+    fn enter_atom(&mut self, atom_id: usize, pos: usize, stack: &mut Vec<Frame<'a>>) -> Step {
+        if self.needs_try_atom(atom_id) {
+            return Step::Return(self.try_atom(atom_id, pos));
+        }
+        if let Err(e) = self.check_resources() {
+            return Step::Return(Err(e));
+        }
+
+        // Capture-dependent results depend on the capture state, so they
+        // are parsed every time, as in `try_atom`
+        let memoize = self.skip_disabled == 0
+            && self.grammar.is_memoized(atom_id)
+            && !self
+                .captures
+                .as_ref()
+                .is_some_and(|captures| captures.is_dependent(atom_id));
+        let mut since = None;
+        if memoize {
+            if let Some(cached) = self.cached_outcome(atom_id, pos) {
+                self.record_outcome(atom_id, pos, &cached);
+                return Step::Return(cached);
+            }
+            since = self.capture_mark(atom_id);
+        }
+
+        let grammar = self.grammar;
+        let (kind, child) = match grammar.get_atom(atom_id) {
+            Some(Atom::Sequence { atoms }) if !atoms.is_empty() => (
+                FrameKind::Sequence {
+                    atoms,
+                    next: 0,
+                    current: pos,
+                    items: Vec::with_capacity(atoms.len()),
+                },
+                atoms[0],
+            ),
+            Some(Atom::Alternative { atoms }) if !atoms.is_empty() => {
+                (FrameKind::Alternative { atoms, next: 0 }, atoms[0])
+            }
+            // Repetitions of a regex take the bulk path and don't nest
+            Some(Atom::Repetition { atom, min, max })
+                if *max != Some(0) && !matches!(grammar.get_atom(*atom), Some(Atom::Re { .. })) =>
+            {
+                (
+                    FrameKind::Repetition {
+                        atom: *atom,
+                        min: *min,
+                        max: *max,
+                        current: pos,
+                        items: Vec::with_capacity((*min).clamp(8, 64)),
+                    },
+                    *atom,
+                )
+            }
+            Some(Atom::Named { name, atom }) => {
+                if let Some(breadcrumbs) = &mut self.breadcrumbs {
+                    breadcrumbs.push(atom_id);
+                }
+                (FrameKind::Named { name }, *atom)
+            }
+            Some(Atom::Entity { atom }) => {
+                if let Err(e) = self.enter_recursive() {
+                    return Step::Return(self.finish_atom(atom_id, pos, memoize, since, Err(e)));
+                }
+                (FrameKind::Entity, *atom)
+            }
+            _ => {
+                let result = self.parse_atom_uncached(atom_id, pos);
+                return Step::Return(self.finish_atom(atom_id, pos, memoize, since, result));
+            }
+        };
+
+        stack.push(Frame {
+            atom_id,
+            pos,
+            memoize,
+            since,
+            kind,
+        });
+        Step::Enter(child, pos)
+    }
+
+    /// Continue the composite atom of `frame` with its child's `result`
+    ///
+    /// Mirrors the recursive `parse_*` methods, error handling included.
+    fn resume_frame(
+        &mut self,
+        mut frame: Frame<'a>,
+        result: Result<ParseResult, ParseError>,
+        stack: &mut Vec<Frame<'a>>,
+    ) -> Step {
+        let pos = frame.pos;
+        let outcome = match &mut frame.kind {
+            FrameKind::Sequence {
+                atoms,
+                next,
+                current,
+                items,
+            } => match result {
+                Ok(result) => {
+                    items.push(result.value);
+                    *current = result.end_pos;
+                    *next += 1;
+                    if *next < atoms.len() {
+                        match self.skip(*current) {
+                            Ok(child_pos) => {
+                                *current = child_pos;
+                                let child = atoms[*next];
+                                stack.push(frame);
+                                return Step::Enter(child, child_pos);
+                            }
+                            Err(e) => Err(e),
+                        }
+                    } else {
+                        let (pool_index, length) =
+                            self.arena.store_tagged_array(":sequence", items);
+                        Ok(ParseResult {
+                            value: AstNode::Array { pool_index, length },
+                            end_pos: *current,
+                            capture_state: None,
+                        })
+                    }
+                }
+                Err(e) => Err(e),
+            },
+            FrameKind::Alternative { atoms, next } => match result {
+                Err(ParseError::Failed { .. }) => {
+                    *next += 1;
+                    if *next < atoms.len() {
+                        let child = atoms[*next];
+                        stack.push(frame);
+                        return Step::Enter(child, pos);
+                    }
+                    Err(ParseError::Failed { position: pos })
+                }
+                result => result,
+            },
+            FrameKind::Repetition {
+                atom,
+                min,
+                max,
+                current,
+                items,
+            } => {
+                match result {
+                    Ok(result) => {
+                        items.push(result.value);
+                        *current = result.end_pos;
+                        if max.is_none_or(|max| items.len() < max) {
+                            // `current` stays before the trivia in case
+                            // the next item fails
+                            match self.skip(*current) {
+                                Ok(child_pos) => {
+                                    let child = *atom;
+                                    stack.push(frame);
+                                    return Step::Enter(child, child_pos);
+                                }
+                                Err(e) => {
+                                    return Step::Return(self.finish_atom(
+                                        frame.atom_id,
+                                        pos,
+                                        frame.memoize,
+                                        frame.since.clone(),
+                                        Err(e),
+                                    ))
+                                }
+                            }
+                        }
+                    }
+                    Err(ParseError::Failed { .. }) => {}
+                    Err(e) => {
+                        return Step::Return(self.finish_atom(
+                            frame.atom_id,
+                            pos,
+                            frame.memoize,
+                            frame.since.clone(),
+                            Err(e),
+                        ))
+                    }
+                }
+                if items.len() < *min {
+                    Err(ParseError::Failed { position: pos })
+                } else {
+                    let (pool_index, length) = self.arena.store_tagged_array(":repetition", items);
+                    Ok(ParseResult {
+                        value: AstNode::Array { pool_index, length },
+                        end_pos: *current,
+                        capture_state: None,
+                    })
+                }
+            }
+            FrameKind::Named { name } => {
+                if let Some(breadcrumbs) = &mut self.breadcrumbs {
+                    breadcrumbs.pop();
+                }
+                result.map(|result| {
+                    let (pool_index, length) = self.arena.store_hash(&[(*name, result.value)]);
+                    ParseResult {
+                        value: AstNode::Hash { pool_index, length },
+                        end_pos: result.end_pos,
+                        capture_state: None,
+                    }
+                })
+            }
+            FrameKind::Entity => {
+                self.exit_recursive();
+                result
+            }
+        };
+        Step::Return(self.finish_atom(frame.atom_id, pos, frame.memoize, frame.since, outcome))
+    }
+
+    /// Cache and record the result of an atom, as [`try_atom`](Self::try_atom) does
+    fn finish_atom(
+        &mut self,
+        atom_id: usize,
+        pos: usize,
+        memoize: bool,
+        since: Option<CaptureSnapshot>,
+        result: Result<ParseResult, ParseError>,
+    ) -> Result<ParseResult, ParseError> {
+        let result = if memoize {
+            self.memoize(atom_id, pos, since, result)
+        } else {
+            result
+        };
+        self.record_outcome(atom_id, pos, &result);
+        result
+    }
+
+    /// Whether `atom_id` bypasses the plain cache-or-parse path of
+    /// [`try_atom`](Self::try_atom), and so is left to it
+    fn needs_try_atom(&self, atom_id: usize) -> bool {
+        self.ignored.is_some()
+            || self.profiler.is_some()
+            || self
+                .left_recursion
+                .as_ref()
+                .is_some_and(|lr| lr.is_head(atom_id) || lr.is_member(atom_id))
+            || self
+                .indentation
+                .as_ref()
+                .is_some_and(|indentation| indentation.is_sensitive(atom_id))
+            || (self.skip_disabled == 0 && self.disables_skip(atom_id))
+    }
+}
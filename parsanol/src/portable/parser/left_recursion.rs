@@ -0,0 +1,67 @@
+//! Left-recursion detection for seed growing
+//!
+//! PEG parsers loop forever on rules like `expr = expr "+" term | term`.
+//! The parser handles these with Warth-style seed growing: the first call
+//! of a left-recursive rule at a position plants a failure "seed" in the
+//! packrat cache, parses the rule body (where the recursive call sees the
+//! seed), and repeats with the improved result until the match stops
+//! growing.
+//!
+//! This module decides which atoms need that treatment, reusing the cycle
+//! detection from [`GrammarAnalyzer`]. Grammars without left recursion get
+//! no [`LeftRecursion`] at all, so their parse path is unchanged.
+
+use crate::portable::grammar::{Atom, Grammar};
+use crate::portable::grammar_analysis::GrammarAnalyzer;
+
+/// Left-recursive atoms of a grammar, indexed by atom ID
+#[derive(Debug, Clone)]
+pub(crate) struct LeftRecursion {
+    /// Atoms on a left-recursive cycle (re-enterable at the same position)
+    members: Vec<bool>,
+    /// Rule atoms at which seed growing is performed (targets of a
+    /// left-recursive entity reference)
+    heads: Vec<bool>,
+}
+
+impl LeftRecursion {
+    /// Analyze a grammar, returning `None` if it has no left recursion
+    pub(crate) fn analyze(grammar: &Grammar) -> Option<Self> {
+        // Every cycle goes through an entity reference
+        if !grammar
+            .atoms
+            .iter()
+            .any(|a| matches!(a, Atom::Entity { .. }))
+        {
+            return None;
+        }
+
+        let members = GrammarAnalyzer::new(grammar).left_recursive_atoms();
+        if !members.iter().any(|&m| m) {
+            return None;
+        }
+
+        let mut heads = vec![false; members.len()];
+        for (idx, atom) in grammar.atoms.iter().enumerate() {
+            if let Atom::Entity { atom: target } = atom {
+                if members[idx] && members.get(*target).copied().unwrap_or(false) {
+                    heads[*target] = true;
+                }
+            }
+        }
+
+        Some(Self { members, heads })
+    }
+
+    /// Whether seed growing is performed at this atom
+    #[inline]
+    pub(crate) fn is_head(&self, atom_id: usize) -> bool {
+        self.heads.get(atom_id).copied().unwrap_or(false)
+    }
+
+    /// Whether this atom is on a left-recursive cycle
+    #[inline]
+    pub(crate) fn is_member(&self, atom_id: usize) -> bool {
+        self.members.get(atom_id).copied().unwrap_or(false)
+    }
+}
@@ -13,25 +13,42 @@
 //! This separation follows the Single Responsibility Principle - each component
 //! has one clear purpose.
 
+mod bytes;
+pub(crate) mod captures;
 mod config;
 mod context;
 mod governor;
+pub(crate) mod indentation;
+mod iterative;
+pub(crate) mod left_recursion;
 mod simd;
 
 #[cfg(test)]
 mod tests;
 
-pub use config::{ParserConfig, DEFAULT_MAX_INPUT_SIZE, DEFAULT_MAX_RECURSION_DEPTH};
-pub use context::ParseContext;
+pub use bytes::PortableParserBytes;
+pub use config::{
+    ParserConfig, RecoveryConfig, DEFAULT_MAX_INPUT_SIZE, DEFAULT_MAX_RECURSION_DEPTH,
+};
+pub use context::{FailureTracker, MemoryBreakdown, ParseContext};
 pub use governor::ResourceGovernor;
 
 use crate::portable::arena::AstArena;
 use crate::portable::ast::{AstNode, ParseError, ParseResult};
-use crate::portable::cache::{CacheEntry, DenseCache};
-use crate::portable::capture_state::CaptureState;
+use crate::portable::cache::{CacheEntry, DenseCache, PackratStats};
+use crate::portable::capture_state::{CaptureSnapshot, CaptureState};
 use crate::portable::char_class::{utf8_char_len, CharacterPattern};
-use crate::portable::grammar::{Atom, Grammar};
+use crate::portable::cst::CstNode;
+use crate::portable::grammar::{Atom, DefaultValue, Grammar};
+use crate::portable::infix::{Assoc, Fixity, InfixOperator};
+use crate::portable::parsed_tree::ParsedTree;
 use crate::portable::regex_cache;
+use crate::portable::source_location::offset_to_line_col;
+use crate::portable::transform::DirectTransform;
+use captures::{CaptureEffects, Captures};
+use indentation::Indentation;
+use left_recursion::LeftRecursion;
+use std::borrow::Cow;
 
 /// Logging macros - no-op when logging feature is disabled
 #[cfg(not(feature = "logging"))]
@@ -70,6 +87,10 @@ pub struct PortableParser<'a> {
     /// Input as bytes (for fast indexing)
     input_bytes: &'a [u8],
 
+    /// Whether regexes match `input_bytes` in byte mode: the grammar is
+    /// [`ascii_only`](Grammar::ascii_only) and so is the input
+    byte_regex: bool,
+
     // ========================================================================
     // Output (mutable)
     // ========================================================================
@@ -82,6 +103,9 @@ pub struct PortableParser<'a> {
     /// Cached AST nodes for cache hits
     cached_nodes: Vec<AstNode>,
 
+    /// Slots of `cached_nodes` freed by cache evictions, for reuse
+    free_cached_nodes: Vec<u32>,
+
     // ========================================================================
     // Resource Management (delegated)
     // ========================================================================
@@ -93,6 +117,62 @@ pub struct PortableParser<'a> {
     // ========================================================================
     /// Capture state for named captures
     capture_state: CaptureState,
+
+    /// Capture-dependent and capturing atoms (`None` for most grammars)
+    captures: Option<Cow<'a, Captures>>,
+
+    /// Captures stored by memoized results, replayed on cache hits
+    capture_effects: CaptureEffects,
+
+    // ========================================================================
+    // Left Recursion
+    // ========================================================================
+    /// Left-recursive atoms needing seed growing (`None` for most grammars)
+    left_recursion: Option<Cow<'a, LeftRecursion>>,
+
+    // ========================================================================
+    // Indentation
+    // ========================================================================
+    /// Indent-sensitive atoms (`None` for most grammars)
+    indentation: Option<Cow<'a, Indentation>>,
+
+    /// Columns of the open indented blocks, innermost last
+    indent_stack: Vec<usize>,
+
+    // ========================================================================
+    // Skipping
+    // ========================================================================
+    /// Number of [`Grammar::no_skip`] atoms (or the skip atom) being
+    /// parsed; the skip atom is only applied while this is 0
+    skip_disabled: usize,
+
+    /// Whether the skip atom is being tried, so its failures aren't
+    /// recorded as expected tokens
+    skipping: bool,
+
+    /// `(start, end)` of each [`Atom::Ignore`] match, in input order
+    /// (`None` unless a streaming builder is being fed)
+    ignored: Option<Vec<(usize, usize)>>,
+
+    // ========================================================================
+    // Diagnostics
+    // ========================================================================
+    /// Atoms that failed at the furthest position reached
+    failures: FailureTracker,
+
+    /// Named atoms being parsed, outermost first (`None` unless a rich
+    /// error was asked for)
+    breadcrumbs: Option<Vec<usize>>,
+
+    /// `breadcrumbs` as they were when the furthest failure was recorded
+    failure_breadcrumbs: Vec<usize>,
+
+    /// Whether [`parse`](Self::parse) reports the furthest failure position
+    /// instead of the root's
+    report_farthest_failure: bool,
+
+    /// Per-atom timings (`None` unless a profile was asked for)
+    profiler: Option<Box<super::debug::ParseProfiler>>,
 }
 
 impl<'a> PortableParser<'a> {
@@ -108,6 +188,71 @@ impl<'a> PortableParser<'a> {
         )
     }
 
+    /// Create a parser over input bytes, checking that they are UTF-8
+    ///
+    /// Returns [`ParseError::InvalidUtf8`] with the offset of the first
+    /// invalid byte instead of parsing. Use this for bytes from FFI callers
+    /// and other untrusted sources, which must never reach
+    /// [`new`](Self::new) unchecked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    /// use parsanol::portable::{AstArena, ParseError, PortableParser};
+    ///
+    /// let grammar = GrammarBuilder::new().rule("word", re("[a-z]").many()).build();
+    /// let mut arena = AstArena::new();
+    /// let result = PortableParser::from_bytes(&grammar, b"ab\xffc", &mut arena);
+    /// assert!(matches!(result, Err(ParseError::InvalidUtf8 { offset: 2 })));
+    /// ```
+    pub fn from_bytes(
+        grammar: &'a Grammar,
+        input: &'a [u8],
+        arena: &'a mut AstArena,
+    ) -> Result<Self, ParseError> {
+        let input = std::str::from_utf8(input).map_err(|e| ParseError::InvalidUtf8 {
+            offset: e.valid_up_to(),
+        })?;
+        Ok(Self::new(grammar, input, arena))
+    }
+
+    /// Create a parser over input bytes, replacing invalid UTF-8
+    ///
+    /// Every invalid sequence becomes U+FFFD (`"\u{FFFD}"`), as with
+    /// [`String::from_utf8_lossy`]. Valid input is parsed in place; otherwise
+    /// the repaired text is written to `buffer` and parsed from there, so
+    /// offsets in the AST and in errors refer to the repaired text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    /// use parsanol::portable::{AstArena, PortableParser};
+    ///
+    /// let grammar = GrammarBuilder::new().rule("text", any().many()).build();
+    /// let mut buffer = String::new();
+    /// let mut arena = AstArena::new();
+    /// let mut parser =
+    ///     PortableParser::from_bytes_lossy(&grammar, b"ab\xffc", &mut buffer, &mut arena);
+    /// assert!(parser.parse().is_ok());
+    /// ```
+    pub fn from_bytes_lossy(
+        grammar: &'a Grammar,
+        input: &'a [u8],
+        buffer: &'a mut String,
+        arena: &'a mut AstArena,
+    ) -> Self {
+        let input = match String::from_utf8_lossy(input) {
+            Cow::Borrowed(input) => input,
+            Cow::Owned(repaired) => {
+                *buffer = repaired;
+                buffer.as_str()
+            }
+        };
+        Self::new(grammar, input, arena)
+    }
+
     /// Create a new parser with a pre-existing cache
     #[inline]
     pub fn new_with_cache(
@@ -120,16 +265,40 @@ impl<'a> PortableParser<'a> {
         let governor = ResourceGovernor::new()
             .with_max_input_size(DEFAULT_MAX_INPUT_SIZE)
             .with_max_recursion_depth(DEFAULT_MAX_RECURSION_DEPTH);
+        arena.attach_span_source(input);
+
+        // The captures stored by results from an earlier parse weren't
+        // kept, so those results can't be replayed
+        let captures = grammar.captures();
+        let mut cache = cache;
+        if let Some(captures) = &captures {
+            cache.retain(|entry| !captures.is_capturing(entry.atom_id as usize));
+        }
 
         Self {
             grammar,
             input,
             input_bytes: input.as_bytes(),
+            byte_regex: grammar.ascii_only && input.is_ascii(),
             arena,
             cache,
             cached_nodes,
+            free_cached_nodes: Vec::new(),
             governor,
             capture_state: CaptureState::new(),
+            captures,
+            capture_effects: CaptureEffects::default(),
+            left_recursion: grammar.left_recursion(),
+            indentation: grammar.indentation(),
+            indent_stack: Vec::new(),
+            skip_disabled: 0,
+            skipping: false,
+            ignored: None,
+            failures: FailureTracker::new(),
+            breadcrumbs: None,
+            failure_breadcrumbs: Vec::new(),
+            report_farthest_failure: true,
+            profiler: None,
         }
     }
 
@@ -148,16 +317,32 @@ impl<'a> PortableParser<'a> {
         let governor = ResourceGovernor::new()
             .with_max_input_size(max_input_size)
             .with_max_recursion_depth(max_recursion_depth);
+        arena.attach_span_source(input);
 
         Self {
             grammar,
             input,
             input_bytes: input.as_bytes(),
+            byte_regex: grammar.ascii_only && input.is_ascii(),
             arena,
             cache,
             cached_nodes: Vec::with_capacity(estimated_entries),
+            free_cached_nodes: Vec::new(),
             governor,
             capture_state: CaptureState::new(),
+            captures: grammar.captures(),
+            capture_effects: CaptureEffects::default(),
+            left_recursion: grammar.left_recursion(),
+            indentation: grammar.indentation(),
+            indent_stack: Vec::new(),
+            skip_disabled: 0,
+            skipping: false,
+            ignored: None,
+            failures: FailureTracker::new(),
+            breadcrumbs: None,
+            failure_breadcrumbs: Vec::new(),
+            report_farthest_failure: true,
+            profiler: None,
         }
     }
 
@@ -191,12 +376,87 @@ impl<'a> PortableParser<'a> {
         self.governor.set_max_memory(max_memory);
     }
 
+    /// Set maximum AST node count (0 = unlimited)
+    ///
+    /// See [`ParserConfig::max_nodes`].
+    #[inline]
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.governor.set_max_nodes(max_nodes);
+    }
+
+    /// Set whether failed parses report the furthest failure position
+    ///
+    /// On by default: a [`ParseError::Failed`] from [`parse`](Self::parse)
+    /// carries the furthest offset any atom failed at, the "deepest error".
+    /// Ordered choice otherwise reports where the failing rule started,
+    /// which is often offset 0 for errors deep in the input. When off, the
+    /// root rule's own failure position is reported.
+    pub fn set_report_farthest_failure(&mut self, enabled: bool) {
+        self.report_farthest_failure = enabled;
+    }
+
+    /// Bound the packrat cache to `max_bytes` (0 = unbounded)
+    ///
+    /// Replaces the cache, so call this before parsing. A bounded cache
+    /// evicts results once full and re-parses them when needed again; see
+    /// [`DenseCache::bounded`].
+    pub fn set_max_cache_bytes(&mut self, max_bytes: usize) {
+        if self.cache.max_bytes().unwrap_or(0) == max_bytes {
+            return;
+        }
+        self.cache = if max_bytes == 0 {
+            DenseCache::for_input(self.input.len(), self.grammar.atom_count())
+        } else {
+            DenseCache::bounded(max_bytes)
+        };
+        self.cached_nodes.clear();
+        self.free_cached_nodes.clear();
+        self.capture_effects.clear();
+    }
+
     /// Get memory usage
     #[inline]
     pub fn memory_usage(&self) -> usize {
         self.arena.memory_usage() + self.cache.memory_usage()
     }
 
+    /// Get memory usage split into arena, cache and cached nodes
+    ///
+    /// Useful for telling whether a
+    /// [`MemoryLimitExceeded`](ParseError::MemoryLimitExceeded) is better
+    /// fixed by bounding the cache ([`set_max_cache_bytes`](Self::set_max_cache_bytes))
+    /// or by feeding smaller inputs.
+    #[inline]
+    pub fn memory_breakdown(&self) -> MemoryBreakdown {
+        MemoryBreakdown::measure(self.arena, &self.cache, &self.cached_nodes)
+    }
+
+    /// Get packrat cache statistics
+    ///
+    /// Call after [`parse`](Self::parse) to see how effective memoization
+    /// was for this input.
+    #[inline]
+    pub fn cache_stats(&self) -> PackratStats {
+        self.cache.packrat_stats()
+    }
+
+    /// Atom ids attempted at the furthest failure position, sorted
+    ///
+    /// After a failed parse these are the atoms that could have matched
+    /// where the input stopped making sense; map them through
+    /// [`Grammar::get_atom`] to the literals and patterns they expect.
+    /// Failures are tracked across every parse call on this parser, so a
+    /// successful parse that backtracked also leaves entries behind.
+    pub fn expected_at_failure(&self) -> Vec<usize> {
+        self.failures.expected()
+    }
+
+    /// Furthest position an atom failed at, if any failed
+    #[inline]
+    pub fn failure_position(&self) -> Option<usize> {
+        self.failures.position()
+    }
+
     /// Get a reference to the capture state
     #[inline]
     pub fn capture_state(&self) -> &CaptureState {
@@ -211,7 +471,8 @@ impl<'a> PortableParser<'a> {
 
     /// Parse from a specific position (for dynamic atom support)
     pub fn parse_from_pos(&mut self, pos: usize) -> Result<ParseResult, ParseError> {
-        let result = self.try_atom(self.grammar.root, pos)?;
+        self.indent_stack.clear();
+        let result = self.parse_root(pos)?;
         Ok(ParseResult {
             value: result.value,
             end_pos: result.end_pos,
@@ -219,6 +480,125 @@ impl<'a> PortableParser<'a> {
         })
     }
 
+    /// Parse the root rule starting at byte offset `start`
+    ///
+    /// Unlike [`parse`](Self::parse), the match may end before the end of
+    /// input; the returned [`ParseResult::end_pos`] says where it stopped,
+    /// after any trivia matched by the [skip](Grammar::skip) rule.
+    /// Positions in the result (and in the packrat cache) are absolute
+    /// offsets into the whole input, so a parser reused across several
+    /// `parse_at` calls shares its cache soundly.
+    ///
+    /// Returns [`ParseError::InvalidOffset`] if `start` is past the end of
+    /// input or not on a UTF-8 char boundary.
+    pub fn parse_at(&mut self, start: usize) -> Result<ParseResult, ParseError> {
+        if !self.input.is_char_boundary(start) {
+            return Err(ParseError::InvalidOffset { offset: start });
+        }
+        self.check_input_size()?;
+        self.start_timeout_timer();
+        self.parse_from_pos(start)
+    }
+
+    /// Find every non-overlapping match of the root rule in the input
+    ///
+    /// Scans left to right: the root rule is tried at each position, and a
+    /// match is recorded as `(start, node)` before scanning resumes at its
+    /// end. Where nothing matches, the scan moves on by one char. A match
+    /// that consumes nothing is recorded, then skipped over by one char so
+    /// the scan always makes progress. With a [skip](Grammar::skip) rule,
+    /// the trivia before each start is skipped and isn't part of the match.
+    ///
+    /// Packrat entries are keyed by absolute position, so a result
+    /// memoized while trying one start is still correct when a later start
+    /// reaches the same `(position, atom)`, and the cache is shared across
+    /// the scan. Results that depend on the captures made so far are never
+    /// cached, and cache hits store the captures their result stored, so
+    /// this holds for grammars with captures too. Captures and the indent
+    /// stack are reset before each start.
+    ///
+    /// Errors other than a failed match, such as a timeout or a memory
+    /// limit, end the scan early; the matches found up to that point are
+    /// returned.
+    pub fn parse_all(&mut self) -> Vec<(usize, AstNode)> {
+        self.parse_all_spans()
+            .into_iter()
+            .map(|(start, _, node)| (start, node))
+            .collect()
+    }
+
+    /// [`parse_all`](Self::parse_all), with each match as `(start, end, node)`
+    pub(crate) fn parse_all_spans(&mut self) -> Vec<(usize, usize, AstNode)> {
+        let mut matches = Vec::new();
+        if self.check_input_size().is_err() {
+            return matches;
+        }
+        self.start_timeout_timer();
+
+        // A literal root can only start at one of its first bytes, so the
+        // scan jumps between those with memchr
+        let first_bytes = self.literal_first_bytes(self.grammar.root);
+        let bytes = self.input.as_bytes();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            if let Some(first_bytes) = &first_bytes {
+                let next = match first_bytes[..] {
+                    [a] => simd::find_byte(bytes, pos, a),
+                    [a, b] => simd::find_byte2(bytes, pos, a, b).map(|(next, _)| next),
+                    [a, b, c] => simd::find_byte3(bytes, pos, a, b, c).map(|(next, _)| next),
+                    _ => Some(pos),
+                };
+                match next {
+                    Some(next) => pos = next,
+                    None => break,
+                }
+            }
+            self.capture_state.clear();
+            self.indent_stack.clear();
+
+            let Ok(start) = self.skip(pos) else {
+                break;
+            };
+            let step = bytes.get(start).map_or(1, |&byte| utf8_char_len(byte));
+            match self.try_atom(self.grammar.root, start) {
+                Ok(result) => {
+                    matches.push((start, result.end_pos, result.value));
+                    pos = if result.end_pos > start {
+                        result.end_pos
+                    } else {
+                        start + step
+                    };
+                }
+                Err(ParseError::Failed { .. }) => pos = start + step,
+                Err(_) => break,
+            }
+        }
+        matches
+    }
+
+    /// First bytes of the literals an atom matches, if it's a literal or a
+    /// choice of literals starting with at most three distinct bytes
+    ///
+    /// The first byte of a literal is never a UTF-8 continuation byte, so
+    /// every input position holding one is a char boundary.
+    fn literal_first_bytes(&self, atom_id: usize) -> Option<Vec<u8>> {
+        let first_byte = |atom_id: usize| match self.grammar.get_atom(atom_id) {
+            Some(Atom::Str { pattern }) => pattern.as_bytes().first().copied(),
+            _ => None,
+        };
+        let mut bytes = match self.grammar.get_atom(atom_id)? {
+            Atom::Str { .. } => vec![first_byte(atom_id)?],
+            Atom::Alternative { atoms } => atoms
+                .iter()
+                .map(|&atom_id| first_byte(atom_id))
+                .collect::<Option<Vec<u8>>>()?,
+            _ => return None,
+        };
+        bytes.sort_unstable();
+        bytes.dedup();
+        (1..=3).contains(&bytes.len()).then_some(bytes)
+    }
+
     // ========================================================================
     // Resource Checking (delegated to governor)
     // ========================================================================
@@ -247,9 +627,10 @@ impl<'a> PortableParser<'a> {
         self.governor.start_timeout_timer()
     }
 
-    /// Check resources (timeout and memory)
+    /// Check resources (timeout, memory and node count)
     #[inline]
     fn check_resources(&mut self) -> Result<(), ParseError> {
+        self.governor.check_nodes(self.arena.node_count())?;
         self.governor.check_resources(self.memory_usage())
     }
 
@@ -269,7 +650,35 @@ impl<'a> PortableParser<'a> {
             self.grammar.root
         );
 
-        match self.try_atom(self.grammar.root, 0) {
+        self.indent_stack.clear();
+        let result = self.parse_root(0);
+        self.complete_parse(result)
+    }
+
+    /// Parse the root rule at `pos`, with the skip rule applied before and
+    /// after it
+    fn parse_root(&mut self, pos: usize) -> Result<ParseResult, ParseError> {
+        let start = self.skip(pos)?;
+        let result = self.try_atom(self.grammar.root, start)?;
+        self.skip_trailing(result)
+    }
+
+    /// `result` with the skip rule applied after its end
+    fn skip_trailing(&mut self, mut result: ParseResult) -> Result<ParseResult, ParseError> {
+        result.end_pos = self.skip(result.end_pos)?;
+        Ok(result)
+    }
+
+    /// Turn the root's result into the result of a whole-input parse
+    ///
+    /// A match that stops short of the end of input is
+    /// [`Incomplete`](ParseError::Incomplete), and a failure reports the
+    /// furthest failure position if asked to.
+    fn complete_parse(
+        &self,
+        result: Result<ParseResult, ParseError>,
+    ) -> Result<AstNode, ParseError> {
+        match result {
             Ok(result) => {
                 if result.end_pos == self.input.len() {
                     log_debug!("Parse successful");
@@ -281,16 +690,60 @@ impl<'a> PortableParser<'a> {
                     })
                 }
             }
+            Err(ParseError::Failed { position }) if self.report_farthest_failure => {
+                Err(ParseError::Failed {
+                    position: self
+                        .failure_position()
+                        .map_or(position, |furthest| furthest.max(position)),
+                })
+            }
             Err(e) => Err(e),
         }
     }
 
+    /// Parse a prefix of the input, returning the AST and where matching stopped
+    ///
+    /// Unlike [`parse`](Self::parse), input left over after the root rule
+    /// matches is not an error. The returned offset is a byte offset, past
+    /// any trivia matched by the [skip](Grammar::skip) rule, so the
+    /// unconsumed remainder is `&input[offset..]`.
+    ///
+    /// Parsing always starts at offset 0. To continue matching from the
+    /// remainder, call [`parse_at`](Self::parse_at) with `offset` on this
+    /// parser: the packrat cache is keyed by absolute positions, so it is
+    /// shared soundly between the two parses.
+    pub fn parse_prefix(&mut self) -> Result<(AstNode, usize), ParseError> {
+        self.parse_with_end_pos()
+            .map(|result| (result.value, result.end_pos))
+    }
+
+    /// Parse and return a lossless concrete syntax tree
+    ///
+    /// Unlike [`parse`](Self::parse), the result accounts for every byte of
+    /// input: text the AST drops (ignored whitespace, comments, punctuation)
+    /// appears as [`CstNode::Trivia`], so [`CstNode::reconstruct`] returns
+    /// the input exactly.
+    pub fn parse_cst(&mut self) -> Result<CstNode, ParseError> {
+        let ast = self.parse()?;
+        Ok(CstNode::from_ast(&ast, self.arena, self.input))
+    }
+
+    /// Parse and return the result as a [`ParsedTree`]
+    ///
+    /// The tree borrows the arena and input, so strings can be read without
+    /// copying them out of the parse result.
+    pub fn parse_tree(&mut self) -> Result<ParsedTree<'_>, ParseError> {
+        let ast = self.parse()?;
+        Ok(ParsedTree::new(ast, &*self.arena, self.input))
+    }
+
     /// Parse with end position
     #[inline]
     pub fn parse_with_end_pos(&mut self) -> Result<ParseResult, ParseError> {
         self.check_input_size()?;
         self.start_timeout_timer();
-        self.try_atom(self.grammar.root, 0)
+        self.indent_stack.clear();
+        self.parse_root(0)
     }
 
     /// Parse with custom config
@@ -300,16 +753,119 @@ impl<'a> PortableParser<'a> {
             .set_max_recursion_depth(config.max_recursion_depth);
         self.governor.set_timeout_ms(config.timeout_ms);
         self.governor.set_max_memory(config.max_memory);
-        self.parse()
+        self.governor.set_max_nodes(config.max_nodes);
+        self.set_max_cache_bytes(config.max_cache_bytes);
+        self.set_report_farthest_failure(config.farthest_failure);
+        match self.parse() {
+            Err(ParseError::Failed { position }) if config.collect_expected => {
+                Err(ParseError::FailedExpecting {
+                    position: self.failure_position().unwrap_or(position),
+                    expected: self.expected_descriptions(),
+                })
+            }
+            result => result,
+        }
+    }
+
+    /// Node for the `length` bytes of input matched at `pos`
+    ///
+    /// An [`AstNode::InputRef`], or an interned [`AstNode::StringRef`] if
+    /// the arena was created with [`AstArena::with_interned_inputs`].
+    #[inline(always)]
+    fn matched_text(&mut self, pos: usize, length: usize) -> AstNode {
+        if self.arena.interns_inputs() {
+            self.arena.intern_input_ref(pos, length, self.input)
+        } else {
+            self.arena.input_ref(pos, length)
+        }
+    }
+
+    /// Describe the terminals that failed at the furthest failure position
+    ///
+    /// Composite atoms (sequences, rule references, ...) are left out: they
+    /// failed because a terminal inside them did. Atoms with a description
+    /// in [`Grammar::rule_descriptions`] are listed by it, composite or not,
+    /// in place of the terminals inside them. Duplicates are removed,
+    /// keeping the first occurrence.
+    fn expected_descriptions(&self) -> Vec<String> {
+        use super::grammar_analysis::GrammarAnalyzer;
+
+        // Terminals inside a described atom are reported by its description
+        let failed = self.failures.expected();
+        let analyzer = GrammarAnalyzer::new(self.grammar);
+        let mut covered = std::collections::HashSet::new();
+        for &atom_id in &failed {
+            if self.grammar.rule_description(atom_id).is_some() {
+                covered.extend(analyzer.reachable_from(atom_id));
+            }
+        }
+
+        let mut expected: Vec<String> = Vec::new();
+        for atom_id in failed {
+            if let Some(description) = self.grammar.rule_description(atom_id) {
+                if !expected.iter().any(|e| e == description) {
+                    expected.push(description.to_string());
+                }
+                continue;
+            }
+            if covered.contains(&atom_id) {
+                continue;
+            }
+            let description = match self.grammar.get_atom(atom_id) {
+                Some(Atom::Str { pattern }) | Some(Atom::StrCi { pattern }) => pattern.clone(),
+                Some(Atom::Re { pattern }) => pattern.clone(),
+                Some(Atom::CharRange { lo, hi }) => format!("{}..={}", lo, hi),
+                Some(Atom::OneOf { chars }) => {
+                    format!("one of {}", chars.iter().collect::<String>())
+                }
+                Some(Atom::NoneOf { chars }) => {
+                    format!("none of {}", chars.iter().collect::<String>())
+                }
+                Some(Atom::Until { terminator, .. }) => terminator.clone(),
+                Some(Atom::ByteRange { lo, hi }) => format!("0x{:02x}..=0x{:02x}", lo, hi),
+                Some(Atom::Eof) => "end of input".to_string(),
+                _ => continue,
+            };
+            if !expected.contains(&description) {
+                expected.push(description);
+            }
+        }
+        expected
     }
 
     /// Parse with streaming builder
+    ///
+    /// Builds the whole AST first and then walks it, so the arena holds the
+    /// complete tree. See [`parse_streaming`](Self::parse_streaming) for a
+    /// mode that delivers events item by item instead.
+    ///
+    /// Input matched by ignored atoms is reported through
+    /// [`on_ignored`](super::streaming_builder::StreamingBuilder::on_ignored).
+    /// When the grammar has ignored atoms, collecting their spans means
+    /// the packrat cache is not consulted during the parse.
     pub fn parse_with_builder<B: super::streaming_builder::StreamingBuilder>(
         &mut self,
         builder: &mut B,
     ) -> Result<B::Output, ParseError> {
+        self.parse_with_builder_control(builder)
+            .map(|(output, _)| output)
+    }
+
+    /// Parse with streaming builder, reporting whether the builder stopped early
+    ///
+    /// Returns the builder's output together with [`BuildControl::Stop`] if
+    /// the builder asked to stop before every event was delivered, or
+    /// [`BuildControl::Continue`] if the whole tree was walked. `finish` is
+    /// called in both cases; builder and parse failures are still errors.
+    ///
+    /// [`BuildControl::Stop`]: super::streaming_builder::BuildControl::Stop
+    /// [`BuildControl::Continue`]: super::streaming_builder::BuildControl::Continue
+    pub fn parse_with_builder_control<B: super::streaming_builder::StreamingBuilder>(
+        &mut self,
+        builder: &mut B,
+    ) -> Result<(B::Output, super::streaming_builder::BuildControl), ParseError> {
         use super::parslet_transform::to_parslet_compatible;
-        use super::streaming_builder::walk_ast;
+        use super::streaming_builder::walk_ast_with_ignored;
 
         builder
             .on_start(self.input)
@@ -317,35 +873,367 @@ impl<'a> PortableParser<'a> {
                 message: e.to_string(),
             })?;
 
-        let raw_ast = self.parse()?;
-        let transformed = to_parslet_compatible(&raw_ast, self.arena, self.input);
+        self.collect_ignored();
+        let raw_ast = self.parse();
+        let ignored = self.ignored.take().unwrap_or_default();
+        let transformed = to_parslet_compatible(&raw_ast?, self.arena, self.input);
 
-        walk_ast(&transformed, self.arena, self.input, builder).map_err(|e| {
-            ParseError::BuilderError {
-                message: e.to_string(),
-            }
-        })?;
+        let control =
+            walk_ast_with_ignored(&transformed, self.arena, self.input, &ignored, builder)
+                .map_err(|e| ParseError::BuilderError {
+                    message: e.to_string(),
+                })?;
 
         builder.on_success().map_err(|e| ParseError::BuilderError {
             message: e.to_string(),
         })?;
 
-        builder.finish().map_err(|e| ParseError::BuilderError {
+        let output = builder.finish().map_err(|e| ParseError::BuilderError {
+            message: e.to_string(),
+        })?;
+        Ok((output, control))
+    }
+
+    /// Parse, convert to [`Value`] and apply semantic actions
+    ///
+    /// The result goes through the Parslet-compatible conversion, then
+    /// `actions` (from
+    /// [`GrammarBuilder::build_with_actions`](super::parser_dsl::GrammarBuilder::build_with_actions))
+    /// are applied bottom-up.
+    pub fn parse_and_transform(
+        &mut self,
+        actions: &super::parser_dsl::SemanticActions,
+    ) -> Result<super::transform::Value, ParseError> {
+        use super::parslet_transform::to_parslet_compatible;
+        use super::transform::ast_to_value;
+
+        let raw_ast = self.parse()?;
+        let transformed = to_parslet_compatible(&raw_ast, self.arena, self.input);
+        Ok(actions.apply(ast_to_value(&transformed, self.arena, self.input)))
+    }
+
+    /// Parse, convert to [`Value`] and run the transforms of `plugins`
+    ///
+    /// The result goes through the Parslet-compatible conversion, as in
+    /// [`parse_and_transform`](Self::parse_and_transform), and then through
+    /// every transform registered with
+    /// [`TransformRegistry::register_transform`](super::plugin::TransformRegistry::register_transform),
+    /// in registration order.
+    ///
+    /// [`Value`]: super::transform::Value
+    pub fn parse_and_apply_plugins(
+        &mut self,
+        plugins: &super::plugin::PluginRegistry,
+    ) -> Result<super::transform::Value, ParseError> {
+        use super::parslet_transform::to_parslet_compatible;
+        use super::transform::ast_to_value;
+
+        let raw_ast = self.parse()?;
+        let transformed = to_parslet_compatible(&raw_ast, self.arena, self.input);
+        Ok(plugins
+            .transforms()
+            .apply(ast_to_value(&transformed, self.arena, self.input)))
+    }
+
+    /// Parse and convert the AST straight into `T`
+    ///
+    /// Shorthand for [`parse`](Self::parse) followed by
+    /// [`T::from_ast`](DirectTransform::from_ast) with this parser's arena
+    /// and input, skipping the intermediate [`Value`] tree. A
+    /// [`TransformError`](super::transform::TransformError) becomes
+    /// [`ParseError::Transform`].
+    ///
+    /// [`Value`]: super::transform::Value
+    pub fn parse_into<T: DirectTransform>(&mut self) -> Result<T, ParseError> {
+        let ast = self.parse()?;
+        T::from_ast(&ast, self.arena, self.input).map_err(|e| ParseError::Transform {
             message: e.to_string(),
         })
     }
 
+    /// Parse, delivering builder events while the input is consumed
+    ///
+    /// When the root rule is a repetition (after following rule references),
+    /// each repeated item is parsed, converted and walked on its own, and its
+    /// nodes are dropped from the arena before the next item is parsed. The
+    /// arena therefore only ever holds one item rather than the whole
+    /// document. For any other root this behaves like
+    /// [`parse_with_builder`](Self::parse_with_builder).
+    ///
+    /// # Differences from `parse_with_builder`
+    ///
+    /// - Events are buffered per item: an item's events are delivered once
+    ///   it has matched, and backtracking never reaches back into an item
+    ///   that was already delivered. If a later item fails, or input is left
+    ///   over, the earlier items' events stand; `on_error` is called and the
+    ///   error is returned.
+    /// - Each item goes through the Parslet-compatible conversion on its own,
+    ///   so rewrites spanning several items (such as joining adjacent string
+    ///   matches) do not apply at the top level.
+    /// - The top-level array is announced with `on_array_start(None)`, since
+    ///   its length is only known at the end.
+    /// - A builder asking to [stop](super::streaming_builder::BuildControl::Stop)
+    ///   stops the parse itself: later items are never parsed, and input left
+    ///   over at that point is not an error.
+    /// - The packrat cache only lives for one item.
+    pub fn parse_streaming<B: super::streaming_builder::StreamingBuilder>(
+        &mut self,
+        builder: &mut B,
+    ) -> Result<B::Output, ParseError> {
+        use super::parslet_transform::to_parslet_compatible;
+        use super::streaming_builder::walk_ast_with_ignored;
+
+        let Some((item, min, max)) = self.streamable_root() else {
+            return self.parse_with_builder(builder);
+        };
+
+        builder.on_start(self.input)?;
+        self.check_input_size()?;
+        self.start_timeout_timer();
+
+        builder.on_array_start(None)?;
+        self.collect_ignored();
+        let between_items = self.skips_between_items(item);
+        let mut pos = 0;
+        let mut count = 0;
+        let mut stopped = false;
+        while max.is_none_or(|max| count < max) {
+            let checkpoint = self.arena.checkpoint();
+            let start = if count == 0 || between_items {
+                self.skip(pos)
+            } else {
+                Ok(pos)
+            };
+            let result = match start.and_then(|start| self.try_atom(item, start)) {
+                Ok(result) if result.end_pos > pos => result,
+                Ok(_) | Err(ParseError::Failed { .. }) => break,
+                Err(e) => {
+                    self.ignored = None;
+                    builder.on_error(&e)?;
+                    return Err(e);
+                }
+            };
+
+            let ignored = self
+                .ignored
+                .as_mut()
+                .map(std::mem::take)
+                .unwrap_or_default();
+            let transformed = to_parslet_compatible(&result.value, self.arena, self.input);
+            stopped =
+                walk_ast_with_ignored(&transformed, self.arena, self.input, &ignored, builder)?
+                    .is_stop();
+            builder.on_array_element(count)?;
+            count += 1;
+            pos = result.end_pos;
+
+            // Nothing from this item is referenced any more
+            self.arena.truncate(checkpoint);
+            self.cache = self.cache.emptied();
+            self.cached_nodes.clear();
+            self.free_cached_nodes.clear();
+            self.capture_effects.clear();
+
+            if stopped || builder.control().is_stop() {
+                stopped = true;
+                break;
+            }
+        }
+        let trailing = if stopped { Ok(pos) } else { self.skip(pos) };
+        self.ignored = None;
+
+        let error = match trailing {
+            Err(e) => Some(e),
+            Ok(_) if count < min => Some(ParseError::Failed { position: pos }),
+            Ok(end) if !stopped && end != self.input.len() => Some(ParseError::Incomplete {
+                expected: self.input.len(),
+                actual: end,
+            }),
+            Ok(_) => None,
+        };
+        if let Some(e) = error {
+            builder.on_error(&e)?;
+            return Err(e);
+        }
+
+        builder.on_array_end(count)?;
+        builder.on_success()?;
+        Ok(builder.finish()?)
+    }
+
+    /// Whether the skip rule applies between the items of a repetition of
+    /// `atom_id`
+    ///
+    /// A repetition of a regex matches a single token, so nothing is skipped
+    /// inside it.
+    fn skips_between_items(&self, atom_id: usize) -> bool {
+        !matches!(self.grammar.get_atom(atom_id), Some(Atom::Re { .. }))
+    }
+
+    /// The repeated item and bounds, if the root rule is a repetition
+    fn streamable_root(&self) -> Option<(usize, usize, Option<usize>)> {
+        let mut atom_id = self.grammar.root;
+        for _ in 0..self.grammar.atom_count() {
+            match self.grammar.get_atom(atom_id)? {
+                Atom::Entity { atom } => atom_id = *atom,
+                Atom::Repetition { atom, min, max } => return Some((*atom, *min, *max)),
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Parse, recovering from syntax errors and collecting all of them
+    ///
+    /// Equivalent to [`parse_recovering_with`](Self::parse_recovering_with)
+    /// with the default [`RecoveryConfig`], which resynchronizes at the next
+    /// newline.
+    pub fn parse_recovering(&mut self) -> (Option<AstNode>, Vec<super::error::RichError>) {
+        self.parse_recovering_with(&RecoveryConfig::default())
+    }
+
+    /// Parse, recovering from syntax errors and collecting all of them
+    ///
+    /// When the root rule is a repetition (after following rule references),
+    /// an item that fails to match is reported as an error, the input is
+    /// skipped up to and including the next synchronization point from
+    /// `config`, and parsing resumes with the next item. The skipped text
+    /// shows up in the `:repetition` array as an error marker, a hash
+    /// `{"error" => <skipped input>}`, so the rest of the document keeps
+    /// its structure.
+    ///
+    /// For any other root a single error is reported, as with
+    /// [`parse_with_rich_error`](Self::parse_with_rich_error). Resource
+    /// errors (input size, timeout, recursion depth) abort the parse and
+    /// return no AST.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parsanol::portable::parser_dsl::*;
+    /// use parsanol::portable::{AstArena, PortableParser};
+    ///
+    /// let stmt = re("[a-z]").many1().then(str(";")).then(str("\n").optional());
+    /// let grammar = GrammarBuilder::new().rule("stmts", stmt.many()).build();
+    ///
+    /// let mut arena = AstArena::new();
+    /// let mut parser = PortableParser::new(&grammar, "ab;\n1;\ncd;\n", &mut arena);
+    /// let (ast, errors) = parser.parse_recovering();
+    /// assert!(ast.is_some());
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].span.start.line, 2);
+    /// ```
+    pub fn parse_recovering_with(
+        &mut self,
+        config: &RecoveryConfig,
+    ) -> (Option<AstNode>, Vec<super::error::RichError>) {
+        let Some((item, min, max)) = self.streamable_root() else {
+            return match self.parse_with_rich_error() {
+                Ok(node) => (Some(node), Vec::new()),
+                Err(e) => (None, vec![e]),
+            };
+        };
+
+        if let Err(e) = self.check_input_size() {
+            return (None, vec![e.into_rich(self.input)]);
+        }
+        self.start_timeout_timer();
+
+        let mut items = Vec::new();
+        let mut errors = Vec::new();
+        let mut matched = 0;
+        let between_items = self.skips_between_items(item);
+        let mut pos = 0;
+        loop {
+            let full = max.is_some_and(|max| matched >= max);
+            if items.is_empty() || between_items || full {
+                pos = match self.skip(pos) {
+                    Ok(start) => start,
+                    Err(e) => {
+                        errors.push(e.into_rich(self.input));
+                        return (None, errors);
+                    }
+                };
+            }
+            if pos >= self.input.len() || full {
+                break;
+            }
+            let position = match self.try_atom(item, pos) {
+                Ok(result) if result.end_pos > pos => {
+                    items.push(result.value);
+                    matched += 1;
+                    pos = result.end_pos;
+                    continue;
+                }
+                Ok(_) => pos,
+                Err(ParseError::Failed { position }) => position,
+                Err(e) => {
+                    errors.push(e.into_rich(self.input));
+                    return (None, errors);
+                }
+            };
+            // Nothing but trivia after the last item of a single token
+            if !between_items && self.skip(pos).is_ok_and(|end| end == self.input.len()) {
+                pos = self.input.len();
+                break;
+            }
+
+            errors.push(self.failure_error(item, position));
+            let end = config.sync_end(self.input, pos);
+            let skipped = AstNode::InputRef {
+                offset: pos as u32,
+                length: (end - pos) as u32,
+            };
+            let (pool_index, length) = self.arena.store_hash(&[("error", skipped)]);
+            items.push(AstNode::Hash { pool_index, length });
+            pos = end;
+        }
+
+        if pos < self.input.len() {
+            let (line, col) = super::error::offset_to_line_col(self.input, pos);
+            errors.push(super::error::RichError::at_position(
+                format!(
+                    "Incomplete parse: consumed {} of {} bytes",
+                    pos,
+                    self.input.len()
+                ),
+                pos,
+                line,
+                col,
+            ));
+        } else if errors.is_empty() && matched < min {
+            errors.push(self.failure_error(item, pos));
+        }
+
+        let (pool_index, length) = self.arena.store_tagged_array(":repetition", &items);
+        (Some(AstNode::Array { pool_index, length }), errors)
+    }
+
     // ========================================================================
     // Cache Management
     // ========================================================================
 
     #[inline(always)]
     fn store_cached_node(&mut self, node: AstNode) -> u32 {
+        if let Some(idx) = self.free_cached_nodes.pop() {
+            self.cached_nodes[idx as usize] = node;
+            return idx;
+        }
         let idx = self.cached_nodes.len() as u32;
         self.cached_nodes.push(node);
         idx
     }
 
+    /// Insert into the cache, recycling the node of any evicted entry
+    #[inline(always)]
+    fn insert_cached(&mut self, entry: CacheEntry) {
+        if let Some(evicted) = self.cache.insert(entry) {
+            if evicted.success() {
+                self.free_cached_nodes.push(evicted.ast_ref());
+            }
+        }
+    }
+
     // ========================================================================
     // Core Parsing - Try Atom
     // ========================================================================
@@ -361,37 +1249,233 @@ impl<'a> PortableParser<'a> {
     /// Both successful AND failed parses are cached. Caching failures is
     /// crucial for PEG parsing performance, especially with grammars that
     /// have many alternatives (like EXPRESS with 2273 atoms).
+    ///
+    /// Failures also feed the furthest-failure watermark behind
+    /// [`expected_at_failure`](Self::expected_at_failure).
     #[inline]
     pub fn try_atom(&mut self, atom_id: usize, pos: usize) -> Result<ParseResult, ParseError> {
+        if self.ignored.is_some() {
+            return self.try_atom_collecting_ignored(atom_id, pos);
+        }
+        if self.profiler.is_some() {
+            return self.try_atom_profiled(atom_id, pos);
+        }
+        let result = self.try_atom_inner(atom_id, pos);
+        self.record_outcome(atom_id, pos, &result);
+        result
+    }
+
+    /// [`try_atom`](Self::try_atom), forgetting the ignored spans of a
+    /// failed attempt
+    #[inline(never)]
+    fn try_atom_collecting_ignored(
+        &mut self,
+        atom_id: usize,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        let mark = self.ignored_mark();
+        let result = if self.profiler.is_some() {
+            self.try_atom_profiled(atom_id, pos)
+        } else {
+            let result = self.try_atom_inner(atom_id, pos);
+            self.record_outcome(atom_id, pos, &result);
+            result
+        };
+        if result.is_err() {
+            self.discard_ignored(mark);
+        }
+        result
+    }
+
+    /// Start collecting ignored spans for a builder, if the grammar has
+    /// any ignored atoms
+    fn collect_ignored(&mut self) {
+        let any_ignored = self
+            .grammar
+            .atoms
+            .iter()
+            .any(|atom| matches!(atom, Atom::Ignore { .. }));
+        self.ignored = any_ignored.then(Vec::new);
+    }
+
+    /// Number of ignored spans collected so far
+    #[inline]
+    fn ignored_mark(&self) -> usize {
+        self.ignored.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Forget the ignored spans collected after `mark`
+    #[inline]
+    fn discard_ignored(&mut self, mark: usize) {
+        if let Some(ignored) = &mut self.ignored {
+            ignored.truncate(mark);
+        }
+    }
+
+    /// [`try_atom`](Self::try_atom), timing the call for the profiler
+    #[inline(never)]
+    fn try_atom_profiled(&mut self, atom_id: usize, pos: usize) -> Result<ParseResult, ParseError> {
+        let named = matches!(self.grammar.get_atom(atom_id), Some(Atom::Named { .. }));
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(atom_id, named);
+        }
+        let start = std::time::Instant::now();
+        let result = self.try_atom_inner(atom_id, pos);
+        let elapsed = start.elapsed();
+        self.record_outcome(atom_id, pos, &result);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit(atom_id, named, elapsed);
+        }
+        result
+    }
+
+    /// Feed a result into the failure watermark and node spans
+    #[inline(always)]
+    fn record_outcome(
+        &mut self,
+        atom_id: usize,
+        pos: usize,
+        result: &Result<ParseResult, ParseError>,
+    ) {
+        match result {
+            Err(ParseError::Failed { .. }) if !self.skipping => {
+                if let Some(breadcrumbs) = &self.breadcrumbs {
+                    if self
+                        .failures
+                        .position()
+                        .is_none_or(|furthest| pos > furthest)
+                    {
+                        self.failure_breadcrumbs.clone_from(breadcrumbs);
+                    }
+                }
+                self.failures.record(atom_id, pos)
+            }
+            Ok(result) if self.arena.spans_enabled() => {
+                self.arena.record_span(&result.value, pos, result.end_pos)
+            }
+            _ => {}
+        }
+    }
+
+    #[inline(always)]
+    fn try_atom_inner(&mut self, atom_id: usize, pos: usize) -> Result<ParseResult, ParseError> {
         self.check_resources()?;
 
-        // Check cache
-        let cache_hit = self
+        // Results involving back-references or dynamic atoms depend on the
+        // capture state, not just (pos, atom), so they bypass the cache
+        if self
+            .captures
+            .as_ref()
+            .is_some_and(|captures| captures.is_dependent(atom_id))
+        {
+            return self.parse_atom_uncached(atom_id, pos);
+        }
+
+        if let Some(lr) = &self.left_recursion {
+            if lr.is_head(atom_id) {
+                return self.grow_left_recursion(atom_id, pos);
+            }
+            // Other atoms on the cycle are derived from the head's seed, so
+            // caching them would keep stale results across growth iterations
+            if lr.is_member(atom_id) {
+                return self.parse_atom_uncached(atom_id, pos);
+            }
+        }
+
+        // Indent-sensitive results depend on the indent stack, so they
+        // bypass the cache, and undo their stack changes on failure
+        if self
+            .indentation
+            .as_ref()
+            .is_some_and(|indentation| indentation.is_sensitive(atom_id))
+        {
+            return self.parse_indent_sensitive(atom_id, pos);
+        }
+
+        // Inside a no-skip atom, results depend on skipping being off, so
+        // they can't share cache entries with the same atom elsewhere. A
+        // cache hit would also lose the ignored spans under the atom.
+        if self.skip_disabled > 0 || self.ignored.is_some() || !self.grammar.is_memoized(atom_id) {
+            return self.parse_atom_uncached(atom_id, pos);
+        }
+
+        if let Some(cached) = self.cached_outcome(atom_id, pos) {
+            return cached;
+        }
+
+        let since = self.capture_mark(atom_id);
+        let result = self.parse_atom_uncached(atom_id, pos);
+        self.memoize(atom_id, pos, since, result)
+    }
+
+    /// The capture state before parsing `atom_id`, if its match may store
+    /// captures that must be memoized with its result
+    #[inline(always)]
+    fn capture_mark(&self, atom_id: usize) -> Option<CaptureSnapshot> {
+        self.captures
+            .as_ref()
+            .is_some_and(|captures| captures.is_capturing(atom_id))
+            .then(|| self.capture_state.snapshot())
+    }
+
+    /// The cached result of `atom_id` at `pos`, if there is one
+    ///
+    /// The captures the result stored when it was parsed are stored again.
+    #[inline(always)]
+    fn cached_outcome(
+        &mut self,
+        atom_id: usize,
+        pos: usize,
+    ) -> Option<Result<ParseResult, ParseError>> {
+        let (success, end_pos, ast_ref) = self
             .cache
             .get(pos as u32, atom_id as u16)
-            .map(|e| (e.success(), e.end_pos, e.ast_ref()));
-
-        if let Some((success, end_pos, ast_ref)) = cache_hit {
-            return if success {
-                let cached = self.cached_nodes[ast_ref as usize].clone();
-                Ok(ParseResult {
-                    value: cached,
-                    end_pos: end_pos as usize,
-                    capture_state: None,
-                })
-            } else {
-                // Cached failure - this is important for PEG performance!
-                // Without caching failures, we'd re-parse failed alternatives every time
-                Err(ParseError::Failed { position: pos })
-            };
+            .map(|e| (e.success(), e.end_pos, e.ast_ref()))?;
+
+        if self
+            .captures
+            .as_ref()
+            .is_some_and(|captures| captures.is_capturing(atom_id))
+        {
+            self.capture_effects
+                .replay(atom_id, pos, &mut self.capture_state);
         }
 
-        // Parse uncached
-        match self.parse_atom_uncached(atom_id, pos) {
+        Some(if success {
+            let cached = self.cached_nodes[ast_ref as usize].clone();
+            Ok(ParseResult {
+                value: cached,
+                end_pos: end_pos as usize,
+                capture_state: None,
+            })
+        } else {
+            // Cached failure - this is important for PEG performance!
+            // Without caching failures, we'd re-parse failed alternatives every time
+            Err(ParseError::Failed { position: pos })
+        })
+    }
+
+    /// Cache the uncached `result` of `atom_id` at `pos`, returning it
+    ///
+    /// `since` is the [`capture_mark`](Self::capture_mark) taken before
+    /// parsing; the captures stored after it are kept with the result.
+    #[inline(always)]
+    fn memoize(
+        &mut self,
+        atom_id: usize,
+        pos: usize,
+        since: Option<CaptureSnapshot>,
+        result: Result<ParseResult, ParseError>,
+    ) -> Result<ParseResult, ParseError> {
+        if let (Some(since), Ok(_) | Err(ParseError::Failed { .. })) = (&since, &result) {
+            self.capture_effects
+                .record(atom_id, pos, &self.capture_state, since);
+        }
+        match result {
             Ok(result) => {
                 // Cache successful result
                 let ast_ref = self.store_cached_node(result.value);
-                self.cache.insert(CacheEntry::new(
+                self.insert_cached(CacheEntry::new(
                     pos as u32,
                     atom_id as u16,
                     true,
@@ -405,11 +1489,14 @@ impl<'a> PortableParser<'a> {
                     capture_state: None,
                 })
             }
+            // Errors other than a failed match abort the parse, and say
+            // nothing about what matches here
+            Err(e) if !matches!(e, ParseError::Failed { .. }) => Err(e),
             Err(e) => {
                 // CRITICAL: Cache failures too!
                 // Without this, failed alternatives are re-parsed exponentially
                 // This is the key to packrat parser performance
-                self.cache.insert(CacheEntry::new(
+                self.insert_cached(CacheEntry::new(
                     pos as u32,
                     atom_id as u16,
                     false, // failure
@@ -421,44 +1508,244 @@ impl<'a> PortableParser<'a> {
         }
     }
 
+    /// Parse a left-recursive rule at `pos` by growing a seed
+    ///
+    /// # Cache invariant
+    ///
+    /// While growing, the cache entry for `(pos, atom_id)` holds the best
+    /// result found so far, starting from a failure seed, so the recursive
+    /// call at the same position returns it instead of recursing. Growth
+    /// stops once an iteration fails to advance past the previous end, and
+    /// the entry then holds the final result. Non-head atoms on the cycle are
+    /// never cached, so no entry derived from an intermediate seed outlives
+    /// the growth loop. If a resource error aborts growth, the seed entry is
+    /// removed so a reused cache (e.g. by incremental parsing) never sees it.
+    /// The entry is pinned while growing, so a bounded cache cannot evict it
+    /// and send the recursive call into unbounded recursion. The captures
+    /// the best result stored are kept with it, as in [`memoize`](Self::memoize).
+    fn grow_left_recursion(
+        &mut self,
+        atom_id: usize,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        if let Some(cached) = self.cached_outcome(atom_id, pos) {
+            return cached;
+        }
+
+        // Plant the failure seed
+        let since = self.capture_mark(atom_id);
+        let seed = CacheEntry::new(pos as u32, atom_id as u16, false, pos as u32, 0).pinned();
+        self.insert_cached(seed);
+        if let Some(since) = &since {
+            self.capture_effects
+                .record(atom_id, pos, &self.capture_state, since);
+        }
+
+        let mut best: Option<ParseResult> = None;
+        loop {
+            // Every iteration starts from the captures the rule started
+            // with; the recursive call replays those of the best result
+            if let Some(since) = &since {
+                self.capture_state.restore(since);
+            }
+            match self.parse_atom_uncached(atom_id, pos) {
+                Ok(result) if best.as_ref().is_none_or(|b| result.end_pos > b.end_pos) => {
+                    let ast_ref = self.store_cached_node(result.value.clone());
+                    let entry = CacheEntry::new(
+                        pos as u32,
+                        atom_id as u16,
+                        true,
+                        result.end_pos as u32,
+                        ast_ref,
+                    )
+                    .pinned();
+                    *self
+                        .cache
+                        .get_or_insert_with(pos as u32, atom_id as u16, || entry)
+                        .0 = entry;
+                    if let Some(since) = &since {
+                        self.capture_effects
+                            .record(atom_id, pos, &self.capture_state, since);
+                    }
+                    best = Some(result);
+                }
+                Ok(_) | Err(ParseError::Failed { .. }) => break,
+                Err(e) => {
+                    self.cache.retain(|entry| {
+                        !(entry.pos == pos as u32 && entry.atom_id == atom_id as u16)
+                    });
+                    return Err(e);
+                }
+            }
+        }
+
+        self.cache.unpin(pos as u32, atom_id as u16);
+        // The last iteration didn't improve on the best result, so its
+        // captures are replaced by the best result's
+        if let Some(since) = &since {
+            self.capture_state.restore(since);
+            self.capture_effects
+                .replay(atom_id, pos, &mut self.capture_state);
+        }
+        best.ok_or(ParseError::Failed { position: pos })
+    }
+
     #[inline]
     fn parse_atom_uncached(
         &mut self,
         atom_id: usize,
         pos: usize,
     ) -> Result<ParseResult, ParseError> {
+        if self.skip_disabled == 0 && self.disables_skip(atom_id) {
+            self.skip_disabled += 1;
+            let result = self.parse_atom_uncached(atom_id, pos);
+            self.skip_disabled -= 1;
+            return result;
+        }
+
         match self.grammar.get_atom(atom_id) {
             Some(atom) => match atom {
                 Atom::Str { pattern } => self.parse_str(pattern, pos),
+                Atom::StrCi { pattern } => self.parse_str_ci(pattern, pos),
                 Atom::Re { pattern } => self.parse_re(pattern, pos),
+                Atom::CharRange { lo, hi } => self.parse_char_range(*lo, *hi, pos),
+                Atom::OneOf { chars } => self.parse_char_set(chars, true, pos),
+                Atom::NoneOf { chars } => self.parse_char_set(chars, false, pos),
+                Atom::Until {
+                    terminator,
+                    inclusive,
+                    or_end,
+                } => self.parse_until(terminator, *inclusive, *or_end, pos),
+                Atom::ByteRange { lo, hi } => match self.input_bytes.get(pos) {
+                    // Only ASCII, so a match never splits a character
+                    Some(&b) if b.is_ascii() && (*lo..=*hi).contains(&b) => Ok(ParseResult {
+                        value: self.matched_text(pos, 1),
+                        end_pos: pos + 1,
+                        capture_state: None,
+                    }),
+                    _ => Err(ParseError::Failed { position: pos }),
+                },
                 Atom::Sequence { atoms } => self.parse_sequence(atoms, pos),
                 Atom::Alternative { atoms } => self.parse_alternative(atoms, pos),
+                Atom::LongestAlternative { atoms } => self.parse_longest_alternative(atoms, pos),
                 Atom::Repetition { atom, min, max } => {
                     self.parse_repetition(*atom, *min, *max, pos)
                 }
-                Atom::Named { name, atom } => self.parse_named(name, *atom, pos),
+                Atom::RepetitionLazy {
+                    atom,
+                    min,
+                    max,
+                    follow,
+                } => self.parse_repetition_lazy(*atom, *min, *max, *follow, pos),
+                Atom::Named { name, atom } => {
+                    if let Some(breadcrumbs) = &mut self.breadcrumbs {
+                        breadcrumbs.push(atom_id);
+                    }
+                    let result = self.parse_named(name, *atom, pos);
+                    if let Some(breadcrumbs) = &mut self.breadcrumbs {
+                        breadcrumbs.pop();
+                    }
+                    result
+                }
                 Atom::Entity { atom } => {
                     self.enter_recursive()?;
                     let result = self.try_atom(*atom, pos);
                     self.exit_recursive();
                     result
                 }
+                Atom::Instantiate { template, .. } => Err(ParseError::InvalidGrammar {
+                    reason: format!("unresolved instantiation of template {:?}", template),
+                }),
                 Atom::Lookahead { atom, positive } => self.parse_lookahead(*atom, *positive, pos),
+                Atom::Peek { atom } => {
+                    let mark = self.ignored_mark();
+                    let result = self.try_atom(*atom, pos)?;
+                    self.discard_ignored(mark);
+                    Ok(ParseResult {
+                        value: result.value,
+                        end_pos: pos,
+                        capture_state: None,
+                    })
+                }
+                Atom::Infix { operand, operators } => self.parse_infix(*operand, operators, 0, pos),
                 Atom::Cut => Ok(ParseResult {
                     value: AstNode::Nil,
                     end_pos: pos,
                     capture_state: None,
                 }),
+                Atom::Eof if pos == self.input.len() => Ok(ParseResult {
+                    value: AstNode::Nil,
+                    end_pos: pos,
+                    capture_state: None,
+                }),
+                Atom::Eof => Err(ParseError::Failed { position: pos }),
+                Atom::Indent => self.parse_indent(pos),
+                Atom::Samedent => match self.line_indent_column(pos) {
+                    Some(column) if column == self.indent_level() => Ok(ParseResult {
+                        value: AstNode::Nil,
+                        end_pos: pos,
+                        capture_state: None,
+                    }),
+                    _ => Err(ParseError::Failed { position: pos }),
+                },
+                Atom::Dedent => match self.indent_stack.pop() {
+                    Some(_) => Ok(ParseResult {
+                        value: AstNode::Nil,
+                        end_pos: pos,
+                        capture_state: None,
+                    }),
+                    None => Err(ParseError::Failed { position: pos }),
+                },
                 Atom::Ignore { atom } => {
+                    let mark = self.ignored_mark();
                     let result = self.try_atom(*atom, pos)?;
+                    // Report the outermost ignored span only
+                    if let Some(ignored) = &mut self.ignored {
+                        ignored.truncate(mark);
+                        if result.end_pos > pos {
+                            ignored.push((pos, result.end_pos));
+                        }
+                    }
                     Ok(ParseResult {
                         value: AstNode::Nil,
                         end_pos: result.end_pos,
                         capture_state: None,
                     })
                 }
+                Atom::Text { atom } => {
+                    let result = self.try_atom(*atom, pos)?;
+                    let value = self.arena.intern_string(&self.input[pos..result.end_pos]);
+                    Ok(ParseResult {
+                        value,
+                        end_pos: result.end_pos,
+                        capture_state: None,
+                    })
+                }
                 Atom::Custom { id } => self.parse_custom(*id, pos),
                 Atom::Capture { name, atom } => self.parse_capture(name, *atom, pos),
+                Atom::BackRef { name } => self.parse_backref(name, pos),
+                Atom::OptionalDefault {
+                    name,
+                    atom,
+                    default,
+                } => self.parse_optional_default(name, *atom, *default, pos),
+                Atom::ParseInt {
+                    atom,
+                    radix_prefixes,
+                } => self.parse_number(*atom, pos, |text| {
+                    parse_int(text, *radix_prefixes).map(AstNode::Int)
+                }),
+                Atom::ParseFloat { atom } => self.parse_number(*atom, pos, |text| {
+                    text.parse::<f64>().ok().map(AstNode::Float)
+                }),
+                Atom::Const { atom, value } => {
+                    let result = self.try_atom(*atom, pos)?;
+                    Ok(ParseResult {
+                        value: value.to_ast(),
+                        end_pos: result.end_pos,
+                        capture_state: None,
+                    })
+                }
                 Atom::Scope { atom } => self.parse_scope(*atom, pos),
                 Atom::Dynamic { callback_id } => self.parse_dynamic(*callback_id, pos),
             },
@@ -485,15 +1772,109 @@ impl<'a> PortableParser<'a> {
         let slice = &self.input_bytes[pos..end];
         if slice == pattern_bytes {
             Ok(ParseResult {
-                value: self.arena.input_ref(pos, pattern_len),
+                value: self.matched_text(pos, pattern_len),
                 end_pos: end,
                 capture_state: None,
-            })
-        } else {
-            Err(ParseError::Failed { position: pos })
+            })
+        } else {
+            Err(ParseError::Failed { position: pos })
+        }
+    }
+
+    #[inline]
+    fn parse_str_ci(&mut self, pattern: &str, pos: usize) -> Result<ParseResult, ParseError> {
+        let end = if pattern.is_ascii() {
+            // Hot path: ASCII case folding, byte for byte
+            let end = pos + pattern.len();
+            if end > self.input.len()
+                || !self.input_bytes[pos..end].eq_ignore_ascii_case(pattern.as_bytes())
+            {
+                return Err(ParseError::Failed { position: pos });
+            }
+            end
+        } else {
+            // Unicode: compare one input char per pattern char, lowercased
+            let mut input_chars = self.input[pos..].char_indices();
+            for expected in pattern.chars() {
+                match input_chars.next() {
+                    Some((_, actual)) if actual.to_lowercase().eq(expected.to_lowercase()) => {}
+                    _ => return Err(ParseError::Failed { position: pos }),
+                }
+            }
+            input_chars
+                .next()
+                .map_or(self.input.len(), |(offset, _)| pos + offset)
+        };
+
+        Ok(ParseResult {
+            value: self.matched_text(pos, end - pos),
+            end_pos: end,
+            capture_state: None,
+        })
+    }
+
+    #[inline]
+    fn parse_char_range(
+        &mut self,
+        lo: char,
+        hi: char,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        match self.input.get(pos..).and_then(|rest| rest.chars().next()) {
+            Some(c) if (lo..=hi).contains(&c) => Ok(ParseResult {
+                value: self.matched_text(pos, c.len_utf8()),
+                end_pos: pos + c.len_utf8(),
+                capture_state: None,
+            }),
+            _ => Err(ParseError::Failed { position: pos }),
+        }
+    }
+
+    /// Match one character that is (`accept`) or isn't in `chars`
+    #[inline]
+    fn parse_char_set(
+        &mut self,
+        chars: &[char],
+        accept: bool,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        match self.input.get(pos..).and_then(|rest| rest.chars().next()) {
+            Some(c) if chars.contains(&c) == accept => Ok(ParseResult {
+                value: self.matched_text(pos, c.len_utf8()),
+                end_pos: pos + c.len_utf8(),
+                capture_state: None,
+            }),
+            _ => Err(ParseError::Failed { position: pos }),
         }
     }
 
+    /// Consume input up to the next `terminator`, see [`Atom::Until`]
+    #[inline]
+    fn parse_until(
+        &mut self,
+        terminator: &str,
+        inclusive: bool,
+        or_end: bool,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        let found = if terminator.is_empty() {
+            Some(pos)
+        } else {
+            simd::find_pattern(self.input_bytes, pos, terminator.as_bytes())
+        };
+        let (body_end, end_pos) = match found {
+            Some(at) if inclusive => (at, at + terminator.len()),
+            Some(at) => (at, at),
+            None if or_end => (self.input.len(), self.input.len()),
+            None => return Err(ParseError::Failed { position: pos }),
+        };
+        Ok(ParseResult {
+            value: self.matched_text(pos, body_end - pos),
+            end_pos,
+            capture_state: None,
+        })
+    }
+
     #[inline]
     fn parse_re(&mut self, pattern: &str, pos: usize) -> Result<ParseResult, ParseError> {
         if pos >= self.input.len() {
@@ -504,6 +1885,16 @@ impl<'a> PortableParser<'a> {
 
         // Fast path for character classes
         if let Some(char_pattern) = CharacterPattern::from_pattern(pattern) {
+            if char_pattern.is_unicode() {
+                return match self.input[pos..].chars().next() {
+                    Some(c) if char_pattern.matches_char(c) => Ok(ParseResult {
+                        value: self.matched_text(pos, c.len_utf8()),
+                        end_pos: pos + c.len_utf8(),
+                        capture_state: None,
+                    }),
+                    _ => Err(ParseError::Failed { position: pos }),
+                };
+            }
             if char_pattern.matches(b) {
                 let char_len = match char_pattern {
                     CharacterPattern::Any
@@ -513,7 +1904,7 @@ impl<'a> PortableParser<'a> {
                     _ => 1,
                 };
                 return Ok(ParseResult {
-                    value: self.arena.input_ref(pos, char_len),
+                    value: self.matched_text(pos, char_len),
                     end_pos: pos + char_len,
                     capture_state: None,
                 });
@@ -522,7 +1913,21 @@ impl<'a> PortableParser<'a> {
             }
         }
 
-        // General case
+        // General case. Byte mode matches the same text on ASCII input
+        // without UTF-8 handling; patterns it can't compile fall through.
+        if self.byte_regex {
+            if let Some(regex) = regex_cache::get_or_compile_bytes(pattern) {
+                return match regex.find(&self.input_bytes[pos..]) {
+                    Some(m) if m.start() == 0 => Ok(ParseResult {
+                        value: self.matched_text(pos, m.end()),
+                        end_pos: pos + m.end(),
+                        capture_state: None,
+                    }),
+                    _ => Err(ParseError::Failed { position: pos }),
+                };
+            }
+        }
+
         let regex = match regex_cache::get_or_compile(pattern) {
             Some(r) => r,
             None => {
@@ -537,7 +1942,7 @@ impl<'a> PortableParser<'a> {
             if m.start() == 0 {
                 let match_len = m.end();
                 return Ok(ParseResult {
-                    value: self.arena.input_ref(pos, match_len),
+                    value: self.matched_text(pos, match_len),
                     end_pos: pos + match_len,
                     capture_state: None,
                 });
@@ -547,12 +1952,68 @@ impl<'a> PortableParser<'a> {
         Err(ParseError::Failed { position: pos })
     }
 
+    /// Parse an atom that can reach an indentation atom
+    ///
+    /// A failing atom leaves the indent stack as it found it, so the next
+    /// alternative sees the indentation from before this one was tried.
+    /// Lookaheads and peeks consume nothing, so they restore it even when
+    /// they succeed.
+    fn parse_indent_sensitive(
+        &mut self,
+        atom_id: usize,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        let saved = self.indent_stack.clone();
+        let result = self.parse_atom_uncached(atom_id, pos);
+        let zero_width = matches!(
+            self.grammar.get_atom(atom_id),
+            Some(Atom::Lookahead { .. } | Atom::Peek { .. })
+        );
+        if result.is_err() || zero_width {
+            self.indent_stack = saved;
+        }
+        result
+    }
+
+    /// Current indentation level (column 1 outside any block)
+    #[inline]
+    fn indent_level(&self) -> usize {
+        self.indent_stack.last().copied().unwrap_or(1)
+    }
+
+    /// Column of `pos` if only spaces and tabs precede it on its line
+    fn line_indent_column(&self, pos: usize) -> Option<usize> {
+        let line_start = memchr::memrchr(b'\n', &self.input_bytes[..pos]).map_or(0, |nl| nl + 1);
+        let leading = &self.input[line_start..pos];
+        if !leading.bytes().all(|b| b == b' ' || b == b'\t') {
+            return None;
+        }
+        Some(offset_to_line_col(leading, leading.len()).1)
+    }
+
+    fn parse_indent(&mut self, pos: usize) -> Result<ParseResult, ParseError> {
+        match self.line_indent_column(pos) {
+            Some(column) if column > self.indent_level() => {
+                self.indent_stack.push(column);
+                Ok(ParseResult {
+                    value: AstNode::Nil,
+                    end_pos: pos,
+                    capture_state: None,
+                })
+            }
+            _ => Err(ParseError::Failed { position: pos }),
+        }
+    }
+
     #[inline]
     fn parse_sequence(&mut self, atoms: &[usize], pos: usize) -> Result<ParseResult, ParseError> {
         let mut current_pos = pos;
         let mut items = Vec::with_capacity(atoms.len());
 
-        for &atom_id in atoms {
+        for (i, &atom_id) in atoms.iter().enumerate() {
+            if i > 0 {
+                current_pos = self.skip(current_pos)?;
+            }
             let result = self.try_atom(atom_id, current_pos)?;
             items.push(result.value);
             current_pos = result.end_pos;
@@ -570,6 +2031,36 @@ impl<'a> PortableParser<'a> {
         })
     }
 
+    /// Position after the grammar's [`skip`](Grammar::skip) atom at `pos`
+    ///
+    /// Returns `pos` unchanged if the grammar has no skip atom, skipping is
+    /// disabled here, or the skip atom doesn't match.
+    #[inline]
+    fn skip(&mut self, pos: usize) -> Result<usize, ParseError> {
+        let Some(skip) = self.grammar.skip else {
+            return Ok(pos);
+        };
+        if self.skip_disabled > 0 {
+            return Ok(pos);
+        }
+        self.skipping = true;
+        let result = self.try_atom(skip, pos);
+        self.skipping = false;
+        match result {
+            Ok(result) => Ok(result.end_pos),
+            Err(ParseError::Failed { .. }) => Ok(pos),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Whether parsing `atom_id` turns skipping off for everything inside it
+    #[inline]
+    fn disables_skip(&self, atom_id: usize) -> bool {
+        self.grammar
+            .skip
+            .is_some_and(|skip| atom_id == skip || self.grammar.no_skip.contains(&atom_id))
+    }
+
     #[inline]
     fn parse_alternative(
         &mut self,
@@ -577,13 +2068,55 @@ impl<'a> PortableParser<'a> {
         pos: usize,
     ) -> Result<ParseResult, ParseError> {
         for &atom_id in atoms {
-            if let Ok(result) = self.try_atom(atom_id, pos) {
-                return Ok(result);
+            match self.try_atom(atom_id, pos) {
+                Err(ParseError::Failed { .. }) => continue,
+                result => return result,
             }
         }
         Err(ParseError::Failed { position: pos })
     }
 
+    /// Try every alternative and keep the longest match (first on ties)
+    ///
+    /// Each alternative starts from the captures and indentation in effect
+    /// before the choice, and only the winner's changes to them are kept.
+    fn parse_longest_alternative(
+        &mut self,
+        atoms: &[usize],
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        let start_captures = self.capture_state.clone();
+        let start_indents = self.indent_stack.clone();
+        let mut best: Option<(ParseResult, CaptureState, Vec<usize>)> = None;
+
+        for &atom_id in atoms {
+            let result = self.try_atom(atom_id, pos);
+            let captures = std::mem::replace(&mut self.capture_state, start_captures.clone());
+            let indents = std::mem::replace(&mut self.indent_stack, start_indents.clone());
+            let result = match result {
+                Ok(result) => result,
+                Err(ParseError::Failed { .. }) => continue,
+                Err(e) => return Err(e),
+            };
+            let longer = match &best {
+                Some((best, _, _)) => result.end_pos > best.end_pos,
+                None => true,
+            };
+            if longer {
+                best = Some((result, captures, indents));
+            }
+        }
+
+        match best {
+            Some((result, captures, indents)) => {
+                self.capture_state = captures;
+                self.indent_stack = indents;
+                Ok(result)
+            }
+            None => Err(ParseError::Failed { position: pos }),
+        }
+    }
+
     #[inline]
     fn parse_repetition(
         &mut self,
@@ -595,7 +2128,15 @@ impl<'a> PortableParser<'a> {
         // Check for SIMD optimization
         if let Some(Atom::Re { pattern }) = self.grammar.get_atom(atom_id) {
             if let Some(char_pattern) = CharacterPattern::from_pattern(pattern) {
-                return self.parse_repetition_bulk(char_pattern.predicate(), min, max, pos);
+                if char_pattern.is_unicode() {
+                    return self.parse_repetition_bulk_chars(
+                        char_pattern.char_predicate(),
+                        min,
+                        max,
+                        pos,
+                    );
+                }
+                return self.parse_repetition_bulk(char_pattern, min, max, pos);
             }
         }
 
@@ -603,22 +2144,27 @@ impl<'a> PortableParser<'a> {
         let mut count = 0;
         let mut items: Vec<AstNode> = Vec::with_capacity(min.clamp(8, 64));
 
-        if let Some(max_count) = max {
-            while count < max_count {
-                match self.try_atom(atom_id, current_pos) {
-                    Ok(result) => {
-                        items.push(result.value);
-                        current_pos = result.end_pos;
-                        count += 1;
-                    }
-                    Err(_) => break,
+        // Trivia skipped before an item that then fails isn't part of the
+        // match, so `current_pos` stays at the end of the last item
+        let skips = self.skips_between_items(atom_id);
+        while max.is_none_or(|max| count < max) {
+            let mark = self.ignored_mark();
+            let item_pos = if count > 0 && skips {
+                self.skip(current_pos)?
+            } else {
+                current_pos
+            };
+            match self.try_atom(atom_id, item_pos) {
+                Ok(result) => {
+                    items.push(result.value);
+                    current_pos = result.end_pos;
+                    count += 1;
                 }
-            }
-        } else {
-            while let Ok(result) = self.try_atom(atom_id, current_pos) {
-                items.push(result.value);
-                current_pos = result.end_pos;
-                count += 1;
+                Err(ParseError::Failed { .. }) => {
+                    self.discard_ignored(mark);
+                    break;
+                }
+                Err(e) => return Err(e),
             }
         }
 
@@ -638,17 +2184,81 @@ impl<'a> PortableParser<'a> {
         })
     }
 
+    /// Repeat `atom` until `follow` matches, see [`Atom::RepetitionLazy`]
+    fn parse_repetition_lazy(
+        &mut self,
+        atom_id: usize,
+        min: usize,
+        max: Option<usize>,
+        follow: usize,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        let mut current_pos = pos;
+        let mut items: Vec<AstNode> = Vec::with_capacity(min.clamp(8, 64));
+
+        // As in `parse_repetition`, trivia before `follow` or the next item
+        // is skipped, but the match ends before the trivia after its last
+        // item
+        let skips = self.skips_between_items(atom_id);
+        loop {
+            let mark = self.ignored_mark();
+            let next_pos = if !items.is_empty() && skips {
+                self.skip(current_pos)?
+            } else {
+                current_pos
+            };
+            if items.len() >= min {
+                // `follow` is only looked at, like a lookahead
+                let found = self.try_atom(follow, next_pos);
+                let found = match found {
+                    Ok(_) => true,
+                    Err(ParseError::Failed { .. }) => false,
+                    Err(e) => return Err(e),
+                };
+                if found {
+                    self.discard_ignored(mark);
+                    break;
+                }
+                if max.is_some_and(|max| items.len() >= max) {
+                    return Err(ParseError::Failed { position: pos });
+                }
+            }
+            match self.try_atom(atom_id, next_pos) {
+                // An empty match would never reach `follow`
+                Ok(result) if result.end_pos > next_pos || items.len() < min => {
+                    items.push(result.value);
+                    current_pos = result.end_pos;
+                }
+                Ok(_) | Err(ParseError::Failed { .. }) => {
+                    return Err(ParseError::Failed { position: pos })
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let (pool_idx, len) = self.arena.store_tagged_array(":repetition", &items);
+        Ok(ParseResult {
+            value: AstNode::Array {
+                pool_index: pool_idx,
+                length: len,
+            },
+            end_pos: current_pos,
+            capture_state: None,
+        })
+    }
+
     #[inline]
     fn parse_repetition_bulk(
         &mut self,
-        predicate: fn(u8) -> bool,
+        char_pattern: CharacterPattern,
         min: usize,
         max: Option<usize>,
         pos: usize,
     ) -> Result<ParseResult, ParseError> {
-        use simd::skip_while;
-
-        let end_pos = skip_while(self.input_bytes, pos, predicate);
+        let end_pos = match char_pattern.byte_ranges() {
+            Some(ranges) => simd::skip_ranges(self.input_bytes, pos, ranges),
+            None => simd::skip_while(self.input_bytes, pos, char_pattern.predicate()),
+        };
         let count = end_pos - pos;
 
         if count < min {
@@ -667,12 +2277,45 @@ impl<'a> PortableParser<'a> {
 
         let actual_count = actual_end - pos;
         Ok(ParseResult {
-            value: self.arena.input_ref(pos, actual_count),
+            value: self.matched_text(pos, actual_count),
             end_pos: actual_end,
             capture_state: None,
         })
     }
 
+    /// Bulk repetition over decoded characters, for Unicode property classes
+    ///
+    /// `min` and `max` count characters rather than bytes.
+    #[inline]
+    fn parse_repetition_bulk_chars(
+        &mut self,
+        predicate: fn(char) -> bool,
+        min: usize,
+        max: Option<usize>,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        let limit = max.unwrap_or(usize::MAX);
+        let mut count = 0;
+        let mut end_pos = pos;
+        for c in self.input[pos..].chars() {
+            if count == limit || !predicate(c) {
+                break;
+            }
+            end_pos += c.len_utf8();
+            count += 1;
+        }
+
+        if count < min {
+            return Err(ParseError::Failed { position: pos });
+        }
+
+        Ok(ParseResult {
+            value: self.matched_text(pos, end_pos - pos),
+            end_pos,
+            capture_state: None,
+        })
+    }
+
     #[inline]
     fn parse_named(
         &mut self,
@@ -692,6 +2335,196 @@ impl<'a> PortableParser<'a> {
         })
     }
 
+    /// Precedence climbing over `prefix* operand postfix* (operator ...)*`
+    ///
+    /// Only operators with a precedence of at least `min_precedence` are
+    /// consumed at this level; tighter-binding operations are parsed by the
+    /// recursive call for the right operand. An operator whose right
+    /// operand fails to parse is left unconsumed.
+    ///
+    /// Unary operators follow Pratt parsing: a prefix operator's operand
+    /// binds every operator of at least its own precedence, and a postfix
+    /// operator applies to everything parsed so far at this level. Binary
+    /// operations become `{left, op, right}` hashes, prefix operations
+    /// `{prefix, operand}` and postfix operations `{operand, postfix}`.
+    fn parse_infix(
+        &mut self,
+        operand: usize,
+        operators: &[InfixOperator],
+        min_precedence: u16,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        let first = self.parse_infix_prefix(operand, operators, pos)?;
+        let mut left = first.value;
+        let mut end = first.end_pos;
+        // Precedence of a non-associative operation just built, which
+        // can't be chained with another of the same level
+        let mut closed = None;
+
+        // Trivia is skipped around operators, but an operator left
+        // unconsumed leaves the trivia before it unconsumed too
+        loop {
+            let mark = self.ignored_mark();
+            let op_pos = self.skip(end)?;
+            let op = match Self::longest_operator(operators, &self.input_bytes[op_pos..], |f| {
+                f != Fixity::Prefix
+            }) {
+                Some(op)
+                    if u16::from(op.precedence) >= min_precedence
+                        && closed != Some(op.precedence) =>
+                {
+                    op
+                }
+                _ => {
+                    self.discard_ignored(mark);
+                    break;
+                }
+            };
+
+            if op.fixity == Fixity::Postfix {
+                let op_node = self.matched_text(op_pos, op.pattern.len());
+                let (pool_index, length) = self
+                    .arena
+                    .store_hash(&[("operand", left), ("postfix", op_node)]);
+                left = AstNode::Hash { pool_index, length };
+                end = op_pos + op.pattern.len();
+                continue;
+            }
+
+            let right_min = match op.associativity {
+                Assoc::Right => u16::from(op.precedence),
+                Assoc::Left | Assoc::NonAssoc => u16::from(op.precedence) + 1,
+            };
+            let right_pos = self.skip(op_pos + op.pattern.len())?;
+            self.enter_recursive()?;
+            let right = self.parse_infix(operand, operators, right_min, right_pos);
+            self.exit_recursive();
+            let right = match right {
+                Ok(right) => right,
+                Err(ParseError::Failed { .. }) => {
+                    self.discard_ignored(mark);
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let op_node = self.matched_text(op_pos, op.pattern.len());
+            let (pool_index, length) =
+                self.arena
+                    .store_hash(&[("left", left), ("op", op_node), ("right", right.value)]);
+            left = AstNode::Hash { pool_index, length };
+            end = right.end_pos;
+            closed = (op.associativity == Assoc::NonAssoc).then_some(op.precedence);
+        }
+
+        Ok(ParseResult {
+            value: left,
+            end_pos: end,
+            capture_state: None,
+        })
+    }
+
+    /// Parse an operand, applying any prefix operators in front of it
+    ///
+    /// If a prefix operator's operand fails to parse, the operator is left
+    /// unconsumed and the operand is tried at `pos` itself, so text such as
+    /// a leading `-` can still belong to the operand.
+    fn parse_infix_prefix(
+        &mut self,
+        operand: usize,
+        operators: &[InfixOperator],
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        let prefix =
+            Self::longest_operator(operators, &self.input_bytes[pos..], |f| f == Fixity::Prefix);
+        if let Some(op) = prefix {
+            let mark = self.ignored_mark();
+            let inner_pos = self.skip(pos + op.pattern.len())?;
+            self.enter_recursive()?;
+            let inner = self.parse_infix(operand, operators, u16::from(op.precedence), inner_pos);
+            self.exit_recursive();
+            let inner = match inner {
+                Ok(inner) => Some(inner),
+                Err(ParseError::Failed { .. }) => {
+                    self.discard_ignored(mark);
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(inner) = inner {
+                let op_node = self.matched_text(pos, op.pattern.len());
+                let (pool_index, length) = self
+                    .arena
+                    .store_hash(&[("prefix", op_node), ("operand", inner.value)]);
+                return Ok(ParseResult {
+                    value: AstNode::Hash { pool_index, length },
+                    end_pos: inner.end_pos,
+                    capture_state: None,
+                });
+            }
+        }
+        self.try_atom(operand, pos)
+    }
+
+    /// Longest operator of an accepted fixity that `rest` starts with, so
+    /// `**` isn't read as `*`
+    fn longest_operator<'o>(
+        operators: &'o [InfixOperator],
+        rest: &[u8],
+        accept: impl Fn(Fixity) -> bool,
+    ) -> Option<&'o InfixOperator> {
+        operators
+            .iter()
+            .filter(|op| accept(op.fixity))
+            .filter(|op| !op.pattern.is_empty() && rest.starts_with(op.pattern.as_bytes()))
+            .max_by_key(|op| op.pattern.len())
+    }
+
+    #[inline]
+    fn parse_optional_default(
+        &mut self,
+        name: &str,
+        atom_id: usize,
+        default: DefaultValue,
+        pos: usize,
+    ) -> Result<ParseResult, ParseError> {
+        let (value, end_pos) = match self.try_atom(atom_id, pos) {
+            Ok(result) => (result.value, result.end_pos),
+            Err(ParseError::Failed { .. }) => (default.to_ast(), pos),
+            Err(e) => return Err(e),
+        };
+        let (pool_idx, len) = self.arena.store_hash(&[(name, value)]);
+        Ok(ParseResult {
+            value: AstNode::Hash {
+                pool_index: pool_idx,
+                length: len,
+            },
+            end_pos,
+            capture_state: None,
+        })
+    }
+
+    /// Match `atom_id` and convert the text it consumed with `convert`
+    fn parse_number(
+        &mut self,
+        atom_id: usize,
+        pos: usize,
+        convert: impl FnOnce(&str) -> Option<AstNode>,
+    ) -> Result<ParseResult, ParseError> {
+        let end_pos = self.try_atom(atom_id, pos)?.end_pos;
+        match convert(&self.input[pos..end_pos]) {
+            Some(value) => Ok(ParseResult {
+                value,
+                end_pos,
+                capture_state: None,
+            }),
+            None => Err(ParseError::InvalidNumber {
+                start: pos,
+                end: end_pos,
+            }),
+        }
+    }
+
     #[inline]
     fn parse_lookahead(
         &mut self,
@@ -699,7 +2532,14 @@ impl<'a> PortableParser<'a> {
         positive: bool,
         pos: usize,
     ) -> Result<ParseResult, ParseError> {
-        let matches = self.try_atom(atom_id, pos).is_ok();
+        let mark = self.ignored_mark();
+        let result = self.try_atom(atom_id, pos);
+        self.discard_ignored(mark);
+        let matches = match result {
+            Ok(_) => true,
+            Err(ParseError::Failed { .. }) => false,
+            Err(e) => return Err(e),
+        };
         if matches == positive {
             Ok(ParseResult {
                 value: AstNode::Nil,
@@ -718,7 +2558,7 @@ impl<'a> PortableParser<'a> {
             Some(result) => {
                 let value = match result.value {
                     Some(node) => node,
-                    None => self.arena.input_ref(pos, result.end_pos - pos),
+                    None => self.matched_text(pos, result.end_pos - pos),
                 };
                 Ok(ParseResult {
                     value,
@@ -754,6 +2594,19 @@ impl<'a> PortableParser<'a> {
         })
     }
 
+    /// Parse a back-reference atom
+    ///
+    /// Matches the input literally against the text previously captured
+    /// under `name`. Fails if no such capture exists.
+    #[inline]
+    fn parse_backref(&mut self, name: &str, pos: usize) -> Result<ParseResult, ParseError> {
+        let captured = match self.capture_state.get(name) {
+            Some(value) => value.get_text(self.input),
+            None => return Err(ParseError::Failed { position: pos }),
+        };
+        self.parse_str(captured, pos)
+    }
+
     /// Parse a scope atom
     ///
     /// Creates an isolated scope for captures. Any captures made inside
@@ -821,11 +2674,28 @@ impl<'a> PortableParser<'a> {
     // ========================================================================
 
     /// Parse with rich error reporting
+    ///
+    /// A failed match carries the chain of named rules that enclosed the
+    /// furthest failure in [`RichError::context_chain`], outermost first,
+    /// so a nested failure reads "while parsing 'function_body' →
+    /// 'statement' → 'expression'". Only [`Atom::Named`] atoms have names;
+    /// rule references ([`Atom::Entity`]) are not part of the chain.
     #[allow(clippy::result_large_err)]
     pub fn parse_with_rich_error(&mut self) -> Result<AstNode, super::error::RichError> {
         use super::error::{offset_to_line_col, RichError};
 
-        match self.try_atom_with_error(self.grammar.root, 0, None) {
+        self.breadcrumbs = Some(Vec::new());
+        self.failure_breadcrumbs.clear();
+        let result = match self.skip(0) {
+            Ok(start) => self.try_atom_with_error(self.grammar.root, start, None),
+            Err(e) => Err(e.into_rich(self.input)),
+        };
+        self.breadcrumbs = None;
+
+        match result.and_then(|result| {
+            self.skip_trailing(result)
+                .map_err(|e| e.into_rich(self.input))
+        }) {
             Ok(result) => {
                 if result.end_pos == self.input.len() {
                     Ok(result.value)
@@ -854,17 +2724,14 @@ impl<'a> PortableParser<'a> {
         pos: usize,
         context: Option<&str>,
     ) -> Result<ParseResult, super::error::RichError> {
-        use super::error::{offset_to_line_col, ErrorBuilder, RichError, Span};
+        use super::error::{offset_to_line_col, RichError};
 
         match self.try_atom(atom_id, pos) {
             Ok(result) => Ok(result),
             Err(ParseError::Failed { position }) => {
-                let (line, col) = offset_to_line_col(self.input, position);
-                let span = Span::at(position, line, col);
-                let atom = self.grammar.get_atom(atom_id);
-                let message = self.describe_atom_failure(atom, position);
-
-                let mut error = ErrorBuilder::new(message).span(span).build();
+                let mut error = self
+                    .failure_error(atom_id, position)
+                    .with_context_chain(self.failure_context_chain());
                 if let Some(ctx) = context {
                     error = error.with_context(ctx);
                 }
@@ -890,7 +2757,76 @@ impl<'a> PortableParser<'a> {
         }
     }
 
-    fn describe_atom_failure(&self, atom: Option<&Atom>, pos: usize) -> String {
+    /// Names of the named atoms enclosing the furthest failure
+    fn failure_context_chain(&self) -> Vec<String> {
+        self.failure_breadcrumbs
+            .iter()
+            .filter_map(|&atom_id| match self.grammar.get_atom(atom_id) {
+                Some(Atom::Named { name, .. }) => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Rich error for `atom_id` failing to match at `position`
+    fn failure_error(&self, atom_id: usize, position: usize) -> super::error::RichError {
+        use super::error::{offset_to_line_col, ErrorBuilder, Span};
+
+        let (line, col) = offset_to_line_col(self.input, position);
+        let span = Span::at(position, line, col);
+        let message = self.describe_atom_failure(atom_id, position);
+        ErrorBuilder::new(message)
+            .span(span)
+            .build()
+            .with_suggestions(self.suggestions_at(position))
+    }
+
+    /// Grammar literals the word at `position` is probably a misspelling of
+    ///
+    /// The word is the run of alphanumeric and `_` characters around
+    /// `position`, so a failure just past a matched prefix (`let` in
+    /// `lett`) still compares the whole word.
+    fn suggestions_at(&self, position: usize) -> Vec<String> {
+        use super::error::suggest_literals;
+        use super::grammar_analysis::GrammarAnalyzer;
+
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let Some(prefix) = self.input.get(..position) else {
+            return Vec::new();
+        };
+        let start = prefix
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_word(c))
+            .last()
+            .map_or(position, |(i, _)| i);
+        let end = self.input[position..]
+            .find(|c: char| !is_word(c))
+            .map_or(self.input.len(), |i| position + i);
+        if start == end {
+            return Vec::new();
+        }
+
+        let literals = GrammarAnalyzer::new(self.grammar).reachable_literals();
+        suggest_literals(&self.input[start..end], literals)
+    }
+
+    /// Description of `atom_id` from [`Grammar::rule_descriptions`], or
+    /// else of a described atom that failed at `pos`, the furthest failure
+    fn failure_description(&self, atom_id: usize, pos: usize) -> Option<&str> {
+        if let Some(description) = self.grammar.rule_description(atom_id) {
+            return Some(description);
+        }
+        if self.failures.position() != Some(pos) {
+            return None;
+        }
+        self.failures
+            .expected()
+            .into_iter()
+            .find_map(|id| self.grammar.rule_description(id))
+    }
+
+    fn describe_atom_failure(&self, atom_id: usize, pos: usize) -> String {
         let char_at = if pos < self.input.len() {
             match self.input[pos..].chars().next() {
                 Some(c) => format!("{:?}", c),
@@ -900,11 +2836,54 @@ impl<'a> PortableParser<'a> {
             "end of input".to_string()
         };
 
-        match atom {
+        if let Some(description) = self.failure_description(atom_id, pos) {
+            return format!("Expected {}, found {}", description, char_at);
+        }
+
+        match self.grammar.get_atom(atom_id) {
             Some(Atom::Str { pattern }) => format!("Expected {:?}, found {}", pattern, char_at),
+            Some(Atom::StrCi { pattern }) => {
+                format!(
+                    "Expected {:?} (case-insensitive), found {}",
+                    pattern, char_at
+                )
+            }
             Some(Atom::Re { pattern }) => {
                 format!("Expected pattern {:?}, found {}", pattern, char_at)
             }
+            Some(Atom::CharRange { lo, hi }) => {
+                format!("Expected {:?}..={:?}, found {}", lo, hi, char_at)
+            }
+            Some(Atom::OneOf { chars }) => {
+                format!("Expected one of {:?}, found {}", chars, char_at)
+            }
+            Some(Atom::NoneOf { chars }) => {
+                format!(
+                    "Expected any character except {:?}, found {}",
+                    chars, char_at
+                )
+            }
+            Some(Atom::Until { terminator, .. }) => {
+                format!("Expected {:?} before end of input", terminator)
+            }
+            Some(Atom::ByteRange { lo, hi }) => {
+                format!(
+                    "Expected a byte in 0x{:02x}..=0x{:02x}, found {}",
+                    lo, hi, char_at
+                )
+            }
+            Some(Atom::Eof) => format!("Expected end of input, found {}", char_at),
+            Some(Atom::Indent) => format!(
+                "Expected an indented block (deeper than column {}) at {}",
+                self.indent_level(),
+                char_at
+            ),
+            Some(Atom::Samedent) => format!(
+                "Expected a line indented to column {}, found {}",
+                self.indent_level(),
+                char_at
+            ),
+            Some(Atom::Dedent) => "No indented block to close".to_string(),
             Some(Atom::Sequence { atoms }) => {
                 format!(
                     "Failed to match sequence of {} items at {}",
@@ -912,20 +2891,26 @@ impl<'a> PortableParser<'a> {
                     char_at
                 )
             }
-            Some(Atom::Alternative { atoms }) => {
+            Some(Atom::Alternative { atoms }) | Some(Atom::LongestAlternative { atoms }) => {
                 format!(
                     "Expected one of {} alternatives, found {}",
                     atoms.len(),
                     char_at
                 )
             }
-            Some(Atom::Repetition { min, max, .. }) => {
+            Some(Atom::Repetition { min, max, .. })
+            | Some(Atom::RepetitionLazy { min, max, .. }) => {
                 let max_str = max
                     .map(|m| m.to_string())
                     .unwrap_or_else(|| "∞".to_string());
                 format!("Expected {}..{} repetitions at {}", min, max_str, char_at)
             }
             Some(Atom::Named { name, .. }) => format!("Failed to match {:?} at {}", name, char_at),
+            Some(Atom::BackRef { name }) => {
+                format!("Expected back-reference to {:?}, found {}", name, char_at)
+            }
+            Some(Atom::Peek { .. }) => format!("Peek failed at {}", char_at),
+            Some(Atom::Infix { .. }) => format!("Expected an operand at {}", char_at),
             Some(Atom::Lookahead { positive, .. }) => {
                 if *positive {
                     format!("Positive lookahead failed at {}", char_at)
@@ -944,7 +2929,10 @@ impl<'a> PortableParser<'a> {
     /// Parse with tracing
     pub fn parse_with_trace(&mut self) -> (Result<AstNode, ParseError>, super::debug::ParseTrace) {
         let mut trace = super::debug::ParseTrace::new();
-        let result = self.try_atom_traced(self.grammar.root, 0, 0, &mut trace);
+        let result = self.skip(0).and_then(|start| {
+            let result = self.try_atom_traced(self.grammar.root, start, 0, &mut trace)?;
+            self.skip_trailing(result)
+        });
 
         let final_result = match result {
             Ok(parse_result) => {
@@ -963,6 +2951,24 @@ impl<'a> PortableParser<'a> {
         (final_result, trace)
     }
 
+    /// Parse, recording call counts and time per atom
+    ///
+    /// Timing every atom call has a cost, so [`parse`](Self::parse) leaves
+    /// it off; the profile's times include that overhead. See
+    /// [`Profile`](super::debug::Profile) for how time is attributed.
+    pub fn parse_with_profile(&mut self) -> (Result<AstNode, ParseError>, super::debug::Profile) {
+        self.profiler = Some(Box::new(super::debug::ParseProfiler::new(
+            self.grammar.atom_count(),
+        )));
+        let result = self.parse();
+        let profile = self
+            .profiler
+            .take()
+            .map(|profiler| profiler.finish(self.grammar))
+            .unwrap_or_default();
+        (result, profile)
+    }
+
     fn try_atom_traced(
         &mut self,
         atom_id: usize,
@@ -1030,3 +3036,31 @@ impl<'a> PortableParser<'a> {
         result
     }
 }
+
+/// Parse an optionally signed integer, with `0x`/`0o`/`0b` if `radix_prefixes`
+///
+/// Returns `None` for malformed text and for values outside `i64`.
+fn parse_int(text: &str, radix_prefixes: bool) -> Option<i64> {
+    let (negative, unsigned) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let (radix, digits) = match unsigned.get(..2) {
+        Some("0x" | "0X") if radix_prefixes => (16, &unsigned[2..]),
+        Some("0o" | "0O") if radix_prefixes => (8, &unsigned[2..]),
+        Some("0b" | "0B") if radix_prefixes => (2, &unsigned[2..]),
+        _ => (10, unsigned),
+    };
+    // A second sign would otherwise be accepted by `from_str_radix`
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    // Parse as u64 magnitude so that `i64::MIN` is representable
+    let magnitude = u64::from_str_radix(digits, radix).ok()?;
+    if negative {
+        0i64.checked_sub_unsigned(magnitude)
+    } else {
+        i64::try_from(magnitude).ok()
+    }
+}
@@ -1,6 +1,9 @@
 //! SIMD-optimized functions for bulk character operations using memchr
 //!
-//! These functions are kept for future optimization use.
+//! Bulk repetitions of ASCII character classes use [`skip_ranges`], which
+//! compares 16 bytes at a time with SSE2 on x86_64, and scans for literals
+//! use the `memchr` searches. The other functions are kept for future
+//! optimization use.
 
 #![allow(dead_code)]
 
@@ -104,6 +107,58 @@ pub fn skip_while<F: Fn(u8) -> bool>(input: &[u8], pos: usize, predicate: F) ->
     current
 }
 
+/// Skip all bytes in one of the inclusive `ranges`
+///
+/// Returns the same position as [`skip_while`] with the equivalent
+/// predicate. Every range must lie below `0x7f`. On x86_64 whole 16-byte
+/// blocks are compared with SSE2 (part of the baseline, so there's no
+/// runtime detection); the tail, and every byte on other targets, goes
+/// through the scalar loop.
+#[inline]
+pub fn skip_ranges(input: &[u8], pos: usize, ranges: &[(u8, u8)]) -> usize {
+    debug_assert!(ranges.iter().all(|&(lo, hi)| lo <= hi && hi < 0x7f));
+    #[cfg(target_arch = "x86_64")]
+    let pos = sse2::skip_ranges(input, pos, ranges);
+    skip_while(input, pos, |b| {
+        ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(&b))
+    })
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+    use std::arch::x86_64::{
+        __m128i, _mm_and_si128, _mm_cmpgt_epi8, _mm_cmplt_epi8, _mm_loadu_si128, _mm_movemask_epi8,
+        _mm_or_si128, _mm_set1_epi8, _mm_setzero_si128,
+    };
+
+    /// Advance over 16-byte blocks while every byte is in `ranges`,
+    /// stopping at the first byte that isn't or before a partial block
+    #[inline]
+    pub(super) fn skip_ranges(input: &[u8], mut pos: usize, ranges: &[(u8, u8)]) -> usize {
+        while pos + 16 <= input.len() {
+            // SAFETY: SSE2 is always available on x86_64, and the unaligned
+            // load reads `input[pos..pos + 16]`, which is in bounds
+            let matched = unsafe {
+                let block = _mm_loadu_si128(input.as_ptr().add(pos) as *const __m128i);
+                let mut hits = _mm_setzero_si128();
+                for &(lo, hi) in ranges {
+                    // Signed compares: bytes from 0x80 up are negative, so
+                    // they fall outside every range below 0x7f
+                    let above = _mm_cmpgt_epi8(block, _mm_set1_epi8(lo as i8 - 1));
+                    let below = _mm_cmplt_epi8(block, _mm_set1_epi8(hi as i8 + 1));
+                    hits = _mm_or_si128(hits, _mm_and_si128(above, below));
+                }
+                _mm_movemask_epi8(hits) as u32
+            };
+            if matched != 0xFFFF {
+                return pos + matched.trailing_ones() as usize;
+            }
+            pos += 16;
+        }
+        pos
+    }
+}
+
 /// Skip digits (0-9)
 #[inline]
 pub fn skip_digits(input: &[u8], pos: usize) -> usize {
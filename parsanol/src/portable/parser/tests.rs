@@ -2,7 +2,12 @@
 
 use super::*;
 use crate::portable::arena::AstArena;
-use crate::portable::parser_dsl::{str, GrammarBuilder};
+use crate::portable::parser_dsl::{
+    any, backref, capture, char_range, choice, const_int, dedent, dynamic, eof, indent,
+    longest_choice, none_of, one_of, optional_with_default, re, ref_, samedent, scope, str, stri,
+    take_until, take_until_inclusive, GrammarBuilder, ParsletExt,
+};
+use crate::portable::transform::{ast_to_value, Value};
 
 #[test]
 fn test_parse_with_rich_error_success() {
@@ -31,6 +36,33 @@ fn test_parse_with_rich_error_failure() {
     assert!(error.message.contains("Expected"));
 }
 
+#[test]
+fn test_rich_error_context_chain_names_enclosing_rules() {
+    let expression = re("[0-9]").many1().label("expression");
+    let statement = expression.then(str(";")).label("statement");
+    let body = str("{")
+        .then(statement.many1())
+        .then(str("}"))
+        .label("function_body");
+    let grammar = GrammarBuilder::new().rule("function", body).build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "{1;x;}", &mut arena);
+    let error = parser.parse_with_rich_error().unwrap_err();
+    assert_eq!(
+        error.context_chain,
+        ["function_body", "statement", "expression"]
+    );
+    assert!(error
+        .ascii_tree()
+        .contains("while parsing 'function_body' → 'statement' → 'expression'"));
+
+    // A successful parse afterwards leaves tracking off
+    let mut parser = PortableParser::new(&grammar, "{1;}", &mut arena);
+    assert!(parser.parse_with_rich_error().is_ok());
+    assert!(parser.breadcrumbs.is_none());
+}
+
 #[test]
 fn test_parse_with_trace_success() {
     let grammar = GrammarBuilder::new().rule("test", str("hello")).build();
@@ -57,6 +89,39 @@ fn test_parse_with_trace_failure() {
     assert!(!trace.entries.is_empty());
 }
 
+#[test]
+fn test_parse_with_profile_attributes_time_to_recursive_rule() {
+    let nested = str("(")
+        .then(ref_("nested"))
+        .then(str(")"))
+        .or(str("0"))
+        .label("nested");
+    let doc = str("x").then(ref_("nested")).label("doc");
+    let grammar = GrammarBuilder::new()
+        .rule("doc", doc)
+        .rule("nested", nested)
+        .build();
+    let input = format!("x{}0{}", "(".repeat(30), ")".repeat(30));
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, &input, &mut arena);
+    let (result, profile) = parser.parse_with_profile();
+    assert!(result.is_ok());
+    assert!(parser.profiler.is_none());
+
+    let rules = profile.rule_self_nanos();
+    assert_eq!(rules[0].0.as_deref(), Some("nested"));
+    assert!(rules[0].1 * 2 > profile.total_nanos());
+    assert!(profile
+        .atoms
+        .iter()
+        .any(|atom| atom.rule.as_deref() == Some("nested") && atom.calls == 31));
+
+    let table = profile.format();
+    assert!(table.lines().next().unwrap().contains("self (us)"));
+    assert_eq!(table.lines().count(), profile.atoms.len() + 1);
+}
+
 #[test]
 fn test_trace_format() {
     let grammar = GrammarBuilder::new().rule("test", str("hello")).build();
@@ -86,6 +151,33 @@ fn test_rich_error_format_with_source() {
     }
 }
 
+#[test]
+fn test_rich_error_suggests_misspelled_keyword() {
+    let keyword = str("let").or(str("const"));
+    let stmt = keyword.then(str(" ")).then(re("[a-z]").many1());
+    let grammar = GrammarBuilder::new().rule("stmt", stmt).build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "lett x", &mut arena);
+    let error = parser.parse_with_rich_error().unwrap_err();
+    assert_eq!(error.suggestions, ["let"]);
+    assert!(error
+        .format_with_source("lett x")
+        .contains("help: did you mean `let`?"));
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "cnst x", &mut arena);
+    let error = parser.parse_with_rich_error().unwrap_err();
+    assert_eq!(error.suggestions, ["const"]);
+
+    // Nothing close enough
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "print x", &mut arena);
+    let error = parser.parse_with_rich_error().unwrap_err();
+    assert!(error.suggestions.is_empty());
+    assert_eq!(error.suggestion_hint(), None);
+}
+
 #[test]
 fn test_set_timeout() {
     let grammar = GrammarBuilder::new().rule("test", str("hello")).build();
@@ -112,6 +204,31 @@ fn test_set_max_memory() {
     assert!(result.is_ok());
 }
 
+#[test]
+fn test_max_nodes_limits_ast_size() {
+    // One hash per letter
+    let grammar = GrammarBuilder::new()
+        .rule("letters", re("[a-z]").label("letter").many())
+        .build();
+    let input = "x".repeat(1_000);
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, &input, &mut arena);
+    let result = parser.parse_with_config(ParserConfig::new().with_max_nodes(100));
+    assert!(matches!(
+        result,
+        Err(ParseError::NodeLimitExceeded {
+            count: 101,
+            max: 100
+        })
+    ));
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, &input, &mut arena);
+    parser.set_max_nodes(3_000);
+    assert!(parser.parse().is_ok());
+}
+
 #[test]
 fn test_memory_usage() {
     let grammar = GrammarBuilder::new().rule("test", str("hello")).build();
@@ -124,6 +241,34 @@ fn test_memory_usage() {
     assert!(usage > 0);
 }
 
+#[test]
+fn test_memory_breakdown_sums_to_memory_usage() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "words",
+            re("[a-z]").many1().label("word").then(str(",")).many1(),
+        )
+        .build();
+    let input = "abc,".repeat(2_000);
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, &input, &mut arena);
+    parser.parse().unwrap();
+
+    let breakdown = parser.memory_breakdown();
+    assert!(breakdown.arena_bytes > 0);
+    assert!(breakdown.cache_bytes > 0);
+    assert!(breakdown.cached_nodes_bytes > 0);
+    assert_eq!(
+        breakdown.arena_bytes + breakdown.cache_bytes,
+        parser.memory_usage()
+    );
+    assert_eq!(
+        breakdown.total(),
+        parser.memory_usage() + breakdown.cached_nodes_bytes
+    );
+}
+
 #[test]
 fn test_resource_limits_combined() {
     let grammar = GrammarBuilder::new().rule("test", str("hello")).build();
@@ -174,3 +319,2080 @@ fn test_parse_with_builder_collects_strings() {
     let strings = result.unwrap();
     assert_eq!(strings, vec!["hello"]);
 }
+
+#[test]
+fn test_parse_with_builder_stops_after_first_string() {
+    use crate::portable::streaming_builder::{BuildControl, BuildResult, StreamingBuilder};
+
+    struct FirstString(Option<String>);
+
+    impl StreamingBuilder for FirstString {
+        type Output = Option<String>;
+
+        fn on_string(&mut self, value: &str, _offset: usize, _length: usize) -> BuildResult<()> {
+            assert!(self.0.is_none(), "event delivered after stop");
+            self.0 = Some(value.to_string());
+            Ok(())
+        }
+
+        fn control(&self) -> BuildControl {
+            if self.0.is_some() {
+                BuildControl::Stop
+            } else {
+                BuildControl::Continue
+            }
+        }
+
+        fn finish(&mut self) -> BuildResult<Option<String>> {
+            Ok(self.0.take())
+        }
+    }
+
+    let grammar = GrammarBuilder::new()
+        .rule("words", re("[a-z]").many1().then(str(" ")).many1())
+        .build();
+    let input = "ab cd ef ";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let (first, control) = parser
+        .parse_with_builder_control(&mut FirstString(None))
+        .unwrap();
+    assert_eq!(control, BuildControl::Stop);
+    assert!(first.is_some());
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let first = parser.parse_with_builder(&mut FirstString(None)).unwrap();
+    assert!(first.is_some());
+}
+
+#[test]
+fn test_parse_with_builder_reports_ignored_input() {
+    use crate::portable::streaming_builder::DebugBuilder;
+
+    // The first branch matches the spaces and then fails, so only the
+    // second branch's match may be reported
+    let word = || re("[a-z]").many1().label("word");
+    let spaces = || str(" ").many1().ignore();
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "words",
+            word()
+                .then(spaces())
+                .then(str(";"))
+                .or(word().then(spaces()))
+                .many1(),
+        )
+        .build();
+    let input = "ab  cd ";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let events = parser.parse_with_builder(&mut DebugBuilder::new()).unwrap();
+    let tokens: Vec<&str> = events
+        .iter()
+        .map(String::as_str)
+        .filter(|event| event.starts_with("string") || event.starts_with("ignored"))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            "string(\"ab\" @ 0 len=2)",
+            "ignored(\"  \" @ 2 len=2)",
+            "string(\"cd\" @ 4 len=2)",
+            "ignored(\" \" @ 6 len=1)",
+        ]
+    );
+}
+
+/// `(word ";")+` -- the root is a repetition, so it streams item by item
+fn streaming_words_grammar() -> Grammar {
+    GrammarBuilder::new()
+        .rule(
+            "words",
+            re("[a-z]").many1().label("word").then(str(";")).many1(),
+        )
+        .build()
+}
+
+#[test]
+fn test_parse_streaming_emits_each_item() {
+    use crate::portable::streaming_builder::BuilderStringCollector;
+
+    let grammar = streaming_words_grammar();
+    let input = "ab;cd;ef;";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let mut builder = BuilderStringCollector::new();
+    let strings = parser.parse_streaming(&mut builder).unwrap();
+    assert_eq!(strings, vec!["ab", "cd", "ef"]);
+
+    // Every item was dropped from the arena once delivered
+    assert!(arena.is_empty());
+
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let walked = parser
+        .parse_with_builder(&mut BuilderStringCollector::new())
+        .unwrap();
+    assert_eq!(walked, strings);
+}
+
+#[test]
+fn test_parse_streaming_errors_after_delivered_items() {
+    use crate::portable::streaming_builder::DebugBuilder;
+
+    let grammar = streaming_words_grammar();
+    let input = "ab;cd;12";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let mut builder = DebugBuilder::new();
+    let result = parser.parse_streaming(&mut builder);
+    assert!(matches!(
+        result,
+        Err(ParseError::Incomplete {
+            expected: 8,
+            actual: 6
+        })
+    ));
+    let delivered = builder
+        .events
+        .iter()
+        .filter(|e| e.starts_with("array_element"))
+        .count();
+    assert_eq!(delivered, 2);
+}
+
+#[test]
+fn test_parse_streaming_stops_when_builder_asks() {
+    use crate::portable::streaming_builder::{BuildControl, BuildResult, StreamingBuilder};
+
+    struct FirstWord(Option<String>);
+
+    impl StreamingBuilder for FirstWord {
+        type Output = Option<String>;
+
+        fn on_string(&mut self, value: &str, _offset: usize, _length: usize) -> BuildResult<()> {
+            self.0.get_or_insert_with(|| value.to_string());
+            Ok(())
+        }
+
+        fn control(&self) -> BuildControl {
+            if self.0.is_some() {
+                BuildControl::Stop
+            } else {
+                BuildControl::Continue
+            }
+        }
+
+        fn finish(&mut self) -> BuildResult<Option<String>> {
+            Ok(self.0.take())
+        }
+    }
+
+    // The input after the first item would not parse
+    let grammar = streaming_words_grammar();
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "ab;!!", &mut arena);
+    let first = parser.parse_streaming(&mut FirstWord(None)).unwrap();
+    assert_eq!(first.as_deref(), Some("ab"));
+}
+
+#[test]
+fn test_parse_streaming_falls_back_for_other_roots() {
+    use crate::portable::streaming_builder::BuilderStringCollector;
+
+    let grammar = GrammarBuilder::new().rule("test", str("hello")).build();
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "hello", &mut arena);
+    let strings = parser
+        .parse_streaming(&mut BuilderStringCollector::new())
+        .unwrap();
+    assert_eq!(strings, vec!["hello"]);
+}
+
+/// `(name "=" digits ";" "\n"?)*`
+fn recovering_statements_grammar() -> Grammar {
+    let stmt = re("[a-z]")
+        .many1()
+        .label("name")
+        .then(str("="))
+        .then(re("[0-9]").many1().label("value"))
+        .then(str(";"))
+        .then(str("\n").optional());
+    GrammarBuilder::new().rule("stmts", stmt.many()).build()
+}
+
+#[test]
+fn test_parse_recovering_collects_every_error() {
+    let grammar = recovering_statements_grammar();
+    let input = "a=1;\nb=;\nc=3;\n=4;\nd=5;\n";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let (ast, errors) = parser.parse_recovering();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].span.start.line, 2);
+    assert_eq!(errors[1].span.start.line, 4);
+
+    let tree = ParsedTree::new(ast.unwrap(), &arena, input);
+    assert_eq!(tree.tag(), Some(":repetition"));
+    let markers: Vec<_> = tree
+        .children()
+        .filter_map(|stmt| stmt.field("error"))
+        .filter_map(|skipped| skipped.as_str())
+        .collect();
+    assert_eq!(markers, ["b=;\n", "=4;\n"]);
+    assert_eq!(tree.children().count(), 5);
+}
+
+#[test]
+fn test_parse_recovering_sync_tokens() {
+    let grammar = recovering_statements_grammar();
+    let input = "a=1;b=;c=3;";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let config = RecoveryConfig::new().with_sync_token(";");
+    let (ast, errors) = parser.parse_recovering_with(&config);
+
+    assert_eq!(errors.len(), 1);
+    // Reported where the digits were expected, not where the statement began
+    assert_eq!(errors[0].span.start.offset, 6);
+    let tree = ParsedTree::new(ast.unwrap(), &arena, input);
+    let children: Vec<_> = tree.children().collect();
+    assert_eq!(children.len(), 3);
+    assert_eq!(children[1].field("error").unwrap().as_str(), Some("b=;"));
+}
+
+#[test]
+fn test_parse_recovering_without_errors_matches_parse() {
+    let grammar = recovering_statements_grammar();
+    let input = "a=1;\nb=2;\n";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let (ast, errors) = parser.parse_recovering();
+    assert!(errors.is_empty());
+    let recovered = ast_to_value(&ast.unwrap(), &arena, input);
+
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let parsed = parser.parse().unwrap();
+    assert_eq!(recovered, ast_to_value(&parsed, &arena, input));
+
+    // Non-repetition roots report a single error
+    let grammar = GrammarBuilder::new().rule("test", str("hello")).build();
+    let mut parser = PortableParser::new(&grammar, "help", &mut arena);
+    let (ast, errors) = parser.parse_recovering();
+    assert!(ast.is_none());
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn test_expected_at_failure_lists_alternatives() {
+    // digits ("+" | "-") digits
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "sum",
+            re("[0-9]")
+                .many1()
+                .then(str("+").or(str("-")))
+                .then(re("[0-9]").many1()),
+        )
+        .build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "12*3", &mut arena);
+    assert!(parser.parse().is_err());
+    assert_eq!(parser.failure_position(), Some(2));
+
+    let expected = parser.expected_at_failure();
+    let literals: Vec<_> = expected
+        .iter()
+        .filter_map(|&id| match grammar.get_atom(id) {
+            Some(Atom::Str { pattern }) => Some(pattern.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(literals, ["+", "-"]);
+    // The alternative itself was attempted there too
+    assert!(expected
+        .iter()
+        .any(|&id| matches!(grammar.get_atom(id), Some(Atom::Alternative { .. }))));
+}
+
+#[test]
+fn test_parse_at_matches_sliced_input() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "pair",
+            re("[a-z]")
+                .many1()
+                .label("key")
+                .then(str("="))
+                .then(re("[0-9]").many1().label("value")),
+        )
+        .build();
+    let input = "xx;; ab=12 rest";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let result = parser.parse_at(5).unwrap();
+    assert_eq!(result.end_pos, 10);
+    let at_offset = ast_to_value(&result.value, &arena, input);
+
+    let sliced = &input[5..10];
+    let mut parser = PortableParser::new(&grammar, sliced, &mut arena);
+    let node = parser.parse().unwrap();
+    assert_eq!(at_offset, ast_to_value(&node, &arena, sliced));
+}
+
+#[test]
+fn test_byte_regex_matches_like_unicode_on_ascii() {
+    let build = |ascii_only: bool| {
+        let word = re("[a-z]+[0-9]*").label("word");
+        let sep = re("\\s+|,").label("sep");
+        let builder = GrammarBuilder::new().rule("words", word.then(sep.optional()).many1());
+        if ascii_only {
+            builder.ascii_only().build()
+        } else {
+            builder.build()
+        }
+    };
+    let (unicode, bytes) = (build(false), build(true));
+    assert!(bytes.ascii_only && !unicode.ascii_only);
+
+    for input in ["abc1 de,fg23", "x", "ab,,cd", "9ab", ""] {
+        let mut arena = AstArena::new();
+        let expected = PortableParser::new(&unicode, input, &mut arena)
+            .parse_prefix()
+            .map(|(node, end)| (ast_to_value(&node, &arena, input), end));
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&bytes, input, &mut arena);
+        assert!(parser.byte_regex);
+        let actual = parser
+            .parse_prefix()
+            .map(|(node, end)| (ast_to_value(&node, &arena, input), end));
+        assert_eq!(actual.ok(), expected.ok(), "input {:?}", input);
+    }
+
+    // Non-ASCII input goes to the Unicode engine
+    let mut arena = AstArena::new();
+    let parser = PortableParser::new(&bytes, "héllo", &mut arena);
+    assert!(!parser.byte_regex);
+}
+
+#[test]
+fn test_parse_at_rejects_invalid_offsets() {
+    let grammar = GrammarBuilder::new().rule("any", re(".")).build();
+    let input = "aλb";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert_eq!(parser.parse_at(1).unwrap().end_pos, 3);
+    assert!(matches!(
+        parser.parse_at(2),
+        Err(ParseError::InvalidOffset { offset: 2 })
+    ));
+    assert!(matches!(
+        parser.parse_at(5),
+        Err(ParseError::InvalidOffset { offset: 5 })
+    ));
+    assert!(matches!(
+        parser.parse_at(4),
+        Err(ParseError::Failed { position: 4 })
+    ));
+}
+
+#[test]
+fn test_parse_all_finds_non_overlapping_matches() {
+    let grammar = GrammarBuilder::new()
+        .rule("digits", re("[0-9]").many1())
+        .build();
+    let input = "a12b3c456";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let matches = parser.parse_all();
+    let found: Vec<_> = matches
+        .iter()
+        .map(|(start, node)| (*start, ast_to_value(node, &arena, input)))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, Value::String("12".to_string())),
+            (4, Value::String("3".to_string())),
+            (6, Value::String("456".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_all_jumps_between_literal_first_bytes() {
+    let grammar = GrammarBuilder::new()
+        .rule("keyword", choice([str("let"), str("loop"), str("fn")]))
+        .build();
+    let input = "x lets fnord; loop ééé fn";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let starts: Vec<usize> = parser
+        .parse_all()
+        .into_iter()
+        .map(|(start, _)| start)
+        .collect();
+    assert_eq!(starts, [2, 7, 14, 26]);
+}
+
+#[test]
+fn test_skip_ranges_matches_scalar_skip() {
+    use super::simd::{skip_ranges, skip_while};
+    use crate::portable::char_class::CharacterPattern;
+
+    let ranges = CharacterPattern::Word.byte_ranges().unwrap();
+    let predicate = CharacterPattern::Word.predicate();
+    // A stop byte of every value at every offset across two SSE2 blocks
+    for len in 0..40 {
+        for stop in 0..=255u8 {
+            let mut input = vec![b'a'; len];
+            input.push(stop);
+            input.extend_from_slice(b"zz");
+            for pos in [0, 1, len] {
+                assert_eq!(
+                    skip_ranges(&input, pos, ranges),
+                    skip_while(&input, pos, predicate),
+                    "len {} stop {:#x} pos {}",
+                    len,
+                    stop,
+                    pos
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_peek_keeps_value_without_consuming() {
+    // peek(letters) letters: the peeked word is matched again for real
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "word",
+            re("[a-z]")
+                .many1()
+                .peek()
+                .label("peeked")
+                .then(re("[a-z]").many1().label("word")),
+        )
+        .build();
+    let input = "hello";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let result = parser.parse_at(0).unwrap();
+    assert_eq!(result.end_pos, input.len());
+
+    let tree = ParsedTree::new(result.value, &arena, input);
+    let items: Vec<_> = tree.children().collect();
+    assert_eq!(items[0].field("peeked").unwrap().as_str(), Some("hello"));
+    assert_eq!(items[1].field("word").unwrap().as_str(), Some("hello"));
+
+    // A lone peek matches without advancing
+    let grammar = GrammarBuilder::new()
+        .rule("peek", re("[a-z]").many1().peek())
+        .build();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert!(parser.parse_at(5).is_err());
+    let result = parser.parse_at(0).unwrap();
+    assert_eq!(result.end_pos, 0);
+    assert_eq!(
+        ast_to_value(&result.value, &arena, input),
+        Value::string("hello")
+    );
+}
+
+#[test]
+fn test_backref_matches_closing_tag() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "element",
+            scope(
+                str("<")
+                    .then(capture("tag", re("[a-z]").many1()))
+                    .then(str(">"))
+                    .then(re("[0-9]").many())
+                    .then(str("</"))
+                    >> backref("tag")
+                    >> str(">"),
+            ),
+        )
+        .build();
+
+    for input in ["<b>123</b>", "<div></div>"] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert!(parser.parse().is_ok(), "should match: {}", input);
+    }
+
+    for input in ["<b>123</i>", "<div></dv>", "<b></bb>"] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert!(parser.parse().is_err(), "should reject: {}", input);
+    }
+}
+
+#[test]
+fn test_backref_without_capture_fails() {
+    let grammar = GrammarBuilder::new()
+        .rule("test", backref("missing"))
+        .build();
+    let input = "anything";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+fn test_rule_containing_backref_is_not_replayed_from_cache() {
+    // `close` fails at 3 under t = "a" and must be retried under t = "ab"
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "doc",
+            scope(capture("t", str("a")).then(str("b:")).then(ref_("close"))).or(scope(
+                capture("t", str("ab")).then(str(":")).then(ref_("close")),
+            )),
+        )
+        .rule("close", backref("t").then(eof()))
+        .build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "ab:ab", &mut arena);
+    assert!(parser.parse().is_ok());
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "ab:ab", &mut arena);
+    assert!(parser.parse_iterative().is_ok());
+}
+
+#[test]
+fn test_captures_are_replayed_from_cache() {
+    // The second alternative reuses `open` from the cache, and must still
+    // capture t for the back-reference
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "doc",
+            scope(ref_("open").then(str("!"))).or(scope(
+                ref_("open").then(str(":")).then(backref("t")).then(eof()),
+            )),
+        )
+        .rule("open", capture("t", re("[a-z]").many1()))
+        .build();
+
+    for input in ["ab:ab", "x:x"] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert!(parser.parse().is_ok(), "should match: {}", input);
+
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert!(parser.parse_iterative().is_ok(), "should match: {}", input);
+    }
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "ab:a", &mut arena);
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+fn test_left_recursive_rule_keeps_captures_of_longest_match() {
+    let grammar = GrammarBuilder::new()
+        .rule("doc", ref_("expr").then(str("=")).then(backref("last")))
+        .rule(
+            "expr",
+            ref_("expr")
+                .then(str("-"))
+                .then(capture("last", re("[0-9]")))
+                .or(capture("last", re("[0-9]"))),
+        )
+        .build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "1-2-3=3", &mut arena);
+    assert!(parser.parse().is_ok());
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "1-2-3=1", &mut arena);
+    assert!(parser.parse().is_err());
+}
+
+/// Find the value stored under `key` in the first hash reachable from `node`
+fn find_hash_value(node: &AstNode, arena: &AstArena, key: &str) -> Option<AstNode> {
+    match node {
+        AstNode::Hash { pool_index, length } => arena
+            .get_hash_items(*pool_index as usize, *length as usize)
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v),
+        AstNode::Array { pool_index, length } => arena
+            .get_array(*pool_index as usize, *length as usize)
+            .iter()
+            .find_map(|item| find_hash_value(item, arena, key)),
+        AstNode::Tagged { value, .. } => find_hash_value(value, arena, key),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_optional_with_default_absent() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "number",
+            optional_with_default("sign", str("-"), AstNode::Int(1))
+                .then(re("[0-9]").many1().label("digits")),
+        )
+        .build();
+    let input = "42";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let ast = parser.parse().unwrap();
+
+    assert_eq!(find_hash_value(&ast, &arena, "sign"), Some(AstNode::Int(1)));
+    assert_eq!(
+        find_hash_value(&ast, &arena, "digits"),
+        Some(AstNode::InputRef {
+            offset: 0,
+            length: 2
+        })
+    );
+}
+
+#[test]
+fn test_optional_with_default_present() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "number",
+            optional_with_default("sign", str("-"), AstNode::Int(1))
+                .then(re("[0-9]").many1().label("digits")),
+        )
+        .build();
+    let input = "-42";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let ast = parser.parse().unwrap();
+
+    assert_eq!(
+        find_hash_value(&ast, &arena, "sign"),
+        Some(AstNode::InputRef {
+            offset: 0,
+            length: 1
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "default must be Nil, Bool, Int or Float")]
+fn test_optional_with_default_rejects_arena_backed_default() {
+    let default = AstNode::InputRef {
+        offset: 0,
+        length: 1,
+    };
+    let _ = optional_with_default("sign", str("-"), default);
+}
+
+#[test]
+fn test_default_value_from_ast() {
+    use crate::portable::grammar::DefaultValue;
+
+    assert_eq!(
+        DefaultValue::from_ast(&AstNode::Float(-0.0)),
+        Some(DefaultValue::Float(-0.0))
+    );
+    assert_ne!(DefaultValue::Float(0.0), DefaultValue::Float(-0.0));
+    assert_eq!(
+        DefaultValue::from_ast(&AstNode::StringRef { pool_index: 0 }),
+        None
+    );
+    assert_eq!(
+        DefaultValue::from_ast(&AstNode::Hash {
+            pool_index: 0,
+            length: 0
+        }),
+        None
+    );
+    // AST equality keeps IEEE float semantics
+    assert_eq!(AstNode::Float(0.0), AstNode::Float(-0.0));
+}
+
+/// expr = expr "-" digit | digit
+fn left_recursive_subtraction_grammar() -> Grammar {
+    GrammarBuilder::new()
+        .rule(
+            "expr",
+            ref_("expr")
+                .label("l")
+                .then(str("-"))
+                .then(re("[0-9]").label("r"))
+                .or(re("[0-9]").label("n")),
+        )
+        .build()
+}
+
+/// Evaluate a subtraction tree built by `left_recursive_subtraction_grammar`
+fn eval_subtraction(value: &Value) -> i64 {
+    fn find(value: &Value, key: &str) -> Option<Value> {
+        match value {
+            Value::Hash(h) => h.get(key).cloned(),
+            Value::Array(items) => items.iter().find_map(|item| find(item, key)),
+            _ => None,
+        }
+    }
+    let digit = |v: Value| v.as_str().unwrap().parse::<i64>().unwrap();
+
+    match find(value, "n") {
+        Some(n) => digit(n),
+        None => eval_subtraction(&find(value, "l").unwrap()) - digit(find(value, "r").unwrap()),
+    }
+}
+
+#[test]
+fn test_left_recursion_grows_seed() {
+    let grammar = left_recursive_subtraction_grammar();
+    let input = "9-3-2";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert!(parser.left_recursion.is_some());
+    let ast = parser.parse().unwrap();
+
+    // Left-associative: (9 - 3) - 2
+    let value = ast_to_value(&ast, &arena, input);
+    assert_eq!(eval_subtraction(&value), 4);
+}
+
+#[test]
+fn test_left_recursion_analysis_shared_across_parsers() {
+    use std::borrow::Cow;
+
+    let mut grammar = left_recursive_subtraction_grammar();
+
+    let mut arena = AstArena::new();
+    let first = PortableParser::new(&grammar, "1", &mut arena).left_recursion;
+    let mut arena = AstArena::new();
+    let second = PortableParser::new(&grammar, "2", &mut arena).left_recursion;
+    match (first, second) {
+        (Some(Cow::Borrowed(a)), Some(Cow::Borrowed(b))) => assert!(std::ptr::eq(a, b)),
+        other => panic!("expected a cached analysis, got {:?}", other),
+    }
+
+    // Editing the grammar drops the cached analysis
+    let root = grammar.root;
+    *grammar.get_atom_mut(root).unwrap() = Atom::Str {
+        pattern: "x".to_string(),
+    };
+    assert!(grammar.left_recursion().is_none());
+}
+
+#[test]
+fn test_atom_replaced_in_place_after_parse_is_reanalyzed() {
+    let mut grammar = left_recursive_subtraction_grammar();
+    let reference = grammar
+        .atoms
+        .iter()
+        .position(|atom| matches!(atom, Atom::Entity { .. }))
+        .unwrap();
+    let left_recursive = std::mem::replace(
+        &mut grammar.atoms[reference],
+        Atom::Str {
+            pattern: "0".to_string(),
+        },
+    );
+    assert!(grammar.parse("0-1").is_ok());
+
+    // The rule becomes left-recursive again without going through
+    // `get_atom_mut`, so the analysis cached by the first parse is stale
+    grammar.atoms[reference] = left_recursive;
+    let input = "9-3-2";
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let ast = parser.parse().unwrap();
+    assert_eq!(eval_subtraction(&ast_to_value(&ast, &arena, input)), 4);
+}
+
+#[test]
+fn test_left_recursion_base_case_and_failure() {
+    let grammar = left_recursive_subtraction_grammar();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "7", &mut arena);
+    let ast = parser.parse().unwrap();
+    assert_eq!(eval_subtraction(&ast_to_value(&ast, &arena, "7")), 7);
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "-1", &mut arena);
+    assert!(parser.parse().is_err());
+
+    // Trailing operator: the seed stops growing at "1-2"
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "1-2-", &mut arena);
+    assert!(matches!(
+        parser.parse(),
+        Err(ParseError::Incomplete { actual: 3, .. })
+    ));
+}
+
+#[test]
+fn test_non_left_recursive_grammar_skips_seed_growing() {
+    // list = digit "," list | digit
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "list",
+            re("[0-9]")
+                .then(str(","))
+                .then(ref_("list"))
+                .or(re("[0-9]")),
+        )
+        .build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "1,2,3", &mut arena);
+    assert!(parser.left_recursion.is_none());
+    assert!(parser.parse().is_ok());
+}
+
+#[test]
+fn test_stri_ascii_keeps_original_slice() {
+    let grammar = GrammarBuilder::new().rule("kw", stri("select")).build();
+    let input = "SeLeCt";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let node = parser.parse().expect("case-insensitive match");
+
+    match node {
+        AstNode::InputRef { offset, length } => {
+            assert_eq!((offset, length), (0, 6));
+            assert_eq!(
+                &input[offset as usize..(offset + length) as usize],
+                "SeLeCt"
+            );
+        }
+        other => panic!("expected InputRef, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_stri_unicode_folding() {
+    let grammar = GrammarBuilder::new().rule("word", stri("äbc")).build();
+    let input = "ÄBC";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let node = parser.parse().expect("unicode case-insensitive match");
+
+    assert!(matches!(
+        node,
+        AstNode::InputRef { offset: 0, length } if length as usize == input.len()
+    ));
+}
+
+#[test]
+fn test_stri_mismatch() {
+    let grammar = GrammarBuilder::new().rule("kw", stri("select")).build();
+
+    for input in ["selec", "insert", "selext"] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert!(parser.parse().is_err(), "{:?} should not match", input);
+    }
+}
+
+#[test]
+fn test_stri_json_roundtrip() {
+    let grammar = GrammarBuilder::new().rule("kw", stri("from")).build();
+    let json = grammar.to_json().unwrap();
+    assert!(json.contains("StrCi"));
+
+    let restored = Grammar::from_json(&json).unwrap();
+    assert_eq!(restored, grammar);
+}
+
+#[test]
+fn test_char_range_ascii_and_multibyte() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "word",
+            char_range('a', 'z').or(char_range('α', 'ω')).many1(),
+        )
+        .build();
+
+    for (input, end) in [("abc", 3), ("λογος", "λογος".len()), ("aβc1", 4)] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        let (_, end_pos) = parser.parse_prefix().unwrap();
+        assert_eq!(end_pos, end, "{:?}", input);
+    }
+
+    for input in ["A", "1", "Ω"] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert!(parser.parse().is_err(), "{:?} should not match", input);
+    }
+}
+
+#[test]
+fn test_char_range_json_roundtrip() {
+    let grammar = GrammarBuilder::new()
+        .rule("greek", char_range('α', 'ω'))
+        .build();
+    let json = grammar.to_json().unwrap();
+    assert!(json.contains("CharRange"));
+
+    let restored = Grammar::from_json(&json).unwrap();
+    assert_eq!(restored, grammar);
+}
+
+#[test]
+fn test_take_until_scans_block_comment_body() {
+    fn comment(body: impl crate::portable::parser_dsl::Parslet) -> Grammar {
+        GrammarBuilder::new()
+            .rule("comment", str("/*").then(body))
+            .build()
+    }
+    let inclusive = comment(take_until_inclusive("*/").label("body"));
+    let input = "/* a * b / c **/";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&inclusive, input, &mut arena);
+    let ast = parser.parse().unwrap();
+    let body = find_hash_value(&ast, &arena, "body").unwrap();
+    assert_eq!(
+        ast_to_value(&body, &arena, input),
+        Value::String(" a * b / c *".to_string())
+    );
+
+    // An unterminated comment fails, or runs to the end with `or_end`
+    assert!(inclusive.parse("/* open").is_err());
+    let to_end = comment(take_until_inclusive("*/").or_end());
+    assert!(to_end.parse("/* open").is_ok());
+
+    // Without `_inclusive` the terminator is left for the next atom
+    let exclusive = comment(take_until("*/").then(str("*/")));
+    assert!(exclusive.parse("/**/").is_ok());
+    let mut parser = PortableParser::new(&exclusive, "/* x */ y", &mut arena);
+    assert_eq!(parser.parse_prefix().unwrap().1, 7);
+}
+
+#[test]
+fn test_none_of_scans_string_literal_body() {
+    let grammar = GrammarBuilder::new()
+        .rule("body", none_of(&['"', '\\']).many())
+        .build();
+
+    // Stops at the quote, and multi-byte characters are consumed whole
+    for (input, end) in [
+        (r#"abc"rest"#, 3),
+        ("naïve ✓\\n", "naïve ✓".len()),
+        (r#""#, 0),
+        (r#"""#, 0),
+    ] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        let (_, end_pos) = parser.parse_prefix().unwrap();
+        assert_eq!(end_pos, end, "{:?}", input);
+    }
+
+    // A single none_of fails at end of input rather than matching nothing
+    let single = GrammarBuilder::new().rule("char", none_of(&['"'])).build();
+    assert!(single.parse("").is_err());
+    assert!(single.parse("é").is_ok());
+    assert!(single.parse("\"").is_err());
+}
+
+#[test]
+fn test_one_of_matches_single_set_member() {
+    let grammar = GrammarBuilder::new()
+        .rule("ops", one_of(&['+', '-', '→']).many1())
+        .build();
+    assert!(grammar.parse("+-→+").is_ok());
+    assert!(grammar.parse("+*").is_err());
+    assert!(grammar.parse("").is_err());
+}
+
+/// `key:` opens a block of statements indented deeper than `key`
+fn indented_block_grammar() -> Grammar {
+    let nl = || str("\n").then(str(" ").many());
+    GrammarBuilder::new()
+        .rule(
+            "file",
+            ref_("stmt").then(nl().then(samedent()).then(ref_("stmt")).many()),
+        )
+        .rule(
+            "stmt",
+            ref_("word")
+                .then(str(":"))
+                .then(nl())
+                .then(indent())
+                .then(ref_("stmt"))
+                .then(nl().then(samedent()).then(ref_("stmt")).many())
+                .then(dedent())
+                .or(ref_("word")),
+        )
+        .rule("word", re("[a-z]").many1())
+        .build()
+}
+
+#[test]
+fn test_indentation_nests_and_closes_blocks() {
+    let grammar = indented_block_grammar();
+    for input in [
+        "a",
+        "a\nb",
+        "a:\n  b",
+        "a:\n  b\n  c:\n    d\n  e\nf",
+        "a:\n b:\n  c\nd",
+    ] {
+        assert!(grammar.parse(input).is_ok(), "{:?}", input);
+    }
+    for input in [
+        // Not deeper than the enclosing block
+        "a:\nb",
+        // Between two open blocks
+        "a:\n  b\n c",
+        "a:\n    b\n  c",
+        // Indented without a header
+        "a\n  b",
+    ] {
+        assert!(grammar.parse(input).is_err(), "{:?}", input);
+    }
+}
+
+#[test]
+fn test_failed_alternative_restores_indentation() {
+    let blank = || str(" ").many();
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "root",
+            blank()
+                .then(indent())
+                .then(str("1"))
+                .or(blank().then(str("2")))
+                .then(str("\n"))
+                .then(samedent())
+                .then(str("3")),
+        )
+        .build();
+
+    // The first branch opens a block at column 3 before failing; "3" is
+    // only at the same indentation once that block is dropped again
+    assert!(grammar.parse("  2\n3").is_ok());
+    assert!(grammar.parse("  1\n3").is_err());
+}
+
+#[test]
+fn test_dedent_without_open_block_fails() {
+    let grammar = GrammarBuilder::new().rule("close", dedent()).build();
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "", &mut arena);
+    let error = parser.parse_with_rich_error().unwrap_err();
+    assert!(
+        error.message.contains("No indented block"),
+        "{}",
+        error.message
+    );
+}
+
+#[test]
+fn test_longest_choice_disambiguates_keywords_from_identifiers() {
+    let keyword = || str("for").label("keyword");
+    let ident = || re("[a-z]").many1().label("ident");
+    let longest = GrammarBuilder::new()
+        .rule(
+            "word",
+            longest_choice([dynamic(keyword()), dynamic(ident())]),
+        )
+        .build();
+    let ordered = GrammarBuilder::new()
+        .rule("word", keyword().or(ident()))
+        .build();
+
+    // Ordered choice commits to the "for" prefix of "format"
+    assert!(ordered.parse("format").is_err());
+
+    // Longest match takes the identifier, and the keyword wins the tie
+    for (input, key) in [("format", "ident"), ("for", "keyword"), ("fo", "ident")] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&longest, input, &mut arena);
+        let ast = parser.parse().unwrap();
+        assert!(
+            find_hash_value(&ast, &arena, key).is_some(),
+            "{:?} should match as {}",
+            input,
+            key
+        );
+    }
+    assert!(longest.parse("").is_err());
+}
+
+#[test]
+fn test_span_arena_records_hash_span() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "assign",
+            str("let ")
+                .then(re("[a-z]").many1().label("name"))
+                .then(str(" = "))
+                .then(re("[0-9]").many1().label("value"))
+                .then(str(";"))
+                .label("assign"),
+        )
+        .build();
+    let input = "let x = 42;";
+
+    let mut arena = AstArena::with_spans();
+    let ast = PortableParser::new(&grammar, input, &mut arena)
+        .parse()
+        .unwrap();
+    let AstNode::Hash { .. } = ast else {
+        panic!("expected a hash, got {:?}", ast);
+    };
+
+    let span = arena.span_of(&ast).unwrap();
+    assert_eq!((span.start.offset, span.end.offset), (0, input.len()));
+    assert_eq!((span.start.line, span.start.column), (1, 1));
+    let body = find_hash_value(&ast, &arena, "assign").unwrap();
+    assert_eq!(arena.span_of(&body), Some(span));
+    let value = find_hash_value(&body, &arena, "value").unwrap();
+    assert_eq!(arena.span_of(&value).unwrap().start.column, 9);
+
+    // Without spans only input references know where they came from
+    let mut plain = AstArena::new();
+    let ast = PortableParser::new(&grammar, input, &mut plain)
+        .parse()
+        .unwrap();
+    assert!(plain.span_of(&ast).is_none());
+}
+
+#[test]
+fn test_memoize_rule_only_caches_marked_rules() {
+    let build = |selective: bool| {
+        let builder = GrammarBuilder::new()
+            .rule(
+                "expr",
+                ref_("term")
+                    .then(str("+"))
+                    .then(ref_("expr"))
+                    .or(ref_("term")),
+            )
+            .rule("term", re("[0-9]").many1());
+        if selective {
+            builder.memoize_rule("term").build()
+        } else {
+            builder.build()
+        }
+    };
+    let input = "1+22+333";
+
+    let run = |grammar: &Grammar| {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(grammar, input, &mut arena);
+        let node = parser.parse().unwrap();
+        let stats = parser.cache_stats();
+        (ast_to_value(&node, &arena, input), stats)
+    };
+
+    let (full_value, full_stats) = run(&build(false));
+    let selective = build(true);
+    assert!(selective.memoized.is_some());
+    let (selective_value, selective_stats) = run(&selective);
+
+    assert_eq!(selective_value, full_value);
+    assert!(selective_stats.entries < full_stats.entries);
+    // `term` is still retried by the second alternative, and still hits
+    assert!(selective_stats.hits > 0);
+}
+
+#[test]
+fn test_memoize_composites_matches_full_memoization() {
+    let build = |selective: bool| {
+        let builder = GrammarBuilder::new()
+            .rule(
+                "list",
+                str("[")
+                    .then(ref_("item").then(str(",").then(ref_("item")).many()))
+                    .then(str("]")),
+            )
+            .rule(
+                "item",
+                ref_("list")
+                    .or(re("[0-9]").many1().label("int"))
+                    .or(re("[a-z]").many1().label("word")),
+            );
+        if selective {
+            builder.memoize_composites().build()
+        } else {
+            builder.build()
+        }
+    };
+    let input = "[1,[ab,22],[[c]],333]";
+
+    let run = |grammar: &Grammar| {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(grammar, input, &mut arena);
+        let node = parser.parse().unwrap();
+        let stats = parser.cache_stats();
+        (ast_to_value(&node, &arena, input), stats)
+    };
+
+    let (full_value, full_stats) = run(&build(false));
+    let (selective_value, selective_stats) = run(&build(true));
+
+    assert_eq!(selective_value, full_value);
+    assert!(selective_stats.entries < full_stats.entries);
+}
+
+#[test]
+fn test_bounded_cache_matches_unbounded_parse() {
+    // items = sum ("," sum)*, with a left-recursive sum and shared prefixes
+    let grammar = GrammarBuilder::new()
+        .rule("items", ref_("sum").then(str(",").then(ref_("sum")).many()))
+        .rule(
+            "sum",
+            ref_("sum")
+                .then(str("+"))
+                .then(ref_("term"))
+                .or(ref_("term")),
+        )
+        .rule(
+            "term",
+            re("[0-9]")
+                .many1()
+                .then(str("*"))
+                .then(ref_("term"))
+                .or(re("[0-9]").many1()),
+        )
+        .build();
+    let input = (0..2000)
+        .map(|i| format!("{}+{}*{}+7", i, i % 13, i % 7))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let run = |config: ParserConfig| {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, &input, &mut arena);
+        let node = parser.parse_with_config(config).unwrap();
+        let stats = parser.cache_stats();
+        let memory = parser.cache.memory_usage();
+        (ast_to_value(&node, &arena, &input), stats, memory)
+    };
+
+    let (unbounded, unbounded_stats, _) = run(ParserConfig::new());
+    let budget = 4096;
+    let (bounded, bounded_stats, memory) = run(ParserConfig::new().with_max_cache_bytes(budget));
+
+    assert_eq!(bounded, unbounded);
+    assert_eq!(unbounded_stats.evictions, 0);
+    assert!(bounded_stats.evictions > 0);
+    assert!(bounded_stats.entries < unbounded_stats.entries);
+    assert!(memory <= budget);
+}
+
+#[test]
+fn test_cache_stats_recursive_grammar() {
+    // Both alternatives start with `term`, so the second one is a cache hit
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "expr",
+            ref_("term")
+                .then(str("+"))
+                .then(ref_("expr"))
+                .or(ref_("term")),
+        )
+        .rule("term", re("[0-9]").many1())
+        .build();
+    let input = "1+22+333";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert_eq!(parser.cache_stats().hits, 0);
+    parser.parse().unwrap();
+
+    let stats = parser.cache_stats();
+    assert!(stats.hits > 0, "expected cache hits, got {:?}", stats);
+    assert!(stats.misses > 0);
+    assert!(stats.insertions > 0);
+    assert_eq!(stats.entries as u64, stats.insertions);
+}
+
+#[test]
+fn test_parse_prefix_returns_remainder_offset() {
+    let grammar = GrammarBuilder::new().rule("greeting", str("hello")).build();
+    let input = "hello world";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let (ast, offset) = parser.parse_prefix().unwrap();
+
+    assert_eq!(offset, 5);
+    assert_eq!(&input[offset..], " world");
+    assert!(matches!(
+        ast,
+        AstNode::InputRef {
+            offset: 0,
+            length: 5
+        }
+    ));
+
+    // `parse` still insists on consuming everything
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert!(matches!(
+        parser.parse(),
+        Err(ParseError::Incomplete { actual: 5, .. })
+    ));
+}
+
+#[test]
+fn test_parse_prefix_full_match_and_failure() {
+    let grammar = GrammarBuilder::new().rule("greeting", str("hello")).build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "hello", &mut arena);
+    assert_eq!(parser.parse_prefix().unwrap().1, 5);
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "goodbye", &mut arena);
+    assert!(parser.parse_prefix().is_err());
+}
+
+#[test]
+fn test_eof_inside_choice() {
+    // The first branch only applies when "a" is the last thing in the input
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "stmt",
+            str("a")
+                .label("last")
+                .then(eof())
+                .or(str("a").then(str("b"))),
+        )
+        .build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "a", &mut arena);
+    let ast = parser.parse().unwrap();
+    assert!(find_hash_value(&ast, &arena, "last").is_some());
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "ab", &mut arena);
+    let ast = parser.parse().unwrap();
+    assert!(find_hash_value(&ast, &arena, "last").is_none());
+}
+
+#[test]
+fn test_eof_consumes_nothing_and_reports_failure() {
+    let grammar = GrammarBuilder::new().rule("empty", eof()).build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "", &mut arena);
+    assert_eq!(parser.parse().unwrap(), AstNode::Nil);
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "x", &mut arena);
+    let error = parser.parse_with_rich_error().unwrap_err();
+    assert!(
+        error.message.contains("Expected end of input"),
+        "{}",
+        error.message
+    );
+}
+
+#[test]
+fn test_as_int_produces_int_nodes() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "int",
+            str("-").optional().then(re("[0-9]").many1()).as_int(),
+        )
+        .build();
+
+    for (input, expected) in [("42", 42), ("-7", -7), ("0", 0)] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert_eq!(parser.parse().unwrap(), AstNode::Int(expected));
+    }
+}
+
+#[test]
+fn test_as_int_overflow_is_invalid_number() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "int",
+            str("-").optional().then(re("[0-9]").many1()).as_int(),
+        )
+        .build();
+
+    let input = "123456789012345678901234567890";
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert!(matches!(
+        parser.parse(),
+        Err(ParseError::InvalidNumber { start: 0, end: 30 })
+    ));
+
+    // The i64 limits themselves still parse
+    for (input, expected) in [
+        ("9223372036854775807", i64::MAX),
+        ("-9223372036854775808", i64::MIN),
+    ] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert_eq!(parser.parse().unwrap(), AstNode::Int(expected));
+    }
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "9223372036854775808", &mut arena);
+    assert!(matches!(
+        parser.parse(),
+        Err(ParseError::InvalidNumber { .. })
+    ));
+}
+
+#[test]
+fn test_as_int_overflow_is_reported_inside_choice_and_repetition() {
+    let int = || re("[0-9]").many1().as_int();
+    let cases = [
+        (
+            GrammarBuilder::new()
+                .rule("value", int().or(re("[0-9a-z]").many1()))
+                .build(),
+            "99999999999999999999",
+        ),
+        (
+            GrammarBuilder::new()
+                .rule("list", int().then(str(" ").optional()).many())
+                .build(),
+            "1 99999999999999999999",
+        ),
+    ];
+
+    for (grammar, input) in &cases {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(grammar, input, &mut arena);
+        assert!(
+            matches!(parser.parse(), Err(ParseError::InvalidNumber { .. })),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_parse_int_radix_prefixes() {
+    assert_eq!(parse_int("0x1F", true), Some(31));
+    assert_eq!(parse_int("-0o17", true), Some(-15));
+    assert_eq!(parse_int("+0b101", true), Some(5));
+    assert_eq!(parse_int("0x1F", false), None);
+    assert_eq!(parse_int("0x", true), None);
+    assert_eq!(parse_int("0x-1", true), None);
+    assert_eq!(parse_int("--1", false), None);
+    assert_eq!(parse_int("0x8000000000000000", true), None);
+    assert_eq!(parse_int("-0x8000000000000000", true), Some(i64::MIN));
+}
+
+#[test]
+fn test_as_float_produces_float_nodes() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "float",
+            re("[0-9]")
+                .many1()
+                .then(str(".").then(re("[0-9]").many1()).optional())
+                .as_float(),
+        )
+        .build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "2.5", &mut arena);
+    assert_eq!(parser.parse().unwrap(), AstNode::Float(2.5));
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "3", &mut arena);
+    assert_eq!(parser.parse().unwrap(), AstNode::Float(3.0));
+}
+
+#[test]
+fn test_unicode_letter_identifiers() {
+    let grammar = GrammarBuilder::new()
+        .rule("ident", re("\\p{L}").many1())
+        .build();
+
+    for (input, end) in [
+        ("λόγος", "λόγος".len()),
+        ("漢字", "漢字".len()),
+        ("abc", 3),
+        ("Ωmega1", "Ωmega".len()),
+    ] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        let (_, end_pos) = parser.parse_prefix().unwrap();
+        assert_eq!(end_pos, end, "{:?}", input);
+    }
+
+    for input in ["1λ", "_", "·"] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert!(parser.parse().is_err(), "{:?} should not match", input);
+    }
+}
+
+#[test]
+fn test_unicode_property_repetition_counts_chars() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "ident",
+            re("\\p{Alphabetic}")
+                .repeat(1, Some(2))
+                .then(re("\\p{N}").many()),
+        )
+        .build();
+
+    let input = "日本語";
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let (_, end_pos) = parser.parse_prefix().unwrap();
+    assert_eq!(end_pos, "日本".len());
+
+    let input = "αβ٣4";
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert!(parser.parse().is_ok());
+}
+
+#[test]
+fn test_collect_expected_reports_literal() {
+    let grammar = GrammarBuilder::new().rule("greeting", str("hello")).build();
+    let config = ParserConfig::new().with_collect_expected(true);
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "wor", &mut arena);
+    match parser.parse_with_config(config) {
+        Err(ParseError::FailedExpecting { position, expected }) => {
+            assert_eq!(position, 0);
+            assert_eq!(expected, vec!["hello".to_string()]);
+        }
+        other => panic!("expected FailedExpecting, got {:?}", other),
+    }
+
+    // Without the option the error stays minimal
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "wor", &mut arena);
+    assert!(matches!(
+        parser.parse_with_config(ParserConfig::new()),
+        Err(ParseError::Failed { .. })
+    ));
+}
+
+#[test]
+fn test_collect_expected_lists_alternatives_at_furthest_failure() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "stmt",
+            str("let ").then(str("x").or(str("y")).or(re("[0-9]"))),
+        )
+        .build();
+    let config = ParserConfig::new().with_collect_expected(true);
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "let z", &mut arena);
+    let err = parser.parse_with_config(config).unwrap_err();
+    match &err {
+        ParseError::FailedExpecting { position, expected } => {
+            assert_eq!(*position, 4);
+            assert_eq!(expected, &["x", "y", "[0-9]"]);
+        }
+        other => panic!("expected FailedExpecting, got {:?}", other),
+    }
+    assert_eq!(
+        err.to_string(),
+        "Parse failed at position 4: expected \"x\", \"y\" or \"[0-9]\""
+    );
+}
+
+#[test]
+fn test_from_bytes_reports_first_invalid_utf8_byte() {
+    let grammar = GrammarBuilder::new().rule("text", any().many()).build();
+    // "é" (0xc3 0xa9) is valid; 0xc3 followed by "(" is a truncated sequence
+    let input = b"caf\xc3\xa9 caf\xc3(";
+
+    let mut arena = AstArena::new();
+    assert!(matches!(
+        PortableParser::from_bytes(&grammar, input, &mut arena),
+        Err(ParseError::InvalidUtf8 { offset: 9 })
+    ));
+
+    let mut buffer = String::new();
+    let mut parser = PortableParser::from_bytes_lossy(&grammar, input, &mut buffer, &mut arena);
+    assert!(parser.parse().is_ok());
+    assert_eq!(buffer, "café caf\u{fffd}(");
+
+    // Valid input isn't copied
+    let mut buffer = String::new();
+    let mut parser = PortableParser::from_bytes_lossy(&grammar, b"ok", &mut buffer, &mut arena);
+    assert!(parser.parse().is_ok());
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_described_rule_failure_uses_description() {
+    let grammar = GrammarBuilder::new()
+        .rule("signup", str("email: ").then(ref_("email")))
+        .rule_described(
+            "email",
+            "a valid email address",
+            re("[a-z]+").then(str("@")).then(re("[a-z]+")),
+        )
+        .build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "email: 42", &mut arena);
+    let error = parser.parse_with_rich_error().unwrap_err();
+    assert_eq!(error.message, "Expected a valid email address, found '4'");
+
+    // The description replaces the patterns inside the rule
+    let config = ParserConfig::new().with_collect_expected(true);
+    let mut parser = PortableParser::new(&grammar, "email: 42", &mut arena);
+    match parser.parse_with_config(config) {
+        Err(ParseError::FailedExpecting { position, expected }) => {
+            assert_eq!(position, 7);
+            assert_eq!(expected, ["a valid email address"]);
+        }
+        other => panic!("expected FailedExpecting, got {:?}", other),
+    }
+
+    // A failure past the start of the rule isn't blamed on the rule
+    let mut parser = PortableParser::new(&grammar, "email: bob", &mut arena);
+    let error = parser.parse_with_rich_error().unwrap_err();
+    assert!(
+        !error.message.contains("email address"),
+        "{}",
+        error.message
+    );
+}
+
+#[test]
+fn test_interned_inputs_share_pool_entry() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "ifs",
+            str("if")
+                .label("first")
+                .then(str(" "))
+                .then(str("if").label("second")),
+        )
+        .build();
+    let input = "if if";
+
+    let mut arena = AstArena::with_interned_inputs();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let ast = parser.parse().unwrap();
+
+    let first = find_hash_value(&ast, &arena, "first").unwrap();
+    let second = find_hash_value(&ast, &arena, "second").unwrap();
+    assert!(matches!(first, AstNode::StringRef { .. }));
+    assert_eq!(first, second);
+    let AstNode::StringRef { pool_index } = first else {
+        unreachable!()
+    };
+    assert_eq!(arena.get_string(pool_index as usize), "if");
+
+    // The default arena keeps zero-copy input references
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let ast = parser.parse().unwrap();
+    assert_eq!(
+        find_hash_value(&ast, &arena, "second"),
+        Some(AstNode::InputRef {
+            offset: 3,
+            length: 2
+        })
+    );
+}
+
+#[test]
+fn test_parse_reports_farthest_failure() {
+    // ("let " name "=" digits) | ("print " digits)
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "stmt",
+            str("let ")
+                .then(re("[a-z]").many1())
+                .then(str("="))
+                .then(re("[0-9]").many1())
+                .or(str("print ").then(re("[0-9]").many1())),
+        )
+        .build();
+    let input = "let x=y";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert!(matches!(
+        parser.parse(),
+        Err(ParseError::Failed { position: 6 })
+    ));
+
+    // The root alternative itself failed where it started
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let config = ParserConfig::new().with_farthest_failure(false);
+    assert!(matches!(
+        parser.parse_with_config(config),
+        Err(ParseError::Failed { position: 0 })
+    ));
+}
+
+#[test]
+fn test_parse_into_direct_transform() {
+    use crate::portable::transform::{direct_helpers, DirectTransform, TransformError};
+
+    #[derive(Debug, PartialEq)]
+    struct Number(i64);
+
+    impl DirectTransform for Number {
+        fn from_ast(node: &AstNode, arena: &AstArena, input: &str) -> Result<Self, TransformError> {
+            let text = direct_helpers::extract_string(node, arena, input)?;
+            text.parse()
+                .map(Number)
+                .map_err(|_| TransformError::Custom(format!("{} does not fit in i64", text)))
+        }
+    }
+
+    let grammar = GrammarBuilder::new().rule("number", re("[0-9]+")).build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "42", &mut arena);
+    assert_eq!(parser.parse_into::<Number>().unwrap(), Number(42));
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "99999999999999999999", &mut arena);
+    match parser.parse_into::<Number>() {
+        Err(ParseError::Transform { message }) => {
+            assert_eq!(message, "99999999999999999999 does not fit in i64")
+        }
+        other => panic!("expected a transform error, got {:?}", other),
+    }
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "x", &mut arena);
+    assert!(matches!(
+        parser.parse_into::<Number>(),
+        Err(ParseError::Failed { .. })
+    ));
+}
+
+/// Two words separated by whitespace and `/* */` comments via a skip rule
+fn skipping_grammar() -> GrammarBuilder {
+    GrammarBuilder::new()
+        .rule(
+            "pair",
+            ref_("word")
+                .label("first")
+                .then(ref_("word").label("second")),
+        )
+        .rule("word", re("[a-z]").many1())
+        .rule(
+            "comment",
+            str("/*")
+                .then(str("*/").not_ahead().then(any()).many())
+                .then(str("*/")),
+        )
+        .set_skip(re("\\s").or(ref_("comment")).many())
+}
+
+#[test]
+fn test_skip_rule_between_sequence_elements() {
+    use crate::portable::parser_dsl::SemanticActions;
+
+    let grammar = skipping_grammar().build();
+    let input = "a /* c */ b";
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    let value = parser
+        .parse_and_transform(&SemanticActions::default())
+        .unwrap();
+    assert_eq!(value.get("first").and_then(|v| v.as_str()), Some("a"));
+    assert_eq!(value.get("second").and_then(|v| v.as_str()), Some("b"));
+
+    // Before the root too, but not inside the words themselves
+    assert!(grammar.parse("  /**/a b").is_ok());
+    assert!(grammar.parse("ab").is_err());
+
+    // The skip rule's own failures aren't reported as expected tokens
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "a /* c */ 1", &mut arena);
+    let config = ParserConfig::new().with_collect_expected(true);
+    match parser.parse_with_config(config) {
+        Err(ParseError::FailedExpecting { position, expected }) => {
+            assert_eq!(position, 10);
+            assert_eq!(expected, ["[a-z]"]);
+        }
+        other => panic!("expected FailedExpecting, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_no_skip_rule_keeps_tokens_contiguous() {
+    let keyword = || {
+        GrammarBuilder::new()
+            .rule("keyword", str("i").then(str("f")))
+            .set_skip(re("\\s").many())
+    };
+    assert!(keyword().build().parse("i f").is_ok());
+
+    let strict = keyword().no_skip_rule("keyword").build();
+    assert!(strict.parse(" if").is_ok());
+    assert!(strict.parse("i f").is_err());
+
+    // Rules referenced from a no-skip rule don't skip either
+    let nested = GrammarBuilder::new()
+        .rule("stmt", ref_("keyword").then(str(";")))
+        .rule("keyword", str("i").then(ref_("tail")))
+        .rule("tail", str("f").then(str("!")))
+        .set_skip(re("\\s").many())
+        .no_skip_rule("keyword")
+        .build();
+    assert!(nested.parse("if! ;").is_ok());
+    assert!(nested.parse("if ! ;").is_err());
+}
+
+/// `name "=" number` rules, with whitespace skipped
+fn skipping_assign(builder: GrammarBuilder) -> GrammarBuilder {
+    builder
+        .rule("assign", ref_("name").then(str("=")).then(ref_("number")))
+        .rule("name", re("[a-z]").many1())
+        .rule("number", re("[0-9]").many1())
+        .set_skip(re("\\s").many())
+}
+
+#[test]
+fn test_skip_rule_after_root() {
+    let grammar = skipping_assign(GrammarBuilder::new()).build();
+    let input = "x = 42 ";
+    assert!(grammar.parse(input).is_ok());
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert!(parser.parse_iterative().is_ok());
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, input, &mut arena);
+    assert!(parser.parse_with_rich_error().is_ok());
+
+    // Each match starts after the trivia before it
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "x=1  y = 2", &mut arena);
+    let starts: Vec<_> = parser.parse_all().iter().map(|(start, _)| *start).collect();
+    assert_eq!(starts, [0, 5]);
+}
+
+#[test]
+fn test_skip_rule_between_repetition_items() {
+    use crate::portable::streaming_builder::BuilderStringCollector;
+
+    let stmts = GrammarBuilder::new().rule("stmts", ref_("assign").many());
+    let grammar = skipping_assign(stmts).build();
+    for input in ["x=1 y=2", " x = 1\ny=2\n"] {
+        assert!(grammar.parse(input).is_ok(), "{:?}", input);
+
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert!(parser.parse_iterative().is_ok(), "{:?}", input);
+
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        assert!(parser
+            .parse_streaming(&mut BuilderStringCollector::new())
+            .is_ok());
+
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        let (ast, errors) = parser.parse_recovering();
+        assert!(ast.is_some());
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    // A repetition of a regex is a single token
+    assert!(grammar.parse("x=1 2").is_err());
+}
+
+#[test]
+fn test_skip_rule_around_infix_operators() {
+    use crate::portable::infix::infix_expr;
+
+    let operators = [
+        InfixOperator::new("+", 1, Assoc::Left),
+        InfixOperator::new("*", 2, Assoc::Left),
+        InfixOperator::prefix("-", 3),
+        InfixOperator::postfix("!", 4),
+    ];
+    let grammar = GrammarBuilder::new()
+        .rule("expr", infix_expr(re("[0-9]").many1(), operators))
+        .set_skip(re("\\s").many())
+        .build();
+    for input in ["1 + 2", " 1 +2 * 3 ", "- 3 ! + 4"] {
+        assert!(grammar.parse(input).is_ok(), "{:?}", input);
+    }
+    // An operator without a right operand is left unconsumed
+    assert!(grammar.parse("1 + ").is_err());
+}
+
+#[test]
+fn test_skip_rule_between_lazy_repetition_items() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "block",
+            ref_("word").many().lazy(str("end")).then(str("end")),
+        )
+        .rule("word", re("[a-z]").many1())
+        .set_skip(re("\\s").many())
+        .build();
+    assert!(grammar.parse("ab cd end").is_ok());
+    assert!(grammar.parse("ab\n  end ").is_ok());
+    assert!(grammar.parse("end").is_ok());
+    assert!(grammar.parse("ab cd").is_err());
+}
+
+#[test]
+fn test_text_returns_matched_span_as_one_string() {
+    let grammars = [
+        GrammarBuilder::new()
+            .rule("word", re("[a-z]").many1().text())
+            .build(),
+        GrammarBuilder::new()
+            .rule("word", re("[a-z]+").text())
+            .build(),
+    ];
+    for grammar in &grammars {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(grammar, "hello", &mut arena);
+        match parser.parse().unwrap() {
+            AstNode::StringRef { pool_index } => {
+                assert_eq!(arena.get_string(pool_index as usize), "hello")
+            }
+            other => panic!("expected StringRef, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_parse_iterative_handles_deep_nesting() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "list",
+            str("[").then(ref_("list").optional()).then(str("]")),
+        )
+        .build();
+    let depth = 50_000;
+    let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+    let mut arena = AstArena::for_input(input.len());
+    let mut parser = PortableParser::new(&grammar, &input, &mut arena);
+    parser.set_max_recursion_depth(depth + 1);
+    assert!(parser.parse_iterative().is_ok());
+
+    // The recursion limit still applies
+    let mut arena = AstArena::for_input(input.len());
+    let mut parser = PortableParser::new(&grammar, &input, &mut arena);
+    parser.set_max_recursion_depth(depth / 2);
+    assert!(parser.parse_iterative().is_err());
+
+    // Unbalanced input fails like the recursive parse does
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "[[[]]", &mut arena);
+    assert!(matches!(
+        parser.parse_iterative(),
+        Err(ParseError::Failed { .. })
+    ));
+}
+
+#[test]
+fn test_parse_iterative_matches_recursive_parse() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "expr",
+            ref_("term")
+                .then(str("+").then(ref_("term")).many())
+                .label("sum"),
+        )
+        .rule(
+            "term",
+            re("[0-9]+")
+                .label("int")
+                .or(str("(").then(ref_("expr")).then(str(")"))),
+        )
+        .build();
+
+    for input in ["1+2", "(1+(2+3))+4", "((7))"] {
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        let recursive = parser.parse().unwrap();
+        let recursive = ast_to_value(&recursive, &arena, input);
+
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, input, &mut arena);
+        let iterative = parser.parse_iterative().unwrap();
+        assert_eq!(ast_to_value(&iterative, &arena, input), recursive);
+    }
+}
+
+#[test]
+fn test_named_captures_flattens_iso_date() {
+    let digits = |count| re("[0-9]").repeat(count, Some(count));
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "date",
+            digits(4)
+                .label("year")
+                .then(str("-"))
+                .then(digits(2).label("month"))
+                .then(str("-"))
+                .then(digits(2).label("day")),
+        )
+        .build();
+    let input = "2024-03-15";
+
+    let mut arena = AstArena::new();
+    let ast = PortableParser::new(&grammar, input, &mut arena)
+        .parse()
+        .unwrap();
+    let captures = ast.named_captures(&arena, input);
+
+    assert_eq!(captures.len(), 3);
+    assert_eq!(captures["year"], "2024");
+    assert_eq!(captures["month"], "03");
+    assert_eq!(captures["day"], "15");
+}
+
+#[test]
+fn test_value_bool_replaces_matched_keyword() {
+    let grammar = GrammarBuilder::new()
+        .rule("boolean", str("true").value_bool(true))
+        .build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "true", &mut arena);
+    assert_eq!(parser.parse().unwrap(), AstNode::Bool(true));
+
+    // A constant on its own consumes nothing
+    let grammar = GrammarBuilder::new()
+        .rule("count", re("[0-9]").many1().as_int().or(const_int(0)))
+        .build();
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "", &mut arena);
+    assert_eq!(parser.parse().unwrap(), AstNode::Int(0));
+}
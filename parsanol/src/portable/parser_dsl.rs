@@ -14,8 +14,11 @@
 //!     .build();
 //! ```
 
-use super::grammar::{Atom, Grammar};
-use std::collections::HashMap;
+use super::ast::AstNode;
+use super::grammar::{Atom, DefaultValue, Grammar};
+use super::transform::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// Parslet trait - implemented by all parser combinators
 pub trait Parslet: Send + Sync {
@@ -34,13 +37,138 @@ pub struct GrammarBuilder {
     /// For tracking forward references
     pending_entities: HashMap<usize, String>,
 
+    /// Names of the rules declared with [`declare`](Self::declare), by the
+    /// index of their reference atom
+    declared_rules: HashMap<usize, String>,
+
+    /// Reference atoms of declared rules not yet defined
+    undefined_rules: HashSet<usize>,
+
     /// Track insertion order for rules (first rule = root)
     first_rule: Option<String>,
 
     /// Last import map (if any)
     last_import: Option<ImportMap>,
+
+    /// Rule templates, expanded at `build()`
+    templates: HashMap<String, RuleTemplate>,
+
+    /// Rules to memoize; `None` memoizes every atom
+    memoized_rules: Option<Vec<String>>,
+
+    /// Whether to memoize every atom but cheap leaves
+    memoize_composites: bool,
+
+    /// Value of [`Grammar::ascii_only`] for the built grammar
+    ascii_only: bool,
+
+    /// Atom set with [`set_skip`](Self::set_skip)
+    skip: Option<usize>,
+
+    /// Rules parsed without skipping
+    no_skip_rules: Vec<String>,
+
+    /// Rule names and their descriptions, for [`Grammar::rule_descriptions`]
+    rule_descriptions: Vec<(String, String)>,
+
+    /// Semantic actions registered by [`ParsletExt::map`], by marker atom
+    actions: HashMap<usize, MapFn>,
+
+    /// Character pattern skipped by [`spaced_seq`] and [`lexeme`]
+    whitespace: String,
+
+    /// Shared ignored whitespace atom, built on first use
+    whitespace_atom: Option<usize>,
+}
+
+/// Whitespace pattern used until [`GrammarBuilder::with_whitespace`] is called
+const DEFAULT_WHITESPACE: &str = "\\s";
+
+/// A semantic action attached with [`ParsletExt::map`]
+type MapFn = Arc<dyn Fn(Value) -> Value + Send + Sync>;
+
+/// Label prefix of the marker atoms built by [`ParsletExt::map`]
+///
+/// A mapped parslet is built as a label named `MAP_LABEL_PREFIX` plus a
+/// counter, so its match shows up as `{ "<prefix>N" => value }` until
+/// [`SemanticActions::apply`] replaces it.
+pub const MAP_LABEL_PREFIX: &str = "@map:";
+
+/// A fatal grammar problem reported by [`GrammarBuilder::build_checked`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrammarError {
+    /// A rule reference names a rule that was never defined
+    UnresolvedRule {
+        /// Name of the missing rule
+        name: String,
+    },
+    /// An instantiation of an unknown template, or with the wrong number
+    /// of arguments
+    UnresolvedTemplate {
+        /// Index of the instantiation atom
+        atom: usize,
+        /// Name of the template
+        template: String,
+    },
+    /// An atom refers to an index past the end of the grammar
+    AtomIndexOutOfRange {
+        /// Index of the referring atom
+        atom: usize,
+        /// The out-of-range index
+        index: usize,
+    },
+    /// The root index is past the end of the grammar
+    InvalidRoot {
+        /// The root index
+        root: usize,
+    },
+}
+
+impl std::fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrammarError::UnresolvedRule { name } => {
+                write!(f, "Reference to undefined rule '{}'", name)
+            }
+            GrammarError::UnresolvedTemplate { atom, template } => {
+                write!(
+                    f,
+                    "Atom {} instantiates unknown template '{}' or passes the wrong number of arguments",
+                    atom, template
+                )
+            }
+            GrammarError::AtomIndexOutOfRange { atom, index } => {
+                write!(f, "Atom {} refers to out-of-range atom {}", atom, index)
+            }
+            GrammarError::InvalidRoot { root } => {
+                write!(f, "Root atom {} is out of range", root)
+            }
+        }
+    }
 }
 
+impl std::error::Error for GrammarError {}
+
+/// A parametrized rule body registered with [`GrammarBuilder::rule_template`]
+#[derive(Debug, Clone)]
+struct RuleTemplate {
+    /// Atoms making up the template body
+    atoms: std::ops::Range<usize>,
+    /// Root atom of the body
+    root: usize,
+    /// Placeholder atom index -> parameter position
+    params: HashMap<usize, usize>,
+    /// Number of parameters
+    arity: usize,
+}
+
+/// Upper bound on template expansions per `build()`
+///
+/// Recursive instantiations with the same arguments share one copy, but a
+/// template that instantiates itself with ever-new arguments (e.g. wrapping
+/// a parameter in a fresh label) would otherwise expand forever.
+const MAX_TEMPLATE_EXPANSIONS: usize = 10_000;
+
 impl GrammarBuilder {
     /// Create a new grammar builder
     pub fn new() -> Self {
@@ -48,9 +176,64 @@ impl GrammarBuilder {
             atoms: Vec::new(),
             rules: HashMap::new(),
             pending_entities: HashMap::new(),
+            declared_rules: HashMap::new(),
+            undefined_rules: HashSet::new(),
             first_rule: None,
             last_import: None,
+            templates: HashMap::new(),
+            memoized_rules: None,
+            memoize_composites: false,
+            ascii_only: false,
+            skip: None,
+            no_skip_rules: Vec::new(),
+            rule_descriptions: Vec::new(),
+            actions: HashMap::new(),
+            whitespace: DEFAULT_WHITESPACE.to_string(),
+            whitespace_atom: None,
+        }
+    }
+
+    /// Set the whitespace skipped by [`spaced_seq`] and [`lexeme`]
+    ///
+    /// `pattern` is a single-character regex such as `"[ \t]"`; zero or
+    /// more matches of it are skipped and ignored. Defaults to `"\\s"`.
+    /// Only parslets built after this call use the new pattern, and rules
+    /// that don't use those helpers are unaffected.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .with_whitespace("[ \t]")
+    ///     .rule("pair", spaced_seq([str("a"), str("b")]))
+    ///     .build();
+    /// assert!(grammar.parse("a \tb").is_ok());
+    /// assert!(grammar.parse("a\nb").is_err());
+    /// ```
+    pub fn with_whitespace(mut self, pattern: &str) -> Self {
+        self.whitespace = pattern.to_string();
+        self.whitespace_atom = None;
+        self
+    }
+
+    /// Index of the ignored whitespace atom, adding it on first use
+    fn whitespace_atom(&mut self) -> usize {
+        if let Some(idx) = self.whitespace_atom {
+            return idx;
         }
+        let space = self.add_atom(Atom::Re {
+            pattern: self.whitespace.clone(),
+        });
+        let spaces = self.add_atom(Atom::Repetition {
+            atom: space,
+            min: 0,
+            max: None,
+        });
+        let idx = self.add_atom(Atom::Ignore { atom: spaces });
+        self.whitespace_atom = Some(idx);
+        idx
     }
 
     /// Add a rule to the grammar
@@ -64,6 +247,35 @@ impl GrammarBuilder {
         self
     }
 
+    /// Add a rule with a human-readable description
+    ///
+    /// Like [`rule`](Self::rule), and records `description` in
+    /// [`Grammar::rule_descriptions`], so a failure of this rule is reported
+    /// as "Expected <description>" instead of by its pattern.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    /// use parsanol::portable::{AstArena, PortableParser};
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule_described("email", "a valid email address", re("[a-z]+@[a-z]+\\.[a-z]+"))
+    ///     .build();
+    ///
+    /// let mut arena = AstArena::new();
+    /// let mut parser = PortableParser::new(&grammar, "bob", &mut arena);
+    /// let error = parser.parse_with_rich_error().unwrap_err();
+    /// assert_eq!(error.message, "Expected a valid email address, found 'b'");
+    /// ```
+    pub fn rule_described(self, name: &str, description: &str, parslet: impl Parslet) -> Self {
+        let mut builder = self.rule(name, parslet);
+        builder
+            .rule_descriptions
+            .push((name.to_string(), description.to_string()));
+        builder
+    }
+
     /// Add a rule to the grammar (mutable version for chaining with import)
     pub fn rule_mut(&mut self, name: &str, parslet: impl Parslet) -> &mut Self {
         let atom_idx = parslet.build(self);
@@ -75,6 +287,219 @@ impl GrammarBuilder {
         self
     }
 
+    /// Declare a rule to be defined later, returning a handle to it
+    ///
+    /// The handle is a parslet that refers to the rule, so recursive rules
+    /// can use it before [`define`](Self::define) gives the rule its body.
+    /// Unlike [`ref_`], which looks a name up when the grammar is built, a
+    /// handle can't misspell its target, and
+    /// [`build_checked`](Self::build_checked) reports a declared rule that
+    /// was never defined. Declaring a rule counts as its first mention when
+    /// picking the root.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// let mut builder = GrammarBuilder::new();
+    /// let list = builder.declare("list");
+    /// builder.define(list, str("[").then(list.optional()).then(str("]")));
+    /// let grammar = builder.build_checked().unwrap();
+    ///
+    /// assert!(grammar.parse("[[[]]]").is_ok());
+    /// ```
+    pub fn declare(&mut self, name: &str) -> RuleHandle {
+        let atom = self.add_atom(Atom::Entity { atom: 0 }); // Placeholder
+        self.declared_rules.insert(atom, name.to_string());
+        self.undefined_rules.insert(atom);
+        if self.first_rule.is_none() {
+            self.first_rule = Some(name.to_string());
+        }
+        RuleHandle { atom }
+    }
+
+    /// Define a rule declared with [`declare`](Self::declare)
+    ///
+    /// The rule can then also be referenced by name with [`ref_`].
+    pub fn define(&mut self, handle: RuleHandle, parslet: impl Parslet) -> &mut Self {
+        let atom_idx = parslet.build(self);
+        self.atoms[handle.atom] = Atom::Entity { atom: atom_idx };
+        self.undefined_rules.remove(&handle.atom);
+        if let Some(name) = self.declared_rules.get(&handle.atom) {
+            self.rules.insert(name.clone(), atom_idx);
+        }
+        self
+    }
+
+    /// Memoize only the named rules
+    ///
+    /// By default the packrat parser caches the result of every atom. Once
+    /// this is called, only rules passed to `memoize_rule` are cached (see
+    /// [`Grammar::memoized`]). Names that never become rules are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule("expr", ref_("term").then(str("+")).then(ref_("expr")).or(ref_("term")))
+    ///     .rule("term", re("[0-9]").many1())
+    ///     .memoize_rule("term")
+    ///     .build();
+    ///
+    /// assert_eq!(grammar.memoized.as_ref().map(|m| m.len()), Some(1));
+    /// assert!(grammar.parse("1+2").is_ok());
+    /// ```
+    pub fn memoize_rule(mut self, name: &str) -> Self {
+        self.memoized_rules
+            .get_or_insert_with(Vec::new)
+            .push(name.to_string());
+        self
+    }
+
+    /// Don't memoize cheap leaf atoms
+    ///
+    /// Literals, character classes and single-character regexes are
+    /// re-matched instead of cached; everything else is memoized as usual
+    /// (see [`Grammar::memoize_composites`]). This saves cache memory
+    /// without changing parse results. Rules passed to
+    /// [`memoize_rule`](Self::memoize_rule) are memoized as well.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule("list", str("[").then(re("[0-9]").many1()).then(str("]")))
+    ///     .memoize_composites()
+    ///     .build();
+    ///
+    /// // Only the two sequences and the repetition are cached
+    /// assert_eq!(grammar.memoized.as_ref().map(|m| m.len()), Some(3));
+    /// assert!(grammar.parse("[12]").is_ok());
+    /// ```
+    pub fn memoize_composites(mut self) -> Self {
+        self.memoize_composites = true;
+        self
+    }
+
+    /// Skip `parslet` around the root and between sequence elements
+    ///
+    /// Sets [`Grammar::skip`]: the parser tries `parslet` before and after
+    /// the root rule, between any two elements of a sequence and between
+    /// the items of a repetition, and ignores what it matches. Repetitions
+    /// of a regex, such as `re("[a-z]").many1()`, match one token and skip
+    /// nothing. It should accept the empty string, e.g. a `.many()` of
+    /// whitespace and comments. Token rules built from sequences must opt
+    /// out with [`no_skip_rule`](Self::no_skip_rule).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule("assign", ref_("name").then(str("=")).then(ref_("number")))
+    ///     .rule("name", re("[a-z]").many1())
+    ///     .rule("number", re("[0-9]").many1())
+    ///     .set_skip(re("\\s").many())
+    ///     .build();
+    ///
+    /// assert!(grammar.parse(" x = 42").is_ok());
+    /// ```
+    pub fn set_skip(mut self, parslet: impl Parslet) -> Self {
+        self.skip = Some(parslet.build(&mut self));
+        self
+    }
+
+    /// Parse the named rule, and everything it references, without skipping
+    ///
+    /// Adds the rule to [`Grammar::no_skip`]. Use it for tokens made of
+    /// several parts, such as string literals or identifiers, so the skip
+    /// atom set with [`set_skip`](Self::set_skip) can't match between their
+    /// characters. Names that never become rules are ignored.
+    pub fn no_skip_rule(mut self, name: &str) -> Self {
+        self.no_skip_rules.push(name.to_string());
+        self
+    }
+
+    /// Flag the grammar as only matching ASCII text
+    ///
+    /// Sets [`Grammar::ascii_only`], so regexes are matched in byte mode
+    /// whenever the input is all ASCII.
+    pub fn ascii_only(mut self) -> Self {
+        self.ascii_only = true;
+        self
+    }
+
+    /// Add a parametrized rule template
+    ///
+    /// `body` receives one reference per parameter name and builds the rule
+    /// from them. Use [`instantiate`] to create concrete copies; each
+    /// instantiation is expanded during [`build`](Self::build) with the
+    /// parameters replaced by the given argument parslets. Inside the body,
+    /// parameter names shadow rules of the same name.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule("group", ref_("braces").or(ref_("parens")))
+    ///     .rule_template("block", &["open", "close"], |p| {
+    ///         p[0].then(re("[a-z]").many()).then(p[1])
+    ///     })
+    ///     .rule("braces", instantiate("block", [str("{"), str("}")]))
+    ///     .rule("parens", instantiate("block", [str("("), str(")")]))
+    ///     .build();
+    ///
+    /// assert!(grammar.parse("{abc}").is_ok());
+    /// assert!(grammar.parse("(abc)").is_ok());
+    /// ```
+    pub fn rule_template<'p, F, P>(mut self, name: &str, params: &[&'p str], body: F) -> Self
+    where
+        F: FnOnce(&[Ref<'p>]) -> P,
+        P: Parslet,
+    {
+        let start = self.atoms.len();
+        let refs: Vec<Ref<'p>> = params.iter().map(|param| Ref(param)).collect();
+        // A whitespace atom first built inside the body is copied with it,
+        // so it can't be shared with rules outside the template
+        let whitespace_atom = self.whitespace_atom;
+        let root = body(&refs).build(&mut self);
+        self.whitespace_atom = whitespace_atom;
+        let atoms = start..self.atoms.len();
+
+        // Forward references to parameter names inside the body are
+        // placeholders for the arguments, not references to rules
+        let mut placeholders = HashMap::new();
+        self.pending_entities.retain(|idx, rule_name| {
+            let param = params.iter().position(|param| param == rule_name);
+            match param {
+                Some(position) if atoms.contains(idx) => {
+                    placeholders.insert(*idx, position);
+                    false
+                }
+                _ => true,
+            }
+        });
+
+        self.templates.insert(
+            name.to_string(),
+            RuleTemplate {
+                atoms,
+                root,
+                params: placeholders,
+                arity: params.len(),
+            },
+        );
+        self
+    }
+
     /// Add an atom directly
     pub fn add_atom(&mut self, atom: Atom) -> usize {
         let idx = self.atoms.len();
@@ -88,12 +513,174 @@ impl GrammarBuilder {
         self
     }
 
+    /// Register `new_name` as another name for the rule `existing_name`
+    ///
+    /// Both names refer to the same atom, so references through either parse
+    /// identically. Useful for presenting imported rules (such as
+    /// `"json:root"`) under a clean public name. `existing_name` must
+    /// already be defined; aliases of aliases are allowed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// let number = GrammarBuilder::new()
+    ///     .rule("integer", re("[0-9]").many1())
+    ///     .build();
+    ///
+    /// let mut builder = GrammarBuilder::new();
+    /// builder.rule_mut("list", sep_by(ref_("number"), str(",")));
+    /// builder.import(&number, Some("num"));
+    /// builder.alias("number", "num:root").unwrap();
+    /// let grammar = builder.build();
+    ///
+    /// assert!(grammar.parse("1,22,333").is_ok());
+    /// ```
+    pub fn alias(
+        &mut self,
+        new_name: &str,
+        existing_name: &str,
+    ) -> Result<&mut Self, GrammarError> {
+        let atom_idx =
+            *self
+                .rules
+                .get(existing_name)
+                .ok_or_else(|| GrammarError::UnresolvedRule {
+                    name: existing_name.to_string(),
+                })?;
+        self.rules.insert(new_name.to_string(), atom_idx);
+        Ok(self)
+    }
+
     /// Register a forward reference
     pub fn add_forward_ref(&mut self, atom_idx: usize, rule_name: String) {
         self.pending_entities.insert(atom_idx, rule_name);
     }
 
+    /// Build the final grammar along with its semantic actions
+    ///
+    /// Use this instead of [`build`](Self::build) when rules use
+    /// [`ParsletExt::map`], and pass the actions to
+    /// [`PortableParser::parse_and_transform`]. The grammar only carries
+    /// marker labels for the mapped parslets: closures can't be serialized,
+    /// so a mapped grammar round-tripped through JSON parses the same but
+    /// has lost its actions.
+    ///
+    /// [`PortableParser::parse_and_transform`]: super::parser::PortableParser::parse_and_transform
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    /// use parsanol::portable::{AstArena, PortableParser, Value};
+    ///
+    /// let number = re("[0-9]").many1().map(|v| {
+    ///     Value::Int(v.as_str().and_then(|s| s.parse().ok()).unwrap_or(0))
+    /// });
+    /// let (grammar, actions) = GrammarBuilder::new()
+    ///     .rule("number", number)
+    ///     .build_with_actions();
+    ///
+    /// let mut arena = AstArena::new();
+    /// let mut parser = PortableParser::new(&grammar, "42", &mut arena);
+    /// assert_eq!(parser.parse_and_transform(&actions).unwrap(), Value::Int(42));
+    /// ```
+    pub fn build_with_actions(mut self) -> (Grammar, SemanticActions) {
+        let actions = std::mem::take(&mut self.actions)
+            .into_iter()
+            .filter_map(|(idx, action)| match self.atoms.get(idx) {
+                Some(Atom::Named { name, .. }) => Some((name.clone(), action)),
+                _ => None,
+            })
+            .collect();
+        (self.build(), SemanticActions { actions })
+    }
+
+    /// Build the final grammar, rejecting grammars that can't parse
+    ///
+    /// Unlike [`build`](Self::build), which leaves unresolved references
+    /// pointing at atom 0, this returns every problem found: references to
+    /// undefined rules, instantiations that didn't expand, atom indices out
+    /// of range and an invalid root. Semantic actions are dropped, as with
+    /// `build`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// let result = GrammarBuilder::new()
+    ///     .rule("greeting", str("hello").then(ref_("name")))
+    ///     .build_checked();
+    /// assert_eq!(
+    ///     result.unwrap_err(),
+    ///     vec![GrammarError::UnresolvedRule { name: "name".to_string() }]
+    /// );
+    /// ```
+    pub fn build_checked(self) -> Result<Grammar, Vec<GrammarError>> {
+        let mut unresolved: Vec<String> = self
+            .pending_entities
+            .values()
+            .filter(|name| !self.rules.contains_key(*name))
+            .chain(
+                self.undefined_rules
+                    .iter()
+                    .filter_map(|atom| self.declared_rules.get(atom)),
+            )
+            .cloned()
+            .collect();
+        unresolved.sort();
+        unresolved.dedup();
+
+        let mut errors: Vec<GrammarError> = unresolved
+            .into_iter()
+            .map(|name| GrammarError::UnresolvedRule { name })
+            .collect();
+        let grammar = self.build();
+        errors.extend(Self::check_indices(&grammar));
+
+        if errors.is_empty() {
+            Ok(grammar)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Find atoms of a built grammar that point outside it
+    fn check_indices(grammar: &Grammar) -> Vec<GrammarError> {
+        let len = grammar.atoms.len();
+        let errors = std::cell::RefCell::new(Vec::new());
+        for (atom, node) in grammar.atoms.iter().enumerate() {
+            if let Atom::Instantiate { template, .. } = node {
+                errors.borrow_mut().push(GrammarError::UnresolvedTemplate {
+                    atom,
+                    template: template.clone(),
+                });
+            }
+            map_atom_indices(node, |index| {
+                if index >= len {
+                    errors
+                        .borrow_mut()
+                        .push(GrammarError::AtomIndexOutOfRange { atom, index });
+                }
+                index
+            });
+        }
+
+        let mut errors = errors.into_inner();
+        if grammar.root >= len {
+            errors.push(GrammarError::InvalidRoot { root: grammar.root });
+        }
+        errors
+    }
+
     /// Build the final grammar
+    ///
+    /// Semantic actions registered with [`ParsletExt::map`] are dropped;
+    /// see [`build_with_actions`](Self::build_with_actions). Problems such
+    /// as references to undefined rules are not reported; use
+    /// [`build_checked`](Self::build_checked) to catch them.
     pub fn build(self) -> Grammar {
         // Resolve any pending entity references
         let mut atoms = self.atoms;
@@ -105,13 +692,142 @@ impl GrammarBuilder {
             }
         }
 
+        Self::expand_templates(&mut atoms, &self.templates);
+
         // Use first rule as root (preserving insertion order)
         let root = self
             .first_rule
             .and_then(|name| self.rules.get(&name).copied())
             .unwrap_or(0);
 
-        Grammar { atoms, root }
+        let (atoms, remap) = Self::remove_template_bodies(atoms, &self.templates);
+        let remapped = |idx: usize| remap.get(idx).copied().flatten();
+
+        let mut grammar = Grammar::from_atoms(atoms, remapped(root).unwrap_or(0));
+        grammar.memoized = self.memoized_rules.map(|names| {
+            names
+                .iter()
+                .filter_map(|name| self.rules.get(name))
+                .filter_map(|&idx| remapped(idx))
+                .collect()
+        });
+        if self.memoize_composites {
+            let rules = grammar.memoized.take();
+            grammar.memoize_composites();
+            if let (Some(memoized), Some(rules)) = (&mut grammar.memoized, rules) {
+                memoized.extend(rules);
+            }
+        }
+        grammar.ascii_only = self.ascii_only;
+        grammar.skip = self.skip.and_then(remapped);
+        grammar.no_skip = self
+            .no_skip_rules
+            .iter()
+            .filter_map(|name| self.rules.get(name))
+            .filter_map(|&idx| remapped(idx))
+            .collect();
+        grammar.rule_descriptions = self
+            .rule_descriptions
+            .into_iter()
+            .filter_map(|(name, description)| {
+                let idx = self.rules.get(&name).copied().and_then(remapped)?;
+                Some((idx, description))
+            })
+            .collect();
+        grammar
+    }
+
+    /// Replace each `Atom::Instantiate` with a reference to a fresh copy of
+    /// its template body
+    ///
+    /// Copies are appended to `atoms` and scanned in turn, so templates may
+    /// instantiate other templates. Instantiations with the same template
+    /// and arguments share one copy, which turns a template that
+    /// instantiates itself into an ordinary entity cycle. Instantiations of
+    /// unknown templates, or with the wrong number of arguments, are left
+    /// in place.
+    fn expand_templates(atoms: &mut Vec<Atom>, templates: &HashMap<String, RuleTemplate>) {
+        let mut expanded: HashMap<(String, Vec<usize>), usize> = HashMap::new();
+        let mut idx = 0;
+        while idx < atoms.len() && expanded.len() < MAX_TEMPLATE_EXPANSIONS {
+            let in_template_body = templates.values().any(|t| t.atoms.contains(&idx));
+            let instantiation = match &atoms[idx] {
+                Atom::Instantiate { template, args } if !in_template_body => templates
+                    .get(template)
+                    .filter(|t| t.arity == args.len())
+                    .map(|t| (t.clone(), (template.clone(), args.clone()))),
+                _ => None,
+            };
+
+            if let Some((template, key)) = instantiation {
+                let root = match expanded.get(&key) {
+                    Some(&root) => root,
+                    None => {
+                        let args = &key.1;
+                        let base = atoms.len();
+                        let map = |i: usize| match template.params.get(&i) {
+                            Some(&position) => args[position],
+                            None if template.atoms.contains(&i) => {
+                                base + (i - template.atoms.start)
+                            }
+                            None => i,
+                        };
+                        for i in template.atoms.clone() {
+                            let copy = map_atom_indices(&atoms[i], map);
+                            atoms.push(copy);
+                        }
+                        let root = map(template.root);
+                        expanded.insert(key, root);
+                        root
+                    }
+                };
+                atoms[idx] = Atom::Entity { atom: root };
+            }
+            idx += 1;
+        }
+    }
+
+    /// Drop the original template bodies once every instantiation has its
+    /// own copy, renumbering the remaining atoms
+    ///
+    /// The bodies still contain parameter placeholders and unexpanded
+    /// instantiations, which would otherwise show up in grammar analysis
+    /// and serialization. Returns the remaining atoms and, for each original
+    /// index, its new index (`None` for dropped atoms).
+    fn remove_template_bodies(
+        atoms: Vec<Atom>,
+        templates: &HashMap<String, RuleTemplate>,
+    ) -> (Vec<Atom>, Vec<Option<usize>>) {
+        if templates.is_empty() {
+            let remap = (0..atoms.len()).map(Some).collect();
+            return (atoms, remap);
+        }
+
+        let mut remap = Vec::with_capacity(atoms.len());
+        let mut next = 0;
+        for idx in 0..atoms.len() {
+            if templates.values().any(|t| t.atoms.contains(&idx)) {
+                remap.push(None);
+            } else {
+                remap.push(Some(next));
+                next += 1;
+            }
+        }
+
+        let map = |i: usize| {
+            remap
+                .get(i)
+                .copied()
+                .flatten()
+                .expect("template body referenced outside its template")
+        };
+        let atoms = atoms
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| remap[*idx].is_some())
+            .map(|(_, atom)| map_atom_indices(atom, map))
+            .collect();
+        (atoms, remap)
     }
 
     /// Get the current number of atoms
@@ -310,46 +1026,115 @@ impl ImportMap {
 
 /// Remap atom indices by adding an offset
 fn remap_atom(atom: &Atom, offset: usize) -> Atom {
+    map_atom_indices(atom, |idx| idx + offset)
+}
+
+/// Rewrite every child atom index of `atom` through `map`
+pub(crate) fn map_atom_indices(atom: &Atom, map: impl Fn(usize) -> usize) -> Atom {
     match atom {
         Atom::Str { pattern } => Atom::Str {
             pattern: pattern.clone(),
         },
+        Atom::StrCi { pattern } => Atom::StrCi {
+            pattern: pattern.clone(),
+        },
         Atom::Re { pattern } => Atom::Re {
             pattern: pattern.clone(),
         },
+        Atom::CharRange { lo, hi } => Atom::CharRange { lo: *lo, hi: *hi },
+        Atom::OneOf { chars } => Atom::OneOf {
+            chars: chars.clone(),
+        },
+        Atom::NoneOf { chars } => Atom::NoneOf {
+            chars: chars.clone(),
+        },
+        Atom::Until {
+            terminator,
+            inclusive,
+            or_end,
+        } => Atom::Until {
+            terminator: terminator.clone(),
+            inclusive: *inclusive,
+            or_end: *or_end,
+        },
+        Atom::ByteRange { lo, hi } => Atom::ByteRange { lo: *lo, hi: *hi },
         Atom::Sequence { atoms } => Atom::Sequence {
-            atoms: atoms.iter().map(|&idx| idx + offset).collect(),
+            atoms: atoms.iter().map(|&idx| map(idx)).collect(),
         },
         Atom::Alternative { atoms } => Atom::Alternative {
-            atoms: atoms.iter().map(|&idx| idx + offset).collect(),
+            atoms: atoms.iter().map(|&idx| map(idx)).collect(),
+        },
+        Atom::LongestAlternative { atoms } => Atom::LongestAlternative {
+            atoms: atoms.iter().map(|&idx| map(idx)).collect(),
         },
         Atom::Repetition { atom, min, max } => Atom::Repetition {
-            atom: atom + offset,
+            atom: map(*atom),
+            min: *min,
+            max: *max,
+        },
+        Atom::RepetitionLazy {
+            atom,
+            min,
+            max,
+            follow,
+        } => Atom::RepetitionLazy {
+            atom: map(*atom),
             min: *min,
             max: *max,
+            follow: map(*follow),
         },
         Atom::Named { name, atom } => Atom::Named {
             name: name.clone(),
-            atom: atom + offset,
+            atom: map(*atom),
         },
-        Atom::Entity { atom } => Atom::Entity {
-            atom: atom + offset,
+        Atom::Entity { atom } => Atom::Entity { atom: map(*atom) },
+        Atom::Instantiate { template, args } => Atom::Instantiate {
+            template: template.clone(),
+            args: args.iter().map(|&idx| map(idx)).collect(),
         },
         Atom::Lookahead { atom, positive } => Atom::Lookahead {
-            atom: atom + offset,
+            atom: map(*atom),
             positive: *positive,
         },
-        Atom::Cut => Atom::Cut,
-        Atom::Ignore { atom } => Atom::Ignore {
-            atom: atom + offset,
+        Atom::Peek { atom } => Atom::Peek { atom: map(*atom) },
+        Atom::Infix { operand, operators } => Atom::Infix {
+            operand: map(*operand),
+            operators: operators.clone(),
         },
+        Atom::Cut => Atom::Cut,
+        Atom::Eof => Atom::Eof,
+        Atom::Indent => Atom::Indent,
+        Atom::Samedent => Atom::Samedent,
+        Atom::Dedent => Atom::Dedent,
+        Atom::Ignore { atom } => Atom::Ignore { atom: map(*atom) },
+        Atom::Text { atom } => Atom::Text { atom: map(*atom) },
         Atom::Capture { name, atom } => Atom::Capture {
             name: name.clone(),
-            atom: atom + offset,
+            atom: map(*atom),
+        },
+        Atom::BackRef { name } => Atom::BackRef { name: name.clone() },
+        Atom::OptionalDefault {
+            name,
+            atom,
+            default,
+        } => Atom::OptionalDefault {
+            name: name.clone(),
+            atom: map(*atom),
+            default: *default,
+        },
+        Atom::ParseInt {
+            atom,
+            radix_prefixes,
+        } => Atom::ParseInt {
+            atom: map(*atom),
+            radix_prefixes: *radix_prefixes,
         },
-        Atom::Scope { atom } => Atom::Scope {
-            atom: atom + offset,
+        Atom::ParseFloat { atom } => Atom::ParseFloat { atom: map(*atom) },
+        Atom::Const { atom, value } => Atom::Const {
+            atom: map(*atom),
+            value: *value,
         },
+        Atom::Scope { atom } => Atom::Scope { atom: map(*atom) },
         Atom::Dynamic { callback_id } => Atom::Dynamic {
             callback_id: *callback_id,
         },
@@ -379,6 +1164,18 @@ impl<'a> Parslet for Str<'a> {
     }
 }
 
+/// Match a literal string, ignoring case
+#[derive(Clone, Copy)]
+pub struct StrCi<'a>(pub &'a str);
+
+impl<'a> Parslet for StrCi<'a> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::StrCi {
+            pattern: self.0.to_string(),
+        })
+    }
+}
+
 /// Match a regular expression
 #[derive(Clone, Copy)]
 pub struct Re<'a>(pub &'a str);
@@ -391,34 +1188,147 @@ impl<'a> Parslet for Re<'a> {
     }
 }
 
-/// Match any single character
-#[derive(Clone, Copy, Default)]
-pub struct Any;
+/// Match a single character in an inclusive range
+#[derive(Clone, Copy)]
+pub struct CharRange(pub char, pub char);
 
-impl Parslet for Any {
+impl Parslet for CharRange {
     fn build(self, builder: &mut GrammarBuilder) -> usize {
-        builder.add_atom(Atom::Re {
-            pattern: ".".to_string(),
+        builder.add_atom(Atom::CharRange {
+            lo: self.0,
+            hi: self.1,
         })
     }
 }
 
-/// A forward reference to a named rule (for recursive grammars)
+/// Match a single character from a set
 #[derive(Clone, Copy)]
-pub struct Ref<'a>(pub &'a str);
+pub struct OneOf<'a>(pub &'a [char]);
 
-impl<'a> Parslet for Ref<'a> {
+impl<'a> Parslet for OneOf<'a> {
     fn build(self, builder: &mut GrammarBuilder) -> usize {
-        let atom_idx = builder.add_atom(Atom::Entity { atom: 0 }); // Placeholder
-        builder.add_forward_ref(atom_idx, self.0.to_string());
-        atom_idx
+        builder.add_atom(Atom::OneOf {
+            chars: self.0.to_vec(),
+        })
     }
 }
 
-/// Sequence of two parslets (A >> B matches A then B)
+/// Match a single character not in a set
 #[derive(Clone, Copy)]
-pub struct Sequence2<A, B> {
-    first: A,
+pub struct NoneOf<'a>(pub &'a [char]);
+
+impl<'a> Parslet for NoneOf<'a> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::NoneOf {
+            chars: self.0.to_vec(),
+        })
+    }
+}
+
+/// Match a single byte in an inclusive range
+#[derive(Clone, Copy)]
+pub struct ByteRange(pub u8, pub u8);
+
+impl Parslet for ByteRange {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::ByteRange {
+            lo: self.0,
+            hi: self.1,
+        })
+    }
+}
+
+/// Consume input up to a terminator literal
+#[derive(Clone, Copy)]
+pub struct TakeUntil<'a> {
+    terminator: &'a str,
+    inclusive: bool,
+    or_end: bool,
+}
+
+impl<'a> TakeUntil<'a> {
+    /// Match the rest of the input when the terminator never occurs,
+    /// instead of failing
+    pub fn or_end(mut self) -> Self {
+        self.or_end = true;
+        self
+    }
+}
+
+impl<'a> Parslet for TakeUntil<'a> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::Until {
+            terminator: self.terminator.to_string(),
+            inclusive: self.inclusive,
+            or_end: self.or_end,
+        })
+    }
+}
+
+/// Match any single character
+#[derive(Clone, Copy, Default)]
+pub struct Any;
+
+impl Parslet for Any {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::Re {
+            pattern: ".".to_string(),
+        })
+    }
+}
+
+/// A forward reference to a named rule (for recursive grammars)
+#[derive(Clone, Copy)]
+pub struct Ref<'a>(pub &'a str);
+
+/// A rule declared with [`GrammarBuilder::declare`]
+///
+/// Used as a parslet, it refers to the rule, whether or not it has been
+/// defined yet. A handle only belongs to the builder that issued it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleHandle {
+    /// Index of the entity atom referring to the rule
+    atom: usize,
+}
+
+impl Parslet for RuleHandle {
+    fn build(self, _builder: &mut GrammarBuilder) -> usize {
+        self.atom
+    }
+}
+
+impl<'a> Parslet for Ref<'a> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let atom_idx = builder.add_atom(Atom::Entity { atom: 0 }); // Placeholder
+        builder.add_forward_ref(atom_idx, self.0.to_string());
+        atom_idx
+    }
+}
+
+/// Instantiation of a rule template (see [`GrammarBuilder::rule_template`])
+pub struct Instantiate<'a, P> {
+    template: &'a str,
+    args: Vec<P>,
+}
+
+impl<'a, P: Parslet> Parslet for Instantiate<'a, P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let args = self
+            .args
+            .into_iter()
+            .map(|arg| arg.build(builder))
+            .collect();
+        builder.add_atom(Atom::Instantiate {
+            template: self.template.to_string(),
+            args,
+        })
+    }
+}
+
+/// Sequence of two parslets (A >> B matches A then B)
+#[derive(Clone, Copy)]
+pub struct Sequence2<A, B> {
+    first: A,
     second: B,
 }
 
@@ -595,6 +1505,37 @@ pub struct Repeat<P> {
     max: Option<usize>,
 }
 
+impl<P> Repeat<P> {
+    /// Stop repeating as soon as `follow` matches
+    ///
+    /// Once the minimum count is reached, `follow` is tried (without
+    /// consuming it) before every further repetition, so the repetition
+    /// matches as little as possible instead of as much as possible. The
+    /// repetition fails if the maximum is reached or the inner parslet
+    /// fails before `follow` matches (see [`Atom::RepetitionLazy`]).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// // A greedy `any().many()` would swallow the closing "end"
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule("block", any().many().lazy(str("end")).then(str("end")))
+    ///     .build();
+    ///
+    /// assert!(grammar.parse("abcend").is_ok());
+    /// ```
+    pub fn lazy<F: Parslet>(self, follow: F) -> LazyRepeat<P, F> {
+        LazyRepeat {
+            inner: self.inner,
+            min: self.min,
+            max: self.max,
+            follow,
+        }
+    }
+}
+
 impl<P: Parslet> Parslet for Repeat<P> {
     fn build(self, builder: &mut GrammarBuilder) -> usize {
         let inner_idx = self.inner.build(builder);
@@ -606,6 +1547,28 @@ impl<P: Parslet> Parslet for Repeat<P> {
     }
 }
 
+/// Lazy repetition (A.many().lazy(B) matches A until B would match)
+#[derive(Clone, Copy)]
+pub struct LazyRepeat<P, F> {
+    inner: P,
+    min: usize,
+    max: Option<usize>,
+    follow: F,
+}
+
+impl<P: Parslet, F: Parslet> Parslet for LazyRepeat<P, F> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let inner_idx = self.inner.build(builder);
+        let follow_idx = self.follow.build(builder);
+        builder.add_atom(Atom::RepetitionLazy {
+            atom: inner_idx,
+            min: self.min,
+            max: self.max,
+            follow: follow_idx,
+        })
+    }
+}
+
 /// Named capture (A.label("name") captures A as "name")
 #[derive(Clone, Copy)]
 pub struct Named<'a, P> {
@@ -640,6 +1603,19 @@ impl<P: Parslet> Parslet for Lookahead<P> {
     }
 }
 
+/// Value-keeping lookahead (A.peek() returns A's value without consuming)
+#[derive(Clone, Copy)]
+pub struct Peek<P> {
+    inner: P,
+}
+
+impl<P: Parslet> Parslet for Peek<P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let inner_idx = self.inner.build(builder);
+        builder.add_atom(Atom::Peek { atom: inner_idx })
+    }
+}
+
 /// Cut operator (commit to this branch, prevent backtracking)
 #[derive(Clone, Copy, Default)]
 pub struct Cut;
@@ -650,6 +1626,46 @@ impl Parslet for Cut {
     }
 }
 
+/// End of input
+#[derive(Clone, Copy, Default)]
+pub struct Eof;
+
+impl Parslet for Eof {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::Eof)
+    }
+}
+
+/// Open an indented block (see [`Atom::Indent`])
+#[derive(Clone, Copy, Default)]
+pub struct Indent;
+
+impl Parslet for Indent {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::Indent)
+    }
+}
+
+/// Continue the current indented block (see [`Atom::Samedent`])
+#[derive(Clone, Copy, Default)]
+pub struct Samedent;
+
+impl Parslet for Samedent {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::Samedent)
+    }
+}
+
+/// Close the current indented block (see [`Atom::Dedent`])
+#[derive(Clone, Copy, Default)]
+pub struct Dedent;
+
+impl Parslet for Dedent {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::Dedent)
+    }
+}
+
 /// A type-erased parslet (for heterogeneous sequences/choices)
 pub struct ErasedParslet(Box<dyn DynParslet>);
 
@@ -686,6 +1702,40 @@ impl<P: Parslet> Parslet for Sequence<P> {
     }
 }
 
+/// A sequence that skips whitespace after each item
+///
+/// Created by [`spaced_seq`].
+pub struct SpacedSeq<P>(pub Vec<P>);
+
+impl<P: Parslet> Parslet for SpacedSeq<P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let mut indices = Vec::with_capacity(self.0.len() * 2);
+        for item in self.0 {
+            indices.push(item.build(builder));
+            indices.push(builder.whitespace_atom());
+        }
+        builder.add_atom(Atom::Sequence { atoms: indices })
+    }
+}
+
+/// A token followed by ignored whitespace
+///
+/// Created by [`lexeme`] and [`ParsletExt::lexeme`].
+#[derive(Clone, Copy)]
+pub struct Lexeme<P> {
+    inner: P,
+}
+
+impl<P: Parslet> Parslet for Lexeme<P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let inner_idx = self.inner.build(builder);
+        let whitespace = builder.whitespace_atom();
+        builder.add_atom(Atom::Sequence {
+            atoms: vec![inner_idx, whitespace],
+        })
+    }
+}
+
 /// A choice of multiple parslets
 pub struct Choice<P>(pub Vec<P>);
 
@@ -696,6 +1746,16 @@ impl<P: Parslet> Parslet for Choice<P> {
     }
 }
 
+/// A choice of multiple parslets that keeps the longest match
+pub struct LongestChoice<P>(pub Vec<P>);
+
+impl<P: Parslet> Parslet for LongestChoice<P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let indices: Vec<usize> = self.0.into_iter().map(|p| p.build(builder)).collect();
+        builder.add_atom(Atom::LongestAlternative { atoms: indices })
+    }
+}
+
 /// Capture parslet - stores matched text with a name
 pub struct Capture<'a, P: Parslet> {
     name: &'a str,
@@ -719,6 +1779,57 @@ impl<'a, P: Parslet> Parslet for Capture<'a, P> {
     }
 }
 
+/// Back-reference parslet - matches the text of a previous named capture
+#[derive(Clone, Copy)]
+pub struct BackRef<'a>(pub &'a str);
+
+impl<'a> Parslet for BackRef<'a> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        builder.add_atom(Atom::BackRef {
+            name: self.0.to_string(),
+        })
+    }
+}
+
+/// Optional named parslet that stores a default value when absent
+pub struct OptionalDefault<'a, P: Parslet> {
+    name: &'a str,
+    inner: P,
+    default: DefaultValue,
+}
+
+impl<'a, P: Parslet> OptionalDefault<'a, P> {
+    /// Create a new optional-with-default parslet
+    ///
+    /// # Panics
+    ///
+    /// Panics if `default` references an arena pool (see [`DefaultValue`]).
+    pub fn new(name: &'a str, inner: P, default: AstNode) -> Self {
+        let default = DefaultValue::from_ast(&default).unwrap_or_else(|| {
+            panic!(
+                "optional_with_default({:?}): default must be Nil, Bool, Int or Float, got {:?}",
+                name, default
+            )
+        });
+        Self {
+            name,
+            inner,
+            default,
+        }
+    }
+}
+
+impl<'a, P: Parslet> Parslet for OptionalDefault<'a, P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let inner_idx = self.inner.build(builder);
+        builder.add_atom(Atom::OptionalDefault {
+            name: self.name.to_string(),
+            atom: inner_idx,
+            default: self.default,
+        })
+    }
+}
+
 /// Scope parslet - creates an isolated capture scope
 pub struct Scope<P: Parslet> {
     inner: P,
@@ -806,6 +1917,40 @@ pub trait ParsletExt: Parslet + Sized {
         }
     }
 
+    /// Match exactly `n` times
+    fn exactly(self, n: usize) -> Repeat<Self> {
+        self.repeat(n, Some(n))
+    }
+
+    /// Match `n` or more times
+    fn at_least(self, n: usize) -> Repeat<Self> {
+        self.repeat(n, None)
+    }
+
+    /// Match at most `n` times
+    fn at_most(self, n: usize) -> Repeat<Self> {
+        self.repeat(0, Some(n))
+    }
+
+    /// Match between `min` and `max` times, inclusive
+    fn between(self, min: usize, max: usize) -> Repeat<Self> {
+        self.repeat(min, Some(max))
+    }
+
+    /// Match between `min` and `max` times with `sep` between matches
+    ///
+    /// Like [`sep_by`], separators are ignored and `max: None` is
+    /// unbounded; `min` and `max` count items, not separators.
+    fn repeat_sep<S: Parslet>(self, min: usize, max: Option<usize>, sep: S) -> SepBy<Self, S> {
+        SepBy {
+            item: self,
+            sep,
+            min,
+            max,
+            allow_trailing: false,
+        }
+    }
+
     /// Positive lookahead (must match, doesn't consume)
     fn lookahead(self) -> Lookahead<Self> {
         Lookahead {
@@ -822,6 +1967,14 @@ pub trait ParsletExt: Parslet + Sized {
         }
     }
 
+    /// Positive lookahead that keeps the value (doesn't consume input)
+    ///
+    /// Unlike [`lookahead`](Self::lookahead), the result is the inner
+    /// parslet's value rather than `Nil`.
+    fn peek(self) -> Peek<Self> {
+        Peek { inner: self }
+    }
+
     /// Sequence: A >> B
     fn then<B: Parslet>(self, other: B) -> Sequence2<Self, B> {
         Sequence2 {
@@ -843,6 +1996,133 @@ pub trait ParsletExt: Parslet + Sized {
     fn ignore(self) -> Ignore<Self> {
         Ignore { inner: self }
     }
+
+    /// Return the text this parslet matched as one string
+    /// (`AstNode::StringRef`), discarding the inner structure
+    ///
+    /// Handy for tokens built from repetitions, where the inner value would
+    /// otherwise be an array of single-character matches.
+    fn text(self) -> Text<Self> {
+        Text { inner: self }
+    }
+
+    /// Parse the matched text as an `i64`, producing `AstNode::Int`
+    ///
+    /// Overflow and malformed text fail with `ParseError::InvalidNumber`,
+    /// which aborts the parse even inside a choice or repetition. Call
+    /// [`with_radix_prefixes`](AsInt::with_radix_prefixes) to accept `0x`,
+    /// `0o` and `0b` literals.
+    #[allow(clippy::wrong_self_convention)]
+    fn as_int(self) -> AsInt<Self> {
+        AsInt {
+            inner: self,
+            radix_prefixes: false,
+        }
+    }
+
+    /// Parse the matched text as an `f64`, producing `AstNode::Float`
+    #[allow(clippy::wrong_self_convention)]
+    fn as_float(self) -> AsFloat<Self> {
+        AsFloat { inner: self }
+    }
+
+    /// Produce `value` when this parslet matches, instead of its own result
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` references an arena pool (see [`DefaultValue`]).
+    fn value(self, value: AstNode) -> Const<Self> {
+        Const::new(self, value)
+    }
+
+    /// Produce `AstNode::Bool(value)` when this parslet matches
+    ///
+    /// ```rust
+    /// use parsanol::portable::parser_dsl::*;
+    ///
+    /// let boolean = str("true").value_bool(true).or(str("false").value_bool(false));
+    /// ```
+    fn value_bool(self, value: bool) -> Const<Self> {
+        Const::new(self, AstNode::Bool(value))
+    }
+
+    /// Skip whitespace after this parslet (see [`lexeme`])
+    fn lexeme(self) -> Lexeme<Self> {
+        Lexeme { inner: self }
+    }
+
+    /// Attach a semantic action applied to this parslet's result
+    ///
+    /// The action runs in [`SemanticActions::apply`], after parsing, on the
+    /// converted [`Value`]. Build with
+    /// [`GrammarBuilder::build_with_actions`] to keep the actions.
+    fn map<F>(self, action: F) -> Map<Self, F>
+    where
+        F: Fn(Value) -> Value + Send + Sync + 'static,
+    {
+        Map {
+            inner: self,
+            action,
+        }
+    }
+}
+
+/// Separated list: `item (sep item)*`, with separators ignored
+///
+/// Created by [`sep_by`], [`sep_by1`] and [`ParsletExt::repeat_sep`].
+#[derive(Clone, Copy)]
+pub struct SepBy<P, S> {
+    item: P,
+    sep: S,
+    min: usize,
+    max: Option<usize>,
+    allow_trailing: bool,
+}
+
+impl<P, S> SepBy<P, S> {
+    /// Also accept a single separator after the last item
+    pub fn allow_trailing(mut self) -> Self {
+        self.allow_trailing = true;
+        self
+    }
+}
+
+impl<P: Parslet, S: Parslet> Parslet for SepBy<P, S> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let item = self.item.build(builder);
+        let sep_atom = self.sep.build(builder);
+        let sep = builder.add_atom(Atom::Ignore { atom: sep_atom });
+
+        let sep_item = builder.add_atom(Atom::Sequence {
+            atoms: vec![sep, item],
+        });
+        // The first item is matched outside the repetition
+        let rest = builder.add_atom(Atom::Repetition {
+            atom: sep_item,
+            min: self.min.saturating_sub(1),
+            max: self.max.map(|max| max.saturating_sub(1)),
+        });
+        let mut atoms = vec![item, rest];
+        if self.allow_trailing {
+            let trailing = builder.add_atom(Atom::Repetition {
+                atom: sep_atom,
+                min: 0,
+                max: Some(1),
+            });
+            atoms.push(builder.add_atom(Atom::Ignore { atom: trailing }));
+        }
+        let list = builder.add_atom(Atom::Sequence { atoms });
+
+        if self.min == 0 {
+            builder.add_atom(Atom::Repetition {
+                atom: list,
+                min: 0,
+                max: Some(if self.max == Some(0) { 0 } else { 1 }),
+            })
+        } else {
+            list
+        }
+    }
 }
 
 /// Ignore wrapper - matches but discards the result
@@ -858,13 +2138,195 @@ impl<P: Parslet> Parslet for Ignore<P> {
     }
 }
 
-impl<T: Parslet + Sized> ParsletExt for T {}
+/// Matched-text wrapper, created by [`ParsletExt::text`]
+#[derive(Clone, Copy)]
+pub struct Text<P> {
+    inner: P,
+}
 
-// ============================================================================
-// Operator Overloading (>> for sequence, | for alternative)
-// ============================================================================
+impl<P: Parslet> Parslet for Text<P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let inner_idx = self.inner.build(builder);
+        builder.add_atom(Atom::Text { atom: inner_idx })
+    }
+}
 
-use std::ops::{BitOr, Shr};
+/// Integer conversion wrapper, created by [`ParsletExt::as_int`]
+#[derive(Clone, Copy)]
+pub struct AsInt<P> {
+    inner: P,
+    radix_prefixes: bool,
+}
+
+impl<P> AsInt<P> {
+    /// Also accept `0x` (hex), `0o` (octal) and `0b` (binary) prefixes
+    pub fn with_radix_prefixes(mut self) -> Self {
+        self.radix_prefixes = true;
+        self
+    }
+}
+
+impl<P: Parslet> Parslet for AsInt<P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let inner_idx = self.inner.build(builder);
+        builder.add_atom(Atom::ParseInt {
+            atom: inner_idx,
+            radix_prefixes: self.radix_prefixes,
+        })
+    }
+}
+
+/// Float conversion wrapper, created by [`ParsletExt::as_float`]
+#[derive(Clone, Copy)]
+pub struct AsFloat<P> {
+    inner: P,
+}
+
+impl<P: Parslet> Parslet for AsFloat<P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let inner_idx = self.inner.build(builder);
+        builder.add_atom(Atom::ParseFloat { atom: inner_idx })
+    }
+}
+
+/// Constant-value wrapper, created by [`ParsletExt::value`] and the
+/// `const_*` functions
+#[derive(Clone, Copy)]
+pub struct Const<P> {
+    inner: P,
+    value: DefaultValue,
+}
+
+impl<P> Const<P> {
+    /// Create a parslet producing `value` when `inner` matches
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` references an arena pool (see [`DefaultValue`]).
+    pub fn new(inner: P, value: AstNode) -> Self {
+        let value = DefaultValue::from_ast(&value).unwrap_or_else(|| {
+            panic!(
+                "value(): constant must be Nil, Bool, Int or Float, got {:?}",
+                value
+            )
+        });
+        Self { inner, value }
+    }
+}
+
+impl<P: Parslet> Parslet for Const<P> {
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let inner_idx = self.inner.build(builder);
+        builder.add_atom(Atom::Const {
+            atom: inner_idx,
+            value: self.value,
+        })
+    }
+}
+
+/// Semantic action wrapper, created by [`ParsletExt::map`]
+#[derive(Clone, Copy)]
+pub struct Map<P, F> {
+    inner: P,
+    action: F,
+}
+
+impl<P, F> Parslet for Map<P, F>
+where
+    P: Parslet,
+    F: Fn(Value) -> Value + Send + Sync + 'static,
+{
+    fn build(self, builder: &mut GrammarBuilder) -> usize {
+        let inner_idx = self.inner.build(builder);
+        let name = format!("{}{}", MAP_LABEL_PREFIX, builder.actions.len());
+        let idx = builder.add_atom(Atom::Named {
+            name,
+            atom: inner_idx,
+        });
+        builder.actions.insert(idx, Arc::new(self.action));
+        idx
+    }
+}
+
+/// Semantic actions collected by [`GrammarBuilder::build_with_actions`]
+///
+/// Kept apart from [`Grammar`] because closures can't be serialized.
+#[derive(Clone, Default)]
+pub struct SemanticActions {
+    /// Marker label -> action
+    actions: HashMap<String, MapFn>,
+}
+
+impl SemanticActions {
+    /// Number of registered actions
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// Whether no actions are registered
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Apply the actions to a converted parse result, bottom-up
+    ///
+    /// Children are transformed before their parents, so an action sees
+    /// the already-mapped values of any mapped parslets nested inside it.
+    /// A marker hash `{ "@map:N" => value }` is replaced by the action's
+    /// result. When a sequence merged the marker with labeled siblings, a
+    /// hash result is merged into the surrounding hash and any other result
+    /// stays under the marker key; label the mapped parslet to give it a
+    /// proper key.
+    pub fn apply(&self, value: Value) -> Value {
+        match value {
+            Value::Array(items) => Value::Array(items.into_iter().map(|v| self.apply(v)).collect()),
+            Value::Hash(map) => {
+                let mut out = HashMap::with_capacity(map.len());
+                let mut mapped = Vec::new();
+                for (key, value) in map {
+                    let value = self.apply(value);
+                    match self.actions.get(&key) {
+                        Some(action) => mapped.push((key, action(value))),
+                        None => {
+                            out.insert(key, value);
+                        }
+                    }
+                }
+                if out.is_empty() && mapped.len() == 1 {
+                    return mapped.pop().map(|(_, value)| value).unwrap_or_default();
+                }
+                for (key, value) in mapped {
+                    match value {
+                        Value::Hash(entries) => out.extend(entries),
+                        other => {
+                            out.insert(key, other);
+                        }
+                    }
+                }
+                Value::Hash(out)
+            }
+            other => other,
+        }
+    }
+}
+
+impl std::fmt::Debug for SemanticActions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut labels: Vec<_> = self.actions.keys().collect();
+        labels.sort();
+        f.debug_struct("SemanticActions")
+            .field("labels", &labels)
+            .finish()
+    }
+}
+
+impl<T: Parslet + Sized> ParsletExt for T {}
+
+// ============================================================================
+// Operator Overloading (>> for sequence, | for alternative)
+// ============================================================================
+
+use std::ops::{BitOr, Shr};
 
 // Shr (>>) for Sequence2 + third item -> Sequence3
 impl<A: Parslet, B: Parslet, C: Parslet> Shr<C> for Sequence2<A, B> {
@@ -955,11 +2417,158 @@ pub fn str(s: &str) -> Str<'_> {
     Str(s)
 }
 
+/// Match a literal string, ignoring case
+///
+/// The matched node references the original input, so transforms see the
+/// source spelling (e.g. `Select`), not the pattern.
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// let grammar = GrammarBuilder::new().rule("kw", stri("select")).build();
+/// assert!(grammar.parse("SeLeCt").is_ok());
+/// ```
+pub fn stri(s: &str) -> StrCi<'_> {
+    StrCi(s)
+}
+
 /// Match a regular expression
 pub fn re(pattern: &str) -> Re<'_> {
     Re(pattern)
 }
 
+/// Match a single character between `lo` and `hi`, inclusive
+///
+/// Unlike `re("[a-z]")` this never touches the regex engine, and works for
+/// any Unicode range.
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// let grammar = GrammarBuilder::new()
+///     .rule("greek", char_range('α', 'ω').many1())
+///     .build();
+/// assert!(grammar.parse("λογος").is_ok());
+/// assert!(grammar.parse("logos").is_err());
+/// ```
+pub fn char_range(lo: char, hi: char) -> CharRange {
+    CharRange(lo, hi)
+}
+
+/// Match a single character that is one of `chars`
+///
+/// Like `char_range`, this never touches the regex engine.
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// let grammar = GrammarBuilder::new().rule("sign", one_of(&['+', '-'])).build();
+/// assert!(grammar.parse("-").is_ok());
+/// assert!(grammar.parse("*").is_err());
+/// ```
+pub fn one_of(chars: &[char]) -> OneOf<'_> {
+    OneOf(chars)
+}
+
+/// Match a single character that is not one of `chars`
+///
+/// The regex-free equivalent of `re("[^...]")`: matches exactly one
+/// character of any width, and fails at end of input.
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// // A double-quoted string without escapes
+/// let grammar = GrammarBuilder::new()
+///     .rule("string", str("\"").then(none_of(&['"']).many()).then(str("\"")))
+///     .build();
+/// assert!(grammar.parse("\"héllo\"").is_ok());
+/// ```
+pub fn none_of(chars: &[char]) -> NoneOf<'_> {
+    NoneOf(chars)
+}
+
+/// Consume input up to, but not including, the next `terminator`
+///
+/// The consumed text (possibly empty) is the result. Fails if the
+/// terminator never occurs, unless [`or_end`](TakeUntil::or_end) is set.
+/// A plain substring search, so much simpler and faster than a
+/// negative-lookahead regex such as `re("(?:[^*]|\\*[^/])*")`.
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// let grammar = GrammarBuilder::new()
+///     .rule("line", take_until("\n").then(str("\n")))
+///     .build();
+/// assert!(grammar.parse("hello\n").is_ok());
+/// assert!(grammar.parse("hello").is_err());
+/// ```
+pub fn take_until(terminator: &str) -> TakeUntil<'_> {
+    TakeUntil {
+        terminator,
+        inclusive: false,
+        or_end: false,
+    }
+}
+
+/// Consume input up to and including the next `terminator`
+///
+/// Like [`take_until`], but the terminator is consumed as well. It is not
+/// part of the result, which is only the text before it.
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// let grammar = GrammarBuilder::new()
+///     .rule("comment", str("/*").then(take_until_inclusive("*/")))
+///     .build();
+/// assert!(grammar.parse("/* a * b */").is_ok());
+/// assert!(grammar.parse("/* open").is_err());
+/// ```
+pub fn take_until_inclusive(terminator: &str) -> TakeUntil<'_> {
+    TakeUntil {
+        terminator,
+        inclusive: true,
+        or_end: false,
+    }
+}
+
+/// Match exactly the byte `b`
+///
+/// For binary input parsed with
+/// [`PortableParserBytes`](super::parser::PortableParserBytes); on `&str`
+/// input only ASCII bytes match.
+pub fn byte(b: u8) -> ByteRange {
+    ByteRange(b, b)
+}
+
+/// Match a single byte between `lo` and `hi` inclusive
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+/// use parsanol::portable::{AstArena, PortableParserBytes};
+///
+/// let grammar = GrammarBuilder::new()
+///     .rule("high", byte_range(0x80, 0xff).many1())
+///     .build();
+/// let mut arena = AstArena::new();
+/// let mut parser = PortableParserBytes::new(&grammar, &[0x80, 0xfe], &mut arena);
+/// assert!(parser.parse().is_ok());
+/// ```
+pub fn byte_range(lo: u8, hi: u8) -> ByteRange {
+    ByteRange(lo, hi)
+}
+
+/// Match any single byte
+pub fn any_byte() -> ByteRange {
+    ByteRange(0x00, 0xff)
+}
+
 /// Match any single character
 pub fn any() -> Any {
     Any
@@ -975,6 +2584,81 @@ pub fn cut() -> Cut {
     Cut
 }
 
+/// Match the end of input, consuming nothing
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// // "a" is only accepted as the last thing in the input
+/// let grammar = GrammarBuilder::new()
+///     .rule("word", str("a").then(eof()).or(str("ab")))
+///     .build();
+/// assert!(grammar.parse("a").is_ok());
+/// assert!(grammar.parse("ab").is_ok());
+/// ```
+pub fn eof() -> Eof {
+    Eof
+}
+
+/// Open an indented block, consuming nothing
+///
+/// Succeeds when only blanks precede the current position on its line and
+/// its column is deeper than the enclosing block's. Pair it with
+/// [`samedent`] between the block's lines and [`dedent`] at its end.
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// // "name:" followed by lines indented deeper than it
+/// let blank = one_of(&[' ', '\t']).many();
+/// let line = str("\n").then(blank);
+/// let grammar = GrammarBuilder::new()
+///     .rule(
+///         "block",
+///         str("name:")
+///             .then(line.clone())
+///             .then(indent())
+///             .then(str("x"))
+///             .then(line.then(samedent()).then(str("x")).many())
+///             .then(dedent()),
+///     )
+///     .build();
+/// assert!(grammar.parse("name:\n  x\n  x").is_ok());
+/// assert!(grammar.parse("name:\n  x\n   x").is_err());
+/// assert!(grammar.parse("name:\nx").is_err());
+/// ```
+pub fn indent() -> Indent {
+    Indent
+}
+
+/// Match a line at the current block's indentation, consuming nothing
+pub fn samedent() -> Samedent {
+    Samedent
+}
+
+/// Close the innermost indented block, consuming nothing
+pub fn dedent() -> Dedent {
+    Dedent
+}
+
+/// Instantiate a rule template with concrete arguments
+///
+/// Arguments are matched to the template's parameters by position. Use
+/// [`dynamic`] to pass parslets of different types.
+pub fn instantiate<'a, I, P>(template: &'a str, args: I) -> Instantiate<'a, P>
+where
+    I: IntoIterator<Item = P>,
+{
+    Instantiate {
+        template,
+        args: args.into_iter().collect(),
+    }
+}
+
 /// Create a sequence from multiple parslets
 pub fn seq<I, P>(items: I) -> Sequence<P>
 where
@@ -983,6 +2667,45 @@ where
     Sequence(items.into_iter().collect())
 }
 
+/// Create a sequence that skips whitespace after each item
+///
+/// Whitespace matching [`GrammarBuilder::with_whitespace`] is allowed, but
+/// not required, between (and after) the items, and is left out of the
+/// result. All spaced sequences of a grammar share one whitespace atom.
+/// Leading whitespace is not skipped.
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// let grammar = GrammarBuilder::new()
+///     .rule("assign", spaced_seq([str("let"), str("x"), str("=")]))
+///     .build();
+/// assert!(grammar.parse("let x =").is_ok());
+/// assert!(grammar.parse("let  x= ").is_ok());
+/// ```
+pub fn spaced_seq<I, P>(items: I) -> SpacedSeq<P>
+where
+    I: IntoIterator<Item = P>,
+{
+    SpacedSeq(items.into_iter().collect())
+}
+
+/// Match `inner` followed by optional, ignored whitespace
+///
+/// The building block for whitespace-insensitive grammars: wrap each
+/// token so the rules combining them don't have to mention whitespace.
+/// Uses the same whitespace atom as [`spaced_seq`].
+pub fn lexeme<P: Parslet>(inner: P) -> Lexeme<P> {
+    Lexeme { inner }
+}
+
+/// Alias for [`lexeme`]
+pub fn token<P: Parslet>(inner: P) -> Lexeme<P> {
+    lexeme(inner)
+}
+
 /// Create a choice from multiple parslets
 pub fn choice<I, P>(items: I) -> Choice<P>
 where
@@ -991,6 +2714,36 @@ where
     Choice(items.into_iter().collect())
 }
 
+/// Create a choice that tries every parslet and keeps the longest match
+///
+/// Ties go to the earliest parslet, so a keyword listed before the
+/// identifier rule wins only when it matches the whole word. Every
+/// alternative is tried at every position, so use [`choice`] when order
+/// alone disambiguates. Only the packrat backend supports it.
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// let token = || {
+///     longest_choice([
+///         dynamic(str("for").label("keyword")),
+///         dynamic(re("[a-z]").many1().label("ident")),
+///     ])
+/// };
+/// let grammar = GrammarBuilder::new().rule("token", token()).build();
+/// // An ordered choice would stop after "for" and fail on "mat"
+/// assert!(grammar.parse("format").is_ok());
+/// assert!(grammar.parse("for").is_ok());
+/// ```
+pub fn longest_choice<I, P>(items: I) -> LongestChoice<P>
+where
+    I: IntoIterator<Item = P>,
+{
+    LongestChoice(items.into_iter().collect())
+}
+
 // ============================================================================
 // Capture, Scope, and Dynamic Helpers
 // ============================================================================
@@ -1010,6 +2763,136 @@ pub fn capture<'a, P: Parslet>(name: &'a str, inner: P) -> Capture<'a, P> {
     Capture::new(name, inner)
 }
 
+/// Create a back-reference parslet
+///
+/// Matches exactly the text previously captured under `name`. Wrap the
+/// enclosing rule in [`scope()`] when it can nest, so inner captures don't
+/// clobber the outer one.
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// // <b>...</b> where the closing tag must repeat the opening one
+/// let grammar = GrammarBuilder::new()
+///     .rule(
+///         "element",
+///         str("<")
+///             .then(capture("tag", re("[a-z]").many1()))
+///             .then(str(">"))
+///             .then(re("[0-9]").many())
+///             .then(str("</"))
+///             >> backref("tag")
+///             >> str(">"),
+///     )
+///     .build();
+/// ```
+pub fn backref(name: &str) -> BackRef<'_> {
+    BackRef(name)
+}
+
+/// Create an optional named parslet with a default value
+///
+/// Behaves like `inner.optional().label(name)` except that the result is
+/// always `{ name => value }`: when `inner` doesn't match, `default` is
+/// stored under `name` instead.
+///
+/// # Panics
+///
+/// Panics if `default` isn't self-contained: only `Nil`, `Bool`, `Int` and
+/// `Float` nodes can be stored in a grammar.
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+/// use parsanol::portable::AstNode;
+///
+/// let grammar = GrammarBuilder::new()
+///     .rule(
+///         "number",
+///         optional_with_default("sign", str("-"), AstNode::Nil).then(re("[0-9]").many1()),
+///     )
+///     .build();
+/// ```
+pub fn optional_with_default<'a, P: Parslet>(
+    name: &'a str,
+    inner: P,
+    default: AstNode,
+) -> OptionalDefault<'a, P> {
+    OptionalDefault::new(name, inner, default)
+}
+
+/// Produce `AstNode::Nil` without consuming input
+pub fn const_nil() -> Const<Str<'static>> {
+    str("").value(AstNode::Nil)
+}
+
+/// Produce `AstNode::Bool(value)` without consuming input
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// // A missing sign reads as positive
+/// let positive = str("+").value_bool(true).or(str("-").value_bool(false)).or(const_bool(true));
+/// ```
+pub fn const_bool(value: bool) -> Const<Str<'static>> {
+    str("").value_bool(value)
+}
+
+/// Produce `AstNode::Int(value)` without consuming input
+pub fn const_int(value: i64) -> Const<Str<'static>> {
+    str("").value(AstNode::Int(value))
+}
+
+/// Produce `AstNode::Float(value)` without consuming input
+pub fn const_float(value: f64) -> Const<Str<'static>> {
+    str("").value(AstNode::Float(value))
+}
+
+/// Match zero or more `item`s separated by `sep`
+///
+/// Builds `(item (sep item)*)?` with the separators wrapped in `Ignore`, so
+/// only the items carry values. A trailing separator is rejected unless
+/// [`SepBy::allow_trailing`] is set.
+///
+/// # Example
+///
+/// ```rust
+/// use parsanol::portable::parser_dsl::*;
+///
+/// let grammar = GrammarBuilder::new()
+///     .rule("list", sep_by(re("[0-9]").many1(), str(",")).allow_trailing())
+///     .build();
+/// assert!(grammar.parse("1,2,3,").is_ok());
+/// assert!(grammar.parse("").is_ok());
+/// ```
+pub fn sep_by<P: Parslet, S: Parslet>(item: P, sep: S) -> SepBy<P, S> {
+    SepBy {
+        item,
+        sep,
+        min: 0,
+        max: None,
+        allow_trailing: false,
+    }
+}
+
+/// Match one or more `item`s separated by `sep`
+///
+/// Like [`sep_by`], but fails on empty input.
+pub fn sep_by1<P: Parslet, S: Parslet>(item: P, sep: S) -> SepBy<P, S> {
+    SepBy {
+        item,
+        sep,
+        min: 1,
+        max: None,
+        allow_trailing: false,
+    }
+}
+
 /// Create a scope parslet
 ///
 /// # Example
@@ -1,4 +1,5 @@
 use super::*;
+use crate::portable::ast::ParseError;
 
 #[test]
 fn test_simple_grammar() {
@@ -236,3 +237,504 @@ fn test_import_with_repetition() {
     // Repetition indices should be remapped
     assert!(combined.atom_count() >= repeat_grammar.atom_count());
 }
+
+fn bracket_grammar() -> Grammar {
+    GrammarBuilder::new()
+        .rule("group", ref_("braces").or(ref_("parens")))
+        .rule_template("block", &["open", "close"], |p| {
+            p[0].then(ref_("word")).then(p[1])
+        })
+        .rule("braces", instantiate("block", [str("{"), str("}")]))
+        .rule("parens", instantiate("block", [str("("), str(")")]))
+        .rule("word", re("[a-z]").many1())
+        .build()
+}
+
+#[test]
+fn test_rule_template_two_bracket_pairs() {
+    let grammar = bracket_grammar();
+
+    assert!(grammar.parse("{abc}").is_ok());
+    assert!(grammar.parse("(abc)").is_ok());
+    assert!(grammar.parse("{abc)").is_err());
+    assert!(grammar.parse("[abc]").is_err());
+
+    // Every instantiation was expanded
+    assert!(!grammar
+        .atoms
+        .iter()
+        .any(|atom| matches!(atom, Atom::Instantiate { .. })));
+}
+
+#[test]
+fn test_rule_template_nested_instantiation() {
+    let grammar = GrammarBuilder::new()
+        .rule("root", instantiate("twice", [str("ab")]))
+        .rule_template("pair", &["a", "b"], |p| p[0].then(p[1]))
+        .rule_template("twice", &["x"], |p| {
+            instantiate("pair", [dynamic(p[0]), dynamic(p[0])])
+        })
+        .build();
+
+    assert!(grammar.parse("abab").is_ok());
+    assert!(grammar.parse("ab").is_err());
+
+    // Nested instantiations are expanded and the template bodies dropped
+    assert!(!grammar
+        .atoms
+        .iter()
+        .any(|atom| matches!(atom, Atom::Instantiate { .. })));
+}
+
+#[test]
+fn test_rule_template_self_recursive() {
+    // nest(open, close) = open nest(open, close)* close
+    let grammar = GrammarBuilder::new()
+        .rule_template("nest", &["open", "close"], |p| {
+            p[0].then(instantiate("nest", [p[0], p[1]]).many())
+                .then(p[1])
+        })
+        .rule("parens", instantiate("nest", [str("("), str(")")]))
+        .build();
+
+    assert!(grammar.parse("()").is_ok());
+    assert!(grammar.parse("(()(()))").is_ok());
+    assert!(grammar.parse("(()").is_err());
+
+    // One shared copy: the recursion is an ordinary entity cycle
+    assert!(!grammar
+        .atoms
+        .iter()
+        .any(|atom| matches!(atom, Atom::Instantiate { .. })));
+    assert!(grammar.atom_count() < 20);
+
+    // The template was declared first, but its body isn't left behind as a
+    // self-referencing placeholder that looks left-recursive
+    assert!(grammar.left_recursion().is_none());
+}
+
+#[test]
+fn test_unresolved_instantiation_is_invalid_grammar() {
+    let grammar = GrammarBuilder::new()
+        .rule("root", instantiate("missing", [str("x")]))
+        .build();
+
+    assert!(matches!(
+        grammar.parse("x"),
+        Err(ParseError::InvalidGrammar { .. })
+    ));
+}
+
+fn sep_by_items(grammar: &Grammar, input: &str) -> Option<Vec<String>> {
+    use crate::portable::arena::AstArena;
+    use crate::portable::parser::PortableParser;
+    use crate::portable::to_parslet_compatible;
+    use crate::portable::transform::{ast_to_value, Value};
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(grammar, input, &mut arena);
+    let ast = parser.parse().ok()?;
+    let ast = to_parslet_compatible(&ast, &mut arena, input);
+    match ast_to_value(&ast, &arena, input) {
+        Value::Array(items) => Some(
+            items
+                .iter()
+                .map(|item| item.get("n").and_then(|n| n.as_str()).unwrap().to_string())
+                .collect(),
+        ),
+        Value::Nil => Some(Vec::new()),
+        other => panic!(
+            "expected an array of items, got {:?} for {:?}",
+            other, input
+        ),
+    }
+}
+
+#[test]
+fn test_sep_by_discards_separators() {
+    let grammar = GrammarBuilder::new()
+        .rule("list", sep_by1(re("[0-9]").many1().label("n"), str(",")))
+        .build();
+
+    assert_eq!(
+        sep_by_items(&grammar, "1,2,3"),
+        Some(vec!["1".to_string(), "2".to_string(), "3".to_string()])
+    );
+    assert_eq!(sep_by_items(&grammar, "1,2,3,"), None);
+    assert_eq!(sep_by_items(&grammar, ""), None);
+}
+
+#[test]
+fn test_sep_by_allow_trailing() {
+    let grammar = GrammarBuilder::new()
+        .rule(
+            "list",
+            sep_by1(re("[0-9]").many1().label("n"), str(",")).allow_trailing(),
+        )
+        .build();
+
+    let expected = Some(vec!["1".to_string(), "2".to_string(), "3".to_string()]);
+    assert_eq!(sep_by_items(&grammar, "1,2,3"), expected);
+    assert_eq!(sep_by_items(&grammar, "1,2,3,"), expected);
+    assert_eq!(sep_by_items(&grammar, "1,2,3,,"), None);
+}
+
+#[test]
+fn test_sep_by_accepts_empty_input() {
+    let grammar = GrammarBuilder::new()
+        .rule("list", sep_by(re("[0-9]").many1().label("n"), str(",")))
+        .build();
+
+    assert!(grammar.parse("").is_ok());
+    assert!(grammar.parse("7").is_ok());
+    assert!(grammar.parse(",").is_err());
+}
+
+fn mapped_number() -> impl Parslet {
+    re("[0-9]")
+        .many1()
+        .map(|v| Value::Int(v.as_str().and_then(|s| s.parse().ok()).unwrap_or(0)))
+}
+
+#[test]
+fn test_map_applies_actions_bottom_up() {
+    use crate::portable::{AstArena, PortableParser};
+
+    let sum = mapped_number().label("left").then(str("+")) >> mapped_number().label("right");
+    let sum = sum.map(|v| {
+        let operand = |key| v.get(key).and_then(Value::as_int).unwrap_or(0);
+        Value::Int(operand("left") + operand("right"))
+    });
+    let (grammar, actions) = GrammarBuilder::new().rule("sum", sum).build_with_actions();
+    assert_eq!(actions.len(), 3);
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "12+30", &mut arena);
+    assert_eq!(
+        parser.parse_and_transform(&actions).unwrap(),
+        Value::Int(42)
+    );
+
+    let mut parser = PortableParser::new(&grammar, "12-30", &mut arena);
+    assert!(parser.parse_and_transform(&actions).is_err());
+}
+
+#[test]
+fn test_map_without_actions_keeps_marker_labels() {
+    use crate::portable::{AstArena, PortableParser};
+
+    let grammar = GrammarBuilder::new()
+        .rule("number", mapped_number())
+        .build();
+
+    let mut arena = AstArena::new();
+    let mut parser = PortableParser::new(&grammar, "7", &mut arena);
+    let value = parser
+        .parse_and_transform(&SemanticActions::default())
+        .unwrap();
+    let key = format!("{}0", MAP_LABEL_PREFIX);
+    assert_eq!(value.get(&key), Some(&Value::string("7")));
+}
+
+#[test]
+fn test_spaced_seq_skips_optional_whitespace() {
+    let grammar = GrammarBuilder::new()
+        .rule("pair", spaced_seq([str("a"), str("b")]))
+        .build();
+
+    assert!(grammar.parse("a   b").is_ok());
+    assert!(grammar.parse("ab").is_ok());
+    assert!(grammar.parse("a\n\tb ").is_ok());
+    assert!(grammar.parse("a c").is_err());
+}
+
+#[test]
+fn test_whitespace_atom_is_shared() {
+    let grammar = GrammarBuilder::new()
+        .with_whitespace("[ \t]")
+        .rule("pair", spaced_seq([ref_("word"), ref_("word")]))
+        .rule("word", lexeme(re("[a-z]").many1()))
+        .rule("tail", str(";").lexeme())
+        .build();
+
+    let ignored = grammar
+        .atoms
+        .iter()
+        .filter(|atom| matches!(atom, Atom::Ignore { .. }))
+        .count();
+    assert_eq!(ignored, 1);
+    assert!(grammar.parse("ab \t cd").is_ok());
+    assert!(grammar.parse("ab\ncd").is_err());
+}
+
+#[test]
+fn test_whitespace_atom_in_template_is_not_shared_outside() {
+    // The template body builds the whitespace atom first; rules defined
+    // afterwards must get their own copy
+    let grammar = GrammarBuilder::new()
+        .rule_template("parens", &["item"], |p| {
+            spaced_seq([dynamic(str("(")), dynamic(p[0]), dynamic(str(")"))])
+        })
+        .rule("root", ref_("group").then(spaced_seq([str(";")])))
+        .rule("group", instantiate("parens", [str("x")]))
+        .build();
+
+    assert!(grammar.parse("( x ) ;").is_ok());
+    assert!(grammar.parse("(x);").is_ok());
+}
+
+#[test]
+fn test_build_checked_reports_undefined_rule() {
+    let errors = GrammarBuilder::new()
+        .rule(
+            "list",
+            ref_("item").then(ref_("missing")).then(ref_("missing")),
+        )
+        .rule("item", str("x"))
+        .build_checked()
+        .unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![GrammarError::UnresolvedRule {
+            name: "missing".to_string()
+        }]
+    );
+    assert!(errors[0].to_string().contains("missing"));
+}
+
+#[test]
+fn test_declared_rule_must_be_defined() {
+    let mut builder = GrammarBuilder::new();
+    let expr = builder.declare("expr");
+    let atom = builder.declare("atom");
+    builder.define(expr, atom.then(str("+").then(expr).optional()));
+    // `atom` is never defined
+
+    assert_eq!(
+        builder.build_checked().unwrap_err(),
+        vec![GrammarError::UnresolvedRule {
+            name: "atom".to_string()
+        }]
+    );
+
+    let mut builder = GrammarBuilder::new();
+    let expr = builder.declare("expr");
+    let atom = builder.declare("atom");
+    builder.define(expr, atom.then(str("+").then(expr).optional()));
+    builder.define(
+        atom,
+        re("[0-9]").or(str("(").then(ref_("expr")).then(str(")"))),
+    );
+    let grammar = builder.build_checked().unwrap();
+    assert!(grammar.parse("1+(2+3)").is_ok());
+    assert!(grammar.parse("1+").is_err());
+}
+
+#[test]
+fn test_build_checked_reports_bad_indices() {
+    let mut builder = GrammarBuilder::new();
+    builder.add_atom(Atom::Repetition {
+        atom: 7,
+        min: 0,
+        max: None,
+    });
+    builder.add_atom(Atom::Instantiate {
+        template: "nope".to_string(),
+        args: vec![],
+    });
+
+    assert_eq!(
+        builder.build_checked().unwrap_err(),
+        vec![
+            GrammarError::AtomIndexOutOfRange { atom: 0, index: 7 },
+            GrammarError::UnresolvedTemplate {
+                atom: 1,
+                template: "nope".to_string()
+            },
+        ]
+    );
+    assert_eq!(
+        GrammarBuilder::new().build_checked().unwrap_err(),
+        vec![GrammarError::InvalidRoot { root: 0 }]
+    );
+}
+
+#[test]
+fn test_build_checked_accepts_valid_grammar() {
+    let grammar = GrammarBuilder::new()
+        .rule("braces", instantiate("block", [str("{"), str("}")]))
+        .rule_template("block", &["open", "close"], |p| {
+            p[0].then(ref_("word")).then(p[1])
+        })
+        .rule("word", re("[a-z]").many())
+        .build_checked()
+        .unwrap();
+
+    assert!(grammar.parse("{abc}").is_ok());
+}
+
+#[test]
+fn test_counted_repetition() {
+    let exactly = re("[0-9]").exactly(4);
+    let grammar = GrammarBuilder::new().rule("year", exactly).build();
+    assert!(grammar.parse("2024").is_ok());
+    assert!(grammar.parse("202").is_err());
+    assert!(grammar.parse("20245").is_err());
+
+    let counts = |parslet: Repeat<Re<'static>>| {
+        let grammar = GrammarBuilder::new().rule("digits", parslet).build();
+        (0..5)
+            .filter(|&n| grammar.parse(&"1".repeat(n)).is_ok())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(counts(re("[0-9]").at_least(3)), vec![3, 4]);
+    assert_eq!(counts(re("[0-9]").at_most(2)), vec![0, 1, 2]);
+    assert_eq!(counts(re("[0-9]").between(1, 3)), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_lazy_repeat_stops_before_follow() {
+    let block = |body: Repeat<Any>| {
+        GrammarBuilder::new()
+            .rule("block", body.lazy(str("end")).then(str("end")))
+            .build()
+    };
+
+    let grammar = block(any().many());
+    assert!(grammar.parse("abcend").is_ok());
+    assert!(grammar.parse("end").is_ok());
+    // Stops at the first "end", leaving the second unconsumed
+    assert!(grammar.parse("abcendend").is_err());
+
+    // The greedy repetition consumes the terminator too
+    let greedy = GrammarBuilder::new()
+        .rule("block", any().many().then(str("end")))
+        .build();
+    assert!(greedy.parse("abcend").is_err());
+
+    // `follow` is only checked once the minimum is reached
+    let grammar = block(any().many1());
+    assert!(grammar.parse("endend").is_ok());
+    assert!(grammar.parse("end").is_err());
+
+    let grammar = block(any().at_most(2));
+    assert!(grammar.parse("abend").is_ok());
+    assert!(grammar.parse("abcend").is_err());
+}
+
+#[test]
+fn test_repeat_sep_counts_items() {
+    let counts = |min, max| {
+        let grammar = GrammarBuilder::new()
+            .rule("list", re("[0-9]").repeat_sep(min, max, str(",")))
+            .build();
+        // Lists of 0 to 4 items
+        (0..5)
+            .filter(|&n| {
+                let items: Vec<String> = (1..=n).map(|i| i.to_string()).collect();
+                grammar.parse(&items.join(",")).is_ok()
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(counts(2, Some(3)), vec![2, 3]);
+    assert_eq!(counts(0, Some(1)), vec![0, 1]);
+    assert_eq!(counts(0, Some(0)), vec![0]);
+    assert_eq!(counts(3, None), vec![3, 4]);
+
+    let grammar = GrammarBuilder::new()
+        .rule("pair", re("[0-9]").repeat_sep(2, Some(2), str(",")))
+        .build();
+    assert!(grammar.parse("1,2").is_ok());
+    assert!(grammar.parse("1,2,").is_err());
+    assert!(grammar.parse("1").is_err());
+}
+
+#[test]
+fn test_alias_parses_like_original() {
+    let mut builder = GrammarBuilder::new();
+    builder.rule_mut("via_alias", ref_("digits"));
+    builder.rule_mut("original", re("[0-9]").many1());
+    builder.alias("digits", "original").unwrap();
+    let aliased = builder.build();
+
+    let original = GrammarBuilder::new()
+        .rule("original", re("[0-9]").many1())
+        .build();
+
+    for input in ["7", "123", "", "12a"] {
+        assert_eq!(
+            aliased.parse(input).is_ok(),
+            original.parse(input).is_ok(),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_alias_exposes_imported_rule() {
+    let number = GrammarBuilder::new()
+        .rule("integer", re("[0-9]").many1())
+        .build();
+
+    let mut builder = GrammarBuilder::new();
+    builder.rule_mut("pair", ref_("int").then(str(":")).then(ref_("int")));
+    builder.import(&number, Some("num"));
+    builder.alias("int", "num:root").unwrap();
+    let grammar = builder.build();
+
+    assert!(grammar.parse("12:34").is_ok());
+    assert!(grammar.parse("12:").is_err());
+}
+
+#[test]
+fn test_alias_unknown_rule_is_error() {
+    let mut builder = GrammarBuilder::new();
+    builder.rule_mut("a", str("a"));
+    let err = builder.alias("b", "missing").err();
+    assert_eq!(
+        err,
+        Some(GrammarError::UnresolvedRule {
+            name: "missing".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_prune_drops_unused_imported_atoms() {
+    let library = GrammarBuilder::new()
+        .rule("integer", re("[0-9]").many1())
+        .rule("word", re("[a-z]").many1().label("word"))
+        .rule(
+            "list",
+            str("[").then(sep_by(ref_("word"), str(","))).then(str("]")),
+        )
+        .build();
+
+    let mut builder = GrammarBuilder::new();
+    builder.rule_mut(
+        "pair",
+        ref_("int")
+            .label("key")
+            .then(str("="))
+            .then(ref_("int").label("value")),
+    );
+    builder.import(&library, Some("lib"));
+    builder.alias("int", "lib:root").unwrap();
+    let grammar = builder.build();
+
+    let pruned = grammar.prune();
+    assert!(pruned.atom_count() < grammar.atom_count());
+    assert_eq!(pruned.reachable_atoms().len(), pruned.atom_count());
+    assert_eq!(pruned.prune(), pruned);
+
+    for input in ["12=34", "1=2", "12=", "a=b", "[a,b]"] {
+        assert_eq!(
+            pruned.parse(input).ok(),
+            grammar.parse(input).ok(),
+            "{:?}",
+            input
+        );
+    }
+}
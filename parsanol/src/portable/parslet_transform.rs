@@ -393,6 +393,7 @@ fn flatten_sequence(items: &[AstNode], arena: &mut AstArena, input: &str) -> Ast
             // Keep as array of hashes
             // Example: [{letter: 'a'}, {letter: 'b'}] or [{schemaDecl: ...}, {schemaDecl: ...}]
             // Flatten nested arrays from repetition results into the parent level
+            // Ignored atoms (Nil) are not content and are dropped
             let mut flat_items: Vec<AstNode> = Vec::new();
             for item in items {
                 match item {
@@ -400,6 +401,7 @@ fn flatten_sequence(items: &[AstNode], arena: &mut AstArena, input: &str) -> Ast
                         let nested = arena.get_array(*pool_index as usize, *length as usize);
                         flat_items.extend(nested.iter().cloned());
                     }
+                    AstNode::Nil => {}
                     _ => flat_items.push(item.clone()),
                 }
             }
@@ -797,4 +799,47 @@ mod tests {
             _ => panic!("Expected array, got {:?}", result),
         }
     }
+
+    #[test]
+    fn test_repetition_drops_ignored_and_lookahead_items() {
+        // item ("," item)* !letter, with the separator ignored: the ignored
+        // separators and the trailing lookahead leave Nil in the raw AST,
+        // which must not show up as items
+        let grammar = GrammarBuilder::new()
+            .rule(
+                "list",
+                seq(vec![
+                    dynamic(ref_("item")),
+                    dynamic(seq(vec![dynamic(str(",").ignore()), dynamic(ref_("item"))]).many()),
+                    dynamic(re("[a-z]").not_ahead()),
+                ]),
+            )
+            .rule("item", re("[a-z]").many1().label("name"))
+            .build();
+
+        let input = "a,b,c";
+        let (result, arena) = parse_and_transform(input, &grammar);
+
+        let AstNode::Array { pool_index, length } = result else {
+            panic!("Expected array, got {:?}", result);
+        };
+        let items = arena.get_array(pool_index as usize, length as usize);
+        let names: Vec<&str> = items
+            .iter()
+            .map(|item| match item {
+                AstNode::Hash { pool_index, length } => {
+                    let pairs = arena.get_hash_items(*pool_index as usize, *length as usize);
+                    assert_eq!(pairs.len(), 1);
+                    match pairs[0].1 {
+                        AstNode::InputRef { offset, length } => {
+                            &input[offset as usize..(offset + length) as usize]
+                        }
+                        ref other => panic!("Expected input ref, got {:?}", other),
+                    }
+                }
+                other => panic!("Expected hash item, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
 }
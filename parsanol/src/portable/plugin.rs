@@ -50,6 +50,7 @@ use std::collections::HashMap;
 use std::sync::{Mutex, OnceLock};
 
 use super::custom::CustomAtom;
+use super::transform::Value;
 
 // ============================================================================
 // Plugin Trait
@@ -226,10 +227,31 @@ impl Default for AtomRegistry {
 // Transform Registry
 // ============================================================================
 
+/// A transformation a plugin applies to parse results
+///
+/// Receives the whole result of the previous transform (or of the parse,
+/// for the first one) and returns its replacement. Implemented for any
+/// `Fn(Value) -> Value + Send + Sync` closure.
+pub trait PluginTransform: Send + Sync {
+    /// Transform a parse result
+    fn apply(&self, value: Value) -> Value;
+}
+
+impl<F> PluginTransform for F
+where
+    F: Fn(Value) -> Value + Send + Sync,
+{
+    fn apply(&self, value: Value) -> Value {
+        self(value)
+    }
+}
+
 /// Registry for transforms provided by plugins
 pub struct TransformRegistry {
     /// Transform functions by name
     transforms: HashMap<String, TransformEntry>,
+    /// Transform names in registration order
+    order: Vec<String>,
 }
 
 /// Entry in the transform registry
@@ -240,6 +262,9 @@ pub struct TransformEntry {
     pub description: String,
     /// Transform function metadata (name patterns it handles)
     pub patterns: Vec<String>,
+    /// The transformation, for transforms registered with
+    /// [`register_transform`](TransformRegistry::register_transform)
+    transform: Option<Box<dyn PluginTransform>>,
 }
 
 impl TransformRegistry {
@@ -247,6 +272,7 @@ impl TransformRegistry {
     pub fn new() -> Self {
         Self {
             transforms: HashMap::new(),
+            order: Vec::new(),
         }
     }
 
@@ -265,21 +291,63 @@ impl TransformRegistry {
         description: &str,
         patterns: Vec<String>,
     ) -> bool {
-        if self.transforms.contains_key(name) {
-            return false;
-        }
-
-        self.transforms.insert(
-            name.to_string(),
+        self.insert(
+            name,
             TransformEntry {
                 plugin: plugin.to_string(),
                 description: description.to_string(),
                 patterns,
+                transform: None,
             },
-        );
+        )
+    }
+
+    /// Register a transform that [`apply`](Self::apply) runs over parse results
+    ///
+    /// Transforms run in the order they were registered, so a plugin's
+    /// transforms run after those of plugins registered before it. As with
+    /// [`register`](Self::register), the first transform registered under a
+    /// name keeps it: registering another one under the same name returns
+    /// `false` and leaves the registry unchanged.
+    pub fn register_transform(
+        &mut self,
+        name: &str,
+        plugin: &str,
+        description: &str,
+        transform: impl PluginTransform + 'static,
+    ) -> bool {
+        self.insert(
+            name,
+            TransformEntry {
+                plugin: plugin.to_string(),
+                description: description.to_string(),
+                patterns: Vec::new(),
+                transform: Some(Box::new(transform)),
+            },
+        )
+    }
+
+    fn insert(&mut self, name: &str, entry: TransformEntry) -> bool {
+        if self.transforms.contains_key(name) {
+            return false;
+        }
+
+        self.transforms.insert(name.to_string(), entry);
+        self.order.push(name.to_string());
         true
     }
 
+    /// Run every registered transform over `value`, in registration order
+    ///
+    /// Transforms registered with [`register`](Self::register) only carry
+    /// metadata and are skipped.
+    pub fn apply(&self, value: Value) -> Value {
+        self.order
+            .iter()
+            .filter_map(|name| self.transforms.get(name)?.transform.as_deref())
+            .fold(value, |value, transform| transform.apply(value))
+    }
+
     /// Get a transform by name
     pub fn get(&self, name: &str) -> Option<&TransformEntry> {
         self.transforms.get(name)
@@ -316,12 +384,21 @@ impl TransformRegistry {
 
     /// Unregister a transform
     pub fn unregister(&mut self, name: &str) -> bool {
+        self.order.retain(|n| n != name);
         self.transforms.remove(name).is_some()
     }
 
+    /// Unregister every transform registered by `plugin`
+    pub fn unregister_plugin(&mut self, plugin: &str) {
+        self.transforms.retain(|_, entry| entry.plugin != plugin);
+        let transforms = &self.transforms;
+        self.order.retain(|name| transforms.contains_key(name));
+    }
+
     /// Clear all registered transforms
     pub fn clear(&mut self) {
         self.transforms.clear();
+        self.order.clear();
     }
 }
 
@@ -390,8 +467,10 @@ impl PluginRegistry {
     /// # Returns
     ///
     /// `true` if the plugin was removed, `false` if it didn't exist.
+    /// The transforms the plugin registered are removed with it.
     pub fn unregister_plugin(&mut self, name: &str) -> bool {
         if let Some(plugin) = self.plugins.remove(name) {
+            self.transforms.unregister_plugin(name);
             plugin.on_unload();
             true
         } else {
@@ -550,6 +629,10 @@ pub fn plugin_count() -> usize {
 
 /// Clear all plugins from the global registry
 ///
+/// Also clears the current thread's regex cache (see
+/// [`regex_cache::clear`](crate::portable::regex_cache::clear)), so patterns
+/// compiled for plugin atoms don't outlive the plugins.
+///
 /// # Warning
 ///
 /// This is intended for testing purposes only.
@@ -557,6 +640,7 @@ pub fn clear_plugins() {
     let registry = get_global_registry();
     let mut guard = registry.lock().unwrap();
     guard.clear();
+    crate::portable::regex_cache::clear();
 }
 
 // ============================================================================
@@ -670,6 +754,64 @@ mod tests {
         assert!(registry.is_empty());
     }
 
+    #[test]
+    fn test_parse_and_apply_plugins_runs_registered_transforms() {
+        use crate::portable::parser_dsl::{re, str, GrammarBuilder, ParsletExt};
+        use crate::portable::{AstArena, PortableParser};
+
+        fn uppercase(value: Value) -> Value {
+            match value {
+                Value::String(s) => Value::String(s.to_uppercase()),
+                Value::Array(items) => Value::Array(items.into_iter().map(uppercase).collect()),
+                Value::Hash(pairs) => {
+                    Value::Hash(pairs.into_iter().map(|(k, v)| (k, uppercase(v))).collect())
+                }
+                other => other,
+            }
+        }
+
+        struct UppercasePlugin;
+
+        impl ParsanolPlugin for UppercasePlugin {
+            fn name(&self) -> &str {
+                "uppercase"
+            }
+
+            fn register_transforms(&self, registry: &mut TransformRegistry) {
+                registry.register_transform(
+                    "uppercase_strings",
+                    self.name(),
+                    "Uppercase every string",
+                    uppercase,
+                );
+            }
+        }
+
+        let mut registry = PluginRegistry::new();
+        registry.register_plugin(Box::new(UppercasePlugin));
+
+        let grammar = GrammarBuilder::new()
+            .rule(
+                "pair",
+                re("[a-z]")
+                    .many1()
+                    .label("key")
+                    .then(str("="))
+                    .then(re("[a-z]").many1().label("value")),
+            )
+            .build();
+        let mut arena = AstArena::new();
+        let mut parser = PortableParser::new(&grammar, "name=ada", &mut arena);
+        let value = parser.parse_and_apply_plugins(&registry).unwrap();
+
+        assert_eq!(value.get("key").and_then(Value::as_str), Some("NAME"));
+        assert_eq!(value.get("value").and_then(Value::as_str), Some("ADA"));
+
+        // Unregistering the plugin removes its transforms
+        registry.unregister_plugin("uppercase");
+        assert!(registry.transforms().is_empty());
+    }
+
     #[test]
     fn test_global_registry() {
         // Clear any existing plugins
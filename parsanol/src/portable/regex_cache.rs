@@ -19,6 +19,7 @@
 //! ```
 
 use hashbrown::HashMap;
+use regex::bytes::Regex as BytesRegex;
 use regex::Regex;
 use std::cell::RefCell;
 
@@ -37,6 +38,9 @@ thread_local! {
     /// Thread-local cache of compiled regex patterns
     static REGEX_CACHE: RefCell<HashMap<String, Regex>> = RefCell::new(HashMap::new());
 
+    /// Thread-local cache of patterns compiled for byte matching
+    static BYTES_REGEX_CACHE: RefCell<HashMap<String, BytesRegex>> = RefCell::new(HashMap::new());
+
     /// Thread-local cache statistics
     static CACHE_STATS: RefCell<CacheStats> = const { RefCell::new(CacheStats { hits: 0, misses: 0, size: 0 }) };
 }
@@ -54,48 +58,71 @@ thread_local! {
 /// * `None` if the pattern is invalid
 #[inline]
 pub fn get_or_compile(pattern: &str) -> Option<Regex> {
-    REGEX_CACHE.with(|cache| {
-        // Check if already compiled
-        if let Some(regex) = cache.borrow().get(pattern) {
-            // Cache hit
-            CACHE_STATS.with(|stats| {
-                stats.borrow_mut().hits += 1;
-            });
-            return Some(regex.clone());
-        }
-
-        // Cache miss - compile and cache
-        match Regex::new(pattern) {
-            Ok(regex) => {
-                cache
-                    .borrow_mut()
-                    .insert(pattern.to_string(), regex.clone());
-
-                // Update stats
-                CACHE_STATS.with(|stats| {
-                    let mut s = stats.borrow_mut();
-                    s.misses += 1;
-                    s.size = cache.borrow().len();
-                });
-
-                Some(regex)
-            }
-            Err(_) => {
-                // Still count as a miss even for invalid patterns
-                CACHE_STATS.with(|stats| {
-                    stats.borrow_mut().misses += 1;
-                });
-                None
-            }
-        }
+    REGEX_CACHE.with(|cache| lookup(cache, pattern, |p| Regex::new(p).ok()))
+}
+
+/// Get or compile a regex pattern for matching bytes
+///
+/// Like [`get_or_compile`], but the pattern is compiled for
+/// [`regex::bytes`] with Unicode mode off: `.` and negated classes match
+/// single bytes, and `\w`, `\d` and `\s` are ASCII-only. On ASCII
+/// haystacks this finds exactly the same matches as the Unicode engine
+/// while skipping UTF-8 decoding, which is what grammars flagged
+/// [`ascii_only`](super::grammar::Grammar::ascii_only) use it for. It must
+/// not be used on non-ASCII text, where its matches can split a char.
+///
+/// Byte-mode patterns are cached separately from Unicode ones. Returns
+/// `None` if the pattern is invalid, or needs Unicode mode (such as a
+/// `\p{..}` class).
+#[inline]
+pub fn get_or_compile_bytes(pattern: &str) -> Option<BytesRegex> {
+    BYTES_REGEX_CACHE.with(|cache| {
+        lookup(cache, pattern, |p| {
+            regex::bytes::RegexBuilder::new(p)
+                .unicode(false)
+                .build()
+                .ok()
+        })
     })
 }
 
-/// Clear the regex cache
+/// Look `pattern` up in `cache`, compiling and caching it on a miss
+fn lookup<R: Clone>(
+    cache: &RefCell<HashMap<String, R>>,
+    pattern: &str,
+    compile: impl FnOnce(&str) -> Option<R>,
+) -> Option<R> {
+    // Check if already compiled
+    if let Some(regex) = cache.borrow().get(pattern) {
+        // Cache hit
+        CACHE_STATS.with(|stats| {
+            stats.borrow_mut().hits += 1;
+        });
+        return Some(regex.clone());
+    }
+
+    // Cache miss - compile and cache; invalid patterns still count as a miss
+    let compiled = compile(pattern);
+    if let Some(regex) = &compiled {
+        cache
+            .borrow_mut()
+            .insert(pattern.to_string(), regex.clone());
+    }
+    CACHE_STATS.with(|stats| {
+        stats.borrow_mut().misses += 1;
+    });
+    compiled
+}
+
+/// Clear the regex cache and release its memory
 ///
-/// Call this to free memory if many unique patterns have been compiled.
-pub fn clear_cache() {
-    REGEX_CACHE.with(|cache| cache.borrow_mut().clear());
+/// Drops every compiled pattern for the current thread and resets the
+/// statistics. Long-lived hosts (e.g. a WASM module in a page that stays
+/// open) can call this to reclaim memory held for transient grammars;
+/// patterns are recompiled on next use.
+pub fn clear() {
+    REGEX_CACHE.with(|cache| *cache.borrow_mut() = HashMap::new());
+    BYTES_REGEX_CACHE.with(|cache| *cache.borrow_mut() = HashMap::new());
     CACHE_STATS.with(|stats| {
         let mut s = stats.borrow_mut();
         s.hits = 0;
@@ -104,9 +131,18 @@ pub fn clear_cache() {
     });
 }
 
-/// Get the number of cached patterns
+/// Clear the regex cache
+///
+/// Call this to free memory if many unique patterns have been compiled.
+/// Same as [`clear`].
+pub fn clear_cache() {
+    clear();
+}
+
+/// Get the number of cached patterns, counting both engines
 pub fn cache_size() -> usize {
     REGEX_CACHE.with(|cache| cache.borrow().len())
+        + BYTES_REGEX_CACHE.with(|cache| cache.borrow().len())
 }
 
 /// Get cache statistics for monitoring
@@ -153,6 +189,20 @@ mod tests {
         assert_eq!(cache_size(), 2);
     }
 
+    #[test]
+    fn test_bytes_patterns_cached_separately() {
+        clear_cache();
+
+        assert!(get_or_compile("[0-9]+").is_some());
+        assert!(get_or_compile_bytes("[0-9]+").is_some());
+        assert_eq!(cache_size(), 2);
+        assert!(get_or_compile_bytes("[0-9]+").is_some());
+        assert_eq!(stats().hits, 1);
+
+        clear();
+        assert_eq!(cache_size(), 0);
+    }
+
     #[test]
     fn test_invalid_pattern() {
         clear_cache();
@@ -207,6 +257,22 @@ mod tests {
         assert_eq!(s.misses, 2);
     }
 
+    #[test]
+    fn test_clear_empties_cache() {
+        clear();
+        let _ = get_or_compile("[0-9]+");
+        let _ = get_or_compile("[a-z]+");
+        let _ = get_or_compile("[0-9]+");
+        let s = stats();
+        assert_eq!((s.hits, s.misses, s.size), (1, 2, 2));
+
+        clear();
+
+        let s = stats();
+        assert_eq!((s.hits, s.misses, s.size), (0, 0, 0));
+        assert_eq!(cache_size(), 0);
+    }
+
     #[test]
     fn test_reset_stats() {
         clear_cache();
@@ -3,10 +3,11 @@
 //! This module provides utilities for tracking and formatting source code positions.
 //! It consolidates line/column calculation logic used throughout the parser.
 
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// A position in source code
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SourcePosition {
     /// Byte offset from start of input
     pub offset: usize,
@@ -69,8 +70,9 @@ impl SourcePosition {
 
     /// Get a slice of source code around this position
     pub fn get_context<'a>(&self, input: &'a str, context_lines: usize) -> SourceContext<'a> {
-        let start_offset = self.offset.saturating_sub(context_lines * 80);
-        let end_offset = (self.offset + context_lines * 80).min(input.len());
+        let offset = clamp_to_char_boundary(input, self.offset);
+        let start_offset = clamp_to_char_boundary(input, offset.saturating_sub(context_lines * 80));
+        let end_offset = clamp_to_char_boundary(input, offset + context_lines * 80);
 
         SourceContext {
             source: &input[start_offset..end_offset],
@@ -93,9 +95,13 @@ impl Default for SourcePosition {
 }
 
 /// A range in source code
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Serializes as the start position's fields plus an `end` object:
+/// `{"offset": 4, "line": 1, "column": 5, "end": {...}}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SourceSpan {
     /// Start position
+    #[serde(flatten)]
     pub start: SourcePosition,
     /// End position
     pub end: SourcePosition,
@@ -201,6 +207,29 @@ impl SourceSpan {
     pub fn is_adjacent(&self, other: &SourceSpan) -> bool {
         self.end.offset == other.start.offset || other.end.offset == self.start.offset
     }
+
+    /// Convert to an LSP-style range
+    ///
+    /// LSP positions are 0-based and count characters in UTF-16 code units,
+    /// so the input is needed to convert the byte offsets of this span.
+    pub fn to_lsp_range(&self, input: &str) -> LspRange {
+        LspRange {
+            start: LspPosition::from_offset(input, self.start.offset),
+            end: LspPosition::from_offset(input, self.end.offset),
+        }
+    }
+
+    /// Create a span from an LSP-style range
+    ///
+    /// Positions past the end of a line are clamped to the line end, and
+    /// lines past the end of input are clamped to the end of input.
+    pub fn from_lsp_range(input: &str, range: &LspRange) -> Self {
+        Self::from_offsets(
+            input,
+            range.start.to_offset(input),
+            range.end.to_offset(input),
+        )
+    }
 }
 
 impl fmt::Display for SourceSpan {
@@ -227,6 +256,153 @@ impl Default for SourceSpan {
     }
 }
 
+/// A position in the Language Server Protocol convention
+///
+/// Both fields are 0-based, and `character` counts UTF-16 code units from
+/// the start of the line, as required by the LSP specification.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+pub struct LspPosition {
+    /// Line number (0-based)
+    pub line: u32,
+    /// Offset within the line in UTF-16 code units (0-based)
+    pub character: u32,
+}
+
+impl LspPosition {
+    /// Create a new LSP position
+    #[inline]
+    pub fn new(line: u32, character: u32) -> Self {
+        Self { line, character }
+    }
+
+    /// Calculate the LSP position of a byte offset in the input
+    pub fn from_offset(input: &str, offset: usize) -> Self {
+        let offset = clamp_to_char_boundary(input, offset);
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+
+        Self {
+            line: before.matches('\n').count() as u32,
+            character: input[line_start..offset].encode_utf16().count() as u32,
+        }
+    }
+
+    /// Convert this position to a byte offset in the input
+    pub fn to_offset(&self, input: &str) -> usize {
+        let mut line_start = 0;
+        for _ in 0..self.line {
+            match input[line_start..].find('\n') {
+                Some(pos) => line_start += pos + 1,
+                None => return input.len(),
+            }
+        }
+
+        let mut units = 0;
+        for (i, ch) in input[line_start..].char_indices() {
+            if ch == '\n' || units >= self.character as usize {
+                return line_start + i;
+            }
+            units += ch.len_utf16();
+        }
+        input.len()
+    }
+}
+
+/// A range in the Language Server Protocol convention
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+pub struct LspRange {
+    /// Start position (inclusive)
+    pub start: LspPosition,
+    /// End position (exclusive)
+    pub end: LspPosition,
+}
+
+impl LspRange {
+    /// Create a new LSP range
+    #[inline]
+    pub fn new(start: LspPosition, end: LspPosition) -> Self {
+        Self { start, end }
+    }
+}
+
+/// Precomputed byte offset to UTF-16 position mapping for one input
+///
+/// [`LspPosition::from_offset`] rescans the input on every call. When many
+/// offsets of the same document need converting (every node of an AST, or
+/// every diagnostic), build a mapper once: it records line starts and the
+/// non-ASCII characters, so each lookup is a pair of binary searches.
+/// Characters above U+FFFF count as two UTF-16 code units.
+#[derive(Debug, Clone, Default)]
+pub struct Utf16Mapper {
+    /// Input length in bytes
+    len: usize,
+    /// Byte offset of the start of each line
+    line_starts: Vec<usize>,
+    /// Non-ASCII characters: (byte start, byte end, UTF-16 units before)
+    wide_chars: Vec<(usize, usize, usize)>,
+}
+
+impl Utf16Mapper {
+    /// Build the mapping for `input`
+    pub fn new(input: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut wide_chars = Vec::new();
+        let mut units = 0;
+        for (i, ch) in input.char_indices() {
+            if ch == '\n' {
+                line_starts.push(i + 1);
+            } else if !ch.is_ascii() {
+                wide_chars.push((i, i + ch.len_utf8(), units));
+            }
+            units += ch.len_utf16();
+        }
+
+        Self {
+            len: input.len(),
+            line_starts,
+            wide_chars,
+        }
+    }
+
+    /// 0-based line and UTF-16 column of a byte offset
+    ///
+    /// Offsets past the end clamp to the end of input, and offsets inside a
+    /// multi-byte character snap back to its start.
+    pub fn to_utf16(&self, byte_offset: usize) -> (usize, usize) {
+        let offset = byte_offset.min(self.len);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.units_before(offset) - self.units_before(self.line_starts[line]);
+        (line, column)
+    }
+
+    /// LSP position of a byte offset
+    pub fn to_lsp_position(&self, byte_offset: usize) -> LspPosition {
+        let (line, character) = self.to_utf16(byte_offset);
+        LspPosition::new(line as u32, character as u32)
+    }
+
+    /// UTF-16 units in the input before `offset`
+    fn units_before(&self, offset: usize) -> usize {
+        let idx = self
+            .wide_chars
+            .partition_point(|&(start, _, _)| start <= offset);
+        match idx.checked_sub(1).map(|i| self.wide_chars[i]) {
+            // Inside (or at the start of) a wide character
+            Some((_, end, units)) if offset < end => units,
+            Some((start, end, units)) => {
+                let width = if end - start == 4 { 2 } else { 1 };
+                units + width + (offset - end)
+            }
+            // Only ASCII so far: one unit per byte
+            None => offset,
+        }
+    }
+}
+
 /// Context around a source position
 #[derive(Debug, Clone)]
 pub struct SourceContext<'a> {
@@ -289,9 +465,24 @@ pub fn offset_to_line_col(input: &str, offset: usize) -> (usize, usize) {
     (pos.line, pos.column)
 }
 
+/// Clamp a byte offset to the input bounds, snapping back to a char boundary
+///
+/// Offsets equal to `input.len()` (end of input) are valid and returned as-is.
+/// Offsets past the end are clamped to `input.len()`, and offsets that fall
+/// inside a multi-byte character are moved back to the start of that character.
+/// The result is always safe to use for slicing `input`.
+#[inline]
+pub fn clamp_to_char_boundary(input: &str, offset: usize) -> usize {
+    let mut offset = offset.min(input.len());
+    while !input.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
 /// Get the line content at a given offset
 pub fn get_line_at_offset(input: &str, offset: usize) -> &str {
-    let offset = offset.min(input.len());
+    let offset = clamp_to_char_boundary(input, offset);
 
     // Find start of line
     let line_start = if let Some(pos) = input[..offset].rfind('\n') {
@@ -412,6 +603,32 @@ mod tests {
         assert_eq!(get_line_at_offset(input, 12), "line3");
     }
 
+    #[test]
+    fn test_clamp_to_char_boundary() {
+        let input = "a世b";
+        assert_eq!(clamp_to_char_boundary(input, 0), 0);
+        assert_eq!(clamp_to_char_boundary(input, 2), 1); // inside '世'
+        assert_eq!(clamp_to_char_boundary(input, 4), 4);
+        assert_eq!(clamp_to_char_boundary(input, 5), 5); // end of input
+        assert_eq!(clamp_to_char_boundary(input, 100), 5);
+    }
+
+    #[test]
+    fn test_get_line_at_offset_eof() {
+        let input = "line1\nline2";
+        assert_eq!(get_line_at_offset(input, input.len()), "line2");
+        assert_eq!(get_line_at_offset(input, 100), "line2");
+        assert_eq!(get_line_at_offset("a世b", 2), "a世b");
+    }
+
+    #[test]
+    fn test_source_context_beyond_end() {
+        let input = "hello";
+        let pos = SourcePosition::new(100, 1, 6);
+        let context = pos.get_context(input, 1);
+        assert_eq!(context.source, "hello");
+    }
+
     #[test]
     fn test_source_position_display() {
         let pos = SourcePosition::new(10, 3, 5);
@@ -488,4 +705,72 @@ mod tests {
         assert!(span2.is_adjacent(&span1));
         assert!(!span1.is_adjacent(&span3));
     }
+
+    #[test]
+    fn test_lsp_position_ascii() {
+        let input = "let x = 1;\nlet y = 2;";
+        assert_eq!(LspPosition::from_offset(input, 0), LspPosition::new(0, 0));
+        assert_eq!(LspPosition::from_offset(input, 4), LspPosition::new(0, 4));
+        assert_eq!(LspPosition::from_offset(input, 15), LspPosition::new(1, 4));
+        assert_eq!(LspPosition::new(1, 4).to_offset(input), 15);
+    }
+
+    #[test]
+    fn test_lsp_range_multibyte_utf16() {
+        // 'é' is 2 bytes / 1 UTF-16 unit, '😀' is 4 bytes / 2 UTF-16 units
+        let input = "é😀x\n😀 = y";
+        let x = input.find('x').unwrap(); // byte 6
+        let y = input.find('y').unwrap();
+
+        let span = SourceSpan::from_offsets(input, x, x + 1);
+        let range = span.to_lsp_range(input);
+        assert_eq!(range.start, LspPosition::new(0, 3));
+        assert_eq!(range.end, LspPosition::new(0, 4));
+
+        let span = SourceSpan::from_offsets(input, y, y + 1);
+        let range = span.to_lsp_range(input);
+        assert_eq!(range.start, LspPosition::new(1, 5));
+
+        // Round trip back to byte offsets
+        let back = SourceSpan::from_lsp_range(input, &range);
+        assert_eq!(back.start.offset, y);
+        assert_eq!(back.end.offset, y + 1);
+    }
+
+    #[test]
+    fn test_lsp_position_clamping() {
+        let input = "ab\ncd";
+        // Past end of line clamps to the newline
+        assert_eq!(LspPosition::new(0, 10).to_offset(input), 2);
+        // Past last line clamps to end of input
+        assert_eq!(LspPosition::new(5, 0).to_offset(input), input.len());
+        // End of input maps to the last line
+        assert_eq!(
+            LspPosition::from_offset(input, input.len()),
+            LspPosition::new(1, 2)
+        );
+    }
+
+    #[test]
+    fn test_utf16_mapper_surrogate_pairs() {
+        // '😀' is 4 bytes / 2 UTF-16 units, 'é' is 2 bytes / 1 unit
+        let input = "a😀b\né😀 = ?";
+        let mapper = Utf16Mapper::new(input);
+
+        assert_eq!(mapper.to_utf16(0), (0, 0));
+        assert_eq!(mapper.to_utf16(input.find('b').unwrap()), (0, 3));
+        assert_eq!(mapper.to_utf16(input.find('?').unwrap()), (1, 6));
+        // Inside the emoji snaps back to its start
+        assert_eq!(mapper.to_utf16(2), (0, 1));
+        assert_eq!(mapper.to_utf16(input.len() + 10), (1, 7));
+
+        for offset in 0..=input.len() {
+            if input.is_char_boundary(offset) {
+                assert_eq!(
+                    mapper.to_lsp_position(offset),
+                    LspPosition::from_offset(input, offset)
+                );
+            }
+        }
+    }
 }
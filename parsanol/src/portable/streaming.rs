@@ -62,6 +62,7 @@
 //! let config = ChunkConfig {
 //!     chunk_size: 64 * 1024,      // 64 KB chunks
 //!     window_size: 3,              // Keep 3 chunks in memory
+//!     overlap: 0,                  // No bytes shared between chunks
 //! };
 //! let mut parser = StreamingParser::new(&grammar, config);
 //!
@@ -90,6 +91,11 @@ pub struct ChunkConfig {
     /// Number of chunks to keep in the sliding window
     /// Larger window = more backtracking capability but more memory
     pub window_size: usize,
+
+    /// Bytes at the end of one chunk that are scanned again with the next,
+    /// so [`StreamingParser::find_matches_in_chunks`] finds tokens that
+    /// straddle a chunk boundary (0 = none)
+    pub overlap: usize,
 }
 
 impl Default for ChunkConfig {
@@ -97,6 +103,7 @@ impl Default for ChunkConfig {
         Self {
             chunk_size: 64 * 1024, // 64 KB
             window_size: 3,        // Keep 3 chunks (~192 KB) in memory
+            overlap: 0,
         }
     }
 }
@@ -108,9 +115,20 @@ impl ChunkConfig {
         Self {
             chunk_size,
             window_size,
+            overlap: 0,
         }
     }
 
+    /// Share the last `bytes` of each chunk with the next one
+    ///
+    /// A token is found across a boundary if it starts within `bytes` of
+    /// it, so set this to at least the longest token the grammar matches.
+    #[inline]
+    pub fn overlap(mut self, bytes: usize) -> Self {
+        self.overlap = bytes;
+        self
+    }
+
     /// Get the maximum memory usage (approximate)
     #[inline]
     pub fn max_memory(&self) -> usize {
@@ -123,6 +141,7 @@ impl ChunkConfig {
         Self {
             chunk_size: 16 * 1024,
             window_size: 2,
+            overlap: 0,
         }
     }
 
@@ -138,6 +157,7 @@ impl ChunkConfig {
         Self {
             chunk_size: 256 * 1024,
             window_size: 4,
+            overlap: 0,
         }
     }
 
@@ -147,6 +167,7 @@ impl ChunkConfig {
         Self {
             chunk_size: 1024 * 1024,
             window_size: 5,
+            overlap: 0,
         }
     }
 }
@@ -334,6 +355,19 @@ pub struct StreamingResult {
     pub capture_state: Option<super::capture_state::CaptureState>,
 }
 
+/// A match found by [`StreamingParser::find_matches_in_chunks`]
+#[derive(Debug, Clone)]
+pub struct StreamMatch {
+    /// Global offset where the match starts
+    pub start: usize,
+
+    /// Global offset where the match ends (exclusive)
+    pub end: usize,
+
+    /// The parsed AST
+    pub ast: AstNode,
+}
+
 impl<'a> StreamingParser<'a> {
     /// Create a new streaming parser
     #[inline]
@@ -498,6 +532,91 @@ impl<'a> StreamingParser<'a> {
         })
     }
 
+    /// Find every match of the root rule in an iterator of byte chunks
+    ///
+    /// Unlike [`parse_from_chunks`](Self::parse_from_chunks), each chunk is
+    /// scanned as it arrives, like
+    /// [`PortableParser::parse_all`](super::parser::PortableParser::parse_all),
+    /// and dropped afterwards, so memory is bounded by the chunk size.
+    ///
+    /// A token split across a chunk boundary is missed unless
+    /// [`ChunkConfig::overlap`] is set: each chunk is then scanned together
+    /// with the last `overlap` bytes of the text before it. Matches lying
+    /// entirely in that shared region were reported with the previous chunk
+    /// and are skipped, as is a match overlapping one already reported, so
+    /// each match is reported once. A match that reaches the end of a chunk
+    /// is held back while the next chunk may extend it.
+    ///
+    /// Offsets in the returned matches are global. The text of a chunk is
+    /// gone once it's scanned, so create `arena` with
+    /// [`AstArena::with_interned_inputs`] for nodes that carry their own
+    /// text.
+    pub fn find_matches_in_chunks<I>(
+        &mut self,
+        chunks: I,
+        arena: &mut AstArena,
+    ) -> Result<Vec<StreamMatch>, StreamingError>
+    where
+        I: IntoIterator<Item = Vec<u8>>,
+    {
+        let mut matches = Vec::new();
+        // Bytes carried over from the previous chunk, and their global offset
+        let mut carried = Vec::new();
+        let mut carried_offset = self.total_bytes_read;
+        // Global offset up to which text has been scanned
+        let mut scanned_to = self.total_bytes_read;
+        // Global end of the last reported match
+        let mut reported_to = 0;
+
+        let mut chunks = chunks.into_iter().peekable();
+        while let Some(chunk_data) = chunks.next() {
+            let is_last = chunks.peek().is_none();
+            self.total_bytes_read += chunk_data.len();
+            let mut window = std::mem::take(&mut carried);
+            window.extend_from_slice(&chunk_data);
+
+            // A char split by the chunk boundary waits for its remaining bytes
+            let text_len = match std::str::from_utf8(&window) {
+                Ok(text) => text.len(),
+                Err(e) if e.error_len().is_none() && !is_last => e.valid_up_to(),
+                Err(e) => return Err(StreamingError::InvalidUtf8(e.to_string())),
+            };
+            let text = std::str::from_utf8(&window[..text_len])
+                .map_err(|e| StreamingError::InvalidUtf8(e.to_string()))?;
+
+            // Start of the text shared with the next chunk
+            let mut keep_from = text_len.saturating_sub(self.config.overlap);
+            while !text.is_char_boundary(keep_from) {
+                keep_from += 1;
+            }
+
+            let found =
+                super::parser::PortableParser::new(self.grammar, text, arena).parse_all_spans();
+            let mut next_scanned_to = carried_offset + text_len;
+            for (start, end, ast) in found {
+                let (start, end) = (carried_offset + start, carried_offset + end);
+                if !is_last && end == next_scanned_to && start >= carried_offset + keep_from {
+                    // Scanned again with the next chunk, which may extend it
+                    next_scanned_to = start;
+                    continue;
+                }
+                let seen = start < scanned_to && end <= scanned_to;
+                if seen || start < reported_to {
+                    continue;
+                }
+                reported_to = end;
+                matches.push(StreamMatch { start, end, ast });
+            }
+
+            scanned_to = next_scanned_to;
+            carried = window.split_off(keep_from);
+            carried_offset += keep_from;
+        }
+
+        self.eof_reached = true;
+        Ok(matches)
+    }
+
     /// Parse from a file path
     pub fn parse_from_file<P: AsRef<std::path::Path>>(
         &mut self,
@@ -751,6 +870,107 @@ impl ChunkSource for Vec<u8> {
     }
 }
 
+/// Chunk source that only emits whole lines
+///
+/// Wraps another [`ChunkSource`] and re-slices its output at newlines, so a
+/// line-oriented grammar (CSV, logs, JSON Lines) never sees a record split
+/// across a chunk boundary. A partial trailing line is buffered until the
+/// rest of it arrives; whatever is left when the inner source runs dry is
+/// emitted as the final chunk, with or without a trailing newline.
+///
+/// A single line longer than the inner chunk size is buffered whole.
+pub struct LineChunkSource<S> {
+    /// The underlying source
+    inner: S,
+
+    /// Bytes read from `inner` but not yet emitted
+    pending: Vec<u8>,
+
+    /// Global offset of the next chunk to be emitted
+    offset: usize,
+
+    /// Global offset of the most recently emitted chunk
+    last_offset: Option<usize>,
+}
+
+impl<S: ChunkSource> LineChunkSource<S> {
+    /// Wrap `inner`, re-chunking its output at line boundaries
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            pending: Vec::new(),
+            offset: 0,
+            last_offset: None,
+        }
+    }
+
+    /// Get the next chunk of whole lines along with its global byte offset
+    pub fn next_chunk_with_offset(&mut self) -> Option<(usize, Vec<u8>)> {
+        loop {
+            if let Some(last_newline) = self.pending.iter().rposition(|&b| b == b'\n') {
+                let rest = self.pending.split_off(last_newline + 1);
+                let chunk = std::mem::replace(&mut self.pending, rest);
+                return Some(self.emit(chunk));
+            }
+
+            match self.inner.next_chunk() {
+                Some(data) => self.pending.extend_from_slice(&data),
+                None if self.pending.is_empty() => return None,
+                None => {
+                    let chunk = std::mem::take(&mut self.pending);
+                    return Some(self.emit(chunk));
+                }
+            }
+        }
+    }
+
+    /// Global byte offset of the most recently emitted chunk
+    ///
+    /// Add it to a position inside that chunk to report errors against
+    /// the whole input.
+    #[inline]
+    pub fn last_offset(&self) -> Option<usize> {
+        self.last_offset
+    }
+
+    /// Total bytes emitted so far
+    #[inline]
+    pub fn bytes_emitted(&self) -> usize {
+        self.offset
+    }
+
+    /// Unwrap the underlying source, dropping any buffered partial line
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn emit(&mut self, chunk: Vec<u8>) -> (usize, Vec<u8>) {
+        let offset = self.offset;
+        self.offset += chunk.len();
+        self.last_offset = Some(offset);
+        (offset, chunk)
+    }
+}
+
+impl<S: ChunkSource> ChunkSource for LineChunkSource<S> {
+    fn next_chunk(&mut self) -> Option<Vec<u8>> {
+        self.next_chunk_with_offset().map(|(_, chunk)| chunk)
+    }
+
+    fn has_more(&self) -> bool {
+        !self.pending.is_empty() || self.inner.has_more()
+    }
+}
+
+/// Lets a `LineChunkSource` feed [`StreamingParser::parse_from_chunks`]
+impl<S: ChunkSource> Iterator for LineChunkSource<S> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        ChunkSource::next_chunk(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -954,4 +1174,147 @@ mod tests {
         // Captures should be in the result
         assert!(streaming_result.capture_state.is_some());
     }
+
+    /// Yields its input a few bytes at a time
+    struct TinyChunks {
+        data: Vec<u8>,
+        size: usize,
+    }
+
+    impl ChunkSource for TinyChunks {
+        fn next_chunk(&mut self) -> Option<Vec<u8>> {
+            if self.data.is_empty() {
+                return None;
+            }
+            let rest = self.data.split_off(self.size.min(self.data.len()));
+            Some(std::mem::replace(&mut self.data, rest))
+        }
+
+        fn has_more(&self) -> bool {
+            !self.data.is_empty()
+        }
+    }
+
+    #[test]
+    fn test_line_chunk_source_emits_whole_lines() {
+        let inner = TinyChunks {
+            data: b"id,name\n1,ab\n2,cd".to_vec(),
+            size: 3,
+        };
+        let mut source = LineChunkSource::new(inner);
+
+        let mut chunks = Vec::new();
+        while let Some((offset, chunk)) = source.next_chunk_with_offset() {
+            assert_eq!(source.last_offset(), Some(offset));
+            chunks.push((offset, String::from_utf8(chunk).unwrap()));
+        }
+
+        // "id,n" + "ame\n1," arrives as two inner chunks but leaves as one line;
+        // the unterminated last line is flushed at EOF
+        assert_eq!(
+            chunks,
+            vec![
+                (0, "id,name\n".to_string()),
+                (8, "1,ab\n".to_string()),
+                (13, "2,cd".to_string()),
+            ]
+        );
+        assert!(!source.has_more());
+        assert_eq!(source.bytes_emitted(), 17);
+    }
+
+    #[test]
+    fn test_streaming_csv_in_tiny_chunks() {
+        use super::super::grammar::{Atom, Grammar};
+
+        // csv = (field ("," field)* "\n")*
+        let mut grammar = Grammar::new();
+        let letter = grammar.add_atom(Atom::Re {
+            pattern: "[a-z]".to_string(),
+        });
+        let field = grammar.add_atom(Atom::Repetition {
+            atom: letter,
+            min: 1,
+            max: None,
+        });
+        let comma = grammar.add_atom(Atom::Str {
+            pattern: ",".to_string(),
+        });
+        let more = grammar.add_atom(Atom::Sequence {
+            atoms: vec![comma, field],
+        });
+        let more_fields = grammar.add_atom(Atom::Repetition {
+            atom: more,
+            min: 0,
+            max: None,
+        });
+        let newline = grammar.add_atom(Atom::Str {
+            pattern: "\n".to_string(),
+        });
+        let row = grammar.add_atom(Atom::Sequence {
+            atoms: vec![field, more_fields, newline],
+        });
+        grammar.root = grammar.add_atom(Atom::Repetition {
+            atom: row,
+            min: 0,
+            max: None,
+        });
+
+        let csv = b"name,age\nann,ten\nbob,six\n";
+        let source = LineChunkSource::new(TinyChunks {
+            data: csv.to_vec(),
+            size: 2,
+        });
+        let chunks: Vec<Vec<u8>> = source.collect();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| chunk.ends_with(b"\n")));
+
+        let mut parser = StreamingParser::new(&grammar, ChunkConfig::new(2, 8));
+        let mut arena = AstArena::for_input(csv.len());
+        let result = parser.parse_from_chunks(chunks, &mut arena).unwrap();
+
+        assert_eq!(result.bytes_processed, csv.len());
+        assert_eq!(result.chunks_processed, 3);
+        // Repetitions are tagged arrays: the tag, then one node per row
+        match result.ast {
+            AstNode::Array { pool_index, length } => {
+                let items = arena.get_array(pool_index as usize, length as usize);
+                assert_eq!(items.len(), 4);
+                match &items[0] {
+                    AstNode::StringRef { pool_index } => {
+                        assert_eq!(arena.get_string(*pool_index as usize), ":repetition")
+                    }
+                    other => panic!("expected a tag, got {:?}", other),
+                }
+            }
+            other => panic!("expected one node per row, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_overlap_finds_token_straddling_chunk_boundary_once() {
+        use super::super::parser_dsl::{str, GrammarBuilder};
+
+        let grammar = GrammarBuilder::new()
+            .rule("token", str("TOKEN12345"))
+            .build();
+        // The second token straddles the boundary between the chunks
+        let chunks = || vec![b"xx TOKEN12345 TOK".to_vec(), b"EN12345 yy".to_vec()];
+        let spans = |config: ChunkConfig| {
+            let mut parser = StreamingParser::new(&grammar, config);
+            let mut arena = AstArena::with_interned_inputs();
+            parser
+                .find_matches_in_chunks(chunks(), &mut arena)
+                .unwrap()
+                .iter()
+                .map(|m| (m.start, m.end))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(spans(ChunkConfig::new(17, 2)), [(3, 13)]);
+        assert_eq!(
+            spans(ChunkConfig::new(17, 2).overlap(16)),
+            [(3, 13), (14, 24)]
+        );
+    }
 }
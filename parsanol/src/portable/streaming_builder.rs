@@ -94,6 +94,29 @@ impl std::fmt::Display for BuildError {
 
 impl std::error::Error for BuildError {}
 
+/// Whether the walk should keep delivering events to a builder
+///
+/// Returned by [`StreamingBuilder::control`] and by [`walk_ast`]. A builder
+/// that already has what it needs returns [`BuildControl::Stop`]; the walk
+/// then delivers no further events, and `finish` is still called. Stopping
+/// is not an error: errors are reported through [`BuildError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuildControl {
+    /// Keep walking
+    #[default]
+    Continue,
+    /// Stop walking; the builder has what it needs
+    Stop,
+}
+
+impl BuildControl {
+    /// Whether this is [`BuildControl::Stop`]
+    #[inline]
+    pub fn is_stop(self) -> bool {
+        self == BuildControl::Stop
+    }
+}
+
 impl From<BuildError> for ParseError {
     fn from(e: BuildError) -> Self {
         ParseError::BuilderError {
@@ -129,6 +152,13 @@ impl From<BuildError> for ParseError {
 /// Return `Err(BuildError::...)` to abort parsing with an error.
 /// The parser will stop immediately and return the error.
 ///
+/// # Early Termination
+///
+/// To stop cleanly once enough has been seen, return
+/// [`BuildControl::Stop`] from [`control`](StreamingBuilder::control).
+/// It is checked after every event; once it reports `Stop` no further
+/// events are delivered and `finish` is called as usual.
+///
 /// # Generic Output
 ///
 /// Each builder defines its own `Output` type. This allows:
@@ -193,6 +223,23 @@ pub trait StreamingBuilder {
         Ok(())
     }
 
+    /// Called when input matched by an ignored atom is skipped
+    ///
+    /// Ignored atoms (`.ignore()`) leave nothing in the tree, so this is
+    /// the only event that sees their text. It is delivered in input
+    /// order, just before the first string that follows it (or at the
+    /// end of the walk).
+    ///
+    /// # Arguments
+    /// * `text` - The skipped input
+    /// * `offset` - Byte offset in input
+    /// * `length` - Byte length
+    ///
+    fn on_ignored(&mut self, text: &str, offset: usize, length: usize) -> BuildResult<()> {
+        let _ = (text, offset, length);
+        Ok(())
+    }
+
     /// Called when an integer value is parsed
     fn on_int(&mut self, value: i64) -> BuildResult<()> {
         let _ = value;
@@ -292,6 +339,15 @@ pub trait StreamingBuilder {
         Ok(())
     }
 
+    // === Flow Control ===
+
+    /// Whether the walk should keep delivering events
+    ///
+    /// Checked after every event. Defaults to [`BuildControl::Continue`].
+    fn control(&self) -> BuildControl {
+        BuildControl::Continue
+    }
+
     /// Finalize and return the built result
     ///
     /// This is called after `on_success` to get the final output.
@@ -350,6 +406,12 @@ impl StreamingBuilder for DebugBuilder {
         Ok(())
     }
 
+    fn on_ignored(&mut self, text: &str, offset: usize, length: usize) -> BuildResult<()> {
+        self.events
+            .push(format!("ignored({:?} @ {} len={})", text, offset, length));
+        Ok(())
+    }
+
     fn on_int(&mut self, value: i64) -> BuildResult<()> {
         self.events.push(format!("int({})", value));
         Ok(())
@@ -623,6 +685,9 @@ impl StreamingBuilder for DepthTracker {
 /// This utility function converts an already-built AST into builder events.
 /// Useful for reusing builder implementations with pre-parsed ASTs.
 ///
+/// Returns [`BuildControl::Stop`] if the builder asked to stop before the
+/// whole tree was walked, [`BuildControl::Continue`] otherwise.
+///
 /// # Example
 ///
 /// ```
@@ -641,8 +706,50 @@ pub fn walk_ast<B: StreamingBuilder>(
     arena: &super::arena::AstArena,
     input: &str,
     builder: &mut B,
-) -> BuildResult<()> {
-    walk_ast_inner(node, arena, input, builder, 0)
+) -> BuildResult<BuildControl> {
+    walk_ast_with_ignored(node, arena, input, &[], builder)
+}
+
+/// [`walk_ast`], also reporting skipped input through
+/// [`on_ignored`](StreamingBuilder::on_ignored)
+///
+/// `ignored` holds `(start, end)` byte ranges in input order. Each one is
+/// delivered just before the first string starting at or after it; any
+/// left over are delivered once the tree has been walked.
+pub fn walk_ast_with_ignored<B: StreamingBuilder>(
+    node: &super::ast::AstNode,
+    arena: &super::arena::AstArena,
+    input: &str,
+    ignored: &[(usize, usize)],
+    builder: &mut B,
+) -> BuildResult<BuildControl> {
+    let mut ignored = ignored;
+    if walk_ast_inner(node, arena, input, &mut ignored, builder, 0)?.is_stop() {
+        return Ok(BuildControl::Stop);
+    }
+    flush_ignored(input, &mut ignored, usize::MAX, builder)
+}
+
+/// Deliver the ignored spans starting before `before`
+fn flush_ignored<B: StreamingBuilder>(
+    input: &str,
+    ignored: &mut &[(usize, usize)],
+    before: usize,
+    builder: &mut B,
+) -> BuildResult<BuildControl> {
+    while let Some((&(start, end), rest)) = ignored.split_first() {
+        if start >= before {
+            break;
+        }
+        *ignored = rest;
+        if let Some(text) = input.get(start..end) {
+            builder.on_ignored(text, start, end - start)?;
+            if builder.control().is_stop() {
+                return Ok(BuildControl::Stop);
+            }
+        }
+    }
+    Ok(BuildControl::Continue)
 }
 
 #[allow(clippy::only_used_in_recursion)]
@@ -650,32 +757,54 @@ fn walk_ast_inner<B: StreamingBuilder>(
     node: &super::ast::AstNode,
     arena: &super::arena::AstArena,
     input: &str,
+    ignored: &mut &[(usize, usize)],
     builder: &mut B,
     depth: usize,
-) -> BuildResult<()> {
+) -> BuildResult<BuildControl> {
+    // Deliver one event, bailing out if it failed or the builder asked to stop
+    macro_rules! emit {
+        ($event:expr) => {
+            $event?;
+            if builder.control().is_stop() {
+                return Ok(BuildControl::Stop);
+            }
+        };
+    }
+    // Walk a child node, propagating a stop request
+    macro_rules! descend {
+        ($node:expr, $depth:expr) => {
+            if walk_ast_inner($node, arena, input, ignored, builder, $depth)?.is_stop() {
+                return Ok(BuildControl::Stop);
+            }
+        };
+    }
+
     match node {
         super::ast::AstNode::Nil => {
-            builder.on_nil()?;
+            emit!(builder.on_nil());
         }
         super::ast::AstNode::Bool(value) => {
-            builder.on_bool(*value)?;
+            emit!(builder.on_bool(*value));
         }
         super::ast::AstNode::Int(value) => {
-            builder.on_int(*value)?;
+            emit!(builder.on_int(*value));
         }
         super::ast::AstNode::Float(value) => {
-            builder.on_float(*value)?;
+            emit!(builder.on_float(*value));
         }
         super::ast::AstNode::StringRef { pool_index } => {
             let value = arena.get_string(*pool_index as usize);
-            builder.on_string(value, 0, value.len())?;
+            emit!(builder.on_string(value, 0, value.len()));
         }
         super::ast::AstNode::InputRef { offset, length } => {
             let start = *offset as usize;
             let end = start + (*length as usize);
             if end <= input.len() {
+                if flush_ignored(input, ignored, start, builder)?.is_stop() {
+                    return Ok(BuildControl::Stop);
+                }
                 let value = &input[start..end];
-                builder.on_string(value, start, *length as usize)?;
+                emit!(builder.on_string(value, start, *length as usize));
             }
         }
         super::ast::AstNode::Array { pool_index, length } => {
@@ -684,15 +813,15 @@ fn walk_ast_inner<B: StreamingBuilder>(
             } else {
                 None
             };
-            builder.on_array_start(expected)?;
+            emit!(builder.on_array_start(expected));
 
             let items = arena.get_array(*pool_index as usize, *length as usize);
             for (i, item) in items.iter().enumerate() {
-                walk_ast_inner(item, arena, input, builder, depth + 1)?;
-                builder.on_array_element(i)?;
+                descend!(item, depth + 1);
+                emit!(builder.on_array_element(i));
             }
 
-            builder.on_array_end(*length as usize)?;
+            emit!(builder.on_array_end(*length as usize));
         }
         super::ast::AstNode::Hash { pool_index, length } => {
             let expected = if *length > 0 {
@@ -700,24 +829,24 @@ fn walk_ast_inner<B: StreamingBuilder>(
             } else {
                 None
             };
-            builder.on_hash_start(expected)?;
+            emit!(builder.on_hash_start(expected));
 
             let pairs = arena.get_hash_items(*pool_index as usize, *length as usize);
             for (key, value) in pairs {
-                builder.on_hash_key(&key)?;
-                walk_ast_inner(&value, arena, input, builder, depth + 1)?;
-                builder.on_hash_value(&key)?;
+                emit!(builder.on_hash_key(&key));
+                descend!(&value, depth + 1);
+                emit!(builder.on_hash_value(&key));
             }
 
-            builder.on_hash_end(*length as usize)?;
+            emit!(builder.on_hash_end(*length as usize));
         }
         super::ast::AstNode::Tagged { tag: _, value } => {
             // For Tagged nodes, just walk the inner value
             // Tags are metadata for the batch encoder, not part of the AST structure
-            walk_ast_inner(value, arena, input, builder, depth)?;
+            descend!(value, depth);
         }
     }
-    Ok(())
+    Ok(BuildControl::Continue)
 }
 
 #[cfg(test)]
@@ -893,4 +1022,61 @@ mod tests {
         assert_eq!(events[0], "hash_start(Some(2))");
         assert_eq!(events[7], "hash_end(2)");
     }
+
+    /// Collects strings until it has `limit` of them
+    struct FirstStrings {
+        limit: usize,
+        strings: Vec<String>,
+    }
+
+    impl StreamingBuilder for FirstStrings {
+        type Output = Vec<String>;
+
+        fn on_string(&mut self, value: &str, _offset: usize, _length: usize) -> BuildResult<()> {
+            self.strings.push(value.to_string());
+            Ok(())
+        }
+
+        fn control(&self) -> BuildControl {
+            if self.strings.len() >= self.limit {
+                BuildControl::Stop
+            } else {
+                BuildControl::Continue
+            }
+        }
+
+        fn finish(&mut self) -> BuildResult<Vec<String>> {
+            Ok(std::mem::take(&mut self.strings))
+        }
+    }
+
+    #[test]
+    fn test_walk_ast_stops_when_builder_asks() {
+        use crate::portable::arena::AstArena;
+        use crate::portable::ast::AstNode;
+
+        let input = "a b c";
+        let mut arena = AstArena::new();
+        let items = [0, 2, 4]
+            .into_iter()
+            .map(|offset| AstNode::InputRef { offset, length: 1 })
+            .collect();
+        let array = arena.alloc_array(items);
+
+        let mut builder = FirstStrings {
+            limit: 1,
+            strings: Vec::new(),
+        };
+        let control = walk_ast(&array, &arena, input, &mut builder).unwrap();
+        assert_eq!(control, BuildControl::Stop);
+        assert_eq!(builder.finish().unwrap(), vec!["a"]);
+
+        let mut builder = FirstStrings {
+            limit: 10,
+            strings: Vec::new(),
+        };
+        let control = walk_ast(&array, &arena, input, &mut builder).unwrap();
+        assert_eq!(control, BuildControl::Continue);
+        assert_eq!(builder.finish().unwrap(), vec!["a", "b", "c"]);
+    }
 }
@@ -7,6 +7,7 @@ use std::collections::HashMap;
 
 use super::super::arena::AstArena;
 use super::super::ast::AstNode;
+use super::super::grammar::{Atom, Grammar};
 use super::{TransformError, Value};
 
 // ============================================================================
@@ -19,7 +20,7 @@ pub fn ast_to_value(node: &AstNode, arena: &AstArena, input: &str) -> Value {
         AstNode::Nil => Value::Nil,
         AstNode::Bool(b) => Value::Bool(*b),
         AstNode::Int(n) => Value::Int(*n),
-        AstNode::Float(f) => Value::Float(f.to_bits() as f64), // Approximate
+        AstNode::Float(f) => Value::Float(*f),
         AstNode::StringRef { pool_index } => {
             let s = arena.get_string(*pool_index as usize);
             Value::String(s.to_string())
@@ -54,6 +55,172 @@ pub fn ast_to_value(node: &AstNode, arena: &AstArena, input: &str) -> Value {
     }
 }
 
+/// Convert an AstNode directly to a `serde_json::Value`
+///
+/// Mirrors [`ast_to_value`] without building an intermediate [`Value`]:
+/// input and interned strings become JSON strings, arrays and hashes are
+/// converted recursively, and tagged nodes are unwrapped. Floats that JSON
+/// cannot represent (NaN, infinities) become `null`.
+#[cfg(feature = "json")]
+pub fn ast_to_json(node: &AstNode, arena: &AstArena, input: &str) -> serde_json::Value {
+    use serde_json::Value as Json;
+
+    match node {
+        AstNode::Nil => Json::Null,
+        AstNode::Bool(b) => Json::Bool(*b),
+        AstNode::Int(n) => Json::from(*n),
+        AstNode::Float(f) => serde_json::Number::from_f64(*f).map_or(Json::Null, Json::Number),
+        AstNode::StringRef { pool_index } => {
+            Json::String(arena.get_string(*pool_index as usize).to_string())
+        }
+        AstNode::InputRef { offset, length } => {
+            let start = *offset as usize;
+            let end = start + *length as usize;
+            Json::String(input[start..end.min(input.len())].to_string())
+        }
+        AstNode::Array { pool_index, length } => Json::Array(
+            arena
+                .get_array(*pool_index as usize, *length as usize)
+                .iter()
+                .map(|item| ast_to_json(item, arena, input))
+                .collect(),
+        ),
+        AstNode::Hash { pool_index, length } => Json::Object(
+            arena
+                .get_hash_items(*pool_index as usize, *length as usize)
+                .into_iter()
+                .map(|(k, v)| (k, ast_to_json(&v, arena, input)))
+                .collect(),
+        ),
+        AstNode::Tagged { tag: _, value } => ast_to_json(value, arena, input),
+    }
+}
+
+/// Convert an AstNode to a Value, producing numbers for numeric rules
+///
+/// Unlike [`ast_to_value`], which always turns matched input into
+/// `Value::String`, this uses the grammar to find labels that name a
+/// numeric regex (e.g. `re("[0-9]+").label("int")`) and converts their
+/// matched text to `Value::Int` or `Value::Float`.
+///
+/// A regex counts as numeric when it only uses digits, `\d`, signs,
+/// decimal points, exponents and regex operators. Matched text that does
+/// not parse as a number is kept as a string. So is text matched by an
+/// integer-only pattern (no decimal point or exponent) that overflows
+/// `i64`, rather than silently losing precision as a float.
+///
+/// Use this when the grammar labels its numbers and you want them typed
+/// without a transform rule; use [`ast_to_value`] when labels are reused
+/// for non-numeric content or the exact matched text must be preserved.
+pub fn ast_to_value_typed(
+    node: &AstNode,
+    arena: &AstArena,
+    input: &str,
+    grammar: &Grammar,
+) -> Value {
+    let numeric_labels = numeric_labels(grammar);
+    ast_to_value_with_labels(node, arena, input, &numeric_labels)
+}
+
+/// Collect the labels of named atoms that wrap a numeric regex
+///
+/// Each label maps to whether its pattern only admits integers.
+fn numeric_labels(grammar: &Grammar) -> HashMap<&str, bool> {
+    let numeric_pattern = |idx: usize| match grammar.get_atom(idx) {
+        Some(Atom::Re { pattern }) if is_numeric_pattern(pattern) => Some(pattern.as_str()),
+        // Repetitions of a char class are matched in bulk into a single input ref
+        Some(Atom::Repetition { atom, .. }) => match grammar.get_atom(*atom) {
+            Some(Atom::Re { pattern }) if is_numeric_pattern(pattern) => Some(pattern.as_str()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    grammar
+        .atoms
+        .iter()
+        .filter_map(|atom| match atom {
+            Atom::Named { name, atom } => {
+                numeric_pattern(*atom).map(|pattern| (name.as_str(), is_integer_pattern(pattern)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Check whether a regex pattern can only match numeric text
+fn is_numeric_pattern(pattern: &str) -> bool {
+    let has_digits = pattern.contains("0-9") || pattern.contains("\\d");
+    let rest = pattern
+        .replace("0-9", "")
+        .replace("\\d", "")
+        .replace("\\.", "");
+    has_digits
+        && rest
+            .chars()
+            .all(|c| c.is_ascii_digit() || "[](){},+-*?.|^$eE".contains(c))
+}
+
+/// Check whether a numeric pattern can only match integers
+fn is_integer_pattern(pattern: &str) -> bool {
+    !pattern.contains('.') && !pattern.contains(['e', 'E'])
+}
+
+fn ast_to_value_with_labels(
+    node: &AstNode,
+    arena: &AstArena,
+    input: &str,
+    numeric_labels: &HashMap<&str, bool>,
+) -> Value {
+    match node {
+        AstNode::Array { pool_index, length } => {
+            let items = arena.get_array(*pool_index as usize, *length as usize);
+            let values: Vec<Value> = items
+                .iter()
+                .map(|i| ast_to_value_with_labels(i, arena, input, numeric_labels))
+                .collect();
+            Value::Array(values)
+        }
+        AstNode::Hash { pool_index, length } => {
+            let pairs = arena.get_hash_items(*pool_index as usize, *length as usize);
+            let mut map = HashMap::new();
+            for (k, v) in pairs {
+                let value = ast_to_value_with_labels(&v, arena, input, numeric_labels);
+                let value = match numeric_labels.get(k.as_str()) {
+                    Some(&integer_only) => parse_numeric(value, integer_only),
+                    None => value,
+                };
+                map.insert(k.clone(), value);
+            }
+            Value::Hash(map)
+        }
+        AstNode::Tagged { tag: _, value } => {
+            ast_to_value_with_labels(value, arena, input, numeric_labels)
+        }
+        _ => ast_to_value(node, arena, input),
+    }
+}
+
+/// Convert a matched string to an integer or float, if it parses as one
+///
+/// With `integer_only`, text that doesn't fit in an `i64` stays a string.
+fn parse_numeric(value: Value, integer_only: bool) -> Value {
+    match value {
+        Value::String(s) => {
+            if let Ok(n) = s.parse::<i64>() {
+                Value::Int(n)
+            } else if integer_only {
+                Value::String(s)
+            } else if let Ok(f) = s.parse::<f64>() {
+                Value::Float(f)
+            } else {
+                Value::String(s)
+            }
+        }
+        other => other,
+    }
+}
+
 /// Get the source span for an AST node, if available
 ///
 /// Returns None for leaf nodes without position info (Nil, Bool, Int, Float)
@@ -51,11 +51,14 @@ mod value;
 
 // Re-export all public types
 pub use direct::{direct_helpers, DirectTransform};
+#[cfg(feature = "json")]
+pub use helpers::ast_to_json;
 pub use helpers::{
-    ast_node_span, ast_to_value, ast_to_value_with_span, extract_field, extract_int, extract_string,
+    ast_node_span, ast_to_value, ast_to_value_typed, ast_to_value_with_span, extract_field,
+    extract_int, extract_string,
 };
 pub use pattern::{Bindings, HashPatternBuilder, Pattern};
-pub use transform::{Transform, TransformError, TypedTransform};
+pub use transform::{Transform, TransformError, TraversalOrder, TypedTransform};
 pub use value::Value;
 
 // ============================================================================
@@ -199,6 +202,166 @@ mod tests {
         assert_eq!(result.as_int(), Some(42));
     }
 
+    fn order_transform(order: TraversalOrder) -> Transform {
+        Transform::new()
+            .with_order(order)
+            .rule("num", |v| {
+                let s = extract_string(v)?;
+                s.parse::<i64>()
+                    .map(Value::int)
+                    .map_err(|e| TransformError::Custom(e.to_string()))
+            })
+            .rule("double", |v| match v.as_int() {
+                Some(n) => Ok(Value::int(n * 2)),
+                None => Ok(v.clone()),
+            })
+    }
+
+    #[test]
+    fn test_transform_post_order() {
+        let value = Value::hash(vec![(
+            "double",
+            Value::hash(vec![("num", Value::string("21"))]),
+        )]);
+
+        // "num" runs first, so "double" sees an int
+        let transform = order_transform(TraversalOrder::Post);
+        assert_eq!(transform.order(), TraversalOrder::Post);
+        assert_eq!(transform.apply(&value).unwrap().as_int(), Some(42));
+    }
+
+    #[test]
+    fn test_transform_pre_order() {
+        let value = Value::hash(vec![(
+            "double",
+            Value::hash(vec![("num", Value::string("21"))]),
+        )]);
+
+        // "double" runs first and sees the raw hash, leaving it unchanged;
+        // "num" then converts its result
+        let transform = order_transform(TraversalOrder::Pre);
+        assert_eq!(transform.apply(&value).unwrap().as_int(), Some(21));
+    }
+
+    #[test]
+    fn test_transform_default_order_is_post() {
+        assert_eq!(Transform::new().order(), TraversalOrder::Post);
+    }
+
+    #[test]
+    fn test_apply_fixpoint_folds_constants() {
+        // Folds an addition once both operands are numbers
+        let transform = Transform::new().pattern(
+            Pattern::hash()
+                .field_pattern(
+                    "add",
+                    Pattern::hash()
+                        .field("left", "l")
+                        .field("right", "r")
+                        .build(),
+                )
+                .build(),
+            |b| Ok(Value::int(b.get_int("l")? + b.get_int("r")?)),
+        );
+        let add = |left: Value, right: Value| {
+            Value::hash(vec![(
+                "add",
+                Value::hash(vec![("left", left), ("right", right)]),
+            )])
+        };
+        // ((1+2)+3)
+        let tree = add(add(Value::int(1), Value::int(2)), Value::int(3));
+
+        // One pass only folds the inner addition
+        assert_eq!(
+            transform.apply(&tree).unwrap(),
+            add(Value::int(3), Value::int(3))
+        );
+        assert_eq!(transform.apply_fixpoint(&tree, 2).unwrap(), Value::int(6));
+        assert!(matches!(
+            transform.apply_fixpoint(&tree, 1),
+            Err(TransformError::NoFixpoint { iterations: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_apply_with_state_assigns_sequential_ids() {
+        // Each named node gets the next id; children are numbered first
+        let transform = Transform::new().stateful_rule("node", |v, next_id: &mut i64| {
+            let mut node = v.as_hash().cloned().unwrap_or_default();
+            node.insert("id".to_string(), Value::int(*next_id));
+            *next_id += 1;
+            Ok(Value::Hash(node))
+        });
+        let node = |name: &str, children: Vec<Value>| {
+            Value::hash(vec![(
+                "node",
+                Value::hash(vec![
+                    ("name", Value::string(name)),
+                    ("children", Value::array(children)),
+                ]),
+            )])
+        };
+        let tree = node(
+            "root",
+            vec![node("a", vec![node("b", vec![])]), node("c", vec![])],
+        );
+
+        let mut next_id = 0i64;
+        let result = transform.apply_with_state(&tree, &mut next_id).unwrap();
+        assert_eq!(next_id, 4);
+
+        let id = |v: &Value| v.get("id").and_then(Value::as_int);
+        let children = |v: &Value| {
+            v.get("children")
+                .and_then(Value::as_array)
+                .unwrap()
+                .to_vec()
+        };
+        let (a, c) = (&children(&result)[0], &children(&result)[1]);
+        assert_eq!(id(&children(a)[0]), Some(0));
+        assert_eq!(id(a), Some(1));
+        assert_eq!(id(c), Some(2));
+        assert_eq!(id(&result), Some(3));
+    }
+
+    #[test]
+    fn test_stateful_pattern_and_missing_state() {
+        let pattern = Pattern::hash().field("ident", "name").build();
+        let transform =
+            Transform::new().stateful_pattern(pattern, |b, symbols: &mut Vec<String>| {
+                let name = b.get_string("name")?.to_string();
+                let index = match symbols.iter().position(|s| *s == name) {
+                    Some(index) => index,
+                    None => {
+                        symbols.push(name);
+                        symbols.len() - 1
+                    }
+                };
+                Ok(Value::int(index as i64))
+            });
+        let ident = |name: &str| Value::hash(vec![("ident", Value::string(name))]);
+        let value = Value::array(vec![ident("x"), ident("y"), ident("x")]);
+
+        let mut symbols: Vec<String> = Vec::new();
+        let result = transform.apply_with_state(&value, &mut symbols).unwrap();
+        assert_eq!(
+            result,
+            Value::array(vec![Value::int(0), Value::int(1), Value::int(0)])
+        );
+        assert_eq!(symbols, ["x", "y"]);
+
+        // Without state, or with the wrong type of state, the rule can't run
+        assert!(matches!(
+            transform.apply(&value),
+            Err(TransformError::Custom(_))
+        ));
+        assert!(matches!(
+            transform.apply_with_state(&value, &mut 0u32),
+            Err(TransformError::TypeMismatch { .. })
+        ));
+    }
+
     #[test]
     fn test_extract_helpers() {
         let value = Value::hash(vec![("x", Value::int(10)), ("y", Value::string("test"))]);
@@ -337,6 +500,15 @@ mod tests {
         assert!(ast_node_span(&AstNode::Float(1.5), "").is_none());
     }
 
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn test_ast_to_value_float() {
+        let arena = AstArena::new();
+        let value = ast_to_value(&AstNode::Float(3.14), &arena, "");
+        let f = value.as_float().unwrap();
+        assert!((f - 3.14).abs() < 1e-12, "got {}", f);
+    }
+
     #[test]
     fn test_ast_to_value_with_span() {
         let input = "hello world";
@@ -357,6 +529,234 @@ mod tests {
         assert_eq!(mapped.span().end.offset, 5);
     }
 
+    #[test]
+    fn test_transform_error_at_span() {
+        let input = "let y = x";
+        let arena = AstArena::new();
+        let node = AstNode::InputRef {
+            offset: 8,
+            length: 1,
+        };
+        let mapped = ast_to_value_with_span(&node, &arena, input);
+
+        let defined = ["y"];
+        let bindings = Pattern::simple("name").match_mapped(&mapped).unwrap();
+        let name = bindings.get_string("name").unwrap();
+        assert!(!defined.contains(&name));
+
+        let err = bindings.error_at("name", format!("undefined variable `{}`", name));
+        match &err {
+            TransformError::AtSpan { span, message } => {
+                assert_eq!(message, "undefined variable `x`");
+                assert_eq!((span.start.offset, span.end.offset), (8, 9));
+                assert_eq!(span.start.column, 9);
+            }
+            other => panic!("expected AtSpan, got {:?}", other),
+        }
+        assert_eq!(err.span(), bindings.span_of("name"));
+        assert!(err
+            .to_string()
+            .starts_with("undefined variable `x` at line 1"));
+
+        // Bindings without spans fall back to a plain error
+        let plain = Pattern::simple("name").match_value(mapped.inner()).unwrap();
+        assert!(plain.span_of("name").is_none());
+        assert!(plain.error_at("name", "oops").span().is_none());
+        assert!(TransformError::at(&mapped, "bad").span().is_some());
+    }
+
+    #[test]
+    fn test_transform_apply_mapped_rule_rejects_at_span() {
+        use std::collections::HashMap;
+
+        let input = "let y = x";
+        let span = SourceSpan::from_offsets(input, 8, 9);
+        let var = SourceMapped::new(Value::string("x"), span);
+
+        let defined = ["y"];
+        let transform = Transform::new().pattern(Pattern::simple("name"), move |b| {
+            let name = b.get_string("name")?;
+            if defined.contains(&name) {
+                Ok(Value::string(name))
+            } else {
+                Err(b.error_at("name", format!("undefined variable `{}`", name)))
+            }
+        });
+
+        let err = transform.apply_mapped(&var).unwrap_err();
+        assert!(
+            matches!(&err, TransformError::AtSpan { message, .. } if message == "undefined variable `x`")
+        );
+        assert_eq!(
+            err.span().map(|s| (s.start.offset, s.end.offset)),
+            Some((8, 9))
+        );
+
+        let ok = SourceMapped::new(Value::string("y"), SourceSpan::from_offsets(input, 4, 5));
+        assert_eq!(transform.apply_mapped(&ok).unwrap(), Value::string("y"));
+
+        // Errors raised without a span are reported at the value's span
+        let strict = Transform::new().rule("n", |_| Err(TransformError::Custom("bad".into())));
+        let mut hash = HashMap::new();
+        hash.insert("n".to_string(), Value::int(1));
+        let mapped = SourceMapped::new(Value::Hash(hash), SourceSpan::from_offsets(input, 0, 3));
+        let err = strict.apply_mapped(&mapped).unwrap_err();
+        assert_eq!(
+            err.span().map(|s| (s.start.offset, s.end.offset)),
+            Some((0, 3))
+        );
+        assert!(err.to_string().starts_with("bad at"));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_ast_to_json_nested_arrays_and_hashes() {
+        use serde_json::{Map, Number, Value as Json};
+
+        let input = "key=42";
+        let mut arena = AstArena::new();
+        let key = arena.input_ref(0, 3);
+        let tag = arena.intern_string(":sequence");
+        let inner = arena.alloc_array(vec![
+            AstNode::Int(42),
+            AstNode::Float(2.5),
+            AstNode::Nil,
+            AstNode::Bool(true),
+        ]);
+        let nested = arena.alloc_array(vec![inner, tag]);
+        let hash = arena.alloc_hash(vec![
+            ("name".to_string(), key),
+            ("items".to_string(), nested),
+        ]);
+        let root = arena.alloc_array(vec![hash, AstNode::Float(f64::NAN)]);
+
+        let mut object = Map::new();
+        object.insert("name".to_string(), Json::String("key".to_string()));
+        object.insert(
+            "items".to_string(),
+            Json::Array(vec![
+                Json::Array(vec![
+                    Json::from(42i64),
+                    Json::Number(Number::from_f64(2.5).unwrap()),
+                    Json::Null,
+                    Json::Bool(true),
+                ]),
+                Json::String(":sequence".to_string()),
+            ]),
+        );
+        let expected = Json::Array(vec![Json::Object(object), Json::Null]);
+
+        assert_eq!(ast_to_json(&root, &arena, input), expected);
+    }
+
+    #[test]
+    fn test_ast_to_value_typed_numeric_rules() {
+        use crate::portable::parser::PortableParser;
+        use crate::portable::parser_dsl::{re, str, GrammarBuilder, ParsletExt};
+
+        let grammar = GrammarBuilder::new()
+            .rule(
+                "root",
+                re("[0-9]")
+                    .many1()
+                    .label("int")
+                    .then(str(" "))
+                    .then(re("[a-z]").many1().label("word")),
+            )
+            .build();
+
+        let input = "42 abc";
+        let mut arena = AstArena::for_input(input.len());
+        let node = PortableParser::new(&grammar, input, &mut arena)
+            .parse()
+            .unwrap();
+
+        let raw = ast_to_value(&node, &arena, input);
+        let typed = ast_to_value_typed(&node, &arena, input, &grammar);
+
+        // Sequences nest as tagged arrays, so search the tree for the label
+        fn find(value: &Value, key: &str) -> Option<Value> {
+            match value {
+                Value::Hash(h) => h.get(key).cloned(),
+                Value::Array(items) => items.iter().find_map(|item| find(item, key)),
+                _ => None,
+            }
+        }
+
+        assert_eq!(find(&raw, "int"), Some(Value::string("42")));
+        assert_eq!(find(&typed, "int"), Some(Value::int(42)));
+        assert_eq!(find(&typed, "word"), Some(Value::string("abc")));
+    }
+
+    #[test]
+    fn test_ast_to_value_typed_float_and_non_numeric() {
+        use crate::portable::grammar::{Atom, Grammar};
+
+        let mut grammar = Grammar::new();
+        let float_re = grammar.add_atom(Atom::Re {
+            pattern: r"-?[0-9]+\.[0-9]+".to_string(),
+        });
+        grammar.add_atom(Atom::Named {
+            name: "float".to_string(),
+            atom: float_re,
+        });
+        let ident_re = grammar.add_atom(Atom::Re {
+            pattern: "[a-z0-9]+".to_string(),
+        });
+        grammar.add_atom(Atom::Named {
+            name: "ident".to_string(),
+            atom: ident_re,
+        });
+
+        let input = "-1.5 123";
+        let mut arena = AstArena::for_input(input.len());
+        let float_node = arena.input_ref(0, 4);
+        let ident_node = arena.input_ref(5, 3);
+        let (pool_index, length) =
+            arena.store_hash(&[("float", float_node), ("ident", ident_node)]);
+        let node = AstNode::Hash { pool_index, length };
+
+        let typed = ast_to_value_typed(&node, &arena, input, &grammar);
+        assert_eq!(typed.get("float"), Some(&Value::float(-1.5)));
+        // Letters in the pattern make the rule non-numeric
+        assert_eq!(typed.get("ident"), Some(&Value::string("123")));
+    }
+
+    #[test]
+    fn test_ast_to_value_typed_integer_overflow_stays_string() {
+        use crate::portable::grammar::{Atom, Grammar};
+
+        let mut grammar = Grammar::new();
+        let int_re = grammar.add_atom(Atom::Re {
+            pattern: "[0-9]+".to_string(),
+        });
+        grammar.add_atom(Atom::Named {
+            name: "int".to_string(),
+            atom: int_re,
+        });
+        let num_re = grammar.add_atom(Atom::Re {
+            pattern: r"[0-9]+(\.[0-9]+)?".to_string(),
+        });
+        grammar.add_atom(Atom::Named {
+            name: "num".to_string(),
+            atom: num_re,
+        });
+
+        let big = "99999999999999999999";
+        let input = format!("{big} {big}");
+        let mut arena = AstArena::for_input(input.len());
+        let int_node = arena.input_ref(0, big.len());
+        let num_node = arena.input_ref(big.len() + 1, big.len());
+        let (pool_index, length) = arena.store_hash(&[("int", int_node), ("num", num_node)]);
+        let node = AstNode::Hash { pool_index, length };
+
+        let typed = ast_to_value_typed(&node, &arena, &input, &grammar);
+        // An integer-only rule never turns into a float
+        assert_eq!(typed.get("int"), Some(&Value::string(big)));
+        // A rule that admits decimals still falls back to a float
+        assert_eq!(typed.get("num"), Some(&Value::float(1e20)));
+    }
+
     #[test]
     fn test_ast_to_value_with_span_int() {
         let arena = AstArena::new();
@@ -389,4 +789,120 @@ mod tests {
         assert_eq!(combined.span().start.offset, 0);
         assert_eq!(combined.span().end.offset, 11);
     }
+
+    #[test]
+    fn test_apply_with_source_map_arithmetic() {
+        use crate::portable::parsed_tree::ParsedTree;
+        use crate::portable::source_map::SourceMapBuilder;
+
+        // { "sum" => { "left" => "12", "op" => "+", "right" => "305" } }
+        let input = "12 + 305";
+        let mut arena = AstArena::new();
+        let operand = |offset, length| AstNode::InputRef { offset, length };
+        let (pool_index, length) = arena.store_hash(&[
+            ("left", operand(0, 2)),
+            ("op", operand(3, 1)),
+            ("right", operand(5, 3)),
+        ]);
+        let operands = AstNode::Hash { pool_index, length };
+        let (pool_index, length) = arena.store_hash(&[("sum", operands)]);
+        let root = AstNode::Hash { pool_index, length };
+
+        let transform = Transform::new().rule("sum", |v| {
+            let operand = |field| -> Result<i64, TransformError> {
+                extract_string(extract_field(v, field)?)?
+                    .parse()
+                    .map_err(|_| TransformError::Custom(format!("bad {}", field)))
+            };
+            Ok(Value::int(operand("left")? + operand("right")?))
+        });
+        let tree = ParsedTree::new(root, &arena, input);
+        let (value, map) = transform
+            .apply_with_source_map(&tree, &SourceMapBuilder::new(input))
+            .unwrap();
+        assert_eq!(value, Value::int(317));
+
+        let span_of = |wanted: &Value| {
+            map.iter()
+                .find(|mapped| mapped.inner() == wanted)
+                .map(|mapped| (mapped.span().start.offset, mapped.span().end.offset))
+        };
+        // The sum maps to the whole expression, each operand to its digits
+        assert_eq!(span_of(&Value::int(317)), Some((0, 8)));
+        assert_eq!(span_of(&Value::string("12")), Some((0, 2)));
+        assert_eq!(span_of(&Value::string("305")), Some((5, 8)));
+        assert_eq!(map.iter().last().unwrap().inner(), &value);
+    }
+
+    #[test]
+    fn test_apply_with_source_map_errors_at_node_span() {
+        use crate::portable::parsed_tree::ParsedTree;
+        use crate::portable::source_map::SourceMapBuilder;
+
+        let input = "a = x";
+        let mut arena = AstArena::new();
+        let (pool_index, length) = arena.store_hash(&[(
+            "var",
+            AstNode::InputRef {
+                offset: 4,
+                length: 1,
+            },
+        )]);
+        let root = AstNode::Hash { pool_index, length };
+
+        let transform = Transform::new().rule("var", |v| {
+            Err(TransformError::Custom(format!(
+                "undefined {:?}",
+                v.as_str()
+            )))
+        });
+        let tree = ParsedTree::new(root, &arena, input);
+        let err = transform
+            .apply_with_source_map(&tree, &SourceMapBuilder::new(input))
+            .unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!((span.start.offset, span.end.offset), (4, 5));
+    }
+
+    #[test]
+    fn test_to_ast_round_trips_nested_value() {
+        let value = Value::hash(vec![
+            ("name", Value::string("point")),
+            (
+                "coords",
+                Value::array(vec![Value::int(-3), Value::float(0.25), Value::Nil]),
+            ),
+            (
+                "meta",
+                Value::hash(vec![
+                    ("visible", Value::bool(true)),
+                    (
+                        "tags",
+                        Value::array(vec![Value::string("a"), Value::array(vec![])]),
+                    ),
+                ]),
+            ),
+        ]);
+
+        let mut arena = AstArena::new();
+        let ast = value.to_ast(&mut arena);
+        assert_eq!(ast_to_value(&ast, &arena, ""), value);
+
+        // Hash keys are stored sorted, so equal values build identical nodes
+        let again = value.clone().to_ast(&mut arena);
+        match (ast, again) {
+            (AstNode::Hash { pool_index: a, .. }, AstNode::Hash { pool_index: b, .. }) => {
+                let keys = |index: u32| {
+                    arena
+                        .get_hash_items(index as usize, 3)
+                        .into_iter()
+                        .map(|(key, _)| key)
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(keys(a), ["coords", "meta", "name"]);
+                assert_eq!(keys(a), keys(b));
+            }
+            other => panic!("expected hashes, got {:?}", other),
+        }
+    }
 }
@@ -7,6 +7,8 @@
 use std::collections::HashMap;
 
 use super::{TransformError, Value};
+use crate::portable::source_location::SourceSpan;
+use crate::portable::source_map::SourceMapped;
 
 /// Pattern for matching values in transformations
 #[derive(Debug, Clone)]
@@ -79,6 +81,26 @@ impl Pattern {
         HashPatternBuilder::new()
     }
 
+    /// Try to match this pattern against a source-mapped value
+    ///
+    /// The resulting bindings retain the value's span, so actions can report
+    /// errors with [`Bindings::span_of`] / [`Bindings::error_at`]. Variables
+    /// bound to the whole value get its exact span; variables bound inside a
+    /// hash or array fall back to it.
+    pub fn match_mapped(&self, value: &SourceMapped<Value>) -> Option<Bindings> {
+        self.match_at(value.inner(), *value.span())
+    }
+
+    /// [`match_mapped`](Self::match_mapped) with the span given separately
+    pub(crate) fn match_at(&self, value: &Value, span: SourceSpan) -> Option<Bindings> {
+        let mut bindings = self.match_value(value)?;
+        bindings.span = Some(span);
+        if let Pattern::Simple(var) | Pattern::Sequence(var) | Pattern::Subtree(var) = self {
+            bindings.spans.insert(var.clone(), span);
+        }
+        Some(bindings)
+    }
+
     /// Try to match this pattern against a value
     pub fn match_value(&self, value: &Value) -> Option<Bindings> {
         match self {
@@ -229,6 +251,10 @@ impl Default for HashPatternBuilder {
 #[derive(Debug, Clone)]
 pub struct Bindings {
     values: HashMap<String, Value>,
+    /// Spans of individual variables, when known
+    spans: HashMap<String, SourceSpan>,
+    /// Span of the whole matched value, when known
+    span: Option<SourceSpan>,
 }
 
 impl Bindings {
@@ -236,6 +262,8 @@ impl Bindings {
     pub fn new() -> Self {
         Self {
             values: HashMap::new(),
+            spans: HashMap::new(),
+            span: None,
         }
     }
 
@@ -244,6 +272,36 @@ impl Bindings {
         self.values.insert(name, value);
     }
 
+    /// Insert a binding together with its source span
+    pub fn insert_with_span(&mut self, name: String, value: Value, span: SourceSpan) {
+        self.spans.insert(name.clone(), span);
+        self.values.insert(name, value);
+    }
+
+    /// Get the source span of a binding
+    ///
+    /// Falls back to the span of the whole matched value when the variable
+    /// has no span of its own. Returns `None` if the bindings carry no spans.
+    ///
+    /// [`Pattern::match_mapped`] only knows the span of the value it was
+    /// given, so a variable bound inside a hash or array pattern reports the
+    /// whole value's span, not that of the field it matched. Use
+    /// [`insert_with_span`](Self::insert_with_span) to record finer spans.
+    pub fn span_of(&self, name: &str) -> Option<&SourceSpan> {
+        self.spans.get(name).or(self.span.as_ref())
+    }
+
+    /// Create an error pointing at a binding's source span
+    ///
+    /// Produces [`TransformError::AtSpan`] when a span is known, and
+    /// [`TransformError::Custom`] otherwise.
+    pub fn error_at(&self, name: &str, message: impl Into<String>) -> TransformError {
+        match self.span_of(name) {
+            Some(span) => TransformError::at_span(*span, message),
+            None => TransformError::Custom(message.into()),
+        }
+    }
+
     /// Get a binding as a Value
     pub fn get(&self, name: &str) -> Option<&Value> {
         self.values.get(name)
@@ -291,6 +349,12 @@ impl Bindings {
 
     /// Merge bindings, checking for conflicts
     pub fn merge(&mut self, other: Bindings) -> Option<()> {
+        for (name, span) in other.spans {
+            self.spans.entry(name).or_insert(span);
+        }
+        if self.span.is_none() {
+            self.span = other.span;
+        }
         for (name, value) in other.values {
             if let Some(existing) = self.values.get(&name) {
                 // Check constraint: same variable must have same value
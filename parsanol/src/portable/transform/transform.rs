@@ -3,10 +3,16 @@
 //! This module provides the `Transform` struct for rule-based transformations
 //! and `TransformError` for error handling.
 
+use std::any::Any;
 use std::collections::HashMap;
 use std::fmt;
 
 use super::{Bindings, HashPatternBuilder, Pattern, Value};
+use crate::portable::arena::AstArena;
+use crate::portable::ast::AstNode;
+use crate::portable::parsed_tree::ParsedTree;
+use crate::portable::source_location::SourceSpan;
+use crate::portable::source_map::{SourceMapBuilder, SourceMapCollection, SourceMapped};
 
 /// A transformation rule
 type TransformFn = Box<dyn Fn(&Value) -> Result<Value, TransformError> + Send + Sync>;
@@ -14,10 +20,66 @@ type TransformFn = Box<dyn Fn(&Value) -> Result<Value, TransformError> + Send +
 /// A pattern action function type
 type PatternAction = Box<dyn Fn(&Bindings) -> Result<Value, TransformError> + Send + Sync>;
 
+/// A transformation rule given the state passed to `apply_with_state`
+type StatefulFn = Box<dyn Fn(&Value, &mut dyn Any) -> Result<Value, TransformError> + Send + Sync>;
+
+/// A pattern action given the state passed to `apply_with_state`
+type StatefulPatternAction =
+    Box<dyn Fn(&Bindings, &mut dyn Any) -> Result<Value, TransformError> + Send + Sync>;
+
+/// A named rule, with or without access to state
+enum Rule {
+    Stateless(TransformFn),
+    Stateful(StatefulFn),
+}
+
+impl Rule {
+    fn call(&self, value: &Value, state: Option<&mut dyn Any>) -> Result<Value, TransformError> {
+        match self {
+            Rule::Stateless(f) => f(value),
+            Rule::Stateful(f) => f(value, state.ok_or_else(missing_state)?),
+        }
+    }
+}
+
+/// A pattern action, with or without access to state
+enum Action {
+    Stateless(PatternAction),
+    Stateful(StatefulPatternAction),
+}
+
+impl Action {
+    fn call(
+        &self,
+        bindings: &Bindings,
+        state: Option<&mut dyn Any>,
+    ) -> Result<Value, TransformError> {
+        match self {
+            Action::Stateless(f) => f(bindings),
+            Action::Stateful(f) => f(bindings, state.ok_or_else(missing_state)?),
+        }
+    }
+}
+
+/// Error for a stateful rule reached by a transform run without state
+fn missing_state() -> TransformError {
+    TransformError::Custom("stateful transform rule requires apply_with_state".to_string())
+}
+
+/// Recover the state type a stateful action was registered for
+fn downcast_state<S: 'static>(state: &mut dyn Any) -> Result<&mut S, TransformError> {
+    state
+        .downcast_mut::<S>()
+        .ok_or_else(|| TransformError::TypeMismatch {
+            expected: std::any::type_name::<S>().to_string(),
+            actual: "a different state type".to_string(),
+        })
+}
+
 /// A pattern-based transformation rule
 struct PatternRule {
     pattern: Pattern,
-    action: PatternAction,
+    action: Action,
 }
 
 /// Error during transformation
@@ -38,6 +100,42 @@ pub enum TransformError {
     PatternMismatch(String),
     /// Custom error
     Custom(String),
+    /// [`Transform::apply_fixpoint`] still changed the value after the
+    /// iteration limit
+    NoFixpoint {
+        /// Number of iterations that ran
+        iterations: usize,
+    },
+    /// Error attributed to a location in the source
+    AtSpan {
+        /// Source span of the offending value
+        span: SourceSpan,
+        /// Error message
+        message: String,
+    },
+}
+
+impl TransformError {
+    /// Create an error pointing at a source span
+    pub fn at_span(span: SourceSpan, message: impl Into<String>) -> Self {
+        TransformError::AtSpan {
+            span,
+            message: message.into(),
+        }
+    }
+
+    /// Create an error pointing at the span of a source-mapped value
+    pub fn at<T>(value: &SourceMapped<T>, message: impl Into<String>) -> Self {
+        Self::at_span(*value.span(), message)
+    }
+
+    /// Get the source span, if this error carries one
+    pub fn span(&self) -> Option<&SourceSpan> {
+        match self {
+            TransformError::AtSpan { span, .. } => Some(span),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for TransformError {
@@ -50,16 +148,44 @@ impl fmt::Display for TransformError {
             TransformError::MissingField(field) => write!(f, "Missing field: {}", field),
             TransformError::PatternMismatch(desc) => write!(f, "Pattern did not match: {}", desc),
             TransformError::Custom(msg) => write!(f, "{}", msg),
+            TransformError::NoFixpoint { iterations } => {
+                write!(
+                    f,
+                    "Transform did not converge after {} iterations",
+                    iterations
+                )
+            }
+            TransformError::AtSpan { span, message } => write!(f, "{} at {}", message, span),
         }
     }
 }
 
 impl std::error::Error for TransformError {}
 
+/// Order in which named rules and child values are transformed
+///
+/// Only named (single-key hash) rules are affected. Pattern rules always
+/// match the untransformed node and their result is returned as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraversalOrder {
+    /// Transform the children first, then apply the rule (the default)
+    ///
+    /// The rule for `{ "name": inner }` receives `inner` after it has
+    /// already been transformed, so rules see fully-built child values.
+    #[default]
+    Post,
+    /// Apply the rule first, then transform its result
+    ///
+    /// The rule for `{ "name": inner }` receives the raw `inner` value and
+    /// the value it returns is transformed again. A rule must therefore not
+    /// return a value that re-triggers itself, or the transform won't terminate.
+    Pre,
+}
+
 /// A transformation system for converting parse trees
 pub struct Transform {
     /// Rules indexed by name/pattern (for simple rule-based transforms)
-    rules: HashMap<String, TransformFn>,
+    rules: HashMap<String, Rule>,
     /// Pattern-based rules (for more complex matching)
     pattern_rules: Vec<PatternRule>,
     /// Default transform for unknown patterns
@@ -69,6 +195,8 @@ pub struct Transform {
     hash_pattern_index: HashMap<String, Vec<usize>>,
     /// Index of non-hash patterns (simple, sequence, subtree, etc.)
     non_hash_patterns: Vec<usize>,
+    /// Whether named rules run before or after their children are transformed
+    order: TraversalOrder,
 }
 
 impl Transform {
@@ -80,27 +208,79 @@ impl Transform {
             default: None,
             hash_pattern_index: HashMap::new(),
             non_hash_patterns: Vec::new(),
+            order: TraversalOrder::default(),
         }
     }
 
+    /// Set the traversal order used for named rules
+    ///
+    /// See [`TraversalOrder`] for the semantics of each order.
+    pub fn with_order(mut self, order: TraversalOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Get the traversal order used for named rules
+    pub fn order(&self) -> TraversalOrder {
+        self.order
+    }
+
     /// Add a transformation rule (simple key-based)
     pub fn rule<F>(mut self, name: &str, f: F) -> Self
     where
         F: Fn(&Value) -> Result<Value, TransformError> + Send + Sync + 'static,
     {
-        self.rules.insert(name.to_string(), Box::new(f));
+        self.rules
+            .insert(name.to_string(), Rule::Stateless(Box::new(f)));
+        self
+    }
+
+    /// Add a key-based rule whose action also receives mutable state
+    ///
+    /// The state is the one passed to [`apply_with_state`](Self::apply_with_state),
+    /// and must be of type `S`; applying the transform with other state, or
+    /// with [`apply`](Self::apply), fails once this rule is reached. Rules
+    /// registered with [`rule`](Self::rule) and this method share one
+    /// namespace, so a later registration replaces an earlier one.
+    pub fn stateful_rule<S, F>(mut self, name: &str, f: F) -> Self
+    where
+        S: 'static,
+        F: Fn(&Value, &mut S) -> Result<Value, TransformError> + Send + Sync + 'static,
+    {
+        let rule = move |value: &Value, state: &mut dyn Any| f(value, downcast_state(state)?);
+        self.rules
+            .insert(name.to_string(), Rule::Stateful(Box::new(rule)));
         self
     }
 
     /// Add a pattern-based transformation rule
-    pub fn pattern<F>(mut self, pattern: Pattern, f: F) -> Self
+    pub fn pattern<F>(self, pattern: Pattern, f: F) -> Self
     where
         F: Fn(&Bindings) -> Result<Value, TransformError> + Send + Sync + 'static,
     {
+        self.add_pattern(pattern, Action::Stateless(Box::new(f)))
+    }
+
+    /// Add a pattern-based rule whose action also receives mutable state
+    ///
+    /// See [`stateful_rule`](Self::stateful_rule) for how the state is
+    /// passed. Stateless and stateful patterns are tried in the order they
+    /// were added.
+    pub fn stateful_pattern<S, F>(self, pattern: Pattern, f: F) -> Self
+    where
+        S: 'static,
+        F: Fn(&Bindings, &mut S) -> Result<Value, TransformError> + Send + Sync + 'static,
+    {
+        let action =
+            move |bindings: &Bindings, state: &mut dyn Any| f(bindings, downcast_state(state)?);
+        self.add_pattern(pattern, Action::Stateful(Box::new(action)))
+    }
+
+    fn add_pattern(mut self, pattern: Pattern, action: Action) -> Self {
         let idx = self.pattern_rules.len();
         self.pattern_rules.push(PatternRule {
             pattern: pattern.clone(),
-            action: Box::new(f),
+            action,
         });
 
         // Index the pattern for faster dispatch
@@ -147,74 +327,169 @@ impl Transform {
         self
     }
 
+    /// Find the first pattern rule matching `value`
+    ///
+    /// Hash values try the patterns indexed by their first key before the
+    /// non-hash patterns; other values only try the non-hash patterns.
+    fn matching_rule<M>(&self, value: &Value, matches: M) -> Option<(&PatternRule, Bindings)>
+    where
+        M: Fn(&Pattern) -> Option<Bindings>,
+    {
+        let indexed = match value {
+            Value::Hash(h) => h
+                .keys()
+                .next()
+                .and_then(|first_key| self.hash_pattern_index.get(first_key)),
+            _ => None,
+        };
+        indexed
+            .into_iter()
+            .flatten()
+            .chain(&self.non_hash_patterns)
+            .find_map(|&idx| {
+                let rule = &self.pattern_rules[idx];
+                matches(&rule.pattern).map(|bindings| (rule, bindings))
+            })
+    }
+
     /// Apply the transform to a value
     pub fn apply(&self, value: &Value) -> Result<Value, TransformError> {
+        self.apply_in(value, None)
+    }
+
+    /// Apply the transform repeatedly until the value stops changing
+    ///
+    /// Rewrites such as constant folding or simplification can expose new
+    /// work for the same rules: folding `(1 + 2)` turns `(1 + 2) + 3` into
+    /// `3 + 3`, which only folds on the next pass. Each pass is an
+    /// [`apply`](Self::apply) of the previous result. After at most
+    /// `max_iterations` changing passes, one more pass must leave the value
+    /// unchanged; otherwise this fails with [`TransformError::NoFixpoint`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parsanol::portable::transform::{Transform, Value};
+    ///
+    /// // Halve even numbers until they're odd
+    /// let transform = Transform::new().default_rule(|v| match v.as_int() {
+    ///     Some(n) if n % 2 == 0 && n != 0 => Ok(Value::int(n / 2)),
+    ///     _ => Ok(v.clone()),
+    /// });
+    /// let result = transform.apply_fixpoint(&Value::int(24), 3).unwrap();
+    /// assert_eq!(result, Value::int(3));
+    /// assert!(transform.apply_fixpoint(&Value::int(24), 2).is_err());
+    /// ```
+    pub fn apply_fixpoint(
+        &self,
+        value: &Value,
+        max_iterations: usize,
+    ) -> Result<Value, TransformError> {
+        let mut current = value.clone();
+        for _ in 0..=max_iterations {
+            let next = self.apply(&current)?;
+            if next == current {
+                return Ok(next);
+            }
+            current = next;
+        }
+        Err(TransformError::NoFixpoint {
+            iterations: max_iterations + 1,
+        })
+    }
+
+    /// Apply the transform, giving stateful rules access to `state`
+    ///
+    /// Works like [`apply`](Self::apply), and also runs the rules added with
+    /// [`stateful_rule`](Self::stateful_rule) and
+    /// [`stateful_pattern`](Self::stateful_pattern), which is what passes
+    /// such as building a symbol table or numbering nodes need. Rules run
+    /// in the traversal order, so with [`TraversalOrder::Post`] a node's
+    /// children update the state before the node itself does.
+    ///
+    /// Actions are `Send + Sync` so a `Transform` can be shared between
+    /// threads, but the state is not: each call borrows `state` mutably
+    /// for its whole run, so a stateful transform is single-threaded. Use
+    /// one state per thread to apply the same transform in parallel.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parsanol::portable::transform::{Transform, Value};
+    ///
+    /// let transform = Transform::new().stateful_rule("var", |v, names: &mut Vec<String>| {
+    ///     names.push(v.as_str().unwrap().to_string());
+    ///     Ok(Value::int(names.len() as i64 - 1))
+    /// });
+    ///
+    /// let mut names: Vec<String> = Vec::new();
+    /// let tree = Value::array(vec![
+    ///     Value::hash(vec![("var", Value::string("x"))]),
+    ///     Value::hash(vec![("var", Value::string("y"))]),
+    /// ]);
+    /// let result = transform.apply_with_state(&tree, &mut names).unwrap();
+    /// assert_eq!(result, Value::array(vec![Value::int(0), Value::int(1)]));
+    /// assert_eq!(names, ["x", "y"]);
+    /// ```
+    pub fn apply_with_state<S: 'static>(
+        &self,
+        value: &Value,
+        state: &mut S,
+    ) -> Result<Value, TransformError> {
+        self.apply_in(value, Some(state))
+    }
+
+    /// [`apply`](Self::apply), with the state for stateful rules, if any
+    fn apply_in(
+        &self,
+        value: &Value,
+        mut state: Option<&mut dyn Any>,
+    ) -> Result<Value, TransformError> {
         // Use indexed pattern matching for faster dispatch
+        if let Some((rule, bindings)) = self.matching_rule(value, |p| p.match_value(value)) {
+            return rule.action.call(&bindings, state);
+        }
+
         match value {
             Value::Hash(h) => {
-                // Try hash-specific patterns first (indexed by first field name)
-                if let Some(first_key) = h.keys().next() {
-                    if let Some(indices) = self.hash_pattern_index.get(first_key) {
-                        for &idx in indices {
-                            let rule = &self.pattern_rules[idx];
-                            if let Some(bindings) = rule.pattern.match_value(value) {
-                                return (rule.action)(&bindings);
-                            }
-                        }
-                    }
-                }
-
-                // Try non-hash patterns (simple, subtree, any_of, etc.)
-                for &idx in &self.non_hash_patterns {
-                    let rule = &self.pattern_rules[idx];
-                    if let Some(bindings) = rule.pattern.match_value(value) {
-                        return (rule.action)(&bindings);
-                    }
-                }
-
                 // Check if this hash has a recognizable pattern
                 // For single-key hashes, use the key as the rule name
                 if h.len() == 1 {
                     // SAFETY: We checked h.len() == 1, so there's exactly one element
                     let (key, inner) = h.iter().next().expect("hash with len==1 must have element");
                     if let Some(rule) = self.rules.get(key) {
-                        // First transform the inner value
-                        let transformed_inner = self.apply(inner)?;
-                        // Then apply the rule
-                        return rule(&transformed_inner);
+                        return match self.order {
+                            TraversalOrder::Post => {
+                                // First transform the inner value, then apply the rule
+                                let transformed_inner =
+                                    self.apply_in(inner, state.as_deref_mut())?;
+                                rule.call(&transformed_inner, state)
+                            }
+                            TraversalOrder::Pre => {
+                                // Apply the rule to the raw value, then transform its result
+                                let result = rule.call(inner, state.as_deref_mut())?;
+                                self.apply_in(&result, state)
+                            }
+                        };
                     }
                 }
 
                 // Recursively transform hash values
                 let mut result = HashMap::new();
                 for (k, v) in h {
-                    result.insert(k.clone(), self.apply(v)?);
+                    result.insert(k.clone(), self.apply_in(v, state.as_deref_mut())?);
                 }
                 Ok(Value::Hash(result))
             }
             Value::Array(arr) => {
-                // Try non-hash patterns for arrays
-                for &idx in &self.non_hash_patterns {
-                    let rule = &self.pattern_rules[idx];
-                    if let Some(bindings) = rule.pattern.match_value(value) {
-                        return (rule.action)(&bindings);
-                    }
-                }
-
                 // Recursively transform array elements
-                let result: Result<Vec<Value>, TransformError> =
-                    arr.iter().map(|v| self.apply(v)).collect();
-                Ok(Value::Array(result?))
+                let mut result = Vec::with_capacity(arr.len());
+                for v in arr {
+                    result.push(self.apply_in(v, state.as_deref_mut())?);
+                }
+                Ok(Value::Array(result))
             }
             _ => {
-                // For leaf values, try non-hash patterns first
-                for &idx in &self.non_hash_patterns {
-                    let rule = &self.pattern_rules[idx];
-                    if let Some(bindings) = rule.pattern.match_value(value) {
-                        return (rule.action)(&bindings);
-                    }
-                }
-
                 // Try default transform or return as-is
                 if let Some(default) = &self.default {
                     default(value)
@@ -225,6 +500,164 @@ impl Transform {
         }
     }
 
+    /// Apply the transform to a source-mapped value
+    ///
+    /// Works like [`apply`](Self::apply), but a pattern rule matching the
+    /// value itself receives bindings from [`Pattern::match_mapped`], so its
+    /// action can reject the input with [`Bindings::error_at`]. Any other
+    /// error that carries no span is reported at the value's span as
+    /// [`TransformError::AtSpan`].
+    ///
+    /// Nested values have no spans of their own, so rules that match below
+    /// the top level see plain bindings.
+    pub fn apply_mapped(&self, value: &SourceMapped<Value>) -> Result<Value, TransformError> {
+        let inner = value.inner();
+        let result = match self.matching_rule(inner, |p| p.match_mapped(value)) {
+            Some((rule, bindings)) => rule.action.call(&bindings, None),
+            None => self.apply(inner),
+        };
+        result.map_err(|err| match err {
+            TransformError::AtSpan { .. } => err,
+            other => TransformError::at(value, other.to_string()),
+        })
+    }
+
+    /// Apply the transform to a parse tree, recording where each result came from
+    ///
+    /// Transforms the tree's [`Value`] exactly like [`apply`](Self::apply),
+    /// and returns, next to the result, every value the transform produced
+    /// along with the span of input it derived from, children before their
+    /// parents and the root last.
+    ///
+    /// Spans propagate as follows:
+    ///
+    /// - Matched input has the span it was parsed from.
+    /// - An array or hash has the span recorded by an arena created with
+    ///   [`AstArena::with_spans`], or else the span covering its children.
+    /// - A value that consumed no input (e.g. an absent optional) has the
+    ///   span of its nearest parent that did.
+    /// - The value returned by a pattern action or named rule has the span
+    ///   of the whole node the rule matched, since an action only sees
+    ///   bindings and can't say which part of its input it used. Pattern
+    ///   actions get that span in their bindings, as with
+    ///   [`apply_mapped`](Self::apply_mapped), so [`Bindings::error_at`]
+    ///   works at any depth.
+    ///
+    /// Errors that carry no span are reported at the span of the node
+    /// being transformed, as [`TransformError::AtSpan`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parsanol::portable::parser_dsl::{re, GrammarBuilder, ParsletExt};
+    /// use parsanol::portable::transform::{Transform, Value};
+    /// use parsanol::portable::{AstArena, ParsedTree, PortableParser, SourceMapBuilder};
+    ///
+    /// let grammar = GrammarBuilder::new()
+    ///     .rule("num", re("[0-9]").many1().as_int().label("n"))
+    ///     .build();
+    /// let input = "42";
+    /// // `as_int` leaves no input reference behind, so let the arena
+    /// // remember where the `n` hash came from
+    /// let mut arena = AstArena::with_spans();
+    /// let node = PortableParser::new(&grammar, input, &mut arena).parse().unwrap();
+    ///
+    /// let transform = Transform::new().rule("n", |v| Ok(Value::int(v.as_int().unwrap() + 1)));
+    /// let tree = ParsedTree::new(node, &arena, input);
+    /// let (value, map) = transform
+    ///     .apply_with_source_map(&tree, &SourceMapBuilder::new(input))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(value, Value::int(43));
+    /// let root = map.iter().last().unwrap();
+    /// assert_eq!((root.span().start.offset, root.span().end.offset), (0, 2));
+    /// ```
+    pub fn apply_with_source_map(
+        &self,
+        tree: &ParsedTree<'_>,
+        builder: &SourceMapBuilder,
+    ) -> Result<(Value, SourceMapCollection<Value>), TransformError> {
+        let value = tree.to_value();
+        let spans = SpanNode::build(tree.node(), tree.arena(), builder);
+        let root_span = spans.span.unwrap_or_else(SourceSpan::start);
+
+        let mut map = SourceMapCollection::new();
+        let result = self.apply_spanned(&value, &spans, root_span, &mut map)?;
+        Ok((result, map))
+    }
+
+    /// [`apply`](Self::apply), walking `spans` alongside `value`
+    ///
+    /// `inherited` is the span of the nearest parent that has one.
+    fn apply_spanned(
+        &self,
+        value: &Value,
+        spans: &SpanNode,
+        inherited: SourceSpan,
+        map: &mut SourceMapCollection<Value>,
+    ) -> Result<Value, TransformError> {
+        let span = spans.span.unwrap_or(inherited);
+        let result =
+            self.apply_spanned_inner(value, spans, span, map)
+                .map_err(|err| match err {
+                    TransformError::AtSpan { .. } => err,
+                    other => TransformError::at_span(span, other.to_string()),
+                })?;
+        map.push_with_span(result.clone(), span);
+        Ok(result)
+    }
+
+    fn apply_spanned_inner(
+        &self,
+        value: &Value,
+        spans: &SpanNode,
+        span: SourceSpan,
+        map: &mut SourceMapCollection<Value>,
+    ) -> Result<Value, TransformError> {
+        if let Some((rule, bindings)) = self.matching_rule(value, |p| p.match_at(value, span)) {
+            return rule.action.call(&bindings, None);
+        }
+
+        match (value, &spans.children) {
+            (Value::Hash(h), SpanChildren::Hash(child_spans)) => {
+                if h.len() == 1 {
+                    // SAFETY: We checked h.len() == 1, so there's exactly one element
+                    let (key, inner) = h.iter().next().expect("hash with len==1 must have element");
+                    if let Some(rule) = self.rules.get(key) {
+                        return match self.order {
+                            TraversalOrder::Post => {
+                                let transformed_inner =
+                                    self.apply_spanned(inner, &child_spans[key], span, map)?;
+                                rule.call(&transformed_inner, None)
+                            }
+                            // The rule's result has no spans of its own
+                            TraversalOrder::Pre => self.apply(&rule.call(inner, None)?),
+                        };
+                    }
+                }
+
+                let mut result = HashMap::new();
+                for (k, v) in h {
+                    result.insert(
+                        k.clone(),
+                        self.apply_spanned(v, &child_spans[k], span, map)?,
+                    );
+                }
+                Ok(Value::Hash(result))
+            }
+            (Value::Array(arr), SpanChildren::Array(child_spans)) => {
+                let result: Result<Vec<Value>, TransformError> = arr
+                    .iter()
+                    .zip(child_spans)
+                    .map(|(v, child)| self.apply_spanned(v, child, span, map))
+                    .collect();
+                Ok(Value::Array(result?))
+            }
+            // Leaves, and anything the span tree can't follow
+            _ => self.apply(value),
+        }
+    }
+
     /// Check if a rule exists
     pub fn has_rule(&self, name: &str) -> bool {
         self.rules.contains_key(name)
@@ -237,6 +670,86 @@ impl Default for Transform {
     }
 }
 
+/// Spans of an AST node and its children, mirroring [`ast_to_value`](super::ast_to_value)
+struct SpanNode {
+    span: Option<SourceSpan>,
+    children: SpanChildren,
+}
+
+enum SpanChildren {
+    Leaf,
+    Array(Vec<SpanNode>),
+    Hash(HashMap<String, SpanNode>),
+}
+
+impl SpanNode {
+    fn build(node: &AstNode, arena: &AstArena, builder: &SourceMapBuilder) -> Self {
+        match node {
+            AstNode::InputRef { offset, length } => Self {
+                span: Some(
+                    *builder
+                        .mapped((), *offset as usize, *length as usize)
+                        .span(),
+                ),
+                children: SpanChildren::Leaf,
+            },
+            AstNode::Array { pool_index, length } => {
+                let children: Vec<SpanNode> = arena
+                    .get_array(*pool_index as usize, *length as usize)
+                    .iter()
+                    .map(|item| Self::build(item, arena, builder))
+                    .collect();
+                let span = Self::own_span(node, arena, builder)
+                    .or_else(|| Self::covering(children.iter()));
+                Self {
+                    span,
+                    children: SpanChildren::Array(children),
+                }
+            }
+            AstNode::Hash { pool_index, length } => {
+                // Later duplicate keys win, as in `ast_to_value`
+                let children: HashMap<String, SpanNode> = arena
+                    .get_hash_items(*pool_index as usize, *length as usize)
+                    .into_iter()
+                    .map(|(key, value)| (key, Self::build(&value, arena, builder)))
+                    .collect();
+                let span = Self::own_span(node, arena, builder)
+                    .or_else(|| Self::covering(children.values()));
+                Self {
+                    span,
+                    children: SpanChildren::Hash(children),
+                }
+            }
+            AstNode::Tagged { value, .. } => Self::build(value, arena, builder),
+            AstNode::Nil
+            | AstNode::Bool(_)
+            | AstNode::Int(_)
+            | AstNode::Float(_)
+            | AstNode::StringRef { .. } => Self {
+                span: None,
+                children: SpanChildren::Leaf,
+            },
+        }
+    }
+
+    /// Span recorded by a span-tracking arena, relocated onto `builder`'s source
+    fn own_span(
+        node: &AstNode,
+        arena: &AstArena,
+        builder: &SourceMapBuilder,
+    ) -> Option<SourceSpan> {
+        let span = arena.span_of(node)?;
+        let (start, end) = (span.start.offset, span.end.offset);
+        Some(*builder.mapped((), start, end - start).span())
+    }
+
+    fn covering<'a>(children: impl Iterator<Item = &'a SpanNode>) -> Option<SourceSpan> {
+        children
+            .filter_map(|child| child.span)
+            .reduce(|a, b| a.merge(&b))
+    }
+}
+
 /// Typed transform for converting to specific types
 #[allow(clippy::type_complexity)]
 pub struct TypedTransform<T> {
@@ -8,6 +8,8 @@ use std::fmt;
 
 // Re-export FromAstError for TryFrom implementations
 use crate::derive::FromAstError;
+use crate::portable::arena::AstArena;
+use crate::portable::ast::AstNode;
 
 /// A value in the transformation system
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -174,6 +176,45 @@ impl Value {
             Value::Hash(_) => "hash",
         }
     }
+
+    /// Allocate this value as AST nodes in `arena`
+    ///
+    /// The inverse of [`ast_to_value`](super::ast_to_value): strings are
+    /// interned as `StringRef`s, and arrays and hashes are stored in the
+    /// arena's pools. Hash keys are stored in sorted order, so equal values
+    /// produce identical nodes.
+    ///
+    /// ```rust
+    /// use parsanol::portable::{transform::{ast_to_value, Value}, AstArena};
+    ///
+    /// let value = Value::hash(vec![("sum", Value::array(vec![Value::int(1), Value::float(2.5)]))]);
+    /// let mut arena = AstArena::new();
+    /// let ast = value.to_ast(&mut arena);
+    /// assert_eq!(ast_to_value(&ast, &arena, ""), value);
+    /// ```
+    pub fn to_ast(&self, arena: &mut AstArena) -> AstNode {
+        match self {
+            Value::Nil => AstNode::Nil,
+            Value::Bool(b) => AstNode::Bool(*b),
+            Value::Int(n) => AstNode::Int(*n),
+            Value::Float(f) => AstNode::Float(*f),
+            Value::String(s) => arena.intern_string(s),
+            Value::Array(items) => {
+                let items: Vec<AstNode> = items.iter().map(|item| item.to_ast(arena)).collect();
+                let (pool_index, length) = arena.store_array(&items);
+                AstNode::Array { pool_index, length }
+            }
+            Value::Hash(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                let values: Vec<AstNode> = keys.iter().map(|key| map[*key].to_ast(arena)).collect();
+                let pairs: Vec<(&str, AstNode)> =
+                    keys.iter().map(|key| key.as_str()).zip(values).collect();
+                let (pool_index, length) = arena.store_hash(&pairs);
+                AstNode::Hash { pool_index, length }
+            }
+        }
+    }
 }
 
 impl fmt::Display for Value {
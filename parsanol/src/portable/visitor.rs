@@ -335,6 +335,140 @@ pub fn walk<V: Visitor>(node: &AstNode, arena: &AstArena, input: &str, mut visit
     visitor
 }
 
+/// Trait for rewriting AST nodes in place
+///
+/// [`walk_mut`] calls [`visit_node_mut`](Self::visit_node_mut) on every
+/// node, children before parents, so a rewrite sees children that have
+/// already been rewritten. Arena pools are never modified in place: when
+/// any child of an array or hash changes, the parent is re-allocated in
+/// the arena with the new children and the old pool entries are left
+/// behind, unreferenced. Other nodes sharing the old entries (e.g. cached
+/// parse results) are unaffected.
+pub trait VisitorMut {
+    /// Rewrite `node`, whose children have already been visited
+    fn visit_node_mut(&mut self, node: &mut AstNode, arena: &mut AstArena, input: &str);
+}
+
+/// Rewrite an AST bottom-up with a [`VisitorMut`]
+///
+/// Returns the visitor for access to any state it collected.
+pub fn walk_mut<V: VisitorMut>(
+    node: &mut AstNode,
+    arena: &mut AstArena,
+    input: &str,
+    mut visitor: V,
+) -> V {
+    walk_mut_node(node, arena, input, &mut visitor);
+    visitor
+}
+
+fn walk_mut_node<V: VisitorMut>(
+    node: &mut AstNode,
+    arena: &mut AstArena,
+    input: &str,
+    visitor: &mut V,
+) {
+    match node {
+        AstNode::Array { pool_index, length } => {
+            let items = arena.get_array(*pool_index as usize, *length as usize);
+            let mut rewritten = items.clone();
+            for item in &mut rewritten {
+                walk_mut_node(item, arena, input, visitor);
+            }
+            if rewritten != items {
+                *node = arena.alloc_array(rewritten);
+            }
+        }
+        AstNode::Hash { pool_index, length } => {
+            let pairs = arena.get_hash_items(*pool_index as usize, *length as usize);
+            let mut rewritten = pairs.clone();
+            for (_, value) in &mut rewritten {
+                walk_mut_node(value, arena, input, visitor);
+            }
+            if rewritten != pairs {
+                *node = arena.alloc_hash(rewritten);
+            }
+        }
+        AstNode::Tagged { value, .. } => {
+            walk_mut_node(value, arena, input, visitor);
+        }
+        _ => {}
+    }
+    visitor.visit_node_mut(node, arena, input);
+}
+
+/// A rewriter that folds integer arithmetic
+///
+/// Replaces every `{left: Int, op: "+" | "-" | "*", right: Int}` hash, as
+/// produced by a binary expression rule, with the resulting `Int`. Since
+/// children are folded first, nested expressions collapse completely.
+/// Operations that would overflow are left unfolded.
+///
+/// # Example
+///
+/// ```
+/// use parsanol::portable::visitor::{walk_mut, ConstantFolder};
+/// use parsanol::portable::{AstArena, AstNode};
+///
+/// let mut arena = AstArena::new();
+/// let op = arena.intern_string("*");
+/// let mut node = arena.alloc_hash(vec![
+///     ("left".to_string(), AstNode::Int(6)),
+///     ("op".to_string(), op),
+///     ("right".to_string(), AstNode::Int(7)),
+/// ]);
+///
+/// let folder = walk_mut(&mut node, &mut arena, "", ConstantFolder::new());
+/// assert_eq!(node, AstNode::Int(42));
+/// assert_eq!(folder.folded, 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConstantFolder {
+    /// Number of hashes folded
+    pub folded: usize,
+}
+
+impl ConstantFolder {
+    /// Create a new folder
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl VisitorMut for ConstantFolder {
+    fn visit_node_mut(&mut self, node: &mut AstNode, arena: &mut AstArena, input: &str) {
+        let AstNode::Hash { pool_index, length } = *node else {
+            return;
+        };
+        let pairs = arena.get_hash_items(pool_index as usize, length as usize);
+        if pairs.len() != 3 {
+            return;
+        }
+        let field = |name: &str| pairs.iter().find(|(key, _)| key == name).map(|(_, v)| v);
+        let (Some(AstNode::Int(left)), Some(AstNode::Int(right))) = (field("left"), field("right"))
+        else {
+            return;
+        };
+        let op = match field("op") {
+            Some(AstNode::StringRef { pool_index }) => arena.get_string(*pool_index as usize),
+            Some(AstNode::InputRef { offset, length }) => input
+                .get(*offset as usize..(*offset + *length) as usize)
+                .unwrap_or(""),
+            _ => return,
+        };
+        let value = match op.trim() {
+            "+" => left.checked_add(*right),
+            "-" => left.checked_sub(*right),
+            "*" => left.checked_mul(*right),
+            _ => None,
+        };
+        if let Some(value) = value {
+            *node = AstNode::Int(value);
+            self.folded += 1;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -432,6 +566,61 @@ mod tests {
         assert_eq!(analyzer.max_depth, 2);
     }
 
+    #[test]
+    fn test_constant_folder_collapses_nested_sums() {
+        let input = "2 + 3";
+        let mut arena = AstArena::new();
+        let plus = arena.input_ref(2, 1);
+        let sum = arena.alloc_hash(vec![
+            ("left".to_string(), AstNode::Int(2)),
+            ("op".to_string(), plus.clone()),
+            ("right".to_string(), AstNode::Int(3)),
+        ]);
+
+        let mut node = sum.clone();
+        walk_mut(&mut node, &mut arena, input, ConstantFolder::new());
+        assert_eq!(node, AstNode::Int(5));
+
+        // The nested sum folds first, then its parent; the array holding
+        // the result is re-allocated and the original left intact
+        let outer = arena.alloc_hash(vec![
+            ("left".to_string(), sum.clone()),
+            ("op".to_string(), plus),
+            ("right".to_string(), AstNode::Int(10)),
+        ]);
+        let mut list = arena.alloc_array(vec![outer, AstNode::Nil]);
+        let original = list.clone();
+        let folder = walk_mut(&mut list, &mut arena, input, ConstantFolder::new());
+        assert_eq!(folder.folded, 2);
+        let AstNode::Array { pool_index, length } = list else {
+            panic!("expected an array, got {:?}", list);
+        };
+        assert_eq!(
+            arena.get_array(pool_index as usize, length as usize),
+            vec![AstNode::Int(15), AstNode::Nil]
+        );
+        let AstNode::Array { pool_index, length } = original else {
+            unreachable!();
+        };
+        assert!(arena.get_array(pool_index as usize, length as usize)[0].is_hash());
+    }
+
+    #[test]
+    fn test_walk_mut_keeps_unchanged_nodes() {
+        let mut arena = AstArena::new();
+        let op = arena.intern_string("/");
+        let mut node = arena.alloc_hash(vec![
+            ("left".to_string(), AstNode::Int(1)),
+            ("op".to_string(), op),
+            ("right".to_string(), AstNode::Int(2)),
+        ]);
+        let original = node.clone();
+
+        let folder = walk_mut(&mut node, &mut arena, "", ConstantFolder::new());
+        assert_eq!(folder.folded, 0);
+        assert_eq!(node, original);
+    }
+
     #[test]
     fn test_walk_function() {
         let mut arena = AstArena::new();
@@ -0,0 +1,176 @@
+/*
+ * Smoke test for the streaming C ABI (`parsanol_parse_streaming`)
+ *
+ * Build the library as a static archive, then compile and run this file
+ * against it:
+ *
+ *     cargo rustc -p parsanol --release --crate-type staticlib
+ *     cc -std=c99 -Wall tests/c/streaming_smoke.c target/release/libparsanol.a \
+ *         -lpthread -ldl -lm -o target/streaming_smoke
+ *     ./target/streaming_smoke
+ *
+ * Exits 0 on success and prints the failing check otherwise.
+ */
+
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+
+/* Declarations mirroring parsanol/src/ffi/c.rs */
+
+typedef struct ParsanolGrammar ParsanolGrammar;
+
+typedef int (*ParsanolStrCallback)(void *user_data, const char *ptr, size_t len);
+typedef int (*ParsanolSizeCallback)(void *user_data, size_t value);
+typedef int (*ParsanolLenHintCallback)(void *user_data, int64_t expected_len);
+
+typedef struct ParsanolCallbacks {
+    ParsanolStrCallback on_named_start;
+    ParsanolStrCallback on_named_end;
+    int (*on_string)(void *user_data, const char *ptr, size_t len, size_t offset);
+    int (*on_int)(void *user_data, int64_t value);
+    int (*on_float)(void *user_data, double value);
+    int (*on_bool)(void *user_data, int value);
+    int (*on_nil)(void *user_data);
+    ParsanolLenHintCallback on_array_start;
+    ParsanolSizeCallback on_array_element;
+    ParsanolSizeCallback on_array_end;
+    ParsanolLenHintCallback on_hash_start;
+    ParsanolStrCallback on_hash_key;
+    ParsanolStrCallback on_hash_value;
+    ParsanolSizeCallback on_hash_end;
+    ParsanolStrCallback on_error;
+} ParsanolCallbacks;
+
+#define PARSANOL_OK 0
+#define PARSANOL_ERROR_PARSE_FAILED (-3)
+#define PARSANOL_ERROR_CALLBACK (-6)
+#define PARSANOL_CONTINUE 0
+#define PARSANOL_STOP 1
+
+ParsanolGrammar *parsanol_grammar_new(const char *json);
+void parsanol_grammar_free(ParsanolGrammar *grammar);
+int parsanol_parse_streaming(const ParsanolGrammar *grammar, const char *input,
+                             size_t input_len, const ParsanolCallbacks *callbacks,
+                             void *user_data);
+
+/* Digits separated by optional commas, each digit captured as `n` */
+static const char *GRAMMAR =
+    "{\"root\": 5, \"atoms\": ["
+    "{\"Re\": {\"pattern\": \"[0-9]\"}},"
+    "{\"Named\": {\"name\": \"n\", \"atom\": 0}},"
+    "{\"Str\": {\"pattern\": \",\"}},"
+    "{\"Repetition\": {\"atom\": 2, \"min\": 0, \"max\": 1}},"
+    "{\"Sequence\": {\"atoms\": [1, 3]}},"
+    "{\"Repetition\": {\"atom\": 4, \"min\": 0, \"max\": null}}"
+    "]}";
+
+typedef struct Events {
+    char digits[16];
+    size_t digit_count;
+    size_t key_count;
+    size_t error_count;
+    char stop_at;
+} Events;
+
+static int on_string(void *user_data, const char *ptr, size_t len, size_t offset) {
+    Events *events = user_data;
+    (void)offset;
+    if (len != 1 || events->digit_count + 1 >= sizeof events->digits) {
+        return -1;
+    }
+    events->digits[events->digit_count++] = ptr[0];
+    return ptr[0] == events->stop_at ? PARSANOL_STOP : PARSANOL_CONTINUE;
+}
+
+static int fail_string(void *user_data, const char *ptr, size_t len, size_t offset) {
+    (void)user_data;
+    (void)ptr;
+    (void)len;
+    (void)offset;
+    return -1;
+}
+
+static int on_hash_key(void *user_data, const char *ptr, size_t len) {
+    Events *events = user_data;
+    if (len != 1 || ptr[0] != 'n') {
+        return -1;
+    }
+    events->key_count++;
+    return PARSANOL_CONTINUE;
+}
+
+static int on_error(void *user_data, const char *ptr, size_t len) {
+    Events *events = user_data;
+    (void)ptr;
+    (void)len;
+    events->error_count++;
+    return PARSANOL_CONTINUE;
+}
+
+static int failures = 0;
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,    \
+                    __LINE__, #cond);                                 \
+            failures++;                                               \
+        }                                                             \
+    } while (0)
+
+static int stream(const ParsanolGrammar *grammar, const char *input, Events *events) {
+    ParsanolCallbacks callbacks;
+    memset(&callbacks, 0, sizeof callbacks);
+    callbacks.on_string = on_string;
+    callbacks.on_hash_key = on_hash_key;
+    callbacks.on_error = on_error;
+
+    memset(events, 0, sizeof *events);
+    return parsanol_parse_streaming(grammar, input, strlen(input), &callbacks, events);
+}
+
+int main(void) {
+    Events events;
+    ParsanolGrammar *grammar = parsanol_grammar_new(GRAMMAR);
+    CHECK(grammar != NULL);
+    if (grammar == NULL) {
+        return 1;
+    }
+
+    CHECK(stream(grammar, "1,2,3", &events) == PARSANOL_OK);
+    CHECK(strcmp(events.digits, "123") == 0);
+    CHECK(events.key_count == 3);
+    CHECK(events.error_count == 0);
+
+    CHECK(stream(grammar, "1,x", &events) == PARSANOL_ERROR_PARSE_FAILED);
+    CHECK(events.error_count == 1);
+
+    /* Stopping early is not an error */
+    memset(&events, 0, sizeof events);
+    {
+        ParsanolCallbacks callbacks;
+        memset(&callbacks, 0, sizeof callbacks);
+        callbacks.on_string = on_string;
+        events.stop_at = '2';
+        CHECK(parsanol_parse_streaming(grammar, "1,2,3", 5, &callbacks, &events) ==
+              PARSANOL_OK);
+        CHECK(strcmp(events.digits, "12") == 0);
+    }
+
+    /* A negative return aborts the parse */
+    {
+        ParsanolCallbacks callbacks;
+        memset(&callbacks, 0, sizeof callbacks);
+        callbacks.on_string = fail_string;
+        CHECK(parsanol_parse_streaming(grammar, "1,2,3", 5, &callbacks, &events) ==
+              PARSANOL_ERROR_CALLBACK);
+    }
+
+    parsanol_grammar_free(grammar);
+    if (failures == 0) {
+        printf("streaming smoke test passed\n");
+    }
+    return failures == 0 ? 0 : 1;
+}
@@ -25,6 +25,7 @@
 //! 2. **RubyBuilder**: Implements StreamingBuilder correctly
 //! 3. **RubyObject**: Conversion traits for primitive types
 //! 4. **Error Handling**: Proper error propagation from Ruby
+//! 5. **RubyObject derive**: ivar and positional construction modes
 
 #![cfg(feature = "ruby")]
 
@@ -321,3 +322,60 @@ fn test_parse_with_builder() {
     // Result should be a Hash with matched: true
     assert!(result.is_kind_of(ruby.class_hash()));
 }
+
+/// Test both RubyObject derive construction modes for named fields
+#[test]
+#[ignore = "Requires Ruby runtime - run with --ignored"]
+fn test_ruby_object_derive_init_modes() {
+    use parsanol::ruby_ffi::RubyObject;
+    use parsanol_ruby_derive::RubyObject;
+
+    #[derive(RubyObject)]
+    #[ruby_class("PointIvars")]
+    struct PointIvars {
+        x: i64,
+        y: i64,
+    }
+
+    #[derive(RubyObject)]
+    #[ruby_class("PointPositional")]
+    #[ruby_init("positional")]
+    struct PointPositional {
+        x: i64,
+        y: i64,
+    }
+
+    let ruby = Ruby::get().expect("Ruby not available");
+
+    ruby.eval::<Value>(
+        r#"
+        class PointIvars
+          attr_reader :x, :y
+        end
+
+        class PointPositional
+          attr_reader :x, :y
+
+          def initialize(x, y)
+            @x = x
+            @y = y
+          end
+        end
+    "#,
+    )
+    .expect("Failed to define point classes");
+
+    let ivars = PointIvars { x: 1, y: 2 }
+        .to_ruby(&ruby)
+        .expect("ivar conversion failed");
+    let positional = PointPositional { x: 3, y: 4 }
+        .to_ruby(&ruby)
+        .expect("positional conversion failed");
+
+    // Fields keep their declaration order in both modes
+    for (value, expected) in [(ivars, (1, 2)), (positional, (3, 4))] {
+        let x = value.funcall::<_, _, i64>("x", ()).expect("x failed");
+        let y = value.funcall::<_, _, i64>("y", ()).expect("y failed");
+        assert_eq!((x, y), expected);
+    }
+}
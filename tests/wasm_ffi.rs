@@ -15,6 +15,11 @@
 //! wasm-pack build --features wasm
 //! ```
 //!
+//! ## Run Tests in a WASM Runtime
+//! ```bash
+//! wasm-pack test --node -- --features wasm
+//! ```
+//!
 //! # What's Tested
 //!
 //! 1. **API Compilation**: All wasm-bindgen annotations are correct
@@ -145,3 +150,69 @@ fn test_jsvalue_conversions() {
     let _number = JsValue::from_f64(42.0);
     // JsValue conversions work if we got here
 }
+
+/// Tests that call into JavaScript, run with `wasm-pack test`
+#[cfg(target_arch = "wasm32")]
+mod js_builder {
+    use js_sys::{Array, Function, Object, Reflect};
+    use parsanol::ffi::wasm::WasmParser;
+    use parsanol::portable::parser_dsl::{re, sep_by, str, GrammarBuilder, ParsletExt};
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    /// Build a JS builder that pushes each string into `this.strings`
+    fn string_collector() -> Object {
+        let builder = Object::new();
+        Reflect::set(&builder, &"strings".into(), &Array::new()).unwrap();
+        let on_string = Function::new_with_args("value", "this.strings.push(value);");
+        Reflect::set(&builder, &"on_string".into(), &on_string).unwrap();
+        let finish = Function::new_no_args("return this.strings;");
+        Reflect::set(&builder, &"finish".into(), &finish).unwrap();
+        builder
+    }
+
+    fn word_list_parser() -> WasmParser {
+        let grammar = GrammarBuilder::new()
+            .rule("words", sep_by(re("[a-z]").many1().label("word"), str(",")))
+            .build();
+        WasmParser::new(&grammar.to_json().unwrap()).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_with_builder_collects_strings() {
+        let mut parser = word_list_parser();
+        let result = parser
+            .parse_with_builder("ab,cd", string_collector().into())
+            .unwrap();
+
+        let strings: Vec<String> = Array::from(&result)
+            .iter()
+            .filter_map(|v| v.as_string())
+            .collect();
+        assert!(strings.contains(&"ab".to_string()));
+        assert!(strings.contains(&"cd".to_string()));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_with_builder_propagates_js_errors() {
+        let builder = Object::new();
+        let on_string = Function::new_no_args("throw new Error('stop here');");
+        Reflect::set(&builder, &"on_string".into(), &on_string).unwrap();
+
+        let mut parser = word_list_parser();
+        let err = parser
+            .parse_with_builder("ab", builder.into())
+            .unwrap_err()
+            .as_string()
+            .unwrap();
+        assert!(err.contains("stop here"), "{}", err);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_with_builder_parse_failure() {
+        let mut parser = word_list_parser();
+        let result: Result<JsValue, JsValue> =
+            parser.parse_with_builder("AB", string_collector().into());
+        assert!(result.is_err());
+    }
+}